@@ -108,11 +108,11 @@ fn few_tests() {
         ",
     );
     assert!(
-        contents.contains("project foo............................OK"),
+        contents.contains("project foo.................OK"),
         "{contents}"
     );
     assert!(
-        contents.contains("project bar............................OK"),
+        contents.contains("project bar.................OK"),
         "{contents}"
     );
     assert!(
@@ -141,7 +141,7 @@ fn failed_test() {
     assert!(
         Regex::new(
             "(?ms)^\
-            project foo..........................FAIL   [\\d\\.]+s\n\
+            project foo...............FAIL   [\\d\\.]+s +[\\d\\.]+ [KMGTPE]?B\n\
             test output\n\
             stderr: thread 'foo' panicked at src/lib.rs:\\d+:\\d+:\n\
             stderr: assertion `left == right` failed\n\
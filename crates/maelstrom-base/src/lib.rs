@@ -20,6 +20,7 @@ use std::{
     error::Error,
     fmt::{self, Debug, Formatter},
     hash::Hash,
+    net::IpAddr,
     num::NonZeroU32,
     result::Result,
     str::{self, FromStr},
@@ -170,6 +171,12 @@ pub enum JobMountForTomlAndJson {
         #[serde(default)]
         read_only: bool,
     },
+    Cache {
+        name: String,
+        mount_point: NonRootUtf8PathBuf,
+        #[serde(default)]
+        sharing: JobCacheSharing,
+    },
     Devices {
         devices: EnumSet<JobDeviceForTomlAndJson>,
     },
@@ -187,6 +194,12 @@ pub enum JobMountForTomlAndJson {
     },
     Tmp {
         mount_point: NonRootUtf8PathBuf,
+        #[serde(default)]
+        size: Option<u64>,
+        #[serde(default)]
+        mode: Option<u32>,
+        #[serde(default)]
+        inodes: Option<u64>,
     },
 }
 
@@ -198,12 +211,23 @@ pub enum JobMount {
         local_path: Utf8PathBuf,
         read_only: bool,
     },
+    /// A writable directory that the worker keeps between jobs that use the same `name`.
+    Cache {
+        name: String,
+        mount_point: Utf8PathBuf,
+        sharing: JobCacheSharing,
+    },
     Devices {
         devices: EnumSet<JobDevice>,
     },
     Devpts {
         mount_point: Utf8PathBuf,
     },
+    /// A read-only file system built from its own stack of layers, separate from the job's root.
+    Layer {
+        mount_point: Utf8PathBuf,
+        layers: NonEmpty<(Sha256Digest, ArtifactType)>,
+    },
     Mqueue {
         mount_point: Utf8PathBuf,
     },
@@ -213,8 +237,13 @@ pub enum JobMount {
     Sys {
         mount_point: Utf8PathBuf,
     },
+    /// A tmpfs. The `size` is in bytes, and may not be larger than the worker's `max-tmpfs-size`, if
+    /// it has one. The `mode` is that of the mount's root directory.
     Tmp {
         mount_point: Utf8PathBuf,
+        size: Option<u64>,
+        mode: Option<u32>,
+        inodes: Option<u64>,
     },
 }
 
@@ -230,6 +259,15 @@ impl From<JobMountForTomlAndJson> for JobMount {
                 local_path,
                 read_only,
             },
+            JobMountForTomlAndJson::Cache {
+                name,
+                mount_point,
+                sharing,
+            } => JobMount::Cache {
+                name,
+                mount_point: mount_point.into(),
+                sharing,
+            },
             JobMountForTomlAndJson::Devices { devices } => JobMount::Devices {
                 devices: devices.into_iter().map(JobDevice::from).collect(),
             },
@@ -245,13 +283,33 @@ impl From<JobMountForTomlAndJson> for JobMount {
             JobMountForTomlAndJson::Sys { mount_point } => JobMount::Sys {
                 mount_point: mount_point.into(),
             },
-            JobMountForTomlAndJson::Tmp { mount_point } => JobMount::Tmp {
+            JobMountForTomlAndJson::Tmp {
+                mount_point,
+                size,
+                mode,
+                inodes,
+            } => JobMount::Tmp {
                 mount_point: mount_point.into(),
+                size,
+                mode,
+                inodes,
             },
         }
     }
 }
 
+/// How a job shares a [`JobMount::Cache`] with other jobs using the same cache on the same worker.
+#[pocket_definition(export)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobCacheSharing {
+    /// Any number of jobs may use the cache at the same time.
+    #[default]
+    Shared,
+    /// The job waits until it is the only job using the cache.
+    Exclusive,
+}
+
 #[pocket_definition(export)]
 #[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -268,7 +326,11 @@ pub enum JobNetwork {
 pub enum JobRootOverlay {
     #[default]
     None,
-    Tmp,
+    /// The writable layer lives on a tmpfs, limited like a [`JobMount::Tmp`].
+    Tmp {
+        size: Option<u64>,
+        inodes: Option<u64>,
+    },
     Local {
         upper: Utf8PathBuf,
         work: Utf8PathBuf,
@@ -325,6 +387,144 @@ impl GroupId {
     }
 }
 
+/// Entries for the job's user and group that the worker adds to the job's `/etc/passwd` and
+/// `/etc/group`, so that programs can look up the user's name and home directory. The worker also
+/// creates the home directory. If the job's image has these files, the entries are added to them,
+/// replacing any entries with the same name or ID. An image's entry for the user or group ID is
+/// kept if no name is given for it. The names and home directory must be valid, as checked by
+/// [`JobUserEntries::validate`].
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobUserEntries {
+    pub user_name: Option<String>,
+    pub group_name: Option<String>,
+    pub home: Option<Utf8PathBuf>,
+}
+
+impl JobUserEntries {
+    /// The user's name. If one isn't given, this is `root` for user 0 and `maelstrom` otherwise.
+    pub fn user_name(&self, user: UserId) -> &str {
+        match &self.user_name {
+            Some(user_name) => user_name,
+            None if user.0 == 0 => "root",
+            None => "maelstrom",
+        }
+    }
+
+    /// The group's name. If one isn't given, this is `root` for group 0 and the user's name
+    /// otherwise.
+    pub fn group_name(&self, user: UserId, group: GroupId) -> &str {
+        match &self.group_name {
+            Some(group_name) => group_name,
+            None if group.0 == 0 => "root",
+            None => self.user_name(user),
+        }
+    }
+
+    /// The user's home directory. If one isn't given, this is `/root` for user 0 and
+    /// `/home/<user-name>` otherwise.
+    pub fn home(&self, user: UserId) -> Utf8PathBuf {
+        match &self.home {
+            Some(home) => home.clone(),
+            None if user.0 == 0 => "/root".into(),
+            None => Utf8Path::new("/home").join(self.user_name(user)),
+        }
+    }
+
+    /// Check that the names and home directory can be written to `/etc/passwd` and `/etc/group`.
+    /// Names must be non-empty and can't contain colons, commas, or newlines. The home directory
+    /// must be absolute and can't contain colons or newlines. Anything else would corrupt the
+    /// files, or add arbitrary entries to them.
+    pub fn validate(&self) -> Result<(), InvalidUserEntriesError> {
+        for name in self.user_name.iter().chain(&self.group_name) {
+            if name.is_empty() || name.contains([':', ',', '\n']) {
+                return Err(InvalidUserEntriesError::Name(name.clone()));
+            }
+        }
+        if let Some(home) = &self.home {
+            if !home.is_absolute() || home.as_str().contains([':', '\n']) {
+                return Err(InvalidUserEntriesError::Home(home.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum InvalidUserEntriesError {
+    Name(String),
+    Home(Utf8PathBuf),
+}
+
+impl fmt::Display for InvalidUserEntriesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "{name:?} isn't a valid user or group name"),
+            Self::Home(home) => write!(f, "{home:?} isn't a valid home directory"),
+        }
+    }
+}
+
+impl Error for InvalidUserEntriesError {}
+
+/// An entry to add to the job's `/etc/hosts`, so that `name` resolves to `address`. The `name` must
+/// be a valid hostname, as checked by [`validate_hostname`].
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobHost {
+    pub name: String,
+    pub address: IpAddr,
+}
+
+#[derive(Debug)]
+pub struct InvalidHostnameError(String);
+
+impl fmt::Display for InvalidHostnameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} isn't a valid hostname", self.0)
+    }
+}
+
+impl Error for InvalidHostnameError {}
+
+/// Check that `name` is a valid hostname, as described in RFC 1123: at most 253 characters of
+/// dot-separated labels, each made up of 1 to 63 ASCII letters, digits, and hyphens, and not
+/// starting or ending with a hyphen. Job hostnames end up in `/etc/hosts` and are given to
+/// `sethostname`, so anything else is rejected.
+pub fn validate_hostname(name: &str) -> Result<(), InvalidHostnameError> {
+    let valid_label = |label: &str| {
+        (1..=63).contains(&label.len())
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+    if name.len() > 253 || !name.split('.').all(valid_label) {
+        return Err(InvalidHostnameError(name.into()));
+    }
+    Ok(())
+}
+
+/// Offsets, in seconds, to add to the job's clocks. The worker applies them by running the job in
+/// its own time namespace. Linux only allows the monotonic and boot-time clocks to be offset, not
+/// the real-time clock.
+#[pocket_definition(export)]
+#[derive(
+    Copy, Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobClockOffsets {
+    /// The offset for `CLOCK_MONOTONIC`, and the related `CLOCK_MONOTONIC_COARSE` and
+    /// `CLOCK_MONOTONIC_RAW`.
+    pub monotonic: i64,
+
+    /// The offset for `CLOCK_BOOTTIME`. This also affects the uptime reported in `/proc/uptime`.
+    pub boottime: i64,
+}
+
 /// A count of seconds.
 #[pocket_definition(export)]
 #[derive(
@@ -389,6 +589,345 @@ impl JobTty {
     }
 }
 
+/// Where a job's standard input comes from. Without one of these, a job's stdin is at EOF. This is
+/// ignored for jobs that allocate a TTY.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum JobStdin {
+    /// The bytes are provided inline.
+    Inline(Box<[u8]>),
+
+    /// The bytes are the contents of the given artifact, which the worker will fetch like any
+    /// other artifact.
+    Artifact(Sha256Digest),
+}
+
+/// Resource limits for a job. The worker enforces these by putting the job in its own cgroup. Each
+/// limit that is `None` is not enforced.
+#[pocket_definition(export)]
+#[derive(
+    Copy, Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub struct JobLimits {
+    /// The maximum amount of memory, in bytes, the job may use. If the job goes over this limit,
+    /// it will be killed and its status will be [`JobTerminationStatus::OutOfMemory`].
+    pub memory: Option<u64>,
+
+    /// The amount of CPU time the job may use, in thousandths of a CPU. For example, 1500 allows
+    /// the job to use one and a half CPUs worth of time. The job is throttled, not killed, when it
+    /// goes over this limit.
+    pub cpu_millis: Option<u32>,
+
+    /// The maximum number of processes (and threads) the job may have at once.
+    pub pids: Option<u32>,
+}
+
+impl JobLimits {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A resource that can be limited with a POSIX rlimit. See `setrlimit(2)`.
+#[pocket_definition(export)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobRlimitResource {
+    /// The maximum size of the process's virtual memory, in bytes.
+    As,
+    /// The maximum size of a core file, in bytes. Zero disables core dumps.
+    Core,
+    /// The amount of CPU time the process may use, in seconds.
+    Cpu,
+    /// One greater than the maximum file descriptor number the process may open.
+    NoFile,
+    /// The maximum size of the process's stack, in bytes.
+    Stack,
+}
+
+/// An rlimit to set for a job before it starts executing. The worker must be allowed to set the
+/// limits: the hard limit can't be raised above the worker's own hard limit.
+#[pocket_definition(export)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct JobRlimit {
+    pub resource: JobRlimitResource,
+    /// The soft limit, which is the value the kernel enforces.
+    pub soft: u64,
+    /// The hard limit, which is the ceiling for the soft limit.
+    pub hard: u64,
+}
+
+impl JobRlimit {
+    /// The value used to indicate no limit.
+    pub const UNLIMITED: u64 = u64::MAX;
+}
+
+/// An rlimit value as written in TOML or JSON. This is either a number or the string
+/// `"unlimited"`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct JobRlimitValueForTomlAndJson(pub u64);
+
+impl<'de> Deserialize<'de> for JobRlimitValueForTomlAndJson {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        use serde::de::Error as _;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(u64),
+            String(String),
+        }
+
+        match Value::deserialize(deserializer)? {
+            Value::Number(value) => Ok(Self(value)),
+            Value::String(value) if value == "unlimited" => Ok(Self(JobRlimit::UNLIMITED)),
+            Value::String(value) => Err(D::Error::custom(format!(
+                "invalid rlimit value {value:?}, expected a number or \"unlimited\""
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct JobRlimitForTomlAndJson {
+    pub resource: JobRlimitResource,
+    pub soft: JobRlimitValueForTomlAndJson,
+    pub hard: JobRlimitValueForTomlAndJson,
+}
+
+impl From<JobRlimitForTomlAndJson> for JobRlimit {
+    fn from(rlimit: JobRlimitForTomlAndJson) -> Self {
+        Self {
+            resource: rlimit.resource,
+            soft: rlimit.soft.0,
+            hard: rlimit.hard.0,
+        }
+    }
+}
+
+/// The set of syscalls a job may make. See [`JobSeccomp`].
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum JobSeccompProfile {
+    /// Block the syscalls blocked by Docker's default profile, such as `mount`, `ptrace`, and
+    /// `kexec_load`.
+    Default,
+    /// Block every syscall except the given ones.
+    Allow { syscalls: Vec<String> },
+    /// Block only the given syscalls.
+    Deny { syscalls: Vec<String> },
+}
+
+/// What happens when a job makes a blocked syscall.
+#[pocket_definition(export)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobSeccompAction {
+    /// The syscall fails with the given errno. The syscalls that were blocked are reported in
+    /// [`JobEffects::blocked_syscalls`].
+    Errno(u16),
+    /// The process is killed. If it's the job's main process, the job's status will be
+    /// [`JobTerminationStatus::SyscallBlocked`].
+    Kill,
+}
+
+impl Default for JobSeccompAction {
+    fn default() -> Self {
+        // EPERM
+        Self::Errno(1)
+    }
+}
+
+/// A seccomp filter to install in a job right before it executes its program.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct JobSeccomp {
+    pub profile: JobSeccompProfile,
+    pub action: JobSeccompAction,
+}
+
+/// A [`JobSeccomp`] as written in TOML or JSON. Exactly one of `profile`, `allow`, or `deny` must
+/// be given. A profile of `"none"` means that no filter should be installed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JobSeccompForTomlAndJson(pub Option<JobSeccomp>);
+
+impl<'de> Deserialize<'de> for JobSeccompForTomlAndJson {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        use serde::de::Error as _;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum ProfileName {
+            None,
+            Default,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            profile: Option<ProfileName>,
+            allow: Option<Vec<String>>,
+            deny: Option<Vec<String>>,
+            #[serde(default)]
+            action: JobSeccompAction,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        let profile = match (fields.profile, fields.allow, fields.deny) {
+            (Some(ProfileName::None), None, None) => return Ok(Self(None)),
+            (Some(ProfileName::Default), None, None) => JobSeccompProfile::Default,
+            (None, Some(syscalls), None) => JobSeccompProfile::Allow { syscalls },
+            (None, None, Some(syscalls)) => JobSeccompProfile::Deny { syscalls },
+            _ => {
+                return Err(D::Error::custom(
+                    "exactly one of `profile`, `allow`, or `deny` must be provided",
+                ))
+            }
+        };
+        Ok(Self(Some(JobSeccomp {
+            profile,
+            action: fields.action,
+        })))
+    }
+}
+
+/// A host endpoint to forward into a job. The worker listens on the job's end, and for each
+/// connection it accepts, connects to the host's end and copies data in both directions.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum JobForward {
+    /// Listen on `port` on the job's loopback interface, and connect to `host`, which is an
+    /// address like `localhost:5432`. This requires [`JobNetwork::Loopback`].
+    Tcp { port: u16, host: String },
+    /// Listen on a Unix-domain socket at `path` in the job, and connect to the Unix-domain socket
+    /// at `host`.
+    Unix {
+        path: Utf8PathBuf,
+        host: Utf8PathBuf,
+    },
+}
+
+/// A [`JobForward`] as written in TOML or JSON. Exactly one of `tcp` or `unix` must be given. For
+/// TCP, `host` defaults to the same port on `localhost`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JobForwardForTomlAndJson(pub JobForward);
+
+impl<'de> Deserialize<'de> for JobForwardForTomlAndJson {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        use serde::de::Error as _;
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            tcp: Option<u16>,
+            unix: Option<Utf8PathBuf>,
+            host: Option<String>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        match (fields.tcp, fields.unix, fields.host) {
+            (Some(port), None, host) => Ok(Self(JobForward::Tcp {
+                port,
+                host: host.unwrap_or_else(|| format!("localhost:{port}")),
+            })),
+            (None, Some(path), Some(host)) => Ok(Self(JobForward::Unix {
+                path,
+                host: host.into(),
+            })),
+            (None, Some(_), None) => Err(D::Error::custom(
+                "field `host` must be provided for `unix` forwards",
+            )),
+            _ => Err(D::Error::custom(
+                "exactly one of `tcp` or `unix` must be provided",
+            )),
+        }
+    }
+}
+
+impl From<JobForwardForTomlAndJson> for JobForward {
+    fn from(forward: JobForwardForTomlAndJson) -> Self {
+        forward.0
+    }
+}
+
+/// How the worker decides that a sidecar is ready. The sidecar is ready once something accepts
+/// connections on `port` on the loopback interface it shares with the job.
+#[pocket_definition(export)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct JobReadinessCheck {
+    pub port: u16,
+    /// How long to wait for the sidecar to become ready before failing the job.
+    pub timeout: Timeout,
+}
+
+impl JobReadinessCheck {
+    /// The timeout used when one isn't specified.
+    pub const DEFAULT_TIMEOUT_SECONDS: u32 = 30;
+}
+
+/// A service program, like a database, run alongside a job on the same worker. Sidecars have their
+/// own file systems, but share a network namespace with the job. They are started before the job,
+/// and are killed when it terminates.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct JobSidecar {
+    pub name: String,
+    pub program: Utf8PathBuf,
+    pub arguments: Vec<String>,
+    pub environment: Vec<String>,
+    pub layers: NonEmpty<(Sha256Digest, ArtifactType)>,
+    pub mounts: Vec<JobMount>,
+    pub root_overlay: JobRootOverlay,
+    pub working_directory: Option<Utf8PathBuf>,
+    pub user: Option<UserId>,
+    pub group: Option<GroupId>,
+    pub ready: Option<JobReadinessCheck>,
+}
+
+impl JobSidecar {
+    pub fn new(
+        name: impl Into<String>,
+        program: impl Into<String>,
+        layers: impl Into<NonEmpty<(Sha256Digest, ArtifactType)>>,
+    ) -> Self {
+        JobSidecar {
+            name: name.into(),
+            program: program.into().into(),
+            layers: layers.into(),
+            arguments: Default::default(),
+            environment: Default::default(),
+            mounts: Default::default(),
+            root_overlay: Default::default(),
+            working_directory: Default::default(),
+            user: Default::default(),
+            group: Default::default(),
+            ready: Default::default(),
+        }
+    }
+
+    pub fn arguments<I, T>(mut self, arguments: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.arguments = arguments.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn ready(mut self, ready: Option<JobReadinessCheck>) -> Self {
+        self.ready = ready;
+        self
+    }
+}
+
 /// All necessary information for the worker to execute a job.
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct JobSpec {
@@ -406,6 +945,19 @@ pub struct JobSpec {
     pub estimated_duration: Option<Duration>,
     pub allocate_tty: Option<JobTty>,
     pub priority: i8,
+    pub stdin: Option<JobStdin>,
+    pub limits: JobLimits,
+    pub rlimits: Vec<JobRlimit>,
+    pub seccomp: Option<JobSeccomp>,
+    pub init: bool,
+    pub forwards: Vec<JobForward>,
+    pub sidecars: Vec<JobSidecar>,
+    pub user_entries: Option<JobUserEntries>,
+    pub hostname: Option<String>,
+    pub extra_hosts: Vec<JobHost>,
+    pub clock_offsets: Option<JobClockOffsets>,
+    pub capture_core_dump: bool,
+    pub keep_writable_layer_on_failure: bool,
 }
 
 impl JobSpec {
@@ -428,6 +980,19 @@ impl JobSpec {
             estimated_duration: Default::default(),
             allocate_tty: Default::default(),
             priority: Default::default(),
+            stdin: Default::default(),
+            limits: Default::default(),
+            rlimits: Default::default(),
+            seccomp: Default::default(),
+            init: Default::default(),
+            forwards: Default::default(),
+            sidecars: Default::default(),
+            user_entries: Default::default(),
+            hostname: Default::default(),
+            extra_hosts: Default::default(),
+            clock_offsets: Default::default(),
+            capture_core_dump: Default::default(),
+            keep_writable_layer_on_failure: Default::default(),
         }
     }
 
@@ -499,6 +1064,79 @@ impl JobSpec {
         self
     }
 
+    pub fn stdin(mut self, stdin: Option<impl Into<JobStdin>>) -> Self {
+        self.stdin = stdin.map(Into::into);
+        self
+    }
+
+    pub fn limits(mut self, limits: JobLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn rlimits(mut self, rlimits: impl IntoIterator<Item = JobRlimit>) -> Self {
+        self.rlimits = rlimits.into_iter().collect();
+        self
+    }
+
+    pub fn seccomp(mut self, seccomp: Option<JobSeccomp>) -> Self {
+        self.seccomp = seccomp;
+        self
+    }
+
+    pub fn init(mut self, init: bool) -> Self {
+        self.init = init;
+        self
+    }
+
+    pub fn forwards(mut self, forwards: impl IntoIterator<Item = JobForward>) -> Self {
+        self.forwards = forwards.into_iter().collect();
+        self
+    }
+
+    pub fn sidecars(mut self, sidecars: impl IntoIterator<Item = JobSidecar>) -> Self {
+        self.sidecars = sidecars.into_iter().collect();
+        self
+    }
+
+    pub fn user_entries(mut self, user_entries: Option<JobUserEntries>) -> Self {
+        self.user_entries = user_entries;
+        self
+    }
+
+    pub fn hostname(mut self, hostname: Option<impl Into<String>>) -> Self {
+        self.hostname = hostname.map(Into::into);
+        self
+    }
+
+    pub fn extra_hosts(mut self, extra_hosts: impl IntoIterator<Item = JobHost>) -> Self {
+        self.extra_hosts = extra_hosts.into_iter().collect();
+        self
+    }
+
+    pub fn clock_offsets(mut self, clock_offsets: Option<JobClockOffsets>) -> Self {
+        self.clock_offsets = clock_offsets;
+        self
+    }
+
+    pub fn capture_core_dump(mut self, capture_core_dump: bool) -> Self {
+        self.capture_core_dump = capture_core_dump;
+        self
+    }
+
+    pub fn keep_writable_layer_on_failure(mut self, keep_writable_layer_on_failure: bool) -> Self {
+        self.keep_writable_layer_on_failure = keep_writable_layer_on_failure;
+        self
+    }
+
+    /// The layer stacks of the job's [`JobMount::Layer`] mounts, in order.
+    pub fn layer_mounts(&self) -> impl Iterator<Item = &NonEmpty<(Sha256Digest, ArtifactType)>> {
+        self.mounts.iter().filter_map(|mount| match mount {
+            JobMount::Layer { layers, .. } => Some(layers),
+            _ => None,
+        })
+    }
+
     pub fn must_be_run_locally(&self) -> bool {
         self.network == JobNetwork::Local
             || self
@@ -511,12 +1149,16 @@ impl JobSpec {
 }
 
 /// How a job's process terminated. A process can either exit of its own accord or be killed by a
-/// signal.
+/// signal. If the worker kills the job because it went over its memory limit, the status will be
+/// `OutOfMemory` instead of `Signaled`. Similarly, if the job's seccomp filter kills it for making
+/// a blocked syscall, the status will be `SyscallBlocked`.
 #[pocket_definition(export)]
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum JobTerminationStatus {
     Exited(u8),
     Signaled(u8),
+    OutOfMemory,
+    SyscallBlocked,
 }
 
 /// The result for stdout or stderr for a job.
@@ -572,6 +1214,65 @@ impl fmt::Display for JobOutputResult {
     }
 }
 
+/// The resources a job used while it ran.
+#[pocket_definition(export)]
+#[derive(
+    Copy, Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub struct JobResourceUsage {
+    /// The peak amount of memory, in bytes, used by the job.
+    pub peak_memory: u64,
+
+    /// The amount of CPU time spent by the job in user mode.
+    pub user_time: Duration,
+
+    /// The amount of CPU time spent by the job in the kernel.
+    pub system_time: Duration,
+
+    /// The number of bytes the job read from block devices.
+    pub read_bytes: u64,
+
+    /// The number of bytes the job wrote to block devices.
+    pub written_bytes: u64,
+
+    /// The number of processes (and threads) the job spawned over its lifetime, including its
+    /// initial process. This is only known if the job ran under the worker's init. See
+    /// [`JobSpec::init`].
+    pub spawned_processes: Option<u64>,
+}
+
+/// A core file dumped by a job that asked for one with [`JobSpec::capture_core_dump`]. The worker
+/// uploads the core file to the broker as an artifact, where the client can fetch it by digest.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct JobCoreDump {
+    pub digest: Sha256Digest,
+    pub size: u64,
+
+    /// The GNU build-id of the job's program, in hex, if it has one. This is what debuggers use to
+    /// find the matching binary and debug info.
+    pub build_id: Option<String>,
+}
+
+/// A tar file of everything a failed job wrote to its root overlay and `tmp` mounts, kept because it
+/// asked for it with [`JobSpec::keep_writable_layer_on_failure`]. Like [`JobCoreDump`], the worker
+/// uploads it to the broker as an artifact.
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct JobWritableLayer {
+    pub digest: Sha256Digest,
+    pub size: u64,
+}
+
+/// A syscall that a job's seccomp filter made fail with an errno, and how many times it did. See
+/// [`JobSeccompAction::Errno`].
+#[pocket_definition(export)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct JobBlockedSyscall {
+    pub name: String,
+    pub count: u64,
+}
+
 /// The output and duration of a job that ran for some amount of time. This is generated regardless
 /// of how the job terminated. From our point of view, it doesn't matter. We ran the job until it
 /// was terminated, and gathered its output.
@@ -581,6 +1282,22 @@ pub struct JobEffects {
     pub stdout: JobOutputResult,
     pub stderr: JobOutputResult,
     pub duration: Duration,
+    pub resource_usage: Option<JobResourceUsage>,
+    pub core_dump: Option<JobCoreDump>,
+
+    /// Why the core file of a job that asked for one with [`JobSpec::capture_core_dump`] wasn't
+    /// captured, if its program was killed by a signal that dumps core.
+    pub core_dump_not_captured: Option<String>,
+    pub writable_layer: Option<JobWritableLayer>,
+
+    /// The mount points of the job's tmpfs file systems that had no space or inodes left when the
+    /// job exited. A tmp root overlay is reported as `/`. Running out of space is a common reason
+    /// for a job to fail in confusing ways.
+    pub full_tmpfs_mounts: Vec<Utf8PathBuf>,
+
+    /// The syscalls that the job's seccomp filter made fail, ordered by name. Syscalls that kill
+    /// the job are reported with [`JobTerminationStatus::SyscallBlocked`] instead.
+    pub blocked_syscalls: Vec<JobBlockedSyscall>,
 }
 
 /// The outcome of a completed job. That is, a job that ran to completion, instead of timing out,
@@ -1044,7 +1761,10 @@ mod tests {
         let spec = spec.root_overlay(JobRootOverlay::None);
         assert_eq!(spec.must_be_run_locally(), false);
 
-        let spec = spec.root_overlay(JobRootOverlay::Tmp);
+        let spec = spec.root_overlay(JobRootOverlay::Tmp {
+            size: None,
+            inodes: None,
+        });
         assert_eq!(spec.must_be_run_locally(), false);
 
         let spec = spec.root_overlay(JobRootOverlay::Local {
@@ -1069,6 +1789,108 @@ mod tests {
         assert_eq!(spec.must_be_run_locally(), false);
     }
 
+    #[test]
+    fn job_spec_must_be_run_locally_stdin() {
+        let spec = JobSpec::new(
+            "foo",
+            nonempty![(Sha256Digest::from(0u32), ArtifactType::Tar)],
+        );
+        assert_eq!(spec.must_be_run_locally(), false);
+
+        let spec = spec.stdin(Some(JobStdin::Inline(b"input".to_vec().into_boxed_slice())));
+        assert_eq!(spec.must_be_run_locally(), false);
+
+        let spec = spec.stdin(Some(JobStdin::Artifact(Sha256Digest::from(1u32))));
+        assert_eq!(spec.must_be_run_locally(), false);
+    }
+
+    #[test]
+    fn validate_user_entries_accepts_good_entries() {
+        for entries in [
+            JobUserEntries::default(),
+            JobUserEntries {
+                user_name: Some("alice".into()),
+                group_name: Some("staff-1".into()),
+                home: Some("/home/alice smith".into()),
+            },
+        ] {
+            entries.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn validate_user_entries_rejects_bad_names() {
+        for name in [
+            "",
+            "alice:x",
+            "alice,bob",
+            "alice\nroot:x:0:0::/root:/bin/sh",
+        ] {
+            let user = JobUserEntries {
+                user_name: Some(name.into()),
+                ..Default::default()
+            };
+            let group = JobUserEntries {
+                group_name: Some(name.into()),
+                ..Default::default()
+            };
+            for entries in [user, group] {
+                assert_eq!(
+                    entries.validate().unwrap_err().to_string(),
+                    format!("{name:?} isn't a valid user or group name"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_user_entries_rejects_bad_homes() {
+        for home in [
+            "",
+            "home/alice",
+            "/home/a:b",
+            "/home/alice\nroot:x:0:0::/root:/bin/sh",
+        ] {
+            let entries = JobUserEntries {
+                home: Some(home.into()),
+                ..Default::default()
+            };
+            assert_eq!(
+                entries.validate().unwrap_err().to_string(),
+                format!("{home:?} isn't a valid home directory"),
+            );
+        }
+    }
+
+    #[test]
+    fn validate_hostname_accepts_rfc_1123_names() {
+        for name in ["db", "db-1", "1db", "db.local", "a.b.c.d", &"a".repeat(63)] {
+            validate_hostname(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn validate_hostname_rejects_bad_names() {
+        for name in [
+            "",
+            "db.",
+            ".db",
+            "db..local",
+            "-db",
+            "db-",
+            "db_1",
+            "db local",
+            "db\n10.0.0.1 evil",
+            &"a".repeat(64),
+            &["a"; 128].join("."),
+        ] {
+            assert_eq!(
+                validate_hostname(name).unwrap_err().to_string(),
+                format!("{name:?} isn't a valid hostname"),
+            );
+        }
+    }
+
     trait AssertError {
         fn assert_error(&self, expected: &str);
     }
@@ -1129,4 +1951,162 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn rlimit_numbers() {
+        let rlimit: JobRlimitForTomlAndJson =
+            deserialize_value(r#"{ resource = "nofile", soft = 1024, hard = 4096 }"#);
+        assert_eq!(
+            JobRlimit::from(rlimit),
+            JobRlimit {
+                resource: JobRlimitResource::NoFile,
+                soft: 1024,
+                hard: 4096,
+            }
+        );
+    }
+
+    #[test]
+    fn rlimit_unlimited() {
+        let rlimit: JobRlimitForTomlAndJson =
+            deserialize_value(r#"{ resource = "stack", soft = 8388608, hard = "unlimited" }"#);
+        assert_eq!(
+            JobRlimit::from(rlimit),
+            JobRlimit {
+                resource: JobRlimitResource::Stack,
+                soft: 8388608,
+                hard: JobRlimit::UNLIMITED,
+            }
+        );
+    }
+
+    #[test]
+    fn rlimit_bad_value() {
+        deserialize_value_error::<JobRlimitForTomlAndJson>(
+            r#"{ resource = "core", soft = "none", hard = 0 }"#,
+        )
+        .assert_error(r#"invalid rlimit value "none""#);
+    }
+
+    #[test]
+    fn rlimit_unknown_resource() {
+        deserialize_value_error::<JobRlimitForTomlAndJson>(
+            r#"{ resource = "memlock", soft = 0, hard = 0 }"#,
+        )
+        .assert_error("unknown variant `memlock`");
+    }
+
+    #[test]
+    fn seccomp_default_profile() {
+        let seccomp: JobSeccompForTomlAndJson =
+            deserialize_value(r#"{ profile = "default", action = "kill" }"#);
+        assert_eq!(
+            seccomp.0,
+            Some(JobSeccomp {
+                profile: JobSeccompProfile::Default,
+                action: JobSeccompAction::Kill,
+            })
+        );
+    }
+
+    #[test]
+    fn seccomp_no_profile() {
+        let seccomp: JobSeccompForTomlAndJson = deserialize_value(r#"{ profile = "none" }"#);
+        assert_eq!(seccomp.0, None);
+    }
+
+    #[test]
+    fn seccomp_allow_with_errno() {
+        let seccomp: JobSeccompForTomlAndJson =
+            deserialize_value(r#"{ allow = ["read", "write"], action = { errno = 38 } }"#);
+        assert_eq!(
+            seccomp.0,
+            Some(JobSeccomp {
+                profile: JobSeccompProfile::Allow {
+                    syscalls: vec!["read".into(), "write".into()]
+                },
+                action: JobSeccompAction::Errno(38),
+            })
+        );
+    }
+
+    #[test]
+    fn seccomp_deny_with_default_action() {
+        let seccomp: JobSeccompForTomlAndJson = deserialize_value(r#"{ deny = ["ptrace"] }"#);
+        assert_eq!(
+            seccomp.0,
+            Some(JobSeccomp {
+                profile: JobSeccompProfile::Deny {
+                    syscalls: vec!["ptrace".into()]
+                },
+                action: JobSeccompAction::Errno(1),
+            })
+        );
+    }
+
+    #[test]
+    fn seccomp_allow_and_deny() {
+        deserialize_value_error::<JobSeccompForTomlAndJson>(
+            r#"{ allow = ["read"], deny = ["ptrace"] }"#,
+        )
+        .assert_error("exactly one of `profile`, `allow`, or `deny` must be provided");
+    }
+
+    #[test]
+    fn forward_tcp() {
+        let forward: JobForwardForTomlAndJson =
+            deserialize_value(r#"{ tcp = 5432, host = "db:5433" }"#);
+        assert_eq!(
+            JobForward::from(forward),
+            JobForward::Tcp {
+                port: 5432,
+                host: "db:5433".into()
+            }
+        );
+    }
+
+    #[test]
+    fn forward_tcp_default_host() {
+        let forward: JobForwardForTomlAndJson = deserialize_value(r#"{ tcp = 5432 }"#);
+        assert_eq!(
+            JobForward::from(forward),
+            JobForward::Tcp {
+                port: 5432,
+                host: "localhost:5432".into()
+            }
+        );
+    }
+
+    #[test]
+    fn forward_unix() {
+        let forward: JobForwardForTomlAndJson =
+            deserialize_value(r#"{ unix = "/tmp/db.sock", host = "/run/db.sock" }"#);
+        assert_eq!(
+            JobForward::from(forward),
+            JobForward::Unix {
+                path: "/tmp/db.sock".into(),
+                host: "/run/db.sock".into()
+            }
+        );
+    }
+
+    #[test]
+    fn forward_unix_without_host() {
+        deserialize_value_error::<JobForwardForTomlAndJson>(r#"{ unix = "/tmp/db.sock" }"#)
+            .assert_error("field `host` must be provided for `unix` forwards");
+    }
+
+    #[test]
+    fn forward_tcp_and_unix() {
+        deserialize_value_error::<JobForwardForTomlAndJson>(
+            r#"{ tcp = 5432, unix = "/tmp/db.sock", host = "/run/db.sock" }"#,
+        )
+        .assert_error("exactly one of `tcp` or `unix` must be provided");
+    }
+
+    #[test]
+    fn seccomp_nothing() {
+        deserialize_value_error::<JobSeccompForTomlAndJson>(r#"{ action = "kill" }"#)
+            .assert_error("exactly one of `profile`, `allow`, or `deny` must be provided");
+    }
 }
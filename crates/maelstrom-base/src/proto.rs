@@ -6,25 +6,36 @@ use crate::{
 };
 use bincode::Options;
 use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, time::SystemTime};
 
 /// The first message sent by a connector to the broker. It identifies what the connector is, and
 /// provides any relevant information.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Hello {
     Client,
-    Worker { slots: u32 },
+    /// A worker. If `artifact_server_port` is set, the worker serves the artifacts in its cache to
+    /// other workers' artifact fetchers on that port, at the address it connected to the broker
+    /// from.
+    Worker {
+        slots: u32,
+        artifact_server_port: Option<u16>,
+    },
     Monitor,
     ArtifactPusher,
     ArtifactFetcher,
+    ChunkedArtifactPusher,
 }
 
 /// Message sent from the broker to a worker. The broker won't send a message until it has received
 /// a [`Hello`] and determined the type of its interlocutor.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-#[allow(clippy::large_enum_variant)]
 pub enum BrokerToWorker {
-    EnqueueJob(JobId, JobSpec),
+    EnqueueJob(JobId, Box<JobSpec>),
     CancelJob(JobId),
+    /// A hint that the broker expects to send the worker this job soon, so it should start
+    /// fetching the job's layers. The worker is free to ignore it. If the job ends up being sent
+    /// to a different worker, the broker will send a [`BrokerToWorker::CancelJob`] for it.
+    Prefetch(JobId, Box<JobSpec>),
 }
 
 /// Message sent from a worker to the broker. These are responses to previous
@@ -32,25 +43,37 @@ pub enum BrokerToWorker {
 /// send a stream of these messages.
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum WorkerToBroker {
-    JobResponse(JobId, JobOutcomeResult),
+    JobResponse(JobId, Box<JobOutcomeResult>),
     JobStatusUpdate(JobId, JobWorkerStatus),
+    /// The worker has fetched the artifact with the given digest into its cache. If the worker has
+    /// an artifact server, the broker may direct other workers' artifact fetchers to it.
+    ArtifactCached(Sha256Digest),
 }
 
 /// Message sent from the broker to a client. The broker won't send a message until it has received
 /// a [`Hello`] and determined the type of its interlocutor.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum BrokerToClient {
-    JobResponse(ClientJobId, JobOutcomeResult),
+    JobResponse(ClientJobId, Box<JobOutcomeResult>),
     JobStatusUpdate(ClientJobId, JobBrokerStatus),
     TransferArtifact(Sha256Digest),
+    /// The answer to a [`ClientToBroker::ArtifactsQuery`]: the queried artifacts the broker
+    /// doesn't have and wants the client to push. If the broker has an artifact store, it looks
+    /// there first, and asks for the artifacts the store doesn't have with
+    /// [`BrokerToClient::TransferArtifact`] instead.
+    MissingArtifacts(Vec<Sha256Digest>),
 }
 
 /// Message sent from a client to the broker. After sending the initial [`Hello`], a client will
 /// send a stream of these messages.
-#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum ClientToBroker {
-    JobRequest(ClientJobId, JobSpec),
+    JobRequest(ClientJobId, Box<JobSpec>),
+    /// Ask the broker which of the given artifacts it is missing. The broker answers with a
+    /// [`BrokerToClient::MissingArtifacts`]. It then expects the client to push those artifacts,
+    /// so it won't ask for them again with [`BrokerToClient::TransferArtifact`] when jobs that use
+    /// them are submitted.
+    ArtifactsQuery(Vec<Sha256Digest>),
 }
 
 /// Message sent from the broker to a monitor. The broker won't send a message until it has
@@ -58,6 +81,13 @@ pub enum ClientToBroker {
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum BrokerToMonitor {
     StatisticsResponse(BrokerStatistics),
+    /// The answer to a [`MonitorToBroker::CacheContentsRequest`]: the artifacts in the broker's
+    /// cache, ordered by digest.
+    CacheContentsResponse(Vec<CachedArtifact>),
+    /// The answer to a [`MonitorToBroker::PinArtifacts`] or [`MonitorToBroker::UnpinArtifacts`]:
+    /// all of the pinned artifacts, including those that aren't in the cache yet, ordered by
+    /// digest.
+    PinnedArtifacts(Vec<Sha256Digest>),
 }
 
 /// Message sent from a monitor to the broker. After sending the initial [`Hello`], a monitor will
@@ -65,6 +95,52 @@ pub enum BrokerToMonitor {
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum MonitorToBroker {
     StatisticsRequest,
+    /// Ask for the contents of the broker's cache. It will be answered with a
+    /// [`BrokerToMonitor::CacheContentsResponse`].
+    CacheContentsRequest,
+    /// Pin the given artifacts, so the broker never evicts them from its cache. Artifacts that
+    /// aren't in the cache are pinned once they get there. Pins survive broker restarts. It will
+    /// be answered with a [`BrokerToMonitor::PinnedArtifacts`].
+    PinArtifacts(Vec<Sha256Digest>),
+    /// Unpin the given artifacts. It will be answered with a [`BrokerToMonitor::PinnedArtifacts`].
+    UnpinArtifacts(Vec<Sha256Digest>),
+}
+
+/// An artifact in the broker's cache. See [`BrokerToMonitor::CacheContentsResponse`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CachedArtifact {
+    pub digest: Sha256Digest,
+    /// The size of the artifact, not counting any compressed copy.
+    pub size: u64,
+    /// How many queued or running jobs and transfers are using the artifact. Pins aren't counted.
+    pub refcount: u32,
+    pub pinned: bool,
+    /// When the artifact was last done being used. This is `None` if it hasn't been used since
+    /// the broker started.
+    pub last_used: Option<SystemTime>,
+}
+
+/// How the body of an artifact is encoded when it is sent over a connection. Sizes and digests sent
+/// along with an artifact always refer to the uncompressed artifact.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ArtifactCompression {
+    /// The body is sent as is.
+    #[default]
+    None,
+    /// The body is sent as a single zstd frame.
+    Zstd,
+}
+
+/// Where an artifact fetcher should get an artifact from. See [`BrokerToArtifactFetcher`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ArtifactSource {
+    /// The broker will send the artifact, which has the given size, immediately after this message.
+    /// The body will be encoded as indicated, which will be either [`ArtifactCompression::None`]
+    /// or the compression the fetcher said it accepts.
+    Broker(u64, ArtifactCompression),
+    /// The fetcher should get the artifact from the artifact server of the worker at the given
+    /// address. If that doesn't work, it should ask the broker again, without allowing peers.
+    Peer(SocketAddr),
 }
 
 /// Message sent from the broker to an artifact fetcher. This will be in response to an
@@ -72,12 +148,40 @@ pub enum MonitorToBroker {
 /// details about what went wrong. After a failure, the broker will close the artifact fetcher
 /// connection.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct BrokerToArtifactFetcher(pub Result<u64, String>);
+pub struct BrokerToArtifactFetcher(pub Result<ArtifactSource, String>);
 
 /// Message sent from an artifact fetcher to the broker. It will be answered with a
-/// [`BrokerToArtifactFetcher`].
+/// [`BrokerToArtifactFetcher`]. If `allow_peer` is set, the broker may send the fetcher to a worker
+/// that already has the artifact instead of sending the artifact itself. The broker may send the
+/// artifact compressed with `accept_compression`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArtifactFetcherToBroker {
+    pub digest: Sha256Digest,
+    pub allow_peer: bool,
+    pub accept_compression: ArtifactCompression,
+}
+
+/// Message sent from a worker's artifact server to an artifact fetcher. This will be in response
+/// to an [`ArtifactFetcherToWorker`] message. On success, it contains the size of the artifact,
+/// and the artifact's body will immediately follow. On failure, it contains details about what
+/// went wrong, and the worker will close the connection.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WorkerToArtifactFetcher(pub Result<u64, String>);
+
+/// Message sent from an artifact fetcher to another worker's artifact server, after being directed
+/// there with [`ArtifactSource::Peer`]. It will be answered with a [`WorkerToArtifactFetcher`].
+/// Since the artifact comes from a peer, the fetcher must verify its digest.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArtifactFetcherToWorker(pub Sha256Digest);
+
+/// Message sent from the broker to an artifact pusher or a chunked artifact pusher right after it
+/// receives the pusher's [`Hello`]. It contains the compression, besides
+/// [`ArtifactCompression::None`], that the broker accepts for artifact bodies. A pusher must send
+/// bodies uncompressed if the broker doesn't accept the compression it would otherwise use.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct ArtifactFetcherToBroker(pub Sha256Digest);
+pub struct BrokerToArtifactPusherHello {
+    pub accept_compression: ArtifactCompression,
+}
 
 /// Message sent from the broker to an artifact pusher. This will be in response to an
 /// [`ArtifactPusherToBroker`] message and the artifact's body. On success, the message contains no
@@ -88,10 +192,56 @@ pub struct ArtifactFetcherToBroker(pub Sha256Digest);
 pub struct BrokerToArtifactPusher(pub Result<(), String>);
 
 /// Message sent from an artifact pusher to the broker. It contains the digest and size of the
-/// artifact. The body of the artifact will immediately follow this message. It will be answered
-/// with a [`BrokerToArtifactPusher`].
+/// artifact, and how its body is encoded. The body of the artifact will immediately follow this
+/// message. It will be answered with a [`BrokerToArtifactPusher`]. The body must be encoded with
+/// either [`ArtifactCompression::None`] or the compression the broker said it accepts in its
+/// [`BrokerToArtifactPusherHello`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArtifactPusherToBroker(pub Sha256Digest, pub u64, pub ArtifactCompression);
+
+/// A chunk of an artifact, as produced by content-defined chunking. It is identified by the digest
+/// and size of its contents.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ArtifactChunk {
+    pub digest: Sha256Digest,
+    pub size: u64,
+}
+
+/// Message sent from a chunked artifact pusher to the broker.
+///
+/// A push starts with [`Self::Artifact`]. The pusher then sends each of the chunks the broker is
+/// missing with [`Self::Chunk`], and finishes with [`Self::Assemble`]. The broker keeps the chunks
+/// it receives even if the push doesn't finish, so a pusher whose connection drops can start over
+/// and only send the chunks that didn't make it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ChunkedArtifactPusherToBroker {
+    /// Start pushing the artifact with the given digest and size, which consists of the given
+    /// chunks, in order. It will be answered with [`BrokerToChunkedArtifactPusher::MissingChunks`].
+    Artifact {
+        digest: Sha256Digest,
+        size: u64,
+        chunks: Vec<ArtifactChunk>,
+    },
+    /// The body of the given chunk, encoded as indicated, will immediately follow this message. It
+    /// isn't answered. The encoding must be either [`ArtifactCompression::None`] or the
+    /// compression the broker said it accepts in its [`BrokerToArtifactPusherHello`].
+    Chunk(ArtifactChunk, ArtifactCompression),
+    /// All of the missing chunks have been sent. It will be answered with
+    /// [`BrokerToChunkedArtifactPusher::ArtifactAssembled`].
+    Assemble,
+}
+
+/// Message sent from the broker to a chunked artifact pusher. See
+/// [`ChunkedArtifactPusherToBroker`].
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct ArtifactPusherToBroker(pub Sha256Digest, pub u64);
+pub enum BrokerToChunkedArtifactPusher {
+    /// The chunks of the artifact that the broker doesn't have, without duplicates.
+    MissingChunks(Vec<ArtifactChunk>),
+    /// The artifact was assembled from its chunks, and its digest matched.
+    ArtifactAssembled,
+    /// Something went wrong. The broker will close the connection after sending this message.
+    Error(String),
+}
 
 fn bincode() -> impl Options {
     bincode::options().with_big_endian()
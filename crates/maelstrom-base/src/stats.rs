@@ -136,9 +136,21 @@ pub struct WorkerStatistics {
     pub slots: usize,
 }
 
+/// Running totals of the artifacts the broker has removed from its cache to make room.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvictionStatistics {
+    /// The number of times the cache has gone over its size and removed artifacts.
+    pub runs: u64,
+    /// The number of artifacts removed.
+    pub artifacts: u64,
+    /// The number of bytes freed by removing artifacts.
+    pub bytes: u64,
+}
+
 /// Useful information for a client to display about the broker's state.
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct BrokerStatistics {
     pub worker_statistics: HashMap<WorkerId, WorkerStatistics>,
     pub job_statistics: JobStatisticsTimeSeries,
+    pub eviction_statistics: EvictionStatistics,
 }
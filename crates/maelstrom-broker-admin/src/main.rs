@@ -0,0 +1,225 @@
+//! Administer a running broker's artifact cache.
+//!
+//! The cache is inspected and pinned through a monitor connection. Exports are fetched through an
+//! artifact fetcher connection, and imports are pushed through an artifact pusher connection, just
+//! like workers and clients do. An exported tarball contains one `sha256/<digest>` file per
+//! artifact.
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use bytesize::ByteSize;
+use chrono::{DateTime, Local};
+use clap::{Parser, Subcommand};
+use maelstrom_base::{
+    proto::{
+        ArtifactCompression, ArtifactFetcherToBroker, ArtifactPusherToBroker, ArtifactSource,
+        BrokerToArtifactFetcher, BrokerToArtifactPusher, BrokerToArtifactPusherHello,
+        BrokerToMonitor, CachedArtifact, Hello, MonitorToBroker,
+    },
+    Sha256Digest,
+};
+use maelstrom_util::net;
+use std::{
+    fs::File,
+    io::{self, BufReader, Read as _},
+    net::TcpStream,
+    path::{Path, PathBuf},
+};
+
+/// Administer a broker's artifact cache.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Socket address of the broker.
+    #[arg(short, long, value_name = "SOCKADDR")]
+    broker: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the artifacts in the cache, with their sizes, refcounts, and when they were last used.
+    List,
+    /// Pin artifacts so the broker never evicts them, then print all pinned artifacts. Artifacts
+    /// that aren't in the cache yet are pinned when they get there.
+    Pin {
+        #[arg(required = true, value_name = "DIGEST")]
+        digests: Vec<Sha256Digest>,
+    },
+    /// Unpin artifacts, then print all pinned artifacts.
+    Unpin {
+        #[arg(required = true, value_name = "DIGEST")]
+        digests: Vec<Sha256Digest>,
+    },
+    /// Write artifacts from the cache to a tarball. If no digests are given, all artifacts in the
+    /// cache are written.
+    Export {
+        /// Only write pinned artifacts.
+        #[arg(long, conflicts_with = "digests")]
+        pinned: bool,
+        path: PathBuf,
+        #[arg(value_name = "DIGEST")]
+        digests: Vec<Sha256Digest>,
+    },
+    /// Push the artifacts in a tarball written by `export` into the cache.
+    Import { path: PathBuf },
+    /// Print how many artifacts the broker has evicted from the cache since it started.
+    Evictions,
+}
+
+fn connect(broker: &str, hello: Hello) -> Result<TcpStream> {
+    let mut socket =
+        TcpStream::connect(broker).with_context(|| format!("connecting to broker at {broker}"))?;
+    net::write_message_to_socket(&mut socket, hello)?;
+    Ok(socket)
+}
+
+fn monitor_request(broker: &str, msg: MonitorToBroker) -> Result<BrokerToMonitor> {
+    let mut socket = connect(broker, Hello::Monitor)?;
+    net::write_message_to_socket(&mut socket, msg)?;
+    net::read_message_from_socket(&mut socket)
+}
+
+fn cached_artifacts(broker: &str) -> Result<Vec<CachedArtifact>> {
+    match monitor_request(broker, MonitorToBroker::CacheContentsRequest)? {
+        BrokerToMonitor::CacheContentsResponse(artifacts) => Ok(artifacts),
+        resp => bail!("unexpected response: {resp:?}"),
+    }
+}
+
+fn list(broker: &str) -> Result<()> {
+    println!(
+        "{:<64}  {:>10}  {:>8}  {:<6}  LAST USED",
+        "DIGEST", "SIZE", "REFCOUNT", "PINNED"
+    );
+    for artifact in cached_artifacts(broker)? {
+        let last_used = match artifact.last_used {
+            Some(last_used) => DateTime::<Local>::from(last_used)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            None => "-".into(),
+        };
+        println!(
+            "{:<64}  {:>10}  {:>8}  {:<6}  {last_used}",
+            artifact.digest.to_string(),
+            ByteSize::b(artifact.size).to_string(),
+            artifact.refcount,
+            if artifact.pinned { "yes" } else { "no" },
+        );
+    }
+    Ok(())
+}
+
+fn evictions(broker: &str) -> Result<()> {
+    match monitor_request(broker, MonitorToBroker::StatisticsRequest)? {
+        BrokerToMonitor::StatisticsResponse(stats) => {
+            let evictions = stats.eviction_statistics;
+            println!("runs:      {}", evictions.runs);
+            println!("artifacts: {}", evictions.artifacts);
+            println!("bytes:     {}", ByteSize::b(evictions.bytes));
+            Ok(())
+        }
+        resp => bail!("unexpected response: {resp:?}"),
+    }
+}
+
+fn change_pins(broker: &str, msg: MonitorToBroker) -> Result<()> {
+    match monitor_request(broker, msg)? {
+        BrokerToMonitor::PinnedArtifacts(pinned) => {
+            for digest in pinned {
+                println!("{digest}");
+            }
+            Ok(())
+        }
+        resp => bail!("unexpected response: {resp:?}"),
+    }
+}
+
+fn export(broker: &str, pinned: bool, path: &Path, digests: Vec<Sha256Digest>) -> Result<()> {
+    let digests = if digests.is_empty() {
+        Vec::from_iter(
+            cached_artifacts(broker)?
+                .into_iter()
+                .filter(|artifact| !pinned || artifact.pinned)
+                .map(|artifact| artifact.digest),
+        )
+    } else {
+        digests
+    };
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let mut builder = tar::Builder::new(file);
+    let mut socket = connect(broker, Hello::ArtifactFetcher)?;
+    for digest in digests {
+        net::write_message_to_socket(
+            &mut socket,
+            ArtifactFetcherToBroker {
+                digest: digest.clone(),
+                allow_peer: false,
+                accept_compression: ArtifactCompression::None,
+            },
+        )?;
+        let BrokerToArtifactFetcher(result) = net::read_message_from_socket(&mut socket)?;
+        let size = match result.map_err(|err| anyhow!("error exporting {digest}: {err}"))? {
+            ArtifactSource::Broker(size, ArtifactCompression::None) => size,
+            source => bail!("unexpected source for {digest}: {source:?}"),
+        };
+        let mut header = tar::Header::new_gnu();
+        header.set_size(size);
+        header.set_mode(0o444);
+        builder.append_data(
+            &mut header,
+            format!("sha256/{digest}"),
+            (&mut socket).take(size),
+        )?;
+        println!("{digest}");
+    }
+    builder.into_inner()?;
+    Ok(())
+}
+
+fn import(broker: &str, path: &Path) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut archive = tar::Archive::new(BufReader::new(file));
+    let mut socket = connect(broker, Hello::ArtifactPusher)?;
+    // We always send uncompressed bodies, which the broker accepts whatever it advertises.
+    let _: BrokerToArtifactPusherHello = net::read_message_from_socket(&mut socket)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let digest: Sha256Digest = entry_path
+            .strip_prefix("sha256")
+            .ok()
+            .and_then(Path::to_str)
+            .ok_or_else(|| anyhow!("unexpected tarball entry {}", entry_path.display()))?
+            .parse()?;
+        let size = entry.size();
+        net::write_message_to_socket(
+            &mut socket,
+            ArtifactPusherToBroker(digest.clone(), size, ArtifactCompression::None),
+        )?;
+        let copied = io::copy(&mut entry, &mut socket)?;
+        ensure!(copied == size, "tarball entry for {digest} is truncated");
+        let BrokerToArtifactPusher(result) = net::read_message_from_socket(&mut socket)?;
+        result.map_err(|err| anyhow!("error importing {digest}: {err}"))?;
+        println!("{digest}");
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let broker = cli.broker.as_str();
+    match cli.command {
+        Command::List => list(broker),
+        Command::Pin { digests } => change_pins(broker, MonitorToBroker::PinArtifacts(digests)),
+        Command::Unpin { digests } => change_pins(broker, MonitorToBroker::UnpinArtifacts(digests)),
+        Command::Export {
+            pinned,
+            path,
+            digests,
+        } => export(broker, pinned, &path, digests),
+        Command::Import { path } => import(broker, &path),
+        Command::Evictions => evictions(broker),
+    }
+}
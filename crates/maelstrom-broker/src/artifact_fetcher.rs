@@ -1,7 +1,12 @@
-use crate::scheduler_task::{SchedulerMessage, SchedulerSender};
+use crate::{
+    artifact_pusher::{self, CompressionConfig},
+    scheduler_task::{SchedulerMessage, SchedulerSender, WorkerArtifactSource},
+};
 use anyhow::Result;
 use maelstrom_base::{
-    proto::{ArtifactFetcherToBroker, BrokerToArtifactFetcher},
+    proto::{
+        ArtifactCompression, ArtifactFetcherToBroker, ArtifactSource, BrokerToArtifactFetcher,
+    },
     Sha256Digest,
 };
 use maelstrom_util::{
@@ -9,58 +14,162 @@ use maelstrom_util::{
     net,
 };
 use slog::{debug, Logger};
-use std::{io, net::TcpStream, sync::mpsc};
+use std::{
+    io,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+use tempfile::NamedTempFile;
 
-fn get_file<'fs>(
-    fs: &'fs Fs,
+fn get_source(
     digest: &Sha256Digest,
+    allow_peer: bool,
     scheduler_sender: &SchedulerSender,
-) -> Result<(File<'fs>, u64)> {
+) -> Result<WorkerArtifactSource> {
     let (channel_sender, channel_receiver) = mpsc::channel();
     scheduler_sender.send(SchedulerMessage::GetArtifactForWorker(
         digest.clone(),
+        allow_peer,
         channel_sender,
     ))?;
+    Ok(channel_receiver.recv()??)
+}
 
-    let (path, size) = channel_receiver.recv()??;
-    let f = fs.open_file(path)?;
-    Ok((f, size))
+/// How we're going to send an artifact from our cache.
+enum Body<'fs> {
+    /// Send the file as is.
+    Uncompressed(File<'fs>),
+    /// Compress the file as we send it, also writing the compressed bytes to the temporary file so
+    /// that they can be kept as the artifact's compressed copy.
+    Compress(File<'fs>, NamedTempFile),
+    /// Send the file, which is the compressed copy of the artifact, as is.
+    Compressed(File<'fs>),
+}
+
+/// Writes everything written to it to both of its writers.
+struct Tee<A, B>(A, B);
+
+impl<A: io::Write, B: io::Write> io::Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.0.write(buf)?;
+        self.1.write_all(&buf[..written])?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        self.1.flush()
+    }
 }
 
 fn send_artifact(
     scheduler_sender: &SchedulerSender,
-    mut file: &mut File<'_>,
+    body: Body<'_>,
     mut socket: &mut impl io::Write,
     size: u64,
     digest: Sha256Digest,
 ) -> Result<()> {
-    let copied = io::copy(&mut file, &mut socket)?;
-    assert_eq!(copied, size);
+    match body {
+        Body::Uncompressed(mut file) => {
+            let copied = io::copy(&mut file, &mut socket)?;
+            assert_eq!(copied, size);
+        }
+        Body::Compress(mut file, mut tmp) => {
+            let mut encoder = zstd::Encoder::new(Tee(&mut socket, &mut tmp), 0)?;
+            let copied = io::copy(&mut file, &mut encoder)?;
+            assert_eq!(copied, size);
+            encoder.finish()?;
+            // We still hold a refcount on the artifact, so the cache can't have removed it yet.
+            let (_, path) = tmp.keep()?;
+            scheduler_sender.send(SchedulerMessage::GotCompressedCopy(digest.clone(), path))?;
+        }
+        Body::Compressed(mut file) => {
+            io::copy(&mut file, &mut socket)?;
+        }
+    }
     scheduler_sender.send(SchedulerMessage::DecrementRefcount(digest))?;
     Ok(())
 }
 
+/// Decide how to send an artifact, given what the fetcher accepts. When both we and the fetcher
+/// accept compression, we send our compressed copy if we have one. Otherwise, we compress on the
+/// fly, and keep what we send as the compressed copy for next time.
+fn choose_body<'fs>(
+    fs: &'fs Fs,
+    path: PathBuf,
+    compressed_path: Option<PathBuf>,
+    accept_compression: ArtifactCompression,
+    compression: CompressionConfig,
+    cache_tmp_path: &Path,
+) -> Result<(Body<'fs>, ArtifactCompression)> {
+    let accept_compression = if accept_compression == compression.accept {
+        accept_compression
+    } else {
+        ArtifactCompression::None
+    };
+    Ok(match (accept_compression, compressed_path) {
+        (ArtifactCompression::None, _) => (
+            Body::Uncompressed(fs.open_file(path)?),
+            ArtifactCompression::None,
+        ),
+        (ArtifactCompression::Zstd, Some(compressed_path)) => (
+            Body::Compressed(fs.open_file(compressed_path)?),
+            ArtifactCompression::Zstd,
+        ),
+        (ArtifactCompression::Zstd, None) => (
+            Body::Compress(
+                fs.open_file(path)?,
+                artifact_pusher::compressed_copy_tempfile(cache_tmp_path)?,
+            ),
+            ArtifactCompression::Zstd,
+        ),
+    })
+}
+
 fn handle_one_message(
     msg: ArtifactFetcherToBroker,
     mut socket: &mut impl io::Write,
     scheduler_sender: &SchedulerSender,
+    cache_tmp_path: &Path,
+    compression: CompressionConfig,
     log: &mut Logger,
 ) -> Result<()> {
     debug!(log, "received artifact fetcher message"; "msg" => ?msg);
-    let ArtifactFetcherToBroker(digest) = msg;
+    let ArtifactFetcherToBroker {
+        digest,
+        allow_peer,
+        accept_compression,
+    } = msg;
     let fs = Fs::new();
-    let result = get_file(&fs, &digest, scheduler_sender);
+    let result = get_source(&digest, allow_peer, scheduler_sender).and_then(|source| {
+        Ok(match source {
+            WorkerArtifactSource::Cache(path, size, compressed_path) => {
+                let (body, body_compression) = choose_body(
+                    &fs,
+                    path,
+                    compressed_path,
+                    accept_compression,
+                    compression,
+                    cache_tmp_path,
+                )?;
+                (Some(body), ArtifactSource::Broker(size, body_compression))
+            }
+            WorkerArtifactSource::Peer(addr) => (None, ArtifactSource::Peer(addr)),
+        })
+    });
     let msg = BrokerToArtifactFetcher(
         result
             .as_ref()
-            .map(|(_, size)| *size)
+            .map(|(_, source)| source.clone())
             .map_err(|e| e.to_string()),
     );
     debug!(log, "sending artifact fetcher message"; "msg" => ?msg);
     net::write_message_to_socket(&mut socket, msg)?;
 
-    let (mut f, size) = result?;
-    send_artifact(scheduler_sender, &mut f, &mut socket, size, digest)?;
+    if let (Some(body), ArtifactSource::Broker(size, _)) = result? {
+        send_artifact(scheduler_sender, body, &mut socket, size, digest)?;
+    }
 
     Ok(())
 }
@@ -68,21 +177,39 @@ fn handle_one_message(
 fn connection_loop(
     mut socket: TcpStream,
     scheduler_sender: &SchedulerSender,
+    cache_tmp_path: &Path,
+    compression: CompressionConfig,
     log: &mut Logger,
 ) -> Result<()> {
     loop {
         let msg = net::read_message_from_socket(&mut socket)?;
-        handle_one_message(msg, &mut socket, scheduler_sender, log)?;
+        handle_one_message(
+            msg,
+            &mut socket,
+            scheduler_sender,
+            cache_tmp_path,
+            compression,
+            log,
+        )?;
     }
 }
 
 pub fn connection_main(
     socket: TcpStream,
     scheduler_sender: SchedulerSender,
+    cache_tmp_path: PathBuf,
+    compression: CompressionConfig,
     mut log: Logger,
 ) -> Result<()> {
     debug!(log, "artifact fetcher connected");
-    let err = connection_loop(socket, &scheduler_sender, &mut log).unwrap_err();
+    let err = connection_loop(
+        socket,
+        &scheduler_sender,
+        &cache_tmp_path,
+        compression,
+        &mut log,
+    )
+    .unwrap_err();
     debug!(log, "artifact fetcher disconnected"; "err" => %err);
     Err(err)
 }
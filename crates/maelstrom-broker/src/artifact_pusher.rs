@@ -1,64 +1,195 @@
-use crate::scheduler_task::{SchedulerMessage, SchedulerSender};
-use anyhow::Result;
-use maelstrom_base::proto::{ArtifactPusherToBroker, BrokerToArtifactPusher};
+use crate::{
+    artifact_store::StoreHandle,
+    scheduler_task::{SchedulerMessage, SchedulerSender},
+};
+use anyhow::{anyhow, Result};
+use maelstrom_base::{
+    proto::{
+        ArtifactCompression, ArtifactPusherToBroker, BrokerToArtifactPusher,
+        BrokerToArtifactPusherHello,
+    },
+    Sha256Digest,
+};
 use maelstrom_util::{
+    fs::Fs,
     io::{FixedSizeReader, Sha256Stream},
     net,
 };
 use slog::{debug, Logger};
 use std::{
-    io,
+    io::{self, BufRead, BufReader, Read as _, Write},
     net::TcpStream,
     path::{Path, PathBuf},
 };
+use tempfile::NamedTempFile;
 
-fn handle_one_message(
-    msg: ArtifactPusherToBroker,
-    socket: &mut TcpStream,
-    scheduler_sender: &SchedulerSender,
-    cache_tmp_path: &Path,
+/// How we compress the artifacts we receive and send.
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionConfig {
+    /// The compression, besides [`ArtifactCompression::None`], that we tell pushers we accept, and
+    /// that we use when sending artifacts to fetchers that accept it. [`receive_body`] can decode
+    /// every kind of compression, so this just determines what pushers use.
+    pub accept: ArtifactCompression,
+
+    /// Whether to write a compressed copy of each artifact as soon as it's pushed. Otherwise, the
+    /// copy is written the first time the artifact is sent compressed.
+    pub cache_compressed_copies: bool,
+}
+
+/// Where received artifacts go: to the scheduler, so they can be put in the cache, and, if there
+/// is one, to the artifact store.
+#[derive(Clone)]
+pub struct ArtifactSink {
+    pub scheduler_sender: SchedulerSender,
+    pub cache_tmp_path: PathBuf,
+    pub cache_compressed_copies: bool,
+    pub artifact_store: Option<StoreHandle>,
+}
+
+/// Tell a newly-connected artifact pusher or chunked artifact pusher which compression we accept.
+/// This must be sent before reading anything from the pusher.
+pub fn send_hello(socket: &TcpStream, compression: CompressionConfig, log: &Logger) -> Result<()> {
+    let msg = BrokerToArtifactPusherHello {
+        accept_compression: compression.accept,
+    };
+    debug!(log, "sending artifact pusher hello"; "msg" => ?msg);
+    net::write_message_to_socket(&mut &*socket, msg)
+}
+
+/// Create a temporary file in the cache's temporary directory for a compressed copy of an
+/// artifact.
+pub fn compressed_copy_tempfile(cache_tmp_path: &Path) -> Result<NamedTempFile> {
+    Ok(tempfile::Builder::new()
+        .suffix(".zst")
+        .tempfile_in(cache_tmp_path)?)
+}
+
+/// Write a zstd-compressed copy of the artifact at `path` to a new temporary file, returning the
+/// new file's path.
+fn write_compressed_copy(path: &Path, cache_tmp_path: &Path) -> Result<PathBuf> {
+    let mut tmp = compressed_copy_tempfile(cache_tmp_path)?;
+    zstd::stream::copy_encode(Fs::new().open_file(path)?, &mut tmp, 0)?;
+    let (_, path) = tmp.keep()?;
+    Ok(path)
+}
+
+/// Read a body with the given size, digest, and encoding from `socket`, and write it to `out`. An
+/// error is returned if the body doesn't have the expected size and digest.
+pub fn receive_body(
+    socket: &mut impl BufRead,
+    digest: &Sha256Digest,
+    size: u64,
+    compression: ArtifactCompression,
+    out: &mut impl Write,
 ) -> Result<()> {
-    let ArtifactPusherToBroker(digest, size) = msg;
-    let mut tmp = tempfile::Builder::new()
-        .prefix(&digest.to_string())
-        .suffix(".tar")
-        .tempfile_in(cache_tmp_path)?;
-    let fixed_size_reader = FixedSizeReader::new(socket, size);
-    let mut sha_reader = Sha256Stream::new(fixed_size_reader);
-    let copied = io::copy(&mut sha_reader, &mut tmp)?;
-    assert_eq!(copied, size);
-    let (_, actual_digest) = sha_reader.finalize();
-    actual_digest.verify(&digest)?;
+    match compression {
+        ArtifactCompression::None => {
+            let fixed_size_reader = FixedSizeReader::new(socket, size);
+            let mut sha_reader = Sha256Stream::new(fixed_size_reader);
+            let copied = io::copy(&mut sha_reader, out)?;
+            assert_eq!(copied, size);
+            let (_, actual_digest) = sha_reader.finalize();
+            actual_digest.verify(digest)?;
+        }
+        ArtifactCompression::Zstd => {
+            // The frame is self-delimiting, and the decoder only consumes what it needs from the
+            // socket's buffer, so whatever the pusher sends next is left for us to read.
+            let decoder = zstd::Decoder::with_buffer(socket)?.single_frame();
+            let mut sha_reader = Sha256Stream::new(decoder.take(size));
+            let copied = io::copy(&mut sha_reader, out)?;
+            if copied != size {
+                return Err(anyhow!("body decompressed to the wrong size"));
+            }
+            let (decoder, actual_digest) = sha_reader.finalize();
+            if decoder.into_inner().read(&mut [0])? != 0 {
+                return Err(anyhow!("body decompressed to the wrong size"));
+            }
+            actual_digest.verify(digest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hand a complete and verified artifact, which has been written to `tmp`, to `sink`.
+pub fn got_artifact(
+    tmp: NamedTempFile,
+    digest: Sha256Digest,
+    size: u64,
+    sink: &ArtifactSink,
+    log: &Logger,
+) -> Result<()> {
+    let compressed_path = sink
+        .cache_compressed_copies
+        .then(|| write_compressed_copy(tmp.path(), &sink.cache_tmp_path))
+        .transpose()?;
+    // The cache may move or remove the file as soon as the scheduler has it, so we open it for
+    // the store first.
+    let store_file = sink
+        .artifact_store
+        .as_ref()
+        .map(|_| tmp.reopen())
+        .transpose()?;
     let (_, path) = tmp.keep()?;
-    scheduler_sender.send(SchedulerMessage::GotArtifact(digest, size, path))?;
+    sink.scheduler_sender.send(SchedulerMessage::GotArtifact(
+        digest.clone(),
+        size,
+        path,
+        compressed_path,
+    ))?;
+    if let (Some(store), Some(file)) = (&sink.artifact_store, store_file) {
+        store.put_in_background(digest, size, file, log.clone());
+    }
     Ok(())
 }
 
+/// Create a temporary file in the cache's temporary directory for the artifact with the given
+/// digest.
+pub fn artifact_tempfile(digest: &Sha256Digest, cache_tmp_path: &Path) -> Result<NamedTempFile> {
+    Ok(tempfile::Builder::new()
+        .prefix(&digest.to_string())
+        .suffix(".tar")
+        .tempfile_in(cache_tmp_path)?)
+}
+
+fn handle_one_message(
+    msg: ArtifactPusherToBroker,
+    socket: &mut impl BufRead,
+    sink: &ArtifactSink,
+    log: &Logger,
+) -> Result<()> {
+    let ArtifactPusherToBroker(digest, size, body_compression) = msg;
+    let mut tmp = artifact_tempfile(&digest, &sink.cache_tmp_path)?;
+    receive_body(socket, &digest, size, body_compression, &mut tmp)?;
+    got_artifact(tmp, digest, size, sink, log)
+}
+
 fn connection_loop(
-    mut socket: TcpStream,
-    scheduler_sender: &SchedulerSender,
-    cache_tmp_path: &Path,
+    socket: TcpStream,
+    sink: &ArtifactSink,
+    compression: CompressionConfig,
     log: &mut Logger,
 ) -> Result<()> {
+    send_hello(&socket, compression, log)?;
+    let mut reader = BufReader::new(&socket);
     loop {
-        let msg = net::read_message_from_socket(&mut socket)?;
+        let msg = net::read_message_from_socket(&mut reader)?;
         debug!(log, "received artifact pusher message"; "msg" => ?msg);
-        let result = handle_one_message(msg, &mut socket, scheduler_sender, cache_tmp_path);
+        let result = handle_one_message(msg, &mut reader, sink, log);
         let msg = BrokerToArtifactPusher(result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
         debug!(log, "sending artifact pusher message"; "msg" => ?msg);
-        net::write_message_to_socket(&mut socket, msg)?;
+        net::write_message_to_socket(&mut &socket, msg)?;
         result?;
     }
 }
 
 pub fn connection_main(
     socket: TcpStream,
-    scheduler_sender: SchedulerSender,
-    cache_tmp_path: PathBuf,
+    sink: ArtifactSink,
+    compression: CompressionConfig,
     mut log: Logger,
 ) -> Result<()> {
     debug!(log, "artifact pusher connected");
-    let err = connection_loop(socket, &scheduler_sender, &cache_tmp_path, &mut log).unwrap_err();
+    let err = connection_loop(socket, &sink, compression, &mut log).unwrap_err();
     debug!(log, "artifact pusher disconnected"; "err" => %err);
     Err(err)
 }
@@ -0,0 +1,108 @@
+//! Stores that keep artifacts beyond the broker's local cache.
+//!
+//! The local cache always holds the artifacts that are in use, and acts as the hot tier. If an
+//! artifact store is configured, every artifact that is pushed to the broker is also put in the
+//! store, and when a job needs an artifact that isn't in the cache, the broker looks for it in the
+//! store before asking the client for it. Since the store outlives the broker, and may be shared
+//! by more than one broker, this lets brokers on ephemeral hosts start with a warm cache.
+//!
+//! There are two stores: [`FsStore`], which keeps artifacts in a directory, and [`S3Store`], which
+//! keeps them in an S3 bucket. Having no store is also fine. The local cache is then the only place
+//! the broker keeps artifacts, and it asks clients for every artifact that isn't there.
+
+mod fs;
+mod s3;
+
+pub use fs::FsStore;
+pub use s3::{Credentials, S3Store};
+
+use crate::artifact_pusher::{self, ArtifactSink};
+use anyhow::Result;
+use maelstrom_base::Sha256Digest;
+use maelstrom_util::io::Sha256Stream;
+use slog::{debug, warn, Logger};
+use std::{fs::File, io::Write, sync::Arc};
+use tokio::{runtime, sync::Semaphore, task};
+
+/// How many operations on the artifact store can be running at once. The rest wait their turn.
+const MAX_CONCURRENT_OPERATIONS: usize = 16;
+
+/// A place to keep artifacts, addressed by their digests. Implementations are used from many
+/// threads at once, and may block.
+pub trait ArtifactStore: Send + Sync {
+    /// Write the contents of the artifact to `out`. Return `false` if the store doesn't have the
+    /// artifact. The contents aren't verified.
+    fn get(&self, digest: &Sha256Digest, out: &mut dyn Write) -> Result<bool>;
+
+    /// Put the artifact, whose `size` bytes are read from `file`, in the store. Doing this for an
+    /// artifact that is already in the store is allowed.
+    fn put(&self, digest: &Sha256Digest, size: u64, file: File) -> Result<()>;
+}
+
+/// A handle to the configured [`ArtifactStore`] that runs operations on it in the background.
+/// The operations run on tokio's blocking thread pool, at most [`MAX_CONCURRENT_OPERATIONS`] at a
+/// time.
+#[derive(Clone)]
+pub struct StoreHandle {
+    store: Arc<dyn ArtifactStore>,
+    permits: Arc<Semaphore>,
+    runtime: runtime::Handle,
+}
+
+impl StoreHandle {
+    /// Create a handle for `store`. This must be called from within a tokio runtime, which is
+    /// where operations will be run. The handle itself can be used from any thread.
+    pub fn new(store: Arc<dyn ArtifactStore>) -> Self {
+        Self {
+            store,
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_OPERATIONS)),
+            runtime: runtime::Handle::current(),
+        }
+    }
+
+    /// Run `f` on the store once there is room for another operation.
+    pub fn spawn(&self, f: impl FnOnce(&dyn ArtifactStore) + Send + 'static) {
+        let store = self.store.clone();
+        let permits = self.permits.clone();
+        self.runtime.spawn(async move {
+            let permit = permits.acquire_owned().await.unwrap();
+            task::spawn_blocking(move || {
+                f(&*store);
+                drop(permit);
+            })
+            .await
+            .unwrap();
+        });
+    }
+
+    /// Put the artifact, whose contents are read from `file`, in the store in the background.
+    /// Failures are logged, since the artifact is still in the local cache.
+    pub fn put_in_background(&self, digest: Sha256Digest, size: u64, file: File, log: Logger) {
+        self.spawn(move |store| match store.put(&digest, size, file) {
+            Ok(()) => debug!(log, "put artifact in store"; "digest" => %digest),
+            Err(err) => {
+                warn!(log, "error putting artifact in store"; "digest" => %digest, "err" => %err)
+            }
+        });
+    }
+}
+
+/// Get the artifact from the store, verify it, and hand it to `sink`, which shouldn't put it back
+/// in the store. Return `false` if the store doesn't have the artifact.
+pub fn fetch(
+    store: &dyn ArtifactStore,
+    digest: Sha256Digest,
+    sink: &ArtifactSink,
+    log: &Logger,
+) -> Result<bool> {
+    let mut tmp = artifact_pusher::artifact_tempfile(&digest, &sink.cache_tmp_path)?;
+    let mut writer = Sha256Stream::new(&mut tmp);
+    if !store.get(&digest, &mut writer)? {
+        return Ok(false);
+    }
+    let (_, actual_digest) = writer.finalize();
+    actual_digest.verify(&digest)?;
+    let size = tmp.as_file().metadata()?.len();
+    artifact_pusher::got_artifact(tmp, digest, size, sink, log)?;
+    Ok(true)
+}
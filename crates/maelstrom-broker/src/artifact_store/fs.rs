@@ -0,0 +1,115 @@
+//! An [`ArtifactStore`] backed by a directory, which may be on a network file system shared by
+//! more than one broker.
+//!
+//! Artifacts are kept under `sha256/<digest>` in the directory. They are first written to `tmp`,
+//! and then renamed into place, so that readers never see a partially written artifact.
+
+use super::ArtifactStore;
+use anyhow::{Context as _, Result};
+use maelstrom_base::Sha256Digest;
+use maelstrom_util::fs::Fs;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+pub struct FsStore {
+    artifact_dir: PathBuf,
+    tmp_dir: PathBuf,
+}
+
+impl FsStore {
+    /// Create a store in the directory `root`, creating the directory if it doesn't exist.
+    pub fn new(root: PathBuf) -> Result<Self> {
+        let fs = Fs::new();
+        let artifact_dir = root.join("sha256");
+        let tmp_dir = root.join("tmp");
+        fs.create_dir_all(&artifact_dir)?;
+        fs.create_dir_all(&tmp_dir)?;
+        Ok(Self {
+            artifact_dir,
+            tmp_dir,
+        })
+    }
+
+    fn artifact_path(&self, digest: &Sha256Digest) -> PathBuf {
+        self.artifact_dir.join(digest.to_string())
+    }
+}
+
+impl ArtifactStore for FsStore {
+    fn get(&self, digest: &Sha256Digest, out: &mut dyn Write) -> Result<bool> {
+        let path = self.artifact_path(digest);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err).with_context(|| format!("opening {}", path.display())),
+        };
+        io::copy(&mut file, out).with_context(|| format!("reading {}", path.display()))?;
+        Ok(true)
+    }
+
+    fn put(&self, digest: &Sha256Digest, _size: u64, mut file: File) -> Result<()> {
+        let mut tmp = tempfile::Builder::new()
+            .prefix(&digest.to_string())
+            .tempfile_in(&self.tmp_dir)?;
+        io::copy(&mut file, &mut tmp)?;
+        tmp.as_file().sync_all()?;
+        tmp.persist(self.artifact_path(digest))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maelstrom_test::*;
+    use std::io::{Seek as _, SeekFrom};
+    use tempfile::TempDir;
+
+    fn file_with_contents(contents: &[u8]) -> File {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(contents).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file
+    }
+
+    #[test]
+    fn get_missing_artifact() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FsStore::new(temp_dir.path().join("store")).unwrap();
+        let mut out = vec![];
+        assert!(!store.get(&digest!(1), &mut out).unwrap());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn put_then_get() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FsStore::new(temp_dir.path().to_owned()).unwrap();
+        store
+            .put(&digest!(1), 5, file_with_contents(b"hello"))
+            .unwrap();
+        store
+            .put(&digest!(1), 5, file_with_contents(b"hello"))
+            .unwrap();
+
+        let mut out = vec![];
+        assert!(store.get(&digest!(1), &mut out).unwrap());
+        assert_eq!(out, b"hello");
+
+        // A second store over the same directory sees the artifact.
+        let store = FsStore::new(temp_dir.path().to_owned()).unwrap();
+        let mut out = vec![];
+        assert!(store.get(&digest!(1), &mut out).unwrap());
+        assert_eq!(out, b"hello");
+        assert_eq!(
+            Fs::new()
+                .read_dir(temp_dir.path().join("tmp"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! The store of artifact chunks that backs chunked artifact pushes.
+//!
+//! Chunks are kept in the `chunks/sha256` subdirectory of the cache root, named by their digests.
+//! Incoming chunks are written to `chunks/tmp` until they have been verified. The chunks are shared
+//! by all of the chunked artifact pusher threads. Like the cache, the store has a target size, and
+//! removes the least recently used chunks when it grows past it.
+//!
+//! The chunks of an artifact being pushed are pinned until the artifact is assembled or the push is
+//! abandoned, and pinned chunks are never removed. This way, an artifact can always be assembled,
+//! even if it's bigger than the store, at the cost of the store temporarily growing past its target
+//! size.
+
+use crate::artifact_pusher;
+use anyhow::{anyhow, Result};
+use bytesize::ByteSize;
+use lru::LruCache;
+use maelstrom_base::{
+    proto::{ArtifactChunk, ArtifactCompression},
+    Sha256Digest,
+};
+use maelstrom_util::{config::common::CacheSize, fs::Fs, io::Sha256Stream};
+use slog::{debug, warn, Logger};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    io::{self, BufRead, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// A chunk that is part of at least one artifact being pushed.
+struct PinnedChunk {
+    /// The chunk's size, if it's in the store yet.
+    size: Option<u64>,
+    /// The number of pushes that have it pinned.
+    pins: NonZeroUsize,
+}
+
+struct Chunks {
+    /// The stored chunks that aren't pinned, in the order they'll be removed.
+    unpinned: LruCache<Sha256Digest, u64>,
+    pinned: HashMap<Sha256Digest, PinnedChunk>,
+    bytes_used: u64,
+}
+
+impl Chunks {
+    fn contains(&self, digest: &Sha256Digest) -> bool {
+        self.unpinned.contains(digest)
+            || self
+                .pinned
+                .get(digest)
+                .is_some_and(|chunk| chunk.size.is_some())
+    }
+
+    fn pin(&mut self, digest: &Sha256Digest) {
+        match self.pinned.entry(digest.clone()) {
+            Entry::Occupied(mut entry) => {
+                let chunk = entry.get_mut();
+                chunk.pins = chunk.pins.checked_add(1).unwrap();
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(PinnedChunk {
+                    size: self.unpinned.pop(digest),
+                    pins: NonZeroUsize::MIN,
+                });
+            }
+        }
+    }
+
+    fn unpin(&mut self, digest: &Sha256Digest) {
+        let Entry::Occupied(mut entry) = self.pinned.entry(digest.clone()) else {
+            panic!("unpinned chunk that wasn't pinned");
+        };
+        let chunk = entry.get_mut();
+        match NonZeroUsize::new(chunk.pins.get() - 1) {
+            Some(pins) => chunk.pins = pins,
+            None => {
+                if let Some(size) = entry.remove().size {
+                    self.unpinned.put(digest.clone(), size);
+                }
+            }
+        }
+    }
+
+    /// Add a chunk that isn't in the store yet.
+    fn insert(&mut self, digest: Sha256Digest, size: u64) {
+        match self.pinned.get_mut(&digest) {
+            Some(chunk) => chunk.size = Some(size),
+            None => {
+                self.unpinned.put(digest, size);
+            }
+        }
+        self.bytes_used += size;
+    }
+}
+
+/// The chunks of an artifact being pushed. They stay in the store at least until this is dropped.
+pub struct PinnedChunks<'store> {
+    store: &'store ChunkStore,
+    digests: Vec<Sha256Digest>,
+}
+
+impl Drop for PinnedChunks<'_> {
+    fn drop(&mut self) {
+        let mut stored = self.store.chunks.lock().unwrap();
+        for digest in &self.digests {
+            stored.unpin(digest);
+        }
+        self.store.possibly_remove_some(&mut stored);
+    }
+}
+
+pub struct ChunkStore {
+    dir: PathBuf,
+    tmp_dir: PathBuf,
+    bytes_used_target: u64,
+    chunks: Mutex<Chunks>,
+    log: Logger,
+}
+
+impl ChunkStore {
+    /// Open the chunk store in `cache_root`, incorporating any chunks left there by a previous
+    /// instance, and removing any incomplete ones.
+    pub fn new(cache_root: &Path, size: CacheSize, log: Logger) -> Result<Self> {
+        let fs = Fs::new();
+        let tmp_dir = cache_root.join("chunks").join("tmp");
+        fs.create_dir_all(&tmp_dir)?;
+        for entry in fs.read_dir(&tmp_dir)? {
+            fs.remove_file(entry?.path())?;
+        }
+
+        let dir = cache_root.join("chunks").join("sha256");
+        fs.create_dir_all(&dir)?;
+
+        let mut chunks = Chunks {
+            unpinned: LruCache::unbounded(),
+            pinned: HashMap::default(),
+            bytes_used: 0,
+        };
+        for entry in fs.read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let digest = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<Sha256Digest>().ok());
+            match digest {
+                Some(digest) => {
+                    chunks.insert(digest, entry.metadata()?.len());
+                }
+                None => fs.remove_file(&path)?,
+            }
+        }
+
+        let store = ChunkStore {
+            dir,
+            tmp_dir,
+            bytes_used_target: size.into(),
+            chunks: Mutex::new(chunks),
+            log,
+        };
+        {
+            let mut chunks = store.chunks.lock().unwrap();
+            store.possibly_remove_some(&mut chunks);
+            debug!(store.log, "chunk store starting";
+                "chunks" => %chunks.unpinned.len(),
+                "bytes_used" => %ByteSize::b(chunks.bytes_used),
+                "byte_used_target" => %ByteSize::b(store.bytes_used_target));
+        }
+
+        Ok(store)
+    }
+
+    fn chunk_path(&self, digest: &Sha256Digest) -> PathBuf {
+        self.dir.join(digest.to_string())
+    }
+
+    /// Start a push of an artifact made up of `chunks`. The chunks are pinned, both the ones in
+    /// the store and the ones that will be received, until the returned [`PinnedChunks`] is
+    /// dropped. The chunks that aren't in the store yet are also returned, without duplicates.
+    pub fn pin_chunks(&self, chunks: &[ArtifactChunk]) -> (PinnedChunks<'_>, Vec<ArtifactChunk>) {
+        let mut stored = self.chunks.lock().unwrap();
+        let mut seen = HashSet::new();
+        let mut pinned = PinnedChunks {
+            store: self,
+            digests: vec![],
+        };
+        let mut missing = vec![];
+        for chunk in chunks {
+            if !seen.insert(&chunk.digest) {
+                continue;
+            }
+            if !stored.contains(&chunk.digest) {
+                missing.push(chunk.clone());
+            }
+            stored.pin(&chunk.digest);
+            pinned.digests.push(chunk.digest.clone());
+        }
+        (pinned, missing)
+    }
+
+    /// Read the body of `chunk` from `socket`, verify it, and add it to the store.
+    pub fn receive_chunk(
+        &self,
+        chunk: &ArtifactChunk,
+        compression: ArtifactCompression,
+        socket: &mut impl BufRead,
+    ) -> Result<()> {
+        let mut tmp = tempfile::Builder::new()
+            .prefix(&chunk.digest.to_string())
+            .suffix(".chunk")
+            .tempfile_in(&self.tmp_dir)?;
+        artifact_pusher::receive_body(socket, &chunk.digest, chunk.size, compression, &mut tmp)?;
+
+        let mut stored = self.chunks.lock().unwrap();
+        if !stored.contains(&chunk.digest) {
+            tmp.persist(self.chunk_path(&chunk.digest))?;
+            stored.insert(chunk.digest.clone(), chunk.size);
+            self.possibly_remove_some(&mut stored);
+        }
+        Ok(())
+    }
+
+    /// Write the artifact with the given digest and size, which consists of `chunks`, to `out`.
+    /// This fails if any of the chunks aren't in the store, or if the result doesn't have the
+    /// expected size and digest. The chunks should be pinned with [`Self::pin_chunks`] first.
+    pub fn assemble(
+        &self,
+        digest: &Sha256Digest,
+        size: u64,
+        chunks: &[ArtifactChunk],
+        out: &mut impl Write,
+    ) -> Result<()> {
+        let fs = Fs::new();
+        let mut writer = Sha256Stream::new(out);
+        let mut copied = 0;
+        for chunk in chunks {
+            let mut file = fs
+                .open_file(self.chunk_path(&chunk.digest))
+                .map_err(|err| anyhow!("chunk {} is missing: {err}", chunk.digest))?;
+            copied += io::copy(&mut file, &mut writer)?;
+        }
+        if copied != size {
+            return Err(anyhow!("assembled artifact has the wrong size"));
+        }
+        let (_, actual_digest) = writer.finalize();
+        actual_digest.verify(digest)?;
+        Ok(())
+    }
+
+    /// Remove the least recently used unpinned chunks until the store is no larger than its target
+    /// size.
+    fn possibly_remove_some(&self, stored: &mut Chunks) {
+        while stored.bytes_used > self.bytes_used_target {
+            let Some((digest, size)) = stored.unpinned.pop_lru() else {
+                break;
+            };
+            stored.bytes_used -= size;
+            if let Err(err) = Fs::new().remove_file(self.chunk_path(&digest)) {
+                warn!(self.log, "error removing chunk"; "digest" => %digest, "err" => %err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn chunk_store(size: u64) -> (TempDir, ChunkStore) {
+        let temp_dir = TempDir::new().unwrap();
+        let log = Logger::root(slog::Discard, slog::o!());
+        let store = ChunkStore::new(temp_dir.path(), CacheSize::from(size), log).unwrap();
+        (temp_dir, store)
+    }
+
+    fn artifact_chunk(contents: &[u8]) -> ArtifactChunk {
+        let mut stream = Sha256Stream::new(io::sink());
+        stream.write_all(contents).unwrap();
+        ArtifactChunk {
+            digest: stream.finalize().1,
+            size: contents.len() as u64,
+        }
+    }
+
+    fn receive_chunk(store: &ChunkStore, contents: &[u8]) {
+        store
+            .receive_chunk(
+                &artifact_chunk(contents),
+                ArtifactCompression::None,
+                &mut &*contents,
+            )
+            .unwrap();
+    }
+
+    fn bytes_used(store: &ChunkStore) -> u64 {
+        store.chunks.lock().unwrap().bytes_used
+    }
+
+    #[test]
+    fn artifact_bigger_than_store_can_be_assembled() {
+        let (_temp_dir, store) = chunk_store(10);
+        let contents: [&[u8]; 3] = [b"aaaaaaaa", b"bbbbbbbb", b"cccccccc"];
+        let chunks = contents.map(artifact_chunk);
+        let artifact = artifact_chunk(&contents.concat());
+
+        let (pinned, missing) = store.pin_chunks(&chunks);
+        assert_eq!(missing, chunks);
+        for contents in contents {
+            receive_chunk(&store, contents);
+        }
+        assert_eq!(bytes_used(&store), 24);
+
+        let mut out = vec![];
+        store
+            .assemble(&artifact.digest, artifact.size, &chunks, &mut out)
+            .unwrap();
+        assert_eq!(out, contents.concat());
+
+        // Once the push is done, the store shrinks back down to its target size, removing the
+        // least recently used chunks first.
+        drop(pinned);
+        assert_eq!(bytes_used(&store), 8);
+        let (_pinned, missing) = store.pin_chunks(&chunks);
+        assert_eq!(missing, chunks[..2]);
+    }
+
+    #[test]
+    fn chunk_pinned_by_two_pushes_stays_until_both_are_done() {
+        let (_temp_dir, store) = chunk_store(0);
+        let chunk = artifact_chunk(b"aaaaaaaa");
+
+        let (pinned_1, missing) = store.pin_chunks(&[chunk.clone()]);
+        assert_eq!(missing, [chunk.clone()]);
+        let (pinned_2, missing) = store.pin_chunks(&[chunk.clone(), chunk.clone()]);
+        assert_eq!(missing, [chunk.clone()]);
+        receive_chunk(&store, b"aaaaaaaa");
+
+        drop(pinned_1);
+        let (pinned_3, missing) = store.pin_chunks(&[chunk.clone()]);
+        assert_eq!(missing, []);
+        drop(pinned_3);
+
+        drop(pinned_2);
+        assert_eq!(bytes_used(&store), 0);
+        let (_pinned, missing) = store.pin_chunks(&[chunk.clone()]);
+        assert_eq!(missing, [chunk]);
+    }
+}
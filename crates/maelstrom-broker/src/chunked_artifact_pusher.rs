@@ -0,0 +1,92 @@
+use crate::{
+    artifact_pusher::{self, ArtifactSink, CompressionConfig},
+    chunk_store::{ChunkStore, PinnedChunks},
+};
+use anyhow::{anyhow, Result};
+use maelstrom_base::{
+    proto::{ArtifactChunk, BrokerToChunkedArtifactPusher, ChunkedArtifactPusherToBroker},
+    Sha256Digest,
+};
+use maelstrom_util::net;
+use slog::{debug, Logger};
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpStream,
+    sync::Arc,
+};
+
+/// The artifact being pushed: its digest, size, and chunks, which are pinned in the store until
+/// the artifact is assembled or the connection is dropped.
+type Artifact<'store> = (Sha256Digest, u64, Vec<ArtifactChunk>, PinnedChunks<'store>);
+
+fn handle_one_message<'store>(
+    msg: ChunkedArtifactPusherToBroker,
+    socket: &mut impl BufRead,
+    artifact: &mut Option<Artifact<'store>>,
+    chunk_store: &'store ChunkStore,
+    sink: &ArtifactSink,
+    log: &Logger,
+) -> Result<Option<BrokerToChunkedArtifactPusher>> {
+    match msg {
+        ChunkedArtifactPusherToBroker::Artifact {
+            digest,
+            size,
+            chunks,
+        } => {
+            let (pinned, missing) = chunk_store.pin_chunks(&chunks);
+            *artifact = Some((digest, size, chunks, pinned));
+            Ok(Some(BrokerToChunkedArtifactPusher::MissingChunks(missing)))
+        }
+        ChunkedArtifactPusherToBroker::Chunk(chunk, chunk_compression) => {
+            chunk_store.receive_chunk(&chunk, chunk_compression, socket)?;
+            Ok(None)
+        }
+        ChunkedArtifactPusherToBroker::Assemble => {
+            let (digest, size, chunks, _pinned) = artifact
+                .take()
+                .ok_or_else(|| anyhow!("no artifact to assemble"))?;
+            let mut tmp = artifact_pusher::artifact_tempfile(&digest, &sink.cache_tmp_path)?;
+            chunk_store.assemble(&digest, size, &chunks, &mut tmp)?;
+            artifact_pusher::got_artifact(tmp, digest, size, sink, log)?;
+            Ok(Some(BrokerToChunkedArtifactPusher::ArtifactAssembled))
+        }
+    }
+}
+
+fn connection_loop(
+    socket: TcpStream,
+    chunk_store: &ChunkStore,
+    sink: &ArtifactSink,
+    compression: CompressionConfig,
+    log: &mut Logger,
+) -> Result<()> {
+    artifact_pusher::send_hello(&socket, compression, log)?;
+    let mut reader = BufReader::new(&socket);
+    let mut artifact = None;
+    loop {
+        let msg = net::read_message_from_socket(&mut reader)?;
+        debug!(log, "received chunked artifact pusher message"; "msg" => ?msg);
+        let result = handle_one_message(msg, &mut reader, &mut artifact, chunk_store, sink, log);
+        let msg = match &result {
+            Ok(None) => continue,
+            Ok(Some(msg)) => msg.clone(),
+            Err(err) => BrokerToChunkedArtifactPusher::Error(err.to_string()),
+        };
+        debug!(log, "sending chunked artifact pusher message"; "msg" => ?msg);
+        net::write_message_to_socket(&mut &socket, msg)?;
+        result?;
+    }
+}
+
+pub fn connection_main(
+    socket: TcpStream,
+    chunk_store: Arc<ChunkStore>,
+    sink: ArtifactSink,
+    compression: CompressionConfig,
+    mut log: Logger,
+) -> Result<()> {
+    debug!(log, "chunked artifact pusher connected");
+    let err = connection_loop(socket, &chunk_store, &sink, compression, &mut log).unwrap_err();
+    debug!(log, "chunked artifact pusher disconnected"; "err" => %err);
+    Err(err)
+}
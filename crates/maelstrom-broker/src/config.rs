@@ -3,15 +3,17 @@ use anyhow::Result;
 use derive_more::From;
 use maelstrom_macro::Config;
 use maelstrom_util::{
-    config::common::{CacheSize, LogLevel},
+    config::common::{CacheSize, LogLevel, StringError},
     root::RootBuf,
 };
 use serde::Deserialize;
 use std::{
     fmt::{self, Debug, Formatter},
+    path::PathBuf,
     result,
     str::FromStr,
 };
+use strum::EnumString;
 use xdg::BaseDirectories;
 
 #[derive(Deserialize, From)]
@@ -68,6 +70,61 @@ impl FromStr for HttpPort {
     }
 }
 
+/// How the broker chooses which unused artifacts to remove from its cache when it gets too big.
+#[derive(Clone, Copy, Debug, Deserialize, EnumString, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum CacheEvictionPolicy {
+    /// Remove the artifact that was used least recently.
+    Lru,
+    /// Remove the artifact that was used by the fewest jobs, breaking ties by recency.
+    Lfu,
+    /// Remove the artifact with the lowest weight per byte, using the GreedyDual-Size algorithm so
+    /// that heavily-weighted artifacts still age out if they stop being used.
+    SizeWeighted,
+}
+
+/// The percentage of the cache size that the broker shrinks the cache to once it gets too big.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "u8")]
+pub struct CacheLowWatermark(u8);
+
+impl CacheLowWatermark {
+    pub fn inner(&self) -> &u8 {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> u8 {
+        self.0
+    }
+}
+
+impl Debug for CacheLowWatermark {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl TryFrom<u8> for CacheLowWatermark {
+    type Error = StringError;
+
+    fn try_from(percent: u8) -> result::Result<Self, Self::Error> {
+        if percent > 100 {
+            Err(StringError::new("must be a percentage between 0 and 100"))
+        } else {
+            Ok(Self(percent))
+        }
+    }
+}
+
+impl FromStr for CacheLowWatermark {
+    type Err = StringError;
+
+    fn from_str(percent: &str) -> result::Result<Self, Self::Err> {
+        Self::try_from(u8::from_str(percent).map_err(StringError::new)?)
+    }
+}
+
 #[derive(Config, Debug)]
 pub struct Config {
     /// The port the broker listens on for connections from workers and clients.
@@ -95,6 +152,67 @@ pub struct Config {
     #[config(value_name = "BYTES", default = "bytesize::ByteSize::gb(1)")]
     pub cache_size: CacheSize,
 
+    /// The part of the cache size to use for the store of chunks of artifacts pushed by clients.
+    /// It must be smaller than the cache size. Like with the cache, this bound won't be followed
+    /// strictly.
+    #[config(value_name = "BYTES", default = "bytesize::ByteSize::mb(250)")]
+    pub chunk_store_size: CacheSize,
+
+    /// Don't compress artifacts sent to or from the broker. Otherwise, they are compressed with
+    /// zstd whenever the other end supports it.
+    #[config(flag)]
+    pub no_artifact_compression: bool,
+
+    /// Write a zstd-compressed copy of each artifact to the cache as soon as it's pushed, instead
+    /// of the first time it's sent to a worker. The copies count towards the cache size.
+    #[config(flag)]
+    pub cache_compressed_copies: bool,
+
+    /// How to choose which unused artifacts to remove when the cache is bigger than its target
+    /// size. One of `lru`, `lfu`, or `size-weighted`.
+    #[config(value_name = "POLICY", default = r#""lru""#)]
+    pub cache_eviction_policy: CacheEvictionPolicy,
+
+    /// Once the cache is bigger than its target size, remove artifacts until it is no bigger than
+    /// this percentage of the target size. Lower values mean fewer, larger batches of removals.
+    #[config(value_name = "PERCENT", default = "90")]
+    pub cache_low_watermark: CacheLowWatermark,
+
+    /// How much the size-weighted eviction policy values keeping tar layers.
+    #[config(value_name = "WEIGHT", default = "4.0")]
+    pub cache_tar_weight: f64,
+
+    /// How much the size-weighted eviction policy values keeping manifest layers.
+    #[config(value_name = "WEIGHT", default = "4.0")]
+    pub cache_manifest_weight: f64,
+
+    /// How much the size-weighted eviction policy values keeping other artifacts, like the files
+    /// referred to by manifests. Artifacts that no job has used since the broker started are
+    /// weighed this way too.
+    #[config(value_name = "WEIGHT", default = "1.0")]
+    pub cache_file_weight: f64,
+
+    /// The directory to use as an artifact store. Artifacts pushed to the broker are also put in
+    /// the directory, and artifacts missing from the cache are looked for there before asking the
+    /// client. This can't be used with `s3-bucket`.
+    #[config(option, value_name = "PATH", default = r#""none""#)]
+    pub artifact_store_dir: Option<PathBuf>,
+
+    /// The S3 bucket to use as an artifact store. Artifacts pushed to the broker are also put in
+    /// the bucket, and artifacts missing from the cache are looked for there before asking the
+    /// client. Credentials are taken from the usual AWS environment variables.
+    #[config(option, value_name = "BUCKET", default = r#""none""#)]
+    pub s3_bucket: Option<String>,
+
+    /// The URL of the S3-compatible service to use for the artifact store. If not given, AWS is
+    /// used.
+    #[config(option, value_name = "URL", default = r#""AWS""#)]
+    pub s3_endpoint: Option<String>,
+
+    /// The region of the S3 bucket used for the artifact store.
+    #[config(value_name = "REGION", default = r#""us-east-1""#)]
+    pub s3_region: String,
+
     /// Minimum log level to output.
     #[config(short = 'l', value_name = "LEVEL", default = r#""info""#)]
     pub log_level: LogLevel,
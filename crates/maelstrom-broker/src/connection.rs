@@ -1,5 +1,9 @@
 use crate::{
-    artifact_fetcher, artifact_pusher,
+    artifact_fetcher,
+    artifact_pusher::{self, ArtifactSink, CompressionConfig},
+    artifact_store::StoreHandle,
+    chunk_store::ChunkStore,
+    chunked_artifact_pusher,
     scheduler_task::{SchedulerMessage, SchedulerSender},
     IdVendor,
 };
@@ -11,7 +15,7 @@ use maelstrom_base::{
 use maelstrom_util::net;
 use serde::Serialize;
 use slog::{debug, error, info, o, warn, Logger};
-use std::{future::Future, path::PathBuf, sync::Arc, thread};
+use std::{future::Future, net::SocketAddr, path::PathBuf, sync::Arc, thread};
 use tokio::{
     io::BufReader,
     net::{TcpListener, TcpStream},
@@ -99,13 +103,35 @@ pub async fn connection_main<IdT, FromSchedulerMessageT, ReaderFutureT, WriterFu
     scheduler_sender.send(disconnected_msg_builder(id)).ok();
 }
 
+/// What the listener gives each new connection, whatever kind of connection it turns out to be.
+#[derive(Clone)]
+pub struct ConnectionContext {
+    pub scheduler_sender: SchedulerSender,
+    pub id_vendor: Arc<IdVendor>,
+    pub chunk_store: Arc<ChunkStore>,
+    pub cache_tmp_path: PathBuf,
+    pub compression: CompressionConfig,
+    pub artifact_store: Option<StoreHandle>,
+}
+
+impl ConnectionContext {
+    fn artifact_sink(self) -> ArtifactSink {
+        ArtifactSink {
+            scheduler_sender: self.scheduler_sender,
+            cache_tmp_path: self.cache_tmp_path,
+            cache_compressed_copies: self.compression.cache_compressed_copies,
+            artifact_store: self.artifact_store,
+        }
+    }
+}
+
 async fn unassigned_connection_main(
     mut socket: TcpStream,
-    scheduler_sender: SchedulerSender,
-    id_vendor: Arc<IdVendor>,
-    cache_tmp_path: PathBuf,
+    context: ConnectionContext,
     log: Logger,
 ) {
+    let scheduler_sender = context.scheduler_sender.clone();
+    let id_vendor = &context.id_vendor;
     match net::read_message_from_async_socket(&mut socket).await {
         Ok(Hello::Client) => {
             let (read_stream, write_stream) = socket.into_split();
@@ -144,18 +170,33 @@ async fn unassigned_connection_main(
             .await;
             debug!(log, "client disconnected");
         }
-        Ok(Hello::Worker { slots }) => {
+        Ok(Hello::Worker {
+            slots,
+            artifact_server_port,
+        }) => {
+            // Other workers reach the worker's artifact server at the address it connected to us
+            // from. We listen on an IPv6 socket, so IPv4 addresses come to us mapped.
+            let artifact_server = artifact_server_port.and_then(|port| {
+                let ip = socket.peer_addr().ok()?.ip().to_canonical();
+                Some(SocketAddr::new(ip, port))
+            });
             let (read_stream, write_stream) = socket.into_split();
             let read_stream = BufReader::new(read_stream);
             let id: WorkerId = id_vendor.vend();
-            let log = log.new(o!("wid" => id.to_string(), "slots" => slots));
+            let log = log.new(o!(
+                "wid" => id.to_string(),
+                "slots" => slots,
+                "artifact_server" => format!("{artifact_server:?}"),
+            ));
             info!(log, "worker connected");
             let log_clone = log.clone();
             let log_clone2 = log.clone();
             connection_main(
                 scheduler_sender,
                 id,
-                |id, sender| SchedulerMessage::WorkerConnected(id, slots as usize, sender),
+                |id, sender| {
+                    SchedulerMessage::WorkerConnected(id, slots as usize, artifact_server, sender)
+                },
                 SchedulerMessage::WorkerDisconnected,
                 |scheduler_sender| async move {
                     net::async_socket_reader(read_stream, scheduler_sender, |msg| {
@@ -221,7 +262,13 @@ async fn unassigned_connection_main(
             let socket = socket.into_std().unwrap();
             socket.set_nonblocking(false).unwrap();
             thread::spawn(move || -> Result<()> {
-                artifact_fetcher::connection_main(socket, scheduler_sender, log)
+                artifact_fetcher::connection_main(
+                    socket,
+                    scheduler_sender,
+                    context.cache_tmp_path,
+                    context.compression,
+                    log,
+                )
             });
         }
         Ok(Hello::ArtifactPusher) => {
@@ -229,7 +276,24 @@ async fn unassigned_connection_main(
             let socket = socket.into_std().unwrap();
             socket.set_nonblocking(false).unwrap();
             thread::spawn(move || -> Result<()> {
-                artifact_pusher::connection_main(socket, scheduler_sender, cache_tmp_path, log)
+                let compression = context.compression;
+                artifact_pusher::connection_main(socket, context.artifact_sink(), compression, log)
+            });
+        }
+        Ok(Hello::ChunkedArtifactPusher) => {
+            let log = log.clone();
+            let socket = socket.into_std().unwrap();
+            socket.set_nonblocking(false).unwrap();
+            thread::spawn(move || -> Result<()> {
+                let chunk_store = context.chunk_store.clone();
+                let compression = context.compression;
+                chunked_artifact_pusher::connection_main(
+                    socket,
+                    chunk_store,
+                    context.artifact_sink(),
+                    compression,
+                    log,
+                )
             });
         }
         Err(err) => {
@@ -241,25 +305,13 @@ async fn unassigned_connection_main(
 /// Main loop for the listener. This should be run on a task of its own. There should be at least
 /// one of these in a broker process. It will only return when it encounters an error. Until then,
 /// it listens on a socket and spawns new tasks for each client or worker that connects.
-pub async fn listener_main(
-    listener: TcpListener,
-    scheduler_sender: SchedulerSender,
-    id_vendor: Arc<IdVendor>,
-    cache_tmp_path: PathBuf,
-    log: Logger,
-) {
+pub async fn listener_main(listener: TcpListener, context: ConnectionContext, log: Logger) {
     loop {
         match listener.accept().await {
             Ok((socket, peer_addr)) => {
                 let log = log.new(o!("peer_addr" => peer_addr));
                 debug!(log, "new connection");
-                task::spawn(unassigned_connection_main(
-                    socket,
-                    scheduler_sender.clone(),
-                    id_vendor.clone(),
-                    cache_tmp_path.clone(),
-                    log,
-                ));
+                task::spawn(unassigned_connection_main(socket, context.clone(), log));
             }
             Err(err) => {
                 error!(log, "error accepting connection"; "err" => err);
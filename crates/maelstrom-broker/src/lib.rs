@@ -2,16 +2,24 @@
 
 mod artifact_fetcher;
 mod artifact_pusher;
+mod artifact_store;
+mod chunk_store;
+mod chunked_artifact_pusher;
 pub mod config;
 mod connection;
 mod http;
 mod scheduler_task;
 
-use anyhow::{Context as _, Result};
-use config::Config;
-use maelstrom_base::stats::BROKER_STATISTICS_INTERVAL;
-use maelstrom_util::{config::common::CacheSize, root::RootBuf};
-use scheduler_task::{CacheDir, SchedulerMessage, SchedulerSender, SchedulerTask};
+use anyhow::{anyhow, bail, Context as _, Result};
+use artifact_pusher::CompressionConfig;
+use artifact_store::{Credentials, FsStore, S3Store, StoreHandle};
+use chunk_store::ChunkStore;
+use config::{CacheEvictionPolicy, Config};
+use connection::ConnectionContext;
+use maelstrom_base::{proto::ArtifactCompression, stats::BROKER_STATISTICS_INTERVAL};
+use scheduler_task::{
+    EvictionConfig, EvictionPolicy, SchedulerMessage, SchedulerSender, SchedulerTask,
+};
 use slog::{error, info, Logger};
 use std::{
     net::{Ipv6Addr, SocketAddrV6},
@@ -61,11 +69,12 @@ async fn stats_heartbeat(sender: SchedulerSender) {
 async fn main_inner_inner(
     listener: TcpListener,
     http_listener: TcpListener,
-    cache_root: RootBuf<CacheDir>,
-    cache_size: CacheSize,
+    scheduler_task: SchedulerTask,
+    chunk_store: ChunkStore,
+    compression: CompressionConfig,
+    artifact_store: Option<StoreHandle>,
     log: Logger,
 ) {
-    let scheduler_task = SchedulerTask::new(cache_root, cache_size, log.clone());
     let id_vendor = Arc::new(IdVendor {
         id: AtomicU32::new(0),
     });
@@ -80,9 +89,14 @@ async fn main_inner_inner(
     ));
     join_set.spawn(connection::listener_main(
         listener,
-        scheduler_task.scheduler_sender().clone(),
-        id_vendor,
-        scheduler_task.cache_tmp_path().to_owned(),
+        ConnectionContext {
+            scheduler_sender: scheduler_task.scheduler_sender().clone(),
+            id_vendor,
+            chunk_store: Arc::new(chunk_store),
+            cache_tmp_path: scheduler_task.cache_tmp_path().to_owned(),
+            compression,
+            artifact_store,
+        },
         log.clone(),
     ));
     join_set.spawn(stats_heartbeat(scheduler_task.scheduler_sender().clone()));
@@ -129,11 +143,75 @@ async fn main_inner(config: Config, log: Logger) -> Result<()> {
         "http_addr" => http_listener_addr,
         "pid" => process::id());
 
+    // The chunk store's size comes out of the cache's, so that the two together stay within the
+    // cache size.
+    let cache_size = u64::from(config.cache_size)
+        .checked_sub(config.chunk_store_size.into())
+        .filter(|&size| size > 0)
+        .ok_or_else(|| anyhow!("chunk-store-size must be smaller than cache-size"))?;
+    let chunk_store = ChunkStore::new(&config.cache_root, config.chunk_store_size, log.clone())
+        .context("opening chunk store")?;
+
+    // Without an artifact store, the cache is the only place artifacts are kept, and clients are
+    // asked for any artifacts that aren't in it.
+    let artifact_store = match (config.artifact_store_dir.clone(), config.s3_bucket.clone()) {
+        (None, None) => None,
+        (Some(_), Some(_)) => {
+            bail!("artifact-store-dir and s3-bucket can't both be set");
+        }
+        (Some(dir), None) => {
+            let store = FsStore::new(dir).context("opening artifact store")?;
+            Some(StoreHandle::new(Arc::new(store)))
+        }
+        (None, Some(bucket)) => {
+            let region = config.s3_region.clone();
+            let endpoint = match &config.s3_endpoint {
+                Some(endpoint) => endpoint.clone(),
+                None => format!("https://s3.{region}.amazonaws.com"),
+            };
+            let store = S3Store::new(&endpoint, bucket, region, Credentials::from_env()?)
+                .context("opening artifact store")?;
+            Some(StoreHandle::new(Arc::new(store)))
+        }
+    };
+
+    let cache_eviction = EvictionConfig {
+        policy: match config.cache_eviction_policy {
+            CacheEvictionPolicy::Lru => EvictionPolicy::Lru,
+            CacheEvictionPolicy::Lfu => EvictionPolicy::Lfu,
+            CacheEvictionPolicy::SizeWeighted => EvictionPolicy::SizeWeighted,
+        },
+        low_watermark_percent: config.cache_low_watermark.into_inner(),
+        tar_weight: config.cache_tar_weight,
+        manifest_weight: config.cache_manifest_weight,
+        file_weight: config.cache_file_weight,
+    };
+
+    let compression = CompressionConfig {
+        accept: if config.no_artifact_compression {
+            ArtifactCompression::None
+        } else {
+            ArtifactCompression::Zstd
+        },
+        cache_compressed_copies: config.cache_compressed_copies,
+    };
+
+    let scheduler_task = SchedulerTask::new(
+        config.cache_root,
+        cache_size.into(),
+        cache_eviction,
+        artifact_store.clone(),
+        compression.cache_compressed_copies,
+        log.clone(),
+    );
+
     main_inner_inner(
         listener,
         http_listener,
-        config.cache_root,
-        config.cache_size,
+        scheduler_task,
+        chunk_store,
+        compression,
+        artifact_store,
         log.clone(),
     )
     .await;
@@ -1,30 +1,45 @@
 mod cache;
 mod scheduler;
 
-pub use cache::CacheDir;
+pub use cache::{CacheDir, EvictionConfig, EvictionPolicy};
+pub use scheduler::WorkerArtifactSource;
 
+use crate::{
+    artifact_pusher::ArtifactSink,
+    artifact_store::{self, StoreHandle},
+};
 use cache::{Cache, GetArtifactForWorkerError, StdCacheFs};
-use maelstrom_base::proto::{BrokerToClient, BrokerToMonitor, BrokerToWorker};
+use maelstrom_base::{
+    proto::{BrokerToClient, BrokerToMonitor, BrokerToWorker},
+    Sha256Digest,
+};
 use maelstrom_util::{config::common::CacheSize, root::RootBuf, sync};
 use scheduler::{Message, Scheduler, SchedulerDeps};
-use slog::Logger;
+use slog::{warn, Logger};
 use std::{
     path::{Path, PathBuf},
     sync::mpsc as std_mpsc,
 };
 use tokio::sync::mpsc as tokio_mpsc;
 
-#[derive(Debug)]
-pub struct PassThroughDeps;
+pub struct PassThroughDeps {
+    artifact_store: Option<StoreHandle>,
+    // This is weak so that the scheduler doesn't keep its own channel open.
+    scheduler_sender: tokio_mpsc::WeakUnboundedSender<SchedulerMessage>,
+    cache_tmp_path: PathBuf,
+    cache_compressed_copies: bool,
+    log: Logger,
+}
 
 /// The production implementation of [SchedulerDeps]. This implementation just hands the
-/// message to the provided sender.
+/// message to the provided sender. Fetches from the artifact store are done in the background by
+/// the [`StoreHandle`].
 impl SchedulerDeps for PassThroughDeps {
     type ClientSender = tokio_mpsc::UnboundedSender<BrokerToClient>;
     type WorkerSender = tokio_mpsc::UnboundedSender<BrokerToWorker>;
     type MonitorSender = tokio_mpsc::UnboundedSender<BrokerToMonitor>;
     type WorkerArtifactFetcherSender =
-        std_mpsc::Sender<Result<(PathBuf, u64), GetArtifactForWorkerError>>;
+        std_mpsc::Sender<Result<WorkerArtifactSource, GetArtifactForWorkerError>>;
 
     fn send_message_to_client(&mut self, sender: &mut Self::ClientSender, message: BrokerToClient) {
         sender.send(message).ok();
@@ -45,10 +60,41 @@ impl SchedulerDeps for PassThroughDeps {
     fn send_message_to_worker_artifact_fetcher(
         &mut self,
         sender: &mut Self::WorkerArtifactFetcherSender,
-        message: Result<(PathBuf, u64), GetArtifactForWorkerError>,
+        message: Result<WorkerArtifactSource, GetArtifactForWorkerError>,
     ) {
         sender.send(message).ok();
     }
+
+    fn fetch_artifact_from_store(&mut self, digest: Sha256Digest) {
+        let Some(scheduler_sender) = self.scheduler_sender.upgrade() else {
+            return;
+        };
+        let Some(store) = &self.artifact_store else {
+            scheduler_sender
+                .send(Message::ArtifactNotInStore(digest))
+                .ok();
+            return;
+        };
+        // The artifact came from the store, so there's no need to put it back there.
+        let sink = ArtifactSink {
+            scheduler_sender,
+            cache_tmp_path: self.cache_tmp_path.clone(),
+            cache_compressed_copies: self.cache_compressed_copies,
+            artifact_store: None,
+        };
+        let log = self.log.clone();
+        store.spawn(move |store| {
+            let found = artifact_store::fetch(store, digest.clone(), &sink, &log).unwrap_or_else(|err| {
+                warn!(log, "error fetching artifact from store"; "digest" => %digest, "err" => %err);
+                false
+            });
+            if !found {
+                sink.scheduler_sender
+                    .send(Message::ArtifactNotInStore(digest))
+                    .ok();
+            }
+        });
+    }
 }
 
 /// The production scheduler message type. Some [Message] arms contain a
@@ -59,20 +105,48 @@ pub type SchedulerMessage = Message<PassThroughDeps>;
 /// This type is used often enough to warrant an alias.
 pub type SchedulerSender = tokio_mpsc::UnboundedSender<SchedulerMessage>;
 
+/// How many queued jobs per slot each worker is asked to prefetch the layers of. Workers limit how
+/// much prefetching they actually do, so there's not much harm in hinting more jobs than they'll
+/// get to, but there's not much to gain either.
+const PREFETCH_HINTS_PER_SLOT: usize = 1;
+
 pub struct SchedulerTask {
     scheduler: Scheduler<Cache<StdCacheFs>, PassThroughDeps>,
+    deps: PassThroughDeps,
     sender: SchedulerSender,
     receiver: tokio_mpsc::UnboundedReceiver<SchedulerMessage>,
     cache_tmp_path: PathBuf,
 }
 
 impl SchedulerTask {
-    pub fn new(cache_root: RootBuf<CacheDir>, cache_size: CacheSize, log: Logger) -> Self {
+    pub fn new(
+        cache_root: RootBuf<CacheDir>,
+        cache_size: CacheSize,
+        cache_eviction: EvictionConfig,
+        artifact_store: Option<StoreHandle>,
+        cache_compressed_copies: bool,
+        log: Logger,
+    ) -> Self {
         let (sender, receiver) = tokio_mpsc::unbounded_channel();
-        let cache = Cache::new(StdCacheFs::new(), cache_root, cache_size, log);
+        let cache = Cache::new(
+            StdCacheFs::new(),
+            cache_root,
+            cache_size,
+            cache_eviction,
+            log.clone(),
+        );
         let cache_tmp_path = cache.tmp_path();
+        let use_artifact_store = artifact_store.is_some();
+        let deps = PassThroughDeps {
+            artifact_store,
+            scheduler_sender: sender.downgrade(),
+            cache_tmp_path: cache_tmp_path.clone(),
+            cache_compressed_copies,
+            log,
+        };
         SchedulerTask {
-            scheduler: Scheduler::new(cache),
+            scheduler: Scheduler::new(cache, PREFETCH_HINTS_PER_SLOT, use_artifact_store),
+            deps,
             sender,
             receiver,
             cache_tmp_path,
@@ -100,7 +174,7 @@ impl SchedulerTask {
     /// give us a way to return an error, for precisely this reason.
     pub async fn run(mut self) {
         sync::channel_reader(self.receiver, |msg| {
-            self.scheduler.receive_message(&mut PassThroughDeps, msg)
+            self.scheduler.receive_message(&mut self.deps, msg)
         })
         .await
         .unwrap();
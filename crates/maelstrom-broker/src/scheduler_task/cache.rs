@@ -6,7 +6,9 @@
 
 use anyhow::{anyhow, bail, Result};
 use bytesize::ByteSize;
-use maelstrom_base::{ClientId, JobId, Sha256Digest};
+use maelstrom_base::{
+    proto::CachedArtifact, stats::EvictionStatistics, ArtifactType, ClientId, JobId, Sha256Digest,
+};
 use maelstrom_util::{
     config::common::CacheSize,
     heap::{Heap, HeapDeps, HeapIndex},
@@ -15,15 +17,17 @@ use maelstrom_util::{
 };
 use slog::debug;
 use std::{
-    collections::{hash_map, HashMap, HashSet},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     error::Error,
     fmt::{self, Debug, Display, Formatter},
-    io,
+    io, mem,
     num::NonZeroU32,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-/// [`Cache`]'s dependencies on the host's file system.
+/// [`Cache`]'s dependencies on the host's file system and clock.
 pub trait CacheFs {
     /// Rename `source` to `destination`. Panic on file system error. Assume that all intermediate
     /// directories exist for `destination`, and that `source` and `destination` are on the same
@@ -47,11 +51,30 @@ pub trait CacheFs {
     /// or if `path` doesn't exist.
     fn file_size(&mut self, path: &Path) -> u64;
 
+    /// Return the modification time of the file at `path`. Panic on file system error or if `path`
+    /// doesn't exist.
+    fn modified(&mut self, path: &Path) -> SystemTime;
+
+    /// Set the modification time of the file at `path`. Errors are ignored, since the time is only
+    /// used to order removals after a restart.
+    fn set_modified(&mut self, path: &Path, time: SystemTime);
+
     type File: io::Read + io::Seek + 'static;
 
     /// Return an object that can be used to read a file. Panic on file system error or if `path`
     /// doesn't exist
     fn open_file(&mut self, path: &Path) -> Self::File;
+
+    /// Return the contents of the file at `path`, or `None` if it doesn't exist. Panic on any
+    /// other file system error.
+    fn read_to_string_if_exists(&mut self, path: &Path) -> Option<String>;
+
+    /// Write `contents` to the file at `path`, replacing it if it exists. Panic on file system
+    /// error.
+    fn write(&mut self, path: &Path, contents: &str);
+
+    /// Return the current time.
+    fn now(&mut self) -> SystemTime;
 }
 
 /// Implement [`CacheFs`] using `std::fs`.
@@ -84,11 +107,88 @@ impl CacheFs for StdCacheFs {
         self.0.metadata(path).unwrap().len()
     }
 
+    fn modified(&mut self, path: &Path) -> SystemTime {
+        self.0.metadata(path).unwrap().modified().unwrap()
+    }
+
+    fn set_modified(&mut self, path: &Path, time: SystemTime) {
+        if let Ok(file) = self.0.open_file(path) {
+            let _ = file.into_inner().set_modified(time);
+        }
+    }
+
     type File = std::fs::File;
 
     fn open_file(&mut self, path: &Path) -> Self::File {
         self.0.open_file(path).unwrap().into_inner()
     }
+
+    fn read_to_string_if_exists(&mut self, path: &Path) -> Option<String> {
+        self.0.read_to_string_if_exists(path).unwrap()
+    }
+
+    fn write(&mut self, path: &Path, contents: &str) {
+        self.0.write(path, contents).unwrap()
+    }
+
+    fn now(&mut self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// What a job uses an artifact for. The size-weighted eviction policy weighs artifacts by kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// A tar layer.
+    Tar,
+    /// A manifest layer.
+    Manifest,
+    /// Anything else, like a file referred to by a manifest, or a job's standard input.
+    File,
+}
+
+impl From<ArtifactType> for ArtifactKind {
+    fn from(type_: ArtifactType) -> Self {
+        match type_ {
+            ArtifactType::Tar => Self::Tar,
+            ArtifactType::Manifest => Self::Manifest,
+        }
+    }
+}
+
+/// Which unused artifact [`Cache`] removes first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// The artifact that was used least recently.
+    Lru,
+    /// The artifact used by the fewest jobs, then the one used least recently.
+    Lfu,
+    /// The artifact with the lowest weight per byte, using the GreedyDual-Size algorithm so that
+    /// heavily-weighted artifacts still age out if they stop being used. An artifact's weight is
+    /// given by its kind, so, among artifacts of the same kind, larger ones are removed first.
+    SizeWeighted,
+}
+
+/// How [`Cache`] decides which unused artifacts to remove, and how many at a time.
+#[derive(Clone, Copy, Debug)]
+pub struct EvictionConfig {
+    pub policy: EvictionPolicy,
+    /// The percentage of the target size to shrink the cache to once it's too big. Must be no
+    /// greater than 100.
+    pub low_watermark_percent: u8,
+    pub tar_weight: f64,
+    pub manifest_weight: f64,
+    pub file_weight: f64,
+}
+
+impl EvictionConfig {
+    fn weight(&self, kind: ArtifactKind) -> f64 {
+        match kind {
+            ArtifactKind::Tar => self.tar_weight,
+            ArtifactKind::Manifest => self.manifest_weight,
+            ArtifactKind::File => self.file_weight,
+        }
+    }
 }
 
 /// Return value for [`Cache::get_artifact`].
@@ -140,32 +240,73 @@ impl Error for GetArtifactForWorkerError {}
 enum CacheEntry {
     /// The artifact is being downloaded, extracted, and having its checksum validated. There is
     /// possibly a subdirectory for this [`Sha256Digest`], depending on where the extraction
-    /// process is.
+    /// process is. The clients are the ones that have been asked to push the artifact. There may
+    /// not be any jobs waiting yet if the clients were asked in response to a query.
     Waiting(Vec<JobId>, HashSet<ClientId>),
 
     /// The artifact has been successfully downloaded and extracted, and the subdirectory is
     /// currently being used by at least one job. We refcount this state since there may be
-    /// multiple jobs that use the same artifact.
+    /// multiple jobs that use the same artifact. A pinned artifact is always in this state, with
+    /// the pin holding one of the references.
     InUse {
         bytes_used: u64,
         refcount: NonZeroU32,
     },
 
     /// The artifact has been successfully downloaded and extracted, but no jobs are currently
-    /// using it. [`Cache`] hands out `priority` values in a monotonically increasing way. This are
-    /// used by the [`Heap`] to implement an LRU cache.
+    /// using it. [`Cache`] computes `priority` according to its eviction policy when the artifact
+    /// enters this state. It is used by the [`Heap`] to pick the next artifact to remove.
     InHeap {
         bytes_used: u64,
-        priority: u64,
+        priority: Priority,
         heap_index: HeapIndex,
     },
 }
 
+/// The position of an unused artifact in the eviction order. Artifacts with lower priorities are
+/// removed first. What `value` means depends on the [`EvictionPolicy`]. Ties are broken by
+/// `tick`, which [`Cache`] hands out in a monotonically increasing way, so that the artifact that
+/// became unused first is removed first. With the LRU policy, `value` is always zero.
+#[derive(Clone, Copy, Debug)]
+struct Priority {
+    value: f64,
+    tick: u64,
+}
+
+impl PartialEq for Priority {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Priority {}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value
+            .total_cmp(&other.value)
+            .then(self.tick.cmp(&other.tick))
+    }
+}
+
+/// How jobs have used an artifact. This is what the LFU and size-weighted eviction policies are
+/// based on.
+struct Usage {
+    kind: ArtifactKind,
+    jobs: u64,
+}
+
 /// An implementation of the "newtype" pattern used to implement [`HeapDeps`] on
 /// [`HashMap<Sha256Digest, CacheEntry>`].
 ///
 /// This implementation compares two [`Sha256Digest`]s based on their priority, which is a measure
-/// of how recently an entry has been used.
+/// of how much an entry is worth keeping.
 #[derive(Default)]
 struct CacheMap(HashMap<Sha256Digest, CacheEntry>);
 
@@ -195,7 +336,7 @@ impl HeapDeps for CacheMap {
             Some(CacheEntry::InHeap { priority, .. }) => *priority,
             _ => panic!("Element should be in heap"),
         };
-        lhs_priority.cmp(&rhs_priority) == std::cmp::Ordering::Less
+        lhs_priority.cmp(&rhs_priority) == Ordering::Less
     }
 
     fn update_index(&mut self, elem: &Self::Element, idx: HeapIndex) {
@@ -206,17 +347,21 @@ impl HeapDeps for CacheMap {
     }
 }
 
-fn try_read_cache_file(fs: &mut impl CacheFs, path: &Path) -> Result<(Sha256Digest, u64)> {
+/// Parse the name of a file in the `sha256` directory, returning its digest, its size, and whether
+/// it is a compressed copy.
+fn try_read_cache_file(fs: &mut impl CacheFs, path: &Path) -> Result<(Sha256Digest, u64, bool)> {
     let path_str = path.file_name().unwrap().to_string_lossy();
     let (left, right) = path_str
         .split_once('.')
         .ok_or_else(|| anyhow!("bad filename"))?;
-    if right != "bin" {
-        bail!("bad extension")
-    }
+    let compressed = match right {
+        "bin" => false,
+        "zst" => true,
+        _ => bail!("bad extension"),
+    };
     let digest = left.parse::<Sha256Digest>()?;
     let size = fs.file_size(path);
-    Ok((digest, size))
+    Ok((digest, size, compressed))
 }
 
 pub struct CacheDir;
@@ -230,20 +375,40 @@ pub struct Cache<FsT> {
     root: PathBuf,
     entries: CacheMap,
     heap: Heap<CacheMap>,
-    next_priority: u64,
+    compressed_copies: HashMap<Sha256Digest, u64>,
+    pinned: HashSet<Sha256Digest>,
+    held_for_clients: HashMap<ClientId, HashSet<Sha256Digest>>,
+    last_used: HashMap<Sha256Digest, SystemTime>,
+    usage: HashMap<Sha256Digest, Usage>,
+    eviction: EvictionConfig,
+    eviction_statistics: EvictionStatistics,
+    next_tick: u64,
+    inflation: f64,
     bytes_used: u64,
     bytes_used_target: u64,
+    bytes_used_low_watermark: u64,
     log: slog::Logger,
 }
 
 impl<FsT: CacheFs> Cache<FsT> {
-    /// Make a new cache rooted at `root`, with a target size of `bytes_used_target`.
+    /// Make a new cache rooted at `root`, with a target size of `size`. Once the cache is bigger
+    /// than `size`, unused artifacts are removed, in the order given by `eviction`, until the
+    /// cache is no bigger than the low watermark.
     ///
     /// This function will attempt to create all of the directories it needs, panicking if it
     /// cannot. If there are existing entries in the cache, this function will scan them and
     /// incorporate them into the new cache. If there are garbage files in the directories, likely
-    /// from incomplete downloads in the previous instance, this function will remove them.
-    pub fn new(mut fs: FsT, root: RootBuf<CacheDir>, size: CacheSize, log: slog::Logger) -> Self {
+    /// from incomplete downloads in the previous instance, this function will remove them. This
+    /// includes compressed copies of artifacts that aren't themselves in the cache. Artifacts that
+    /// were pinned by the previous cache are still pinned, and the previous cache's record of when
+    /// each artifact was last used is kept.
+    pub fn new(
+        mut fs: FsT,
+        root: RootBuf<CacheDir>,
+        size: CacheSize,
+        eviction: EvictionConfig,
+        log: slog::Logger,
+    ) -> Self {
         let root = root.into_path_buf();
         let mut path = root.clone();
 
@@ -254,50 +419,100 @@ impl<FsT: CacheFs> Cache<FsT> {
         }
         path.pop();
 
+        let bytes_used_target = u64::from(size);
+        let bytes_used_low_watermark = u64::try_from(
+            u128::from(bytes_used_target) * u128::from(eviction.low_watermark_percent) / 100,
+        )
+        .unwrap();
         let mut result = Cache {
             fs,
             root,
             entries: CacheMap::default(),
             heap: Heap::default(),
-            next_priority: 0,
+            compressed_copies: HashMap::default(),
+            pinned: HashSet::default(),
+            held_for_clients: HashMap::default(),
+            last_used: HashMap::default(),
+            usage: HashMap::default(),
+            eviction,
+            eviction_statistics: EvictionStatistics::default(),
+            next_tick: 0,
+            inflation: 0.0,
             bytes_used: 0,
-            bytes_used_target: size.into(),
+            bytes_used_target,
+            bytes_used_low_watermark,
             log,
         };
 
+        if let Some(pinned) = result.fs.read_to_string_if_exists(&result.pinned_path()) {
+            result.pinned = pinned
+                .lines()
+                .filter_map(|line| line.parse().ok())
+                .collect();
+        }
+
         path.push("sha256");
         result.fs.mkdir_recursively(&path);
+        let mut artifacts = vec![];
+        let mut compressed_copies = vec![];
         for child in result.fs.read_dir(&path) {
-            if let Ok((digest, size)) = try_read_cache_file(&mut result.fs, &child) {
-                result.entries.insert(
-                    digest.clone(),
-                    CacheEntry::InHeap {
-                        bytes_used: size,
-                        priority: result.next_priority,
-                        heap_index: HeapIndex::default(),
-                    },
-                );
-                result.heap.push(&mut result.entries, digest);
-                result.next_priority = result.next_priority.checked_add(1).unwrap();
+            match try_read_cache_file(&mut result.fs, &child) {
+                Ok((digest, size, false)) => {
+                    let last_used = result.fs.modified(&child);
+                    artifacts.push((digest, size, last_used));
+                }
+                Ok((digest, size, true)) => {
+                    compressed_copies.push((digest, size, child));
+                }
+                Err(_) => {
+                    result.fs.remove_file(&child);
+                }
+            }
+        }
+        // Artifacts' last uses are kept as their files' modification times. Adding them in that
+        // order means the least recently used ones are removed first.
+        artifacts.sort_by_key(|(_, _, last_used)| *last_used);
+        for (digest, size, last_used) in artifacts {
+            result.insert_unused(digest.clone(), size);
+            result.last_used.insert(digest, last_used);
+            result.bytes_used = result.bytes_used.checked_add(size).unwrap();
+        }
+        for (digest, size, child) in compressed_copies {
+            if result.entries.contains_key(&digest) {
+                result.compressed_copies.insert(digest, size);
                 result.bytes_used = result.bytes_used.checked_add(size).unwrap();
-                continue;
+            } else {
+                result.fs.remove_file(&child);
             }
-            result.fs.remove_file(&child);
         }
         result.possibly_remove_some();
 
         debug!(result.log, "cache starting";
             "entries" => %result.entries.len(),
             "bytes_used" => %ByteSize::b(result.bytes_used),
-            "byte_used_target" => %ByteSize::b(result.bytes_used_target));
+            "byte_used_target" => %ByteSize::b(result.bytes_used_target),
+            "byte_used_low_watermark" => %ByteSize::b(result.bytes_used_low_watermark),
+            "eviction_policy" => ?result.eviction.policy);
 
         result
     }
 
-    /// Attempt to get an artifact from the cache.
+    /// Attempt to get an artifact from the cache. `kind` is what the job is going to use the
+    /// artifact for.
     ///
     /// See [`GetArtifact`] for details on what the return values mean.
-    pub fn get_artifact(&mut self, jid: JobId, digest: Sha256Digest) -> GetArtifact {
+    pub fn get_artifact(
+        &mut self,
+        jid: JobId,
+        digest: Sha256Digest,
+        kind: ArtifactKind,
+    ) -> GetArtifact {
+        let usage = self
+            .usage
+            .entry(digest.clone())
+            .or_insert(Usage { kind, jobs: 0 });
+        usage.kind = kind;
+        usage.jobs = usage.jobs.checked_add(1).unwrap();
         let entry = self
             .entries
             .0
@@ -313,7 +528,13 @@ impl<FsT: CacheFs> Cache<FsT> {
                 }
             }
             CacheEntry::InUse { refcount, .. } => {
-                *refcount = refcount.checked_add(1).unwrap();
+                let held = self
+                    .held_for_clients
+                    .get_mut(&jid.cid)
+                    .is_some_and(|held| held.remove(&digest));
+                if !held {
+                    *refcount = refcount.checked_add(1).unwrap();
+                }
                 GetArtifact::Success
             }
             CacheEntry::InHeap {
@@ -332,6 +553,28 @@ impl<FsT: CacheFs> Cache<FsT> {
         }
     }
 
+    /// Tell the cache that a client asked whether we have an artifact, in anticipation of
+    /// submitting jobs that use it.
+    ///
+    /// Return `true` if the artifact isn't in the cache and the client should push it. In that
+    /// case, the client is recorded as pushing the artifact, and subsequent calls to
+    /// [`Self::get_artifact`] for the client's jobs will return [`GetArtifact::Wait`] instead of
+    /// [`GetArtifact::Get`]. If the artifact arrives before any of the client's jobs ask for it,
+    /// the cache holds a reference to it on the client's behalf, which the client's first job to
+    /// call [`Self::get_artifact`] takes over. The reference is dropped if the client disconnects
+    /// first.
+    pub fn expect_artifact_from_client(&mut self, cid: ClientId, digest: Sha256Digest) -> bool {
+        match self
+            .entries
+            .0
+            .entry(digest)
+            .or_insert(CacheEntry::Waiting(Vec::default(), HashSet::default()))
+        {
+            CacheEntry::Waiting(_, clients) => clients.insert(cid),
+            CacheEntry::InUse { .. } | CacheEntry::InHeap { .. } => false,
+        }
+    }
+
     /// Tell the cache that a artifact has been successfully retrieved and should be incorporated
     /// into the cache.
     ///
@@ -340,45 +583,71 @@ impl<FsT: CacheFs> Cache<FsT> {
     /// `path` must be a file in the temporary directory provided by [`Self::tmp_path`]. This
     /// function will move the file from the temporary directory into its correct place in the
     /// cache. For this reason, the file must be on the same file system as the cache directory.
-    /// [`Self::tmp_path`] ensures this.
+    /// [`Self::tmp_path`] ensures this. The same goes for `compressed_path`, which, if provided, is
+    /// a zstd-compressed copy of the artifact that will be kept alongside it. Its size counts
+    /// towards the size of the cache.
     ///
     /// The return value is a vector of `JobId` that are now no longer blocked by this artifact.
     /// These were `JobId`s provided by previous calls to [`Self::get_artifact`]. Each entry in
     /// this vec has its own refcount, and thus, [`Self::decrement_refcount`] must be called
     /// appropriately.
-    pub fn got_artifact(&mut self, digest: Sha256Digest, size: u64, path: &Path) -> Vec<JobId> {
-        let mut result = vec![];
+    pub fn got_artifact(
+        &mut self,
+        digest: Sha256Digest,
+        size: u64,
+        path: &Path,
+        compressed_path: Option<&Path>,
+    ) -> Vec<JobId> {
         let new_path = self.cache_path(&digest);
-        match self.entries.entry(digest.clone()) {
-            hash_map::Entry::Vacant(entry) => {
-                entry.insert(CacheEntry::InHeap {
-                    bytes_used: size,
-                    priority: self.next_priority,
-                    heap_index: HeapIndex::default(),
-                });
-                self.heap.push(&mut self.entries, digest.clone());
-                self.next_priority = self.next_priority.checked_add(1).unwrap();
+        let (result, held_for) = match self.entries.get_mut(&digest) {
+            None => (vec![], vec![]),
+            Some(CacheEntry::Waiting(jids, clients)) => {
+                let held_for = clients
+                    .iter()
+                    .filter(|cid| !jids.iter().any(|jid| jid.cid == **cid))
+                    .copied()
+                    .collect::<Vec<_>>();
+                (mem::take(jids), held_for)
             }
-            hash_map::Entry::Occupied(entry) => {
-                let entry = entry.into_mut();
-                match entry {
-                    CacheEntry::Waiting(jids, _) => {
-                        let refcount = NonZeroU32::new(u32::try_from(jids.len()).unwrap()).unwrap();
-                        std::mem::swap(jids, &mut result);
-                        *entry = CacheEntry::InUse {
-                            bytes_used: size,
-                            refcount,
-                        };
-                    }
-                    CacheEntry::InUse { .. } | CacheEntry::InHeap { .. } => {
-                        self.fs.remove_file(path);
-                        return vec![];
-                    }
+            Some(CacheEntry::InUse { .. } | CacheEntry::InHeap { .. }) => {
+                self.fs.remove_file(path);
+                if let Some(compressed_path) = compressed_path {
+                    self.fs.remove_file(compressed_path);
                 }
+                return vec![];
             }
+        };
+        for cid in &held_for {
+            self.held_for_clients
+                .entry(*cid)
+                .or_default()
+                .insert(digest.clone());
+        }
+        if result.is_empty() && held_for.is_empty() {
+            self.insert_unused(digest.clone(), size);
+        } else {
+            let refcount = u32::try_from(result.len() + held_for.len()).unwrap()
+                + u32::from(self.pinned.contains(&digest));
+            self.entries.insert(
+                digest.clone(),
+                CacheEntry::InUse {
+                    bytes_used: size,
+                    refcount: NonZeroU32::new(refcount).unwrap(),
+                },
+            );
         }
         self.fs.rename(path, &new_path);
+        let now = self.fs.now();
+        self.last_used.insert(digest.clone(), now);
         self.bytes_used = self.bytes_used.checked_add(size).unwrap();
+        if let Some(compressed_path) = compressed_path {
+            let compressed_size = self.fs.file_size(compressed_path);
+            self.fs
+                .rename(compressed_path, &self.compressed_cache_path(&digest));
+            self.compressed_copies
+                .insert(digest.clone(), compressed_size);
+            self.bytes_used = self.bytes_used.checked_add(compressed_size).unwrap();
+        }
         debug!(self.log, "cache added artifact";
             "digest" => %digest,
             "artifact_bytes_used" => %ByteSize::b(size),
@@ -390,6 +659,31 @@ impl<FsT: CacheFs> Cache<FsT> {
         result
     }
 
+    /// Tell the cache that a zstd-compressed copy of an artifact has been made. This happens when
+    /// an artifact without a compressed copy is sent compressed to a worker. Like with
+    /// [`Self::got_artifact`], `path` must be a file in the temporary directory provided by
+    /// [`Self::tmp_path`].
+    ///
+    /// The copy is kept alongside the artifact, and its size counts towards the size of the cache.
+    /// If the artifact is no longer in the cache, or already has a compressed copy, the file is
+    /// removed instead.
+    pub fn got_compressed_copy(&mut self, digest: &Sha256Digest, path: &Path) {
+        let in_cache = matches!(
+            self.entries.get(digest),
+            Some(CacheEntry::InUse { .. } | CacheEntry::InHeap { .. })
+        );
+        if !in_cache || self.compressed_copies.contains_key(digest) {
+            self.fs.remove_file(path);
+            return;
+        }
+        let compressed_size = self.fs.file_size(path);
+        self.fs.rename(path, &self.compressed_cache_path(digest));
+        self.compressed_copies
+            .insert(digest.clone(), compressed_size);
+        self.bytes_used = self.bytes_used.checked_add(compressed_size).unwrap();
+        self.possibly_remove_some();
+    }
+
     pub fn read_manifest(
         &mut self,
         digest: Sha256Digest,
@@ -403,69 +697,247 @@ impl<FsT: CacheFs> Cache<FsT> {
     /// size. On the other hand, as long as the refcount is non-zero, the holder of a refcount can
     /// be assured that the artifact won't go away, not matter how large the cache is, and how much
     /// larger it is than the target size.
+    ///
+    /// The time is recorded in memory as the artifact's last use. It is written to the file once the
+    /// artifact becomes unused: see [`Self::release`].
     pub fn decrement_refcount(&mut self, digest: Sha256Digest) {
-        let entry = self.entries.get_mut(&digest).unwrap();
-        let CacheEntry::InUse {
+        let now = self.fs.now();
+        self.last_used.insert(digest.clone(), now);
+        self.release(digest);
+    }
+
+    /// Pin an artifact, so that it is never removed. If the artifact isn't in the cache yet, it
+    /// will be pinned once it gets there. Pins are recorded in the cache directory, so they
+    /// outlive the cache.
+    pub fn pin_artifact(&mut self, digest: Sha256Digest) {
+        if !self.pinned.insert(digest.clone()) {
+            return;
+        }
+        self.write_pinned();
+        let Some(entry) = self.entries.get_mut(&digest) else {
+            return;
+        };
+        match entry {
+            CacheEntry::Waiting(..) => {}
+            CacheEntry::InUse { refcount, .. } => {
+                *refcount = refcount.checked_add(1).unwrap();
+            }
+            CacheEntry::InHeap {
+                bytes_used,
+                heap_index,
+                ..
+            } => {
+                let heap_index = *heap_index;
+                *entry = CacheEntry::InUse {
+                    bytes_used: *bytes_used,
+                    refcount: NonZeroU32::new(1).unwrap(),
+                };
+                self.heap.remove(&mut self.entries, heap_index);
+            }
+        }
+    }
+
+    /// Unpin an artifact. If nothing else is using it, it becomes a candidate for removal again.
+    pub fn unpin_artifact(&mut self, digest: Sha256Digest) {
+        if !self.pinned.remove(&digest) {
+            return;
+        }
+        self.write_pinned();
+        if let Some(CacheEntry::InUse { .. }) = self.entries.get(&digest) {
+            self.release(digest);
+        }
+    }
+
+    /// Return all of the pinned artifacts, including those that aren't in the cache yet, ordered
+    /// by digest.
+    pub fn pinned_artifacts(&self) -> Vec<Sha256Digest> {
+        let mut result = Vec::from_iter(self.pinned.iter().cloned());
+        result.sort();
+        result
+    }
+
+    /// Describe all of the artifacts in the cache, ordered by digest. Artifacts that are still
+    /// being transferred aren't included.
+    pub fn cached_artifacts(&self) -> Vec<CachedArtifact> {
+        let mut result = Vec::from_iter(self.entries.iter().filter_map(|(digest, entry)| {
+            let pinned = self.pinned.contains(digest);
+            let (size, refcount) = match entry {
+                CacheEntry::Waiting(..) => return None,
+                CacheEntry::InUse {
+                    bytes_used,
+                    refcount,
+                } => (*bytes_used, refcount.get() - u32::from(pinned)),
+                CacheEntry::InHeap { bytes_used, .. } => (*bytes_used, 0),
+            };
+            Some(CachedArtifact {
+                digest: digest.clone(),
+                size,
+                refcount,
+                pinned,
+                last_used: self.last_used.get(digest).copied(),
+            })
+        }));
+        result.sort_by(|lhs, rhs| lhs.digest.cmp(&rhs.digest));
+        result
+    }
+
+    /// Drop a reference on an artifact that is in use. If that was the last reference, the
+    /// artifact's last use is written as the modification time of its file, so that it survives a
+    /// broker restart. This is only done then, so that jobs sharing an artifact don't each touch
+    /// its file.
+    fn release(&mut self, digest: Sha256Digest) {
+        let Some(CacheEntry::InUse {
             bytes_used,
             refcount,
-        } = entry
+        }) = self.entries.get_mut(&digest)
         else {
             panic!()
         };
         match NonZeroU32::new(refcount.get() - 1) {
             Some(new_refcount) => *refcount = new_refcount,
             None => {
-                *entry = CacheEntry::InHeap {
-                    bytes_used: *bytes_used,
-                    priority: self.next_priority,
-                    heap_index: HeapIndex::default(),
-                };
-                self.heap.push(&mut self.entries, digest);
-                self.next_priority = self.next_priority.checked_add(1).unwrap();
+                let bytes_used = *bytes_used;
+                if let Some(last_used) = self.last_used.get(&digest) {
+                    self.fs.set_modified(&self.cache_path(&digest), *last_used);
+                }
+                self.push_on_heap(digest, bytes_used);
                 self.possibly_remove_some();
             }
         }
     }
 
+    /// Add an entry for an artifact that no job is using. Unless the artifact is pinned, this puts
+    /// it in the heap, where it may be removed.
+    fn insert_unused(&mut self, digest: Sha256Digest, bytes_used: u64) {
+        if self.pinned.contains(&digest) {
+            self.entries.insert(
+                digest,
+                CacheEntry::InUse {
+                    bytes_used,
+                    refcount: NonZeroU32::new(1).unwrap(),
+                },
+            );
+        } else {
+            self.push_on_heap(digest, bytes_used);
+        }
+    }
+
+    /// Make an artifact that no job is using a candidate for removal, computing its priority
+    /// according to the eviction policy.
+    fn push_on_heap(&mut self, digest: Sha256Digest, bytes_used: u64) {
+        let usage = self.usage.get(&digest);
+        let value = match self.eviction.policy {
+            EvictionPolicy::Lru => 0.0,
+            EvictionPolicy::Lfu => usage.map(|usage| usage.jobs).unwrap_or_default() as f64,
+            EvictionPolicy::SizeWeighted => {
+                let kind = usage.map(|usage| usage.kind).unwrap_or(ArtifactKind::File);
+                self.inflation + self.eviction.weight(kind) / bytes_used.max(1) as f64
+            }
+        };
+        let priority = Priority {
+            value,
+            tick: self.next_tick,
+        };
+        self.next_tick = self.next_tick.checked_add(1).unwrap();
+        self.entries.insert(
+            digest.clone(),
+            CacheEntry::InHeap {
+                bytes_used,
+                priority,
+                heap_index: HeapIndex::default(),
+            },
+        );
+        self.heap.push(&mut self.entries, digest);
+    }
+
+    /// Record the pinned artifacts in the cache directory. The file is written in the temporary
+    /// directory and then renamed into place, so it's never left partially written.
+    fn write_pinned(&mut self) {
+        let pinned = String::from_iter(
+            self.pinned_artifacts()
+                .into_iter()
+                .map(|digest| format!("{digest}\n")),
+        );
+        let tmp_path = self.tmp_path().join("pinned");
+        self.fs.write(&tmp_path, &pinned);
+        self.fs.rename(&tmp_path, &self.pinned_path());
+    }
+
     /// Notify the cache that a client disconnected.
     ///
     /// This will clear out any `JobId`s from `cid` for any waiting cache entries, guaranteeing
     /// that `got_artifact` will never return a `JobId` with `cid` in it.
     ///
-    /// This fuction does nothing about refcounts held on behalf of the given client. The Cache
-    /// doesn't track which client holds which refcounts. When a client disconnects, its the
-    /// caller's responsibility to also call `decrement_refcount` appropriately.
+    /// It also drops the references the cache itself holds on the client's behalf for artifacts it
+    /// pushed in response to a query (see [`Self::expect_artifact_from_client`]). Otherwise, this
+    /// fuction does nothing about refcounts held on behalf of the given client. The Cache doesn't
+    /// track which client holds which refcounts. When a client disconnects, its the caller's
+    /// responsibility to also call `decrement_refcount` appropriately.
     pub fn client_disconnected(&mut self, cid: ClientId) {
-        self.entries.retain(|_, e| {
+        let usage = &mut self.usage;
+        self.entries.retain(|digest, e| {
             let CacheEntry::Waiting(jids, clients) = e else {
                 return true;
             };
             jids.retain(|jid| jid.cid != cid);
             clients.retain(|c| *c != cid);
-            !jids.is_empty()
-        })
+            if clients.is_empty() {
+                usage.remove(digest);
+            }
+            !clients.is_empty()
+        });
+        for digest in self.held_for_clients.remove(&cid).unwrap_or_default() {
+            self.release(digest);
+        }
     }
 
     /// Get an artifact for a worker.
     ///
-    /// On success, this will give the caller a refcount as well as the path to the artifact and
-    /// the size of the file in bytes.
+    /// On success, this will give the caller a refcount as well as the path to the artifact, the
+    /// size of the file in bytes, and the path to the artifact's compressed copy, if there is one.
+    ///
+    /// Artifacts that aren't in use are also returned, as long as they haven't been evicted. This is
+    /// how clients fetch artifacts that workers have pushed, like jobs' core files.
     ///
     /// See the comment for [`GetArtifactForWorkerError`].
     pub fn get_artifact_for_worker(
         &mut self,
         digest: &Sha256Digest,
-    ) -> Result<(PathBuf, u64), GetArtifactForWorkerError> {
-        let Some(CacheEntry::InUse {
-            bytes_used,
-            refcount,
-        }) = self.entries.get_mut(digest)
-        else {
+    ) -> Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError> {
+        let Some(entry) = self.entries.get_mut(digest) else {
             return Err(GetArtifactForWorkerError);
         };
-        *refcount = refcount.checked_add(1).unwrap();
-        let bytes_used = *bytes_used;
-        Ok((self.cache_path(digest), bytes_used))
+        let bytes_used = match entry {
+            CacheEntry::Waiting(..) => {
+                return Err(GetArtifactForWorkerError);
+            }
+            CacheEntry::InUse {
+                bytes_used,
+                refcount,
+            } => {
+                *refcount = refcount.checked_add(1).unwrap();
+                *bytes_used
+            }
+            CacheEntry::InHeap {
+                bytes_used,
+                heap_index,
+                ..
+            } => {
+                let bytes_used = *bytes_used;
+                let heap_index = *heap_index;
+                *entry = CacheEntry::InUse {
+                    bytes_used,
+                    refcount: NonZeroU32::new(1).unwrap(),
+                };
+                self.heap.remove(&mut self.entries, heap_index);
+                bytes_used
+            }
+        };
+        let compressed_path = self
+            .compressed_copies
+            .contains_key(digest)
+            .then(|| self.compressed_cache_path(digest));
+        Ok((self.cache_path(digest), bytes_used, compressed_path))
     }
 
     /// Return a [`PathBuf`] that contains the temporary directory for the cache. This is where
@@ -476,6 +948,13 @@ impl<FsT: CacheFs> Cache<FsT> {
         path
     }
 
+    /// Return the path of the file that records the pinned artifacts.
+    fn pinned_path(&self) -> PathBuf {
+        let mut path = self.root.clone();
+        path.push("pinned");
+        path
+    }
+
     /// Return the path of a cached artifact.
     fn cache_path(&self, digest: &Sha256Digest) -> PathBuf {
         let mut path = self.root.clone();
@@ -484,17 +963,51 @@ impl<FsT: CacheFs> Cache<FsT> {
         path
     }
 
-    /// Try to ensure that the size of the cache is smaller than or equal to `byte_used_target`.
+    /// Return the path of the compressed copy of a cached artifact.
+    fn compressed_cache_path(&self, digest: &Sha256Digest) -> PathBuf {
+        let mut path = self.root.clone();
+        path.push("sha256");
+        path.push(format!("{digest}.zst"));
+        path
+    }
+
+    /// Return running totals of the artifacts removed to keep the cache within its target size.
+    pub fn eviction_statistics(&self) -> EvictionStatistics {
+        self.eviction_statistics
+    }
+
+    /// If the size of the cache is larger than `bytes_used_target`, try to bring it down to
+    /// `bytes_used_low_watermark`.
     fn possibly_remove_some(&mut self) {
-        while self.bytes_used > self.bytes_used_target {
+        if self.bytes_used <= self.bytes_used_target {
+            return;
+        }
+        let bytes_used_before = self.bytes_used;
+        let mut artifacts_removed = 0;
+        while self.bytes_used > self.bytes_used_low_watermark {
             let Some(digest) = self.heap.pop(&mut self.entries) else {
                 break;
             };
-            let Some(CacheEntry::InHeap { bytes_used, .. }) = self.entries.remove(&digest) else {
+            let Some(CacheEntry::InHeap {
+                bytes_used,
+                priority,
+                ..
+            }) = self.entries.remove(&digest)
+            else {
                 panic!("Entry popped off of heap was in unexpected state");
             };
+            if self.eviction.policy == EvictionPolicy::SizeWeighted {
+                self.inflation = priority.value;
+            }
             self.fs.remove_file(&self.cache_path(&digest));
             self.bytes_used = self.bytes_used.checked_sub(bytes_used).unwrap();
+            self.last_used.remove(&digest);
+            self.usage.remove(&digest);
+            if let Some(compressed_size) = self.compressed_copies.remove(&digest) {
+                self.fs.remove_file(&self.compressed_cache_path(&digest));
+                self.bytes_used = self.bytes_used.checked_sub(compressed_size).unwrap();
+            }
+            artifacts_removed += 1;
             debug!(self.log, "cache removed artifact";
                 "digest" => %digest,
                 "artifact_bytes_used" => %ByteSize::b(bytes_used),
@@ -503,6 +1016,18 @@ impl<FsT: CacheFs> Cache<FsT> {
                 "byte_used_target" => %ByteSize::b(self.bytes_used_target)
             );
         }
+        if artifacts_removed > 0 {
+            let bytes_freed = bytes_used_before - self.bytes_used;
+            self.eviction_statistics.runs += 1;
+            self.eviction_statistics.artifacts += artifacts_removed;
+            self.eviction_statistics.bytes += bytes_freed;
+            debug!(self.log, "cache finished removing artifacts";
+                "artifacts_removed" => artifacts_removed,
+                "bytes_freed" => %ByteSize::b(bytes_freed),
+                "bytes_used" => %ByteSize::b(self.bytes_used),
+                "byte_used_low_watermark" => %ByteSize::b(self.bytes_used_low_watermark)
+            );
+        }
     }
 }
 
@@ -515,7 +1040,7 @@ mod tests {
     };
     use maelstrom_test::*;
     use maelstrom_util::manifest::ManifestWriter;
-    use std::{cell::RefCell, rc::Rc};
+    use std::{cell::RefCell, rc::Rc, time::Duration};
     use TestMessage::*;
 
     #[derive(Debug, PartialEq)]
@@ -526,13 +1051,19 @@ mod tests {
         ReadDir(PathBuf),
         FileSize(PathBuf),
         OpenFile(PathBuf),
+        ReadToStringIfExists(PathBuf),
+        Write(PathBuf, String),
     }
 
     #[derive(Default)]
     struct TestCacheFs {
         messages: Vec<TestMessage>,
+        seconds_since_epoch: u64,
         files: HashMap<PathBuf, Vec<u8>>,
         directories: HashMap<PathBuf, Vec<PathBuf>>,
+        /// Modification times aren't recorded as messages. Files without one were last modified at
+        /// the epoch.
+        modified: HashMap<PathBuf, SystemTime>,
     }
 
     impl CacheFs for Rc<RefCell<TestCacheFs>> {
@@ -569,12 +1100,54 @@ mod tests {
             self.borrow().files.get(path).unwrap().len() as u64
         }
 
+        fn modified(&mut self, path: &Path) -> SystemTime {
+            self.borrow()
+                .modified
+                .get(path)
+                .copied()
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        }
+
+        fn set_modified(&mut self, path: &Path, time: SystemTime) {
+            self.borrow_mut().modified.insert(path.to_owned(), time);
+        }
+
         type File = std::io::Cursor<Vec<u8>>;
 
         fn open_file(&mut self, path: &Path) -> Self::File {
             self.borrow_mut().messages.push(OpenFile(path.to_owned()));
             std::io::Cursor::new(self.borrow().files.get(path).unwrap().clone())
         }
+
+        fn read_to_string_if_exists(&mut self, path: &Path) -> Option<String> {
+            self.borrow_mut()
+                .messages
+                .push(ReadToStringIfExists(path.to_owned()));
+            self.borrow()
+                .files
+                .get(path)
+                .map(|contents| String::from_utf8(contents.clone()).unwrap())
+        }
+
+        fn write(&mut self, path: &Path, contents: &str) {
+            self.borrow_mut()
+                .messages
+                .push(Write(path.to_owned(), contents.to_owned()));
+        }
+
+        fn now(&mut self) -> SystemTime {
+            let mut fs = self.borrow_mut();
+            fs.seconds_since_epoch += 1;
+            SystemTime::UNIX_EPOCH + Duration::from_secs(fs.seconds_since_epoch)
+        }
+    }
+
+    /// The file system operations that record the pinned artifacts.
+    fn write_pinned(contents: String) -> Vec<TestMessage> {
+        vec![
+            Write(path_buf!("/z/tmp/pinned"), contents),
+            Rename(path_buf!("/z/tmp/pinned"), path_buf!("/z/pinned")),
+        ]
     }
 
     struct Fixture {
@@ -582,13 +1155,36 @@ mod tests {
         cache: Cache<Rc<RefCell<TestCacheFs>>>,
     }
 
+    fn eviction_config(policy: EvictionPolicy, low_watermark_percent: u8) -> EvictionConfig {
+        EvictionConfig {
+            policy,
+            low_watermark_percent,
+            tar_weight: 4.0,
+            manifest_weight: 4.0,
+            file_weight: 1.0,
+        }
+    }
+
     impl Fixture {
         fn new(fs: TestCacheFs, bytes_used_target: u64) -> Self {
+            Self::new_with_eviction(
+                fs,
+                bytes_used_target,
+                eviction_config(EvictionPolicy::Lru, 100),
+            )
+        }
+
+        fn new_with_eviction(
+            fs: TestCacheFs,
+            bytes_used_target: u64,
+            eviction: EvictionConfig,
+        ) -> Self {
             let fs = Rc::new(RefCell::new(fs));
             let cache = Cache::new(
                 fs.clone(),
                 "/z".parse().unwrap(),
                 ByteSize::b(bytes_used_target).into(),
+                eviction,
                 slog::Logger::root(slog::Discard, slog::o!()),
             );
             Fixture { fs, cache }
@@ -623,13 +1219,22 @@ mod tests {
             expected: GetArtifact,
             expected_fs_operations: Vec<TestMessage>,
         ) {
-            let result = self.cache.get_artifact(jid, digest);
+            let result = self.cache.get_artifact(jid, digest, ArtifactKind::Tar);
             assert_eq!(result, expected);
             self.expect_fs_operations(expected_fs_operations);
         }
 
         fn get_artifact_ign(&mut self, jid: JobId, digest: Sha256Digest) {
-            _ = self.cache.get_artifact(jid, digest);
+            self.get_artifact_of_kind_ign(jid, digest, ArtifactKind::Tar);
+        }
+
+        fn get_artifact_of_kind_ign(
+            &mut self,
+            jid: JobId,
+            digest: Sha256Digest,
+            kind: ArtifactKind,
+        ) {
+            _ = self.cache.get_artifact(jid, digest, kind);
             self.clear_fs_operations();
         }
 
@@ -641,13 +1246,13 @@ mod tests {
             expected: Vec<JobId>,
             expected_fs_operations: Vec<TestMessage>,
         ) {
-            let result = self.cache.got_artifact(digest, size, &path);
+            let result = self.cache.got_artifact(digest, size, &path, None);
             assert_eq!(result, expected);
             self.expect_fs_operations(expected_fs_operations);
         }
 
         fn got_artifact_ign(&mut self, digest: Sha256Digest, size: u64, path: PathBuf) {
-            _ = self.cache.got_artifact(digest, size, &path);
+            _ = self.cache.got_artifact(digest, size, &path, None);
             self.clear_fs_operations();
         }
 
@@ -665,10 +1270,24 @@ mod tests {
             self.clear_fs_operations();
         }
 
+        fn pin_artifact(&mut self, digest: Sha256Digest, expected_fs_operations: Vec<TestMessage>) {
+            self.cache.pin_artifact(digest);
+            self.expect_fs_operations(expected_fs_operations);
+        }
+
+        fn unpin_artifact(
+            &mut self,
+            digest: Sha256Digest,
+            expected_fs_operations: Vec<TestMessage>,
+        ) {
+            self.cache.unpin_artifact(digest);
+            self.expect_fs_operations(expected_fs_operations);
+        }
+
         fn get_artifact_for_worker(
             &mut self,
             digest: Sha256Digest,
-            expected: Result<(PathBuf, u64), GetArtifactForWorkerError>,
+            expected: Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError>,
         ) {
             assert_eq!(self.cache.get_artifact_for_worker(&digest), expected);
         }
@@ -690,6 +1309,7 @@ mod tests {
         fixture.expect_fs_operations(vec![
             MkdirRecursively(path_buf!("/z/tmp")),
             ReadDir(path_buf!("/z/tmp")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
             MkdirRecursively(path_buf!("/z/sha256")),
             ReadDir(path_buf!("/z/sha256")),
         ]);
@@ -710,6 +1330,7 @@ mod tests {
             ReadDir(path_buf!("/z/tmp")),
             Remove(path_buf!("/z/tmp/one")),
             Remove(path_buf!("/z/tmp/two")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
             MkdirRecursively(path_buf!("/z/sha256")),
             ReadDir(path_buf!("/z/sha256")),
         ]);
@@ -738,6 +1359,7 @@ mod tests {
         fixture.expect_fs_operations(vec![
             MkdirRecursively(path_buf!("/z/tmp")),
             ReadDir(path_buf!("/z/tmp")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
             MkdirRecursively(path_buf!("/z/sha256")),
             ReadDir(path_buf!("/z/sha256")),
             Remove(path_buf!("/z/sha256/one")),
@@ -771,6 +1393,7 @@ mod tests {
         fixture.expect_fs_operations(vec![
             MkdirRecursively(path_buf!("/z/tmp")),
             ReadDir(path_buf!("/z/tmp")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
             MkdirRecursively(path_buf!("/z/sha256")),
             ReadDir(path_buf!("/z/sha256")),
             FileSize(long_path!("/z/sha256", 1, "bin")),
@@ -783,43 +1406,159 @@ mod tests {
     }
 
     #[test]
-    fn get_artifact_once() {
-        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
-        fixture.get_artifact(jid!(1, 1001), digest!(1), GetArtifact::Get, vec![]);
-    }
-
-    #[test]
-    fn get_artifact_again_from_same_client() {
-        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
-        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
-        fixture.get_artifact(jid!(1, 1002), digest!(1), GetArtifact::Wait, vec![]);
-    }
-
-    #[test]
-    fn get_artifact_again_from_different_client() {
-        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
-        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
-        fixture.get_artifact(jid!(2, 1001), digest!(1), GetArtifact::Get, vec![]);
-    }
-
-    #[test]
-    fn get_artifact_in_use() {
-        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
-        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
-        fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
-        fixture.get_artifact(jid!(2, 1001), digest!(1), GetArtifact::Success, vec![]);
-
-        // Refcount should be 2.
-        fixture.decrement_refcount(digest!(1), vec![]);
-        fixture.decrement_refcount(digest!(1), vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
-    }
-
-    #[test]
-    fn get_artifact_in_heap_removes_from_heap() {
+    fn new_removes_least_recently_used_first() {
         let fs = TestCacheFs {
             directories: HashMap::from([(
                 path_buf!("/z/sha256"),
-                vec![long_path!("/z/sha256", 112358, "bin")],
+                vec![
+                    long_path!("/z/sha256", 1, "bin"),
+                    long_path!("/z/sha256", 2, "bin"),
+                    long_path!("/z/sha256", 3, "bin"),
+                ],
+            )]),
+            files: HashMap::from([
+                (long_path!("/z/sha256", 1, "bin"), vec![0; 1001]),
+                (long_path!("/z/sha256", 2, "bin"), vec![0; 1002]),
+                (long_path!("/z/sha256", 3, "bin"), vec![0; 1003]),
+            ]),
+            modified: HashMap::from([
+                (
+                    long_path!("/z/sha256", 1, "bin"),
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(3),
+                ),
+                (
+                    long_path!("/z/sha256", 2, "bin"),
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(1),
+                ),
+                (
+                    long_path!("/z/sha256", 3, "bin"),
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(2),
+                ),
+            ]),
+            ..Default::default()
+        };
+        let mut fixture = Fixture::new(fs, 1003);
+        fixture.expect_fs_operations(vec![
+            MkdirRecursively(path_buf!("/z/tmp")),
+            ReadDir(path_buf!("/z/tmp")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
+            MkdirRecursively(path_buf!("/z/sha256")),
+            ReadDir(path_buf!("/z/sha256")),
+            FileSize(long_path!("/z/sha256", 1, "bin")),
+            FileSize(long_path!("/z/sha256", 2, "bin")),
+            FileSize(long_path!("/z/sha256", 3, "bin")),
+            Remove(long_path!("/z/sha256", 2, "bin")),
+            Remove(long_path!("/z/sha256", 3, "bin")),
+        ]);
+        assert_eq!(
+            fixture.cache.cached_artifacts(),
+            vec![CachedArtifact {
+                digest: digest!(1),
+                size: 1001,
+                refcount: 0,
+                pinned: false,
+                last_used: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(3)),
+            }]
+        );
+    }
+
+    #[test]
+    fn last_decrement_refcount_records_last_use_in_file() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 10);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.get_artifact_ign(jid!(1, 1002), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
+        let path: PathBuf = long_path!("/z/sha256", 1, "bin");
+
+        fixture.decrement_refcount(digest!(1), vec![]);
+        assert_eq!(fixture.fs.borrow().modified.get(&path), None);
+
+        fixture.decrement_refcount(digest!(1), vec![]);
+        assert_eq!(
+            fixture.fs.borrow().modified.get(&path),
+            Some(&(SystemTime::UNIX_EPOCH + Duration::from_secs(3))),
+        );
+    }
+
+    #[test]
+    fn new_with_compressed_copies_in_sha256() {
+        let fs = TestCacheFs {
+            directories: HashMap::from([(
+                path_buf!("/z/sha256"),
+                vec![
+                    long_path!("/z/sha256", 1, "zst"),
+                    long_path!("/z/sha256", 1, "bin"),
+                    long_path!("/z/sha256", 2, "zst"),
+                ],
+            )]),
+            files: HashMap::from([
+                (long_path!("/z/sha256", 1, "zst"), vec![0; 100]),
+                (long_path!("/z/sha256", 1, "bin"), vec![0; 1000]),
+                (long_path!("/z/sha256", 2, "zst"), vec![0; 10]),
+            ]),
+            ..Default::default()
+        };
+        let mut fixture = Fixture::new(fs, 2000);
+        fixture.expect_fs_operations(vec![
+            MkdirRecursively(path_buf!("/z/tmp")),
+            ReadDir(path_buf!("/z/tmp")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
+            MkdirRecursively(path_buf!("/z/sha256")),
+            ReadDir(path_buf!("/z/sha256")),
+            FileSize(long_path!("/z/sha256", 1, "zst")),
+            FileSize(long_path!("/z/sha256", 1, "bin")),
+            FileSize(long_path!("/z/sha256", 2, "zst")),
+            Remove(long_path!("/z/sha256", 2, "zst")),
+        ]);
+        assert_eq!(fixture.cache.bytes_used, 1100);
+        fixture.get_artifact_for_worker(
+            digest!(1),
+            Ok((
+                long_path!("/z/sha256", 1, "bin"),
+                1000,
+                Some(long_path!("/z/sha256", 1, "zst")),
+            )),
+        );
+    }
+
+    #[test]
+    fn get_artifact_once() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
+        fixture.get_artifact(jid!(1, 1001), digest!(1), GetArtifact::Get, vec![]);
+    }
+
+    #[test]
+    fn get_artifact_again_from_same_client() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.get_artifact(jid!(1, 1002), digest!(1), GetArtifact::Wait, vec![]);
+    }
+
+    #[test]
+    fn get_artifact_again_from_different_client() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.get_artifact(jid!(2, 1001), digest!(1), GetArtifact::Get, vec![]);
+    }
+
+    #[test]
+    fn get_artifact_in_use() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
+        fixture.get_artifact(jid!(2, 1001), digest!(1), GetArtifact::Success, vec![]);
+
+        // Refcount should be 2.
+        fixture.decrement_refcount(digest!(1), vec![]);
+        fixture.decrement_refcount(digest!(1), vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
+    }
+
+    #[test]
+    fn get_artifact_in_heap_removes_from_heap() {
+        let fs = TestCacheFs {
+            directories: HashMap::from([(
+                path_buf!("/z/sha256"),
+                vec![long_path!("/z/sha256", 112358, "bin")],
             )]),
             files: HashMap::from([(long_path!("/z/sha256", 112358, "bin"), vec![0; 1])]),
             ..Default::default()
@@ -1048,6 +1787,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn got_artifact_with_compressed_copy() {
+        let fs = TestCacheFs {
+            files: HashMap::from([(short_path!("/z/tmp", 2, "zst"), vec![0; 5])]),
+            ..Default::default()
+        };
+        let mut fixture = Fixture::new_and_clear_fs_operations(fs, 0);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+
+        let path: PathBuf = short_path!("/z/tmp", 1, "bin");
+        let compressed_path: PathBuf = short_path!("/z/tmp", 2, "zst");
+        let result = fixture
+            .cache
+            .got_artifact(digest!(1), 10, &path, Some(&compressed_path));
+        assert_eq!(result, vec![jid!(1, 1001)]);
+        fixture.expect_fs_operations(vec![
+            Rename(
+                short_path!("/z/tmp", 1, "bin"),
+                long_path!("/z/sha256", 1, "bin"),
+            ),
+            FileSize(short_path!("/z/tmp", 2, "zst")),
+            Rename(
+                short_path!("/z/tmp", 2, "zst"),
+                long_path!("/z/sha256", 1, "zst"),
+            ),
+        ]);
+        assert_eq!(fixture.cache.bytes_used, 15);
+
+        fixture.get_artifact_for_worker(
+            digest!(1),
+            Ok((
+                long_path!("/z/sha256", 1, "bin"),
+                10,
+                Some(long_path!("/z/sha256", 1, "zst")),
+            )),
+        );
+
+        // The compressed copy is removed along with the artifact.
+        fixture.decrement_refcount(digest!(1), vec![]);
+        fixture.decrement_refcount(
+            digest!(1),
+            vec![
+                Remove(long_path!("/z/sha256", 1, "bin")),
+                Remove(long_path!("/z/sha256", 1, "zst")),
+            ],
+        );
+        assert_eq!(fixture.cache.bytes_used, 0);
+    }
+
+    #[test]
+    fn got_artifact_with_compressed_copy_already_in_use() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 10, short_path!("/z/tmp", 1, "bin"));
+
+        let path: PathBuf = short_path!("/z/tmp", 2, "bin");
+        let compressed_path: PathBuf = short_path!("/z/tmp", 3, "zst");
+        let result = fixture
+            .cache
+            .got_artifact(digest!(1), 10, &path, Some(&compressed_path));
+        assert_eq!(result, vec![]);
+        fixture.expect_fs_operations(vec![
+            Remove(short_path!("/z/tmp", 2, "bin")),
+            Remove(short_path!("/z/tmp", 3, "zst")),
+        ]);
+    }
+
+    #[test]
+    fn got_compressed_copy_is_kept_with_artifact() {
+        let fs = TestCacheFs {
+            files: HashMap::from([(short_path!("/z/tmp", 2, "zst"), vec![0; 5])]),
+            ..Default::default()
+        };
+        let mut fixture = Fixture::new_and_clear_fs_operations(fs, 0);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 10, short_path!("/z/tmp", 1, "bin"));
+
+        let path: PathBuf = short_path!("/z/tmp", 2, "zst");
+
+        fixture.cache.got_compressed_copy(&digest!(1), &path);
+        fixture.expect_fs_operations(vec![
+            FileSize(short_path!("/z/tmp", 2, "zst")),
+            Rename(
+                short_path!("/z/tmp", 2, "zst"),
+                long_path!("/z/sha256", 1, "zst"),
+            ),
+        ]);
+        assert_eq!(fixture.cache.bytes_used, 15);
+
+        fixture.get_artifact_for_worker(
+            digest!(1),
+            Ok((
+                long_path!("/z/sha256", 1, "bin"),
+                10,
+                Some(long_path!("/z/sha256", 1, "zst")),
+            )),
+        );
+
+        // A second copy, made by a concurrent fetch, is thrown away.
+        let path: PathBuf = short_path!("/z/tmp", 3, "zst");
+        fixture.cache.got_compressed_copy(&digest!(1), &path);
+        fixture.expect_fs_operations(vec![Remove(short_path!("/z/tmp", 3, "zst"))]);
+        assert_eq!(fixture.cache.bytes_used, 15);
+    }
+
+    #[test]
+    fn got_compressed_copy_for_artifact_not_in_cache() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        let path: PathBuf = short_path!("/z/tmp", 1, "zst");
+        fixture.cache.got_compressed_copy(&digest!(1), &path);
+        fixture.expect_fs_operations(vec![Remove(short_path!("/z/tmp", 1, "zst"))]);
+        assert_eq!(fixture.cache.bytes_used, 0);
+    }
+
     #[test]
     fn decrement_refcount_sets_priority_properly() {
         let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 10);
@@ -1164,6 +2017,271 @@ mod tests {
         fixture.decrement_refcount(digest!(1), vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
     }
 
+    #[test]
+    fn expect_artifact_from_client_not_in_cache() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(1), digest!(1)));
+        assert!(!fixture
+            .cache
+            .expect_artifact_from_client(cid!(1), digest!(1)));
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(2), digest!(1)));
+        fixture.get_artifact(jid!(1, 1001), digest!(1), GetArtifact::Wait, vec![]);
+        fixture.get_artifact(jid!(3, 1001), digest!(1), GetArtifact::Get, vec![]);
+        fixture.got_artifact(
+            digest!(1),
+            1,
+            short_path!("/z/tmp", 1, "bin"),
+            vec![jid!(1, 1001), jid!(3, 1001)],
+            vec![Rename(
+                short_path!("/z/tmp", 1, "bin"),
+                long_path!("/z/sha256", 1, "bin"),
+            )],
+        );
+    }
+
+    #[test]
+    fn expect_artifact_from_client_in_cache() {
+        let mut fixture = Fixture::new(TestCacheFs::default(), 1);
+        fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
+        assert!(!fixture
+            .cache
+            .expect_artifact_from_client(cid!(1), digest!(1)));
+    }
+
+    #[test]
+    fn expect_artifact_from_client_no_waiters_is_held_for_clients_first_job() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(1), digest!(1)));
+        fixture.got_artifact(
+            digest!(1),
+            1,
+            short_path!("/z/tmp", 1, "bin"),
+            vec![],
+            vec![Rename(
+                short_path!("/z/tmp", 1, "bin"),
+                long_path!("/z/sha256", 1, "bin"),
+            )],
+        );
+        fixture.get_artifact(jid!(1, 1001), digest!(1), GetArtifact::Success, vec![]);
+        fixture.decrement_refcount(digest!(1), vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
+    }
+
+    #[test]
+    fn expect_artifact_from_client_no_waiters_held_until_client_disconnected() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(1), digest!(1)));
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(2), digest!(1)));
+        fixture.get_artifact(jid!(2, 1001), digest!(1), GetArtifact::Wait, vec![]);
+        fixture.got_artifact(
+            digest!(1),
+            1,
+            short_path!("/z/tmp", 1, "bin"),
+            vec![jid!(2, 1001)],
+            vec![Rename(
+                short_path!("/z/tmp", 1, "bin"),
+                long_path!("/z/sha256", 1, "bin"),
+            )],
+        );
+        fixture.decrement_refcount(digest!(1), vec![]);
+        fixture.cache.client_disconnected(cid!(1));
+        fixture.expect_fs_operations(vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
+    }
+
+    #[test]
+    fn expect_artifact_from_client_then_client_disconnected() {
+        let mut fixture = Fixture::new(TestCacheFs::default(), 0);
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(1), digest!(1)));
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(2), digest!(1)));
+        fixture.cache.client_disconnected(cid!(1));
+        assert!(!fixture
+            .cache
+            .expect_artifact_from_client(cid!(2), digest!(1)));
+        fixture.cache.client_disconnected(cid!(2));
+        assert!(fixture
+            .cache
+            .expect_artifact_from_client(cid!(2), digest!(1)));
+    }
+
+    #[test]
+    fn new_with_pinned_artifacts() {
+        let fs = TestCacheFs {
+            directories: HashMap::from([(
+                path_buf!("/z/sha256"),
+                vec![
+                    long_path!("/z/sha256", 1, "bin"),
+                    long_path!("/z/sha256", 2, "bin"),
+                ],
+            )]),
+            files: HashMap::from([
+                (
+                    path_buf!("/z/pinned"),
+                    format!("{}\ngarbage\n", digest!(1)).into_bytes(),
+                ),
+                (long_path!("/z/sha256", 1, "bin"), vec![0; 1000]),
+                (long_path!("/z/sha256", 2, "bin"), vec![0; 1000]),
+            ]),
+            ..Default::default()
+        };
+        let mut fixture = Fixture::new(fs, 0);
+        fixture.expect_fs_operations(vec![
+            MkdirRecursively(path_buf!("/z/tmp")),
+            ReadDir(path_buf!("/z/tmp")),
+            ReadToStringIfExists(path_buf!("/z/pinned")),
+            MkdirRecursively(path_buf!("/z/sha256")),
+            ReadDir(path_buf!("/z/sha256")),
+            FileSize(long_path!("/z/sha256", 1, "bin")),
+            FileSize(long_path!("/z/sha256", 2, "bin")),
+            Remove(long_path!("/z/sha256", 2, "bin")),
+        ]);
+        assert_eq!(fixture.cache.bytes_used, 1000);
+        assert_eq!(fixture.cache.pinned_artifacts(), vec![digest!(1)]);
+    }
+
+    #[test]
+    fn pin_artifact_in_heap_takes_it_out_of_heap() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 10);
+        fixture.got_artifact_ign(digest!(1), 10, short_path!("/z/tmp", 1, "bin"));
+        fixture.pin_artifact(digest!(1), write_pinned(format!("{}\n", digest!(1))));
+        fixture.got_artifact(
+            digest!(2),
+            1,
+            short_path!("/z/tmp", 2, "bin"),
+            vec![],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 2, "bin"),
+                    long_path!("/z/sha256", 2, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 2, "bin")),
+            ],
+        );
+        assert_eq!(fixture.cache.bytes_used, 10);
+    }
+
+    #[test]
+    fn pin_artifact_twice() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 10);
+        fixture.pin_artifact(digest!(1), write_pinned(format!("{}\n", digest!(1))));
+        fixture.pin_artifact(digest!(1), vec![]);
+        fixture.pin_artifact(
+            digest!(2),
+            write_pinned(format!("{}\n{}\n", digest!(1), digest!(2))),
+        );
+    }
+
+    #[test]
+    fn pin_artifact_not_in_cache_pins_it_when_it_arrives() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        fixture.pin_artifact(digest!(1), write_pinned(format!("{}\n", digest!(1))));
+        fixture.got_artifact(
+            digest!(1),
+            10,
+            short_path!("/z/tmp", 1, "bin"),
+            vec![],
+            vec![Rename(
+                short_path!("/z/tmp", 1, "bin"),
+                long_path!("/z/sha256", 1, "bin"),
+            )],
+        );
+        assert_eq!(fixture.cache.bytes_used, 10);
+    }
+
+    #[test]
+    fn pin_artifact_waiting_pins_it_when_it_arrives() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.pin_artifact(digest!(1), write_pinned(format!("{}\n", digest!(1))));
+        fixture.got_artifact(
+            digest!(1),
+            10,
+            short_path!("/z/tmp", 1, "bin"),
+            vec![jid!(1, 1001)],
+            vec![Rename(
+                short_path!("/z/tmp", 1, "bin"),
+                long_path!("/z/sha256", 1, "bin"),
+            )],
+        );
+        fixture.decrement_refcount(digest!(1), vec![]);
+        assert_eq!(fixture.cache.bytes_used, 10);
+    }
+
+    #[test]
+    fn unpin_artifact_makes_it_removable() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        fixture.pin_artifact(digest!(1), write_pinned(format!("{}\n", digest!(1))));
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 10, short_path!("/z/tmp", 1, "bin"));
+        fixture.unpin_artifact(digest!(1), write_pinned("".into()));
+        fixture.unpin_artifact(digest!(1), vec![]);
+        fixture.decrement_refcount(digest!(1), vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
+        assert_eq!(fixture.cache.bytes_used, 0);
+    }
+
+    #[test]
+    fn unpin_artifact_not_in_cache() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 0);
+        fixture.pin_artifact(digest!(1), write_pinned(format!("{}\n", digest!(1))));
+        fixture.unpin_artifact(digest!(1), write_pinned("".into()));
+        fixture.got_artifact(
+            digest!(1),
+            10,
+            short_path!("/z/tmp", 1, "bin"),
+            vec![],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 1, "bin"),
+                    long_path!("/z/sha256", 1, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 1, "bin")),
+            ],
+        );
+    }
+
+    #[test]
+    fn cached_artifacts() {
+        let mut fixture = Fixture::new_and_clear_fs_operations(TestCacheFs::default(), 1000);
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.get_artifact_ign(jid!(1, 1002), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 10, short_path!("/z/tmp", 1, "bin"));
+        fixture.decrement_refcount_ign(digest!(1));
+        fixture.got_artifact_ign(digest!(2), 20, short_path!("/z/tmp", 2, "bin"));
+        fixture.get_artifact_ign(jid!(1, 1003), digest!(3));
+        fixture.pin_artifact(digest!(2), write_pinned(format!("{}\n", digest!(2))));
+        assert_eq!(
+            fixture.cache.cached_artifacts(),
+            vec![
+                CachedArtifact {
+                    digest: digest!(1),
+                    size: 10,
+                    refcount: 1,
+                    pinned: false,
+                    last_used: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(2)),
+                },
+                CachedArtifact {
+                    digest: digest!(2),
+                    size: 20,
+                    refcount: 0,
+                    pinned: true,
+                    last_used: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(3)),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn get_artifact_for_worker_no_entry() {
         let mut fixture = Fixture::new(TestCacheFs::default(), 0);
@@ -1181,7 +2299,25 @@ mod tests {
     fn get_artifact_for_worker_in_cache() {
         let mut fixture = Fixture::new(TestCacheFs::default(), 1);
         fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
-        fixture.get_artifact_for_worker(digest!(1), Err(GetArtifactForWorkerError));
+        fixture
+            .get_artifact_for_worker(digest!(1), Ok((long_path!("/z/sha256", 1, "bin"), 1, None)));
+
+        // The artifact is taken off of the heap while the worker has it, so another artifact is
+        // evicted instead when the cache goes over its target size.
+        fixture.got_artifact(
+            digest!(2),
+            1,
+            short_path!("/z/tmp", 2, "bin"),
+            vec![],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 2, "bin"),
+                    long_path!("/z/sha256", 2, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 2, "bin")),
+            ],
+        );
+        fixture.decrement_refcount(digest!(1), vec![]);
     }
 
     #[test]
@@ -1189,13 +2325,207 @@ mod tests {
         let mut fixture = Fixture::new(TestCacheFs::default(), 0);
         fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
         fixture.got_artifact_ign(digest!(1), 42, short_path!("/z/tmp", 1, "bin"));
-        fixture.get_artifact_for_worker(digest!(1), Ok((long_path!("/z/sha256", 1, "bin"), 42)));
+        fixture.get_artifact_for_worker(
+            digest!(1),
+            Ok((long_path!("/z/sha256", 1, "bin"), 42, None)),
+        );
 
         // Refcount should be 2.
         fixture.decrement_refcount(digest!(1), vec![]);
         fixture.decrement_refcount(digest!(1), vec![Remove(long_path!("/z/sha256", 1, "bin"))]);
     }
 
+    #[test]
+    fn low_watermark_removes_in_batches() {
+        let mut fixture = Fixture::new_with_eviction(
+            TestCacheFs::default(),
+            10,
+            eviction_config(EvictionPolicy::Lru, 50),
+        );
+        fixture.clear_fs_operations();
+        for i in 1..=10 {
+            fixture.got_artifact(
+                digest!(i),
+                1,
+                short_path!("/z/tmp", i, "bin"),
+                vec![],
+                vec![Rename(
+                    short_path!("/z/tmp", i, "bin"),
+                    long_path!("/z/sha256", i, "bin"),
+                )],
+            );
+        }
+        assert_eq!(
+            fixture.cache.eviction_statistics(),
+            EvictionStatistics::default()
+        );
+
+        let mut expected = vec![Rename(
+            short_path!("/z/tmp", 11, "bin"),
+            long_path!("/z/sha256", 11, "bin"),
+        )];
+        expected.extend((1..=6).map(|i| Remove(long_path!("/z/sha256", i, "bin"))));
+        fixture.got_artifact(
+            digest!(11),
+            1,
+            short_path!("/z/tmp", 11, "bin"),
+            vec![],
+            expected,
+        );
+        assert_eq!(fixture.cache.bytes_used, 5);
+
+        fixture.got_artifact(
+            digest!(12),
+            1,
+            short_path!("/z/tmp", 12, "bin"),
+            vec![],
+            vec![Rename(
+                short_path!("/z/tmp", 12, "bin"),
+                long_path!("/z/sha256", 12, "bin"),
+            )],
+        );
+        assert_eq!(
+            fixture.cache.eviction_statistics(),
+            EvictionStatistics {
+                runs: 1,
+                artifacts: 6,
+                bytes: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn lfu_removes_least_used_first() {
+        let mut fixture = Fixture::new_with_eviction(
+            TestCacheFs::default(),
+            2,
+            eviction_config(EvictionPolicy::Lfu, 100),
+        );
+        fixture.get_artifact_ign(jid!(1, 1001), digest!(1));
+        fixture.get_artifact_ign(jid!(1, 1002), digest!(1));
+        fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
+        fixture.decrement_refcount_ign(digest!(1));
+        fixture.decrement_refcount_ign(digest!(1));
+
+        fixture.get_artifact_ign(jid!(1, 1003), digest!(2));
+        fixture.got_artifact_ign(digest!(2), 1, short_path!("/z/tmp", 2, "bin"));
+        fixture.decrement_refcount_ign(digest!(2));
+
+        // Artifact 1 became unused first, but it has been used by more jobs.
+        fixture.get_artifact_ign(jid!(1, 1004), digest!(3));
+        fixture.got_artifact(
+            digest!(3),
+            1,
+            short_path!("/z/tmp", 3, "bin"),
+            vec![jid!(1, 1004)],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 3, "bin"),
+                    long_path!("/z/sha256", 3, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 2, "bin")),
+            ],
+        );
+    }
+
+    #[test]
+    fn size_weighted_prefers_layers_but_ages_them_out() {
+        let mut fixture = Fixture::new_with_eviction(
+            TestCacheFs::default(),
+            20,
+            eviction_config(EvictionPolicy::SizeWeighted, 100),
+        );
+        fixture.get_artifact_of_kind_ign(jid!(1, 1001), digest!(1), ArtifactKind::Tar);
+        fixture.got_artifact_ign(digest!(1), 10, short_path!("/z/tmp", 1, "bin"));
+        fixture.decrement_refcount_ign(digest!(1));
+
+        fixture.get_artifact_of_kind_ign(jid!(1, 1002), digest!(2), ArtifactKind::File);
+        fixture.got_artifact_ign(digest!(2), 5, short_path!("/z/tmp", 2, "bin"));
+        fixture.decrement_refcount_ign(digest!(2));
+
+        // The file is smaller, but it's worth less per byte than the layer.
+        fixture.get_artifact_of_kind_ign(jid!(1, 1003), digest!(3), ArtifactKind::File);
+        fixture.got_artifact(
+            digest!(3),
+            10,
+            short_path!("/z/tmp", 3, "bin"),
+            vec![jid!(1, 1003)],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 3, "bin"),
+                    long_path!("/z/sha256", 3, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 2, "bin")),
+            ],
+        );
+        fixture.decrement_refcount(digest!(3), vec![]);
+
+        fixture.get_artifact_of_kind_ign(jid!(1, 1004), digest!(4), ArtifactKind::Manifest);
+        fixture.got_artifact(
+            digest!(4),
+            5,
+            short_path!("/z/tmp", 4, "bin"),
+            vec![jid!(1, 1004)],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 4, "bin"),
+                    long_path!("/z/sha256", 4, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 3, "bin")),
+            ],
+        );
+        fixture.decrement_refcount(digest!(4), vec![]);
+
+        // Each removal raises the priority of artifacts that become unused afterwards, so the
+        // layer that hasn't been used in a while is eventually removed.
+        fixture.get_artifact_of_kind_ign(jid!(1, 1005), digest!(5), ArtifactKind::File);
+        fixture.got_artifact(
+            digest!(5),
+            10,
+            short_path!("/z/tmp", 5, "bin"),
+            vec![jid!(1, 1005)],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 5, "bin"),
+                    long_path!("/z/sha256", 5, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 1, "bin")),
+            ],
+        );
+    }
+
+    #[test]
+    fn size_weighted_removes_larger_artifacts_of_the_same_kind_first() {
+        let mut fixture = Fixture::new_with_eviction(
+            TestCacheFs::default(),
+            11,
+            eviction_config(EvictionPolicy::SizeWeighted, 100),
+        );
+        fixture.get_artifact_of_kind_ign(jid!(1, 1001), digest!(1), ArtifactKind::File);
+        fixture.got_artifact_ign(digest!(1), 1, short_path!("/z/tmp", 1, "bin"));
+        fixture.decrement_refcount_ign(digest!(1));
+
+        fixture.get_artifact_of_kind_ign(jid!(1, 1002), digest!(2), ArtifactKind::File);
+        fixture.got_artifact_ign(digest!(2), 10, short_path!("/z/tmp", 2, "bin"));
+        fixture.decrement_refcount_ign(digest!(2));
+
+        // The small file became unused first, but the large one is worth less per byte.
+        fixture.get_artifact_of_kind_ign(jid!(1, 1003), digest!(3), ArtifactKind::File);
+        fixture.got_artifact(
+            digest!(3),
+            1,
+            short_path!("/z/tmp", 3, "bin"),
+            vec![jid!(1, 1003)],
+            vec![
+                Rename(
+                    short_path!("/z/tmp", 3, "bin"),
+                    long_path!("/z/sha256", 3, "bin"),
+                ),
+                Remove(long_path!("/z/sha256", 2, "bin")),
+            ],
+        );
+    }
+
     #[test]
     fn tmp_path() {
         let fixture = Fixture::new(TestCacheFs::default(), 0);
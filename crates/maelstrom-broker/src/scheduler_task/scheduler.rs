@@ -1,19 +1,21 @@
 //! Central processing module for the broker. Receives and sends messages to and from clients and
 //! workers.
 
-use crate::scheduler_task::cache::{Cache, CacheFs, GetArtifact, GetArtifactForWorkerError};
+use crate::scheduler_task::cache::{
+    ArtifactKind, Cache, CacheFs, GetArtifact, GetArtifactForWorkerError,
+};
 use anyhow::Result;
 use maelstrom_base::{
     manifest::{ManifestEntryData, ManifestFileData},
     proto::{
-        BrokerToClient, BrokerToMonitor, BrokerToWorker, ClientToBroker, MonitorToBroker,
-        WorkerToBroker,
+        BrokerToClient, BrokerToMonitor, BrokerToWorker, CachedArtifact, ClientToBroker,
+        MonitorToBroker, WorkerToBroker,
     },
     stats::{
-        BrokerStatistics, JobState, JobStateCounts, JobStatisticsSample, JobStatisticsTimeSeries,
-        WorkerStatistics,
+        BrokerStatistics, EvictionStatistics, JobState, JobStateCounts, JobStatisticsSample,
+        JobStatisticsTimeSeries, WorkerStatistics,
     },
-    ArtifactType, ClientId, ClientJobId, JobBrokerStatus, JobId, JobOutcomeResult, JobSpec,
+    ClientId, ClientJobId, JobBrokerStatus, JobId, JobOutcomeResult, JobSpec, JobStdin,
     JobWorkerStatus, MonitorId, Sha256Digest, WorkerId,
 };
 use maelstrom_util::{
@@ -23,10 +25,11 @@ use maelstrom_util::{
     manifest::ManifestReader,
 };
 use std::{
-    cmp::Ordering,
-    collections::{BinaryHeap, HashMap, HashSet},
+    cmp::{Ordering, Reverse},
+    collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque},
     fmt::{self, Debug, Formatter},
-    io,
+    io, mem,
+    net::SocketAddr,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -58,8 +61,24 @@ pub trait SchedulerDeps {
     fn send_message_to_worker_artifact_fetcher(
         &mut self,
         sender: &mut Self::WorkerArtifactFetcherSender,
-        message: Result<(PathBuf, u64), GetArtifactForWorkerError>,
+        message: Result<WorkerArtifactSource, GetArtifactForWorkerError>,
     );
+    /// Start getting the given artifact from the artifact store. This will eventually result in
+    /// either a [`Message::GotArtifact`] or a [`Message::ArtifactNotInStore`].
+    fn fetch_artifact_from_store(&mut self, digest: Sha256Digest);
+}
+
+/// Where a worker's artifact fetcher should get an artifact from. This is the answer to a
+/// [`Message::GetArtifactForWorker`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WorkerArtifactSource {
+    /// The artifact is in our cache at the given path, and has the given size. If we have a
+    /// compressed copy of the artifact, its path is also given. Once the artifact has been sent,
+    /// the refcount needs to be decremented with a [`Message::DecrementRefcount`] message.
+    Cache(PathBuf, u64, Option<PathBuf>),
+
+    /// The artifact can be fetched from the artifact server of a worker at the given address.
+    Peer(SocketAddr),
 }
 
 /// The required interface for the cache that is provided to the [`Scheduler`]. This mirrors the API
@@ -72,10 +91,23 @@ pub trait SchedulerDeps {
 /// [`SchedulerDeps`].
 pub trait SchedulerCache {
     /// See [`super::cache::Cache::get_artifact`].
-    fn get_artifact(&mut self, jid: JobId, digest: Sha256Digest) -> GetArtifact;
+    fn get_artifact(&mut self, jid: JobId, digest: Sha256Digest, kind: ArtifactKind)
+        -> GetArtifact;
+
+    /// See [`super::cache::Cache::expect_artifact_from_client`].
+    fn expect_artifact_from_client(&mut self, cid: ClientId, digest: Sha256Digest) -> bool;
 
     /// See [`super::cache::Cache::got_artifact`].
-    fn got_artifact(&mut self, digest: Sha256Digest, size: u64, path: &Path) -> Vec<JobId>;
+    fn got_artifact(
+        &mut self,
+        digest: Sha256Digest,
+        size: u64,
+        path: &Path,
+        compressed_path: Option<&Path>,
+    ) -> Vec<JobId>;
+
+    /// See [`super::cache::Cache::got_compressed_copy`].
+    fn got_compressed_copy(&mut self, digest: &Sha256Digest, path: &Path);
 
     /// See [`super::cache::Cache::read_manifest`].
     fn read_manifest(
@@ -93,16 +125,50 @@ pub trait SchedulerCache {
     fn get_artifact_for_worker(
         &mut self,
         digest: &Sha256Digest,
-    ) -> Result<(PathBuf, u64), GetArtifactForWorkerError>;
+    ) -> Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError>;
+
+    /// See [`super::cache::Cache::pin_artifact`].
+    fn pin_artifact(&mut self, digest: Sha256Digest);
+
+    /// See [`super::cache::Cache::unpin_artifact`].
+    fn unpin_artifact(&mut self, digest: Sha256Digest);
+
+    /// See [`super::cache::Cache::pinned_artifacts`].
+    fn pinned_artifacts(&self) -> Vec<Sha256Digest>;
+
+    /// See [`super::cache::Cache::cached_artifacts`].
+    fn cached_artifacts(&self) -> Vec<CachedArtifact>;
+
+    /// See [`super::cache::Cache::eviction_statistics`].
+    fn eviction_statistics(&self) -> EvictionStatistics;
 }
 
 impl<FsT: CacheFs> SchedulerCache for Cache<FsT> {
-    fn get_artifact(&mut self, jid: JobId, digest: Sha256Digest) -> GetArtifact {
-        self.get_artifact(jid, digest)
+    fn get_artifact(
+        &mut self,
+        jid: JobId,
+        digest: Sha256Digest,
+        kind: ArtifactKind,
+    ) -> GetArtifact {
+        self.get_artifact(jid, digest, kind)
     }
 
-    fn got_artifact(&mut self, digest: Sha256Digest, size: u64, path: &Path) -> Vec<JobId> {
-        self.got_artifact(digest, size, path)
+    fn expect_artifact_from_client(&mut self, cid: ClientId, digest: Sha256Digest) -> bool {
+        self.expect_artifact_from_client(cid, digest)
+    }
+
+    fn got_artifact(
+        &mut self,
+        digest: Sha256Digest,
+        size: u64,
+        path: &Path,
+        compressed_path: Option<&Path>,
+    ) -> Vec<JobId> {
+        self.got_artifact(digest, size, path, compressed_path)
+    }
+
+    fn got_compressed_copy(&mut self, digest: &Sha256Digest, path: &Path) {
+        self.got_compressed_copy(digest, path)
     }
 
     fn read_manifest(
@@ -123,9 +189,29 @@ impl<FsT: CacheFs> SchedulerCache for Cache<FsT> {
     fn get_artifact_for_worker(
         &mut self,
         digest: &Sha256Digest,
-    ) -> Result<(PathBuf, u64), GetArtifactForWorkerError> {
+    ) -> Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError> {
         self.get_artifact_for_worker(digest)
     }
+
+    fn pin_artifact(&mut self, digest: Sha256Digest) {
+        self.pin_artifact(digest)
+    }
+
+    fn unpin_artifact(&mut self, digest: Sha256Digest) {
+        self.unpin_artifact(digest)
+    }
+
+    fn pinned_artifacts(&self) -> Vec<Sha256Digest> {
+        self.pinned_artifacts()
+    }
+
+    fn cached_artifacts(&self) -> Vec<CachedArtifact> {
+        self.cached_artifacts()
+    }
+
+    fn eviction_statistics(&self) -> EvictionStatistics {
+        self.eviction_statistics()
+    }
 }
 
 /// The incoming messages, or events, for [`Scheduler`].
@@ -142,8 +228,9 @@ pub enum Message<DepsT: SchedulerDeps> {
     FromClient(ClientId, ClientToBroker),
 
     /// The given worker connected. It has the given number of slots and messages can be sent to it
-    /// on the given sender.
-    WorkerConnected(WorkerId, usize, DepsT::WorkerSender),
+    /// on the given sender. If it serves artifacts to other workers, its artifact server is at the
+    /// given address.
+    WorkerConnected(WorkerId, usize, Option<SocketAddr>, DepsT::WorkerSender),
 
     /// The given worker disconnected.
     WorkerDisconnected(WorkerId),
@@ -161,13 +248,18 @@ pub enum Message<DepsT: SchedulerDeps> {
     FromMonitor(MonitorId, MonitorToBroker),
 
     /// An artifact has been pushed to us. The artifact has the given digest and length. It is
-    /// temporarily stored at the given path.
-    GotArtifact(Sha256Digest, u64, PathBuf),
+    /// temporarily stored at the given path. If we're keeping compressed copies of artifacts, the
+    /// path of the compressed copy is also given.
+    GotArtifact(Sha256Digest, u64, PathBuf, Option<PathBuf>),
+
+    /// An artifact fetcher compressed the given artifact while sending it to a worker. The
+    /// compressed copy is temporarily stored at the given path.
+    GotCompressedCopy(Sha256Digest, PathBuf),
 
-    /// A worker has requested the given artifact be sent to it over the given sender. After the
-    /// contents are sent to the worker, the refcount needs to be decremented with a
-    /// [`Message::DecrementRefcount`] message.
-    GetArtifactForWorker(Sha256Digest, DepsT::WorkerArtifactFetcherSender),
+    /// A worker has requested the given artifact be sent to it over the given sender. If the
+    /// boolean is true, we may instead tell the worker to get the artifact from a peer that has
+    /// it. See [`WorkerArtifactSource`].
+    GetArtifactForWorker(Sha256Digest, bool, DepsT::WorkerArtifactFetcherSender),
 
     /// A worker has been sent an artifact, and we can now release the refcount that was keeping
     /// the artifact from being removed while being transferred.
@@ -175,6 +267,10 @@ pub enum Message<DepsT: SchedulerDeps> {
 
     /// The stats heartbeat task has decided it's time to take another statistics sample.
     StatisticsHeartbeat,
+
+    /// The artifact store couldn't provide the given artifact, so it has to be pushed by a
+    /// client. See [`SchedulerDeps::fetch_artifact_from_store`].
+    ArtifactNotInStore(Sha256Digest),
 }
 
 impl<DepsT: SchedulerDeps> Debug for Message<DepsT> {
@@ -189,10 +285,11 @@ impl<DepsT: SchedulerDeps> Debug for Message<DepsT> {
             Message::FromClient(cid, msg) => {
                 f.debug_tuple("FromClient").field(cid).field(msg).finish()
             }
-            Message::WorkerConnected(wid, slots, _sender) => f
+            Message::WorkerConnected(wid, slots, artifact_server, _sender) => f
                 .debug_tuple("WorkerConnected")
                 .field(wid)
                 .field(slots)
+                .field(artifact_server)
                 .finish(),
             Message::WorkerDisconnected(wid) => {
                 f.debug_tuple("WorkerDisconnected").field(wid).finish()
@@ -209,19 +306,30 @@ impl<DepsT: SchedulerDeps> Debug for Message<DepsT> {
             Message::FromMonitor(mid, msg) => {
                 f.debug_tuple("FromMonitor").field(mid).field(msg).finish()
             }
-            Message::GotArtifact(digest, size, path) => f
+            Message::GotArtifact(digest, size, path, compressed_path) => f
                 .debug_tuple("GotArtifact")
                 .field(digest)
                 .field(size)
                 .field(path)
+                .field(compressed_path)
+                .finish(),
+            Message::GotCompressedCopy(digest, path) => f
+                .debug_tuple("GotCompressedCopy")
+                .field(digest)
+                .field(path)
+                .finish(),
+            Message::GetArtifactForWorker(digest, allow_peer, _sender) => f
+                .debug_tuple("GetArtifactForWorker")
+                .field(digest)
+                .field(allow_peer)
                 .finish(),
-            Message::GetArtifactForWorker(digest, _sender) => {
-                f.debug_tuple("GetArtifactForWorker").field(digest).finish()
-            }
             Message::DecrementRefcount(digest) => {
                 f.debug_tuple("DecrementRefcount").field(digest).finish()
             }
             Message::StatisticsHeartbeat => f.debug_tuple("StatisticsHeartbeat").finish(),
+            Message::ArtifactNotInStore(digest) => {
+                f.debug_tuple("ArtifactNotInStore").field(digest).finish()
+            }
         }
     }
 }
@@ -229,15 +337,26 @@ impl<DepsT: SchedulerDeps> Debug for Message<DepsT> {
 impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
     /// Create a new scheduler with the given [`SchedulerCache`]. Note that [`SchedulerDeps`] are
     /// passed in to `Self::receive_message`.
-    pub fn new(cache: CacheT) -> Self {
+    ///
+    /// Each worker will be asked to prefetch the layers of up to `prefetch_hints_per_slot` queued
+    /// jobs per slot. Zero disables prefetch hints.
+    ///
+    /// If `use_artifact_store` is true, artifacts that aren't in the cache are looked for in the
+    /// artifact store before clients are asked to push them.
+    pub fn new(cache: CacheT, prefetch_hints_per_slot: usize, use_artifact_store: bool) -> Self {
         Scheduler {
             cache,
             clients: ClientMap(HashMap::default()),
             workers: WorkerMap(HashMap::default()),
             monitors: HashMap::default(),
             queued_jobs: BinaryHeap::default(),
+            waiting_for_hint: BTreeSet::default(),
             worker_heap: Heap::default(),
             job_statistics: JobStatisticsTimeSeries::default(),
+            prefetch_hints_per_slot,
+            artifact_holders: HashMap::default(),
+            use_artifact_store,
+            store_fetches: HashMap::default(),
         }
     }
 
@@ -250,8 +369,11 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
             Message::FromClient(cid, ClientToBroker::JobRequest(cjid, spec)) => {
                 self.receive_client_job_request(deps, cid, cjid, spec)
             }
-            Message::WorkerConnected(id, slots, sender) => {
-                self.receive_worker_connected(deps, id, slots, sender)
+            Message::FromClient(cid, ClientToBroker::ArtifactsQuery(digests)) => {
+                self.receive_client_artifacts_query(deps, cid, digests)
+            }
+            Message::WorkerConnected(id, slots, artifact_server, sender) => {
+                self.receive_worker_connected(deps, id, slots, artifact_server, sender)
             }
             Message::WorkerDisconnected(id) => self.receive_worker_disconnected(deps, id),
             Message::FromWorker(wid, WorkerToBroker::JobResponse(jid, result)) => {
@@ -260,19 +382,35 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
             Message::FromWorker(wid, WorkerToBroker::JobStatusUpdate(jid, status)) => {
                 self.receive_worker_job_status_update(deps, wid, jid, status)
             }
+            Message::FromWorker(wid, WorkerToBroker::ArtifactCached(digest)) => {
+                self.receive_worker_artifact_cached(wid, digest)
+            }
             Message::MonitorConnected(id, sender) => self.receive_monitor_connected(id, sender),
             Message::MonitorDisconnected(id) => self.receive_monitor_disconnected(id),
             Message::FromMonitor(mid, MonitorToBroker::StatisticsRequest) => {
                 self.receive_monitor_statistics_request(deps, mid)
             }
-            Message::GotArtifact(digest, size, path) => {
-                self.receive_got_artifact(deps, digest, size, path)
+            Message::FromMonitor(mid, MonitorToBroker::CacheContentsRequest) => {
+                self.receive_monitor_cache_contents_request(deps, mid)
+            }
+            Message::FromMonitor(mid, MonitorToBroker::PinArtifacts(digests)) => {
+                self.receive_monitor_pin_artifacts(deps, mid, digests)
+            }
+            Message::FromMonitor(mid, MonitorToBroker::UnpinArtifacts(digests)) => {
+                self.receive_monitor_unpin_artifacts(deps, mid, digests)
+            }
+            Message::GotArtifact(digest, size, path, compressed_path) => {
+                self.receive_got_artifact(deps, digest, size, path, compressed_path)
+            }
+            Message::GotCompressedCopy(digest, path) => {
+                self.cache.got_compressed_copy(&digest, &path)
             }
-            Message::GetArtifactForWorker(digest, sender) => {
-                self.receive_get_artifact_for_worker(deps, digest, sender)
+            Message::GetArtifactForWorker(digest, allow_peer, sender) => {
+                self.receive_get_artifact_for_worker(deps, digest, allow_peer, sender)
             }
             Message::DecrementRefcount(digest) => self.receive_decrement_refcount(digest),
             Message::StatisticsHeartbeat => self.receive_statistics_heartbeat(),
+            Message::ArtifactNotInStore(digest) => self.receive_artifact_not_in_store(deps, digest),
         }
     }
 }
@@ -286,40 +424,33 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
  *  FIGLET: private
  */
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum IsManifest {
-    Manifest,
-    NotManifest,
-}
+/// Whether a worker has been asked to prefetch a job's layers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PrefetchHint {
+    /// The job isn't queued, so there's no point in hinting it.
+    NotNeeded,
 
-impl From<bool> for IsManifest {
-    fn from(is_manifest: bool) -> Self {
-        if is_manifest {
-            Self::Manifest
-        } else {
-            Self::NotManifest
-        }
-    }
-}
+    /// The job is queued and is waiting for a worker with room for another hint.
+    Waiting,
 
-impl IsManifest {
-    fn is_manifest(&self) -> bool {
-        self == &Self::Manifest
-    }
+    /// The job is queued, and the given worker has been asked to prefetch its layers.
+    SentTo(WorkerId),
 }
 
 struct Job {
-    spec: JobSpec,
+    spec: Box<JobSpec>,
     acquired_artifacts: HashSet<Sha256Digest>,
-    missing_artifacts: HashMap<Sha256Digest, IsManifest>,
+    missing_artifacts: HashMap<Sha256Digest, ArtifactKind>,
+    prefetch_hint: PrefetchHint,
 }
 
 impl Job {
-    fn new(spec: JobSpec) -> Self {
+    fn new(spec: Box<JobSpec>) -> Self {
         Job {
             spec,
             acquired_artifacts: Default::default(),
             missing_artifacts: Default::default(),
+            prefetch_hint: PrefetchHint::NotNeeded,
         }
     }
 }
@@ -346,22 +477,41 @@ impl<DepsT: SchedulerDeps> ClientMap<DepsT> {
     fn job_from_jid(&self, jid: JobId) -> &Job {
         self.0.get(&jid.cid).unwrap().jobs.get(&jid.cjid).unwrap()
     }
+
+    fn job_from_jid_mut(&mut self, jid: JobId) -> &mut Job {
+        self.0
+            .get_mut(&jid.cid)
+            .unwrap()
+            .jobs
+            .get_mut(&jid.cjid)
+            .unwrap()
+    }
 }
 
 struct Worker<DepsT: SchedulerDeps> {
     slots: usize,
     pending: HashSet<JobId>,
+    /// Queued jobs that this worker has been asked to prefetch the layers of.
+    hinted: HashSet<JobId>,
     heap_index: HeapIndex,
     sender: DepsT::WorkerSender,
+    /// Where the worker serves artifacts to other workers, if it does.
+    artifact_server: Option<SocketAddr>,
+    /// The artifacts the worker has told us it has fetched. It may have since evicted some of
+    /// them from its cache. This is only populated if the worker has an artifact server.
+    artifacts: HashSet<Sha256Digest>,
 }
 
 impl<DepsT: SchedulerDeps> Worker<DepsT> {
-    fn new(slots: usize, sender: DepsT::WorkerSender) -> Self {
+    fn new(slots: usize, artifact_server: Option<SocketAddr>, sender: DepsT::WorkerSender) -> Self {
         Worker {
             slots,
             sender,
             pending: HashSet::default(),
+            hinted: HashSet::default(),
             heap_index: HeapIndex::default(),
+            artifact_server,
+            artifacts: HashSet::default(),
         }
     }
 }
@@ -384,6 +534,7 @@ impl<DepsT: SchedulerDeps> HeapDeps for WorkerMap<DepsT> {
     }
 }
 
+#[derive(Clone, Copy)]
 struct QueuedJob {
     jid: JobId,
     priority: i8,
@@ -428,21 +579,34 @@ pub struct Scheduler<CacheT, DepsT: SchedulerDeps> {
     workers: WorkerMap<DepsT>,
     monitors: HashMap<MonitorId, DepsT::MonitorSender>,
     queued_jobs: BinaryHeap<QueuedJob>,
+    /// The queued jobs whose prefetch hint is [`PrefetchHint::Waiting`], in the order they will
+    /// be sent to workers.
+    waiting_for_hint: BTreeSet<(Reverse<QueuedJob>, JobId)>,
     worker_heap: Heap<WorkerMap<DepsT>>,
     job_statistics: JobStatisticsTimeSeries,
+    prefetch_hints_per_slot: usize,
+    /// For each artifact, the workers with artifact servers that have told us they have it. We
+    /// rotate through them so that no single worker serves all of the requests for an artifact.
+    artifact_holders: HashMap<Sha256Digest, VecDeque<WorkerId>>,
+    use_artifact_store: bool,
+    /// For each artifact being fetched from the artifact store, the clients to ask for it if the
+    /// store doesn't have it.
+    store_fetches: HashMap<Sha256Digest, HashSet<ClientId>>,
 }
 
 impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
     fn possibly_start_jobs(&mut self, deps: &mut DepsT, mut just_enqueued: HashSet<JobId>) {
         while !self.queued_jobs.is_empty() && !self.workers.0.is_empty() {
-            let wid = self.worker_heap.peek().unwrap();
-            let worker = self.workers.0.get_mut(wid).unwrap();
+            let wid = *self.worker_heap.peek().unwrap();
+            let worker = self.workers.0.get(&wid).unwrap();
 
             if worker.pending.len() == 2 * worker.slots {
                 break;
             }
 
             let jid = self.queued_jobs.pop().unwrap().jid;
+            self.retract_prefetch_hint(deps, jid, wid);
+            let worker = self.workers.0.get_mut(&wid).unwrap();
             let job = self.clients.job_from_jid(jid);
             deps.send_message_to_worker(
                 &mut worker.sender,
@@ -460,6 +624,84 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
                 &mut client.sender,
                 BrokerToClient::JobStatusUpdate(jid.cjid, JobBrokerStatus::WaitingForWorker),
             );
+            if self.prefetch_hints_per_slot > 0 {
+                let job = client.jobs.get_mut(&jid.cjid).unwrap();
+                job.prefetch_hint = PrefetchHint::Waiting;
+                self.waiting_for_hint
+                    .insert(Self::hint_key(jid, job))
+                    .assert_is_true();
+            }
+        }
+        self.send_prefetch_hints(deps);
+    }
+
+    fn hint_key(jid: JobId, job: &Job) -> (Reverse<QueuedJob>, JobId) {
+        let queued = QueuedJob::new(jid, job.spec.priority, job.spec.estimated_duration);
+        (Reverse(queued), jid)
+    }
+
+    /// Ask workers to prefetch the layers of queued jobs, in the order the jobs will be sent to
+    /// workers: by priority, then by estimated duration. Each worker is given up to
+    /// `prefetch_hints_per_slot` hints per slot. Jobs are hinted to the worker with the fewest
+    /// hints relative to its slot count, since we can't know which worker will free up first.
+    fn send_prefetch_hints(&mut self, deps: &mut DepsT) {
+        let room = |worker: &Worker<DepsT>| {
+            (worker.slots * self.prefetch_hints_per_slot).saturating_sub(worker.hinted.len())
+        };
+        let has_room = |worker: &Worker<DepsT>| room(worker) > 0;
+        let total_room: usize = self.workers.0.values().map(room).sum();
+        let candidates: Vec<_> = self
+            .waiting_for_hint
+            .iter()
+            .take(total_room)
+            .copied()
+            .collect();
+
+        for key @ (_, jid) in candidates {
+            let Some(wid) = self
+                .workers
+                .0
+                .iter()
+                .filter(|(_, worker)| has_room(worker))
+                .min_by(|(lhs_id, lhs), (rhs_id, rhs)| {
+                    (lhs.hinted.len() * rhs.slots, *lhs_id)
+                        .cmp(&(rhs.hinted.len() * lhs.slots, *rhs_id))
+                })
+                .map(|(wid, _)| *wid)
+            else {
+                break;
+            };
+
+            self.waiting_for_hint.remove(&key).assert_is_true();
+            let job = self.clients.job_from_jid_mut(jid);
+            job.prefetch_hint = PrefetchHint::SentTo(wid);
+            let worker = self.workers.0.get_mut(&wid).unwrap();
+            worker.hinted.insert(jid).assert_is_true();
+            deps.send_message_to_worker(
+                &mut worker.sender,
+                BrokerToWorker::Prefetch(jid, job.spec.clone()),
+            );
+        }
+    }
+
+    /// The job is about to be sent to the given worker. If a different worker was asked to
+    /// prefetch its layers, tell that worker it won't be getting it after all.
+    fn retract_prefetch_hint(&mut self, deps: &mut DepsT, jid: JobId, wid: WorkerId) {
+        let job = self.clients.job_from_jid_mut(jid);
+        let hint = mem::replace(&mut job.prefetch_hint, PrefetchHint::NotNeeded);
+        if hint == PrefetchHint::Waiting {
+            let key = Self::hint_key(jid, job);
+            self.waiting_for_hint.remove(&key).assert_is_true();
+        }
+        if let PrefetchHint::SentTo(hinted_wid) = hint {
+            let hinted_worker = self.workers.0.get_mut(&hinted_wid).unwrap();
+            hinted_worker.hinted.remove(&jid).assert_is_true();
+            if hinted_wid != wid {
+                deps.send_message_to_worker(
+                    &mut hinted_worker.sender,
+                    BrokerToWorker::CancelJob(jid),
+                );
+            }
         }
     }
 
@@ -481,6 +723,7 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         }
 
         self.queued_jobs.retain(|qj| qj.jid.cid != id);
+        self.waiting_for_hint.retain(|(_, jid)| jid.cid != id);
         for worker in self.workers.0.values_mut() {
             worker.pending.retain(|jid| {
                 jid.cid != id || {
@@ -491,6 +734,15 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
                     false
                 }
             });
+            worker.hinted.retain(|jid| {
+                jid.cid != id || {
+                    deps.send_message_to_worker(
+                        &mut worker.sender,
+                        BrokerToWorker::CancelJob(*jid),
+                    );
+                    false
+                }
+            });
         }
         self.worker_heap.rebuild(&mut self.workers);
         self.possibly_start_jobs(deps, HashSet::default());
@@ -501,57 +753,78 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         deps: &mut DepsT,
         digest: Sha256Digest,
         jid: JobId,
-        is_manifest: IsManifest,
+        kind: ArtifactKind,
     ) {
         let client = self.clients.0.get_mut(&jid.cid).unwrap();
         let job = client.jobs.get_mut(&jid.cjid).unwrap();
         if job.acquired_artifacts.contains(&digest) || job.missing_artifacts.contains_key(&digest) {
             return;
         }
-        match self.cache.get_artifact(jid, digest.clone()) {
+        match self.cache.get_artifact(jid, digest.clone(), kind) {
             GetArtifact::Success => {
                 job.acquired_artifacts
                     .insert(digest.clone())
                     .assert_is_true();
-                if is_manifest.is_manifest() {
+                if kind == ArtifactKind::Manifest {
                     self.ensure_manifest_artifacts_for_job(deps, jid, digest)
                         .unwrap();
                 }
             }
             GetArtifact::Wait => {
-                job.missing_artifacts
-                    .insert(digest, is_manifest)
-                    .assert_is_none();
+                job.missing_artifacts.insert(digest, kind).assert_is_none();
             }
             GetArtifact::Get => {
                 job.missing_artifacts
-                    .insert(digest.clone(), is_manifest)
+                    .insert(digest.clone(), kind)
                     .assert_is_none();
-                deps.send_message_to_client(
-                    &mut client.sender,
-                    BrokerToClient::TransferArtifact(digest),
-                );
+                if self.use_artifact_store {
+                    self.fetch_artifact_from_store(deps, digest, jid.cid);
+                } else {
+                    deps.send_message_to_client(
+                        &mut client.sender,
+                        BrokerToClient::TransferArtifact(digest),
+                    );
+                }
             }
         }
     }
 
+    /// Look for an artifact in the artifact store. If the store doesn't have it, `cid` will be
+    /// asked to push it.
+    fn fetch_artifact_from_store(&mut self, deps: &mut DepsT, digest: Sha256Digest, cid: ClientId) {
+        let clients = self.store_fetches.entry(digest.clone()).or_default();
+        if clients.is_empty() {
+            deps.fetch_artifact_from_store(digest);
+        }
+        clients.insert(cid);
+    }
+
     fn receive_client_job_request(
         &mut self,
         deps: &mut DepsT,
         cid: ClientId,
         cjid: ClientJobId,
-        spec: JobSpec,
+        spec: Box<JobSpec>,
     ) {
         let jid = JobId { cid, cjid };
         let client = self.clients.0.get_mut(&cid).unwrap();
-        let layers = spec.layers.clone();
+        let layers: Vec<_> = spec
+            .layers
+            .iter()
+            .chain(spec.sidecars.iter().flat_map(|sidecar| &sidecar.layers))
+            .chain(spec.layer_mounts().flatten())
+            .cloned()
+            .collect();
+        let stdin = spec.stdin.clone();
         let priority = spec.priority;
         let estimated_duration = spec.estimated_duration;
         client.jobs.insert(cjid, Job::new(spec)).assert_is_none();
 
         for (digest, type_) in layers {
-            let is_manifest = IsManifest::from(type_ == ArtifactType::Manifest);
-            self.ensure_artifact_for_job(deps, digest, jid, is_manifest);
+            self.ensure_artifact_for_job(deps, digest, jid, type_.into());
+        }
+        if let Some(JobStdin::Artifact(digest)) = stdin {
+            self.ensure_artifact_for_job(deps, digest, jid, ArtifactKind::File);
         }
 
         let client = self.clients.0.get_mut(&jid.cid).unwrap();
@@ -569,16 +842,43 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         }
     }
 
+    fn receive_client_artifacts_query(
+        &mut self,
+        deps: &mut DepsT,
+        cid: ClientId,
+        digests: Vec<Sha256Digest>,
+    ) {
+        // If there is an artifact store, the client is only asked to push the artifacts the store
+        // doesn't have either. It is asked for those with individual transfer requests.
+        let mut missing = vec![];
+        for digest in digests {
+            if !self.cache.expect_artifact_from_client(cid, digest.clone()) {
+                continue;
+            }
+            if self.use_artifact_store {
+                self.fetch_artifact_from_store(deps, digest, cid);
+            } else {
+                missing.push(digest);
+            }
+        }
+        let client = self.clients.0.get_mut(&cid).unwrap();
+        deps.send_message_to_client(
+            &mut client.sender,
+            BrokerToClient::MissingArtifacts(missing),
+        );
+    }
+
     fn receive_worker_connected(
         &mut self,
         deps: &mut DepsT,
         id: WorkerId,
         slots: usize,
+        artifact_server: Option<SocketAddr>,
         sender: DepsT::WorkerSender,
     ) {
         self.workers
             .0
-            .insert(id, Worker::new(slots, sender))
+            .insert(id, Worker::new(slots, artifact_server, sender))
             .assert_is_none();
         self.worker_heap.push(&mut self.workers, id);
         self.possibly_start_jobs(deps, HashSet::default());
@@ -589,6 +889,23 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         self.worker_heap
             .remove(&mut self.workers, worker.heap_index);
 
+        for digest in worker.artifacts.drain() {
+            let holders = self.artifact_holders.get_mut(&digest).unwrap();
+            holders.retain(|&wid| wid != id);
+            if holders.is_empty() {
+                self.artifact_holders.remove(&digest);
+            }
+        }
+
+        // The jobs hinted to this worker are still queued. They can be hinted to another worker.
+        for jid in worker.hinted.drain() {
+            let job = self.clients.job_from_jid_mut(jid);
+            job.prefetch_hint = PrefetchHint::Waiting;
+            self.waiting_for_hint
+                .insert(Self::hint_key(jid, job))
+                .assert_is_true();
+        }
+
         let mut just_enqueued = HashSet::new();
         for jid in worker.pending.drain() {
             let job = self.clients.job_from_jid(jid);
@@ -608,7 +925,7 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         deps: &mut DepsT,
         wid: WorkerId,
         jid: JobId,
-        result: JobOutcomeResult,
+        result: Box<JobOutcomeResult>,
     ) {
         let worker = self.workers.0.get_mut(&wid).unwrap();
 
@@ -634,12 +951,15 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
             // If there are any queued_requests, we can just pop one off of the front of
             // the queue and not have to update the worker's used slot count or position in the
             // workers list.
+            self.retract_prefetch_hint(deps, jid, wid);
+            let worker = self.workers.0.get_mut(&wid).unwrap();
             let job = self.clients.job_from_jid(jid);
             deps.send_message_to_worker(
                 &mut worker.sender,
                 BrokerToWorker::EnqueueJob(jid, job.spec.clone()),
             );
             worker.pending.insert(jid);
+            self.send_prefetch_hints(deps);
         } else {
             // Since there are no queued_requests, we're going to have to update the
             // worker's position in the workers list.
@@ -666,6 +986,16 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         );
     }
 
+    fn receive_worker_artifact_cached(&mut self, wid: WorkerId, digest: Sha256Digest) {
+        let worker = self.workers.0.get_mut(&wid).unwrap();
+        if worker.artifact_server.is_some() && worker.artifacts.insert(digest.clone()) {
+            self.artifact_holders
+                .entry(digest)
+                .or_default()
+                .push_back(wid);
+        }
+    }
+
     fn receive_monitor_connected(&mut self, id: MonitorId, sender: DepsT::MonitorSender) {
         self.monitors.insert(id, sender).assert_is_none();
     }
@@ -681,10 +1011,42 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
                 .map(|(id, w)| (*id, WorkerStatistics { slots: w.slots }))
                 .collect(),
             job_statistics: self.job_statistics.clone(),
+            eviction_statistics: self.cache.eviction_statistics(),
         });
         deps.send_message_to_monitor(self.monitors.get_mut(&mid).unwrap(), resp);
     }
 
+    fn receive_monitor_cache_contents_request(&mut self, deps: &mut DepsT, mid: MonitorId) {
+        let resp = BrokerToMonitor::CacheContentsResponse(self.cache.cached_artifacts());
+        deps.send_message_to_monitor(self.monitors.get_mut(&mid).unwrap(), resp);
+    }
+
+    fn receive_monitor_pin_artifacts(
+        &mut self,
+        deps: &mut DepsT,
+        mid: MonitorId,
+        digests: Vec<Sha256Digest>,
+    ) {
+        for digest in digests {
+            self.cache.pin_artifact(digest);
+        }
+        let resp = BrokerToMonitor::PinnedArtifacts(self.cache.pinned_artifacts());
+        deps.send_message_to_monitor(self.monitors.get_mut(&mid).unwrap(), resp);
+    }
+
+    fn receive_monitor_unpin_artifacts(
+        &mut self,
+        deps: &mut DepsT,
+        mid: MonitorId,
+        digests: Vec<Sha256Digest>,
+    ) {
+        for digest in digests {
+            self.cache.unpin_artifact(digest);
+        }
+        let resp = BrokerToMonitor::PinnedArtifacts(self.cache.pinned_artifacts());
+        deps.send_message_to_monitor(self.monitors.get_mut(&mid).unwrap(), resp);
+    }
+
     fn ensure_manifest_artifacts_for_job(
         &mut self,
         deps: &mut DepsT,
@@ -694,7 +1056,7 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         for entry in self.cache.read_manifest(digest)? {
             let entry = entry?;
             if let ManifestEntryData::File(ManifestFileData::Digest(digest)) = entry.data {
-                self.ensure_artifact_for_job(deps, digest, jid, IsManifest::NotManifest);
+                self.ensure_artifact_for_job(deps, digest, jid, ArtifactKind::File);
             }
         }
         Ok(())
@@ -706,17 +1068,22 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         digest: Sha256Digest,
         size: u64,
         path: PathBuf,
+        compressed_path: Option<PathBuf>,
     ) {
+        self.store_fetches.remove(&digest);
         let mut just_enqueued = HashSet::default();
-        for jid in self.cache.got_artifact(digest.clone(), size, &path) {
+        for jid in self
+            .cache
+            .got_artifact(digest.clone(), size, &path, compressed_path.as_deref())
+        {
             let client = self.clients.0.get_mut(&jid.cid).unwrap();
             let job = client.jobs.get_mut(&jid.cjid).unwrap();
             job.acquired_artifacts
                 .insert(digest.clone())
                 .assert_is_true();
-            let is_manifest = job.missing_artifacts.remove(&digest.clone()).unwrap();
+            let kind = job.missing_artifacts.remove(&digest.clone()).unwrap();
 
-            if is_manifest.is_manifest() {
+            if kind == ArtifactKind::Manifest {
                 self.ensure_manifest_artifacts_for_job(deps, jid, digest.clone())
                     .unwrap();
             }
@@ -738,14 +1105,45 @@ impl<CacheT: SchedulerCache, DepsT: SchedulerDeps> Scheduler<CacheT, DepsT> {
         &mut self,
         deps: &mut DepsT,
         digest: Sha256Digest,
+        allow_peer: bool,
         mut sender: DepsT::WorkerArtifactFetcherSender,
     ) {
+        if allow_peer {
+            if let Some(holders) = self.artifact_holders.get_mut(&digest) {
+                // The worker may have evicted the artifact since it told us about it. If so, the
+                // fetcher will come back to us.
+                let wid = holders.pop_front().unwrap();
+                holders.push_back(wid);
+                let artifact_server = self.workers.0.get(&wid).unwrap().artifact_server.unwrap();
+                deps.send_message_to_worker_artifact_fetcher(
+                    &mut sender,
+                    Ok(WorkerArtifactSource::Peer(artifact_server)),
+                );
+                return;
+            }
+        }
         deps.send_message_to_worker_artifact_fetcher(
             &mut sender,
-            self.cache.get_artifact_for_worker(&digest),
+            self.cache
+                .get_artifact_for_worker(&digest)
+                .map(|(path, size, compressed_path)| {
+                    WorkerArtifactSource::Cache(path, size, compressed_path)
+                }),
         );
     }
 
+    fn receive_artifact_not_in_store(&mut self, deps: &mut DepsT, digest: Sha256Digest) {
+        for cid in self.store_fetches.remove(&digest).into_iter().flatten() {
+            // The client may have disconnected while we were looking in the store.
+            if let Some(client) = self.clients.0.get_mut(&cid) {
+                deps.send_message_to_client(
+                    &mut client.sender,
+                    BrokerToClient::TransferArtifact(digest.clone()),
+                );
+            }
+        }
+    }
+
     fn receive_decrement_refcount(&mut self, digest: Sha256Digest) {
         self.cache.decrement_refcount(digest);
     }
@@ -807,7 +1205,9 @@ mod tests {
     use itertools::Itertools;
     use maelstrom_base::{
         manifest::{ManifestEntry, ManifestEntryMetadata, Mode, UnixTimestamp},
+        nonempty,
         proto::BrokerToWorker::{self, *},
+        ArtifactType, JobMount, JobSidecar,
     };
     use maelstrom_test::*;
     use maelstrom_util::manifest::ManifestWriter;
@@ -819,12 +1219,20 @@ mod tests {
         ToClient(ClientId, BrokerToClient),
         ToWorker(WorkerId, BrokerToWorker),
         ToMonitor(MonitorId, BrokerToMonitor),
-        ToWorkerArtifactFetcher(u32, Result<(PathBuf, u64), GetArtifactForWorkerError>),
-        CacheGetArtifact(JobId, Sha256Digest),
-        CacheGotArtifact(Sha256Digest, u64, PathBuf),
+        ToWorkerArtifactFetcher(u32, Result<WorkerArtifactSource, GetArtifactForWorkerError>),
+        CacheGetArtifact(JobId, Sha256Digest, ArtifactKind),
+        CacheExpectArtifactFromClient(ClientId, Sha256Digest),
+        CacheGotArtifact(Sha256Digest, u64, PathBuf, Option<PathBuf>),
+        CacheGotCompressedCopy(Sha256Digest, PathBuf),
         CacheDecrementRefcount(Sha256Digest),
         CacheClientDisconnected(ClientId),
         CacheGetArtifactForWorker(Sha256Digest),
+        CachePinArtifact(Sha256Digest),
+        CacheUnpinArtifact(Sha256Digest),
+        CachePinnedArtifacts,
+        CacheCachedArtifacts,
+        CacheEvictionStatistics,
+        FetchArtifactFromStore(Sha256Digest),
     }
 
     use TestMessage::*;
@@ -838,29 +1246,57 @@ mod tests {
     struct TestState {
         messages: Vec<TestMessage>,
         get_artifact_returns: HashMap<(JobId, Sha256Digest), Vec<GetArtifact>>,
+        expect_artifact_from_client_returns: HashMap<(ClientId, Sha256Digest), bool>,
         got_artifact_returns: HashMap<Sha256Digest, Vec<Vec<JobId>>>,
         #[allow(clippy::type_complexity)]
-        get_artifact_for_worker_returns:
-            HashMap<Sha256Digest, Vec<Result<(PathBuf, u64), GetArtifactForWorkerError>>>,
+        get_artifact_for_worker_returns: HashMap<
+            Sha256Digest,
+            Vec<Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError>>,
+        >,
         read_manifest_returns: HashMap<Sha256Digest, Vec<ManifestEntry>>,
+        pinned_artifacts_returns: Vec<Sha256Digest>,
+        cached_artifacts_returns: Vec<CachedArtifact>,
+        eviction_statistics_returns: EvictionStatistics,
     }
 
     impl SchedulerCache for Rc<RefCell<TestState>> {
-        fn get_artifact(&mut self, jid: JobId, digest: Sha256Digest) -> GetArtifact {
+        fn get_artifact(
+            &mut self,
+            jid: JobId,
+            digest: Sha256Digest,
+            kind: ArtifactKind,
+        ) -> GetArtifact {
             self.borrow_mut()
                 .messages
-                .push(CacheGetArtifact(jid, digest.clone()));
+                .push(CacheGetArtifact(jid, digest.clone(), kind));
             self.borrow_mut()
                 .get_artifact_returns
                 .get_mut(&(jid, digest))
                 .unwrap()
                 .remove(0)
         }
-        fn got_artifact(&mut self, digest: Sha256Digest, size: u64, path: &Path) -> Vec<JobId> {
+        fn expect_artifact_from_client(&mut self, cid: ClientId, digest: Sha256Digest) -> bool {
+            self.borrow_mut()
+                .messages
+                .push(CacheExpectArtifactFromClient(cid, digest.clone()));
+            *self
+                .borrow_mut()
+                .expect_artifact_from_client_returns
+                .get(&(cid, digest))
+                .unwrap()
+        }
+        fn got_artifact(
+            &mut self,
+            digest: Sha256Digest,
+            size: u64,
+            path: &Path,
+            compressed_path: Option<&Path>,
+        ) -> Vec<JobId> {
             self.borrow_mut().messages.push(CacheGotArtifact(
                 digest.clone(),
                 size,
                 path.to_owned(),
+                compressed_path.map(Path::to_owned),
             ));
             self.borrow_mut()
                 .got_artifact_returns
@@ -868,6 +1304,13 @@ mod tests {
                 .unwrap()
                 .remove(0)
         }
+
+        fn got_compressed_copy(&mut self, digest: &Sha256Digest, path: &Path) {
+            self.borrow_mut()
+                .messages
+                .push(CacheGotCompressedCopy(digest.clone(), path.to_owned()));
+        }
+
         fn read_manifest(
             &mut self,
             digest: Sha256Digest,
@@ -897,7 +1340,7 @@ mod tests {
         fn get_artifact_for_worker(
             &mut self,
             digest: &Sha256Digest,
-        ) -> Result<(PathBuf, u64), GetArtifactForWorkerError> {
+        ) -> Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError> {
             self.borrow_mut()
                 .messages
                 .push(CacheGetArtifactForWorker(digest.clone()));
@@ -907,6 +1350,24 @@ mod tests {
                 .unwrap()
                 .remove(0)
         }
+        fn pin_artifact(&mut self, digest: Sha256Digest) {
+            self.borrow_mut().messages.push(CachePinArtifact(digest));
+        }
+        fn unpin_artifact(&mut self, digest: Sha256Digest) {
+            self.borrow_mut().messages.push(CacheUnpinArtifact(digest));
+        }
+        fn pinned_artifacts(&self) -> Vec<Sha256Digest> {
+            self.borrow_mut().messages.push(CachePinnedArtifacts);
+            self.borrow().pinned_artifacts_returns.clone()
+        }
+        fn cached_artifacts(&self) -> Vec<CachedArtifact> {
+            self.borrow_mut().messages.push(CacheCachedArtifacts);
+            self.borrow().cached_artifacts_returns.clone()
+        }
+        fn eviction_statistics(&self) -> EvictionStatistics {
+            self.borrow_mut().messages.push(CacheEvictionStatistics);
+            self.borrow().eviction_statistics_returns
+        }
     }
 
     impl SchedulerDeps for Rc<RefCell<TestState>> {
@@ -944,12 +1405,18 @@ mod tests {
         fn send_message_to_worker_artifact_fetcher(
             &mut self,
             sender: &mut TestWorkerArtifactFetcherSender,
-            message: Result<(PathBuf, u64), GetArtifactForWorkerError>,
+            message: Result<WorkerArtifactSource, GetArtifactForWorkerError>,
         ) {
             self.borrow_mut()
                 .messages
                 .push(ToWorkerArtifactFetcher(sender.0, message));
         }
+
+        fn fetch_artifact_from_store(&mut self, digest: Sha256Digest) {
+            self.borrow_mut()
+                .messages
+                .push(FetchArtifactFromStore(digest));
+        }
     }
 
     struct Fixture {
@@ -962,7 +1429,7 @@ mod tests {
             let test_state = Rc::new(RefCell::new(TestState::default()));
             Fixture {
                 test_state: test_state.clone(),
-                scheduler: Scheduler::new(test_state),
+                scheduler: Scheduler::new(test_state, 0, false),
             }
         }
     }
@@ -974,7 +1441,7 @@ mod tests {
             got_artifact_returns: [(Sha256Digest, Vec<Vec<JobId>>); M],
             get_artifact_for_worker_returns: [(
                 Sha256Digest,
-                Vec<Result<(PathBuf, u64), GetArtifactForWorkerError>>,
+                Vec<Result<(PathBuf, u64, Option<PathBuf>), GetArtifactForWorkerError>>,
             ); N],
             read_manifest_returns: [(Sha256Digest, Vec<ManifestEntry>); O],
         ) -> Self {
@@ -1009,6 +1476,45 @@ mod tests {
             );
         }
 
+        fn expect_artifact_from_client_returns<const N: usize>(
+            self,
+            expect_artifact_from_client_returns: [((ClientId, Sha256Digest), bool); N],
+        ) -> Self {
+            self.test_state
+                .borrow_mut()
+                .expect_artifact_from_client_returns =
+                HashMap::from(expect_artifact_from_client_returns);
+            self
+        }
+
+        fn pinned_artifacts_returns(self, pinned_artifacts_returns: Vec<Sha256Digest>) -> Self {
+            self.test_state.borrow_mut().pinned_artifacts_returns = pinned_artifacts_returns;
+            self
+        }
+
+        fn cached_artifacts_returns(self, cached_artifacts_returns: Vec<CachedArtifact>) -> Self {
+            self.test_state.borrow_mut().cached_artifacts_returns = cached_artifacts_returns;
+            self
+        }
+
+        fn eviction_statistics_returns(
+            self,
+            eviction_statistics_returns: EvictionStatistics,
+        ) -> Self {
+            self.test_state.borrow_mut().eviction_statistics_returns = eviction_statistics_returns;
+            self
+        }
+
+        fn use_artifact_store(mut self) -> Self {
+            self.scheduler.use_artifact_store = true;
+            self
+        }
+
+        fn prefetch_hints_per_slot(mut self, prefetch_hints_per_slot: usize) -> Self {
+            self.scheduler.prefetch_hints_per_slot = prefetch_hints_per_slot;
+            self
+        }
+
         fn receive_message(&mut self, msg: Message<Rc<RefCell<TestState>>>) {
             self.scheduler.receive_message(&mut self.test_state, msg);
         }
@@ -1054,8 +1560,8 @@ mod tests {
             ], [], [], [])
         },
         ClientConnected(cid![1], client_sender![1]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
         };
     }
@@ -1066,7 +1572,7 @@ mod tests {
         let mut fixture = Fixture::default();
         fixture.receive_message(FromClient(
             cid![1],
-            ClientToBroker::JobRequest(cjid![1], spec![1, Tar]),
+            ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar])),
         ));
     }
 
@@ -1094,7 +1600,7 @@ mod tests {
 
         fixture.receive_message(FromWorker(
             wid![1],
-            WorkerToBroker::JobResponse(jid![1], Ok(outcome![1])),
+            WorkerToBroker::JobResponse(jid![1], Box::new(Ok(outcome![1]))),
         ));
     }
 
@@ -1109,14 +1615,14 @@ mod tests {
     #[should_panic]
     fn connect_from_duplicate_worker_panics() {
         let mut fixture = Fixture::default();
-        fixture.receive_message(WorkerConnected(wid![1], 2, worker_sender![1]));
-        fixture.receive_message(WorkerConnected(wid![1], 2, worker_sender![1]));
+        fixture.receive_message(WorkerConnected(wid![1], 2, None, worker_sender![1]));
+        fixture.receive_message(WorkerConnected(wid![1], 2, None, worker_sender![1]));
     }
 
     script_test! {
         response_from_known_worker_for_unknown_job_ignored,
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1], Ok(outcome![1]))) => {};
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1], Box::new(Ok(outcome![1])))) => {};
     }
 
     script_test! {
@@ -1127,21 +1633,21 @@ mod tests {
             ], [], [], [])
         },
         ClientConnected(cid![1], client_sender![1]) => {};
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1], spec![1, Tar])),
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1], Box::new(spec![1, Tar]))),
         };
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![1]),
         };
     }
 
     script_test! {
         response_from_worker_for_disconnected_client_ignored,
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1], Ok(outcome![1]))) => {};
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1], Box::new(Ok(outcome![1])))) => {};
     }
 
     script_test! {
@@ -1160,78 +1666,78 @@ mod tests {
                 ((jid![1, 10], digest![10]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
-        WorkerConnected(wid![2], 2, worker_sender![2]) => {};
-        WorkerConnected(wid![3], 3, worker_sender![3]) => {};
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 2, None, worker_sender![2]) => {};
+        WorkerConnected(wid![3], 3, None, worker_sender![3]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         // 0/2 0/2 0/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
 
         // 1/2 0/2 0/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid![1, 2], digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![2, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
 
         // 1/2 1/2 0/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar])) => {
-            CacheGetArtifact(jid![1, 3], digest![3]),
-            ToWorker(wid![3], EnqueueJob(jid![1, 3], spec![3, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToWorker(wid![3], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar]))),
         };
 
         // 1/2 1/2 1/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], spec![4, Tar])) => {
-            CacheGetArtifact(jid![1, 4], digest![4]),
-            ToWorker(wid![3], EnqueueJob(jid![1, 4], spec![4, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar]))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToWorker(wid![3], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar]))),
         };
 
         // 1/2 1/2 2/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], spec![5, Tar])) => {
-            CacheGetArtifact(jid![1, 5], digest![5]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 5], spec![5, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar]))) => {
+            CacheGetArtifact(jid![1, 5], digest![5], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 5], Box::new(spec![5, Tar]))),
         };
 
         // 2/2 1/2 2/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], spec![6, Tar])) => {
-            CacheGetArtifact(jid![1, 6], digest![6]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 6], spec![6, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], Box::new(spec![6, Tar]))) => {
+            CacheGetArtifact(jid![1, 6], digest![6], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 6], Box::new(spec![6, Tar]))),
         };
 
         // 2/2 2/2 2/3
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![7], spec![7, Tar])) => {
-            CacheGetArtifact(jid![1, 7], digest![7]),
-            ToWorker(wid![3], EnqueueJob(jid![1, 7], spec![7, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![7], Box::new(spec![7, Tar]))) => {
+            CacheGetArtifact(jid![1, 7], digest![7], ArtifactKind::Tar),
+            ToWorker(wid![3], EnqueueJob(jid![1, 7], Box::new(spec![7, Tar]))),
         };
 
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![1]),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![8], spec![8, Tar])) => {
-            CacheGetArtifact(jid![1, 8], digest![8]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 8], spec![8, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![8], Box::new(spec![8, Tar]))) => {
+            CacheGetArtifact(jid![1, 8], digest![8], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 8], Box::new(spec![8, Tar]))),
         };
 
-        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Ok(outcome![2]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Ok(outcome![2]))),
+        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![2])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![2])))),
             CacheDecrementRefcount(digest![2]),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![9], spec![9, Tar])) => {
-            CacheGetArtifact(jid![1, 9], digest![9]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 9], spec![9, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![9], Box::new(spec![9, Tar]))) => {
+            CacheGetArtifact(jid![1, 9], digest![9], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 9], Box::new(spec![9, Tar]))),
         };
 
-        FromWorker(wid![3], WorkerToBroker::JobResponse(jid![1, 3], Ok(outcome![3]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![3], Ok(outcome![3]))),
+        FromWorker(wid![3], WorkerToBroker::JobResponse(jid![1, 3], Box::new(Ok(outcome![3])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![3], Box::new(Ok(outcome![3])))),
             CacheDecrementRefcount(digest![3]),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![10], spec![10, Tar])) => {
-            CacheGetArtifact(jid![1, 10], digest![10]),
-            ToWorker(wid![3], EnqueueJob(jid![1, 10], spec![10, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![10], Box::new(spec![10, Tar]))) => {
+            CacheGetArtifact(jid![1, 10], digest![10], ArtifactKind::Tar),
+            ToWorker(wid![3], EnqueueJob(jid![1, 10], Box::new(spec![10, Tar]))),
         };
     }
 
@@ -1247,61 +1753,61 @@ mod tests {
                 ((jid![1, 6], digest![6]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         // 0/1 0/1
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
 
         // 1/1 0/1
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid![1, 2], digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![2, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
 
         // 1/1 1/1
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar])) => {
-            CacheGetArtifact(jid![1, 3], digest![3]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 3], spec![3, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar]))),
         };
 
         // 2/1 1/1
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], spec![4, Tar])) => {
-            CacheGetArtifact(jid![1, 4], digest![4]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 4], spec![4, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar]))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar]))),
         };
 
         // 2/1 2/1
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], spec![5, Tar])) => {
-            CacheGetArtifact(jid![1, 5], digest![5]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar]))) => {
+            CacheGetArtifact(jid![1, 5], digest![5], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![5], JobBrokerStatus::WaitingForWorker)),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], spec![6, Tar])) => {
-            CacheGetArtifact(jid![1, 6], digest![6]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], Box::new(spec![6, Tar]))) => {
+            CacheGetArtifact(jid![1, 6], digest![6], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![6], JobBrokerStatus::WaitingForWorker)),
         };
 
         // 2/2 1/2
-        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Ok(outcome![2]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Ok(outcome![2]))),
+        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![2])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![2])))),
             CacheDecrementRefcount(digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 5], spec![5, Tar])),
+            ToWorker(wid![2], EnqueueJob(jid![1, 5], Box::new(spec![5, Tar]))),
         };
 
         // 1/2 2/2
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 6], spec![6, Tar])),
+            ToWorker(wid![1], EnqueueJob(jid![1, 6], Box::new(spec![6, Tar]))),
         };
     }
 
     script_test! {
-        queued_requests_go_to_workers_on_connect,
+        queued_requests_are_hinted_to_workers,
         {
             Fixture::new([
                 ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
@@ -1310,50 +1816,73 @@ mod tests {
                 ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
                 ((jid![1, 5], digest![5]), vec![GetArtifact::Success]),
                 ((jid![1, 6], digest![6]), vec![GetArtifact::Success]),
-            ], [], [], [])
+                ((jid![1, 7], digest![7]), vec![GetArtifact::Success]),
+            ], [], [], []).prefetch_hints_per_slot(1)
         },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest!(1)),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar].priority(-1).estimated_duration(Some(millis!(6))))) => {
-            CacheGetArtifact(jid![1, 2], digest!(2)),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForWorker)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar].estimated_duration(Some(millis!(5))))) => {
-            CacheGetArtifact(jid![1, 3], digest!(3)),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar]))),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], spec![4, Tar].estimated_duration(Some(millis!(4))))) => {
-            CacheGetArtifact(jid![1, 4], digest!(4)),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar]))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar]))),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], spec![5, Tar].estimated_duration(Some(millis!(3))))) => {
-            CacheGetArtifact(jid![1, 5], digest!(5)),
+
+        // Each worker has room for one hint.
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar].priority(3)))) => {
+            CacheGetArtifact(jid![1, 5], digest![5], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![5], JobBrokerStatus::WaitingForWorker)),
+            ToWorker(wid![1], Prefetch(jid![1, 5], Box::new(spec![5, Tar].priority(3)))),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], spec![6, Tar].priority(1).estimated_duration(Some(millis!(2))))) => {
-            CacheGetArtifact(jid![1, 6], digest!(6)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], Box::new(spec![6, Tar].priority(2)))) => {
+            CacheGetArtifact(jid![1, 6], digest![6], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![6], JobBrokerStatus::WaitingForWorker)),
+            ToWorker(wid![2], Prefetch(jid![1, 6], Box::new(spec![6, Tar].priority(2)))),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![7], Box::new(spec![7, Tar].priority(1)))) => {
+            CacheGetArtifact(jid![1, 7], digest![7], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![7], JobBrokerStatus::WaitingForWorker)),
         };
 
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {
-            ToWorker(wid![1], EnqueueJob(jid![1, 6], spec![6, Tar].priority(1).estimated_duration(Some(millis!(2))))),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
-            ToWorker(wid![1], EnqueueJob(jid![1, 3], spec![3, Tar].estimated_duration(Some(millis!(5))))),
-            ToWorker(wid![1], EnqueueJob(jid![1, 4], spec![4, Tar].estimated_duration(Some(millis!(4))))),
+        // Job 5 goes to worker 2 instead of the worker it was hinted to, which frees up room for
+        // job 7 to be hinted.
+        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![2])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![2])))),
+            CacheDecrementRefcount(digest![2]),
+            ToWorker(wid![1], CancelJob(jid![1, 5])),
+            ToWorker(wid![2], EnqueueJob(jid![1, 5], Box::new(spec![5, Tar].priority(3)))),
+            ToWorker(wid![1], Prefetch(jid![1, 7], Box::new(spec![7, Tar].priority(1)))),
+        };
+
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
+            CacheDecrementRefcount(digest![1]),
+            ToWorker(wid![2], CancelJob(jid![1, 6])),
+            ToWorker(wid![1], EnqueueJob(jid![1, 6], Box::new(spec![6, Tar].priority(2)))),
         };
 
-        WorkerConnected(wid![2], 2, worker_sender![2]) => {
-            ToWorker(wid![2], EnqueueJob(jid![1, 5], spec![5, Tar].estimated_duration(Some(millis!(3))))),
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![2, Tar].priority(-1).estimated_duration(Some(millis!(6))))),
+        // Job 7 goes to the worker it was hinted to.
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 3], Box::new(Ok(outcome![3])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![3], Box::new(Ok(outcome![3])))),
+            CacheDecrementRefcount(digest![3]),
+            ToWorker(wid![1], EnqueueJob(jid![1, 7], Box::new(spec![7, Tar].priority(1)))),
         };
     }
 
     script_test! {
-        requests_outstanding_on_disconnected_worker_get_sent_to_new_workers,
+        queued_requests_are_hinted_in_priority_order,
         {
             Fixture::new([
                 ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
@@ -1361,181 +1890,364 @@ mod tests {
                 ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
                 ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
                 ((jid![1, 5], digest![5]), vec![GetArtifact::Success]),
-            ], [], [], [])
+            ], [], [], []).prefetch_hints_per_slot(1)
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {};
-        WorkerConnected(wid![3], 1, worker_sender![3]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar].priority(1).estimated_duration(Some(millis!(50))))) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar].priority(1).estimated_duration(Some(millis!(50))))),
-        };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid![1, 2], digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![2, Tar])),
-        };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar])) => {
-            CacheGetArtifact(jid![1, 3], digest![3]),
-            ToWorker(wid![3], EnqueueJob(jid![1, 3], spec![3, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], spec![4, Tar].estimated_duration(Some(millis!(40))))) => {
-            CacheGetArtifact(jid![1, 4], digest![4]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 4], spec![4, Tar].estimated_duration(Some(millis!(40))))),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], spec![5, Tar])) => {
-            CacheGetArtifact(jid![1, 5], digest![5]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 5], spec![5, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar].priority(2)))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+            ToWorker(wid![1], Prefetch(jid![1, 3], Box::new(spec![3, Tar].priority(2)))),
         };
 
-        WorkerDisconnected(wid![1]) => {
-            ToWorker(wid![3], EnqueueJob(jid![1, 1], spec![1, Tar].priority(1).estimated_duration(Some(millis!(50))))),
+        // The worker has no more room for hints.
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar]))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
         };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar].priority(1)))) => {
+            CacheGetArtifact(jid![1, 5], digest![5], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![5], JobBrokerStatus::WaitingForWorker)),
+        };
 
-        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Ok(outcome![2]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Ok(outcome![2]))),
-            CacheDecrementRefcount(digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 4], spec![4, Tar].estimated_duration(Some(millis!(40))))),
+        // Job 3 goes to the worker it was hinted to. Job 5 is hinted next, even though job 4 was
+        // queued first, since job 5 will be sent to a worker first.
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
+            CacheDecrementRefcount(digest![1]),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar].priority(2)))),
+            ToWorker(wid![1], Prefetch(jid![1, 5], Box::new(spec![5, Tar].priority(1)))),
         };
     }
 
     script_test! {
-        requests_outstanding_on_disconnected_worker_get_sent_to_new_workers_2,
+        hints_on_disconnected_worker_go_to_other_workers,
         {
             Fixture::new([
                 ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
                 ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
                 ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
                 ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
-            ], [], [], [])
+                ((jid![1, 5], digest![5]), vec![GetArtifact::Success]),
+            ], [], [], []).prefetch_hints_per_slot(1)
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
-        };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid![1, 2], digest![2]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![2, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar].estimated_duration(Some(millis!(300))))) => {
-            CacheGetArtifact(jid![1, 3], digest![3]),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], spec![4, Tar].estimated_duration(Some(millis!(40))))) => {
-            CacheGetArtifact(jid![1, 4], digest![4]),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar]))),
         };
-
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
-            CacheDecrementRefcount(digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 3], spec![3, Tar].estimated_duration(Some(millis!(300))))),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar]))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar]))),
         };
-
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {
-            ToWorker(wid![2], EnqueueJob(jid![1, 4], spec![4, Tar].estimated_duration(Some(millis!(40))))),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar]))) => {
+            CacheGetArtifact(jid![1, 5], digest![5], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![5], JobBrokerStatus::WaitingForWorker)),
+            ToWorker(wid![1], Prefetch(jid![1, 5], Box::new(spec![5, Tar]))),
         };
 
+        // Worker 2 only has room for one hint. Jobs 1, 3, and 5 all have the same priority, so
+        // job 1 goes first.
         WorkerDisconnected(wid![1]) => {
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![2, Tar])),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
-        };
-
-        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Ok(outcome![2]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Ok(outcome![2]))),
-            CacheDecrementRefcount(digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 3], spec![3, Tar].estimated_duration(Some(millis!(300))))),
+            ToWorker(wid![2], Prefetch(jid![1, 1], Box::new(spec![1, Tar]))),
         };
     }
 
     script_test! {
-        requests_outstanding_on_disconnected_worker_get_reenqueued_for_other_workers,
+        client_disconnects_with_hinted_work,
         {
             Fixture::new([
                 ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
                 ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
                 ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
-                ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
-            ], [], [], [])
+            ], [], [], []).prefetch_hints_per_slot(1)
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar].estimated_duration(Some(millis!(10))))) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar].estimated_duration(Some(millis!(10))))),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar].estimated_duration(Some(millis!(20))))) => {
-            CacheGetArtifact(jid![1, 2], digest![2]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![2, Tar].estimated_duration(Some(millis!(20))))),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
-
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar].priority(1).estimated_duration(Some(millis!(1))))) => {
-            CacheGetArtifact(jid![1, 3], digest![3]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
-        };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], spec![4, Tar].estimated_duration(Some(millis!(40))))) => {
-            CacheGetArtifact(jid![1, 4], digest![4]),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
-        };
-
-        WorkerDisconnected(wid![1]) => {
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
-            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForWorker)),
+            ToWorker(wid![1], Prefetch(jid![1, 3], Box::new(spec![3, Tar]))),
         };
 
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {
-            ToWorker(wid![2], EnqueueJob(jid![1, 3], spec![3, Tar].priority(1).estimated_duration(Some(millis!(1))))),
-            ToWorker(wid![2], EnqueueJob(jid![1, 4], spec![4, Tar].estimated_duration(Some(millis!(40))))),
+        ClientDisconnected(cid![1]) => {
+            ToWorker(wid![1], CancelJob(jid![1, 1])),
+            ToWorker(wid![1], CancelJob(jid![1, 2])),
+            ToWorker(wid![1], CancelJob(jid![1, 3])),
+            CacheDecrementRefcount(digest![1]),
+            CacheDecrementRefcount(digest![2]),
+            CacheDecrementRefcount(digest![3]),
+            CacheClientDisconnected(cid![1]),
         };
     }
 
     script_test! {
-        requests_get_removed_from_workers_pending_map,
+        queued_requests_go_to_workers_on_connect,
         {
             Fixture::new([
                 ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
                 ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
+                ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
+                ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
+                ((jid![1, 5], digest![5]), vec![GetArtifact::Success]),
+                ((jid![1, 6], digest![6]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
+        ClientConnected(cid![1], client_sender![1]) => {};
 
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest!(1), ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar].priority(-1).estimated_duration(Some(millis!(6)))))) => {
+            CacheGetArtifact(jid![1, 2], digest!(2), ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar].estimated_duration(Some(millis!(5)))))) => {
+            CacheGetArtifact(jid![1, 3], digest!(3), ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(4)))))) => {
+            CacheGetArtifact(jid![1, 4], digest!(4), ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar].estimated_duration(Some(millis!(3)))))) => {
+            CacheGetArtifact(jid![1, 5], digest!(5), ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![5], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![6], Box::new(spec![6, Tar].priority(1).estimated_duration(Some(millis!(2)))))) => {
+            CacheGetArtifact(jid![1, 6], digest!(6), ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![6], JobBrokerStatus::WaitingForWorker)),
+        };
+
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {
+            ToWorker(wid![1], EnqueueJob(jid![1, 6], Box::new(spec![6, Tar].priority(1).estimated_duration(Some(millis!(2)))))),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar].estimated_duration(Some(millis!(5)))))),
+            ToWorker(wid![1], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(4)))))),
+        };
+
+        WorkerConnected(wid![2], 2, None, worker_sender![2]) => {
+            ToWorker(wid![2], EnqueueJob(jid![1, 5], Box::new(spec![5, Tar].estimated_duration(Some(millis!(3)))))),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar].priority(-1).estimated_duration(Some(millis!(6)))))),
+        };
+    }
+
+    script_test! {
+        requests_outstanding_on_disconnected_worker_get_sent_to_new_workers,
+        {
+            Fixture::new([
+                ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
+                ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
+                ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
+                ((jid![1, 5], digest![5]), vec![GetArtifact::Success]),
+            ], [], [], [])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
+        WorkerConnected(wid![3], 1, None, worker_sender![3]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar].priority(1).estimated_duration(Some(millis!(50)))))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar].priority(1).estimated_duration(Some(millis!(50)))))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToWorker(wid![3], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar]))),
         };
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid![1, 2], digest![2]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![2, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))),
         };
 
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![5], Box::new(spec![5, Tar]))) => {
+            CacheGetArtifact(jid![1, 5], digest![5], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 5], Box::new(spec![5, Tar]))),
+        };
+
+        WorkerDisconnected(wid![1]) => {
+            ToWorker(wid![3], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar].priority(1).estimated_duration(Some(millis!(50)))))),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
+        };
+
+        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![2])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![2])))),
+            CacheDecrementRefcount(digest![2]),
+            ToWorker(wid![2], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))),
+        };
+    }
+
+    script_test! {
+        requests_outstanding_on_disconnected_worker_get_sent_to_new_workers_2,
+        {
+            Fixture::new([
+                ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
+                ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
+                ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
+            ], [], [], [])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar].estimated_duration(Some(millis!(300)))))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
+        };
+
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![1]),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar].estimated_duration(Some(millis!(300)))))),
+        };
+
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {
+            ToWorker(wid![2], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))),
         };
 
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 2], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Ok(outcome![1]))),
+        WorkerDisconnected(wid![1]) => {
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+        };
+
+        FromWorker(wid![2], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![2])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![2])))),
+            CacheDecrementRefcount(digest![2]),
+            ToWorker(wid![2], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar].estimated_duration(Some(millis!(300)))))),
+        };
+    }
+
+    script_test! {
+        requests_outstanding_on_disconnected_worker_get_reenqueued_for_other_workers,
+        {
+            Fixture::new([
+                ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
+                ((jid![1, 3], digest![3]), vec![GetArtifact::Success]),
+                ((jid![1, 4], digest![4]), vec![GetArtifact::Success]),
+            ], [], [], [])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar].estimated_duration(Some(millis!(10)))))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar].estimated_duration(Some(millis!(10)))))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar].estimated_duration(Some(millis!(20)))))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar].estimated_duration(Some(millis!(20)))))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar].priority(1).estimated_duration(Some(millis!(1)))))) => {
+            CacheGetArtifact(jid![1, 3], digest![3], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
+        };
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))) => {
+            CacheGetArtifact(jid![1, 4], digest![4], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
+        };
+
+        WorkerDisconnected(wid![1]) => {
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForWorker)),
+        };
+
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {
+            ToWorker(wid![2], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar].priority(1).estimated_duration(Some(millis!(1)))))),
+            ToWorker(wid![2], EnqueueJob(jid![1, 4], Box::new(spec![4, Tar].estimated_duration(Some(millis!(40)))))),
+        };
+    }
+
+    script_test! {
+        requests_get_removed_from_workers_pending_map,
+        {
+            Fixture::new([
+                ((jid![1, 1], digest![1]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![2]), vec![GetArtifact::Success]),
+            ], [], [], [])
+        },
+
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
+        };
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid![1, 2], digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
+        };
+
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
+            CacheDecrementRefcount(digest![1]),
+        };
+
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![2]),
         };
 
         WorkerDisconnected(wid![1]) => {};
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
     }
 
     script_test! {
@@ -1545,12 +2257,12 @@ mod tests {
                 ((jid!(1, 1), digest![1]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid!(1, 1), digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid!(1, 1), digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -1569,19 +2281,19 @@ mod tests {
                 ((jid!(1, 2), digest![3]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
         ClientConnected(cid![2], client_sender![2]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid!(1, 1), digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid!(1, 1), digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
 
-        FromClient(cid![2], ClientToBroker::JobRequest(cjid![1], spec![2, Tar])) => {
-            CacheGetArtifact(jid!(2, 1), digest![2]),
-            ToWorker(wid![2], EnqueueJob(jid![2, 1], spec![2, Tar])),
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![1], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid!(2, 1), digest![2], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![2, 1], Box::new(spec![2, Tar]))),
         };
 
         ClientDisconnected(cid![2]) => {
@@ -1590,9 +2302,9 @@ mod tests {
             CacheClientDisconnected(cid![2]),
         };
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![3, Tar])) => {
-            CacheGetArtifact(jid!(1, 2), digest![3]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![3, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid!(1, 2), digest![3], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![3, Tar]))),
         };
     }
 
@@ -1606,26 +2318,26 @@ mod tests {
                 ((jid!(2, 1), digest![1]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid!(1, 1), digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid!(1, 1), digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid!(1, 2), digest![2]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![2, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid!(1, 2), digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
         };
 
         ClientConnected(cid![2], client_sender![2]) => {};
-        FromClient(cid![2], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid!(2, 1), digest![1]),
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid!(2, 1), digest![1], ArtifactKind::Tar),
             ToClient(cid![2], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![3, Tar])) => {
-            CacheGetArtifact(jid!(1, 3), digest![3]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid!(1, 3), digest![3], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForWorker)),
         };
 
@@ -1634,10 +2346,10 @@ mod tests {
             CacheClientDisconnected(cid![2]),
         };
 
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 3], spec![3, Tar])),
+            ToWorker(wid![1], EnqueueJob(jid![1, 3], Box::new(spec![3, Tar]))),
         };
     }
 
@@ -1653,37 +2365,37 @@ mod tests {
                 ((jid!(2, 4), digest![4]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
         ClientConnected(cid![2], client_sender![2]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid!(1, 1), digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid!(1, 1), digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
 
-        FromClient(cid![2], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid!(2, 1), digest![1]),
-            ToWorker(wid![2], EnqueueJob(jid![2, 1], spec![1, Tar])),
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid!(2, 1), digest![1], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![2, 1], Box::new(spec![1, Tar]))),
         };
 
-        FromClient(cid![2], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid!(2, 2), digest![2]),
-            ToWorker(wid![1], EnqueueJob(jid![2, 2], spec![2, Tar])),
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid!(2, 2), digest![2], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![2, 2], Box::new(spec![2, Tar]))),
         };
 
-        FromClient(cid![2], ClientToBroker::JobRequest(cjid![3], spec![3, Tar])) => {
-            CacheGetArtifact(jid!(2, 3), digest![3]),
-            ToWorker(wid![2], EnqueueJob(jid![2, 3], spec![3, Tar])),
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![3], Box::new(spec![3, Tar]))) => {
+            CacheGetArtifact(jid!(2, 3), digest![3], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![2, 3], Box::new(spec![3, Tar]))),
         };
 
-        FromClient(cid![2], ClientToBroker::JobRequest(cjid![4], spec![4, Tar])) => {
-            CacheGetArtifact(jid!(2, 4), digest![4]),
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![4], Box::new(spec![4, Tar]))) => {
+            CacheGetArtifact(jid!(2, 4), digest![4], ArtifactKind::Tar),
             ToClient(cid![2], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForWorker)),
         };
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![2, Tar])) => {
-            CacheGetArtifact(jid!(1, 2), digest![2]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![2, Tar]))) => {
+            CacheGetArtifact(jid!(1, 2), digest![2], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForWorker)),
         };
 
@@ -1697,7 +2409,7 @@ mod tests {
             ToWorker(wid![1], CancelJob(jid![2, 2])),
             ToWorker(wid![2], CancelJob(jid![2, 3])),
 
-            ToWorker(wid![2], EnqueueJob(jid![1, 2], spec![2, Tar])),
+            ToWorker(wid![2], EnqueueJob(jid![1, 2], Box::new(spec![2, Tar]))),
 
             CacheClientDisconnected(cid![2]),
         };
@@ -1712,16 +2424,16 @@ mod tests {
                 ((jid![1, 2], digest![44]), vec![GetArtifact::Get]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         FromClient(
             cid![1],
-            ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Tar), (43, Tar), (44, Tar)]])
+            ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (43, Tar), (44, Tar)]]))
         ) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
-            CacheGetArtifact(jid![1, 2], digest![44]),
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![44], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![44])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
@@ -1741,27 +2453,305 @@ mod tests {
                 ((jid![1, 2], digest![44]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         FromClient(
             cid![1],
-            ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Tar), (43, Tar), (44, Tar)]])
+            ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (43, Tar), (44, Tar)]]))
         ) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
-            CacheGetArtifact(jid![1, 2], digest![44]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Tar), (43, Tar), (44, Tar)]])),
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![44], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Tar), (43, Tar), (44, Tar)]]))),
         };
 
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 2], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Ok(outcome![1]))),
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![42]),
             CacheDecrementRefcount(digest![43]),
             CacheDecrementRefcount(digest![44]),
         };
     }
 
+    script_test! {
+        artifacts_query_then_request,
+        {
+            Fixture::new([
+                ((jid![1, 2], digest![42]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![43]), vec![GetArtifact::Wait]),
+            ], [
+                (digest![43], vec![vec![jid![1, 2]]]),
+            ], [], []).expect_artifact_from_client_returns([
+                ((cid![1], digest![42]), false),
+                ((cid![1], digest![43]), true),
+            ])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(cid![1], ClientToBroker::ArtifactsQuery(vec![digest![42], digest![43]])) => {
+            CacheExpectArtifactFromClient(cid![1], digest![42]),
+            CacheExpectArtifactFromClient(cid![1], digest![43]),
+            ToClient(cid![1], BrokerToClient::MissingArtifacts(vec![digest![43]])),
+        };
+
+        FromClient(
+            cid![1],
+            ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (43, Tar)]]))
+        ) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
+        };
+
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Tar), (43, Tar)]]))),
+        };
+    }
+
+    script_test! {
+        artifacts_query_with_artifact_store,
+        {
+            Fixture::new([], [
+                (digest![43], vec![vec![]]),
+            ], [], []).expect_artifact_from_client_returns([
+                ((cid![1], digest![42]), false),
+                ((cid![1], digest![43]), true),
+                ((cid![1], digest![44]), true),
+            ]).use_artifact_store()
+        },
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(
+            cid![1],
+            ClientToBroker::ArtifactsQuery(vec![digest![42], digest![43], digest![44]])
+        ) => {
+            CacheExpectArtifactFromClient(cid![1], digest![42]),
+            CacheExpectArtifactFromClient(cid![1], digest![43]),
+            FetchArtifactFromStore(digest![43]),
+            CacheExpectArtifactFromClient(cid![1], digest![44]),
+            FetchArtifactFromStore(digest![44]),
+            ToClient(cid![1], BrokerToClient::MissingArtifacts(vec![])),
+        };
+
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
+        };
+
+        ArtifactNotInStore(digest![44]) => {
+            ToClient(cid![1], BrokerToClient::TransferArtifact(digest![44])),
+        };
+    }
+
+    script_test! {
+        request_with_layer_from_artifact_store,
+        {
+            Fixture::new([
+                ((jid![1, 2], digest![42]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![43]), vec![GetArtifact::Get]),
+            ], [
+                (digest![43], vec![vec![jid![1, 2]]]),
+            ], [], []).use_artifact_store()
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(
+            cid![1],
+            ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (43, Tar)]]))
+        ) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            FetchArtifactFromStore(digest![43]),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
+        };
+
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Tar), (43, Tar)]]))),
+        };
+
+        ArtifactNotInStore(digest![43]) => {};
+    }
+
+    script_test! {
+        request_with_layer_not_in_artifact_store,
+        {
+            Fixture::new([
+                ((jid![1, 2], digest![43]), vec![GetArtifact::Get]),
+                ((jid![2, 3], digest![43]), vec![GetArtifact::Get]),
+                ((jid![3, 4], digest![43]), vec![GetArtifact::Get]),
+            ], [], [], []).use_artifact_store()
+        },
+        ClientConnected(cid![1], client_sender![1]) => {};
+        ClientConnected(cid![2], client_sender![2]) => {};
+        ClientConnected(cid![3], client_sender![3]) => {};
+
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(43, Tar)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            FetchArtifactFromStore(digest![43]),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
+        };
+        FromClient(cid![2], ClientToBroker::JobRequest(cjid![3], Box::new(spec![2, [(43, Tar)]]))) => {
+            CacheGetArtifact(jid![2, 3], digest![43], ArtifactKind::Tar),
+            ToClient(cid![2], BrokerToClient::JobStatusUpdate(cjid![3], JobBrokerStatus::WaitingForLayers)),
+        };
+        FromClient(cid![3], ClientToBroker::JobRequest(cjid![4], Box::new(spec![3, [(43, Tar)]]))) => {
+            CacheGetArtifact(jid![3, 4], digest![43], ArtifactKind::Tar),
+            ToClient(cid![3], BrokerToClient::JobStatusUpdate(cjid![4], JobBrokerStatus::WaitingForLayers)),
+        };
+        ClientDisconnected(cid![3]) => {
+            CacheClientDisconnected(cid![3]),
+        };
+
+        ArtifactNotInStore(digest![43]) => {
+            ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
+            ToClient(cid![2], BrokerToClient::TransferArtifact(digest![43])),
+        };
+    }
+
+    script_test! {
+        request_with_stdin_artifact,
+        {
+            Fixture::new([
+                ((jid![1, 2], digest![42]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![43]), vec![GetArtifact::Get]),
+            ], [
+                (digest![43], vec![vec![jid![1, 2]]]),
+            ], [], [])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(
+            cid![1],
+            ClientToBroker::JobRequest(
+                cjid![2],
+                Box::new(spec![1, [(42, Tar)]].stdin(Some(JobStdin::Artifact(digest![43])))),
+            )
+        ) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
+            ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
+        };
+
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
+            ToWorker(
+                wid![1],
+                EnqueueJob(
+                    jid![1, 2],
+                    Box::new(spec![1, [(42, Tar)]].stdin(Some(JobStdin::Artifact(digest![43])))),
+                ),
+            ),
+        };
+
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![1])))),
+            CacheDecrementRefcount(digest![42]),
+            CacheDecrementRefcount(digest![43]),
+        };
+    }
+
+    script_test! {
+        request_with_sidecar_layers,
+        {
+            Fixture::new([
+                ((jid![1, 2], digest![42]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![43]), vec![GetArtifact::Get]),
+            ], [
+                (digest![43], vec![vec![jid![1, 2]]]),
+            ], [], [])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(
+            cid![1],
+            ClientToBroker::JobRequest(
+                cjid![2],
+                Box::new(spec![1, [(42, Tar)]].sidecars([
+                    JobSidecar::new("db", "db", nonempty![(digest![42], ArtifactType::Tar), (digest![43], ArtifactType::Tar)]),
+                ])),
+            )
+        ) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
+        };
+
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
+            ToWorker(
+                wid![1],
+                EnqueueJob(
+                    jid![1, 2],
+                    Box::new(spec![1, [(42, Tar)]].sidecars([
+                        JobSidecar::new("db", "db", nonempty![(digest![42], ArtifactType::Tar), (digest![43], ArtifactType::Tar)]),
+                    ])),
+                ),
+            ),
+        };
+
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 2], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![2], Box::new(Ok(outcome![1])))),
+            CacheDecrementRefcount(digest![42]),
+            CacheDecrementRefcount(digest![43]),
+        };
+    }
+
+    script_test! {
+        request_with_layer_mount_layers,
+        {
+            Fixture::new([
+                ((jid![1, 2], digest![42]), vec![GetArtifact::Success]),
+                ((jid![1, 2], digest![43]), vec![GetArtifact::Get]),
+            ], [
+                (digest![43], vec![vec![jid![1, 2]]]),
+            ], [], [])
+        },
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
+        ClientConnected(cid![1], client_sender![1]) => {};
+
+        FromClient(
+            cid![1],
+            ClientToBroker::JobRequest(
+                cjid![2],
+                Box::new(spec![1, [(42, Tar)]].mounts([
+                    JobMount::Layer {
+                        mount_point: "/opt".into(),
+                        layers: nonempty![(digest![43], ArtifactType::Tar)],
+                    },
+                ])),
+            )
+        ) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
+            ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
+        };
+
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
+            ToWorker(
+                wid![1],
+                EnqueueJob(
+                    jid![1, 2],
+                    Box::new(spec![1, [(42, Tar)]].mounts([
+                        JobMount::Layer {
+                            mount_point: "/opt".into(),
+                            layers: nonempty![(digest![43], ArtifactType::Tar)],
+                        },
+                    ])),
+                ),
+            ),
+        };
+    }
+
     script_test! {
         request_with_layers_3,
         {
@@ -1774,26 +2764,26 @@ mod tests {
                 (digest![44], vec![vec![jid![1, 2]]]),
             ], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         FromClient(
             cid![1],
-            ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Tar), (43, Tar), (44, Tar)]])
+            ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (43, Tar), (44, Tar)]]))
         ) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
-            CacheGetArtifact(jid![1, 2], digest![44]),
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::Tar),
+            CacheGetArtifact(jid![1, 2], digest![44], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![44])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
 
-        GotArtifact(digest![43], 100, "/z/tmp/foo".into()) => {
-            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into()),
+        GotArtifact(digest![43], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/foo".into(), None),
         };
-        GotArtifact(digest![44], 100, "/z/tmp/bar".into()) => {
-            CacheGotArtifact(digest![44], 100, "/z/tmp/bar".into()),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Tar), (43, Tar), (44, Tar)]])),
+        GotArtifact(digest![44], 100, "/z/tmp/bar".into(), None) => {
+            CacheGotArtifact(digest![44], 100, "/z/tmp/bar".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Tar), (43, Tar), (44, Tar)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -1812,14 +2802,14 @@ mod tests {
                 ((jid![1, 2], digest![42]), vec![GetArtifact::Success]),
             ], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         FromClient(
-            cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Tar), (42, Tar)]])
+            cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (42, Tar)]]))
         ) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Tar), (42, Tar)]])),
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Tar), (42, Tar)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -1838,20 +2828,20 @@ mod tests {
                 (digest![42], vec![vec![jid![1, 2]]]),
             ], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
         FromClient(
-            cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Tar), (42, Tar)]])
+            cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Tar), (42, Tar)]]))
         ) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![42])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
 
-        GotArtifact(digest![42], 100, "/z/tmp/bar".into()) => {
-            CacheGotArtifact(digest![42], 100, "/z/tmp/bar".into()),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Tar), (42, Tar)]])),
+        GotArtifact(digest![42], 100, "/z/tmp/bar".into(), None) => {
+            CacheGotArtifact(digest![42], 100, "/z/tmp/bar".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Tar), (42, Tar)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -1882,24 +2872,24 @@ mod tests {
                 }])
             ])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Manifest)]])) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Manifest)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Manifest),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![42])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
 
-        GotArtifact(digest![42], 100, "/z/tmp/foo".into()) => {
-            CacheGotArtifact(digest![42], 100, "/z/tmp/foo".into()),
-            CacheGetArtifact(jid![1, 2], digest![43]),
+        GotArtifact(digest![42], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![42], 100, "/z/tmp/foo".into(), None),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
         };
 
-        GotArtifact(digest![43], 100, "/z/tmp/bar".into()) => {
-            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into()),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Manifest)]])),
+        GotArtifact(digest![43], 100, "/z/tmp/bar".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Manifest)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -1942,24 +2932,24 @@ mod tests {
                 ])
             ])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Manifest)]])) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Manifest)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Manifest),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![42])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
 
-        GotArtifact(digest![42], 100, "/z/tmp/foo".into()) => {
-            CacheGotArtifact(digest![42], 100, "/z/tmp/foo".into()),
-            CacheGetArtifact(jid![1, 2], digest![43]),
+        GotArtifact(digest![42], 100, "/z/tmp/foo".into(), None) => {
+            CacheGotArtifact(digest![42], 100, "/z/tmp/foo".into(), None),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
         };
 
-        GotArtifact(digest![43], 100, "/z/tmp/bar".into()) => {
-            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into()),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Manifest)]])),
+        GotArtifact(digest![43], 100, "/z/tmp/bar".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Manifest)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -1991,19 +2981,19 @@ mod tests {
                 }])
             ])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Manifest)]])) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Manifest)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Manifest),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
 
-        GotArtifact(digest![43], 100, "/z/tmp/bar".into()) => {
-            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into()),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Manifest)]])),
+        GotArtifact(digest![43], 100, "/z/tmp/bar".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Manifest)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -2035,19 +3025,19 @@ mod tests {
                 }])
             ])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Manifest)]])) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Manifest)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Manifest),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
             ToClient(cid![1], BrokerToClient::TransferArtifact(digest![43])),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![2], JobBrokerStatus::WaitingForLayers)),
         };
 
-        GotArtifact(digest![43], 100, "/z/tmp/bar".into()) => {
-            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into()),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Manifest)]])),
+        GotArtifact(digest![43], 100, "/z/tmp/bar".into(), None) => {
+            CacheGotArtifact(digest![43], 100, "/z/tmp/bar".into(), None),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Manifest)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -2079,13 +3069,13 @@ mod tests {
                 }])
             ])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Manifest)]])) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Manifest)]])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Manifest)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Manifest),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Manifest)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -2128,13 +3118,13 @@ mod tests {
                 ])
             ])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         ClientConnected(cid![1], client_sender![1]) => {};
 
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], spec![1, [(42, Manifest)]])) => {
-            CacheGetArtifact(jid![1, 2], digest![42]),
-            CacheGetArtifact(jid![1, 2], digest![43]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 2], spec![1, [(42, Manifest)]])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![2], Box::new(spec![1, [(42, Manifest)]]))) => {
+            CacheGetArtifact(jid![1, 2], digest![42], ArtifactKind::Manifest),
+            CacheGetArtifact(jid![1, 2], digest![43], ArtifactKind::File),
+            ToWorker(wid![1], EnqueueJob(jid![1, 2], Box::new(spec![1, [(42, Manifest)]]))),
         };
 
         ClientDisconnected(cid![1]) => {
@@ -2151,13 +3141,30 @@ mod tests {
             Fixture::new([], [], [
                 (
                     digest![42],
-                    vec![Ok(("/a/good/path".into(), 42))],
+                    vec![Ok(("/a/good/path".into(), 42, None))],
+                ),
+            ], [])
+        },
+        GetArtifactForWorker(digest![42], true, worker_artifact_fetcher_sender![1]) => {
+            CacheGetArtifactForWorker(digest![42]),
+            ToWorkerArtifactFetcher(1, Ok(WorkerArtifactSource::Cache("/a/good/path".into(), 42, None))),
+        }
+    }
+
+    script_test! {
+        get_artifact_for_worker_with_compressed_copy,
+        {
+            Fixture::new([], [], [
+                (
+                    digest![42],
+                    vec![Ok(("/a/good/path".into(), 42, Some("/a/good/path.zst".into())))],
                 ),
             ], [])
         },
-        GetArtifactForWorker(digest![42], worker_artifact_fetcher_sender![1]) => {
+        GetArtifactForWorker(digest![42], false, worker_artifact_fetcher_sender![1]) => {
             CacheGetArtifactForWorker(digest![42]),
-            ToWorkerArtifactFetcher(1, Ok(("/a/good/path".into(), 42))),
+            ToWorkerArtifactFetcher(1, Ok(WorkerArtifactSource::Cache(
+                "/a/good/path".into(), 42, Some("/a/good/path.zst".into())))),
         }
     }
 
@@ -2167,16 +3174,66 @@ mod tests {
             Fixture::new([], [], [
                 (
                     digest![42],
-                    vec![Ok(("/a/good/path".into(), 42))]
+                    vec![Ok(("/a/good/path".into(), 42, None))]
                 ),
             ], [])
         },
-        GetArtifactForWorker(digest![42], worker_artifact_fetcher_sender![1]) => {
+        GetArtifactForWorker(digest![42], true, worker_artifact_fetcher_sender![1]) => {
             CacheGetArtifactForWorker(digest![42]),
-            ToWorkerArtifactFetcher(1, Ok(("/a/good/path".into(), 42)))
+            ToWorkerArtifactFetcher(1, Ok(WorkerArtifactSource::Cache("/a/good/path".into(), 42, None)))
         }
     }
 
+    script_test! {
+        get_artifact_for_worker_from_peers,
+        {
+            Fixture::new([], [], [
+                (
+                    digest![42],
+                    vec![Ok(("/a/good/path".into(), 42, None))],
+                ),
+            ], [])
+        },
+        WorkerConnected(wid![1], 1, Some("1.2.3.4:1001".parse().unwrap()), worker_sender![1]) => {};
+        WorkerConnected(wid![2], 1, Some("1.2.3.5:1002".parse().unwrap()), worker_sender![2]) => {};
+        WorkerConnected(wid![3], 1, None, worker_sender![3]) => {};
+        FromWorker(wid![1], WorkerToBroker::ArtifactCached(digest![42])) => {};
+        FromWorker(wid![2], WorkerToBroker::ArtifactCached(digest![42])) => {};
+        FromWorker(wid![3], WorkerToBroker::ArtifactCached(digest![42])) => {};
+        GetArtifactForWorker(digest![42], true, worker_artifact_fetcher_sender![1]) => {
+            ToWorkerArtifactFetcher(1, Ok(WorkerArtifactSource::Peer("1.2.3.4:1001".parse().unwrap()))),
+        };
+        GetArtifactForWorker(digest![42], true, worker_artifact_fetcher_sender![2]) => {
+            ToWorkerArtifactFetcher(2, Ok(WorkerArtifactSource::Peer("1.2.3.5:1002".parse().unwrap()))),
+        };
+        GetArtifactForWorker(digest![42], true, worker_artifact_fetcher_sender![3]) => {
+            ToWorkerArtifactFetcher(3, Ok(WorkerArtifactSource::Peer("1.2.3.4:1001".parse().unwrap()))),
+        };
+        GetArtifactForWorker(digest![42], false, worker_artifact_fetcher_sender![4]) => {
+            CacheGetArtifactForWorker(digest![42]),
+            ToWorkerArtifactFetcher(4, Ok(WorkerArtifactSource::Cache("/a/good/path".into(), 42, None))),
+        };
+    }
+
+    script_test! {
+        get_artifact_for_worker_peers_go_away_on_disconnect,
+        {
+            Fixture::new([], [], [
+                (
+                    digest![42],
+                    vec![Ok(("/a/good/path".into(), 42, None))],
+                ),
+            ], [])
+        },
+        WorkerConnected(wid![1], 1, Some("1.2.3.4:1001".parse().unwrap()), worker_sender![1]) => {};
+        FromWorker(wid![1], WorkerToBroker::ArtifactCached(digest![42])) => {};
+        WorkerDisconnected(wid![1]) => {};
+        GetArtifactForWorker(digest![42], true, worker_artifact_fetcher_sender![1]) => {
+            CacheGetArtifactForWorker(digest![42]),
+            ToWorkerArtifactFetcher(1, Ok(WorkerArtifactSource::Cache("/a/good/path".into(), 42, None))),
+        };
+    }
+
     script_test! {
         decrement_refcount,
         DecrementRefcount(digest![42]) => {
@@ -2184,6 +3241,68 @@ mod tests {
         }
     }
 
+    script_test! {
+        got_compressed_copy,
+        GotCompressedCopy(digest![42], "/z/tmp/foo.zst".into()) => {
+            CacheGotCompressedCopy(digest![42], "/z/tmp/foo.zst".into()),
+        }
+    }
+
+    script_test! {
+        monitor_cache_contents_request,
+        {
+            Fixture::default().cached_artifacts_returns(vec![
+                CachedArtifact {
+                    digest: digest![42],
+                    size: 100,
+                    refcount: 1,
+                    pinned: true,
+                    last_used: None,
+                },
+            ])
+        },
+        MonitorConnected(mid![1], monitor_sender![1]) => {};
+        FromMonitor(mid![1], MonitorToBroker::CacheContentsRequest) => {
+            CacheCachedArtifacts,
+            ToMonitor(mid![1], BrokerToMonitor::CacheContentsResponse(vec![
+                CachedArtifact {
+                    digest: digest![42],
+                    size: 100,
+                    refcount: 1,
+                    pinned: true,
+                    last_used: None,
+                },
+            ])),
+        };
+    }
+
+    script_test! {
+        monitor_pin_artifacts,
+        {
+            Fixture::default().pinned_artifacts_returns(vec![digest![41], digest![42], digest![43]])
+        },
+        MonitorConnected(mid![1], monitor_sender![1]) => {};
+        FromMonitor(mid![1], MonitorToBroker::PinArtifacts(vec![digest![42], digest![43]])) => {
+            CachePinArtifact(digest![42]),
+            CachePinArtifact(digest![43]),
+            CachePinnedArtifacts,
+            ToMonitor(mid![1], BrokerToMonitor::PinnedArtifacts(vec![digest![41], digest![42], digest![43]])),
+        };
+    }
+
+    script_test! {
+        monitor_unpin_artifacts,
+        {
+            Fixture::default().pinned_artifacts_returns(vec![digest![41]])
+        },
+        MonitorConnected(mid![1], monitor_sender![1]) => {};
+        FromMonitor(mid![1], MonitorToBroker::UnpinArtifacts(vec![digest![42]])) => {
+            CacheUnpinArtifact(digest![42]),
+            CachePinnedArtifacts,
+            ToMonitor(mid![1], BrokerToMonitor::PinnedArtifacts(vec![digest![41]])),
+        };
+    }
+
     script_test! {
         job_statistics_waiting_for_artifacts,
         {
@@ -2192,14 +3311,15 @@ mod tests {
             ], [], [], [])
         },
         ClientConnected(cid![1], client_sender![1]) => {};
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
         MonitorConnected(mid![1], monitor_sender![1]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, [(42, Tar)]])) => {
-            CacheGetArtifact(jid![1, 1], digest![42]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, [(42, Tar)]]))) => {
+            CacheGetArtifact(jid![1, 1], digest![42], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForLayers)),
         };
         StatisticsHeartbeat => {};
         FromMonitor(mid![1], MonitorToBroker::StatisticsRequest) => {
+            CacheEvictionStatistics,
             ToMonitor(mid![1], BrokerToMonitor::StatisticsResponse(BrokerStatistics {
                 worker_statistics: hashmap! {
                     wid![1] => WorkerStatistics { slots: 2 }
@@ -2213,7 +3333,8 @@ mod tests {
                             JobState::Complete => 0,
                         }
                     }
-                }].into_iter().collect()
+                }].into_iter().collect(),
+                eviction_statistics: EvictionStatistics::default(),
             }))
         }
     }
@@ -2227,12 +3348,13 @@ mod tests {
         },
         ClientConnected(cid![1], client_sender![1]) => {};
         MonitorConnected(mid![1], monitor_sender![1]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
             ToClient(cid![1], BrokerToClient::JobStatusUpdate(cjid![1], JobBrokerStatus::WaitingForWorker)),
         };
         StatisticsHeartbeat => {};
         FromMonitor(mid![1], MonitorToBroker::StatisticsRequest) => {
+            CacheEvictionStatistics,
             ToMonitor(mid![1], BrokerToMonitor::StatisticsResponse(BrokerStatistics {
                 worker_statistics: hashmap!{},
                 job_statistics: [JobStatisticsSample {
@@ -2244,7 +3366,8 @@ mod tests {
                             JobState::Complete => 0,
                         }
                     }
-                }].into_iter().collect()
+                }].into_iter().collect(),
+                eviction_statistics: EvictionStatistics::default(),
             }))
         }
     }
@@ -2257,14 +3380,15 @@ mod tests {
             ], [], [], [])
         },
         ClientConnected(cid![1], client_sender![1]) => {};
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
         MonitorConnected(mid![1], monitor_sender![1]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
         StatisticsHeartbeat => {};
         FromMonitor(mid![1], MonitorToBroker::StatisticsRequest) => {
+            CacheEvictionStatistics,
             ToMonitor(mid![1], BrokerToMonitor::StatisticsResponse(BrokerStatistics {
                 worker_statistics: hashmap! {
                     wid![1] => WorkerStatistics { slots: 2 }
@@ -2278,7 +3402,8 @@ mod tests {
                             JobState::Complete => 0,
                         }
                     }
-                }].into_iter().collect()
+                }].into_iter().collect(),
+                eviction_statistics: EvictionStatistics::default(),
             }))
         }
     }
@@ -2291,18 +3416,19 @@ mod tests {
             ], [], [], [])
         },
         ClientConnected(cid![1], client_sender![1]) => {};
-        WorkerConnected(wid![1], 2, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 2, None, worker_sender![1]) => {};
         MonitorConnected(mid![1], monitor_sender![1]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 1], digest![1]),
-            ToWorker(wid![1], EnqueueJob(jid![1, 1], spec![1, Tar])),
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![1], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 1], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![1], EnqueueJob(jid![1, 1], Box::new(spec![1, Tar]))),
         };
-        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Ok(outcome![1]))) => {
-            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Ok(outcome![1]))),
+        FromWorker(wid![1], WorkerToBroker::JobResponse(jid![1, 1], Box::new(Ok(outcome![1])))) => {
+            ToClient(cid![1], BrokerToClient::JobResponse(cjid![1], Box::new(Ok(outcome![1])))),
             CacheDecrementRefcount(digest![1]),
         };
         StatisticsHeartbeat => {};
         FromMonitor(mid![1], MonitorToBroker::StatisticsRequest) => {
+            CacheEvictionStatistics,
             ToMonitor(mid![1], BrokerToMonitor::StatisticsResponse(BrokerStatistics {
                 worker_statistics: hashmap! {
                     wid![1] => WorkerStatistics { slots: 2 }
@@ -2316,7 +3442,32 @@ mod tests {
                             JobState::Complete => 1,
                         }
                     }
-                }].into_iter().collect()
+                }].into_iter().collect(),
+                eviction_statistics: EvictionStatistics::default(),
+            }))
+        }
+    }
+
+    script_test! {
+        eviction_statistics,
+        {
+            Fixture::default().eviction_statistics_returns(EvictionStatistics {
+                runs: 2,
+                artifacts: 5,
+                bytes: 1000,
+            })
+        },
+        MonitorConnected(mid![1], monitor_sender![1]) => {};
+        FromMonitor(mid![1], MonitorToBroker::StatisticsRequest) => {
+            CacheEvictionStatistics,
+            ToMonitor(mid![1], BrokerToMonitor::StatisticsResponse(BrokerStatistics {
+                worker_statistics: hashmap! {},
+                job_statistics: JobStatisticsTimeSeries::default(),
+                eviction_statistics: EvictionStatistics {
+                    runs: 2,
+                    artifacts: 5,
+                    bytes: 1000,
+                },
             }))
         }
     }
@@ -2329,10 +3480,10 @@ mod tests {
             ], [], [], [])
         },
         ClientConnected(cid![1], client_sender![1]) => {};
-        WorkerConnected(wid![2], 1, worker_sender![2]) => {};
-        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], spec![1, Tar])) => {
-            CacheGetArtifact(jid![1, 3], digest![1]),
-            ToWorker(wid![2], EnqueueJob(jid![1, 3], spec![1, Tar])),
+        WorkerConnected(wid![2], 1, None, worker_sender![2]) => {};
+        FromClient(cid![1], ClientToBroker::JobRequest(cjid![3], Box::new(spec![1, Tar]))) => {
+            CacheGetArtifact(jid![1, 3], digest![1], ArtifactKind::Tar),
+            ToWorker(wid![2], EnqueueJob(jid![1, 3], Box::new(spec![1, Tar]))),
         };
         FromWorker(
             wid![2],
@@ -2377,7 +3528,7 @@ mod tests {
         {
             Fixture::new([], [], [], [])
         },
-        WorkerConnected(wid![1], 1, worker_sender![1]) => {};
+        WorkerConnected(wid![1], 1, None, worker_sender![1]) => {};
         FromWorker(
             wid![1],
             WorkerToBroker::JobStatusUpdate(jid![2, 3], JobWorkerStatus::WaitingForLayers)
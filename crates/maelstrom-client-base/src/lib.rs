@@ -14,7 +14,7 @@ pub use proto_buf_conv::{IntoProtoBuf, TryFromProtoBuf};
 
 use derive_more::{From, Into};
 use maelstrom_base::{
-    stats::JobState, ClientJobId, JobBrokerStatus, JobOutcomeResult, JobWorkerStatus,
+    stats::JobState, ClientJobId, JobBrokerStatus, JobOutcomeResult, JobWorkerStatus, Sha256Digest,
 };
 use maelstrom_container::ContainerImageDepotDir;
 use maelstrom_macro::{IntoProtoBuf, TryFromProtoBuf};
@@ -23,7 +23,7 @@ use maelstrom_util::{
     root::RootBuf,
 };
 use serde::Deserialize;
-use std::fmt;
+use std::{fmt, path::PathBuf};
 
 /// The project directory is used for two things. First, any relative paths in layer specifications
 /// are resolved based on this path. Second, it's where the client process looks for the
@@ -50,6 +50,7 @@ pub struct CacheDir;
 pub const MANIFEST_DIR: &str = "manifests";
 pub const STUB_MANIFEST_DIR: &str = "manifests/stubs";
 pub const SYMLINK_MANIFEST_DIR: &str = "manifests/symlinks";
+pub const STDIN_DIR: &str = "stdin";
 pub const SO_LISTINGS_DIR: &str = "so_listings";
 
 impl From<proto::Error> for anyhow::Error {
@@ -200,7 +201,7 @@ pub enum JobStatus {
     Completed {
         client_job_id: ClientJobId,
         #[proto(option)]
-        result: JobOutcomeResult,
+        result: Box<JobOutcomeResult>,
     },
 }
 
@@ -239,3 +240,10 @@ pub struct AddContainerRequest {
     #[proto(option)]
     pub container: spec::ContainerSpec,
 }
+
+#[derive(IntoProtoBuf, TryFromProtoBuf)]
+#[proto(proto_buf_type = "proto::DownloadArtifactRequest")]
+pub struct DownloadArtifactRequest {
+    pub digest: Sha256Digest,
+    pub path: PathBuf,
+}
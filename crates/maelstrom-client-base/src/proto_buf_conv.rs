@@ -3,16 +3,26 @@ use anyhow::{anyhow, Result};
 use enumset::{EnumSet, EnumSetType};
 use maelstrom_base::{
     client_job_id_pocket_definition, group_id_pocket_definition,
-    job_broker_status_pocket_definition, job_completed_pocket_definition,
-    job_device_pocket_definition, job_effects_pocket_definition, job_mount_pocket_definition,
-    job_network_pocket_definition, job_outcome_pocket_definition,
-    job_output_result_pocket_definition, job_root_overlay_pocket_definition,
+    job_blocked_syscall_pocket_definition, job_broker_status_pocket_definition,
+    job_cache_sharing_pocket_definition, job_clock_offsets_pocket_definition,
+    job_completed_pocket_definition, job_core_dump_pocket_definition, job_device_pocket_definition,
+    job_effects_pocket_definition, job_forward_pocket_definition, job_host_pocket_definition,
+    job_limits_pocket_definition, job_mount_pocket_definition, job_network_pocket_definition,
+    job_outcome_pocket_definition, job_output_result_pocket_definition,
+    job_readiness_check_pocket_definition, job_resource_usage_pocket_definition,
+    job_rlimit_pocket_definition, job_rlimit_resource_pocket_definition,
+    job_root_overlay_pocket_definition, job_seccomp_action_pocket_definition,
+    job_seccomp_pocket_definition, job_seccomp_profile_pocket_definition,
     job_termination_status_pocket_definition, job_tty_pocket_definition,
-    job_worker_status_pocket_definition, timeout_pocket_definition, user_id_pocket_definition,
-    window_size_pocket_definition, worker_id_pocket_definition, ClientJobId, GroupId,
-    JobBrokerStatus, JobCompleted, JobDevice, JobEffects, JobMount, JobNetwork, JobOutcome,
-    JobOutputResult, JobRootOverlay, JobTerminationStatus, JobTty, JobWorkerStatus, Timeout,
-    UserId, Utf8PathBuf, WindowSize, WorkerId,
+    job_user_entries_pocket_definition, job_worker_status_pocket_definition,
+    job_writable_layer_pocket_definition, timeout_pocket_definition, user_id_pocket_definition,
+    window_size_pocket_definition, worker_id_pocket_definition, ArtifactType, ClientJobId, GroupId,
+    JobBlockedSyscall, JobBrokerStatus, JobCacheSharing, JobClockOffsets, JobCompleted,
+    JobCoreDump, JobDevice, JobEffects, JobForward, JobHost, JobLimits, JobMount, JobNetwork,
+    JobOutcome, JobOutputResult, JobReadinessCheck, JobResourceUsage, JobRlimit, JobRlimitResource,
+    JobRootOverlay, JobSeccomp, JobSeccompAction, JobSeccompProfile, JobTerminationStatus, JobTty,
+    JobUserEntries, JobWorkerStatus, JobWritableLayer, Sha256Digest, Timeout, UserId, Utf8PathBuf,
+    WindowSize, WorkerId,
 };
 use maelstrom_macro::{
     into_proto_buf_remote_derive, remote_derive, try_from_proto_buf_remote_derive,
@@ -95,6 +105,22 @@ impl TryFromProtoBuf for i8 {
     }
 }
 
+impl IntoProtoBuf for i64 {
+    type ProtoBufType = i64;
+
+    fn into_proto_buf(self) -> i64 {
+        self
+    }
+}
+
+impl TryFromProtoBuf for i64 {
+    type ProtoBufType = i64;
+
+    fn try_from_proto_buf(v: i64) -> Result<Self> {
+        Ok(v)
+    }
+}
+
 impl IntoProtoBuf for u8 {
     type ProtoBufType = u32;
 
@@ -255,6 +281,22 @@ impl<V: TryFromProtoBuf> TryFromProtoBuf for Option<V> {
     }
 }
 
+impl<V: IntoProtoBuf> IntoProtoBuf for Box<V> {
+    type ProtoBufType = V::ProtoBufType;
+
+    fn into_proto_buf(self) -> V::ProtoBufType {
+        (*self).into_proto_buf()
+    }
+}
+
+impl<V: TryFromProtoBuf> TryFromProtoBuf for Box<V> {
+    type ProtoBufType = V::ProtoBufType;
+
+    fn try_from_proto_buf(v: Self::ProtoBufType) -> Result<Self> {
+        Ok(Box::new(V::try_from_proto_buf(v)?))
+    }
+}
+
 impl<V: IntoProtoBuf> IntoProtoBuf for Vec<V> {
     type ProtoBufType = Vec<V::ProtoBufType>;
 
@@ -348,6 +390,22 @@ impl TryFromProtoBuf for std::time::Duration {
     }
 }
 
+impl IntoProtoBuf for std::net::IpAddr {
+    type ProtoBufType = String;
+
+    fn into_proto_buf(self) -> String {
+        self.to_string()
+    }
+}
+
+impl TryFromProtoBuf for std::net::IpAddr {
+    type ProtoBufType = String;
+
+    fn try_from_proto_buf(s: String) -> Result<Self> {
+        Ok(s.parse()?)
+    }
+}
+
 //  _____         _                        _
 // |___ / _ __ __| |      _ __   __ _ _ __| |_ _   _
 //   |_ \| '__/ _` |_____| '_ \ / _` | '__| __| | | |
@@ -449,6 +507,67 @@ impl<V: TryFromProtoBuf> TryFromProtoBuf for maelstrom_base::NonEmpty<V> {
     }
 }
 
+impl IntoProtoBuf for Sha256Digest {
+    type ProtoBufType = Vec<u8>;
+
+    fn into_proto_buf(self) -> Vec<u8> {
+        self.into()
+    }
+}
+
+impl TryFromProtoBuf for Sha256Digest {
+    type ProtoBufType = Vec<u8>;
+
+    fn try_from_proto_buf(v: Vec<u8>) -> Result<Self> {
+        Ok(v.try_into()?)
+    }
+}
+
+impl IntoProtoBuf for ArtifactType {
+    type ProtoBufType = i32;
+
+    fn into_proto_buf(self) -> i32 {
+        (match self {
+            ArtifactType::Tar => proto::ArtifactType::Tar,
+            ArtifactType::Manifest => proto::ArtifactType::Manifest,
+        }) as i32
+    }
+}
+
+impl TryFromProtoBuf for ArtifactType {
+    type ProtoBufType = i32;
+
+    fn try_from_proto_buf(v: i32) -> Result<Self> {
+        Ok(match proto::ArtifactType::try_from(v)? {
+            proto::ArtifactType::Tar => ArtifactType::Tar,
+            proto::ArtifactType::Manifest => ArtifactType::Manifest,
+        })
+    }
+}
+
+impl IntoProtoBuf for (Sha256Digest, ArtifactType) {
+    type ProtoBufType = proto::DigestLayer;
+
+    fn into_proto_buf(self) -> proto::DigestLayer {
+        let (digest, artifact_type) = self;
+        proto::DigestLayer {
+            digest: digest.into_proto_buf(),
+            artifact_type: artifact_type.into_proto_buf(),
+        }
+    }
+}
+
+impl TryFromProtoBuf for (Sha256Digest, ArtifactType) {
+    type ProtoBufType = proto::DigestLayer;
+
+    fn try_from_proto_buf(v: proto::DigestLayer) -> Result<Self> {
+        Ok((
+            TryFromProtoBuf::try_from_proto_buf(v.digest)?,
+            TryFromProtoBuf::try_from_proto_buf(v.artifact_type)?,
+        ))
+    }
+}
+
 remote_derive!(
     UserId,
     (IntoProtoBuf, TryFromProtoBuf),
@@ -503,16 +622,132 @@ remote_derive!(
     proto(proto_buf_type = "proto::job_completed::Status")
 );
 
+remote_derive!(
+    JobCacheSharing,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobCacheSharing")
+);
+
 remote_derive!(
     JobNetwork,
     (IntoProtoBuf, TryFromProtoBuf),
     proto(proto_buf_type = "proto::JobNetwork")
 );
 
+remote_derive!(
+    JobResourceUsage,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobResourceUsage"),
+    @user_time: proto(option),
+    @system_time: proto(option),
+);
+
+remote_derive!(
+    JobCoreDump,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobCoreDump")
+);
+
+remote_derive!(
+    JobWritableLayer,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobWritableLayer")
+);
+
+remote_derive!(
+    JobBlockedSyscall,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobBlockedSyscall")
+);
+
 remote_derive!(
     JobEffects,
     (IntoProtoBuf, TryFromProtoBuf),
-    proto(proto_buf_type = "proto::JobEffects", option_all)
+    proto(proto_buf_type = "proto::JobEffects"),
+    @stdout: proto(option),
+    @stderr: proto(option),
+    @duration: proto(option),
+);
+
+remote_derive!(
+    JobLimits,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobLimits")
+);
+
+remote_derive!(
+    JobRlimitResource,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobRlimitResource")
+);
+
+remote_derive!(
+    JobRlimit,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobRlimit")
+);
+
+remote_derive!(
+    JobSeccompProfile,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(
+        proto_buf_type = "proto::JobSeccompProfile",
+        enum_type = "proto::job_seccomp_profile::Profile"
+    ),
+    @Allow: proto(proto_buf_type = "proto::JobSeccompSyscalls"),
+    @Deny: proto(proto_buf_type = "proto::JobSeccompSyscalls"),
+);
+
+remote_derive!(
+    JobSeccompAction,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(
+        proto_buf_type = "proto::JobSeccompAction",
+        enum_type = "proto::job_seccomp_action::Action"
+    )
+);
+
+remote_derive!(
+    JobForward,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(
+        proto_buf_type = "proto::JobForward",
+        enum_type = "proto::job_forward::Forward"
+    ),
+    @Tcp: proto(proto_buf_type = "proto::JobTcpForward"),
+    @Unix: proto(proto_buf_type = "proto::JobUnixForward"),
+);
+
+remote_derive!(
+    JobReadinessCheck,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobReadinessCheck")
+);
+
+remote_derive!(
+    JobSeccomp,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobSeccomp"),
+    @profile: proto(option),
+    @action: proto(option),
+);
+
+remote_derive!(
+    JobUserEntries,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobUserEntries")
+);
+
+remote_derive!(
+    JobClockOffsets,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobClockOffsets")
+);
+
+remote_derive!(
+    JobHost,
+    (IntoProtoBuf, TryFromProtoBuf),
+    proto(proto_buf_type = "proto::JobHost")
 );
 
 remote_derive!(
@@ -527,8 +762,10 @@ remote_derive!(
     (IntoProtoBuf, TryFromProtoBuf),
     proto(proto_buf_type = "proto::JobMount", enum_type = "proto::job_mount::Mount"),
     @Bind: proto(proto_buf_type = "proto::BindMount"),
+    @Cache: proto(proto_buf_type = "proto::CacheMount"),
     @Devices: proto(proto_buf_type = "proto::DevicesMount"),
     @Devpts: proto(proto_buf_type = "proto::DevptsMount"),
+    @Layer: proto(proto_buf_type = "proto::LayerMount"),
     @Mqueue: proto(proto_buf_type = "proto::MqueueMount"),
     @Proc: proto(proto_buf_type = "proto::ProcMount"),
     @Sys: proto(proto_buf_type = "proto::SysMount"),
@@ -542,6 +779,7 @@ remote_derive!(
         proto_buf_type = "proto::JobRootOverlay",
         enum_type = "proto::job_root_overlay::Overlay"
     ),
+    @Tmp: proto(proto_buf_type = "proto::TmpJobRootOverlay"),
     @Local: proto(proto_buf_type = "proto::LocalJobRootOverlay"),
 );
 
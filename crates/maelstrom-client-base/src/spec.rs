@@ -11,7 +11,9 @@ use anyhow::{anyhow, bail, Error, Result};
 use derive_more::From;
 use enumset::{EnumSet, EnumSetType};
 use maelstrom_base::{
-    enum_set, GroupId, JobMount, JobNetwork, JobRootOverlay, JobTty, Timeout, UserId, Utf8PathBuf,
+    enum_set, GroupId, JobClockOffsets, JobForward, JobHost, JobLimits, JobMount,
+    JobMountForTomlAndJson, JobNetwork, JobReadinessCheck, JobRlimit, JobRootOverlay, JobSeccomp,
+    JobTty, JobUserEntries, Timeout, UserId, Utf8PathBuf,
 };
 use maelstrom_util::template::{replace_template_vars, TemplateVars};
 use serde::{de, Deserialize, Deserializer, Serialize};
@@ -181,6 +183,8 @@ pub struct ContainerSpec {
     pub network: JobNetwork,
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
+    /// Mounted before `mounts`, so a mount in `mounts` can be placed inside a layer mount.
+    pub layer_mounts: Vec<LayerMountSpec>,
 }
 
 impl ContainerSpec {
@@ -228,6 +232,165 @@ impl ContainerRef {
     }
 }
 
+/// Where a job's standard input comes from. A file will be uploaded as an artifact.
+#[derive(IntoProtoBuf, TryFromProtoBuf, Clone, Debug, PartialEq, Eq)]
+#[proto(
+    proto_buf_type = "proto::JobStdinSpec",
+    enum_type = "proto::job_stdin_spec::Source"
+)]
+pub enum JobStdinSpec {
+    Inline(Box<[u8]>),
+    File(Utf8PathBuf),
+}
+
+/// A service program run alongside a job in the job's network namespace. See
+/// [`maelstrom_base::JobSidecar`].
+#[derive(IntoProtoBuf, TryFromProtoBuf, Clone, Debug, PartialEq, Eq)]
+#[proto(proto_buf_type = "proto::SidecarSpec")]
+pub struct SidecarSpec {
+    pub name: String,
+    #[proto(option)]
+    pub container: ContainerRef,
+    pub program: Utf8PathBuf,
+    pub arguments: Vec<String>,
+    pub ready: Option<JobReadinessCheck>,
+}
+
+impl SidecarSpec {
+    pub fn new(
+        name: impl Into<String>,
+        program: impl Into<String>,
+        layers: impl Into<Vec<LayerSpec>>,
+    ) -> Self {
+        let JobSpec {
+            container, program, ..
+        } = JobSpec::new(program, layers);
+        SidecarSpec {
+            name: name.into(),
+            container,
+            program,
+            arguments: Default::default(),
+            ready: Default::default(),
+        }
+    }
+
+    pub fn arguments<I, T>(mut self, arguments: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.arguments = arguments.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn ready(mut self, ready: Option<JobReadinessCheck>) -> Self {
+        self.ready = ready;
+        self
+    }
+}
+
+/// A [`SidecarSpec`] as it appears in TOML and JSON job specifications. Unlike jobs, sidecars
+/// always have an inline container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SidecarSpecForTomlAndJson(pub SidecarSpec);
+
+impl<'de> Deserialize<'de> for SidecarSpecForTomlAndJson {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use de::Error as _;
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Ready {
+            port: u16,
+            timeout: Option<u32>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            name: String,
+            program: Utf8PathBuf,
+            #[serde(default)]
+            arguments: Vec<String>,
+            image: Option<Image>,
+            #[serde(default)]
+            layers: Vec<LayerSpec>,
+            #[serde(default)]
+            environment: BTreeMap<String, String>,
+            #[serde(default)]
+            mounts: Vec<JobMountForTomlAndJson>,
+            #[serde(default)]
+            enable_writable_file_system: bool,
+            writable_file_system_size: Option<u64>,
+            writable_file_system_inodes: Option<u64>,
+            working_directory: Option<Utf8PathBuf>,
+            user: Option<UserId>,
+            group: Option<GroupId>,
+            ready: Option<Ready>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        let ready = fields
+            .ready
+            .map(|Ready { port, timeout }| {
+                Timeout::new(timeout.unwrap_or(JobReadinessCheck::DEFAULT_TIMEOUT_SECONDS))
+                    .map(|timeout| JobReadinessCheck { port, timeout })
+                    .ok_or_else(|| D::Error::custom("field `ready.timeout` must be nonzero"))
+            })
+            .transpose()?;
+        let image = fields.image.map(|Image { name, use_ }| ImageSpec {
+            name,
+            use_layers: use_.contains(ImageUse::Layers),
+            use_environment: use_.contains(ImageUse::Environment),
+            use_working_directory: use_.contains(ImageUse::WorkingDirectory)
+                && fields.working_directory.is_none(),
+        });
+        if image.is_none() && fields.layers.is_empty() {
+            return Err(D::Error::custom(
+                "at least one of `image` or `layers` must be provided",
+            ));
+        }
+        let container = ContainerSpec {
+            image,
+            layers: fields.layers,
+            root_overlay: if fields.enable_writable_file_system {
+                JobRootOverlay::Tmp {
+                    size: fields.writable_file_system_size,
+                    inodes: fields.writable_file_system_inodes,
+                }
+            } else {
+                JobRootOverlay::None
+            },
+            environment: vec![EnvironmentSpec {
+                vars: fields.environment,
+                extend: true,
+            }],
+            working_directory: fields.working_directory,
+            mounts: fields.mounts.into_iter().map(Into::into).collect(),
+            network: Default::default(),
+            user: fields.user,
+            group: fields.group,
+            layer_mounts: Default::default(),
+        };
+        Ok(Self(SidecarSpec {
+            name: fields.name,
+            container: container.into(),
+            program: fields.program,
+            arguments: fields.arguments,
+            ready,
+        }))
+    }
+}
+
+impl From<SidecarSpecForTomlAndJson> for SidecarSpec {
+    fn from(sidecar: SidecarSpecForTomlAndJson) -> Self {
+        sidecar.0
+    }
+}
+
 #[derive(IntoProtoBuf, TryFromProtoBuf, Clone, Debug, PartialEq, Eq)]
 #[proto(proto_buf_type = "proto::JobSpec")]
 pub struct JobSpec {
@@ -239,6 +402,20 @@ pub struct JobSpec {
     pub estimated_duration: Option<Duration>,
     pub allocate_tty: Option<JobTty>,
     pub priority: i8,
+    pub stdin: Option<JobStdinSpec>,
+    #[proto(option)]
+    pub limits: JobLimits,
+    pub rlimits: Vec<JobRlimit>,
+    pub seccomp: Option<JobSeccomp>,
+    pub init: bool,
+    pub forwards: Vec<JobForward>,
+    pub sidecars: Vec<SidecarSpec>,
+    pub user_entries: Option<JobUserEntries>,
+    pub hostname: Option<String>,
+    pub extra_hosts: Vec<JobHost>,
+    pub clock_offsets: Option<JobClockOffsets>,
+    pub capture_core_dump: bool,
+    pub keep_writable_layer_on_failure: bool,
 }
 
 impl JobSpec {
@@ -254,6 +431,7 @@ impl JobSpec {
                 working_directory: Default::default(),
                 user: Default::default(),
                 group: Default::default(),
+                layer_mounts: Default::default(),
             }
             .into(),
             program: program.into().into(),
@@ -262,6 +440,19 @@ impl JobSpec {
             estimated_duration: Default::default(),
             allocate_tty: Default::default(),
             priority: Default::default(),
+            stdin: Default::default(),
+            limits: Default::default(),
+            rlimits: Default::default(),
+            seccomp: Default::default(),
+            init: Default::default(),
+            forwards: Default::default(),
+            sidecars: Default::default(),
+            user_entries: Default::default(),
+            hostname: Default::default(),
+            extra_hosts: Default::default(),
+            clock_offsets: Default::default(),
+            capture_core_dump: Default::default(),
+            keep_writable_layer_on_failure: Default::default(),
         }
     }
 
@@ -289,6 +480,11 @@ impl JobSpec {
         self
     }
 
+    pub fn layer_mounts(mut self, layer_mounts: impl IntoIterator<Item = LayerMountSpec>) -> Self {
+        self.container.as_inline_mut().unwrap().layer_mounts = layer_mounts.into_iter().collect();
+        self
+    }
+
     pub fn network(mut self, network: JobNetwork) -> Self {
         self.container.as_inline_mut().unwrap().network = network;
         self
@@ -324,6 +520,71 @@ impl JobSpec {
         self.priority = priority;
         self
     }
+
+    pub fn stdin(mut self, stdin: Option<impl Into<JobStdinSpec>>) -> Self {
+        self.stdin = stdin.map(Into::into);
+        self
+    }
+
+    pub fn limits(mut self, limits: JobLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn rlimits(mut self, rlimits: impl IntoIterator<Item = JobRlimit>) -> Self {
+        self.rlimits = rlimits.into_iter().collect();
+        self
+    }
+
+    pub fn seccomp(mut self, seccomp: Option<JobSeccomp>) -> Self {
+        self.seccomp = seccomp;
+        self
+    }
+
+    pub fn init(mut self, init: bool) -> Self {
+        self.init = init;
+        self
+    }
+
+    pub fn forwards(mut self, forwards: impl IntoIterator<Item = JobForward>) -> Self {
+        self.forwards = forwards.into_iter().collect();
+        self
+    }
+
+    pub fn sidecars(mut self, sidecars: impl IntoIterator<Item = SidecarSpec>) -> Self {
+        self.sidecars = sidecars.into_iter().collect();
+        self
+    }
+
+    pub fn user_entries(mut self, user_entries: Option<JobUserEntries>) -> Self {
+        self.user_entries = user_entries;
+        self
+    }
+
+    pub fn hostname(mut self, hostname: Option<impl Into<String>>) -> Self {
+        self.hostname = hostname.map(Into::into);
+        self
+    }
+
+    pub fn extra_hosts(mut self, extra_hosts: impl IntoIterator<Item = JobHost>) -> Self {
+        self.extra_hosts = extra_hosts.into_iter().collect();
+        self
+    }
+
+    pub fn clock_offsets(mut self, clock_offsets: Option<JobClockOffsets>) -> Self {
+        self.clock_offsets = clock_offsets;
+        self
+    }
+
+    pub fn capture_core_dump(mut self, capture_core_dump: bool) -> Self {
+        self.capture_core_dump = capture_core_dump;
+        self
+    }
+
+    pub fn keep_writable_layer_on_failure(mut self, keep_writable_layer_on_failure: bool) -> Self {
+        self.keep_writable_layer_on_failure = keep_writable_layer_on_failure;
+        self
+    }
 }
 
 #[derive(
@@ -439,6 +700,27 @@ impl LayerSpec {
     }
 }
 
+/// A stack of layers mounted read-only at `mount_point`, separate from the container's root file
+/// system. The layers are stacked in the same way as a container's layers.
+#[derive(
+    Clone, Debug, Deserialize, Eq, Hash, IntoProtoBuf, PartialEq, Serialize, TryFromProtoBuf,
+)]
+#[proto(proto_buf_type = "proto::LayerMountSpec")]
+#[serde(deny_unknown_fields)]
+pub struct LayerMountSpec {
+    pub mount_point: Utf8PathBuf,
+    pub layers: Vec<LayerSpec>,
+}
+
+impl LayerMountSpec {
+    pub fn replace_template_vars(&mut self, vars: &TemplateVars) -> Result<()> {
+        for layer in &mut self.layers {
+            layer.replace_template_vars(vars)?;
+        }
+        Ok(())
+    }
+}
+
 /// An enum and struct (`EnumSet<ImageUse>`) used for deserializing "image use" statements in JSON,
 /// TOML, or other similar formats. This allows users to specify things like
 /// `use = ["layers", "environment"]` in TOML, or the equivalent in JSON.
@@ -863,4 +1145,104 @@ mod tests {
             })
         );
     }
+
+    fn parse_sidecar(file: &str) -> Result<SidecarSpec, toml::de::Error> {
+        toml::from_str::<SidecarSpecForTomlAndJson>(file).map(Into::into)
+    }
+
+    #[test]
+    fn sidecar_deserialize() {
+        assert_eq!(
+            parse_sidecar(indoc! {r#"
+                name = "db"
+                program = "/usr/bin/postgres"
+                arguments = ["-D", "/data"]
+                image = { name = "postgres", use = ["layers", "working_directory"] }
+                environment = { PGDATA = "/data" }
+                enable_writable_file_system = true
+                writable_file_system_size = 1048576
+                ready = { port = 5432 }
+            "#})
+            .unwrap(),
+            SidecarSpec {
+                name: "db".into(),
+                container: ContainerSpec {
+                    image: Some(ImageSpec {
+                        name: "postgres".into(),
+                        use_layers: true,
+                        use_environment: false,
+                        use_working_directory: true,
+                    }),
+                    layers: vec![],
+                    root_overlay: JobRootOverlay::Tmp {
+                        size: Some(1048576),
+                        inodes: None,
+                    },
+                    environment: vec![EnvironmentSpec {
+                        vars: btreemap! { string!("PGDATA") => string!("/data") },
+                        extend: true,
+                    }],
+                    working_directory: None,
+                    mounts: vec![],
+                    network: JobNetwork::Disabled,
+                    user: None,
+                    group: None,
+                    layer_mounts: vec![],
+                }
+                .into(),
+                program: "/usr/bin/postgres".into(),
+                arguments: string_vec!["-D", "/data"],
+                ready: Some(JobReadinessCheck {
+                    port: 5432,
+                    timeout: Timeout::new(JobReadinessCheck::DEFAULT_TIMEOUT_SECONDS).unwrap(),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn sidecar_deserialize_explicit_working_directory_overrides_image() {
+        let sidecar = parse_sidecar(indoc! {r#"
+            name = "db"
+            program = "/usr/bin/postgres"
+            image = { name = "postgres", use = ["layers", "working_directory"] }
+            working_directory = "/data"
+            ready = { port = 5432, timeout = 5 }
+        "#})
+        .unwrap();
+        let container = sidecar.container.as_inline().unwrap();
+        assert!(!container.image.as_ref().unwrap().use_working_directory);
+        assert_eq!(container.working_directory, Some("/data".into()));
+        assert_eq!(sidecar.ready.unwrap().timeout, Timeout::new(5).unwrap());
+    }
+
+    #[test]
+    fn sidecar_deserialize_needs_layers() {
+        let err = parse_sidecar(indoc! {r#"
+            name = "db"
+            program = "/usr/bin/postgres"
+        "#})
+        .unwrap_err();
+        assert!(
+            err.message()
+                .contains("at least one of `image` or `layers` must be provided"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn sidecar_deserialize_zero_timeout() {
+        let err = parse_sidecar(indoc! {r#"
+            name = "db"
+            program = "/usr/bin/postgres"
+            layers = [{ tar = "postgres.tar" }]
+            ready = { port = 5432, timeout = 0 }
+        "#})
+        .unwrap_err();
+        assert!(
+            err.message()
+                .contains("field `ready.timeout` must be nonzero"),
+            "{err}"
+        );
+    }
 }
@@ -0,0 +1,54 @@
+use anyhow::{anyhow, Result};
+use async_compression::tokio::bufread::ZstdDecoder;
+use maelstrom_base::{
+    proto::{
+        ArtifactCompression, ArtifactFetcherToBroker, ArtifactSource, BrokerToArtifactFetcher,
+        Hello,
+    },
+    Sha256Digest,
+};
+use maelstrom_util::{async_fs::Fs, config::common::BrokerAddr, net};
+use std::path::Path;
+use tokio::{
+    io::{self, AsyncReadExt as _},
+    net::TcpStream,
+};
+
+/// Fetch the artifact with the given digest from the broker and write it to `path`. This is used
+/// for artifacts that workers have pushed to the broker, like jobs' core files.
+pub async fn fetch_one_artifact(
+    broker_addr: BrokerAddr,
+    digest: Sha256Digest,
+    path: &Path,
+) -> Result<()> {
+    let mut stream = TcpStream::connect(broker_addr.inner()).await?;
+    net::write_message_to_async_socket(&mut stream, Hello::ArtifactFetcher).await?;
+    // Artifacts like core files are never in workers' caches, so there's no point in asking for a
+    // peer.
+    let msg = ArtifactFetcherToBroker {
+        digest,
+        allow_peer: false,
+        accept_compression: ArtifactCompression::Zstd,
+    };
+    net::write_message_to_async_socket(&mut stream, msg).await?;
+
+    let BrokerToArtifactFetcher(resp) = net::read_message_from_async_socket(&mut stream).await?;
+    let ArtifactSource::Broker(size, compression) =
+        resp.map_err(|e| anyhow!("Error from broker: {e}"))?
+    else {
+        return Err(anyhow!("broker sent us to a peer for an artifact"));
+    };
+
+    let mut file = Fs::new().create_file(path).await?;
+    let copied = match compression {
+        ArtifactCompression::None => io::copy(&mut (&mut stream).take(size), &mut file).await?,
+        ArtifactCompression::Zstd => {
+            let decoder = ZstdDecoder::new(io::BufReader::new(&mut stream));
+            io::copy(&mut decoder.take(size), &mut file).await?
+        }
+    };
+    if copied != size {
+        return Err(anyhow!("got unexpected EOF receiving artifact"));
+    }
+    Ok(())
+}
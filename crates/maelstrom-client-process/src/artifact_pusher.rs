@@ -1,18 +1,35 @@
 use crate::progress::{ProgressTracker, UploadProgressReader};
 use anyhow::{anyhow, Context as _, Result};
+use async_compression::tokio::bufread::ZstdEncoder;
 use maelstrom_base::{
-    proto::{ArtifactPusherToBroker, BrokerToArtifactPusher, Hello},
+    proto::{
+        ArtifactChunk, ArtifactCompression, BrokerToArtifactPusherHello,
+        BrokerToChunkedArtifactPusher, ChunkedArtifactPusherToBroker, Hello,
+    },
     Sha256Digest,
 };
-use maelstrom_util::{async_fs::Fs, config::common::BrokerAddr, net};
-use std::path::{Path, PathBuf};
+use maelstrom_util::{
+    async_fs::Fs,
+    chunking::{self, Chunk},
+    config::common::BrokerAddr,
+    fs, net,
+};
+use std::{
+    collections::HashSet,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
 use tokio::{
-    io::{self, AsyncReadExt as _},
+    io::{self, AsyncReadExt as _, AsyncSeekExt as _},
     net::TcpStream,
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
-    task::JoinSet,
+    task::{self, JoinSet},
 };
 
+/// How many times we try to push an artifact before giving up. The broker keeps the chunks it got
+/// on earlier attempts, so each attempt only sends what is still missing.
+const PUSH_ATTEMPTS: usize = 3;
+
 fn construct_upload_name(digest: &Sha256Digest, path: &Path) -> String {
     let digest_string = digest.to_string();
     let short_digest = &digest_string[digest_string.len() - 7..];
@@ -20,32 +37,126 @@ fn construct_upload_name(digest: &Sha256Digest, path: &Path) -> String {
     format!("{short_digest} {file_name}")
 }
 
-async fn push_one_artifact(
-    upload_tracker: ProgressTracker,
+async fn chunk_file(path: PathBuf) -> Result<Vec<Chunk>> {
+    task::spawn_blocking(move || {
+        let fs = fs::Fs::new();
+        Ok(chunking::chunk(fs.open_file(path)?)?)
+    })
+    .await?
+}
+
+fn unexpected_response(msg: BrokerToChunkedArtifactPusher) -> anyhow::Error {
+    match msg {
+        BrokerToChunkedArtifactPusher::Error(err) => anyhow!("Error from broker: {err}"),
+        msg => anyhow!("unexpected response from broker: {msg:?}"),
+    }
+}
+
+async fn push_missing_chunks(
+    upload_tracker: &ProgressTracker,
+    upload_name: &str,
     broker_addr: BrokerAddr,
-    path: PathBuf,
-    digest: Sha256Digest,
+    path: &Path,
+    digest: &Sha256Digest,
+    chunks: &[Chunk],
 ) -> Result<()> {
     let mut stream = TcpStream::connect(broker_addr.inner()).await?;
-    net::write_message_to_async_socket(&mut stream, Hello::ArtifactPusher).await?;
+    net::write_message_to_async_socket(&mut stream, Hello::ChunkedArtifactPusher).await?;
+    let BrokerToArtifactPusherHello { accept_compression } =
+        net::read_message_from_async_socket(&mut stream).await?;
 
-    let fs = Fs::new();
-    let file = fs.open_file(&path).await?;
-    let size = file.metadata().await?.len();
+    net::write_message_to_async_socket(
+        &mut stream,
+        ChunkedArtifactPusherToBroker::Artifact {
+            digest: digest.clone(),
+            size: chunks.iter().map(|chunk| chunk.size).sum(),
+            chunks: chunks
+                .iter()
+                .map(|chunk| ArtifactChunk {
+                    digest: chunk.digest.clone(),
+                    size: chunk.size,
+                })
+                .collect(),
+        },
+    )
+    .await?;
+    let missing = match net::read_message_from_async_socket(&mut stream).await? {
+        BrokerToChunkedArtifactPusher::MissingChunks(missing) => missing,
+        msg => return Err(unexpected_response(msg)),
+    };
 
-    let upload_name = construct_upload_name(&digest, &path);
-    let prog = upload_tracker.new_task(&upload_name, size);
+    let prog = upload_tracker.new_task(upload_name, missing.iter().map(|chunk| chunk.size).sum());
+    let mut missing: HashSet<_> = missing.into_iter().map(|chunk| chunk.digest).collect();
 
-    let mut file = UploadProgressReader::new(prog, file.chain(io::repeat(0)).take(size));
+    let mut file = Fs::new().open_file(path).await?;
+    for chunk in chunks {
+        if !missing.remove(&chunk.digest) {
+            continue;
+        }
 
-    net::write_message_to_async_socket(&mut stream, ArtifactPusherToBroker(digest, size)).await?;
-    let copied = io::copy(&mut file, &mut stream).await?;
-    assert_eq!(copied, size);
+        // The broker has to send artifacts on to every worker that needs them, so it is usually
+        // more constrained on bandwidth than we are on CPU. Compress whenever the broker accepts
+        // it.
+        net::write_message_to_async_socket(
+            &mut stream,
+            ChunkedArtifactPusherToBroker::Chunk(
+                ArtifactChunk {
+                    digest: chunk.digest.clone(),
+                    size: chunk.size,
+                },
+                accept_compression,
+            ),
+        )
+        .await?;
+        file.seek(SeekFrom::Start(chunk.offset)).await?;
+        let mut reader = io::BufReader::new(UploadProgressReader::new(
+            prog.clone(),
+            (&mut file).chain(io::repeat(0)).take(chunk.size),
+        ));
+        match accept_compression {
+            ArtifactCompression::None => {
+                io::copy(&mut reader, &mut stream).await?;
+            }
+            ArtifactCompression::Zstd => {
+                io::copy(&mut ZstdEncoder::new(reader), &mut stream).await?;
+            }
+        }
+    }
 
-    let BrokerToArtifactPusher(resp) = net::read_message_from_async_socket(&mut stream).await?;
+    net::write_message_to_async_socket(&mut stream, ChunkedArtifactPusherToBroker::Assemble)
+        .await?;
+    match net::read_message_from_async_socket(&mut stream).await? {
+        BrokerToChunkedArtifactPusher::ArtifactAssembled => Ok(()),
+        msg => Err(unexpected_response(msg)),
+    }
+}
 
-    upload_tracker.remove_task(&upload_name);
-    resp.map_err(|e| anyhow!("Error from broker: {e}"))
+async fn push_one_artifact(
+    upload_tracker: ProgressTracker,
+    broker_addr: BrokerAddr,
+    path: PathBuf,
+    digest: Sha256Digest,
+) -> Result<()> {
+    let chunks = chunk_file(path.clone()).await?;
+    let upload_name = construct_upload_name(&digest, &path);
+
+    let mut attempt = 1;
+    loop {
+        let result = push_missing_chunks(
+            &upload_tracker,
+            &upload_name,
+            broker_addr,
+            &path,
+            &digest,
+            &chunks,
+        )
+        .await;
+        upload_tracker.remove_task(&upload_name);
+        match result {
+            Err(_) if attempt < PUSH_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
 }
 
 pub struct Message {
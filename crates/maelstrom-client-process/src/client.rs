@@ -3,7 +3,7 @@ mod layer_cache;
 mod state_machine;
 
 use crate::{
-    artifact_pusher,
+    artifact_fetcher, artifact_pusher,
     digest_repo::DigestRepository,
     progress::{LazyProgress, ProgressTracker},
     router,
@@ -15,12 +15,13 @@ use layer_builder::LayerBuilder;
 use layer_cache::{CacheResult, LayerCache};
 use maelstrom_base::{
     proto::{Hello, WorkerToBroker},
-    ArtifactType, Sha256Digest,
+    validate_hostname, ArtifactType, GroupId, JobMount, JobNetwork, JobRootOverlay, JobSidecar,
+    JobStdin, NonEmpty, Sha256Digest, UserId, Utf8PathBuf,
 };
 use maelstrom_client_base::{
     spec::{
         environment_eval, std_env_lookup, ContainerRef, ContainerSpec, ConvertedImage, ImageConfig,
-        JobSpec, LayerSpec,
+        JobSpec, JobStdinSpec, LayerMountSpec, LayerSpec,
     },
     AcceptInvalidRemoteContainerTlsCerts, CacheDir, IntrospectResponse, JobStatus, ProjectDir,
     StateDir, MANIFEST_DIR, STUB_MANIFEST_DIR, SYMLINK_MANIFEST_DIR,
@@ -58,6 +59,8 @@ pub struct Client {
 }
 
 struct ClientState {
+    broker_addr: Option<BrokerAddr>,
+    local_worker_output_dir: RootBuf<local_worker::OutputDir>,
     local_broker_sender: router::Sender,
     layer_builder: Arc<LayerBuilder>,
     artifact_upload_tracker: ProgressTracker,
@@ -94,6 +97,18 @@ impl CleanUpWork {
     }
 }
 
+/// A container with its image and layers resolved.
+struct ResolvedContainer {
+    environment: Vec<String>,
+    layers: NonEmpty<(Sha256Digest, ArtifactType)>,
+    mounts: Vec<JobMount>,
+    network: JobNetwork,
+    root_overlay: JobRootOverlay,
+    working_directory: Option<Utf8PathBuf>,
+    user: Option<UserId>,
+    group: Option<GroupId>,
+}
+
 #[derive(Clone)]
 struct Uploader {
     log: slog::Logger,
@@ -194,6 +209,99 @@ impl ClientState {
             .collect())
     }
 
+    /// Look up a container if necessary, then resolve its image and layers into what the worker
+    /// needs to run a job or sidecar in it.
+    async fn resolve_container(&self, container: ContainerRef) -> Result<ResolvedContainer> {
+        let container = match container {
+            ContainerRef::Name(n) => {
+                let locked = self.locked.lock().await;
+                locked
+                    .containers
+                    .get(&n)
+                    .ok_or_else(|| anyhow!("container {n:?} unknown"))?
+                    .clone()
+            }
+            ContainerRef::Inline(c) => {
+                c.check_for_local_network_and_sys_mount()?;
+                c
+            }
+        };
+
+        let mut layers = container.layers;
+        let mut initial_env = Default::default();
+        let mut image_working_directory = None;
+        if let Some(image_spec) = container.image {
+            let image = self.get_container_image(&image_spec.name).await?;
+            let image_config = ImageConfig {
+                layers: image.layers.clone(),
+                environment: image.env().cloned(),
+                working_directory: image.working_dir().map(From::from),
+            };
+            let image = ConvertedImage::new(&image_spec.name, image_config);
+            if image_spec.use_layers {
+                let end = mem::replace(&mut layers, image.layers()?);
+                layers.extend(end);
+            }
+            if image_spec.use_environment {
+                initial_env = image.environment()?;
+            }
+            if image_spec.use_working_directory {
+                image_working_directory = Some(image.working_directory()?);
+            }
+        }
+        if image_working_directory.is_some() && container.working_directory.is_some() {
+            bail!("can't provide both `working_directory` and `image.use_working_directory`");
+        }
+
+        // Layer mounts come before the other mounts so that those can be placed inside them.
+        let mut mounts = vec![];
+        for LayerMountSpec {
+            mount_point,
+            layers,
+        } in container.layer_mounts
+        {
+            let layers = self
+                .get_layers(layers)
+                .await?
+                .try_into()
+                .map_err(|_| anyhow!("layer mount {mount_point:?} has no layers"))?;
+            mounts.push(JobMount::Layer {
+                mount_point,
+                layers,
+            });
+        }
+        mounts.extend(container.mounts);
+
+        Ok(ResolvedContainer {
+            environment: environment_eval(initial_env, container.environment, std_env_lookup)?,
+            layers: self
+                .get_layers(layers)
+                .await?
+                .try_into()
+                .map_err(|_| anyhow!("missing layers"))?,
+            mounts,
+            network: container.network,
+            root_overlay: container.root_overlay,
+            working_directory: image_working_directory.or(container.working_directory),
+            user: container.user,
+            group: container.group,
+        })
+    }
+
+    async fn get_stdin(&self, stdin: JobStdinSpec) -> Result<JobStdin> {
+        Ok(match stdin {
+            JobStdinSpec::Inline(bytes) => JobStdin::Inline(bytes),
+            JobStdinSpec::File(path) => {
+                let uploader = Uploader {
+                    log: self.log.clone(),
+                    local_broker_sender: self.local_broker_sender.clone(),
+                    locked: self.locked.clone(),
+                };
+                JobStdin::Artifact(uploader.upload(path.as_std_path()).await?)
+            }
+        })
+    }
+
     async fn get_container_image(&self, name: &str) -> Result<ContainerImage> {
         let dl_name = name.to_owned();
 
@@ -274,6 +382,7 @@ impl Client {
                 /* 1 for the manifest, 1 for file we are reading, 1 for directory we are listing */
                 MAX_IN_FLIGHT_LAYER_BUILDS * 3;
             local_worker::check_open_file_limit(&log, slots, extra as u64)?;
+            local_worker::check_core_pattern(&log);
 
             // We recreate all the manifests every time. We delete it here to clean-up unused
             // manifests and leaked temporary files.
@@ -385,10 +494,15 @@ impl Client {
             );
 
             // Start the local_worker.
+            let local_worker_output_dir = cache_dir
+                .join::<local_worker::WorkerCacheDir>(LOCAL_WORKER_DIR)
+                .join::<local_worker::OutputDir>("output");
             let worker_handle = {
                 let cache_root = cache_dir.join::<local_worker::WorkerCacheDir>(LOCAL_WORKER_DIR);
                 let mount_dir = cache_root.join::<local_worker::MountDir>("mount");
                 let tmpfs_dir = cache_root.join::<local_worker::TmpfsDir>("upper");
+                let cache_mount_dir =
+                    cache_root.join::<local_worker::CacheMountDir>("cache-mounts");
                 let cache_root = cache_root.join::<local_worker::CacheDir>("artifacts");
                 let blob_dir = cache_root.join::<local_worker::BlobDir>("blob/sha256");
 
@@ -409,6 +523,15 @@ impl Client {
                     mount_dir,
                     tmpfs_dir,
                     blob_dir,
+                    cache_mount_dir,
+                    cache_size,
+                    None,
+                    None,
+                    // The local worker runs on the client's own machine, so any of its endpoints
+                    // may be forwarded.
+                    None,
+                    local_worker_output_dir.clone(),
+                    None,
                 )?;
 
                 // Create an ArtifactFetcher for the local_worker that just forwards requests to
@@ -446,6 +569,8 @@ impl Client {
                     worker_broker_sender,
                     local_worker_cache,
                     slots,
+                    // The local broker never sends prefetch hints.
+                    local_worker::PrefetchBudget::default(),
                 );
 
                 let handle_worker_message =
@@ -497,6 +622,8 @@ impl Client {
 
             Ok((
                 ClientState {
+                    broker_addr,
+                    local_worker_output_dir,
                     local_broker_sender,
                     layer_builder: Arc::new(LayerBuilder::new(
                         cache_dir,
@@ -606,71 +733,82 @@ impl Client {
         let (sender, receiver) = futures::channel::mpsc::unbounded();
         debug!(state.log, "run_job"; "spec" => ?spec);
 
-        let container = match spec.container {
-            ContainerRef::Name(n) => {
-                let locked = state.locked.lock().await;
-                locked
-                    .containers
-                    .get(&n)
-                    .ok_or_else(|| anyhow!("container {n:?} unknown"))?
-                    .clone()
-            }
-            ContainerRef::Inline(c) => {
-                c.check_for_local_network_and_sys_mount()?;
-                c
-            }
-        };
-
-        let mut layers = container.layers;
-        let mut initial_env = Default::default();
-        let mut image_working_directory = None;
-        if let Some(image_spec) = container.image {
-            let image = state.get_container_image(&image_spec.name).await?;
-            let image_config = ImageConfig {
-                layers: image.layers.clone(),
-                environment: image.env().cloned(),
-                working_directory: image.working_dir().map(From::from),
-            };
-            let image = ConvertedImage::new(&image_spec.name, image_config);
-            if image_spec.use_layers {
-                let end = mem::replace(&mut layers, image.layers()?);
-                layers.extend(end);
-            }
-            if image_spec.use_environment {
-                initial_env = image.environment()?;
-            }
-            if image_spec.use_working_directory {
-                image_working_directory = Some(image.working_directory()?);
-            }
+        // These names end up in the job's `/etc/hosts`, so reject anything that isn't a hostname.
+        for name in spec
+            .hostname
+            .iter()
+            .chain(spec.extra_hosts.iter().map(|host| &host.name))
+        {
+            validate_hostname(name)?;
         }
-        if image_working_directory.is_some() && container.working_directory.is_some() {
-            bail!("can't provide both `working_directory` and `image.use_working_directory`");
+
+        // Likewise, these end up in the job's `/etc/passwd` and `/etc/group`.
+        if let Some(user_entries) = &spec.user_entries {
+            user_entries.validate()?;
         }
 
-        let working_directory = image_working_directory.or(container.working_directory);
-        let converted_layers = state.get_layers(layers).await?;
+        let container = state.resolve_container(spec.container).await?;
+        let stdin = match spec.stdin {
+            Some(stdin) => Some(state.get_stdin(stdin).await?),
+            None => None,
+        };
+        let mut sidecars = vec![];
+        for sidecar in spec.sidecars {
+            let container = state.resolve_container(sidecar.container).await?;
+            if container
+                .mounts
+                .iter()
+                .any(|mount| matches!(mount, JobMount::Layer { .. }))
+            {
+                bail!("sidecar {:?} can't have layer mounts", sidecar.name);
+            }
+            sidecars.push(JobSidecar {
+                name: sidecar.name,
+                program: sidecar.program,
+                arguments: sidecar.arguments,
+                environment: container.environment,
+                layers: container.layers,
+                mounts: container.mounts,
+                root_overlay: container.root_overlay,
+                working_directory: container.working_directory,
+                user: container.user,
+                group: container.group,
+                ready: sidecar.ready,
+            });
+        }
 
         let spec = maelstrom_base::JobSpec {
             program: spec.program,
             arguments: spec.arguments,
-            environment: environment_eval(initial_env, container.environment, std_env_lookup)?,
-            layers: converted_layers
-                .try_into()
-                .map_err(|_| anyhow!("missing layers"))?,
+            environment: container.environment,
+            layers: container.layers,
             mounts: container.mounts,
             network: container.network,
             root_overlay: container.root_overlay,
-            working_directory,
+            working_directory: container.working_directory,
             user: container.user,
             group: container.group,
             timeout: spec.timeout,
             estimated_duration: spec.estimated_duration,
             allocate_tty: spec.allocate_tty,
             priority: spec.priority,
+            stdin,
+            limits: spec.limits,
+            rlimits: spec.rlimits,
+            seccomp: spec.seccomp,
+            init: spec.init,
+            forwards: spec.forwards,
+            sidecars,
+            user_entries: spec.user_entries,
+            hostname: spec.hostname,
+            extra_hosts: spec.extra_hosts,
+            clock_offsets: spec.clock_offsets,
+            capture_core_dump: spec.capture_core_dump,
+            keep_writable_layer_on_failure: spec.keep_writable_layer_on_failure,
         };
         state
             .local_broker_sender
-            .send(router::Message::RunJob(spec, sender))?;
+            .send(router::Message::RunJob(Box::new(spec), sender))?;
         Ok(receiver)
     }
 
@@ -697,6 +835,27 @@ impl Client {
         })
     }
 
+    pub async fn download_artifact(&self, digest: Sha256Digest, path: PathBuf) -> Result<()> {
+        let state = self.state_machine.active()?;
+        debug!(state.log, "download_artifact"; "digest" => %digest, "path" => ?path);
+
+        // Artifacts produced by jobs run on the local worker are left in its output directory.
+        // Otherwise, they've been pushed to the broker.
+        let fs = async_fs::Fs::new();
+        let local_path = state.local_worker_output_dir.join::<()>(digest.to_string());
+        if fs.exists(&local_path).await {
+            fs.copy(&local_path, &path).await?;
+            fs.remove_file(&local_path).await?;
+        } else if let Some(broker_addr) = state.broker_addr {
+            artifact_fetcher::fetch_one_artifact(broker_addr, digest.clone(), &path)
+                .await
+                .with_context(|| format!("fetching artifact {digest}"))?;
+        } else {
+            bail!("artifact {digest} not found");
+        }
+        Ok(())
+    }
+
     pub async fn shutdown(&self) {
         self.clean_up.execute().await;
     }
@@ -1,3 +1,4 @@
+mod artifact_fetcher;
 mod artifact_pusher;
 mod client;
 mod digest_repo;
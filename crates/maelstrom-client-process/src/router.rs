@@ -5,10 +5,11 @@ use maelstrom_base::{
     ClientId, ClientJobId, JobId, JobOutcomeResult, JobSpec, Sha256Digest,
 };
 use maelstrom_client_base::{JobRunningStatus, JobStatus};
-use maelstrom_util::{ext::OptionExt as _, fs::Fs, sync};
-use maelstrom_worker::local_worker;
+use maelstrom_util::{ext::OptionExt as _, fs::Fs};
+use maelstrom_worker::local_worker::{self, EntryStamp};
 use std::{
     collections::HashMap,
+    mem,
     path::{Path, PathBuf},
 };
 use tokio::{
@@ -21,24 +22,25 @@ pub trait Deps {
     fn job_update(&self, handle: &Self::JobHandle, status: JobStatus);
 
     // Only in remote broker mode.
-    fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: JobSpec);
+    fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: Box<JobSpec>);
+    fn send_artifacts_query_to_broker(&self, digests: Vec<Sha256Digest>);
     fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest, path: PathBuf);
 
     // Only in standalone mode.
-    fn send_enqueue_job_to_local_worker(&self, jid: JobId, spec: JobSpec);
+    fn send_enqueue_job_to_local_worker(&self, jid: JobId, spec: Box<JobSpec>);
     fn send_artifact_fetch_completed_to_local_worker(
         &self,
         digest: Sha256Digest,
-        result: Result<u64>,
+        result: Result<EntryStamp>,
     );
-    fn link_artifact_for_local_worker(&self, from: &Path, to: &Path) -> Result<u64>;
+    fn link_artifact_for_local_worker(&self, from: &Path, to: &Path) -> Result<EntryStamp>;
     fn shutdown_local_worker(&self, error: Error);
 }
 
 pub enum Message<DepsT: Deps> {
     // These are requests from the client.
     AddArtifact(PathBuf, Sha256Digest),
-    RunJob(JobSpec, DepsT::JobHandle),
+    RunJob(Box<JobSpec>, DepsT::JobHandle),
 
     // Only in non-standalone mode.
     Broker(BrokerToClient),
@@ -67,6 +69,7 @@ struct Router<DepsT: Deps> {
     deps: DepsT,
     standalone: bool,
     artifacts: HashMap<Sha256Digest, PathBuf>,
+    unqueried_artifacts: Vec<Sha256Digest>,
     next_client_job_id: u32,
     jobs: HashMap<ClientJobId, JobEntry<DepsT::JobHandle>>,
     completed_jobs: u64,
@@ -78,13 +81,14 @@ impl<DepsT: Deps> Router<DepsT> {
             deps,
             standalone,
             artifacts: Default::default(),
+            unqueried_artifacts: Default::default(),
             next_client_job_id: Default::default(),
             jobs: Default::default(),
             completed_jobs: Default::default(),
         }
     }
 
-    fn receive_job_response(&mut self, client_job_id: ClientJobId, result: JobOutcomeResult) {
+    fn receive_job_response(&mut self, client_job_id: ClientJobId, result: Box<JobOutcomeResult>) {
         let handle = self
             .jobs
             .remove(&client_job_id)
@@ -100,10 +104,31 @@ impl<DepsT: Deps> Router<DepsT> {
         self.completed_jobs += 1;
     }
 
+    fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest) {
+        assert!(!self.standalone);
+        let path = self
+            .artifacts
+            .get(&digest)
+            .unwrap_or_else(|| panic!("got request for unknown artifact with digest {digest}"));
+        self.deps
+            .start_artifact_transfer_to_broker(digest, path.to_owned());
+    }
+
+    /// Ask the broker about all of the artifacts added since we last asked, so that we can start
+    /// pushing the ones it's missing before it asks for them one by one.
+    fn query_artifacts(&mut self) {
+        if !self.unqueried_artifacts.is_empty() {
+            self.deps
+                .send_artifacts_query_to_broker(mem::take(&mut self.unqueried_artifacts));
+        }
+    }
+
     fn receive_message(&mut self, message: Message<DepsT>) {
         match message {
             Message::AddArtifact(path, digest) => {
-                self.artifacts.insert(digest, path);
+                if self.artifacts.insert(digest.clone(), path).is_none() && !self.standalone {
+                    self.unqueried_artifacts.push(digest);
+                }
             }
             Message::RunJob(spec, handle) => {
                 let cjid = self.next_client_job_id.into();
@@ -122,6 +147,9 @@ impl<DepsT: Deps> Router<DepsT> {
                         spec,
                     );
                 } else {
+                    // If the broker learns about the job's artifacts from the job itself, it will
+                    // ask us for each one separately.
+                    self.query_artifacts();
                     self.deps.send_job_request_to_broker(cjid, spec);
                 }
             }
@@ -136,12 +164,12 @@ impl<DepsT: Deps> Router<DepsT> {
                 self.deps.job_update(&job.handle, status.into());
             }
             Message::Broker(BrokerToClient::TransferArtifact(digest)) => {
-                assert!(!self.standalone);
-                let path = self.artifacts.get(&digest).unwrap_or_else(|| {
-                    panic!("got request for unknown artifact with digest {digest}")
-                });
-                self.deps
-                    .start_artifact_transfer_to_broker(digest, path.to_owned());
+                self.start_artifact_transfer_to_broker(digest);
+            }
+            Message::Broker(BrokerToClient::MissingArtifacts(digests)) => {
+                for digest in digests {
+                    self.start_artifact_transfer_to_broker(digest);
+                }
             }
             Message::LocalWorker(WorkerToBroker::JobResponse(jid, result)) => {
                 self.receive_job_response(jid.cjid, result);
@@ -152,6 +180,9 @@ impl<DepsT: Deps> Router<DepsT> {
                 job.status = Some(status.clone());
                 self.deps.job_update(&job.handle, status.into());
             }
+            Message::LocalWorker(WorkerToBroker::ArtifactCached(_)) => {
+                // The local worker doesn't serve artifacts to anyone.
+            }
             Message::LocalWorkerStartArtifactFetch(digest, path) => {
                 self.deps.send_artifact_fetch_completed_to_local_worker(
                     digest.clone(),
@@ -197,19 +228,25 @@ impl Deps for Adapter {
         handle.unbounded_send(status).ok();
     }
 
-    fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: JobSpec) {
+    fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: Box<JobSpec>) {
         let _ = self
             .broker_sender
             .send(ClientToBroker::JobRequest(cjid, spec));
     }
 
+    fn send_artifacts_query_to_broker(&self, digests: Vec<Sha256Digest>) {
+        let _ = self
+            .broker_sender
+            .send(ClientToBroker::ArtifactsQuery(digests));
+    }
+
     fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest, path: PathBuf) {
         let _ = self
             .artifact_pusher_sender
             .send(artifact_pusher::Message { digest, path });
     }
 
-    fn send_enqueue_job_to_local_worker(&self, jid: JobId, spec: JobSpec) {
+    fn send_enqueue_job_to_local_worker(&self, jid: JobId, spec: Box<JobSpec>) {
         let _ = self.local_worker_sender.send(local_worker::Message::Broker(
             BrokerToWorker::EnqueueJob(jid, spec),
         ));
@@ -218,7 +255,7 @@ impl Deps for Adapter {
     fn send_artifact_fetch_completed_to_local_worker(
         &self,
         digest: Sha256Digest,
-        result: Result<u64>,
+        result: Result<EntryStamp>,
     ) {
         let _ = self
             .local_worker_sender
@@ -227,9 +264,9 @@ impl Deps for Adapter {
             ));
     }
 
-    fn link_artifact_for_local_worker(&self, from: &Path, to: &Path) -> Result<u64> {
+    fn link_artifact_for_local_worker(&self, from: &Path, to: &Path) -> Result<EntryStamp> {
         self.fs.symlink(from, to)?;
-        Ok(self.fs.metadata(to)?.len())
+        Ok(EntryStamp::read(to)?)
     }
 
     fn shutdown_local_worker(&self, error: Error) {
@@ -249,16 +286,24 @@ pub fn channel() -> (Sender, Receiver) {
 pub fn start_task(
     join_set: &mut JoinSet<Result<()>>,
     standalone: bool,
-    receiver: Receiver,
+    mut receiver: Receiver,
     broker_sender: UnboundedSender<ClientToBroker>,
     artifact_pusher_sender: artifact_pusher::Sender,
     local_worker_sender: maelstrom_worker::DispatcherSender,
 ) {
     let adapter = Adapter::new(broker_sender, artifact_pusher_sender, local_worker_sender);
     let mut router = Router::new(adapter, standalone);
-    join_set.spawn(sync::channel_reader(receiver, move |msg| {
-        router.receive_message(msg)
-    }));
+    join_set.spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            router.receive_message(msg);
+            // Once we've caught up, ask about all of the artifacts that were added in the
+            // meantime in one batch.
+            if receiver.is_empty() {
+                router.query_artifacts();
+            }
+        }
+        Ok(())
+    });
 }
 
 #[cfg(test)]
@@ -274,9 +319,10 @@ mod tests {
     enum TestMessage {
         JobUpdate(ClientJobId, JobStatus),
         JobRequestToBroker(ClientJobId, JobSpec),
+        ArtifactsQueryToBroker(Vec<Sha256Digest>),
         StartArtifactTransferToBroker(Sha256Digest, PathBuf),
         EnqueueJobToLocalWorker(JobId, JobSpec),
-        ArtifactFetchCompletedToLocalWorker(Sha256Digest, result::Result<u64, String>),
+        ArtifactFetchCompletedToLocalWorker(Sha256Digest, result::Result<EntryStamp, String>),
         LinkArtifactForLocalWorker(PathBuf, PathBuf),
         ShutdownLocalWorker(String),
     }
@@ -295,10 +341,16 @@ mod tests {
                 .push(TestMessage::JobUpdate(*handle, status));
         }
 
-        fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: JobSpec) {
+        fn send_job_request_to_broker(&self, cjid: ClientJobId, spec: Box<JobSpec>) {
             self.borrow_mut()
                 .messages
-                .push(TestMessage::JobRequestToBroker(cjid, spec));
+                .push(TestMessage::JobRequestToBroker(cjid, *spec));
+        }
+
+        fn send_artifacts_query_to_broker(&self, digests: Vec<Sha256Digest>) {
+            self.borrow_mut()
+                .messages
+                .push(TestMessage::ArtifactsQueryToBroker(digests));
         }
 
         fn start_artifact_transfer_to_broker(&self, digest: Sha256Digest, path: PathBuf) {
@@ -307,16 +359,16 @@ mod tests {
                 .push(TestMessage::StartArtifactTransferToBroker(digest, path));
         }
 
-        fn send_enqueue_job_to_local_worker(&self, jid: JobId, spec: JobSpec) {
+        fn send_enqueue_job_to_local_worker(&self, jid: JobId, spec: Box<JobSpec>) {
             self.borrow_mut()
                 .messages
-                .push(TestMessage::EnqueueJobToLocalWorker(jid, spec));
+                .push(TestMessage::EnqueueJobToLocalWorker(jid, *spec));
         }
 
         fn send_artifact_fetch_completed_to_local_worker(
             &self,
             digest: Sha256Digest,
-            result: Result<u64>,
+            result: Result<EntryStamp>,
         ) {
             self.borrow_mut()
                 .messages
@@ -326,7 +378,7 @@ mod tests {
                 ));
         }
 
-        fn link_artifact_for_local_worker(&self, from: &Path, to: &Path) -> Result<u64> {
+        fn link_artifact_for_local_worker(&self, from: &Path, to: &Path) -> Result<EntryStamp> {
             let mut test_state = self.borrow_mut();
             test_state
                 .messages
@@ -338,7 +390,10 @@ mod tests {
                 .link_artifact_for_local_worker_returns
                 .remove(&(from.into(), to.into()))
             {
-                Ok(size)
+                Ok(EntryStamp {
+                    bytes_used: size,
+                    checksum: digest!(0),
+                })
             } else {
                 Err(anyhow!("link error"))
             }
@@ -436,7 +491,10 @@ mod tests {
         AddArtifact(path_buf!("bar"), digest!(1)) => {};
         LocalWorkerStartArtifactFetch(digest!(1), path_buf!("foo")) => {
             LinkArtifactForLocalWorker(path_buf!("bar"), path_buf!("foo")),
-            ArtifactFetchCompletedToLocalWorker(digest!(1), Ok(1234)),
+            ArtifactFetchCompletedToLocalWorker(
+                digest!(1),
+                Ok(EntryStamp { bytes_used: 1234, checksum: digest!(0) }),
+            ),
         };
     }
 
@@ -447,7 +505,10 @@ mod tests {
         AddArtifact(path_buf!("bar"), digest!(1)) => {};
         LocalWorkerStartArtifactFetch(digest!(1), path_buf!("foo")) => {
             LinkArtifactForLocalWorker(path_buf!("bar"), path_buf!("foo")),
-            ArtifactFetchCompletedToLocalWorker(digest!(1), Ok(1234)),
+            ArtifactFetchCompletedToLocalWorker(
+                digest!(1),
+                Ok(EntryStamp { bytes_used: 1234, checksum: digest!(0) }),
+            ),
         };
     }
 
@@ -481,7 +542,10 @@ mod tests {
         AddArtifact(path_buf!("bar"), digest!(1)) => {};
         LocalWorkerStartArtifactFetch(digest!(1), path_buf!("foo")) => {
             LinkArtifactForLocalWorker(path_buf!("bar"), path_buf!("foo")),
-            ArtifactFetchCompletedToLocalWorker(digest!(1), Ok(1234)),
+            ArtifactFetchCompletedToLocalWorker(
+                digest!(1),
+                Ok(EntryStamp { bytes_used: 1234, checksum: digest!(0) }),
+            ),
         };
     }
 
@@ -492,7 +556,10 @@ mod tests {
         AddArtifact(path_buf!("bar"), digest!(1)) => {};
         LocalWorkerStartArtifactFetch(digest!(1), path_buf!("foo")) => {
             LinkArtifactForLocalWorker(path_buf!("bar"), path_buf!("foo")),
-            ArtifactFetchCompletedToLocalWorker(digest!(1), Ok(1234)),
+            ArtifactFetchCompletedToLocalWorker(
+                digest!(1),
+                Ok(EntryStamp { bytes_used: 1234, checksum: digest!(0) }),
+            ),
         };
     }
 
@@ -535,13 +602,73 @@ mod tests {
         };
     }
 
+    script_test! {
+        broker_missing_artifacts_clustered,
+        Fixture::new(false, []),
+        AddArtifact(path_buf!("foo"), digest!(1)) => {};
+        AddArtifact(path_buf!("bar"), digest!(2)) => {};
+        Broker(MissingArtifacts(vec![digest!(1), digest!(2)])) => {
+            StartArtifactTransferToBroker(digest!(1), path_buf!("foo")),
+            StartArtifactTransferToBroker(digest!(2), path_buf!("bar")),
+        };
+    }
+
+    script_test! {
+        run_job_queries_artifacts_clustered,
+        Fixture::new(false, []),
+        AddArtifact(path_buf!("foo"), digest!(1)) => {};
+        AddArtifact(path_buf!("bar"), digest!(2)) => {};
+        AddArtifact(path_buf!("baz"), digest!(1)) => {};
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
+            ArtifactsQueryToBroker(vec![digest!(1), digest!(2)]),
+            JobRequestToBroker(cjid!(0), spec!(0, Tar)),
+        };
+        AddArtifact(path_buf!("qux"), digest!(3)) => {};
+        RunJob(Box::new(spec!(1, Tar)), cjid!(1)) => {
+            ArtifactsQueryToBroker(vec![digest!(3)]),
+            JobRequestToBroker(cjid!(1), spec!(1, Tar)),
+        };
+        RunJob(Box::new(spec!(2, Tar)), cjid!(2)) => {
+            JobRequestToBroker(cjid!(2), spec!(2, Tar)),
+        };
+    }
+
+    script_test! {
+        run_job_doesnt_query_artifacts_standalone,
+        Fixture::new(true, []),
+        AddArtifact(path_buf!("foo"), digest!(1)) => {};
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
+            EnqueueJobToLocalWorker(jid!(0, 0), spec!(0, Tar)),
+        };
+    }
+
+    #[test]
+    fn query_artifacts_clustered() {
+        let mut fixture = Fixture::new(false, []);
+        fixture.router.query_artifacts();
+        fixture.expect_messages_in_any_order(vec![]);
+        fixture
+            .router
+            .receive_message(AddArtifact(path_buf!("foo"), digest!(1)));
+        fixture
+            .router
+            .receive_message(AddArtifact(path_buf!("bar"), digest!(2)));
+        fixture.router.query_artifacts();
+        fixture.expect_messages_in_any_order(vec![ArtifactsQueryToBroker(vec![
+            digest!(1),
+            digest!(2),
+        ])]);
+        fixture.router.query_artifacts();
+        fixture.expect_messages_in_any_order(vec![]);
+    }
+
     script_test! {
         run_job_standalone,
         Fixture::new(true, []),
-        RunJob(spec!(0, Tar), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
             EnqueueJobToLocalWorker(jid!(0, 0), spec!(0, Tar)),
         };
-        RunJob(spec!(1, Tar), cjid!(1)) => {
+        RunJob(Box::new(spec!(1, Tar)), cjid!(1)) => {
             EnqueueJobToLocalWorker(jid!(0, 1), spec!(1, Tar)),
         };
     }
@@ -549,10 +676,10 @@ mod tests {
     script_test! {
         shutdown,
         Fixture::new(true, []),
-        RunJob(spec!(0, Tar), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
             EnqueueJobToLocalWorker(jid!(0, 0), spec!(0, Tar)),
         };
-        RunJob(spec!(1, Tar), cjid!(1)) => {
+        RunJob(Box::new(spec!(1, Tar)), cjid!(1)) => {
             EnqueueJobToLocalWorker(jid!(0, 1), spec!(1, Tar)),
         };
         Shutdown(anyhow!("test error")) => {
@@ -563,10 +690,10 @@ mod tests {
     script_test! {
         run_job_clustered,
         Fixture::new(false, []),
-        RunJob(spec!(0, Tar), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
             JobRequestToBroker(cjid!(0), spec!(0, Tar)),
         };
-        RunJob(spec!(1, Tar), cjid!(1)) => {
+        RunJob(Box::new(spec!(1, Tar)), cjid!(1)) => {
             JobRequestToBroker(cjid!(1), spec!(1, Tar)),
         };
     }
@@ -574,10 +701,10 @@ mod tests {
     script_test! {
         run_job_must_be_local_clustered,
         Fixture::new(false, []),
-        RunJob(spec!(0, Tar), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
             JobRequestToBroker(cjid!(0), spec!(0, Tar)),
         };
-        RunJob(spec!(1, Tar).network(JobNetwork::Local), cjid!(1)) => {
+        RunJob(Box::new(spec!(1, Tar).network(JobNetwork::Local)), cjid!(1)) => {
             EnqueueJobToLocalWorker(jid!(0, 1), spec!(1, Tar).network(JobNetwork::Local)),
         };
     }
@@ -589,23 +716,23 @@ mod tests {
         // Give it a job just so it doesn't crash subracting the job counts.
         fixture
             .router
-            .receive_message(RunJob(spec!(0, Tar), cjid!(0)));
+            .receive_message(RunJob(Box::new(spec!(0, Tar)), cjid!(0)));
         fixture
             .router
             .receive_message(LocalWorker(WorkerToBroker::JobResponse(
                 jid!(0, 1),
-                Ok(outcome!(0)),
+                Box::new(Ok(outcome!(0))),
             )));
     }
 
     script_test! {
         job_response_from_local_worker_known_standalone,
         Fixture::new(true, []),
-        RunJob(spec!(0, Tar), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
             EnqueueJobToLocalWorker(jid!(0, 0), spec!(0, Tar)),
         };
-        LocalWorker(WorkerToBroker::JobResponse(jid!(0, 0), Ok(outcome!(0)))) => {
-            JobUpdate(cjid!(0), JobStatus::Completed { client_job_id: cjid!(0), result: Ok(outcome!(0)) }),
+        LocalWorker(WorkerToBroker::JobResponse(jid!(0, 0), Box::new(Ok(outcome!(0))))) => {
+            JobUpdate(cjid!(0), JobStatus::Completed { client_job_id: cjid!(0), result: Box::new(Ok(outcome!(0))) }),
         };
     }
 
@@ -614,25 +741,26 @@ mod tests {
     fn job_response_from_local_worker_unknown_clustered() {
         let mut fixture = Fixture::new(false, []);
         // Give it a job just so it doesn't crash subracting the job counts.
-        fixture
-            .router
-            .receive_message(RunJob(spec!(0, Tar).network(JobNetwork::Local), cjid!(0)));
+        fixture.router.receive_message(RunJob(
+            Box::new(spec!(0, Tar).network(JobNetwork::Local)),
+            cjid!(0),
+        ));
         fixture
             .router
             .receive_message(LocalWorker(WorkerToBroker::JobResponse(
                 jid!(0, 1),
-                Ok(outcome!(0)),
+                Box::new(Ok(outcome!(0))),
             )));
     }
 
     script_test! {
         job_response_from_local_worker_known_clustered,
         Fixture::new(false, []),
-        RunJob(spec!(0, Tar).network(JobNetwork::Local), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar).network(JobNetwork::Local)), cjid!(0)) => {
             EnqueueJobToLocalWorker(jid!(0, 0), spec!(0, Tar).network(JobNetwork::Local)),
         };
-        LocalWorker(WorkerToBroker::JobResponse(jid!(0, 0), Ok(outcome!(0)))) => {
-            JobUpdate(cjid!(0), JobStatus::Completed { client_job_id: cjid!(0), result: Ok(outcome!(0)) }),
+        LocalWorker(WorkerToBroker::JobResponse(jid!(0, 0), Box::new(Ok(outcome!(0))))) => {
+            JobUpdate(cjid!(0), JobStatus::Completed { client_job_id: cjid!(0), result: Box::new(Ok(outcome!(0))) }),
         };
     }
 
@@ -644,7 +772,7 @@ mod tests {
             .router
             .receive_message(Broker(BrokerToClient::JobResponse(
                 cjid!(0),
-                Ok(outcome!(0)),
+                Box::new(Ok(outcome!(0))),
             )));
     }
 
@@ -654,12 +782,12 @@ mod tests {
         let mut fixture = Fixture::new(true, []);
         fixture
             .router
-            .receive_message(RunJob(spec!(0, Tar), cjid!(0)));
+            .receive_message(RunJob(Box::new(spec!(0, Tar)), cjid!(0)));
         fixture
             .router
             .receive_message(Broker(BrokerToClient::JobResponse(
                 cjid!(0),
-                Ok(outcome!(0)),
+                Box::new(Ok(outcome!(0))),
             )));
     }
 
@@ -671,18 +799,18 @@ mod tests {
             .router
             .receive_message(Broker(BrokerToClient::JobResponse(
                 cjid!(0),
-                Ok(outcome!(0)),
+                Box::new(Ok(outcome!(0))),
             )));
     }
 
     script_test! {
         job_response_from_broker_known_clustered,
         Fixture::new(false, []),
-        RunJob(spec!(0, Tar), cjid!(0)) => {
+        RunJob(Box::new(spec!(0, Tar)), cjid!(0)) => {
             JobRequestToBroker(cjid!(0), spec!(0, Tar)),
         };
-        Broker(BrokerToClient::JobResponse(cjid!(0), Ok(outcome!(0)))) => {
-            JobUpdate(cjid!(0), JobStatus::Completed { client_job_id: cjid!(0), result: Ok(outcome!(0)) }),
+        Broker(BrokerToClient::JobResponse(cjid!(0), Box::new(Ok(outcome!(0))))) => {
+            JobUpdate(cjid!(0), JobStatus::Completed { client_job_id: cjid!(0), result: Box::new(Ok(outcome!(0))) }),
         };
     }
 }
@@ -4,7 +4,8 @@ use anyhow::{bail, Result};
 use futures::{Stream, StreamExt as _};
 use maelstrom_client_base::{
     proto::{self, client_process_server::ClientProcess},
-    AddContainerRequest, IntoProtoBuf, RunJobRequest, StartRequest, TryFromProtoBuf,
+    AddContainerRequest, DownloadArtifactRequest, IntoProtoBuf, RunJobRequest, StartRequest,
+    TryFromProtoBuf,
 };
 use maelstrom_util::config::common::LogLevel;
 use slog::Drain as _;
@@ -132,4 +133,20 @@ impl ClientProcess for Handler {
             .map(|res| res.into_proto_buf())
             .map_to_tonic()
     }
+
+    async fn download_artifact(
+        &self,
+        request: Request<proto::DownloadArtifactRequest>,
+    ) -> TonicResponse<proto::Void> {
+        async {
+            let DownloadArtifactRequest { digest, path } =
+                TryFromProtoBuf::try_from_proto_buf(request.into_inner())?;
+            self.client
+                .download_artifact(digest, path)
+                .await
+                .map(IntoProtoBuf::into_proto_buf)
+        }
+        .await
+        .map_to_tonic()
+    }
 }
@@ -2,20 +2,21 @@ pub use maelstrom_client_base::{
     spec,
     spec::{ContainerSpec, ImageSpec, JobSpec},
     AcceptInvalidRemoteContainerTlsCerts, CacheDir, IntrospectResponse, JobRunningStatus,
-    JobStatus, ProjectDir, RemoteProgress, RpcLogMessage, StateDir, MANIFEST_DIR,
+    JobStatus, ProjectDir, RemoteProgress, RpcLogMessage, StateDir, MANIFEST_DIR, STDIN_DIR,
 };
 pub use maelstrom_container::ContainerImageDepotDir;
 
 use anyhow::{anyhow, Context as _, Result};
 use futures::stream::StreamExt as _;
-use maelstrom_base::{ClientJobId, JobOutcomeResult};
+use maelstrom_base::{ClientJobId, JobOutcomeResult, Sha256Digest};
 use maelstrom_client_base::{
     proto::{self, client_process_client::ClientProcessClient},
-    AddContainerRequest, IntoProtoBuf, StartRequest, TryFromProtoBuf,
+    AddContainerRequest, DownloadArtifactRequest, IntoProtoBuf, StartRequest, TryFromProtoBuf,
 };
 use maelstrom_linux::{self as linux, Pid};
 use maelstrom_util::{
     config::common::{BrokerAddr, CacheSize, InlineLimit, LogLevel, Slots},
+    fs::Fs,
     root::Root,
 };
 use std::{
@@ -24,7 +25,7 @@ use std::{
     net::Shutdown,
     os::linux::net::SocketAddrExt as _,
     os::unix::net::{SocketAddr, UnixListener, UnixStream as StdUnixStream},
-    path::Path,
+    path::{Path, PathBuf},
     pin::Pin,
     process,
     process::{Command, Stdio},
@@ -202,7 +203,7 @@ fn wait_for_job_completed_blocking(
             result,
         } = receiver.recv().map_err(|_| anyhow!("job canceled"))??
         {
-            break Ok((client_job_id, result));
+            break Ok((client_job_id, *result));
         }
     }
 }
@@ -369,6 +370,34 @@ impl Client {
     pub fn introspect(&self) -> Result<IntrospectResponse> {
         self.send_sync(move |mut client| async move { client.introspect(proto::Void {}).await })
     }
+
+    /// Download the artifact with the given digest to `path`. This is used to retrieve files that
+    /// jobs produced, like core dumps, from wherever the job ran.
+    pub fn download_artifact(&self, digest: Sha256Digest, path: &Path) -> Result<()> {
+        let path = path.to_owned();
+        self.send_sync(|mut client| async move {
+            client
+                .download_artifact(DownloadArtifactRequest { digest, path }.into_proto_buf())
+                .await
+        })
+    }
+
+    /// Download the tar artifact with the given digest and unpack it into the directory at
+    /// `path`, which is created if necessary. This is used to retrieve the writable layers that
+    /// failed jobs left behind.
+    pub fn download_and_unpack_artifact(&self, digest: Sha256Digest, path: &Path) -> Result<()> {
+        let fs = Fs::new();
+        let mut tar_path = path.as_os_str().to_owned();
+        tar_path.push(".tar");
+        let tar_path = PathBuf::from(tar_path);
+        fs.create_dir_all(path)?;
+        self.download_artifact(digest, &tar_path)?;
+        let result = tar::Archive::new(fs.open_file(&tar_path)?)
+            .unpack(path)
+            .with_context(|| format!("unpacking {}", tar_path.display()));
+        fs.remove_file(&tar_path)?;
+        result
+    }
 }
 
 pub fn bg_proc_main() -> Result<()> {
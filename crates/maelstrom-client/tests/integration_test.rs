@@ -140,6 +140,7 @@ impl ClientFixture {
             network,
             user: None,
             group: None,
+            layer_mounts: vec![],
         };
         self.client.add_container(name, spec).unwrap_err()
     }
@@ -144,10 +144,10 @@ fn many_different_tests_success() {
         ExitCode::SUCCESS,
     );
     let tests = [
-        "maelstrom-software.com/pkg1 TestA......OK",
-        "maelstrom-software.com/pkg1 ExampleB...OK",
-        "maelstrom-software.com/pkg2 TestA......OK",
-        "maelstrom-software.com/pkg2 FuzzB......OK",
+        "<om-software.com/pkg1 TestA.OK",
+        "<software.com/pkg1 ExampleB.OK",
+        "<om-software.com/pkg2 TestA.OK",
+        "<om-software.com/pkg2 FuzzB.OK",
         "maelstrom-software.com/pkg1 TestC.IGNORED",
     ];
     for t in tests {
@@ -213,7 +213,7 @@ fn single_test_failure() {
     assert!(
         Regex::new(
             "(?ms)^\
-            maelstrom-software.com/pkg1 TestA....FAIL   [\\d\\.]+s\n\
+            <-software.com/pkg1 TestA.FAIL   [\\d\\.]+s +[\\d\\.]+ [KMGTPE]?B\n\
             test output\n\
             \\s\\s\\s\\sfoo_test.go:8: test failure\n\
             \n\
@@ -276,7 +276,7 @@ fn single_fuzz_failure() {
     assert!(
         Regex::new(
             "(?ms)^\
-            maelstrom-software.com/pkg1 FuzzB....FAIL   [\\d\\.]+s\n\
+            <-software.com/pkg1 FuzzB.FAIL   [\\d\\.]+s +[\\d\\.]+ [KMGTPE]?B\n\
             === RUN   FuzzB\n\
             === RUN   FuzzB/seed\\#0\n\
             === RUN   FuzzB/seed\\#1\n\
@@ -108,11 +108,11 @@ pub use dir::DirectoryDataReader;
 pub use file::FileMetadataReader;
 pub use ty::{FileAttributes, FileData, FileId, FileType, LayerId, LayerSuper};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use anyhow_trace::anyhow_trace;
 use futures::stream::StreamExt as _;
 use lru::LruCache;
-use maelstrom_base::Sha256Digest;
+use maelstrom_base::{Sha256Digest, Utf8Component, Utf8Path};
 use maelstrom_fuse::{
     AttrResponse, EntryResponse, ErrnoResult, FileAttr, FuseFileSystem, ReadLinkResponse,
     ReadResponse, Request,
@@ -124,6 +124,7 @@ use maelstrom_util::{
 };
 use std::{
     ffi::OsStr,
+    os::unix::fs::FileExt as _,
     path::{Path, PathBuf},
     pin::Pin,
     sync::Arc,
@@ -283,6 +284,64 @@ impl LayerFs {
         self.blob_dir.join(digest.to_string())
     }
 
+    /// Read the contents of the regular file at the absolute `path`, without going through FUSE.
+    /// Return `None` if there is no such file, or if it isn't a regular file. Symbolic links aren't
+    /// followed.
+    pub async fn read_file(
+        &self,
+        cache: &Mutex<ReaderCache>,
+        path: &Utf8Path,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut file_id = self.root().await?;
+        for component in path.components() {
+            let name = match component {
+                Utf8Component::RootDir => continue,
+                Utf8Component::Normal(name) => name,
+                _ => bail!("unexpected component in path {path:?}"),
+            };
+            let files = cache.lock().await.files(self, file_id.layer()).await?;
+            let (kind, _) = files.lock().await.get_attr(file_id).await?;
+            if kind != FileType::Directory {
+                return Ok(None);
+            }
+            let dir = cache.lock().await.open_dir(self, file_id).await?;
+            let Some(child_id) = dir.lock().await.look_up(name).await? else {
+                return Ok(None);
+            };
+            file_id = child_id;
+        }
+
+        let files = cache.lock().await.files(self, file_id.layer()).await?;
+        let (kind, data) = files.lock().await.get_data(file_id).await?;
+        if kind != FileType::RegularFile {
+            return Ok(None);
+        }
+        let (file, offset, length) = match data {
+            FileData::Empty => return Ok(Some(vec![])),
+            FileData::Inline { offset, length } => (
+                cache
+                    .lock()
+                    .await
+                    .inline_data(self, file_id.layer())
+                    .await?,
+                offset,
+                length,
+            ),
+            FileData::Digest {
+                digest,
+                offset,
+                length,
+            } => (
+                cache.lock().await.data_file(self, &digest).await?,
+                offset,
+                length,
+            ),
+        };
+        let mut contents = vec![0; length.try_into()?];
+        file.read_exact_at(&mut contents, offset)?;
+        Ok(Some(contents))
+    }
+
     /// Mount the file-system in a child process. It can then be accessed via a path in `/proc`.
     /// See [`maelstrom_fuse::fuse_mount_namespace`] for more details.
     pub async fn mount(
@@ -1411,6 +1470,34 @@ mod tests {
         mount_handle.umount_and_join().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn read_file_without_fuse() {
+        let mut fix = Fixture::new().await;
+        let digest = fix.add_to_cache(b"root:x:0:0::/root:/bin/sh\n").await;
+
+        let layer_fs = fix
+            .build_bottom_layer(vec![
+                BuildEntry::reg("/Foo", b"hello world"),
+                BuildEntry::reg_empty("/Bar"),
+                BuildEntry::reg_digest("/etc/passwd", digest, 0, 26),
+                BuildEntry::sym("/Baz", "./Foo"),
+            ])
+            .await;
+        let cache = Mutex::new(ReaderCache::new());
+
+        let read = |path: &'static str| layer_fs.read_file(&cache, Utf8Path::new(path));
+        assert_eq!(read("/Foo").await.unwrap().unwrap(), b"hello world");
+        assert_eq!(read("/Bar").await.unwrap().unwrap(), b"");
+        assert_eq!(
+            read("/etc/passwd").await.unwrap().unwrap(),
+            b"root:x:0:0::/root:/bin/sh\n"
+        );
+        assert_eq!(read("/Baz").await.unwrap(), None);
+        assert_eq!(read("/etc").await.unwrap(), None);
+        assert_eq!(read("/Foo/passwd").await.unwrap(), None);
+        assert_eq!(read("/Qux").await.unwrap(), None);
+    }
+
     #[tokio::test]
     async fn read_link() {
         let mut fix = Fixture::new().await;
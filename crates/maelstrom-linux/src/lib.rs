@@ -59,6 +59,14 @@ pub trait AsFd {
     fn fd(&self) -> Fd;
 }
 
+#[derive(Clone, Copy)]
+pub struct ClockId(libc::clockid_t);
+
+impl ClockId {
+    pub const BOOTTIME: Self = Self(libc::CLOCK_BOOTTIME);
+    pub const MONOTONIC: Self = Self(libc::CLOCK_MONOTONIC);
+}
+
 #[derive(Clone)]
 #[repr(transparent)]
 pub struct CloneArgs(libc::clone_args);
@@ -93,7 +101,9 @@ impl CloneFlags {
     pub const NEWNET: Self = Self(libc::CLONE_NEWNET);
     pub const NEWNS: Self = Self(libc::CLONE_NEWNS);
     pub const NEWPID: Self = Self(libc::CLONE_NEWPID);
+    pub const NEWTIME: Self = Self(libc::CLONE_NEWTIME);
     pub const NEWUSER: Self = Self(libc::CLONE_NEWUSER);
+    pub const NEWUTS: Self = Self(libc::CLONE_NEWUTS);
     pub const VM: Self = Self(libc::CLONE_VM);
 
     fn as_u64(&self) -> u64 {
@@ -198,6 +208,7 @@ impl Errno {
     pub const EPIPE: Self = Self(libc::EPIPE);
     pub const EDOM: Self = Self(libc::EDOM);
     pub const ERANGE: Self = Self(libc::ERANGE);
+    pub const ETIMEDOUT: Self = Self(libc::ETIMEDOUT);
     pub const EWOULDBLOCK: Self = Self::EAGAIN;
 }
 
@@ -370,6 +381,51 @@ impl Gid {
     }
 }
 
+/// An event read from an inotify file descriptor with [`inotify_read_events`].
+pub struct InotifyEvent<'a> {
+    pub wd: WatchDescriptor,
+    pub mask: InotifyMask,
+    /// The name of the file the event is about, if the watched path is a directory.
+    pub name: Option<&'a CStr>,
+}
+
+#[derive(BitOr, Clone, Copy, Default)]
+pub struct InotifyFlags(c_int);
+
+impl InotifyFlags {
+    pub const CLOEXEC: Self = Self(libc::IN_CLOEXEC);
+    pub const NONBLOCK: Self = Self(libc::IN_NONBLOCK);
+}
+
+#[derive(BitOr, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InotifyMask(u32);
+
+impl InotifyMask {
+    pub const CLOSE_WRITE: Self = Self(libc::IN_CLOSE_WRITE);
+    pub const CREATE: Self = Self(libc::IN_CREATE);
+    pub const DELETE: Self = Self(libc::IN_DELETE);
+    pub const DONT_FOLLOW: Self = Self(libc::IN_DONT_FOLLOW);
+    pub const IGNORED: Self = Self(libc::IN_IGNORED);
+    pub const ISDIR: Self = Self(libc::IN_ISDIR);
+    pub const MODIFY: Self = Self(libc::IN_MODIFY);
+    pub const MOVED_FROM: Self = Self(libc::IN_MOVED_FROM);
+    pub const MOVED_TO: Self = Self(libc::IN_MOVED_TO);
+    pub const ONLYDIR: Self = Self(libc::IN_ONLYDIR);
+    pub const Q_OVERFLOW: Self = Self(libc::IN_Q_OVERFLOW);
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[derive(BitOr, Clone, Copy, Default)]
+pub struct MapFlags(c_int);
+
+impl MapFlags {
+    pub const PRIVATE: Self = Self(libc::MAP_PRIVATE);
+    pub const STACK: Self = Self(libc::MAP_STACK);
+}
+
 #[derive(BitOr, Clone, Copy, Default)]
 pub struct MountAttrs(c_uint);
 
@@ -411,11 +467,15 @@ impl MoveMountFlags {
 pub struct OpenFlags(c_int);
 
 impl OpenFlags {
+    pub const RDONLY: Self = Self(libc::O_RDONLY);
     pub const RDWR: Self = Self(libc::O_RDWR);
     pub const WRONLY: Self = Self(libc::O_WRONLY);
     pub const TRUNC: Self = Self(libc::O_TRUNC);
+    pub const CREAT: Self = Self(libc::O_CREAT);
     pub const NONBLOCK: Self = Self(libc::O_NONBLOCK);
     pub const NOCTTY: Self = Self(libc::O_NOCTTY);
+    pub const DIRECTORY: Self = Self(libc::O_DIRECTORY);
+    pub const PATH: Self = Self(libc::O_PATH);
 }
 
 #[derive(BitOr, Clone, Copy, Default)]
@@ -505,6 +565,14 @@ impl TryFrom<OwnedFd> for tokio::net::UnixListener {
     }
 }
 
+#[cfg(feature = "tokio")]
+impl TryFrom<OwnedFd> for tokio::net::TcpListener {
+    type Error = std::io::Error;
+    fn try_from(owned_fd: OwnedFd) -> Result<tokio::net::TcpListener, Self::Error> {
+        tokio::net::TcpListener::from_std(fd::OwnedFd::from(owned_fd).into())
+    }
+}
+
 impl Drop for OwnedFd {
     fn drop(&mut self) {
         // Just ignore the return value from close.
@@ -542,6 +610,11 @@ pub struct PollEvents(c_short);
 
 impl PollEvents {
     pub const IN: Self = Self(libc::POLLIN);
+    pub const HUP: Self = Self(libc::POLLHUP);
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
 }
 
 #[repr(transparent)]
@@ -555,15 +628,80 @@ impl PollFd {
             revents: 0,
         })
     }
+
+    /// The events that [`poll`] found. `HUP` can be returned even if it wasn't asked for.
+    pub fn revents(&self) -> PollEvents {
+        PollEvents(self.0.revents)
+    }
+}
+
+#[derive(BitOr, Clone, Copy, Default)]
+pub struct ProtFlags(c_int);
+
+impl ProtFlags {
+    pub const NONE: Self = Self(libc::PROT_NONE);
+    pub const READ: Self = Self(libc::PROT_READ);
+    pub const WRITE: Self = Self(libc::PROT_WRITE);
+}
+
+/// The resources used by a child process, as returned by [`waitid_with_rusage`].
+#[derive(Clone, Copy)]
+pub struct Rusage(libc::rusage);
+
+impl Rusage {
+    fn timeval_to_duration(tv: libc::timeval) -> Duration {
+        Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+    }
+
+    /// The maximum resident set size, in bytes.
+    pub fn max_rss(&self) -> u64 {
+        self.0.ru_maxrss as u64 * 1024
+    }
+
+    pub fn user_time(&self) -> Duration {
+        Self::timeval_to_duration(self.0.ru_utime)
+    }
+
+    pub fn system_time(&self) -> Duration {
+        Self::timeval_to_duration(self.0.ru_stime)
+    }
+
+    /// The number of 512-byte blocks read from the file system.
+    pub fn in_blocks(&self) -> u64 {
+        self.0.ru_inblock as u64
+    }
+
+    /// The number of 512-byte blocks written to the file system.
+    pub fn out_blocks(&self) -> u64 {
+        self.0.ru_oublock as u64
+    }
+}
+
+/// A notification that a process made a syscall that its seccomp filter sent to user space, as
+/// returned by [`seccomp_notif_recv`].
+pub struct SeccompNotif(libc::seccomp_notif);
+
+impl SeccompNotif {
+    pub fn id(&self) -> u64 {
+        self.0.id
+    }
+
+    pub fn syscall_number(&self) -> u32 {
+        self.0.data.nr as u32
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, Into, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Signal(c_int);
 
 impl Signal {
+    pub const ABRT: Self = Self(libc::SIGABRT);
     pub const ALRM: Self = Self(libc::SIGALRM);
+    pub const BUS: Self = Self(libc::SIGBUS);
     pub const CHLD: Self = Self(libc::SIGCHLD);
+    pub const FPE: Self = Self(libc::SIGFPE);
     pub const HUP: Self = Self(libc::SIGHUP);
+    pub const ILL: Self = Self(libc::SIGILL);
     pub const INT: Self = Self(libc::SIGINT);
     pub const IO: Self = Self(libc::SIGIO);
     pub const KILL: Self = Self(libc::SIGKILL);
@@ -573,7 +711,10 @@ impl Signal {
     pub const PROF: Self = Self(libc::SIGPROF);
     pub const PWR: Self = Self(libc::SIGPWR);
     pub const QUIT: Self = Self(libc::SIGQUIT);
+    pub const SEGV: Self = Self(libc::SIGSEGV);
+    pub const SYS: Self = Self(libc::SIGSYS);
     pub const TERM: Self = Self(libc::SIGTERM);
+    pub const TRAP: Self = Self(libc::SIGTRAP);
     pub const TSTP: Self = Self(libc::SIGTSTP);
     pub const TTIN: Self = Self(libc::SIGTTIN);
     pub const TTOU: Self = Self(libc::SIGTTOU);
@@ -581,6 +722,22 @@ impl Signal {
     pub const USR2: Self = Self(libc::SIGUSR2);
     pub const VTALRM: Self = Self(libc::SIGVTALRM);
     pub const WINCH: Self = Self(libc::SIGWINCH);
+    pub const XCPU: Self = Self(libc::SIGXCPU);
+    pub const XFSZ: Self = Self(libc::SIGXFSZ);
+
+    /// The signals whose default action is to dump core.
+    pub const CORE_DUMPING: [Self; 10] = [
+        Self::ABRT,
+        Self::BUS,
+        Self::FPE,
+        Self::ILL,
+        Self::QUIT,
+        Self::SEGV,
+        Self::SYS,
+        Self::TRAP,
+        Self::XCPU,
+        Self::XFSZ,
+    ];
 
     pub fn as_u8(&self) -> u8 {
         self.0.try_into().unwrap()
@@ -782,6 +939,35 @@ impl Deref for SockaddrNetlink {
     }
 }
 
+#[repr(transparent)]
+pub struct SockaddrIn(libc::sockaddr_in);
+
+impl SockaddrIn {
+    /// The address of the given port on the loopback interface.
+    pub fn loopback(port: u16) -> Self {
+        Self(libc::sockaddr_in {
+            sin_family: libc::AF_INET as sa_family_t,
+            sin_port: port.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: libc::INADDR_LOOPBACK.to_be(),
+            },
+            sin_zero: [0; 8],
+        })
+    }
+}
+
+impl Deref for SockaddrIn {
+    type Target = Sockaddr;
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            Sockaddr::from_raw_parts(
+                &self.0 as *const libc::sockaddr_in as *const sockaddr,
+                mem::size_of_val(&self.0),
+            )
+        }
+    }
+}
+
 #[repr(C)]
 pub struct SockaddrUn {
     family: sa_family_t,
@@ -888,6 +1074,7 @@ impl std::error::Error for SockaddrUnStoragePathTooLongError {}
 pub struct SocketDomain(c_int);
 
 impl SocketDomain {
+    pub const INET: Self = Self(libc::PF_INET);
     pub const NETLINK: Self = Self(libc::PF_NETLINK);
     pub const UNIX: Self = Self(libc::PF_UNIX);
 }
@@ -897,6 +1084,7 @@ pub struct SocketProtocol(c_int);
 
 impl SocketProtocol {
     pub const NETLINK_ROUTE: Self = Self(0);
+    pub const TCP: Self = Self(libc::IPPROTO_TCP);
 }
 
 #[derive(BitOr, Clone, Copy, Default)]
@@ -909,6 +1097,32 @@ impl SocketType {
     pub const CLOEXEC: Self = Self(libc::SOCK_CLOEXEC);
 }
 
+/// Statistics about a mounted file system, as returned by [`fstatfs`].
+#[derive(Clone, Copy)]
+pub struct Statfs(libc::statfs);
+
+impl Statfs {
+    /// The total number of blocks. Some file systems, like an unlimited tmpfs, report zero.
+    pub fn blocks(&self) -> u64 {
+        self.0.f_blocks
+    }
+
+    /// The number of free blocks available to unprivileged users.
+    pub fn blocks_available(&self) -> u64 {
+        self.0.f_bavail
+    }
+
+    /// The total number of inodes. Some file systems, like an unlimited tmpfs, report zero.
+    pub fn inodes(&self) -> u64 {
+        self.0.f_files
+    }
+
+    /// The number of free inodes.
+    pub fn inodes_free(&self) -> u64 {
+        self.0.f_ffree
+    }
+}
+
 #[derive(Clone, Copy, Display)]
 pub struct Uid(uid_t);
 
@@ -941,6 +1155,9 @@ pub enum WaitStatus {
     Signaled(Signal),
 }
 
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct WatchDescriptor(c_int);
+
 pub enum Whence {
     SeekSet,
     SeekCur,
@@ -1011,6 +1228,12 @@ pub fn chdir(path: &CStr) -> Result<(), Errno> {
     Errno::result(unsafe { libc::chdir(path_ptr) }).map(drop)
 }
 
+pub fn clock_gettime(clock: ClockId) -> Result<Duration, Errno> {
+    let mut ts: libc::timespec = unsafe { mem::zeroed() };
+    Errno::result(unsafe { libc::clock_gettime(clock.0, &mut ts) })
+        .map(|_| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
 pub fn clone3(args: &mut CloneArgs) -> Result<Option<Pid>, Errno> {
     assert_eq!(args.0.flags & libc::CLONE_VM as c_ulong, 0);
     let args_ptr = args as *mut CloneArgs;
@@ -1166,6 +1389,12 @@ pub fn fsopen(fsname: &CStr, flags: FsopenFlags) -> Result<OwnedFd, Errno> {
         .map(OwnedFd)
 }
 
+pub fn fstatfs(fd: &impl AsFd) -> Result<Statfs, Errno> {
+    let fd = fd.fd();
+    let mut buf: libc::statfs = unsafe { mem::zeroed() };
+    Errno::result(unsafe { libc::fstatfs(fd.0, &mut buf) }).map(|_| Statfs(buf))
+}
+
 pub fn getgid() -> Gid {
     Gid(unsafe { libc::getgid() })
 }
@@ -1191,6 +1420,51 @@ pub fn grantpt(fd: &impl AsFd) -> Result<(), Errno> {
     Errno::result(unsafe { libc::grantpt(fd.0) }).map(drop)
 }
 
+pub fn inotify_add_watch(
+    fd: &impl AsFd,
+    path: &CStr,
+    mask: InotifyMask,
+) -> Result<WatchDescriptor, Errno> {
+    let fd = fd.fd();
+    let path_ptr = path.as_ptr();
+    Errno::result(unsafe { libc::inotify_add_watch(fd.0, path_ptr, mask.0) }).map(WatchDescriptor)
+}
+
+pub fn inotify_init1(flags: InotifyFlags) -> Result<OwnedFd, Errno> {
+    Errno::result(unsafe { libc::inotify_init1(flags.0) })
+        .map(Fd)
+        .map(OwnedFd)
+}
+
+/// Parse the events in `buf`, which must have been filled by a [`read`] of an inotify file
+/// descriptor.
+pub fn inotify_read_events(buf: &[u8]) -> impl Iterator<Item = InotifyEvent<'_>> {
+    let header_len = mem::size_of::<libc::inotify_event>();
+    let mut offset = 0;
+    core::iter::from_fn(move || {
+        if offset + header_len > buf.len() {
+            return None;
+        }
+        let header =
+            unsafe { ptr::read_unaligned(buf[offset..].as_ptr() as *const libc::inotify_event) };
+        let name_start = offset + header_len;
+        offset = name_start + header.len as usize;
+        let name = (header.len > 0)
+            .then(|| CStr::from_bytes_until_nul(&buf[name_start..offset]).ok())
+            .flatten();
+        Some(InotifyEvent {
+            wd: WatchDescriptor(header.wd),
+            mask: InotifyMask(header.mask),
+            name,
+        })
+    })
+}
+
+pub fn inotify_rm_watch(fd: &impl AsFd, wd: WatchDescriptor) -> Result<(), Errno> {
+    let fd = fd.fd();
+    Errno::result(unsafe { libc::inotify_rm_watch(fd.0, wd.0) }).map(drop)
+}
+
 pub fn ioctl_tiocsctty(fd: &impl AsFd, arg: i32) -> Result<(), Errno> {
     let fd = fd.fd();
     Errno::result(unsafe { libc::ioctl(fd.0, libc::TIOCSCTTY, arg as c_int) }).map(drop)
@@ -1233,6 +1507,13 @@ pub fn mkdir(path: &CStr, mode: FileMode) -> Result<(), Errno> {
     Errno::result(unsafe { libc::mkdir(path_ptr, mode.0) }).map(drop)
 }
 
+/// Map `len` bytes of anonymous memory at an address chosen by the kernel.
+pub fn mmap_anonymous(len: usize, prot: ProtFlags, flags: MapFlags) -> Result<*mut c_void, Errno> {
+    let flags = flags.0 | libc::MAP_ANONYMOUS;
+    let addr = unsafe { libc::mmap(ptr::null_mut(), len, prot.0, flags, -1, 0) };
+    Errno::result(addr as isize).map(|addr| addr as *mut c_void)
+}
+
 pub fn mount(
     source: Option<&CStr>,
     target: &CStr,
@@ -1272,6 +1553,21 @@ pub fn move_mount(
     .map(drop)
 }
 
+/// # Safety
+///
+/// The range must be part of a mapping owned by the caller, and nothing may access it in a way
+/// that the new protection forbids.
+pub unsafe fn mprotect(addr: *mut c_void, len: usize, prot: ProtFlags) -> Result<(), Errno> {
+    Errno::result(unsafe { libc::mprotect(addr, len, prot.0) }).map(drop)
+}
+
+/// # Safety
+///
+/// The range must be part of a mapping owned by the caller, and nothing may access it afterwards.
+pub unsafe fn munmap(addr: *mut c_void, len: usize) -> Result<(), Errno> {
+    Errno::result(unsafe { libc::munmap(addr, len) }).map(drop)
+}
+
 pub fn open(path: &CStr, flags: OpenFlags, mode: FileMode) -> Result<OwnedFd, Errno> {
     let path_ptr = path.as_ptr();
     let fd = Errno::result(unsafe { libc::open(path_ptr, flags.0, mode.0) })
@@ -1288,6 +1584,12 @@ pub fn open_tree(dirfd: &impl AsFd, path: &CStr, flags: OpenTreeFlags) -> Result
         .map(OwnedFd)
 }
 
+pub fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) }
+        .try_into()
+        .unwrap()
+}
+
 pub fn pause() {
     unsafe { libc::pause() };
 }
@@ -1342,6 +1644,10 @@ pub fn prctl_set_pdeathsig(signal: Signal) -> Result<(), Errno> {
     Errno::result(unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, signal) }).map(drop)
 }
 
+pub fn prctl_set_no_new_privs() -> Result<(), Errno> {
+    Errno::result(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) }).map(drop)
+}
+
 pub fn pthread_sigmask(how: SigprocmaskHow, set: Option<&SignalSet>) -> Result<SignalSet, Errno> {
     let set: *const sigset_t = set.map(|s| &s.0 as *const sigset_t).unwrap_or(ptr::null());
     let mut oldset: MaybeUninit<sigset_t> = MaybeUninit::uninit();
@@ -1367,6 +1673,68 @@ pub fn read(fd: &impl AsFd, buf: &mut [u8]) -> Result<usize, Errno> {
     Errno::result(unsafe { libc::read(fd.0, buf_ptr, buf_len) }).map(|ret| ret as usize)
 }
 
+/// Receive the next notification from a seccomp listener. This blocks until there is one.
+pub fn seccomp_notif_recv(fd: &impl AsFd) -> Result<SeccompNotif, Errno> {
+    const SECCOMP_IOCTL_NOTIF_RECV: c_ulong = 0xc0502100;
+    let fd = fd.fd();
+    // The kernel insists that the structure be zeroed.
+    let mut notif: libc::seccomp_notif = unsafe { mem::zeroed() };
+    Errno::result(unsafe { libc::ioctl(fd.0, SECCOMP_IOCTL_NOTIF_RECV, &mut notif) })?;
+    Ok(SeccompNotif(notif))
+}
+
+/// Make the syscall that caused the notification with the given `id` fail with `errno`.
+pub fn seccomp_notif_send_errno(fd: &impl AsFd, id: u64, errno: u16) -> Result<(), Errno> {
+    const SECCOMP_IOCTL_NOTIF_SEND: c_ulong = 0xc0182101;
+    let fd = fd.fd();
+    let resp = libc::seccomp_notif_resp {
+        id,
+        val: 0,
+        error: -c_int::from(errno),
+        flags: 0,
+    };
+    Errno::result(unsafe { libc::ioctl(fd.0, SECCOMP_IOCTL_NOTIF_SEND, &resp) }).map(drop)
+}
+
+pub fn seccomp_set_mode_filter(filter: &[SockFilter]) -> Result<(), Errno> {
+    let prog = libc::sock_fprog {
+        len: filter.len().try_into().map_err(|_| Errno::EINVAL)?,
+        filter: filter.as_ptr() as *mut libc::sock_filter,
+    };
+    Errno::result(unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            libc::SECCOMP_SET_MODE_FILTER,
+            0,
+            &prog as *const libc::sock_fprog,
+        )
+    })
+    .map(drop)
+}
+
+/// Like [`seccomp_set_mode_filter`], but also return a listener for the filter's
+/// [`SockFilter::return_user_notif`] notifications. The listener is close-on-exec.
+pub fn seccomp_set_mode_filter_with_listener(filter: &[SockFilter]) -> Result<OwnedFd, Errno> {
+    let prog = libc::sock_fprog {
+        len: filter.len().try_into().map_err(|_| Errno::EINVAL)?,
+        filter: filter.as_ptr() as *mut libc::sock_filter,
+    };
+    let fd = Errno::result(unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            libc::SECCOMP_SET_MODE_FILTER,
+            libc::SECCOMP_FILTER_FLAG_NEW_LISTENER,
+            &prog as *const libc::sock_fprog,
+        )
+    })?;
+    Ok(OwnedFd(Fd(fd as c_int)))
+}
+
+pub fn sethostname(name: &[u8]) -> Result<(), Errno> {
+    let name_ptr = name.as_ptr() as *const c_char;
+    Errno::result(unsafe { libc::sethostname(name_ptr, name.len()) }).map(drop)
+}
+
 pub fn setsid() -> Result<(), Errno> {
     Errno::result(unsafe { libc::setsid() }).map(drop)
 }
@@ -1399,6 +1767,10 @@ pub fn umount2(path: &CStr, flags: UmountFlags) -> Result<(), Errno> {
     Errno::result(unsafe { libc::umount2(path_ptr, flags.0) }).map(drop)
 }
 
+pub fn unshare(flags: CloneFlags) -> Result<(), Errno> {
+    Errno::result(unsafe { libc::unshare(flags.0) }).map(drop)
+}
+
 pub fn unlockpt(fd: &impl AsFd) -> Result<(), Errno> {
     let fd = fd.fd();
     Errno::result(unsafe { libc::unlockpt(fd.0) }).map(drop)
@@ -1435,6 +1807,22 @@ pub fn wait() -> Result<WaitResult, Errno> {
     })
 }
 
+/// Reap any child that has terminated, without blocking. Returns `None` if there are children, but
+/// none of them have terminated yet.
+pub fn try_wait() -> Result<Option<WaitResult>, Errno> {
+    let inner = |status: &mut c_int| {
+        let status_ptr = status as *mut c_int;
+        unsafe { libc::waitpid(-1, status_ptr, libc::WNOHANG) }
+    };
+    let mut status = 0;
+    Errno::result(inner(&mut status)).map(|pid| {
+        (pid != 0).then(|| WaitResult {
+            pid: Pid(pid),
+            status: extract_wait_status(status),
+        })
+    })
+}
+
 pub fn waitpid(pid: Pid) -> Result<WaitStatus, Errno> {
     let inner = |status: &mut c_int| {
         let status_ptr = status as *mut c_int;
@@ -1458,6 +1846,26 @@ pub fn waitid(pidfd: &impl AsFd) -> Result<WaitStatus, Errno> {
     Errno::result(inner(&mut siginfo)).map(|_| extract_wait_status_from_siginfo(siginfo))
 }
 
+/// Like [`waitid`], but also return the resources used by the child and all of its waited-for
+/// descendants. The libc wrapper doesn't expose the kernel's rusage argument, so we make the system
+/// call directly.
+pub fn waitid_with_rusage(pidfd: &impl AsFd) -> Result<(WaitStatus, Rusage), Errno> {
+    let pidfd = pidfd.fd();
+    let mut siginfo: siginfo_t = unsafe { mem::zeroed() };
+    let mut rusage: libc::rusage = unsafe { mem::zeroed() };
+    Errno::result(unsafe {
+        libc::syscall(
+            libc::SYS_waitid,
+            libc::P_PIDFD,
+            pidfd.0,
+            &mut siginfo as *mut siginfo_t,
+            libc::WEXITED,
+            &mut rusage as *mut libc::rusage,
+        )
+    })
+    .map(|_| (extract_wait_status_from_siginfo(siginfo), Rusage(rusage)))
+}
+
 pub fn write(fd: &impl AsFd, buf: &[u8]) -> Result<usize, Errno> {
     let fd = fd.fd();
     let buf_ptr = buf.as_ptr() as *const c_void;
@@ -1703,6 +2111,37 @@ mod tests {
         assert_eq!(std::format!("{:?}", Errno(1234)).as_str(), "UNKNOWN(1234)");
     }
 
+    #[test]
+    fn inotify_read_events_parses_names() {
+        let mut buf = std::vec::Vec::new();
+        for (wd, mask, name) in [
+            (1, libc::IN_CREATE, &b"foo\0\0\0\0\0"[..]),
+            (2, libc::IN_IGNORED, b""),
+        ] {
+            let event = libc::inotify_event {
+                wd,
+                mask,
+                cookie: 0,
+                len: name.len() as u32,
+            };
+            let event_ptr = &event as *const libc::inotify_event as *const u8;
+            let header_len = mem::size_of::<libc::inotify_event>();
+            buf.extend_from_slice(unsafe { core::slice::from_raw_parts(event_ptr, header_len) });
+            buf.extend_from_slice(name);
+        }
+
+        let events = inotify_read_events(&buf)
+            .map(|event| (event.wd, event.mask, event.name))
+            .collect::<std::vec::Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                (WatchDescriptor(1), InotifyMask::CREATE, Some(c"foo")),
+                (WatchDescriptor(2), InotifyMask::IGNORED, None),
+            ]
+        );
+    }
+
     #[test]
     fn unix_stream_send_recv() {
         let (a, b) = UnixStream::pair().unwrap();
@@ -1788,9 +2227,14 @@ pub fn abort() -> ! {
     unsafe { libc::abort() }
 }
 
+#[derive(Clone, Copy)]
 #[repr(u32)]
 pub enum RlimitResource {
+    As = libc::RLIMIT_AS,
+    Core = libc::RLIMIT_CORE,
+    Cpu = libc::RLIMIT_CPU,
     NoFile = libc::RLIMIT_NOFILE,
+    Stack = libc::RLIMIT_STACK,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -1807,3 +2251,558 @@ pub fn getrlimit(resource: RlimitResource) -> Result<Rlimit, Errno> {
         max: rlimit.rlim_max,
     })
 }
+
+pub fn setrlimit(resource: RlimitResource, rlimit: &Rlimit) -> Result<(), Errno> {
+    let rlimit = libc::rlimit {
+        rlim_cur: rlimit.current,
+        rlim_max: rlimit.max,
+    };
+    Errno::result(unsafe { libc::setrlimit(resource as u32, &rlimit) }).map(drop)
+}
+
+/// A single classic BPF instruction, as used by seccomp filters. Only the instructions needed to
+/// filter on the syscall number and simple arguments are provided.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct SockFilter(libc::sock_filter);
+
+impl SockFilter {
+    const fn new(code: u32, k: u32, jt: u8, jf: u8) -> Self {
+        Self(libc::sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        })
+    }
+
+    /// Load the `arch` field of `struct seccomp_data` into the accumulator.
+    pub const fn load_arch() -> Self {
+        Self::new(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 4, 0, 0)
+    }
+
+    /// Load the `nr` field of `struct seccomp_data` into the accumulator.
+    pub const fn load_syscall_number() -> Self {
+        Self::new(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 0, 0, 0)
+    }
+
+    /// Load the low 32 bits of the syscall argument with the given index into the accumulator.
+    pub const fn load_syscall_arg_low(index: u32) -> Self {
+        #[cfg(target_endian = "little")]
+        let offset = 16 + index * 8;
+        #[cfg(target_endian = "big")]
+        let offset = 20 + index * 8;
+        Self::new(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset, 0, 0)
+    }
+
+    /// Skip `jt` instructions if the accumulator equals `k`, otherwise skip `jf` instructions.
+    pub const fn jump_if_equal(k: u32, jt: u8, jf: u8) -> Self {
+        Self::new(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, k, jt, jf)
+    }
+
+    /// Skip `jt` instructions if the accumulator is at least `k`, otherwise skip `jf` instructions.
+    pub const fn jump_if_greater_or_equal(k: u32, jt: u8, jf: u8) -> Self {
+        Self::new(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, k, jt, jf)
+    }
+
+    pub const fn return_allow() -> Self {
+        Self::new(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW, 0, 0)
+    }
+
+    pub const fn return_errno(errno: u16) -> Self {
+        Self::new(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_ERRNO | errno as u32,
+            0,
+            0,
+        )
+    }
+
+    /// Send a notification to the filter's listener, and let it decide what the syscall returns.
+    /// See [`seccomp_set_mode_filter_with_listener`].
+    pub const fn return_user_notif() -> Self {
+        const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc00000;
+        Self::new(libc::BPF_RET | libc::BPF_K, SECCOMP_RET_USER_NOTIF, 0, 0)
+    }
+
+    pub const fn return_kill_process() -> Self {
+        Self::new(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_KILL_PROCESS,
+            0,
+            0,
+        )
+    }
+}
+
+impl fmt::Debug for SockFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SockFilter")
+            .field("code", &self.0.code)
+            .field("jt", &self.0.jt)
+            .field("jf", &self.0.jf)
+            .field("k", &self.0.k)
+            .finish()
+    }
+}
+
+impl PartialEq for SockFilter {
+    fn eq(&self, other: &Self) -> bool {
+        (self.0.code, self.0.jt, self.0.jf, self.0.k)
+            == (other.0.code, other.0.jt, other.0.jf, other.0.k)
+    }
+}
+
+impl Eq for SockFilter {}
+
+/// The `AUDIT_ARCH_*` value the kernel reports in `struct seccomp_data` for native syscalls.
+#[cfg(target_arch = "x86_64")]
+pub const AUDIT_ARCH_NATIVE: u32 = 0xc000003e;
+#[cfg(target_arch = "aarch64")]
+pub const AUDIT_ARCH_NATIVE: u32 = 0xc00000b7;
+
+/// On x86-64, syscalls made through the x32 ABI have this bit set in their syscall number.
+#[cfg(target_arch = "x86_64")]
+pub const X32_SYSCALL_BIT: u32 = 0x40000000;
+
+/// Look up the number of the syscall with the given name on the current architecture.
+pub fn syscall_number(name: &str) -> Option<u32> {
+    SYSCALL_NUMBERS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, nr)| *nr as u32)
+}
+
+/// Look up the name of the syscall with the given number on the current architecture.
+pub fn syscall_name(nr: u32) -> Option<&'static str> {
+    SYSCALL_NUMBERS
+        .iter()
+        .find(|(_, n)| *n as u32 == nr)
+        .map(|(name, _)| *name)
+}
+
+const SYSCALL_NUMBERS: &[(&str, c_long)] = &[
+    #[cfg(target_arch = "x86_64")]
+    ("_sysctl", libc::SYS__sysctl),
+    ("accept", libc::SYS_accept),
+    ("accept4", libc::SYS_accept4),
+    #[cfg(target_arch = "x86_64")]
+    ("access", libc::SYS_access),
+    ("acct", libc::SYS_acct),
+    ("add_key", libc::SYS_add_key),
+    ("adjtimex", libc::SYS_adjtimex),
+    #[cfg(target_arch = "x86_64")]
+    ("afs_syscall", libc::SYS_afs_syscall),
+    #[cfg(target_arch = "x86_64")]
+    ("alarm", libc::SYS_alarm),
+    #[cfg(target_arch = "x86_64")]
+    ("arch_prctl", libc::SYS_arch_prctl),
+    ("bind", libc::SYS_bind),
+    ("bpf", libc::SYS_bpf),
+    ("brk", libc::SYS_brk),
+    ("capget", libc::SYS_capget),
+    ("capset", libc::SYS_capset),
+    ("chdir", libc::SYS_chdir),
+    #[cfg(target_arch = "x86_64")]
+    ("chmod", libc::SYS_chmod),
+    #[cfg(target_arch = "x86_64")]
+    ("chown", libc::SYS_chown),
+    ("chroot", libc::SYS_chroot),
+    ("clock_adjtime", libc::SYS_clock_adjtime),
+    ("clock_getres", libc::SYS_clock_getres),
+    ("clock_gettime", libc::SYS_clock_gettime),
+    ("clock_nanosleep", libc::SYS_clock_nanosleep),
+    ("clock_settime", libc::SYS_clock_settime),
+    ("clone", libc::SYS_clone),
+    ("clone3", libc::SYS_clone3),
+    ("close", libc::SYS_close),
+    ("close_range", libc::SYS_close_range),
+    ("connect", libc::SYS_connect),
+    ("copy_file_range", libc::SYS_copy_file_range),
+    #[cfg(target_arch = "x86_64")]
+    ("creat", libc::SYS_creat),
+    #[cfg(target_arch = "x86_64")]
+    ("create_module", libc::SYS_create_module),
+    ("delete_module", libc::SYS_delete_module),
+    ("dup", libc::SYS_dup),
+    #[cfg(target_arch = "x86_64")]
+    ("dup2", libc::SYS_dup2),
+    ("dup3", libc::SYS_dup3),
+    #[cfg(target_arch = "x86_64")]
+    ("epoll_create", libc::SYS_epoll_create),
+    ("epoll_create1", libc::SYS_epoll_create1),
+    ("epoll_ctl", libc::SYS_epoll_ctl),
+    #[cfg(target_arch = "x86_64")]
+    ("epoll_ctl_old", libc::SYS_epoll_ctl_old),
+    ("epoll_pwait", libc::SYS_epoll_pwait),
+    ("epoll_pwait2", libc::SYS_epoll_pwait2),
+    #[cfg(target_arch = "x86_64")]
+    ("epoll_wait", libc::SYS_epoll_wait),
+    #[cfg(target_arch = "x86_64")]
+    ("epoll_wait_old", libc::SYS_epoll_wait_old),
+    #[cfg(target_arch = "x86_64")]
+    ("eventfd", libc::SYS_eventfd),
+    ("eventfd2", libc::SYS_eventfd2),
+    ("execve", libc::SYS_execve),
+    ("execveat", libc::SYS_execveat),
+    ("exit", libc::SYS_exit),
+    ("exit_group", libc::SYS_exit_group),
+    ("faccessat", libc::SYS_faccessat),
+    ("faccessat2", libc::SYS_faccessat2),
+    #[cfg(target_arch = "x86_64")]
+    ("fadvise64", libc::SYS_fadvise64),
+    ("fallocate", libc::SYS_fallocate),
+    ("fanotify_init", libc::SYS_fanotify_init),
+    ("fanotify_mark", libc::SYS_fanotify_mark),
+    ("fchdir", libc::SYS_fchdir),
+    ("fchmod", libc::SYS_fchmod),
+    ("fchmodat", libc::SYS_fchmodat),
+    ("fchown", libc::SYS_fchown),
+    ("fchownat", libc::SYS_fchownat),
+    ("fcntl", libc::SYS_fcntl),
+    ("fdatasync", libc::SYS_fdatasync),
+    ("fgetxattr", libc::SYS_fgetxattr),
+    ("finit_module", libc::SYS_finit_module),
+    ("flistxattr", libc::SYS_flistxattr),
+    ("flock", libc::SYS_flock),
+    #[cfg(target_arch = "x86_64")]
+    ("fork", libc::SYS_fork),
+    ("fremovexattr", libc::SYS_fremovexattr),
+    ("fsconfig", libc::SYS_fsconfig),
+    ("fsetxattr", libc::SYS_fsetxattr),
+    ("fsmount", libc::SYS_fsmount),
+    ("fsopen", libc::SYS_fsopen),
+    ("fspick", libc::SYS_fspick),
+    ("fstat", libc::SYS_fstat),
+    ("fstatfs", libc::SYS_fstatfs),
+    ("fsync", libc::SYS_fsync),
+    ("ftruncate", libc::SYS_ftruncate),
+    ("futex", libc::SYS_futex),
+    ("futex_waitv", libc::SYS_futex_waitv),
+    #[cfg(target_arch = "x86_64")]
+    ("futimesat", libc::SYS_futimesat),
+    #[cfg(target_arch = "x86_64")]
+    ("get_kernel_syms", libc::SYS_get_kernel_syms),
+    ("get_mempolicy", libc::SYS_get_mempolicy),
+    ("get_robust_list", libc::SYS_get_robust_list),
+    #[cfg(target_arch = "x86_64")]
+    ("get_thread_area", libc::SYS_get_thread_area),
+    ("getcpu", libc::SYS_getcpu),
+    ("getcwd", libc::SYS_getcwd),
+    #[cfg(target_arch = "x86_64")]
+    ("getdents", libc::SYS_getdents),
+    ("getdents64", libc::SYS_getdents64),
+    ("getegid", libc::SYS_getegid),
+    ("geteuid", libc::SYS_geteuid),
+    ("getgid", libc::SYS_getgid),
+    ("getgroups", libc::SYS_getgroups),
+    ("getitimer", libc::SYS_getitimer),
+    ("getpeername", libc::SYS_getpeername),
+    ("getpgid", libc::SYS_getpgid),
+    #[cfg(target_arch = "x86_64")]
+    ("getpgrp", libc::SYS_getpgrp),
+    ("getpid", libc::SYS_getpid),
+    #[cfg(target_arch = "x86_64")]
+    ("getpmsg", libc::SYS_getpmsg),
+    ("getppid", libc::SYS_getppid),
+    ("getpriority", libc::SYS_getpriority),
+    ("getrandom", libc::SYS_getrandom),
+    ("getresgid", libc::SYS_getresgid),
+    ("getresuid", libc::SYS_getresuid),
+    #[cfg(target_arch = "x86_64")]
+    ("getrlimit", libc::SYS_getrlimit),
+    ("getrusage", libc::SYS_getrusage),
+    ("getsid", libc::SYS_getsid),
+    ("getsockname", libc::SYS_getsockname),
+    ("getsockopt", libc::SYS_getsockopt),
+    ("gettid", libc::SYS_gettid),
+    ("gettimeofday", libc::SYS_gettimeofday),
+    ("getuid", libc::SYS_getuid),
+    ("getxattr", libc::SYS_getxattr),
+    ("init_module", libc::SYS_init_module),
+    ("inotify_add_watch", libc::SYS_inotify_add_watch),
+    #[cfg(target_arch = "x86_64")]
+    ("inotify_init", libc::SYS_inotify_init),
+    ("inotify_init1", libc::SYS_inotify_init1),
+    ("inotify_rm_watch", libc::SYS_inotify_rm_watch),
+    ("io_cancel", libc::SYS_io_cancel),
+    ("io_destroy", libc::SYS_io_destroy),
+    ("io_getevents", libc::SYS_io_getevents),
+    ("io_setup", libc::SYS_io_setup),
+    ("io_submit", libc::SYS_io_submit),
+    ("io_uring_enter", libc::SYS_io_uring_enter),
+    ("io_uring_register", libc::SYS_io_uring_register),
+    ("io_uring_setup", libc::SYS_io_uring_setup),
+    ("ioctl", libc::SYS_ioctl),
+    #[cfg(target_arch = "x86_64")]
+    ("ioperm", libc::SYS_ioperm),
+    #[cfg(target_arch = "x86_64")]
+    ("iopl", libc::SYS_iopl),
+    ("ioprio_get", libc::SYS_ioprio_get),
+    ("ioprio_set", libc::SYS_ioprio_set),
+    ("kcmp", libc::SYS_kcmp),
+    ("kexec_file_load", libc::SYS_kexec_file_load),
+    ("kexec_load", libc::SYS_kexec_load),
+    ("keyctl", libc::SYS_keyctl),
+    ("kill", libc::SYS_kill),
+    ("landlock_add_rule", libc::SYS_landlock_add_rule),
+    ("landlock_create_ruleset", libc::SYS_landlock_create_ruleset),
+    ("landlock_restrict_self", libc::SYS_landlock_restrict_self),
+    #[cfg(target_arch = "x86_64")]
+    ("lchown", libc::SYS_lchown),
+    ("lgetxattr", libc::SYS_lgetxattr),
+    #[cfg(target_arch = "x86_64")]
+    ("link", libc::SYS_link),
+    ("linkat", libc::SYS_linkat),
+    ("listen", libc::SYS_listen),
+    ("listxattr", libc::SYS_listxattr),
+    ("llistxattr", libc::SYS_llistxattr),
+    ("lookup_dcookie", libc::SYS_lookup_dcookie),
+    ("lremovexattr", libc::SYS_lremovexattr),
+    ("lseek", libc::SYS_lseek),
+    ("lsetxattr", libc::SYS_lsetxattr),
+    #[cfg(target_arch = "x86_64")]
+    ("lstat", libc::SYS_lstat),
+    ("madvise", libc::SYS_madvise),
+    ("mbind", libc::SYS_mbind),
+    ("membarrier", libc::SYS_membarrier),
+    ("memfd_create", libc::SYS_memfd_create),
+    ("memfd_secret", libc::SYS_memfd_secret),
+    ("migrate_pages", libc::SYS_migrate_pages),
+    ("mincore", libc::SYS_mincore),
+    #[cfg(target_arch = "x86_64")]
+    ("mkdir", libc::SYS_mkdir),
+    ("mkdirat", libc::SYS_mkdirat),
+    #[cfg(target_arch = "x86_64")]
+    ("mknod", libc::SYS_mknod),
+    ("mknodat", libc::SYS_mknodat),
+    ("mlock", libc::SYS_mlock),
+    ("mlock2", libc::SYS_mlock2),
+    ("mlockall", libc::SYS_mlockall),
+    ("mmap", libc::SYS_mmap),
+    #[cfg(target_arch = "x86_64")]
+    ("modify_ldt", libc::SYS_modify_ldt),
+    ("mount", libc::SYS_mount),
+    ("mount_setattr", libc::SYS_mount_setattr),
+    ("move_mount", libc::SYS_move_mount),
+    ("move_pages", libc::SYS_move_pages),
+    ("mprotect", libc::SYS_mprotect),
+    ("mq_getsetattr", libc::SYS_mq_getsetattr),
+    ("mq_notify", libc::SYS_mq_notify),
+    ("mq_open", libc::SYS_mq_open),
+    ("mq_timedreceive", libc::SYS_mq_timedreceive),
+    ("mq_timedsend", libc::SYS_mq_timedsend),
+    ("mq_unlink", libc::SYS_mq_unlink),
+    ("mremap", libc::SYS_mremap),
+    ("msgctl", libc::SYS_msgctl),
+    ("msgget", libc::SYS_msgget),
+    ("msgrcv", libc::SYS_msgrcv),
+    ("msgsnd", libc::SYS_msgsnd),
+    ("msync", libc::SYS_msync),
+    ("munlock", libc::SYS_munlock),
+    ("munlockall", libc::SYS_munlockall),
+    ("munmap", libc::SYS_munmap),
+    ("name_to_handle_at", libc::SYS_name_to_handle_at),
+    ("nanosleep", libc::SYS_nanosleep),
+    ("newfstatat", libc::SYS_newfstatat),
+    ("nfsservctl", libc::SYS_nfsservctl),
+    #[cfg(target_arch = "x86_64")]
+    ("open", libc::SYS_open),
+    ("open_by_handle_at", libc::SYS_open_by_handle_at),
+    ("open_tree", libc::SYS_open_tree),
+    ("openat", libc::SYS_openat),
+    ("openat2", libc::SYS_openat2),
+    #[cfg(target_arch = "x86_64")]
+    ("pause", libc::SYS_pause),
+    ("perf_event_open", libc::SYS_perf_event_open),
+    ("personality", libc::SYS_personality),
+    ("pidfd_getfd", libc::SYS_pidfd_getfd),
+    ("pidfd_open", libc::SYS_pidfd_open),
+    ("pidfd_send_signal", libc::SYS_pidfd_send_signal),
+    #[cfg(target_arch = "x86_64")]
+    ("pipe", libc::SYS_pipe),
+    ("pipe2", libc::SYS_pipe2),
+    ("pivot_root", libc::SYS_pivot_root),
+    ("pkey_alloc", libc::SYS_pkey_alloc),
+    ("pkey_free", libc::SYS_pkey_free),
+    ("pkey_mprotect", libc::SYS_pkey_mprotect),
+    #[cfg(target_arch = "x86_64")]
+    ("poll", libc::SYS_poll),
+    ("ppoll", libc::SYS_ppoll),
+    ("prctl", libc::SYS_prctl),
+    ("pread64", libc::SYS_pread64),
+    ("preadv", libc::SYS_preadv),
+    ("preadv2", libc::SYS_preadv2),
+    ("prlimit64", libc::SYS_prlimit64),
+    ("process_madvise", libc::SYS_process_madvise),
+    ("process_mrelease", libc::SYS_process_mrelease),
+    ("process_vm_readv", libc::SYS_process_vm_readv),
+    ("process_vm_writev", libc::SYS_process_vm_writev),
+    ("pselect6", libc::SYS_pselect6),
+    ("ptrace", libc::SYS_ptrace),
+    #[cfg(target_arch = "x86_64")]
+    ("putpmsg", libc::SYS_putpmsg),
+    ("pwrite64", libc::SYS_pwrite64),
+    ("pwritev", libc::SYS_pwritev),
+    ("pwritev2", libc::SYS_pwritev2),
+    #[cfg(target_arch = "x86_64")]
+    ("query_module", libc::SYS_query_module),
+    ("quotactl", libc::SYS_quotactl),
+    ("quotactl_fd", libc::SYS_quotactl_fd),
+    ("read", libc::SYS_read),
+    ("readahead", libc::SYS_readahead),
+    #[cfg(target_arch = "x86_64")]
+    ("readlink", libc::SYS_readlink),
+    ("readlinkat", libc::SYS_readlinkat),
+    ("readv", libc::SYS_readv),
+    ("reboot", libc::SYS_reboot),
+    ("recvfrom", libc::SYS_recvfrom),
+    ("recvmmsg", libc::SYS_recvmmsg),
+    ("recvmsg", libc::SYS_recvmsg),
+    ("remap_file_pages", libc::SYS_remap_file_pages),
+    ("removexattr", libc::SYS_removexattr),
+    #[cfg(target_arch = "x86_64")]
+    ("rename", libc::SYS_rename),
+    #[cfg(target_arch = "x86_64")]
+    ("renameat", libc::SYS_renameat),
+    ("renameat2", libc::SYS_renameat2),
+    ("request_key", libc::SYS_request_key),
+    ("restart_syscall", libc::SYS_restart_syscall),
+    #[cfg(target_arch = "x86_64")]
+    ("rmdir", libc::SYS_rmdir),
+    ("rseq", libc::SYS_rseq),
+    ("rt_sigaction", libc::SYS_rt_sigaction),
+    ("rt_sigpending", libc::SYS_rt_sigpending),
+    ("rt_sigprocmask", libc::SYS_rt_sigprocmask),
+    ("rt_sigqueueinfo", libc::SYS_rt_sigqueueinfo),
+    ("rt_sigreturn", libc::SYS_rt_sigreturn),
+    ("rt_sigsuspend", libc::SYS_rt_sigsuspend),
+    ("rt_sigtimedwait", libc::SYS_rt_sigtimedwait),
+    ("rt_tgsigqueueinfo", libc::SYS_rt_tgsigqueueinfo),
+    ("sched_get_priority_max", libc::SYS_sched_get_priority_max),
+    ("sched_get_priority_min", libc::SYS_sched_get_priority_min),
+    ("sched_getaffinity", libc::SYS_sched_getaffinity),
+    ("sched_getattr", libc::SYS_sched_getattr),
+    ("sched_getparam", libc::SYS_sched_getparam),
+    ("sched_getscheduler", libc::SYS_sched_getscheduler),
+    ("sched_rr_get_interval", libc::SYS_sched_rr_get_interval),
+    ("sched_setaffinity", libc::SYS_sched_setaffinity),
+    ("sched_setattr", libc::SYS_sched_setattr),
+    ("sched_setparam", libc::SYS_sched_setparam),
+    ("sched_setscheduler", libc::SYS_sched_setscheduler),
+    ("sched_yield", libc::SYS_sched_yield),
+    ("seccomp", libc::SYS_seccomp),
+    #[cfg(target_arch = "x86_64")]
+    ("security", libc::SYS_security),
+    #[cfg(target_arch = "x86_64")]
+    ("select", libc::SYS_select),
+    ("semctl", libc::SYS_semctl),
+    ("semget", libc::SYS_semget),
+    ("semop", libc::SYS_semop),
+    ("semtimedop", libc::SYS_semtimedop),
+    #[cfg(target_arch = "x86_64")]
+    ("sendfile", libc::SYS_sendfile),
+    ("sendmmsg", libc::SYS_sendmmsg),
+    ("sendmsg", libc::SYS_sendmsg),
+    ("sendto", libc::SYS_sendto),
+    ("set_mempolicy", libc::SYS_set_mempolicy),
+    ("set_mempolicy_home_node", libc::SYS_set_mempolicy_home_node),
+    ("set_robust_list", libc::SYS_set_robust_list),
+    #[cfg(target_arch = "x86_64")]
+    ("set_thread_area", libc::SYS_set_thread_area),
+    ("set_tid_address", libc::SYS_set_tid_address),
+    ("setdomainname", libc::SYS_setdomainname),
+    ("setfsgid", libc::SYS_setfsgid),
+    ("setfsuid", libc::SYS_setfsuid),
+    ("setgid", libc::SYS_setgid),
+    ("setgroups", libc::SYS_setgroups),
+    ("sethostname", libc::SYS_sethostname),
+    ("setitimer", libc::SYS_setitimer),
+    ("setns", libc::SYS_setns),
+    ("setpgid", libc::SYS_setpgid),
+    ("setpriority", libc::SYS_setpriority),
+    ("setregid", libc::SYS_setregid),
+    ("setresgid", libc::SYS_setresgid),
+    ("setresuid", libc::SYS_setresuid),
+    ("setreuid", libc::SYS_setreuid),
+    #[cfg(target_arch = "x86_64")]
+    ("setrlimit", libc::SYS_setrlimit),
+    ("setsid", libc::SYS_setsid),
+    ("setsockopt", libc::SYS_setsockopt),
+    ("settimeofday", libc::SYS_settimeofday),
+    ("setuid", libc::SYS_setuid),
+    ("setxattr", libc::SYS_setxattr),
+    ("shmat", libc::SYS_shmat),
+    ("shmctl", libc::SYS_shmctl),
+    ("shmdt", libc::SYS_shmdt),
+    ("shmget", libc::SYS_shmget),
+    ("shutdown", libc::SYS_shutdown),
+    ("sigaltstack", libc::SYS_sigaltstack),
+    #[cfg(target_arch = "x86_64")]
+    ("signalfd", libc::SYS_signalfd),
+    ("signalfd4", libc::SYS_signalfd4),
+    ("socket", libc::SYS_socket),
+    ("socketpair", libc::SYS_socketpair),
+    ("splice", libc::SYS_splice),
+    #[cfg(target_arch = "x86_64")]
+    ("stat", libc::SYS_stat),
+    ("statfs", libc::SYS_statfs),
+    ("statx", libc::SYS_statx),
+    ("swapoff", libc::SYS_swapoff),
+    ("swapon", libc::SYS_swapon),
+    #[cfg(target_arch = "x86_64")]
+    ("symlink", libc::SYS_symlink),
+    ("symlinkat", libc::SYS_symlinkat),
+    ("sync", libc::SYS_sync),
+    #[cfg(target_arch = "x86_64")]
+    ("sync_file_range", libc::SYS_sync_file_range),
+    ("syncfs", libc::SYS_syncfs),
+    #[cfg(target_arch = "x86_64")]
+    ("sysfs", libc::SYS_sysfs),
+    ("sysinfo", libc::SYS_sysinfo),
+    ("syslog", libc::SYS_syslog),
+    ("tee", libc::SYS_tee),
+    ("tgkill", libc::SYS_tgkill),
+    #[cfg(target_arch = "x86_64")]
+    ("time", libc::SYS_time),
+    ("timer_create", libc::SYS_timer_create),
+    ("timer_delete", libc::SYS_timer_delete),
+    ("timer_getoverrun", libc::SYS_timer_getoverrun),
+    ("timer_gettime", libc::SYS_timer_gettime),
+    ("timer_settime", libc::SYS_timer_settime),
+    ("timerfd_create", libc::SYS_timerfd_create),
+    ("timerfd_gettime", libc::SYS_timerfd_gettime),
+    ("timerfd_settime", libc::SYS_timerfd_settime),
+    ("times", libc::SYS_times),
+    ("tkill", libc::SYS_tkill),
+    ("truncate", libc::SYS_truncate),
+    #[cfg(target_arch = "x86_64")]
+    ("tuxcall", libc::SYS_tuxcall),
+    ("umask", libc::SYS_umask),
+    ("umount2", libc::SYS_umount2),
+    ("uname", libc::SYS_uname),
+    #[cfg(target_arch = "x86_64")]
+    ("unlink", libc::SYS_unlink),
+    ("unlinkat", libc::SYS_unlinkat),
+    ("unshare", libc::SYS_unshare),
+    #[cfg(target_arch = "x86_64")]
+    ("uselib", libc::SYS_uselib),
+    ("userfaultfd", libc::SYS_userfaultfd),
+    #[cfg(target_arch = "x86_64")]
+    ("ustat", libc::SYS_ustat),
+    #[cfg(target_arch = "x86_64")]
+    ("utime", libc::SYS_utime),
+    ("utimensat", libc::SYS_utimensat),
+    #[cfg(target_arch = "x86_64")]
+    ("utimes", libc::SYS_utimes),
+    #[cfg(target_arch = "x86_64")]
+    ("vfork", libc::SYS_vfork),
+    ("vhangup", libc::SYS_vhangup),
+    ("vmsplice", libc::SYS_vmsplice),
+    #[cfg(target_arch = "x86_64")]
+    ("vserver", libc::SYS_vserver),
+    ("wait4", libc::SYS_wait4),
+    ("waitid", libc::SYS_waitid),
+    ("write", libc::SYS_write),
+    ("writev", libc::SYS_writev),
+];
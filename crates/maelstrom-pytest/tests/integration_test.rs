@@ -163,7 +163,7 @@ fn test_simple_success() {
         },
     );
     assert!(
-        contents.contains("test_foo.py::test_noop.................OK"),
+        contents.contains("test_foo.py::test_noop......OK"),
         "{contents}"
     );
     assert!(
@@ -200,7 +200,7 @@ fn test_simple_failure() {
     let rest = &contents[first_line.len() + 1..];
 
     assert!(
-        first_line.starts_with("test_foo.py::test_error..............FAIL"),
+        first_line.starts_with("test_foo.py::test_error...FAIL"),
         "{contents}"
     );
     assert_eq!(
@@ -378,7 +378,7 @@ fn test_ignore() {
         "{contents}"
     );
     assert!(
-        contents.contains("test_foo.py::test_baz..................OK"),
+        contents.contains("test_foo.py::test_baz.......OK"),
         "{contents}"
     );
 
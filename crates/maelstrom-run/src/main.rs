@@ -1,12 +1,12 @@
 use anyhow::{anyhow, Error, Result};
 use clap::Args;
 use maelstrom_base::{
-    tty, ClientJobId, JobCompleted, JobEffects, JobError, JobOutcome, JobOutcomeResult,
-    JobOutputResult, JobTerminationStatus, JobTty, WindowSize,
+    tty, ClientJobId, JobBlockedSyscall, JobCompleted, JobEffects, JobError, JobOutcome,
+    JobOutcomeResult, JobOutputResult, JobTerminationStatus, JobTty, Utf8PathBuf, WindowSize,
 };
 use maelstrom_client::{
     AcceptInvalidRemoteContainerTlsCerts, CacheDir, Client, ClientBgProcess,
-    ContainerImageDepotDir, JobSpec, JobStatus, ProjectDir, StateDir,
+    ContainerImageDepotDir, JobSpec, JobStatus, ProjectDir, StateDir, STDIN_DIR,
 };
 use maelstrom_linux::{self as linux, Fd, PollEvents, PollFd, Signal, SignalSet, SigprocmaskHow};
 use maelstrom_macro::Config;
@@ -184,6 +184,12 @@ fn print_effects(
         stdout,
         stderr,
         duration: _,
+        resource_usage: _,
+        core_dump: _,
+        core_dump_not_captured,
+        writable_layer: _,
+        full_tmpfs_mounts,
+        blocked_syscalls,
     }: JobEffects,
 ) -> Result<()> {
     match stdout {
@@ -215,6 +221,27 @@ fn print_effects(
             }
         }
     }
+    for mount_point in full_tmpfs_mounts {
+        if let Some(cjid) = cjid {
+            eprintln!("job {cjid}: tmpfs at {mount_point} was full when the job exited");
+        } else {
+            eprintln!("tmpfs at {mount_point} was full when the job exited");
+        }
+    }
+    if let Some(reason) = core_dump_not_captured {
+        if let Some(cjid) = cjid {
+            eprintln!("job {cjid}: core dump not captured: {reason}");
+        } else {
+            eprintln!("core dump not captured: {reason}");
+        }
+    }
+    for JobBlockedSyscall { name, count } in blocked_syscalls {
+        if let Some(cjid) = cjid {
+            eprintln!("job {cjid}: seccomp blocked {name} {count} time(s)");
+        } else {
+            eprintln!("seccomp blocked {name} {count} time(s)");
+        }
+    }
     Ok(())
 }
 
@@ -223,7 +250,7 @@ fn visitor(res: Result<JobStatus>, tracker: &JobTracker) {
         Ok(JobStatus::Completed {
             client_job_id,
             result,
-        }) => Ok((client_job_id, result)),
+        }) => Ok((client_job_id, *result)),
         Ok(_) => return,
         Err(err) => Err(err),
     };
@@ -243,6 +270,16 @@ fn visitor(res: Result<JobStatus>, tracker: &JobTracker) {
                     eprintln!("job {cjid}: killed by signal {signum}");
                     ExitCode::FAILURE
                 }
+                JobTerminationStatus::OutOfMemory => {
+                    io::stdout().lock().flush().ok();
+                    eprintln!("job {cjid}: killed for exceeding memory limit");
+                    ExitCode::FAILURE
+                }
+                JobTerminationStatus::SyscallBlocked => {
+                    io::stdout().lock().flush().ok();
+                    eprintln!("job {cjid}: killed for making a blocked syscall");
+                    ExitCode::FAILURE
+                }
             }
         }
         Ok((cjid, Ok(JobOutcome::TimedOut(effects)))) => {
@@ -306,6 +343,16 @@ fn mimic_child_death(res: JobOutcomeResult) -> Result<ExitCode> {
                     let _ = linux::raise(Signal::KILL);
                     unreachable!()
                 }
+                JobTerminationStatus::OutOfMemory => {
+                    io::stdout().lock().flush()?;
+                    eprintln!("killed for exceeding memory limit");
+                    ExitCode::FAILURE
+                }
+                JobTerminationStatus::SyscallBlocked => {
+                    io::stdout().lock().flush()?;
+                    eprintln!("killed for making a blocked syscall");
+                    ExitCode::FAILURE
+                }
             }
         }
         Ok(JobOutcome::TimedOut(effects)) => {
@@ -742,13 +789,21 @@ fn main_with_logger(
     log: Logger,
 ) -> Result<ExitCode> {
     let fs = Fs::new();
-    let reader: Box<dyn Read> = match extra_options.file {
-        Some(path) => Box::new(fs.open_file(path)?),
-        None => Box::new(io::stdin().lock()),
+    let (reader, stdin): (Box<dyn Read>, _) = match extra_options.file {
+        Some(path) => (Box::new(fs.open_file(path)?), Some(io::stdin())),
+        None => (Box::new(io::stdin().lock()), None),
     };
     fs.create_dir_all(&config.cache_root)?;
     fs.create_dir_all(&config.state_root)?;
     fs.create_dir_all(&config.container_image_depot_root)?;
+
+    // Inherited stdin that is too big to inline gets saved in a directory of our own, which is
+    // removed when we return. By then, all of our jobs are done with it.
+    let stdin_root = (**config.cache_root).join(STDIN_DIR);
+    fs.create_dir_all(&stdin_root)?;
+    let stdin_dir = tempfile::Builder::new()
+        .prefix("run-")
+        .tempdir_in(&stdin_root)?;
     let client = Client::new(
         bg_proc,
         config.broker,
@@ -762,7 +817,11 @@ fn main_with_logger(
         config.accept_invalid_remote_container_tls_certs,
         log,
     )?;
-    let mut job_specs = spec::job_spec_iter_from_reader(reader);
+    let mut job_specs = spec::job_spec_iter_from_reader(
+        reader,
+        stdin,
+        Utf8PathBuf::try_from(stdin_dir.path().to_owned())?,
+    );
     if extra_options.one_or_tty.any() {
         if extra_options.one_or_tty.tty {
             // Unblock the signals for the local thread. We'll re-block them again once we've read
@@ -1,38 +1,108 @@
 use anyhow::{anyhow, Result};
 use maelstrom_base::{
-    GroupId, JobMountForTomlAndJson, JobNetwork, JobRootOverlay, NonEmpty, Timeout, UserId,
-    Utf8PathBuf,
+    validate_hostname, GroupId, JobClockOffsets, JobForwardForTomlAndJson, JobHost, JobLimits,
+    JobMountForTomlAndJson, JobNetwork, JobRlimitForTomlAndJson, JobRootOverlay,
+    JobSeccompForTomlAndJson, JobUserEntries, NonEmpty, Timeout, UserId, Utf8PathBuf,
 };
 use maelstrom_client::spec::{
     incompatible, ContainerSpec, EnvironmentSpec, Image, ImageSpec, ImageUse, IntoEnvironment,
-    JobSpec, LayerSpec, PossiblyImage,
+    JobSpec, JobStdinSpec, LayerMountSpec, LayerSpec, PossiblyImage, SidecarSpecForTomlAndJson,
 };
+use maelstrom_util::fs::Fs;
 use serde::de::Error as _;
 use serde::{de, Deserialize, Deserializer};
 use std::collections::BTreeMap;
-use std::io::Read;
+use std::io::{self, Read, Write as _};
 
-struct JobSpecIterator<InnerT> {
+/// Inherited stdin larger than this is written to a file, which the client uploads as an artifact,
+/// instead of being sent inline with each job spec.
+const STDIN_INLINE_LIMIT: u64 = 64 * 1024;
+
+struct JobSpecIterator<InnerT, StdinT> {
     inner: InnerT,
+    stdin: Option<StdinT>,
+    stdin_dir: Utf8PathBuf,
+    inherited_stdin: Option<JobStdinSpec>,
+}
+
+impl<InnerT, StdinT: Read> JobSpecIterator<InnerT, StdinT> {
+    /// Read all of our stdin the first time a job asks for it. Every job that asks for it gets the
+    /// same bytes. If there are too many bytes to inline, they are saved in a file in `stdin_dir`,
+    /// which must already exist.
+    fn inherited_stdin(&mut self) -> Result<JobStdinSpec> {
+        if self.inherited_stdin.is_none() {
+            let stdin = self.stdin.as_mut().ok_or_else(|| {
+                anyhow!("can't inherit stdin when job specifications are read from stdin")
+            })?;
+            let mut bytes = vec![];
+            stdin
+                .by_ref()
+                .take(STDIN_INLINE_LIMIT + 1)
+                .read_to_end(&mut bytes)?;
+            self.inherited_stdin = Some(if bytes.len() as u64 <= STDIN_INLINE_LIMIT {
+                JobStdinSpec::Inline(bytes.into_boxed_slice())
+            } else {
+                let fs = Fs::new();
+                let path = self.stdin_dir.join("stdin");
+                let mut file = fs.create_file(&path)?;
+                file.write_all(&bytes)?;
+                io::copy(stdin, &mut file)?;
+                JobStdinSpec::File(path)
+            });
+        }
+        Ok(self.inherited_stdin.clone().unwrap())
+    }
 }
 
-impl<InnerT> Iterator for JobSpecIterator<InnerT>
+impl<InnerT, StdinT> Iterator for JobSpecIterator<InnerT, StdinT>
 where
     InnerT: Iterator<Item = serde_json::Result<Job>>,
+    StdinT: Read,
 {
     type Item = Result<JobSpec>;
 
     fn next(&mut self) -> Option<Self::Item> {
         Some(match self.inner.next()? {
             Err(err) => Err(err.into()),
-            Ok(job) => job.into_job_spec(),
+            Ok(job) => {
+                let inherit_stdin = job.stdin == Some(StdinSpec::Inherit);
+                job.into_job_spec().and_then(|mut spec| {
+                    if inherit_stdin {
+                        spec.stdin = Some(self.inherited_stdin()?);
+                    }
+                    Ok(spec)
+                })
+            }
         })
     }
 }
 
-pub fn job_spec_iter_from_reader(reader: impl Read) -> impl Iterator<Item = Result<JobSpec>> {
+/// Return an iterator over the job specifications read from `reader`. Jobs that inherit stdin will
+/// get the contents of `stdin`. If `stdin` is `None`, it's an error for a job to inherit stdin. If
+/// `stdin` is too big to inline, it's saved in a file in `stdin_dir`. The caller owns `stdin_dir`,
+/// and has to keep it around until the client is done uploading the file.
+pub fn job_spec_iter_from_reader(
+    reader: impl Read,
+    stdin: Option<impl Read>,
+    stdin_dir: Utf8PathBuf,
+) -> impl Iterator<Item = Result<JobSpec>> {
     let inner = serde_json::Deserializer::from_reader(reader).into_iter::<Job>();
-    JobSpecIterator { inner }
+    JobSpecIterator {
+        inner,
+        stdin,
+        stdin_dir,
+        inherited_stdin: None,
+    }
+}
+
+/// Where a job's stdin should come from.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum StdinSpec {
+    /// Use this process's stdin.
+    Inherit,
+    /// Use the contents of the given file.
+    File(Utf8PathBuf),
 }
 
 #[derive(Debug, PartialEq)]
@@ -44,14 +114,28 @@ struct Job {
     layers: PossiblyImage<NonEmpty<LayerSpec>>,
     added_layers: Vec<LayerSpec>,
     mounts: Option<Vec<JobMountForTomlAndJson>>,
+    layer_mounts: Option<Vec<LayerMountSpec>>,
     network: Option<JobNetwork>,
     enable_writable_file_system: Option<bool>,
+    writable_file_system_size: Option<u64>,
+    writable_file_system_inodes: Option<u64>,
     working_directory: Option<PossiblyImage<Utf8PathBuf>>,
     user: Option<UserId>,
     group: Option<GroupId>,
     image: Option<String>,
     timeout: Option<u32>,
     priority: Option<i8>,
+    stdin: Option<StdinSpec>,
+    limits: Option<JobLimits>,
+    rlimits: Option<Vec<JobRlimitForTomlAndJson>>,
+    seccomp: Option<JobSeccompForTomlAndJson>,
+    init: Option<bool>,
+    forwards: Option<Vec<JobForwardForTomlAndJson>>,
+    sidecars: Option<Vec<SidecarSpecForTomlAndJson>>,
+    user_entries: Option<JobUserEntries>,
+    hostname: Option<String>,
+    extra_hosts: Option<Vec<JobHost>>,
+    clock_offsets: Option<JobClockOffsets>,
 }
 
 impl Job {
@@ -65,14 +149,28 @@ impl Job {
             environment: None,
             use_image_environment: false,
             mounts: None,
+            layer_mounts: None,
             network: None,
             enable_writable_file_system: None,
+            writable_file_system_size: None,
+            writable_file_system_inodes: None,
             working_directory: None,
             user: None,
             group: None,
             image: None,
             timeout: None,
             priority: None,
+            stdin: None,
+            limits: None,
+            rlimits: None,
+            seccomp: None,
+            init: None,
+            forwards: None,
+            sidecars: None,
+            user_entries: None,
+            hostname: None,
+            extra_hosts: None,
+            clock_offsets: None,
         }
     }
 
@@ -118,13 +216,17 @@ impl Job {
                 .collect(),
             network: self.network.unwrap_or_default(),
             root_overlay: if self.enable_writable_file_system.unwrap_or_default() {
-                JobRootOverlay::Tmp
+                JobRootOverlay::Tmp {
+                    size: self.writable_file_system_size,
+                    inodes: self.writable_file_system_inodes,
+                }
             } else {
                 JobRootOverlay::None
             },
             working_directory,
             user: self.user,
             group: self.group,
+            layer_mounts: self.layer_mounts.unwrap_or_default(),
         }
         .into();
         Ok(JobSpec {
@@ -135,6 +237,37 @@ impl Job {
             estimated_duration: None,
             allocate_tty: None,
             priority: self.priority.unwrap_or_default(),
+            stdin: match self.stdin {
+                Some(StdinSpec::File(path)) => Some(JobStdinSpec::File(path)),
+                Some(StdinSpec::Inherit) | None => None,
+            },
+            limits: self.limits.unwrap_or_default(),
+            rlimits: self
+                .rlimits
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            seccomp: self.seccomp.and_then(|seccomp| seccomp.0),
+            init: self.init.unwrap_or_default(),
+            forwards: self
+                .forwards
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            sidecars: self
+                .sidecars
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            user_entries: self.user_entries,
+            hostname: self.hostname,
+            extra_hosts: self.extra_hosts.unwrap_or_default(),
+            clock_offsets: self.clock_offsets,
+            capture_core_dump: false,
+            keep_writable_layer_on_failure: false,
         })
     }
 }
@@ -148,14 +281,28 @@ enum JobField {
     Layers,
     AddedLayers,
     Mounts,
+    LayerMounts,
     Network,
     EnableWritableFileSystem,
+    WritableFileSystemSize,
+    WritableFileSystemInodes,
     WorkingDirectory,
     User,
     Group,
     Image,
     Timeout,
     Priority,
+    Stdin,
+    Limits,
+    Rlimits,
+    Seccomp,
+    Init,
+    Forwards,
+    Sidecars,
+    UserEntries,
+    Hostname,
+    ExtraHosts,
+    ClockOffsets,
 }
 
 struct JobVisitor;
@@ -209,14 +356,28 @@ impl<'de> de::Visitor<'de> for JobVisitor {
         let mut layers = None;
         let mut added_layers = None;
         let mut mounts = None;
+        let mut layer_mounts = None;
         let mut network = None;
         let mut enable_writable_file_system = None;
+        let mut writable_file_system_size = None;
+        let mut writable_file_system_inodes = None;
         let mut working_directory = None;
         let mut user = None;
         let mut group = None;
         let mut image = None;
         let mut timeout = None;
         let mut priority = None;
+        let mut stdin = None;
+        let mut limits = None;
+        let mut rlimits = None;
+        let mut seccomp = None;
+        let mut init = None;
+        let mut forwards = None;
+        let mut sidecars = None;
+        let mut user_entries = None;
+        let mut hostname = None;
+        let mut extra_hosts = None;
+        let mut clock_offsets = None;
         while let Some(key) = map.next_key()? {
             match key {
                 JobField::Program => {
@@ -261,12 +422,21 @@ impl<'de> de::Visitor<'de> for JobVisitor {
                 JobField::Mounts => {
                     mounts = Some(map.next_value()?);
                 }
+                JobField::LayerMounts => {
+                    layer_mounts = Some(map.next_value()?);
+                }
                 JobField::Network => {
                     network = Some(map.next_value()?);
                 }
                 JobField::EnableWritableFileSystem => {
                     enable_writable_file_system = Some(map.next_value()?);
                 }
+                JobField::WritableFileSystemSize => {
+                    writable_file_system_size = Some(map.next_value()?);
+                }
+                JobField::WritableFileSystemInodes => {
+                    writable_file_system_inodes = Some(map.next_value()?);
+                }
                 JobField::WorkingDirectory => {
                     incompatible(
                         &working_directory,
@@ -289,6 +459,47 @@ impl<'de> de::Visitor<'de> for JobVisitor {
                 JobField::Priority => {
                     priority = Some(map.next_value()?);
                 }
+                JobField::Stdin => {
+                    stdin = Some(map.next_value()?);
+                }
+                JobField::Limits => {
+                    limits = Some(map.next_value()?);
+                }
+                JobField::Rlimits => {
+                    rlimits = Some(map.next_value()?);
+                }
+                JobField::Seccomp => {
+                    seccomp = Some(map.next_value()?);
+                }
+                JobField::Init => {
+                    init = Some(map.next_value()?);
+                }
+                JobField::Forwards => {
+                    forwards = Some(map.next_value()?);
+                }
+                JobField::Sidecars => {
+                    sidecars = Some(map.next_value()?);
+                }
+                JobField::UserEntries => {
+                    let value: JobUserEntries = map.next_value()?;
+                    value.validate().map_err(A::Error::custom)?;
+                    user_entries = Some(value);
+                }
+                JobField::Hostname => {
+                    let value: String = map.next_value()?;
+                    validate_hostname(&value).map_err(A::Error::custom)?;
+                    hostname = Some(value);
+                }
+                JobField::ExtraHosts => {
+                    let value: Vec<JobHost> = map.next_value()?;
+                    for host in &value {
+                        validate_hostname(&host.name).map_err(A::Error::custom)?;
+                    }
+                    extra_hosts = Some(value);
+                }
+                JobField::ClockOffsets => {
+                    clock_offsets = Some(map.next_value()?);
+                }
                 JobField::Image => {
                     let i = map.next_value::<Image>()?;
                     image = Some(i.name);
@@ -336,14 +547,28 @@ impl<'de> de::Visitor<'de> for JobVisitor {
             layers: layers.ok_or_else(|| de::Error::missing_field("layers"))?,
             added_layers: added_layers.unwrap_or_default(),
             mounts,
+            layer_mounts,
             network,
             enable_writable_file_system,
+            writable_file_system_size,
+            writable_file_system_inodes,
             working_directory,
             user,
             group,
             image,
             timeout,
             priority,
+            stdin,
+            limits,
+            rlimits,
+            seccomp,
+            init,
+            forwards,
+            sidecars,
+            user_entries,
+            hostname,
+            extra_hosts,
+            clock_offsets,
         })
     }
 }
@@ -360,8 +585,14 @@ impl<'de> de::Deserialize<'de> for Job {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use maelstrom_base::{enum_set, nonempty, JobDevice, JobDeviceForTomlAndJson, JobMount};
-    use maelstrom_test::{non_root_utf8_path_buf, string, string_vec, tar_layer, utf8_path_buf};
+    use maelstrom_base::{
+        enum_set, nonempty, JobCacheSharing, JobDevice, JobDeviceForTomlAndJson, JobForward,
+        JobMount, JobReadinessCheck, JobRlimit, JobRlimitResource, JobSeccomp, JobSeccompAction,
+        JobSeccompProfile,
+    };
+    use maelstrom_test::{
+        boxed_u8, non_root_utf8_path_buf, string, string_vec, tar_layer, utf8_path_buf,
+    };
     use maplit::btreemap;
 
     #[test]
@@ -383,6 +614,9 @@ mod tests {
                 mounts: Some(vec![
                     JobMountForTomlAndJson::Tmp {
                         mount_point: non_root_utf8_path_buf!("/tmp"),
+                        size: None,
+                        mode: None,
+                        inodes: None,
                     },
                     JobMountForTomlAndJson::Devices {
                         devices: enum_set! {JobDeviceForTomlAndJson::Null},
@@ -401,6 +635,9 @@ mod tests {
                 .mounts([
                     JobMount::Tmp {
                         mount_point: utf8_path_buf!("/tmp"),
+                        size: None,
+                        mode: None,
+                        inodes: None,
                     },
                     JobMount::Devices {
                         devices: enum_set! {JobDevice::Null},
@@ -470,7 +707,10 @@ mod tests {
             }
             .into_job_spec()
             .unwrap(),
-            JobSpec::new("program", vec![tar_layer!("1")]).root_overlay(JobRootOverlay::Tmp),
+            JobSpec::new("program", vec![tar_layer!("1")]).root_overlay(JobRootOverlay::Tmp {
+                size: None,
+                inodes: None,
+            }),
         );
     }
 
@@ -502,6 +742,422 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stdin_file() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "stdin": { "file": "input.txt" }
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")])
+                .stdin(Some(JobStdinSpec::File("input.txt".into())))
+        );
+    }
+
+    #[test]
+    fn limits() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "limits": { "memory": 1048576, "cpu_millis": 500, "pids": 10 }
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).limits(JobLimits {
+                memory: Some(1048576),
+                cpu_millis: Some(500),
+                pids: Some(10),
+            })
+        );
+    }
+
+    #[test]
+    fn rlimits() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "rlimits": [
+                        { "resource": "core", "soft": 0, "hard": "unlimited" },
+                        { "resource": "nofile", "soft": 1024, "hard": 4096 }
+                    ]
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).rlimits([
+                JobRlimit {
+                    resource: JobRlimitResource::Core,
+                    soft: 0,
+                    hard: JobRlimit::UNLIMITED,
+                },
+                JobRlimit {
+                    resource: JobRlimitResource::NoFile,
+                    soft: 1024,
+                    hard: 4096,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn seccomp() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "seccomp": { "deny": [ "ptrace" ], "action": { "errno": 38 } }
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).seccomp(Some(JobSeccomp {
+                profile: JobSeccompProfile::Deny {
+                    syscalls: vec!["ptrace".into()],
+                },
+                action: JobSeccompAction::Errno(38),
+            }))
+        );
+    }
+
+    #[test]
+    fn init() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "init": true
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).init(true)
+        );
+    }
+
+    #[test]
+    fn user_entries() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "user": 1000,
+                    "user_entries": { "user_name": "alice" }
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")])
+                .user(Some(1000))
+                .user_entries(Some(JobUserEntries {
+                    user_name: Some("alice".into()),
+                    ..Default::default()
+                }))
+        );
+    }
+
+    #[test]
+    fn user_entries_bad_name() {
+        assert_error(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "user_entries": { "user_name": "alice:x:0:0::/root:/bin/sh" }
+                }"#,
+            )
+            .unwrap_err(),
+            r#""alice:x:0:0::/root:/bin/sh" isn't a valid user or group name"#,
+        );
+    }
+
+    #[test]
+    fn hostname_and_extra_hosts() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "hostname": "builder",
+                    "extra_hosts": [
+                        { "name": "db", "address": "10.0.0.2" },
+                        { "name": "cache", "address": "::1" }
+                    ]
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")])
+                .hostname(Some("builder"))
+                .extra_hosts([
+                    JobHost {
+                        name: "db".into(),
+                        address: "10.0.0.2".parse().unwrap(),
+                    },
+                    JobHost {
+                        name: "cache".into(),
+                        address: "::1".parse().unwrap(),
+                    },
+                ]),
+        );
+    }
+
+    #[test]
+    fn extra_hosts_bad_address() {
+        assert_error(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "extra_hosts": [ { "name": "db", "address": "db.local" } ]
+                }"#,
+            )
+            .unwrap_err(),
+            "invalid IP address syntax",
+        );
+    }
+
+    #[test]
+    fn bad_hostname() {
+        assert_error(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "hostname": "build_box"
+                }"#,
+            )
+            .unwrap_err(),
+            r#""build_box" isn't a valid hostname"#,
+        );
+    }
+
+    #[test]
+    fn extra_hosts_bad_name() {
+        assert_error(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "extra_hosts": [ { "name": "db\n1.2.3.4 evil", "address": "10.0.0.2" } ]
+                }"#,
+            )
+            .unwrap_err(),
+            r#""db\n1.2.3.4 evil" isn't a valid hostname"#,
+        );
+    }
+
+    #[test]
+    fn clock_offsets() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "clock_offsets": { "boottime": -3600 }
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).clock_offsets(Some(
+                JobClockOffsets {
+                    monotonic: 0,
+                    boottime: -3600,
+                }
+            )),
+        );
+    }
+
+    #[test]
+    fn forwards() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "forwards": [
+                        { "tcp": 5432 },
+                        { "tcp": 8080, "host": "127.0.0.1:18080" },
+                        { "unix": "/run/app.sock", "host": "/tmp/app.sock" }
+                    ]
+                }"#
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).forwards([
+                JobForward::Tcp {
+                    port: 5432,
+                    host: "localhost:5432".into(),
+                },
+                JobForward::Tcp {
+                    port: 8080,
+                    host: "127.0.0.1:18080".into(),
+                },
+                JobForward::Unix {
+                    path: utf8_path_buf!("/run/app.sock"),
+                    host: utf8_path_buf!("/tmp/app.sock"),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn sidecars() {
+        let spec = parse_job(
+            r#"{
+                "program": "/bin/sh",
+                "layers": [ { "tar": "1" } ],
+                "network": "loopback",
+                "sidecars": [
+                    {
+                        "name": "db",
+                        "program": "/usr/bin/postgres",
+                        "arguments": [ "-D", "/data" ],
+                        "layers": [ { "tar": "2" } ],
+                        "ready": { "port": 5432, "timeout": 10 }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+        .into_job_spec()
+        .unwrap();
+        let [sidecar] = spec.sidecars.as_slice() else {
+            panic!("expected one sidecar, got {:?}", spec.sidecars);
+        };
+        assert_eq!(sidecar.name, "db");
+        assert_eq!(sidecar.program, "/usr/bin/postgres");
+        assert_eq!(sidecar.arguments, string_vec!["-D", "/data"]);
+        assert_eq!(
+            sidecar.ready,
+            Some(JobReadinessCheck {
+                port: 5432,
+                timeout: Timeout::new(10).unwrap(),
+            })
+        );
+        assert_eq!(
+            sidecar.container.as_inline().unwrap().layers,
+            vec![tar_layer!("2")]
+        );
+    }
+
+    #[test]
+    fn sidecars_without_name() {
+        assert_error(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "sidecars": [ { "program": "/usr/bin/postgres", "layers": [ { "tar": "2" } ] } ]
+                }"#,
+            )
+            .unwrap_err(),
+            "missing field `name`",
+        );
+    }
+
+    #[test]
+    fn forwards_unix_without_host() {
+        assert_error(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "forwards": [ { "unix": "/run/app.sock" } ]
+                }"#,
+            )
+            .unwrap_err(),
+            "field `host` must be provided for `unix` forwards",
+        );
+    }
+
+    #[test]
+    fn stdin_inherit() {
+        let mut iter = job_spec_iter_from_reader(
+            r#"
+                { "program": "/bin/cat", "layers": [ { "tar": "1" } ], "stdin": "inherit" }
+                { "program": "/bin/cat", "layers": [ { "tar": "1" } ], "stdin": "inherit" }
+                { "program": "/bin/cat", "layers": [ { "tar": "1" } ] }
+            "#
+            .as_bytes(),
+            Some(&b"input"[..]),
+            utf8_path_buf!("stdin"),
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                iter.next().unwrap().unwrap(),
+                JobSpec::new(string!("/bin/cat"), vec![tar_layer!("1")])
+                    .stdin(Some(JobStdinSpec::Inline(boxed_u8!(b"input"))))
+            );
+        }
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            JobSpec::new(string!("/bin/cat"), vec![tar_layer!("1")])
+        );
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn stdin_inherit_too_big_to_inline() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let stdin_dir = Utf8PathBuf::try_from(temp_dir.path().to_owned()).unwrap();
+        let input = vec![b'x'; STDIN_INLINE_LIMIT as usize + 1];
+        let mut iter = job_spec_iter_from_reader(
+            r#"
+                { "program": "/bin/cat", "layers": [ { "tar": "1" } ], "stdin": "inherit" }
+                { "program": "/bin/cat", "layers": [ { "tar": "1" } ], "stdin": "inherit" }
+            "#
+            .as_bytes(),
+            Some(&input[..]),
+            stdin_dir.clone(),
+        );
+        let path = stdin_dir.join("stdin");
+        for _ in 0..2 {
+            assert_eq!(
+                iter.next().unwrap().unwrap(),
+                JobSpec::new(string!("/bin/cat"), vec![tar_layer!("1")])
+                    .stdin(Some(JobStdinSpec::File(path.clone())))
+            );
+        }
+        assert!(iter.next().is_none());
+        assert_eq!(std::fs::read(&path).unwrap(), input);
+    }
+
+    #[test]
+    fn stdin_inherit_without_stdin() {
+        let mut iter = job_spec_iter_from_reader(
+            r#"{ "program": "/bin/cat", "layers": [ { "tar": "1" } ], "stdin": "inherit" }"#
+                .as_bytes(),
+            None::<&[u8]>,
+            utf8_path_buf!("stdin"),
+        );
+        assert_eq!(
+            iter.next().unwrap().unwrap_err().to_string(),
+            "can't inherit stdin when job specifications are read from stdin"
+        );
+    }
+
     #[test]
     fn missing_program() {
         assert_error(
@@ -924,7 +1580,12 @@ mod tests {
             .unwrap(),
             JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")])
                 .mounts([
-                    JobMount::Tmp { mount_point: utf8_path_buf!("/tmp") },
+                    JobMount::Tmp {
+                        mount_point: utf8_path_buf!("/tmp"),
+                        size: None,
+                        mode: None,
+                        inodes: None,
+                    },
                     JobMount::Bind {
                         mount_point: utf8_path_buf!("/bind"),
                         local_path: utf8_path_buf!("/a"),
@@ -944,6 +1605,86 @@ mod tests {
         )
     }
 
+    #[test]
+    fn tmp_mount_with_limits() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "mounts": [
+                        { "type": "tmp", "mount_point": "/tmp", "size": 1048576, "mode": 1023, "inodes": 100 }
+                    ]
+                }"#,
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).mounts([JobMount::Tmp {
+                mount_point: utf8_path_buf!("/tmp"),
+                size: Some(1048576),
+                mode: Some(0o1777),
+                inodes: Some(100),
+            }])
+        )
+    }
+
+    #[test]
+    fn cache_mounts() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "mounts": [
+                        { "type": "cache", "name": "pip", "mount_point": "/root/.cache/pip" },
+                        { "type": "cache", "name": "go", "mount_point": "/go", "sharing": "exclusive" }
+                    ]
+                }"#,
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")])
+                .mounts([
+                    JobMount::Cache {
+                        name: string!("pip"),
+                        mount_point: utf8_path_buf!("/root/.cache/pip"),
+                        sharing: JobCacheSharing::Shared,
+                    },
+                    JobMount::Cache {
+                        name: string!("go"),
+                        mount_point: utf8_path_buf!("/go"),
+                        sharing: JobCacheSharing::Exclusive,
+                    },
+                ])
+        )
+    }
+
+    #[test]
+    fn layer_mounts() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "layer_mounts": [
+                        { "mount_point": "/opt/toolchain", "layers": [ { "tar": "toolchain.tar" } ] }
+                    ]
+                }"#,
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).layer_mounts([
+                LayerMountSpec {
+                    mount_point: utf8_path_buf!("/opt/toolchain"),
+                    layers: vec![tar_layer!("toolchain.tar")],
+                }
+            ])
+        )
+    }
+
     #[test]
     fn foo() {
         assert_eq!(
@@ -974,8 +1715,53 @@ mod tests {
             .unwrap()
             .into_job_spec()
             .unwrap(),
-            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")])
-                .root_overlay(JobRootOverlay::Tmp),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).root_overlay(
+                JobRootOverlay::Tmp {
+                    size: None,
+                    inodes: None,
+                }
+            ),
+        )
+    }
+
+    #[test]
+    fn enable_writable_file_system_with_limits() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "enable_writable_file_system": true,
+                    "writable_file_system_size": 1048576,
+                    "writable_file_system_inodes": 100
+                }"#,
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]).root_overlay(
+                JobRootOverlay::Tmp {
+                    size: Some(1048576),
+                    inodes: Some(100),
+                }
+            ),
+        )
+    }
+
+    #[test]
+    fn writable_file_system_limits_without_writable_file_system() {
+        assert_eq!(
+            parse_job(
+                r#"{
+                    "program": "/bin/sh",
+                    "layers": [ { "tar": "1" } ],
+                    "writable_file_system_size": 1048576
+                }"#,
+            )
+            .unwrap()
+            .into_job_spec()
+            .unwrap(),
+            JobSpec::new(string!("/bin/sh"), vec![tar_layer!("1")]),
         )
     }
 
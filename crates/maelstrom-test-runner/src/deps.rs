@@ -1,6 +1,6 @@
 use crate::{metadata::TestMetadata, ui};
 use anyhow::Result;
-use maelstrom_base::Utf8PathBuf;
+use maelstrom_base::{Sha256Digest, Utf8PathBuf};
 use maelstrom_client::{
     spec::{JobSpec, LayerSpec},
     IntrospectResponse, JobStatus,
@@ -25,6 +25,8 @@ pub trait ClientTrait: Sync {
         spec: JobSpec,
         handler: impl FnMut(Result<JobStatus>) + Send + Sync + Clone + 'static,
     ) -> Result<()>;
+    fn download_artifact(&self, digest: &Sha256Digest, path: &Path) -> Result<()>;
+    fn download_and_unpack_artifact(&self, digest: &Sha256Digest, path: &Path) -> Result<()>;
 }
 
 impl ClientTrait for maelstrom_client::Client {
@@ -39,6 +41,14 @@ impl ClientTrait for maelstrom_client::Client {
     ) -> Result<()> {
         maelstrom_client::Client::add_job(self, spec, handler)
     }
+
+    fn download_artifact(&self, digest: &Sha256Digest, path: &Path) -> Result<()> {
+        maelstrom_client::Client::download_artifact(self, digest.clone(), path)
+    }
+
+    fn download_and_unpack_artifact(&self, digest: &Sha256Digest, path: &Path) -> Result<()> {
+        maelstrom_client::Client::download_and_unpack_artifact(self, digest.clone(), path)
+    }
 }
 
 pub trait TestArtifactKey:
@@ -23,7 +23,11 @@ use maelstrom_client::{
     ClientBgProcess, ProjectDir, StateDir,
 };
 use maelstrom_util::{
-    config::common::LogLevel, config::Config, fs::Fs, process::ExitCode, root::Root,
+    config::common::LogLevel,
+    config::Config,
+    fs::Fs,
+    process::ExitCode,
+    root::{Root, RootBuf},
 };
 use metadata::AllMetadata;
 use slog::Drain as _;
@@ -97,6 +101,12 @@ type BuildHandleM<MainAppDepsT> =
 type ArtifactStreamM<MainAppDepsT> =
     <<MainAppDepsT as MainAppDeps>::TestCollector as CollectTests>::ArtifactStream;
 
+/// The directory in the state directory that core files dumped by jobs are downloaded to.
+pub struct CoreDumpDir;
+
+/// The directory in the state directory that the writable layers of failed jobs are unpacked to.
+pub struct WritableLayerDir;
+
 /// A collection of dependencies that are used while enqueuing jobs.
 struct JobQueuingDeps<TestCollectorT: CollectTests> {
     filter: TestCollectorT::TestFilter,
@@ -107,6 +117,8 @@ struct JobQueuingDeps<TestCollectorT: CollectTests> {
     repeat: Repeat,
     stop_after: Option<StopAfter>,
     collector_options: TestCollectorT::Options,
+    core_dump_dir: RootBuf<CoreDumpDir>,
+    writable_layer_dir: RootBuf<WritableLayerDir>,
 }
 
 impl<TestCollectorT: CollectTests> JobQueuingDeps<TestCollectorT> {
@@ -120,6 +132,8 @@ impl<TestCollectorT: CollectTests> JobQueuingDeps<TestCollectorT> {
         repeat: Repeat,
         stop_after: Option<StopAfter>,
         collector_options: TestCollectorT::Options,
+        core_dump_dir: RootBuf<CoreDumpDir>,
+        writable_layer_dir: RootBuf<WritableLayerDir>,
     ) -> Result<Self> {
         Ok(Self {
             filter,
@@ -130,6 +144,8 @@ impl<TestCollectorT: CollectTests> JobQueuingDeps<TestCollectorT> {
             repeat,
             stop_after,
             collector_options,
+            core_dump_dir,
+            writable_layer_dir,
         })
     }
 }
@@ -148,7 +164,11 @@ impl<'a, TestCollectorT: CollectTests> JobQueuingState<'a, TestCollectorT> {
     fn new(deps: &'a JobQueuingDeps<TestCollectorT>, expected_job_count: u64) -> Self {
         Self {
             deps,
-            tracker: Arc::new(JobStatusTracker::new(deps.stop_after)),
+            tracker: Arc::new(JobStatusTracker::new(
+                deps.stop_after,
+                deps.core_dump_dir.to_path_buf(),
+                deps.writable_layer_dir.to_path_buf(),
+            )),
             jobs_queued: 0,
             expected_job_count,
             all_jobs_queued: false,
@@ -360,6 +380,7 @@ where
         };
 
         let (program, arguments) = self.artifact.build_command(case_name, case_metadata);
+        let init = test_metadata.init();
         let container = ContainerSpec {
             image: test_metadata.image,
             environment: test_metadata.environment,
@@ -367,13 +388,17 @@ where
             mounts: test_metadata.mounts,
             network: test_metadata.network,
             root_overlay: if test_metadata.enable_writable_file_system {
-                JobRootOverlay::Tmp
+                JobRootOverlay::Tmp {
+                    size: test_metadata.writable_file_system_size,
+                    inodes: test_metadata.writable_file_system_inodes,
+                }
             } else {
                 JobRootOverlay::None
             },
             working_directory: test_metadata.working_directory,
             user: test_metadata.user,
             group: test_metadata.group,
+            layer_mounts: test_metadata.layer_mounts,
         }
         .into();
         Ok(TestToEnqueue {
@@ -388,6 +413,19 @@ where
                 estimated_duration,
                 allocate_tty: None,
                 priority,
+                stdin: None,
+                limits: test_metadata.limits,
+                rlimits: test_metadata.rlimits,
+                seccomp: test_metadata.seccomp,
+                init,
+                forwards: test_metadata.forwards,
+                sidecars: test_metadata.sidecars,
+                user_entries: test_metadata.user_entries,
+                hostname: test_metadata.hostname,
+                extra_hosts: test_metadata.extra_hosts,
+                clock_offsets: test_metadata.clock_offsets,
+                capture_core_dump: test_metadata.capture_core_dump,
+                keep_writable_layer_on_failure: test_metadata.keep_writable_layer_on_failure,
             },
             visitor,
         }
@@ -642,6 +680,8 @@ impl<MainAppDepsT: MainAppDeps> MainAppCombinedDeps<MainAppDepsT> {
                 repeat,
                 stop_after,
                 collector_options,
+                state_dir.as_ref().join("core-dumps"),
+                state_dir.as_ref().join("writable-layers"),
             )?,
             test_db_store,
             log,
@@ -860,10 +900,57 @@ where
         Ok(())
     }
 
+    /// Downloads any core files dumped by jobs into the core dump directory.
+    fn download_core_dumps(&self) {
+        let core_dumps = self.queuing.state.tracker.take_core_dumps();
+        if core_dumps.is_empty() {
+            return;
+        }
+        if let Err(err) = Fs::new().create_dir_all(&self.deps.queuing_deps.core_dump_dir) {
+            slog::error!(self.deps.log, "error creating core dump directory"; "error" => %err);
+            return;
+        }
+        for (digest, path) in core_dumps {
+            if let Err(err) = self
+                .deps
+                .abstract_deps
+                .client()
+                .download_artifact(&digest, &path)
+            {
+                slog::error!(
+                    self.deps.log, "error downloading core dump";
+                    "path" => %path.display(),
+                    "error" => %err
+                );
+            }
+        }
+    }
+
+    /// Downloads and unpacks any writable layers kept from failed jobs into the writable layer
+    /// directory.
+    fn download_writable_layers(&self) {
+        for (digest, path) in self.queuing.state.tracker.take_writable_layers() {
+            if let Err(err) = self
+                .deps
+                .abstract_deps
+                .client()
+                .download_and_unpack_artifact(&digest, &path)
+            {
+                slog::error!(
+                    self.deps.log, "error downloading writable layer";
+                    "path" => %path.display(),
+                    "error" => %err
+                );
+            }
+        }
+    }
+
     /// Displays a summary, and obtains an `ExitCode`
     fn finish(self) -> Result<ExitCode> {
         let nre = self.queuing.state.not_run_estimate();
         let summary = self.queuing.state.tracker.ui_summary(nre);
+        self.download_core_dumps();
+        self.download_writable_layers();
         self.ui.finished(summary);
 
         self.deps.test_db_store.save(
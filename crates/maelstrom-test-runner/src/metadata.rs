@@ -3,9 +3,12 @@ mod directive;
 use crate::TestFilter;
 use anyhow::{anyhow, Context as _, Result};
 use directive::TestDirective;
-use maelstrom_base::{GroupId, JobMount, JobNetwork, Timeout, UserId, Utf8PathBuf};
+use maelstrom_base::{
+    GroupId, JobClockOffsets, JobForward, JobHost, JobLimits, JobMount, JobNetwork, JobRlimit,
+    JobSeccomp, JobUserEntries, Timeout, UserId, Utf8PathBuf,
+};
 use maelstrom_client::{
-    spec::{EnvironmentSpec, ImageSpec, LayerSpec, PossiblyImage},
+    spec::{EnvironmentSpec, ImageSpec, LayerMountSpec, LayerSpec, PossiblyImage, SidecarSpec},
     ProjectDir,
 };
 use maelstrom_util::{fs::Fs, root::Root, template::TemplateVars};
@@ -22,19 +25,44 @@ pub struct AllMetadata<TestFilterT> {
     directives: Vec<TestDirective<TestFilterT>>,
 }
 
+/// Return whether two forwards are for the same endpoint in the job. A later directive's forward
+/// replaces an earlier one for the same endpoint.
+fn same_forward_endpoint(lhs: &JobForward, rhs: &JobForward) -> bool {
+    match (lhs, rhs) {
+        (JobForward::Tcp { port: lhs, .. }, JobForward::Tcp { port: rhs, .. }) => lhs == rhs,
+        (JobForward::Unix { path: lhs, .. }, JobForward::Unix { path: rhs, .. }) => lhs == rhs,
+        _ => false,
+    }
+}
+
 #[derive(Debug, Default, Eq, PartialEq)]
 pub struct TestMetadata {
     include_shared_libraries: Option<bool>,
     pub image: Option<ImageSpec>,
     pub network: JobNetwork,
     pub enable_writable_file_system: bool,
+    pub writable_file_system_size: Option<u64>,
+    pub writable_file_system_inodes: Option<u64>,
     pub working_directory: Option<Utf8PathBuf>,
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
+    pub user_entries: Option<JobUserEntries>,
+    pub hostname: Option<String>,
+    pub extra_hosts: Vec<JobHost>,
+    pub clock_offsets: Option<JobClockOffsets>,
+    pub capture_core_dump: bool,
+    pub keep_writable_layer_on_failure: bool,
     pub timeout: Option<Timeout>,
+    pub limits: JobLimits,
+    pub rlimits: Vec<JobRlimit>,
+    pub seccomp: Option<JobSeccomp>,
+    init: Option<bool>,
+    pub forwards: Vec<JobForward>,
+    pub sidecars: Vec<SidecarSpec>,
     pub layers: Vec<LayerSpec>,
     pub environment: Vec<EnvironmentSpec>,
     pub mounts: Vec<JobMount>,
+    pub layer_mounts: Vec<LayerMountSpec>,
     pub ignore: bool,
 }
 
@@ -53,6 +81,12 @@ impl TestMetadata {
         }
     }
 
+    /// Return whether to run the test under an init process. This is the default, since test
+    /// binaries generally don't expect to be PID 1.
+    pub fn init(&self) -> bool {
+        self.init.unwrap_or(true)
+    }
+
     fn try_fold<TestFilterT>(
         mut self,
         &TestDirective {
@@ -61,13 +95,31 @@ impl TestMetadata {
             include_shared_libraries,
             network,
             enable_writable_file_system,
+            writable_file_system_size,
+            writable_file_system_inodes,
             user,
             group,
+            ref user_entries,
+            ref hostname,
+            ref extra_hosts,
+            clock_offsets,
+            capture_core_dump,
+            keep_writable_layer_on_failure,
             timeout,
+            memory_limit,
+            cpu_limit,
+            pids_limit,
+            ref rlimits,
+            ref seccomp,
+            init,
+            ref forwards,
+            ref sidecars,
             ref layers,
             ref added_layers,
             ref mounts,
             ref added_mounts,
+            ref layer_mounts,
+            ref added_layer_mounts,
             ref environment,
             ref added_environment,
             ref working_directory,
@@ -85,9 +137,64 @@ impl TestMetadata {
         self.network = network.unwrap_or(self.network);
         self.enable_writable_file_system =
             enable_writable_file_system.unwrap_or(self.enable_writable_file_system);
+        self.writable_file_system_size =
+            writable_file_system_size.or(self.writable_file_system_size);
+        self.writable_file_system_inodes =
+            writable_file_system_inodes.or(self.writable_file_system_inodes);
         self.user = user.or(self.user);
         self.group = group.or(self.group);
+        self.user_entries = user_entries.clone().or(self.user_entries);
+        self.hostname = hostname.clone().or(self.hostname);
+        self.clock_offsets = clock_offsets.or(self.clock_offsets);
+        self.capture_core_dump = capture_core_dump.unwrap_or(self.capture_core_dump);
+        self.keep_writable_layer_on_failure =
+            keep_writable_layer_on_failure.unwrap_or(self.keep_writable_layer_on_failure);
+        for host in extra_hosts.iter().cloned() {
+            match self
+                .extra_hosts
+                .iter_mut()
+                .find(|existing| existing.name == host.name)
+            {
+                Some(existing) => *existing = host,
+                None => self.extra_hosts.push(host),
+            }
+        }
         self.timeout = timeout.unwrap_or(self.timeout);
+        self.limits.memory = memory_limit.unwrap_or(self.limits.memory);
+        self.limits.cpu_millis = cpu_limit.unwrap_or(self.limits.cpu_millis);
+        self.limits.pids = pids_limit.unwrap_or(self.limits.pids);
+        for rlimit in rlimits.iter().copied().map(JobRlimit::from) {
+            match self
+                .rlimits
+                .iter_mut()
+                .find(|existing| existing.resource == rlimit.resource)
+            {
+                Some(existing) => *existing = rlimit,
+                None => self.rlimits.push(rlimit),
+            }
+        }
+        self.seccomp = seccomp.clone().unwrap_or(self.seccomp);
+        self.init = init.or(self.init);
+        for forward in forwards.iter().cloned().map(JobForward::from) {
+            match self
+                .forwards
+                .iter_mut()
+                .find(|existing| same_forward_endpoint(existing, &forward))
+            {
+                Some(existing) => *existing = forward,
+                None => self.forwards.push(forward),
+            }
+        }
+        for sidecar in sidecars.iter().cloned().map(SidecarSpec::from) {
+            match self
+                .sidecars
+                .iter_mut()
+                .find(|existing| existing.name == sidecar.name)
+            {
+                Some(existing) => *existing = sidecar,
+                None => self.sidecars.push(sidecar),
+            }
+        }
         self.ignore = ignore.unwrap_or(self.ignore);
 
         match layers {
@@ -109,6 +216,11 @@ impl TestMetadata {
         self.mounts
             .extend(added_mounts.iter().cloned().map(Into::into));
 
+        if let Some(layer_mounts) = layer_mounts {
+            self.layer_mounts = layer_mounts.clone();
+        }
+        self.layer_mounts.extend(added_layer_mounts.iter().cloned());
+
         match environment {
             Some(PossiblyImage::Explicit(environment)) => {
                 self.environment.push(EnvironmentSpec {
@@ -174,6 +286,21 @@ where
             for added_layer in &mut directive.added_layers {
                 added_layer.replace_template_vars(vars)?;
             }
+            for layer_mount in directive
+                .layer_mounts
+                .iter_mut()
+                .flatten()
+                .chain(&mut directive.added_layer_mounts)
+            {
+                layer_mount.replace_template_vars(vars)?;
+            }
+            for sidecar in &mut directive.sidecars {
+                if let Some(container) = sidecar.0.container.as_inline_mut() {
+                    for layer in &mut container.layers {
+                        layer.replace_template_vars(vars)?;
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -237,8 +364,10 @@ mod tests {
     use super::*;
     use crate::{NoCaseMetadata, SimpleFilter};
     use anyhow::Error;
-    use maelstrom_base::{enum_set, JobDevice};
-    use maelstrom_test::{tar_layer, utf8_path_buf};
+    use maelstrom_base::{
+        enum_set, JobDevice, JobRlimitResource, JobSeccompAction, JobSeccompProfile,
+    };
+    use maelstrom_test::{string, tar_layer, utf8_path_buf};
     use maelstrom_util::root::RootBuf;
     use maplit::btreemap;
     use slog::Drain as _;
@@ -526,6 +655,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn writable_file_system_limits() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            enable_writable_file_system = true
+            writable_file_system_size = 1048576
+            writable_file_system_inodes = 100
+
+            [[directives]]
+            filter = "package = \"package1\""
+            writable_file_system_size = 2097152
+            "#,
+        )
+        .unwrap();
+        let metadata = all
+            .get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata),
+            )
+            .unwrap();
+        assert_eq!(metadata.writable_file_system_size, Some(2097152));
+        assert_eq!(metadata.writable_file_system_inodes, Some(100));
+        let metadata = all
+            .get_metadata_for_test(
+                &"package2".into(),
+                &"package2".into(),
+                ("test1", &NoCaseMetadata),
+            )
+            .unwrap();
+        assert_eq!(metadata.writable_file_system_size, Some(1048576));
+        assert_eq!(metadata.writable_file_system_inodes, Some(100));
+    }
+
     #[test]
     fn working_directory() {
         let all = AllMetadata::<SimpleFilter>::from_str(
@@ -642,6 +806,169 @@ mod tests {
         );
     }
 
+    #[test]
+    fn user_entries() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            user_entries = {}
+
+            [[directives]]
+            filter = "name = \"test1\""
+            user = 1000
+            user_entries = { user_name = "alice" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .user_entries,
+            Some(JobUserEntries {
+                user_name: Some("alice".into()),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .user_entries,
+            Some(JobUserEntries::default()),
+        );
+    }
+
+    #[test]
+    fn hostname_and_extra_hosts() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            hostname = "builder"
+            extra_hosts = [
+                { name = "db", address = "10.0.0.2" },
+                { name = "cache", address = "10.0.0.3" },
+            ]
+
+            [[directives]]
+            filter = "name = \"test1\""
+            hostname = "tester"
+            extra_hosts = [{ name = "db", address = "10.0.0.4" }]
+            "#,
+        )
+        .unwrap();
+        let test1 = all
+            .get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata),
+            )
+            .unwrap();
+        assert_eq!(test1.hostname.as_deref(), Some("tester"));
+        assert_eq!(
+            test1.extra_hosts,
+            vec![
+                JobHost {
+                    name: "db".into(),
+                    address: "10.0.0.4".parse().unwrap(),
+                },
+                JobHost {
+                    name: "cache".into(),
+                    address: "10.0.0.3".parse().unwrap(),
+                },
+            ],
+        );
+        let test2 = all
+            .get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata),
+            )
+            .unwrap();
+        assert_eq!(test2.hostname.as_deref(), Some("builder"));
+        assert_eq!(test2.extra_hosts.len(), 2);
+    }
+
+    #[test]
+    fn clock_offsets() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            clock_offsets = { monotonic = 100 }
+
+            [[directives]]
+            filter = "name = \"test1\""
+            clock_offsets = { boottime = 200 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .clock_offsets,
+            Some(JobClockOffsets {
+                monotonic: 0,
+                boottime: 200,
+            }),
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .clock_offsets,
+            Some(JobClockOffsets {
+                monotonic: 100,
+                boottime: 0,
+            }),
+        );
+    }
+
+    #[test]
+    fn capture_core_dump() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            capture_core_dump = true
+
+            [[directives]]
+            filter = "name = \"test1\""
+            capture_core_dump = false
+            "#,
+        )
+        .unwrap();
+        assert!(
+            !all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .capture_core_dump
+        );
+        assert!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .capture_core_dump
+        );
+    }
+
     #[test]
     fn group() {
         let all = AllMetadata::<SimpleFilter>::from_str(
@@ -734,6 +1061,411 @@ mod tests {
         );
     }
 
+    #[test]
+    fn limits() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            filter = "package = \"package1\""
+            memory_limit = "1 GiB"
+            cpu_limit = 0.5
+
+            [[directives]]
+            filter = "and = [{ package = \"package1\" }, { name = \"test1\" }]"
+            memory_limit = 0
+            pids_limit = 10
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .limits,
+            JobLimits {
+                memory: None,
+                cpu_millis: Some(500),
+                pids: Some(10),
+            },
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .limits,
+            JobLimits {
+                memory: Some(1024 * 1024 * 1024),
+                cpu_millis: Some(500),
+                pids: None,
+            },
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package2".into(),
+                &"package2".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .limits,
+            JobLimits::default(),
+        );
+    }
+
+    #[test]
+    fn rlimits() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            rlimits = [
+                { resource = "core", soft = 0, hard = 0 },
+                { resource = "nofile", soft = 1024, hard = 4096 },
+            ]
+
+            [[directives]]
+            filter = "name = \"test1\""
+            rlimits = [
+                { resource = "nofile", soft = 4096, hard = 4096 },
+                { resource = "stack", soft = "unlimited", hard = "unlimited" },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .rlimits,
+            vec![
+                JobRlimit {
+                    resource: JobRlimitResource::Core,
+                    soft: 0,
+                    hard: 0,
+                },
+                JobRlimit {
+                    resource: JobRlimitResource::NoFile,
+                    soft: 4096,
+                    hard: 4096,
+                },
+                JobRlimit {
+                    resource: JobRlimitResource::Stack,
+                    soft: JobRlimit::UNLIMITED,
+                    hard: JobRlimit::UNLIMITED,
+                },
+            ],
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .rlimits,
+            vec![
+                JobRlimit {
+                    resource: JobRlimitResource::Core,
+                    soft: 0,
+                    hard: 0,
+                },
+                JobRlimit {
+                    resource: JobRlimitResource::NoFile,
+                    soft: 1024,
+                    hard: 4096,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn forwards() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            forwards = [
+                { tcp = 5432 },
+                { unix = "/run/app.sock", host = "/tmp/app.sock" },
+            ]
+
+            [[directives]]
+            filter = "name = \"test1\""
+            forwards = [
+                { tcp = 5432, host = "db.example.com:5432" },
+                { tcp = 6379 },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .forwards,
+            vec![
+                JobForward::Tcp {
+                    port: 5432,
+                    host: "db.example.com:5432".into(),
+                },
+                JobForward::Unix {
+                    path: utf8_path_buf!("/run/app.sock"),
+                    host: utf8_path_buf!("/tmp/app.sock"),
+                },
+                JobForward::Tcp {
+                    port: 6379,
+                    host: "localhost:6379".into(),
+                },
+            ],
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .forwards,
+            vec![
+                JobForward::Tcp {
+                    port: 5432,
+                    host: "localhost:5432".into(),
+                },
+                JobForward::Unix {
+                    path: utf8_path_buf!("/run/app.sock"),
+                    host: utf8_path_buf!("/tmp/app.sock"),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn sidecars() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            sidecars = [
+                { name = "db", program = "/usr/bin/postgres", layers = [{ tar = "pg.tar" }] },
+                { name = "cache", program = "/usr/bin/redis-server", layers = [{ tar = "redis.tar" }] },
+            ]
+
+            [[directives]]
+            filter = "name = \"test1\""
+            sidecars = [
+                { name = "db", program = "/usr/bin/mysqld", layers = [{ tar = "mysql.tar" }] },
+                { name = "queue", program = "/usr/bin/rabbitmq-server", layers = [{ tar = "mq.tar" }] },
+            ]
+            "#,
+        )
+        .unwrap();
+        let sidecars = |test| {
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                (test, &NoCaseMetadata),
+            )
+            .unwrap()
+            .sidecars
+            .into_iter()
+            .map(|sidecar| (sidecar.name, sidecar.program.into_string()))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sidecars("test1"),
+            vec![
+                (string!("db"), string!("/usr/bin/mysqld")),
+                (string!("cache"), string!("/usr/bin/redis-server")),
+                (string!("queue"), string!("/usr/bin/rabbitmq-server")),
+            ],
+        );
+        assert_eq!(
+            sidecars("test2"),
+            vec![
+                (string!("db"), string!("/usr/bin/postgres")),
+                (string!("cache"), string!("/usr/bin/redis-server")),
+            ],
+        );
+    }
+
+    #[test]
+    fn sidecar_layers_replace_template_vars() {
+        let mut all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            sidecars = [
+                { name = "db", program = "/usr/bin/postgres", layers = [{ tar = "<build-dir>/pg.tar" }] },
+            ]
+            "#,
+        )
+        .unwrap();
+        all.replace_template_vars(
+            &TemplateVars::new()
+                .with_var("build-dir", "target/debug")
+                .unwrap(),
+        )
+        .unwrap();
+        let metadata = all
+            .get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata),
+            )
+            .unwrap();
+        assert_eq!(
+            metadata.sidecars[0].container.as_inline().unwrap().layers,
+            vec![tar_layer!("target/debug/pg.tar")],
+        );
+    }
+
+    #[test]
+    fn layer_mounts() {
+        let mut all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            layer_mounts = [
+                { mount_point = "/opt/toolchain", layers = [{ tar = "<build-dir>/toolchain.tar" }] },
+            ]
+
+            [[directives]]
+            filter = "package = \"package1\""
+            added_layer_mounts = [
+                { mount_point = "/data", layers = [{ tar = "data.tar" }] },
+            ]
+
+            [[directives]]
+            filter = "and = [{ package = \"package1\" }, { name = \"test2\" }]"
+            layer_mounts = []
+            "#,
+        )
+        .unwrap();
+        all.replace_template_vars(
+            &TemplateVars::new()
+                .with_var("build-dir", "target/debug")
+                .unwrap(),
+        )
+        .unwrap();
+        let layer_mounts = |package: &str, test| {
+            all.get_metadata_for_test(&package.into(), &package.into(), (test, &NoCaseMetadata))
+                .unwrap()
+                .layer_mounts
+        };
+        assert_eq!(
+            layer_mounts("package1", "test1"),
+            vec![
+                LayerMountSpec {
+                    mount_point: utf8_path_buf!("/opt/toolchain"),
+                    layers: vec![tar_layer!("target/debug/toolchain.tar")],
+                },
+                LayerMountSpec {
+                    mount_point: utf8_path_buf!("/data"),
+                    layers: vec![tar_layer!("data.tar")],
+                },
+            ],
+        );
+        assert_eq!(layer_mounts("package1", "test2"), vec![]);
+        assert_eq!(
+            layer_mounts("package2", "test1"),
+            vec![LayerMountSpec {
+                mount_point: utf8_path_buf!("/opt/toolchain"),
+                layers: vec![tar_layer!("target/debug/toolchain.tar")],
+            }],
+        );
+    }
+
+    #[test]
+    fn seccomp() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            seccomp = { profile = "default" }
+
+            [[directives]]
+            filter = "name = \"test1\""
+            seccomp = { profile = "none" }
+
+            [[directives]]
+            filter = "name = \"test2\""
+            seccomp = { allow = ["read"], action = "kill" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .seccomp,
+            None,
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .seccomp,
+            Some(JobSeccomp {
+                profile: JobSeccompProfile::Allow {
+                    syscalls: vec!["read".into()],
+                },
+                action: JobSeccompAction::Kill,
+            }),
+        );
+        assert_eq!(
+            all.get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test3", &NoCaseMetadata)
+            )
+            .unwrap()
+            .seccomp,
+            Some(JobSeccomp {
+                profile: JobSeccompProfile::Default,
+                action: JobSeccompAction::Errno(1),
+            }),
+        );
+    }
+
+    #[test]
+    fn init() {
+        let all = AllMetadata::<SimpleFilter>::from_str(
+            r#"
+            [[directives]]
+            filter = "name = \"test1\""
+            init = false
+            "#,
+        )
+        .unwrap();
+        assert!(!all
+            .get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test1", &NoCaseMetadata)
+            )
+            .unwrap()
+            .init());
+        assert!(all
+            .get_metadata_for_test(
+                &"package1".into(),
+                &"package1".into(),
+                ("test2", &NoCaseMetadata)
+            )
+            .unwrap()
+            .init());
+    }
+
     #[test]
     fn layers() {
         let all = AllMetadata::<SimpleFilter>::from_str(
@@ -1230,6 +1962,9 @@ mod tests {
             vec![
                 JobMount::Tmp {
                     mount_point: utf8_path_buf!("/tmp"),
+                    size: None,
+                    mode: None,
+                    inodes: None,
                 },
                 JobMount::Sys {
                     mount_point: utf8_path_buf!("/sys"),
@@ -1321,6 +2056,9 @@ mod tests {
                 },
                 JobMount::Tmp {
                     mount_point: utf8_path_buf!("/tmp"),
+                    size: None,
+                    mode: None,
+                    inodes: None,
                 },
             ],
         );
@@ -1341,6 +2079,9 @@ mod tests {
                 },
                 JobMount::Tmp {
                     mount_point: utf8_path_buf!("/tmp"),
+                    size: None,
+                    mode: None,
+                    inodes: None,
                 },
                 JobMount::Bind {
                     mount_point: utf8_path_buf!("/foo"),
@@ -1358,7 +2099,10 @@ mod tests {
             .unwrap()
             .mounts,
             vec![JobMount::Tmp {
-                mount_point: utf8_path_buf!("/tmp")
+                mount_point: utf8_path_buf!("/tmp"),
+                size: None,
+                mode: None,
+                inodes: None,
             }],
         );
         assert_eq!(
@@ -1370,7 +2114,10 @@ mod tests {
             .unwrap()
             .mounts,
             vec![JobMount::Tmp {
-                mount_point: utf8_path_buf!("/tmp")
+                mount_point: utf8_path_buf!("/tmp"),
+                size: None,
+                mode: None,
+                inodes: None,
             }],
         );
     }
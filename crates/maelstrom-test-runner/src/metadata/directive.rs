@@ -1,6 +1,14 @@
 use anyhow::Result;
-use maelstrom_base::{GroupId, JobMountForTomlAndJson, JobNetwork, Timeout, UserId, Utf8PathBuf};
-use maelstrom_client::spec::{incompatible, Image, ImageUse, LayerSpec, PossiblyImage};
+use bytesize::ByteSize;
+use maelstrom_base::{
+    validate_hostname, GroupId, JobClockOffsets, JobForwardForTomlAndJson, JobHost,
+    JobMountForTomlAndJson, JobNetwork, JobRlimitForTomlAndJson, JobSeccomp,
+    JobSeccompForTomlAndJson, JobUserEntries, Timeout, UserId, Utf8PathBuf,
+};
+use maelstrom_client::spec::{
+    incompatible, Image, ImageUse, LayerMountSpec, LayerSpec, PossiblyImage,
+    SidecarSpecForTomlAndJson,
+};
 use serde::{de, Deserialize, Deserializer};
 use std::{
     collections::BTreeMap,
@@ -17,13 +25,31 @@ pub struct TestDirective<TestFilterT> {
     pub include_shared_libraries: Option<bool>,
     pub network: Option<JobNetwork>,
     pub enable_writable_file_system: Option<bool>,
+    pub writable_file_system_size: Option<u64>,
+    pub writable_file_system_inodes: Option<u64>,
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
+    pub user_entries: Option<JobUserEntries>,
+    pub hostname: Option<String>,
+    pub extra_hosts: Vec<JobHost>,
+    pub clock_offsets: Option<JobClockOffsets>,
+    pub capture_core_dump: Option<bool>,
+    pub keep_writable_layer_on_failure: Option<bool>,
     pub timeout: Option<Option<Timeout>>,
+    pub memory_limit: Option<Option<u64>>,
+    pub cpu_limit: Option<Option<u32>>,
+    pub pids_limit: Option<Option<u32>>,
+    pub rlimits: Vec<JobRlimitForTomlAndJson>,
+    pub seccomp: Option<Option<JobSeccomp>>,
+    pub init: Option<bool>,
+    pub forwards: Vec<JobForwardForTomlAndJson>,
+    pub sidecars: Vec<SidecarSpecForTomlAndJson>,
     pub layers: Option<PossiblyImage<Vec<LayerSpec>>>,
     pub added_layers: Vec<LayerSpec>,
     pub mounts: Option<Vec<JobMountForTomlAndJson>>,
     pub added_mounts: Vec<JobMountForTomlAndJson>,
+    pub layer_mounts: Option<Vec<LayerMountSpec>>,
+    pub added_layer_mounts: Vec<LayerMountSpec>,
     pub environment: Option<PossiblyImage<BTreeMap<String, String>>>,
     pub added_environment: BTreeMap<String, String>,
     pub working_directory: Option<PossiblyImage<Utf8PathBuf>>,
@@ -39,13 +65,31 @@ impl<TestFilterT> Default for TestDirective<TestFilterT> {
             include_shared_libraries: None,
             network: None,
             enable_writable_file_system: None,
+            writable_file_system_size: None,
+            writable_file_system_inodes: None,
             user: None,
             group: None,
+            user_entries: None,
+            hostname: None,
+            extra_hosts: Default::default(),
+            clock_offsets: None,
+            capture_core_dump: None,
+            keep_writable_layer_on_failure: None,
             timeout: None,
+            memory_limit: None,
+            cpu_limit: None,
+            pids_limit: None,
+            rlimits: Default::default(),
+            seccomp: None,
+            init: None,
+            forwards: Default::default(),
+            sidecars: Default::default(),
             layers: None,
             added_layers: Default::default(),
             mounts: None,
             added_mounts: Default::default(),
+            layer_mounts: None,
+            added_layer_mounts: Default::default(),
             environment: None,
             added_environment: Default::default(),
             working_directory: None,
@@ -61,11 +105,29 @@ enum DirectiveField {
     IncludeSharedLibraries,
     Network,
     EnableWritableFileSystem,
+    WritableFileSystemSize,
+    WritableFileSystemInodes,
     User,
     Group,
+    UserEntries,
+    Hostname,
+    ExtraHosts,
+    ClockOffsets,
+    CaptureCoreDump,
+    KeepWritableLayerOnFailure,
     Timeout,
+    MemoryLimit,
+    CpuLimit,
+    PidsLimit,
+    Rlimits,
+    Seccomp,
+    Init,
+    Forwards,
+    Sidecars,
     Mounts,
     AddedMounts,
+    LayerMounts,
+    AddedLayerMounts,
     Image,
     WorkingDirectory,
     Layers,
@@ -75,6 +137,20 @@ enum DirectiveField {
     Ignore,
 }
 
+/// A memory limit can be given either as a number of bytes or as a string like "512 MiB".
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct MemoryLimit(#[serde(with = "bytesize_serde")] ByteSize);
+
+/// Convert a CPU limit given as a (possibly fractional) number of CPUs into thousandths of a CPU.
+fn cpu_limit_to_millis(cpus: f64) -> Result<u32, String> {
+    let millis = (cpus * 1000.0).round();
+    if !(0.0..=f64::from(u32::MAX)).contains(&millis) {
+        return Err(format!("invalid CPU limit {cpus}"));
+    }
+    Ok(millis as u32)
+}
+
 struct DirectiveVisitor<TestFilterT>(PhantomData<TestFilterT>);
 
 impl<'de, TestFilterT: FromStr> de::Visitor<'de> for DirectiveVisitor<TestFilterT>
@@ -95,11 +171,29 @@ where
         let mut include_shared_libraries = None;
         let mut network = None;
         let mut enable_writable_file_system = None;
+        let mut writable_file_system_size = None;
+        let mut writable_file_system_inodes = None;
         let mut user = None;
         let mut group = None;
+        let mut user_entries = None;
+        let mut hostname = None;
+        let mut extra_hosts = None;
+        let mut clock_offsets = None;
+        let mut capture_core_dump = None;
+        let mut keep_writable_layer_on_failure = None;
         let mut timeout = None;
+        let mut memory_limit = None;
+        let mut cpu_limit = None;
+        let mut pids_limit = None;
+        let mut rlimits = None;
+        let mut seccomp = None;
+        let mut init = None;
+        let mut forwards = None;
+        let mut sidecars = None;
         let mut mounts = None;
         let mut added_mounts = None;
+        let mut layer_mounts = None;
+        let mut added_layer_mounts = None;
         let mut image = None;
         let mut working_directory = None;
         let mut layers = None;
@@ -125,6 +219,12 @@ where
                 DirectiveField::EnableWritableFileSystem => {
                     enable_writable_file_system = Some(map.next_value()?);
                 }
+                DirectiveField::WritableFileSystemSize => {
+                    writable_file_system_size = Some(map.next_value()?);
+                }
+                DirectiveField::WritableFileSystemInodes => {
+                    writable_file_system_inodes = Some(map.next_value()?);
+                }
                 DirectiveField::User => {
                     user = Some(map.next_value()?);
                 }
@@ -134,6 +234,61 @@ where
                 DirectiveField::Timeout => {
                     timeout = Some(Timeout::new(map.next_value()?));
                 }
+                DirectiveField::MemoryLimit => {
+                    let MemoryLimit(limit) = map.next_value()?;
+                    memory_limit = Some(Some(limit.as_u64()).filter(|limit| *limit != 0));
+                }
+                DirectiveField::CpuLimit => {
+                    let millis =
+                        cpu_limit_to_millis(map.next_value()?).map_err(de::Error::custom)?;
+                    cpu_limit = Some(Some(millis).filter(|millis| *millis != 0));
+                }
+                DirectiveField::PidsLimit => {
+                    let limit: u32 = map.next_value()?;
+                    pids_limit = Some(Some(limit).filter(|limit| *limit != 0));
+                }
+                DirectiveField::Rlimits => {
+                    rlimits = Some(map.next_value()?);
+                }
+                DirectiveField::Seccomp => {
+                    let JobSeccompForTomlAndJson(value) = map.next_value()?;
+                    seccomp = Some(value);
+                }
+                DirectiveField::UserEntries => {
+                    let value: JobUserEntries = map.next_value()?;
+                    value.validate().map_err(de::Error::custom)?;
+                    user_entries = Some(value);
+                }
+                DirectiveField::Hostname => {
+                    let value: String = map.next_value()?;
+                    validate_hostname(&value).map_err(de::Error::custom)?;
+                    hostname = Some(value);
+                }
+                DirectiveField::ExtraHosts => {
+                    let value: Vec<JobHost> = map.next_value()?;
+                    for host in &value {
+                        validate_hostname(&host.name).map_err(de::Error::custom)?;
+                    }
+                    extra_hosts = Some(value);
+                }
+                DirectiveField::ClockOffsets => {
+                    clock_offsets = Some(map.next_value()?);
+                }
+                DirectiveField::CaptureCoreDump => {
+                    capture_core_dump = Some(map.next_value()?);
+                }
+                DirectiveField::KeepWritableLayerOnFailure => {
+                    keep_writable_layer_on_failure = Some(map.next_value()?);
+                }
+                DirectiveField::Init => {
+                    init = Some(map.next_value()?);
+                }
+                DirectiveField::Forwards => {
+                    forwards = Some(map.next_value()?);
+                }
+                DirectiveField::Sidecars => {
+                    sidecars = Some(map.next_value()?);
+                }
                 DirectiveField::Mounts => {
                     incompatible(
                         &added_mounts,
@@ -144,6 +299,16 @@ where
                 DirectiveField::AddedMounts => {
                     added_mounts = Some(map.next_value()?);
                 }
+                DirectiveField::LayerMounts => {
+                    incompatible(
+                        &added_layer_mounts,
+                        "field `layer_mounts` cannot be set after `added_layer_mounts`",
+                    )?;
+                    layer_mounts = Some(map.next_value()?);
+                }
+                DirectiveField::AddedLayerMounts => {
+                    added_layer_mounts = Some(map.next_value()?);
+                }
                 DirectiveField::Image => {
                     let i = map.next_value::<Image>()?;
                     image = Some(i.name);
@@ -226,13 +391,31 @@ where
             include_shared_libraries,
             network,
             enable_writable_file_system,
+            writable_file_system_size,
+            writable_file_system_inodes,
             user,
             group,
+            user_entries,
+            hostname,
+            extra_hosts: extra_hosts.unwrap_or_default(),
+            clock_offsets,
+            capture_core_dump,
+            keep_writable_layer_on_failure,
             timeout,
+            memory_limit,
+            cpu_limit,
+            pids_limit,
+            rlimits: rlimits.unwrap_or_default(),
+            seccomp,
+            init,
+            forwards: forwards.unwrap_or_default(),
+            sidecars: sidecars.unwrap_or_default(),
             layers,
             added_layers: added_layers.unwrap_or_default(),
             mounts,
             added_mounts: added_mounts.unwrap_or_default(),
+            layer_mounts,
+            added_layer_mounts: added_layer_mounts.unwrap_or_default(),
             image,
             working_directory,
             environment,
@@ -259,7 +442,10 @@ mod tests {
     use super::*;
     use anyhow::Error;
     use indoc::indoc;
-    use maelstrom_base::{enum_set, JobDeviceForTomlAndJson};
+    use maelstrom_base::{
+        enum_set, JobDeviceForTomlAndJson, JobForward, JobRlimit, JobRlimitResource,
+        JobSeccompAction, JobSeccompProfile,
+    };
     use maelstrom_client::spec::SymlinkSpec;
     use maelstrom_test::{
         glob_layer, non_root_utf8_path_buf, paths_layer, so_deps_layer, string, tar_layer,
@@ -318,6 +504,8 @@ mod tests {
                 include_shared_libraries = true
                 network = "loopback"
                 enable_writable_file_system = true
+                writable_file_system_size = 1048576
+                writable_file_system_inodes = 100
                 user = 101
                 group = 202
                 timeout = 1
@@ -333,6 +521,8 @@ mod tests {
                 include_shared_libraries: Some(true),
                 network: Some(JobNetwork::Loopback),
                 enable_writable_file_system: Some(true),
+                writable_file_system_size: Some(1048576),
+                writable_file_system_inodes: Some(100),
                 user: Some(UserId::from(101)),
                 group: Some(GroupId::from(202)),
                 timeout: Some(Timeout::new(1)),
@@ -363,6 +553,358 @@ mod tests {
         );
     }
 
+    #[test]
+    fn limits() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                memory_limit = "512 MiB"
+                cpu_limit = 1.5
+                pids_limit = 100
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                memory_limit: Some(Some(512 * 1024 * 1024)),
+                cpu_limit: Some(Some(1500)),
+                pids_limit: Some(Some(100)),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn seccomp() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                seccomp = { deny = ["ptrace", "mount"], action = "kill" }
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                seccomp: Some(Some(JobSeccomp {
+                    profile: JobSeccompProfile::Deny {
+                        syscalls: vec!["ptrace".into(), "mount".into()],
+                    },
+                    action: JobSeccompAction::Kill,
+                })),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn seccomp_none() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                seccomp = { profile = "none" }
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                seccomp: Some(None),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn init() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                init = false
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                init: Some(false),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn user_entries() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                user = 1000
+                user_entries = { user_name = "alice", home = "/home/alice" }
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                user: Some(UserId::from(1000)),
+                user_entries: Some(JobUserEntries {
+                    user_name: Some("alice".into()),
+                    group_name: None,
+                    home: Some("/home/alice".into()),
+                }),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn user_entries_unknown_field() {
+        assert_toml_error(
+            parse_test_directive(
+                r#"
+                user_entries = { name = "alice" }
+                "#,
+            )
+            .unwrap_err(),
+            "unknown field `name`, expected one of",
+        );
+    }
+
+    #[test]
+    fn user_entries_relative_home() {
+        assert_toml_error(
+            parse_test_directive(
+                r#"
+                user_entries = { home = "home/alice" }
+                "#,
+            )
+            .unwrap_err(),
+            r#""home/alice" isn't a valid home directory"#,
+        );
+    }
+
+    #[test]
+    fn hostname_and_extra_hosts() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                hostname = "builder"
+                extra_hosts = [{ name = "db", address = "10.0.0.2" }]
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                hostname: Some("builder".into()),
+                extra_hosts: vec![JobHost {
+                    name: "db".into(),
+                    address: "10.0.0.2".parse().unwrap(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn extra_hosts_bad_name() {
+        assert_toml_error(
+            parse_test_directive(
+                r#"
+                extra_hosts = [{ name = "db_1", address = "10.0.0.2" }]
+                "#,
+            )
+            .unwrap_err(),
+            r#""db_1" isn't a valid hostname"#,
+        );
+    }
+
+    #[test]
+    fn clock_offsets() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                clock_offsets = { monotonic = 86400, boottime = 86400 }
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                clock_offsets: Some(JobClockOffsets {
+                    monotonic: 86400,
+                    boottime: 86400,
+                }),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn capture_core_dump() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                capture_core_dump = true
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                capture_core_dump: Some(true),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn keep_writable_layer_on_failure() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                keep_writable_layer_on_failure = true
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                keep_writable_layer_on_failure: Some(true),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn integer_limits() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                memory_limit = 1000000
+                cpu_limit = 2
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                memory_limit: Some(Some(1000000)),
+                cpu_limit: Some(Some(2000)),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn zero_limits() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                memory_limit = 0
+                cpu_limit = 0
+                pids_limit = 0
+                "#
+            )
+            .unwrap(),
+            TestDirective {
+                memory_limit: Some(None),
+                cpu_limit: Some(None),
+                pids_limit: Some(None),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn rlimits() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                rlimits = [
+                    { resource = "nofile", soft = 4096, hard = 4096 },
+                    { resource = "stack", soft = "unlimited", hard = "unlimited" },
+                ]
+                "#
+            )
+            .unwrap()
+            .rlimits
+            .into_iter()
+            .map(JobRlimit::from)
+            .collect::<Vec<_>>(),
+            vec![
+                JobRlimit {
+                    resource: JobRlimitResource::NoFile,
+                    soft: 4096,
+                    hard: 4096,
+                },
+                JobRlimit {
+                    resource: JobRlimitResource::Stack,
+                    soft: JobRlimit::UNLIMITED,
+                    hard: JobRlimit::UNLIMITED,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn forwards() {
+        assert_eq!(
+            parse_test_directive(
+                r#"
+                forwards = [
+                    { tcp = 5432 },
+                    { unix = "/run/postgresql/.s.PGSQL.5432", host = "/tmp/pg.sock" },
+                ]
+                "#
+            )
+            .unwrap()
+            .forwards
+            .into_iter()
+            .map(JobForward::from)
+            .collect::<Vec<_>>(),
+            vec![
+                JobForward::Tcp {
+                    port: 5432,
+                    host: "localhost:5432".into(),
+                },
+                JobForward::Unix {
+                    path: utf8_path_buf!("/run/postgresql/.s.PGSQL.5432"),
+                    host: utf8_path_buf!("/tmp/pg.sock"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sidecars() {
+        let sidecars = parse_test_directive(
+            r#"
+            [[sidecars]]
+            name = "db"
+            program = "/usr/bin/postgres"
+            image = { name = "postgres", use = ["layers"] }
+            ready = { port = 5432 }
+
+            [[sidecars]]
+            name = "cache"
+            program = "/usr/bin/redis-server"
+            layers = [{ tar = "redis.tar" }]
+            "#,
+        )
+        .unwrap()
+        .sidecars;
+        assert_eq!(
+            sidecars
+                .iter()
+                .map(|sidecar| (sidecar.0.name.as_str(), sidecar.0.program.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("db", "/usr/bin/postgres"),
+                ("cache", "/usr/bin/redis-server")
+            ],
+        );
+        assert_eq!(sidecars[0].0.ready.unwrap().port, 5432);
+        assert_eq!(
+            sidecars[1].0.container.as_inline().unwrap().layers,
+            vec![tar_layer!("redis.tar")],
+        );
+    }
+
+    #[test]
+    fn negative_cpu_limit() {
+        assert_toml_error(
+            parse_test_directive(
+                r#"
+                cpu_limit = -1.0
+                "#,
+            )
+            .unwrap_err(),
+            "invalid CPU limit -1",
+        );
+    }
+
     #[test]
     fn mounts() {
         assert_eq!(
@@ -445,6 +987,9 @@ mod tests {
                 }]),
                 added_mounts: vec![JobMountForTomlAndJson::Tmp {
                     mount_point: non_root_utf8_path_buf!("/tmp"),
+                    size: None,
+                    mode: None,
+                    inodes: None,
                 }],
                 ..Default::default()
             }
@@ -463,6 +1008,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn layer_mounts() {
+        assert_eq!(
+            parse_test_directive(indoc! {r#"
+                layer_mounts = [
+                    { mount_point = "/opt/toolchain", layers = [{ tar = "toolchain.tar" }] },
+                ]
+                added_layer_mounts = [
+                    { mount_point = "/data", layers = [{ paths = ["data"] }] },
+                ]
+            "#})
+            .unwrap(),
+            TestDirective {
+                layer_mounts: Some(vec![LayerMountSpec {
+                    mount_point: utf8_path_buf!("/opt/toolchain"),
+                    layers: vec![tar_layer!("toolchain.tar")],
+                }]),
+                added_layer_mounts: vec![LayerMountSpec {
+                    mount_point: utf8_path_buf!("/data"),
+                    layers: vec![paths_layer!(["data"])],
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn layer_mounts_after_added_layer_mounts() {
+        assert_toml_error(
+            parse_test_directive(indoc! {r#"
+                added_layer_mounts = [ { mount_point = "/data", layers = [] } ]
+                layer_mounts = [ { mount_point = "/opt", layers = [] } ]
+            "#})
+            .unwrap_err(),
+            "field `layer_mounts` cannot be set after `added_layer_mounts`",
+        );
+    }
+
     #[test]
     fn unknown_field_in_simple_mount() {
         assert_toml_error(
@@ -8,7 +8,7 @@
 
 use crate::{TestArtifactKey, TestCaseMetadata, TestFilter};
 use anyhow::{anyhow, bail, Result};
-use maelstrom_base::{nonempty, NonEmpty};
+use maelstrom_base::{nonempty, JobResourceUsage, NonEmpty};
 use maelstrom_client::StateDir;
 use maelstrom_util::{
     fs::Fs,
@@ -74,6 +74,9 @@ struct CaseData<CaseMetadataT> {
 
     /// The information about the test case that has been accumulated since the db was read.
     this_run: Option<(CaseOutcome, NonEmpty<Duration>)>,
+
+    /// The resources used the most recent time the test case was run, if known.
+    resource_usage: Option<JobResourceUsage>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -191,6 +194,7 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
                         metadata,
                         when_read: None,
                         this_run: None,
+                        resource_usage: None,
                     },
                 )
             }));
@@ -253,7 +257,8 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
     /// Update the database entry for a given test case.
     ///
     /// This will update the `this_run` field of the database, leaving the `when_read` field
-    /// untouched.
+    /// untouched. If `resource_usage` is provided, it replaces any previously recorded resource
+    /// usage.
     ///
     /// The package, artifact, and case must have been previously added using
     /// [`Self::update_artifact_cases`]. If not, the function will panic.
@@ -264,6 +269,7 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
         case_name: &str,
         failed: bool,
         timing: Duration,
+        resource_usage: Option<JobResourceUsage>,
     ) {
         const MAX_TIMINGS_PER_CASE: usize = 3;
         fn add_timing(timings: &mut NonEmpty<Duration>, timing: Duration) {
@@ -308,6 +314,9 @@ impl<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCaseMetadata>
                 *existing_outcome = existing_outcome.combine(outcome);
             }
         };
+        if resource_usage.is_some() {
+            case.resource_usage = resource_usage;
+        }
     }
 
     /// Return some information about the specified test case.
@@ -390,6 +399,46 @@ impl From<CaseOutcome> for OnDiskCaseOutcome {
     }
 }
 
+#[serde_as]
+#[derive(Clone, Serialize, Deserialize)]
+struct OnDiskResourceUsage {
+    peak_memory: u64,
+    #[serde_as(as = "DurationSecondsWithFrac")]
+    user_time: Duration,
+    #[serde_as(as = "DurationSecondsWithFrac")]
+    system_time: Duration,
+    read_bytes: u64,
+    written_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spawned_processes: Option<u64>,
+}
+
+impl From<JobResourceUsage> for OnDiskResourceUsage {
+    fn from(in_memory: JobResourceUsage) -> Self {
+        Self {
+            peak_memory: in_memory.peak_memory,
+            user_time: in_memory.user_time,
+            system_time: in_memory.system_time,
+            read_bytes: in_memory.read_bytes,
+            written_bytes: in_memory.written_bytes,
+            spawned_processes: in_memory.spawned_processes,
+        }
+    }
+}
+
+impl From<OnDiskResourceUsage> for JobResourceUsage {
+    fn from(on_disk: OnDiskResourceUsage) -> Self {
+        Self {
+            peak_memory: on_disk.peak_memory,
+            user_time: on_disk.user_time,
+            system_time: on_disk.system_time,
+            read_bytes: on_disk.read_bytes,
+            written_bytes: on_disk.written_bytes,
+            spawned_processes: on_disk.spawned_processes,
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Serialize, Deserialize)]
 struct OnDiskCaseData<CaseMetadataT: TestCaseMetadata> {
@@ -401,29 +450,35 @@ struct OnDiskCaseData<CaseMetadataT: TestCaseMetadata> {
     metadata: CaseMetadataT,
     #[serde(default)]
     outcome: OnDiskCaseOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resource_usage: Option<OnDiskResourceUsage>,
 }
 
 impl<CaseMetadataT: TestCaseMetadata> From<CaseData<CaseMetadataT>>
     for OnDiskCaseData<CaseMetadataT>
 {
     fn from(in_memory: CaseData<CaseMetadataT>) -> Self {
+        let resource_usage = in_memory.resource_usage.map(Into::into);
         if let Some((outcome, timings)) = in_memory.this_run {
             OnDiskCaseData {
                 timings: timings.into_iter().collect(),
                 metadata: in_memory.metadata,
                 outcome: outcome.into(),
+                resource_usage,
             }
         } else if let Some((outcome, timings)) = in_memory.when_read {
             OnDiskCaseData {
                 timings: timings.into_iter().collect(),
                 metadata: in_memory.metadata,
                 outcome: outcome.into(),
+                resource_usage,
             }
         } else {
             OnDiskCaseData {
                 timings: vec![],
                 metadata: in_memory.metadata,
                 outcome: OnDiskCaseOutcome::New,
+                resource_usage,
             }
         }
     }
@@ -443,6 +498,7 @@ impl<CaseMetadataT: TestCaseMetadata> From<OnDiskCaseData<CaseMetadataT>>
             metadata: on_disk.metadata,
             when_read,
             this_run: None,
+            resource_usage: on_disk.resource_usage.map(Into::into),
         }
     }
 }
@@ -697,6 +753,7 @@ mod tests {
                             metadata,
                             when_read,
                             this_run,
+                            resource_usage: None,
                         },
                     )
                 }),
@@ -1137,6 +1194,7 @@ mod tests {
             "case-1-1L-1",
             false,
             millis!(10),
+            None,
         );
         assert_eq!(
             db,
@@ -1159,6 +1217,7 @@ mod tests {
             "case-1-1L-1",
             true,
             millis!(11),
+            None,
         );
         assert_eq!(
             db,
@@ -1181,6 +1240,7 @@ mod tests {
             "case-1-1L-1",
             false,
             millis!(12),
+            None,
         );
         assert_eq!(
             db,
@@ -1203,6 +1263,7 @@ mod tests {
             "case-1-1L-1",
             false,
             millis!(13),
+            None,
         );
         assert_eq!(
             db,
@@ -1249,6 +1310,7 @@ mod tests {
             "case-1-1L-1",
             false,
             millis!(15),
+            None,
         );
         assert_eq!(
             db,
@@ -1275,6 +1337,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn update_case_resource_usage() {
+        let mut db = TestDb::<StringArtifactKey, NoCaseMetadata>::default();
+        let artifact_key = StringArtifactKey::from("artifact-1.library");
+        db.update_artifact_cases(
+            "package-1",
+            artifact_key.clone(),
+            [("case-1-1L-1", NoCaseMetadata)],
+        );
+        let case =
+            |db: &TestDb<_, _>| db.0["package-1"].0[&artifact_key].0["case-1-1L-1"].resource_usage;
+        let usage = JobResourceUsage {
+            peak_memory: 1 << 30,
+            user_time: millis!(10),
+            ..Default::default()
+        };
+
+        db.update_case(
+            "package-1",
+            &artifact_key,
+            "case-1-1L-1",
+            false,
+            millis!(10),
+            Some(usage),
+        );
+        assert_eq!(case(&db), Some(usage));
+
+        // A run without any resource usage doesn't erase the last known usage.
+        db.update_case(
+            "package-1",
+            &artifact_key,
+            "case-1-1L-1",
+            false,
+            millis!(11),
+            None,
+        );
+        assert_eq!(case(&db), Some(usage));
+
+        let usage = JobResourceUsage {
+            peak_memory: 1 << 20,
+            ..usage
+        };
+        db.update_case(
+            "package-1",
+            &artifact_key,
+            "case-1-1L-1",
+            true,
+            millis!(12),
+            Some(usage),
+        );
+        assert_eq!(case(&db), Some(usage));
+    }
+
     #[test]
     fn get_case() {
         let artifact_1 = StringArtifactKey::from("artifact-1.library");
@@ -1731,4 +1846,72 @@ mod tests {
                 "#},
         );
     }
+
+    #[test]
+    fn save_and_load_of_resource_usage() {
+        let deps = Rc::new(RefCell::new(LoggingDeps::default()));
+        let store = TestDbStore::<StringArtifactKey, NoCaseMetadata, _>::new(
+            deps.clone(),
+            RootBuf::new("maelstrom/state/".into()),
+        );
+        let mut db = TestDb::<StringArtifactKey, NoCaseMetadata>::default();
+        let artifact_key = StringArtifactKey::from("artifact-1.library");
+        db.update_artifact_cases(
+            "package-1",
+            artifact_key.clone(),
+            [("case-1-1L-1", NoCaseMetadata)],
+        );
+        let usage = JobResourceUsage {
+            peak_memory: 8 << 30,
+            user_time: millis!(1500),
+            system_time: millis!(250),
+            read_bytes: 4096,
+            written_bytes: 8192,
+            spawned_processes: Some(3),
+        };
+        db.update_case(
+            "package-1",
+            &artifact_key,
+            "case-1-1L-1",
+            false,
+            millis!(10),
+            Some(usage),
+        );
+        store.save(db).unwrap();
+        let (_, actual_contents) = deps.borrow_mut().write.take().unwrap();
+        assert_eq!(
+            actual_contents,
+            indoc! {r#"
+                    version = 3
+
+                    [package-1."artifact-1.library".case-1-1L-1]
+                    timings = [0.01]
+                    outcome = "success"
+
+                    [package-1."artifact-1.library".case-1-1L-1.resource_usage]
+                    peak_memory = 8589934592
+                    user_time = 1.5
+                    system_time = 0.25
+                    read_bytes = 4096
+                    written_bytes = 8192
+                    spawned_processes = 3
+                "#},
+        );
+
+        struct Deps(String);
+        impl TestDbStoreDeps for Deps {
+            fn read_to_string_if_exists(&self, _: impl AsRef<Path>) -> Result<Option<String>> {
+                Ok(Some(self.0.clone()))
+            }
+        }
+        let store = TestDbStore::<StringArtifactKey, NoCaseMetadata, _>::new(
+            Deps(actual_contents),
+            RootBuf::new("".into()),
+        );
+        let db = store.load().unwrap();
+        assert_eq!(
+            db.0["package-1"].0[&artifact_key].0["case-1-1L-1"].resource_usage,
+            Some(usage)
+        );
+    }
 }
@@ -16,7 +16,7 @@ use indicatif::InMemoryTerm;
 use indoc::indoc;
 use maelstrom_base::{
     stats::JobState, ClientJobId, JobBrokerStatus, JobCompleted, JobEffects, JobOutcome,
-    JobOutputResult, JobTerminationStatus, JobWorkerStatus,
+    JobOutputResult, JobTerminationStatus, JobWorkerStatus, Sha256Digest,
 };
 use maelstrom_client::{
     spec::JobSpec, IntrospectResponse, JobRunningStatus, JobStatus, ProjectDir, StateDir,
@@ -30,6 +30,7 @@ use maelstrom_util::{
 use pretty_assertions::assert_eq;
 use std::{
     cell::RefCell,
+    path::Path,
     rc::Rc,
     sync::atomic::{AtomicU32, Ordering},
     time::Duration,
@@ -69,6 +70,14 @@ impl ClientTrait for TestClient {
         todo!()
     }
 
+    fn download_artifact(&self, _digest: &Sha256Digest, _path: &Path) -> Result<()> {
+        todo!()
+    }
+
+    fn download_and_unpack_artifact(&self, _digest: &Sha256Digest, _path: &Path) -> Result<()> {
+        todo!()
+    }
+
     fn add_job(
         &self,
         spec: JobSpec,
@@ -97,7 +106,7 @@ impl ClientTrait for TestClient {
             JobState::Complete => {
                 handler(Ok(JobStatus::Completed {
                     client_job_id,
-                    result: Ok(case.outcome.clone()),
+                    result: Box::new(Ok(case.outcome.clone())),
                 }));
             }
         }
@@ -107,7 +116,7 @@ impl ClientTrait for TestClient {
             case.cb.set(move || {
                 handler(Ok(JobStatus::Completed {
                     client_job_id,
-                    result: Ok(outcome),
+                    result: Box::new(Ok(outcome)),
                 }));
             });
         }
@@ -906,6 +915,12 @@ fn failed_outcome() -> JobOutcome {
             )),
             stderr: JobOutputResult::Inline(Box::new(*b"error output")),
             duration: Duration::from_secs(1),
+            resource_usage: None,
+            core_dump: None,
+            core_dump_not_captured: None,
+            writable_layer: None,
+            full_tmpfs_mounts: vec![],
+            blocked_syscalls: vec![],
         },
     })
 }
@@ -917,6 +932,12 @@ fn failed_outcome_no_output() -> JobOutcome {
             stdout: JobOutputResult::Inline(Box::new(*b"")),
             stderr: JobOutputResult::Inline(Box::new(*b"")),
             duration: Duration::from_secs(1),
+            resource_usage: None,
+            core_dump: None,
+            core_dump_not_captured: None,
+            writable_layer: None,
+            full_tmpfs_mounts: vec![],
+            blocked_syscalls: vec![],
         },
     })
 }
@@ -1284,6 +1305,12 @@ fn expected_count_updates_packages() {
                         stdout: JobOutputResult::None,
                         stderr: JobOutputResult::None,
                         duration: Duration::from_secs(1),
+                        resource_usage: None,
+                        core_dump: None,
+                        core_dump_not_captured: None,
+                        writable_layer: None,
+                        full_tmpfs_mounts: vec![],
+                        blocked_syscalls: vec![],
                     }),
                     ..Default::default()
                 }],
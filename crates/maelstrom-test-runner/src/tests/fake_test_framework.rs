@@ -86,6 +86,12 @@ impl Default for FakeTestCase {
                     stdout: JobOutputResult::None,
                     stderr: JobOutputResult::Inline(Box::new(*b"this output should be ignored")),
                     duration: Duration::from_secs(1),
+                    resource_usage: None,
+                    core_dump: None,
+                    core_dump_not_captured: None,
+                    writable_layer: None,
+                    full_tmpfs_mounts: vec![],
+                    blocked_syscalls: vec![],
                 },
             }),
             complete_at_end: false,
@@ -171,6 +177,7 @@ impl FakeTests {
                         })
                     ),
                     case.timing(),
+                    None,
                 );
             }
         }
@@ -4,8 +4,12 @@ mod simple;
 
 use crate::{LoggingOutput, NotRunEstimate};
 use anyhow::Result;
+use bytesize::ByteSize;
 use derive_more::{From, Into};
-use maelstrom_base::stats::{JobState, JobStateCounts};
+use maelstrom_base::{
+    stats::{JobState, JobStateCounts},
+    JobResourceUsage,
+};
 use maelstrom_client::{IntrospectResponse, JobRunningStatus};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -182,11 +186,20 @@ pub struct UiJobResult {
     pub name: String,
     pub job_id: UiJobId,
     pub duration: Option<Duration>,
+    pub resource_usage: Option<JobResourceUsage>,
     pub status: UiJobStatus,
     pub stdout: Vec<String>,
     pub stderr: Vec<String>,
 }
 
+impl UiJobResult {
+    /// The job's peak memory usage, formatted for display, if the worker reported it.
+    fn peak_memory(&self) -> Option<String> {
+        self.resource_usage
+            .map(|usage| ByteSize::b(usage.peak_memory).to_string())
+    }
+}
+
 #[derive(Debug, Copy, Clone, From, Into, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct UiJobId(u32);
 
@@ -217,6 +217,7 @@ fn format_finished(res: UiJobResult) -> Vec<PrintAbove> {
         UiJobStatus::Ignored => "IGNORED".yellow(),
     };
 
+    let peak_memory = res.peak_memory();
     let case = res.name.bold();
     let mut line = vec![Cell::from(case), Cell::from(result_span)];
 
@@ -226,6 +227,12 @@ fn format_finished(res: UiJobResult) -> Vec<PrintAbove> {
         ));
     }
 
+    if let Some(peak_memory) = peak_memory {
+        line.push(Cell::from(
+            Text::from(peak_memory).alignment(Alignment::Right),
+        ));
+    }
+
     let mut output = vec![(Row::new(line.into_iter()), test_status_constraints()).into()];
 
     if let Some(details) = res.status.details() {
@@ -286,6 +293,7 @@ fn test_status_constraints() -> Vec<Constraint> {
         Constraint::Fill(1),
         Constraint::Length(7),
         Constraint::Length(8),
+        Constraint::Length(11),
     ]
 }
 
@@ -83,11 +83,16 @@ where
         UiJobStatus::Ignored => "IGNORED".yellow(),
     };
 
+    let peak_memory = res.peak_memory();
     prog.println_width(move |width| {
         let duration_str = res
             .duration
             .map(|d| format!("{:.3}s", d.as_secs_f64()))
             .unwrap_or_default();
+        let duration_str = match peak_memory {
+            Some(peak_memory) => format!("{duration_str:>8} {peak_memory:>10}"),
+            None => duration_str,
+        };
         if width > 10 {
             let case_width = res.name.width();
             let trailer_str = format!("{result_str} {duration_str:>8}");
@@ -4,12 +4,16 @@ use crate::ui::{UiJobId, UiJobResult, UiJobStatus, UiJobSummary, UiJobUpdate, Ui
 use crate::{NotRunEstimate, TestArtifactKey, TestCaseMetadata};
 use anyhow::Result;
 use maelstrom_base::{
-    ClientJobId, JobCompleted, JobEffects, JobError, JobOutcome, JobOutcomeResult, JobOutputResult,
-    JobTerminationStatus,
+    ClientJobId, JobBlockedSyscall, JobCompleted, JobCoreDump, JobEffects, JobError, JobOutcome,
+    JobOutcomeResult, JobOutputResult, JobTerminationStatus, JobWritableLayer, Sha256Digest,
+    Utf8PathBuf,
 };
 use maelstrom_client::JobStatus;
 use maelstrom_util::process::{ExitCode, ExitCodeAccumulator};
-use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+};
 
 #[derive(Clone)]
 enum CaseResult {
@@ -24,6 +28,8 @@ struct LockedJobStatusTracker {
     num_failed: u64,
     stop_after: Option<StopAfter>,
     exit_code: ExitCodeAccumulator,
+    core_dumps: Vec<(Sha256Digest, PathBuf)>,
+    writable_layers: Vec<(Sha256Digest, PathBuf)>,
 }
 
 impl LockedJobStatusTracker {
@@ -78,16 +84,24 @@ impl LockedJobStatusTracker {
 pub struct JobStatusTracker {
     inner: Mutex<LockedJobStatusTracker>,
     condvar: Condvar,
+    core_dump_dir: PathBuf,
+    writable_layer_dir: PathBuf,
 }
 
 impl JobStatusTracker {
-    pub fn new(stop_after: Option<StopAfter>) -> Self {
+    pub fn new(
+        stop_after: Option<StopAfter>,
+        core_dump_dir: PathBuf,
+        writable_layer_dir: PathBuf,
+    ) -> Self {
         Self {
             inner: Mutex::new(LockedJobStatusTracker {
                 stop_after,
                 ..Default::default()
             }),
             condvar: Condvar::new(),
+            core_dump_dir,
+            writable_layer_dir,
         }
     }
 
@@ -122,6 +136,18 @@ impl JobStatusTracker {
         let inner = self.inner.lock().unwrap();
         inner.exit_code.get()
     }
+
+    /// The core dumps captured so far, along with where they should be downloaded to.
+    pub fn take_core_dumps(&self) -> Vec<(Sha256Digest, PathBuf)> {
+        let mut inner = self.inner.lock().unwrap();
+        std::mem::take(&mut inner.core_dumps)
+    }
+
+    /// The writable layers kept so far, along with where they should be unpacked to.
+    pub fn take_writable_layers(&self) -> Vec<(Sha256Digest, PathBuf)> {
+        let mut inner = self.inner.lock().unwrap();
+        std::mem::take(&mut inner.writable_layers)
+    }
 }
 
 #[derive(Clone)]
@@ -137,6 +163,16 @@ pub struct JobStatusVisitor<ArtifactKeyT: TestArtifactKey, CaseMetadataT: TestCa
     was_ignored: fn(&str, &[String]) -> bool,
 }
 
+/// Lines to display with a failed job's output for each syscall its seccomp filter made fail.
+fn blocked_syscall_lines(cjid: ClientJobId, blocked_syscalls: &[JobBlockedSyscall]) -> Vec<String> {
+    blocked_syscalls
+        .iter()
+        .map(|JobBlockedSyscall { name, count }| {
+            format!("job {cjid}: seccomp blocked {name} {count} time(s)")
+        })
+        .collect()
+}
+
 impl<ArtifactKeyT, CaseMetadataT> JobStatusVisitor<ArtifactKeyT, CaseMetadataT>
 where
     ArtifactKeyT: TestArtifactKey,
@@ -234,6 +270,16 @@ fn format_test_output(
     test_output_lines
 }
 
+/// Lines to display with a failed job's output for each tmpfs that was full when it exited.
+fn full_tmpfs_lines(cjid: ClientJobId, full_tmpfs_mounts: &[Utf8PathBuf]) -> Vec<String> {
+    full_tmpfs_mounts
+        .iter()
+        .map(|mount_point| {
+            format!("job {cjid}: tmpfs at {mount_point} was full when the job exited")
+        })
+        .collect()
+}
+
 impl<ArtifactKeyT, CaseMetadataT> JobStatusVisitor<ArtifactKeyT, CaseMetadataT>
 where
     ArtifactKeyT: TestArtifactKey,
@@ -249,6 +295,7 @@ where
         let mut test_output_stderr: Vec<String> = vec![];
         let mut test_output_stdout: Vec<String> = vec![];
         let mut test_duration = None;
+        let mut test_resource_usage = None;
         let exit_code = match res {
             Ok((
                 cjid,
@@ -259,10 +306,17 @@ where
                             stdout,
                             stderr,
                             duration,
+                            resource_usage,
+                            core_dump,
+                            core_dump_not_captured,
+                            writable_layer,
+                            full_tmpfs_mounts,
+                            blocked_syscalls,
                         },
                 })),
             )) => {
                 test_duration = Some(duration);
+                test_resource_usage = resource_usage;
                 let mut job_failed = true;
                 let exit_code = match status {
                     JobTerminationStatus::Exited(code) => {
@@ -279,6 +333,17 @@ where
                             UiJobStatus::Failure(Some(format!("killed by signal {signo}")));
                         ExitCode::FAILURE
                     }
+                    JobTerminationStatus::OutOfMemory => {
+                        test_status =
+                            UiJobStatus::Failure(Some("killed for exceeding memory limit".into()));
+                        ExitCode::FAILURE
+                    }
+                    JobTerminationStatus::SyscallBlocked => {
+                        test_status = UiJobStatus::Failure(Some(
+                            "killed for making a blocked syscall".into(),
+                        ));
+                        ExitCode::FAILURE
+                    }
                 };
                 if job_failed {
                     test_output_stdout.extend(format_test_output(
@@ -295,6 +360,22 @@ where
                         &self.case_str,
                         self.remove_fixture_output,
                     ));
+                    test_output_stderr.extend(full_tmpfs_lines(cjid, &full_tmpfs_mounts));
+                    test_output_stderr.extend(blocked_syscall_lines(cjid, &blocked_syscalls));
+                }
+                if let Some(core_dump) = core_dump {
+                    test_output_stderr.push(self.core_dumped(&mut locked_tracker, cjid, core_dump));
+                }
+                if let Some(reason) = core_dump_not_captured {
+                    test_output_stderr
+                        .push(format!("job {cjid}: core dump not captured: {reason}"));
+                }
+                if let Some(writable_layer) = writable_layer {
+                    test_output_stderr.push(self.writable_layer_kept(
+                        &mut locked_tracker,
+                        cjid,
+                        writable_layer,
+                    ));
                 }
 
                 if !job_failed && was_ignored(&stdout, &self.case_str, self.was_ignored) {
@@ -309,6 +390,7 @@ where
                     self.case.as_str(),
                     job_failed,
                     duration,
+                    resource_usage,
                 );
                 exit_code
             }
@@ -318,9 +400,16 @@ where
                     stdout,
                     stderr,
                     duration,
+                    resource_usage,
+                    core_dump: _,
+                    core_dump_not_captured: _,
+                    writable_layer,
+                    full_tmpfs_mounts,
+                    blocked_syscalls,
                 })),
             )) => {
                 test_duration = Some(duration);
+                test_resource_usage = resource_usage;
                 test_status = UiJobStatus::TimedOut;
                 test_output_stdout.extend(format_test_output(
                     &stdout,
@@ -336,12 +425,22 @@ where
                     &self.case_str,
                     self.remove_fixture_output,
                 ));
+                test_output_stderr.extend(full_tmpfs_lines(cjid, &full_tmpfs_mounts));
+                test_output_stderr.extend(blocked_syscall_lines(cjid, &blocked_syscalls));
+                if let Some(writable_layer) = writable_layer {
+                    test_output_stderr.push(self.writable_layer_kept(
+                        &mut locked_tracker,
+                        cjid,
+                        writable_layer,
+                    ));
+                }
                 self.test_db.lock().unwrap().as_mut().unwrap().update_case(
                     self.package.as_str(),
                     &self.artifact,
                     self.case.as_str(),
                     true,
                     duration,
+                    resource_usage,
                 );
                 ExitCode::FAILURE
             }
@@ -364,6 +463,7 @@ where
             name: self.case_str.clone(),
             status: test_status,
             duration: test_duration,
+            resource_usage: test_resource_usage,
             stdout: test_output_stdout,
             stderr: test_output_stderr,
         });
@@ -374,6 +474,64 @@ where
         self.tracker.condvar.notify_all();
     }
 
+    /// Record that the job dumped core, so that the core file can be downloaded at the end of the
+    /// run. Returns a line to be displayed with the job's output.
+    fn core_dumped(
+        &self,
+        locked_tracker: &mut LockedJobStatusTracker,
+        cjid: ClientJobId,
+        core_dump: JobCoreDump,
+    ) -> String {
+        let path = self
+            .tracker
+            .core_dump_dir
+            .join(format!("{}.core", self.output_file_name(cjid)));
+        let line = match &core_dump.build_id {
+            Some(build_id) => format!(
+                "job {cjid}: core dumped (build-id {build_id}), saved to {}",
+                path.display()
+            ),
+            None => format!("job {cjid}: core dumped, saved to {}", path.display()),
+        };
+        locked_tracker.core_dumps.push((core_dump.digest, path));
+        line
+    }
+
+    /// Record that the job's writable layer was kept, so that it can be downloaded and unpacked at
+    /// the end of the run. Returns a line to be displayed with the job's output.
+    fn writable_layer_kept(
+        &self,
+        locked_tracker: &mut LockedJobStatusTracker,
+        cjid: ClientJobId,
+        writable_layer: JobWritableLayer,
+    ) -> String {
+        let path = self
+            .tracker
+            .writable_layer_dir
+            .join(self.output_file_name(cjid));
+        let line = format!("job {cjid}: writable layer saved to {}", path.display());
+        locked_tracker
+            .writable_layers
+            .push((writable_layer.digest, path));
+        line
+    }
+
+    /// A name for files saved from the job, unique to this run, that is safe to use as a file name.
+    fn output_file_name(&self, cjid: ClientJobId) -> String {
+        let case_str: String = self
+            .case_str
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{case_str}.{cjid}")
+    }
+
     pub fn job_update(&self, ui_job_id: UiJobId, res: Result<JobStatus>) {
         let locked_tracker = self.tracker.inner.lock().unwrap();
         if locked_tracker.is_failure_limit_reached() {
@@ -384,7 +542,7 @@ where
             Ok(JobStatus::Completed {
                 client_job_id,
                 result,
-            }) => self.job_finished(locked_tracker, ui_job_id, Ok((client_job_id, result))),
+            }) => self.job_finished(locked_tracker, ui_job_id, Ok((client_job_id, *result))),
             Ok(JobStatus::Running(status)) => self.ui_job_updated(UiJobUpdate {
                 job_id: ui_job_id,
                 status,
@@ -404,6 +562,7 @@ where
             job_id: ui_job_id,
             status: UiJobStatus::Ignored,
             duration: None,
+            resource_usage: None,
             stdout: vec![],
             stderr: vec![],
         });
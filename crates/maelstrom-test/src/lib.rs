@@ -82,6 +82,12 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                resource_usage: None,
+                core_dump: None,
+                core_dump_not_captured: None,
+                writable_layer: None,
+                full_tmpfs_mounts: vec![],
+                blocked_syscalls: vec![],
             }
         }
     };
@@ -92,6 +98,12 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                resource_usage: None,
+                core_dump: None,
+                core_dump_not_captured: None,
+                writable_layer: None,
+                full_tmpfs_mounts: vec![],
+                blocked_syscalls: vec![],
             }
         }
     };
@@ -102,6 +114,12 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                resource_usage: None,
+                core_dump: None,
+                core_dump_not_captured: None,
+                writable_layer: None,
+                full_tmpfs_mounts: vec![],
+                blocked_syscalls: vec![],
             }
         }
     };
@@ -112,6 +130,12 @@ macro_rules! completed {
                 stdout: maelstrom_base::JobOutputResult::None,
                 stderr: maelstrom_base::JobOutputResult::None,
                 duration: std::time::Duration::from_secs(1),
+                resource_usage: None,
+                core_dump: None,
+                core_dump_not_captured: None,
+                writable_layer: None,
+                full_tmpfs_mounts: vec![],
+                blocked_syscalls: vec![],
             }
         }
     };
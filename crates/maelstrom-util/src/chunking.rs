@@ -0,0 +1,211 @@
+//! Content-defined chunking of artifacts.
+//!
+//! Artifacts are split into chunks at positions determined by their contents, using a gear hash
+//! over a rolling window. Inserting or removing bytes in one part of an artifact only changes the
+//! chunks around that part, so two versions of the same artifact tend to share most of their
+//! chunks. This lets us avoid sending chunks the receiver already has.
+
+use maelstrom_base::Sha256Digest;
+use sha2::{Digest as _, Sha256};
+use std::io::{self, Read};
+
+/// No chunk, except for the last one of an artifact, is smaller than this.
+pub const MIN_CHUNK_SIZE: usize = 256 * 1024;
+
+/// No chunk is larger than this.
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// A boundary is placed when all of these bits of the hash are zero. With 20 bits, chunks are on
+/// average about 1 MiB larger than [`MIN_CHUNK_SIZE`].
+const BOUNDARY_MASK: u64 = !0 << (64 - 20);
+
+/// The random values each byte contributes to the hash. These must never change, or the chunks of
+/// artifacts will no longer match chunks computed before the change.
+const GEAR: [u64; 256] = gear_table();
+
+/// Generate [`GEAR`] using splitmix64 with a fixed seed.
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Find chunk boundaries in a stream of bytes that is fed in piece by piece.
+#[derive(Default)]
+pub struct Chunker {
+    hash: u64,
+    len: usize,
+}
+
+impl Chunker {
+    /// Feed the next bytes of the stream to the chunker. If the current chunk ends within `data`,
+    /// return the offset into `data` just past its end, and start a new chunk. The caller should
+    /// then call this function again with the rest of `data`.
+    pub fn next_boundary(&mut self, data: &[u8]) -> Option<usize> {
+        for (i, byte) in data.iter().enumerate() {
+            self.len += 1;
+            if self.len < MIN_CHUNK_SIZE {
+                continue;
+            }
+            self.hash = (self.hash << 1).wrapping_add(GEAR[*byte as usize]);
+            if self.hash & BOUNDARY_MASK == 0 || self.len == MAX_CHUNK_SIZE {
+                *self = Self::default();
+                return Some(i + 1);
+            }
+        }
+        None
+    }
+}
+
+/// A chunk of an artifact.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Chunk {
+    pub offset: u64,
+    pub size: u64,
+    pub digest: Sha256Digest,
+}
+
+/// Split everything read from `reader` into chunks. The chunks are returned in order, and cover
+/// everything that was read. An empty stream has no chunks.
+pub fn chunk(mut reader: impl Read) -> io::Result<Vec<Chunk>> {
+    let mut chunks = vec![];
+    let mut chunker = Chunker::default();
+    let mut hasher = Sha256::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let mut data = &buf[..read];
+        while let Some(end) = chunker.next_boundary(data) {
+            hasher.update(&data[..end]);
+            offset += end as u64;
+            chunks.push(Chunk {
+                offset: start,
+                size: offset - start,
+                digest: Sha256Digest::new(hasher.finalize_reset().into()),
+            });
+            start = offset;
+            data = &data[end..];
+        }
+        hasher.update(data);
+        offset += data.len() as u64;
+    }
+    if offset > start {
+        chunks.push(Chunk {
+            offset: start,
+            size: offset - start,
+            digest: Sha256Digest::new(hasher.finalize().into()),
+        });
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn digest_of(data: &[u8]) -> Sha256Digest {
+        Sha256Digest::new(Sha256::digest(data).into())
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(chunk(io::empty()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn smaller_than_min_chunk_size() {
+        let data = random_bytes(1000, 1);
+        assert_eq!(
+            chunk(&data[..]).unwrap(),
+            vec![Chunk {
+                offset: 0,
+                size: 1000,
+                digest: digest_of(&data),
+            }]
+        );
+    }
+
+    #[test]
+    fn chunks_cover_data_and_respect_bounds() {
+        let data = random_bytes(16 * 1024 * 1024, 2);
+        let chunks = chunk(&data[..]).unwrap();
+        assert!(chunks.len() > 1);
+        let mut offset = 0;
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.offset, offset);
+            assert!(chunk.size <= MAX_CHUNK_SIZE as u64);
+            if i != chunks.len() - 1 {
+                assert!(chunk.size >= MIN_CHUNK_SIZE as u64);
+            }
+            let range = chunk.offset as usize..(chunk.offset + chunk.size) as usize;
+            assert_eq!(chunk.digest, digest_of(&data[range]));
+            offset += chunk.size;
+        }
+        assert_eq!(offset, data.len() as u64);
+    }
+
+    #[test]
+    fn chunks_dont_depend_on_read_sizes() {
+        let data = random_bytes(8 * 1024 * 1024, 3);
+        let expected = chunk(&data[..]).unwrap();
+
+        // A reader that returns at most 1000 bytes at a time.
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(self.0.len()).min(1000);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+        assert_eq!(chunk(Trickle(&data)).unwrap(), expected);
+    }
+
+    #[test]
+    fn insertion_only_changes_nearby_chunks() {
+        let data = random_bytes(16 * 1024 * 1024, 4);
+        let mut modified = data[..100].to_vec();
+        modified.extend(random_bytes(5000, 5));
+        modified.extend(&data[100..]);
+
+        let original: HashSet<_> = chunk(&data[..])
+            .unwrap()
+            .into_iter()
+            .map(|c| c.digest)
+            .collect();
+        let modified: Vec<_> = chunk(&modified[..]).unwrap();
+        let new = modified
+            .iter()
+            .filter(|c| !original.contains(&c.digest))
+            .count();
+        assert!(new <= 3, "{new} of {} chunks changed", modified.len());
+    }
+}
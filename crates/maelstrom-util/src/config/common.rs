@@ -225,6 +225,33 @@ impl FromStr for InlineLimit {
     }
 }
 
+#[derive(Clone, Copy, Deserialize, Eq, PartialEq, From, Into)]
+#[serde(transparent)]
+pub struct TmpfsSize(#[serde(with = "bytesize_serde")] ByteSize);
+
+byte_size_u64_from_impls!(TmpfsSize);
+
+impl Debug for TmpfsSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for TmpfsSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for TmpfsSize {
+    type Err = StringError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            <ByteSize as FromStr>::from_str(s).map_err(StringError)?,
+        ))
+    }
+}
+
 #[pocket_definition(export)]
 #[derive(Clone, Copy, Deserialize, Into)]
 #[serde(try_from = "u16")]
@@ -547,6 +547,10 @@ impl<'fs> File<'fs> {
         fs_inner_trampoline!(self, set_permissions, perm)
     }
 
+    pub fn set_modified(&self, time: std::time::SystemTime) -> Result<()> {
+        fs_inner_trampoline!(self, set_modified, time)
+    }
+
     pub fn lock_shared(&self) -> Result<()> {
         fs_inner_trampoline!(self, lock_shared)
     }
@@ -4,6 +4,7 @@
 
 pub mod r#async;
 pub mod async_fs;
+pub mod chunking;
 pub mod clap;
 pub mod config;
 pub mod duration;
@@ -201,6 +201,16 @@ impl<RpcConnectionT: MonitorConnection> UiHandler<RpcConnectionT> {
                 ui.label("total job(s)");
             });
 
+            let evictions = &stats.eviction_statistics;
+            ui.vertical(|ui| {
+                ui.heading(evictions.artifacts.to_string());
+                ui.label("artifact(s) evicted from cache");
+                ui.heading(evictions.bytes.to_string());
+                ui.label("byte(s) evicted from cache");
+                ui.heading(evictions.runs.to_string());
+                ui.label("eviction run(s)");
+            });
+
             if num_slots > 0 {
                 ui.add(
                     Gauge::new(num_running_jobs, 0..=(num_slots * 2), 150.0, Color32::RED)
@@ -6,12 +6,13 @@
 //! its dependencies carefully.
 #![no_std]
 
-use core::{cell::UnsafeCell, ffi::CStr, fmt::Write as _, result};
+use core::{cell::UnsafeCell, ffi::CStr, fmt::Write as _, result, time::Duration};
 use maelstrom_linux::{
-    self as linux, AccessMode, CloseRangeFirst, CloseRangeFlags, CloseRangeLast, Errno, Fd,
-    FileMode, FsconfigCommand, FsmountFlags, FsopenFlags, Gid, MountAttrs, MountFlags,
-    MoveMountFlags, OpenFlags, OpenTreeFlags, OwnedFd, Sockaddr, SocketDomain, SocketProtocol,
-    SocketType, Uid, UmountFlags,
+    self as linux, AccessMode, CloneArgs, CloneFlags, CloseRangeFirst, CloseRangeFlags,
+    CloseRangeLast, Errno, Fd, FileMode, FsconfigCommand, FsmountFlags, FsopenFlags, Gid,
+    MountAttrs, MountFlags, MoveMountFlags, OpenFlags, OpenTreeFlags, OwnedFd, Pid, PollEvents,
+    PollFd, Rlimit, RlimitResource, Signal, SignalSet, SigprocmaskHow, SockFilter, Sockaddr,
+    SocketDomain, SocketProtocol, SocketType, Uid, UmountFlags, WaitResult, WaitStatus,
 };
 
 struct SliceFmt<'a> {
@@ -64,6 +65,48 @@ impl<'a> linux::AsFd for FdSlot<'a> {
     }
 }
 
+/// Where the init process started by [`Syscall::StartInit`] stores the wait status of the program
+/// it ran. This is shared with the parent, which can read it after the init process has exited.
+#[derive(Clone, Copy)]
+pub struct WaitStatusSlot<'a>(&'a UnsafeCell<Option<WaitStatus>>);
+
+impl<'a> WaitStatusSlot<'a> {
+    pub fn new(slot: &'a UnsafeCell<Option<WaitStatus>>) -> Self {
+        Self(slot)
+    }
+
+    pub fn set(&self, status: WaitStatus) {
+        let status_ptr = self.0.get();
+        unsafe { *status_ptr = Some(status) };
+    }
+
+    pub fn get(&self) -> Option<WaitStatus> {
+        let status_ptr = self.0.get();
+        unsafe { *status_ptr }
+    }
+}
+
+/// Where the init process started by [`Syscall::StartInit`] stores the number of processes the job
+/// spawned. Like [`WaitStatusSlot`], this is shared with the parent.
+#[derive(Clone, Copy)]
+pub struct ProcessCountSlot<'a>(&'a UnsafeCell<Option<u64>>);
+
+impl<'a> ProcessCountSlot<'a> {
+    pub fn new(slot: &'a UnsafeCell<Option<u64>>) -> Self {
+        Self(slot)
+    }
+
+    pub fn set(&self, count: u64) {
+        let count_ptr = self.0.get();
+        unsafe { *count_ptr = Some(count) };
+    }
+
+    pub fn get(&self) -> Option<u64> {
+        let count_ptr = self.0.get();
+        unsafe { *count_ptr }
+    }
+}
+
 /// A syscall to call. This should be part of slice, which we refer to as a script. Some variants
 /// deal with a value. This is a `usize` local variable that can be written to and read from.
 pub enum Syscall<'a> {
@@ -125,6 +168,10 @@ pub enum Syscall<'a> {
         fd: Fd,
         arg: i32,
     },
+    Listen {
+        fd: FdSlot<'a>,
+        backlog: u32,
+    },
     Mkdir {
         path: &'a CStr,
         mode: FileMode,
@@ -149,12 +196,25 @@ pub enum Syscall<'a> {
         mode: FileMode,
         out: FdSlot<'a>,
     },
+    /// Open the first of `paths` that we can execute, or `fallback` if there isn't one. This finds
+    /// the same file that [`Syscall::ExecveList`] would execute.
+    OpenList {
+        paths: &'a [&'a CStr],
+        fallback: &'a CStr,
+        flags: OpenFlags,
+        mode: FileMode,
+        out: FdSlot<'a>,
+    },
     OpenTree {
         dirfd: Fd,
         path: &'a CStr,
         flags: OpenTreeFlags,
         out: FdSlot<'a>,
     },
+    /// Open a pidfd for the calling process.
+    PidfdOpenSelf {
+        out: FdSlot<'a>,
+    },
     PivotRoot {
         new_root: &'a CStr,
         put_old: &'a CStr,
@@ -163,21 +223,62 @@ pub enum Syscall<'a> {
         fd: FdSlot<'a>,
         buf: &'a mut [u8],
     },
+    Seccomp {
+        filter: &'a [SockFilter],
+    },
+    SeccompWithListener {
+        filter: &'a [SockFilter],
+        out: FdSlot<'a>,
+    },
     SendMsg {
         buf: &'a [u8],
         fd_to_send: FdSlot<'a>,
     },
+    Sethostname {
+        name: &'a [u8],
+    },
     SetSid,
+    Setrlimit {
+        resource: RlimitResource,
+        rlimit: Rlimit,
+    },
     Socket {
         domain: SocketDomain,
         type_: SocketType,
         protocol: SocketProtocol,
         out: FdSlot<'a>,
     },
+    /// Fork, continuing the script in the child, and run a minimal init in the parent. The init
+    /// forwards signals to the child, reaps orphaned processes, and exits after the child does,
+    /// storing the child's status in `status` and the number of processes the job spawned in
+    /// `spawned`.
+    StartInit {
+        status: WaitStatusSlot<'a>,
+        spawned: ProcessCountSlot<'a>,
+    },
+    /// Clone a sidecar that runs the script in `args` on `stack`. The sidecar gets its own user,
+    /// mount, PID, IPC, and cgroup namespaces, nested in ours, but shares our network namespace.
+    /// A pidfd for the sidecar is stored in `out`.
+    StartSidecar {
+        stack: &'a mut [u8],
+        args: &'a mut ChildArgs<'a, 'a>,
+        out: FdSlot<'a>,
+    },
     Umount2 {
         path: &'a CStr,
         flags: UmountFlags,
     },
+    Unshare {
+        flags: CloneFlags,
+    },
+    /// Try to connect to `addr` every 100 milliseconds, up to `attempts` times, until it succeeds.
+    /// Fail with `ESRCH` if the process referred to by `pidfd` exits first, or with `ETIMEDOUT` if
+    /// we run out of attempts.
+    WaitUntilListening {
+        addr: &'a Sockaddr,
+        pidfd: FdSlot<'a>,
+        attempts: u32,
+    },
     Write {
         fd: FdSlot<'a>,
         buf: &'a [u8],
@@ -249,6 +350,7 @@ impl<'a> Syscall<'a> {
                 linux::mount(source, target, fstype, *flags, Some(options.as_slice()))
             }
             Syscall::IoctlTiocsctty { fd, arg } => linux::ioctl_tiocsctty(fd, *arg),
+            Syscall::Listen { fd, backlog } => linux::listen(fd, *backlog),
             Syscall::Mkdir { path, mode } => linux::mkdir(path, *mode),
             Syscall::Mount {
                 source,
@@ -273,6 +375,21 @@ impl<'a> Syscall<'a> {
                 out.set(linux::open(path, *flags, *mode).map(OwnedFd::into_fd)?);
                 Ok(())
             }
+            Syscall::OpenList {
+                paths,
+                fallback,
+                flags,
+                mode,
+                out,
+            } => {
+                let path = paths
+                    .iter()
+                    .find(|path| linux::access(path, AccessMode::X).is_ok())
+                    .copied()
+                    .unwrap_or(*fallback);
+                out.set(linux::open(path, *flags, *mode).map(OwnedFd::into_fd)?);
+                Ok(())
+            }
             Syscall::OpenTree {
                 dirfd,
                 path,
@@ -282,14 +399,31 @@ impl<'a> Syscall<'a> {
                 out.set(linux::open_tree(dirfd, path, *flags).map(OwnedFd::into_fd)?);
                 Ok(())
             }
+            Syscall::PidfdOpenSelf { out } => {
+                out.set(linux::pidfd_open(linux::getpid()).map(OwnedFd::into_fd)?);
+                Ok(())
+            }
             Syscall::PivotRoot { new_root, put_old } => linux::pivot_root(new_root, put_old),
             Syscall::Read { fd, buf } => linux::read(fd, buf).map(drop),
+            Syscall::Seccomp { filter } => {
+                linux::prctl_set_no_new_privs()?;
+                linux::seccomp_set_mode_filter(filter)
+            }
+            Syscall::SeccompWithListener { filter, out } => {
+                linux::prctl_set_no_new_privs()?;
+                out.set(
+                    linux::seccomp_set_mode_filter_with_listener(filter).map(OwnedFd::into_fd)?,
+                );
+                Ok(())
+            }
             Syscall::SendMsg { buf, fd_to_send } => {
                 let count = write_sock.send_with_fd(buf, fd_to_send.get())?;
                 assert_eq!(count, buf.len());
                 Ok(())
             }
+            Syscall::Sethostname { name } => linux::sethostname(name),
             Syscall::SetSid => linux::setsid(),
+            Syscall::Setrlimit { resource, rlimit } => linux::setrlimit(*resource, rlimit),
             Syscall::Socket {
                 domain,
                 type_,
@@ -299,12 +433,117 @@ impl<'a> Syscall<'a> {
                 out.set(linux::socket(*domain, *type_, *protocol).map(OwnedFd::into_fd)?);
                 Ok(())
             }
+            Syscall::StartInit { status, spawned } => {
+                // Block all signals before forking, so that the init won't miss any of them. The
+                // child restores its signal mask before continuing.
+                let old_mask = linux::sigprocmask(SigprocmaskHow::BLOCK, Some(&SignalSet::full()))?;
+                let mut clone_args = CloneArgs::default().exit_signal(Signal::CHLD);
+                match linux::clone3(&mut clone_args)? {
+                    None => linux::sigprocmask(SigprocmaskHow::SETMASK, Some(&old_mask)).map(drop),
+                    Some(child) => run_init(child, *status, *spawned),
+                }
+            }
+            Syscall::StartSidecar { stack, args, out } => {
+                let mut clone_args = CloneArgs::default().flags(
+                    CloneFlags::NEWCGROUP
+                        | CloneFlags::NEWIPC
+                        | CloneFlags::NEWNS
+                        | CloneFlags::NEWPID
+                        | CloneFlags::NEWUSER
+                        | CloneFlags::VM,
+                );
+                let stack_ptr = stack.as_mut_ptr().wrapping_add(stack.len());
+                let (_, pidfd) = unsafe {
+                    linux::clone_with_child_pidfd(
+                        start_and_exec_in_child_trampoline,
+                        stack_ptr as *mut _,
+                        &mut **args as *mut ChildArgs as *mut _,
+                        &mut clone_args,
+                    )
+                }?;
+                out.set(pidfd.into_fd());
+                Ok(())
+            }
             Syscall::Umount2 { path, flags } => linux::umount2(path, *flags),
+            Syscall::Unshare { flags } => linux::unshare(*flags),
+            Syscall::WaitUntilListening {
+                addr,
+                pidfd,
+                attempts,
+            } => {
+                for _ in 0..*attempts {
+                    let sock = linux::socket(
+                        SocketDomain::INET,
+                        SocketType::STREAM | SocketType::CLOEXEC,
+                        SocketProtocol::TCP,
+                    )?;
+                    if linux::connect(&sock, addr).is_ok() {
+                        return Ok(());
+                    }
+                    drop(sock);
+                    let mut fds = [PollFd::new(pidfd.get(), PollEvents::IN)];
+                    if linux::poll(&mut fds, Duration::from_millis(100))? > 0 {
+                        return Err(Errno::ESRCH);
+                    }
+                }
+                Err(Errno::ETIMEDOUT)
+            }
             Syscall::Write { fd, buf } => linux::write(fd, buf).map(drop),
         }
     }
 }
 
+/// Run as PID 1 of the job's PID namespace until `child` terminates. This is only ever called from
+/// [`Syscall::StartInit`], with all signals blocked.
+fn run_init(child: Pid, status: WaitStatusSlot, spawned: ProcessCountSlot) -> ! {
+    // We don't need any of the file descriptors above stderr. In particular, we need to close the
+    // exec result socket so that the worker can tell when the child has exec-ed.
+    let _ = linux::close_range(
+        CloseRangeFirst::AfterStderr,
+        CloseRangeLast::Max,
+        CloseRangeFlags::default(),
+    );
+    let signals = SignalSet::full();
+    loop {
+        // Reap everything that has terminated. Orphaned processes are reparented to us, so they
+        // end up here too.
+        while let Ok(Some(WaitResult {
+            pid,
+            status: wait_status,
+        })) = linux::try_wait()
+        {
+            if pid == child {
+                // When we exit, the kernel will kill whatever is left in the PID namespace.
+                status.set(wait_status);
+                if let Some(count) = count_spawned(child) {
+                    spawned.set(count);
+                }
+                linux::_exit(linux::ExitCode::from_u8(0));
+            }
+        }
+        match linux::sigwait(&signals) {
+            Ok(Signal::CHLD) | Err(_) => {}
+            Ok(signal) => {
+                let _ = linux::kill(child, signal);
+            }
+        }
+    }
+}
+
+/// Return the number of processes, including threads, started in our PID namespace since `child`,
+/// counting `child` itself.
+///
+/// The kernel hands out PIDs in a namespace in increasing order, so we can tell how many have been
+/// used by starting one more process and looking at its PID. This is only wrong if the job used
+/// so many PIDs that they wrapped around, in which case we may return `None`.
+fn count_spawned(child: Pid) -> Option<u64> {
+    match linux::clone3(&mut CloneArgs::default()) {
+        Ok(None) => linux::_exit(linux::ExitCode::from_u8(0)),
+        Ok(Some(probe)) => u64::try_from(probe.as_i32() - child.as_i32()).ok(),
+        Err(_) => None,
+    }
+}
+
 /// The guts of the child code. This function shouldn't return on success, because in that case,
 /// the last syscall should be an execve. If this function returns, than an error was encountered.
 /// In that case, the script item index and the errno will be returned.
@@ -324,15 +563,19 @@ fn start_and_exec_in_child_inner(
 ///
 /// It is assumed that the last syscall won't return (i.e. will be `execve`). If there is an error,
 /// write an 8-byte value to `write_sock` describing the error in little-endian format.
-/// The upper 32 bits will be the index in the script of the syscall that errored, and the lower 32
-/// bits will be the errno value.
+/// The upper 32 bits will be the index in the script of the syscall that errored, plus
+/// `index_offset`, and the lower 32 bits will be the errno value.
 ///
 /// The caller should ensure that `write_sock` is marked close-on-exec. This way, upon
 /// normal completion, no bytes will be written to the file descriptor and the worker can
 /// distinguish between an error and no error.
-pub fn start_and_exec_in_child(write_sock: linux::UnixStream, syscalls: &mut [Syscall]) -> ! {
+pub fn start_and_exec_in_child(
+    write_sock: linux::UnixStream,
+    syscalls: &mut [Syscall],
+    index_offset: usize,
+) -> ! {
     let (index, errno) = start_and_exec_in_child_inner(&write_sock, syscalls);
-    let result = (index as u64) << 32 | errno.as_u64();
+    let result = ((index + index_offset) as u64) << 32 | errno.as_u64();
     // There's not really much to do if this write fails. Therefore, we just ignore the result.
     // However, it's hard to imagine any case where this could fail and we'd actually care.
     let _ = write_sock.send(result.to_ne_bytes().as_slice());
@@ -342,6 +585,9 @@ pub fn start_and_exec_in_child(write_sock: linux::UnixStream, syscalls: &mut [Sy
 pub struct ChildArgs<'a, 'b> {
     pub write_sock: linux::Fd,
     pub syscalls: &'a mut [Syscall<'b>],
+    /// Added to the index of the failing syscall when reporting an error. This lets the parent
+    /// tell which script an error came from when several share `write_sock`.
+    pub index_offset: usize,
 }
 
 pub extern "C" fn start_and_exec_in_child_trampoline(arg: *mut core::ffi::c_void) -> i32 {
@@ -349,5 +595,6 @@ pub extern "C" fn start_and_exec_in_child_trampoline(arg: *mut core::ffi::c_void
     start_and_exec_in_child(
         linux::OwnedFd::from_fd(args.write_sock).into(),
         args.syscalls,
+        args.index_offset,
     )
 }
@@ -0,0 +1,132 @@
+//! Serve the blobs in the worker's cache to other workers' artifact fetchers. The broker sends
+//! fetchers here for artifacts we've told it we've fetched.
+//!
+//! We don't coordinate with the cache. If a blob has been evicted, we tell the fetcher, and it goes
+//! back to the broker. If we happen to send a blob that isn't completely written, the fetcher will
+//! notice when it verifies the digest.
+
+use crate::{dispatcher::Message, DispatcherSender};
+use anyhow::{anyhow, Result};
+use maelstrom_base::proto::{ArtifactFetcherToWorker, WorkerToArtifactFetcher};
+use maelstrom_layer_fs::BlobDir;
+use maelstrom_util::{
+    fs::{File, Fs},
+    net,
+    root::RootBuf,
+};
+use slog::{debug, error, o, Logger};
+use std::{
+    io::{self, Read as _},
+    net::TcpStream,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::Semaphore};
+
+/// The maximum number of fetchers we serve at once. Once we're at the limit, further connections
+/// wait in the listen backlog until an existing one finishes.
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long a fetcher may leave a connection idle, or stop reading what we send it, before we
+/// close the connection. This keeps stuck fetchers from holding on to connections forever.
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn get_file<'fs>(
+    fs: &'fs Fs,
+    blob_dir: &RootBuf<BlobDir>,
+    msg: &ArtifactFetcherToWorker,
+) -> Result<(File<'fs>, u64)> {
+    let ArtifactFetcherToWorker(digest) = msg;
+    let file = fs.open_file(blob_dir.join::<()>(digest.to_string()))?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn handle_one_message(
+    msg: ArtifactFetcherToWorker,
+    socket: &mut TcpStream,
+    blob_dir: &RootBuf<BlobDir>,
+    log: &mut Logger,
+) -> Result<()> {
+    debug!(log, "received artifact fetcher message"; "msg" => ?msg);
+    let fs = Fs::new();
+    let result = get_file(&fs, blob_dir, &msg);
+    let msg = WorkerToArtifactFetcher(
+        result
+            .as_ref()
+            .map(|(_, size)| *size)
+            .map_err(|e| e.to_string()),
+    );
+    debug!(log, "sending artifact fetcher message"; "msg" => ?msg);
+    net::write_message_to_socket(socket, msg)?;
+
+    // If the file is still being written, it may grow while we're sending it. Make sure we only
+    // send what we said we would.
+    let (file, size) = result?;
+    let copied = io::copy(&mut (&file).take(size), socket)?;
+    if copied != size {
+        return Err(anyhow!("artifact shrank while sending it"));
+    }
+
+    Ok(())
+}
+
+fn connection_main(mut socket: TcpStream, blob_dir: RootBuf<BlobDir>, mut log: Logger) {
+    debug!(log, "artifact fetcher connected");
+    let err = loop {
+        let result = net::read_message_from_socket(&mut socket)
+            .and_then(|msg| handle_one_message(msg, &mut socket, &blob_dir, &mut log));
+        if let Err(err) = result {
+            break err;
+        }
+    };
+    debug!(log, "artifact fetcher disconnected"; "err" => %err);
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    blob_dir: RootBuf<BlobDir>,
+    log: &Logger,
+) -> Result<()> {
+    let semaphore = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        let permit = semaphore.clone().acquire_owned().await?;
+        let (socket, peer_addr) = listener.accept().await.map_err(|err| {
+            error!(log, "error accepting artifact server connection"; "err" => %err);
+            err
+        })?;
+        let log = log.new(o!("peer_addr" => peer_addr));
+        let socket = socket.into_std()?;
+        socket.set_nonblocking(false)?;
+        socket.set_read_timeout(Some(IO_TIMEOUT))?;
+        socket.set_write_timeout(Some(IO_TIMEOUT))?;
+        let blob_dir = blob_dir.clone();
+        thread::spawn(move || {
+            connection_main(socket, blob_dir, log);
+            drop(permit);
+        });
+    }
+}
+
+/// Main loop for the artifact server. This should be run on a task of its own. It will only
+/// return when it encounters an error. Each connection is handled on a thread of its own, with at
+/// most [`MAX_CONNECTIONS`] at once.
+///
+/// The dispatcher is told when the server starts and stops, so that it only tells the broker about
+/// artifacts we can actually serve.
+pub async fn main(
+    listener: TcpListener,
+    blob_dir: RootBuf<BlobDir>,
+    dispatcher_sender: DispatcherSender,
+    log: Logger,
+) -> Result<()> {
+    dispatcher_sender
+        .send(Message::ArtifactServerRunning(true))
+        .ok();
+    let result = accept_connections(listener, blob_dir, &log).await;
+    dispatcher_sender
+        .send(Message::ArtifactServerRunning(false))
+        .ok();
+    result
+}
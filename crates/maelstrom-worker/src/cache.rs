@@ -5,15 +5,20 @@ use maelstrom_base::{JobId, Sha256Digest};
 use maelstrom_util::{
     config::common::CacheSize,
     heap::{Heap, HeapDeps, HeapIndex},
+    io::Sha256Stream,
     root::RootBuf,
 };
 use slog::{debug, Logger};
 use std::{
     cmp::Ordering,
     collections::{hash_map::Entry as HashEntry, HashMap},
-    fmt, fs, mem,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    mem,
     num::NonZeroU32,
     ops::{Deref, DerefMut},
+    os::unix::ffi::OsStrExt as _,
     path::{Path, PathBuf},
     thread,
 };
@@ -45,6 +50,22 @@ pub trait Fs {
     /// Return and iterator that will yield all of the children of a directory. Panic on file
     /// system error or if `path` doesn't exist or isn't a directory.
     fn read_dir(&self, path: &Path) -> Box<dyn Iterator<Item = PathBuf>>;
+
+    /// Return the contents of the file at `path`, or `None` if it doesn't exist. Panic on any
+    /// other file system error.
+    fn read_file(&self, path: &Path) -> Option<String>;
+
+    /// Atomically replace the contents of the file at `path` with `contents`. Panic on file system
+    /// error.
+    fn write_file(&mut self, path: &Path, contents: &str);
+
+    /// Append `contents` to the file at `path`, creating it if it doesn't exist. Panic on file
+    /// system error.
+    fn append_to_file(&mut self, path: &Path, contents: &str);
+
+    /// Return the [`EntryStamp`] of the file or directory at `path`, or `None` if it can't be
+    /// read.
+    fn stamp(&self, path: &Path) -> Option<EntryStamp>;
 }
 
 /// The standard implementation of CacheFs that uses [std] and [rand].
@@ -80,6 +101,85 @@ impl Fs for StdFs {
     fn read_dir(&self, path: &Path) -> Box<dyn Iterator<Item = PathBuf>> {
         Box::new(fs::read_dir(path).unwrap().map(|de| de.unwrap().path()))
     }
+
+    fn read_file(&self, path: &Path) -> Option<String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Some(contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => panic!("error reading {}: {err}", path.display()),
+        }
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &str) {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, contents).unwrap();
+        fs::rename(&temp, path).unwrap();
+    }
+
+    fn append_to_file(&mut self, path: &Path, contents: &str) {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .unwrap();
+    }
+
+    fn stamp(&self, path: &Path) -> Option<EntryStamp> {
+        EntryStamp::read(path).ok()
+    }
+}
+
+/// A fingerprint of an entry on disk: how many bytes it uses and a SHA-256 checksum of its
+/// contents. It's taken by whatever filled in the entry, once the entry is complete, and recorded
+/// in the cache's journal. When the cache is restarted, an entry is only kept if it still has the
+/// same stamp.
+///
+/// For a directory, the stamp covers the files directly in it, which is all that a layer-fs layer
+/// has. The checksum covers each file's name, size, and contents, in order of name.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EntryStamp {
+    pub bytes_used: u64,
+    pub checksum: Sha256Digest,
+}
+
+impl EntryStamp {
+    /// Take the stamp of the file or directory at `path`. This reads all of the entry's contents.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        fn hash_file(path: &Path, hasher: &mut Sha256Stream<io::Sink>) -> io::Result<u64> {
+            io::copy(&mut File::open(path)?, hasher)
+        }
+
+        let mut hasher = Sha256Stream::new(io::sink());
+        if !fs::metadata(path)?.is_dir() {
+            let bytes_used = hash_file(path, &mut hasher)?;
+            let (_, checksum) = hasher.finalize();
+            return Ok(EntryStamp {
+                bytes_used,
+                checksum,
+            });
+        }
+        let mut children = fs::read_dir(path)?
+            .map(|child| child.map(|child| child.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        children.sort();
+        let mut bytes_used = 0;
+        for child in children {
+            let metadata = fs::metadata(&child)?;
+            hasher.write_all(child.file_name().unwrap_or_default().as_bytes())?;
+            hasher.write_all(&metadata.len().to_le_bytes())?;
+            if metadata.is_file() {
+                hash_file(&child, &mut hasher)?;
+            }
+            bytes_used += metadata.len();
+        }
+        let (_, checksum) = hasher.finalize();
+        Ok(EntryStamp {
+            bytes_used,
+            checksum,
+        })
+    }
 }
 
 /// Type returned from [Cache::get_artifact].
@@ -125,7 +225,46 @@ impl fmt::Display for EntryKind {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A record in the cache's journal. One of these is appended to the journal each time an entry is
+/// successfully added to the cache. They are used to rebuild the cache when it's restarted.
+///
+/// A record may outlive the entry it describes, since we don't record removals. That's fine, since
+/// an entry's record is only used if the entry exists and matches the record's stamp. The stale
+/// records are dropped when the journal is compacted.
+struct JournalRecord {
+    key: Key,
+    stamp: EntryStamp,
+}
+
+impl JournalRecord {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next()?;
+        let kind = EntryKind::iter().find(|k| k.to_string() == kind)?;
+        let digest = fields.next()?.parse().ok()?;
+        let bytes_used = fields.next()?.parse().ok()?;
+        let checksum = fields.next()?.parse().ok()?;
+        fields.next().is_none().then_some(JournalRecord {
+            key: Key::new(kind, digest),
+            stamp: EntryStamp {
+                bytes_used,
+                checksum,
+            },
+        })
+    }
+}
+
+impl fmt::Display for JournalRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} {} {}",
+            self.key.kind, self.key.digest, self.stamp.bytes_used, self.stamp.checksum
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Key {
     pub kind: EntryKind,
     pub digest: Sha256Digest,
@@ -149,7 +288,7 @@ enum Entry {
     /// currently being used by at least one job. We reference count this state since there may be
     /// multiple jobs using the same artifact.
     InUse {
-        bytes_used: u64,
+        stamp: EntryStamp,
         ref_count: NonZeroU32,
     },
 
@@ -157,7 +296,7 @@ enum Entry {
     /// currently using it. The `priority` is provided by [Cache] and is used by the [Heap] to
     /// determine which entry should be removed first when freeing up space.
     InHeap {
-        bytes_used: u64,
+        stamp: EntryStamp,
         priority: u64,
         heap_index: HeapIndex,
     },
@@ -206,6 +345,10 @@ impl HeapDeps for Map {
 
 pub struct CacheDir;
 
+/// The journal is never compacted while it's smaller than this. Past this, it's compacted whenever
+/// it grows to twice the size it had after it was last compacted.
+const JOURNAL_COMPACTION_THRESHOLD: u64 = 1024 * 1024;
+
 /// Manage a directory of downloaded, extracted artifacts. Coordinate fetching of these artifacts,
 /// and removing them when they are no longer in use and the amount of space used by the directory
 /// has grown too large.
@@ -217,14 +360,23 @@ pub struct Cache<FsT> {
     next_priority: u64,
     bytes_used: u64,
     bytes_used_target: u64,
+    journal_bytes: u64,
+    journal_bytes_after_compaction: u64,
+    journal_compaction_threshold: u64,
     log: Logger,
 }
 
 impl<FsT: Fs> Cache<FsT> {
     /// Create a new [Cache] rooted at `root`. The directory `root` and all necessary ancestors
     /// will be created, along with `{root}/removing` and `{root}/{kind}/sha256`. Any pre-existing
-    /// entries in `{root}/removing` and `{root}/{kind}/sha256` will be removed. That implies that
-    /// the [Cache] doesn't currently keep data stored across invocations.
+    /// entries in `{root}/removing` will be removed.
+    ///
+    /// Pre-existing entries in `{root}/{kind}/sha256` are kept if they have a record in
+    /// `{root}/journal` and still match the [`EntryStamp`] in that record. Otherwise, they were
+    /// either left unfinished or have been changed since, and are removed. This reads every entry
+    /// to verify its checksum. A blob's checksum also has to match its digest, since a blob's
+    /// digest is the digest of its contents. The kept entries aren't in use, so they can be removed
+    /// to make space in the order they were originally added.
     ///
     /// `bytes_used_target` is the goal on-disk size for the cache. The cache will periodically grow
     /// larger than this size, but then shrink back down to this size. Ideally, the cache would use
@@ -240,17 +392,51 @@ impl<FsT: Fs> Cache<FsT> {
         }
         path.pop();
 
+        // Later records for the same key supersede earlier ones.
+        let journal_path = Self::journal_path(&root);
+        let mut records: HashMap<Key, (usize, JournalRecord)> = fs
+            .read_file(&journal_path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(JournalRecord::parse)
+            .enumerate()
+            .map(|(index, record)| (record.key.clone(), (index, record)))
+            .collect();
+
+        let mut kept = vec![];
         for kind in EntryKind::iter() {
             let mut path = path.clone();
             path.push(kind.to_string());
             path.push("sha256");
-            if fs.file_exists(&path) {
-                Self::remove_in_background(&mut fs, &root, &path);
-            }
             fs.mkdir_recursively(&path);
+            for child in fs.read_dir(&path) {
+                let record = child
+                    .file_name()
+                    .and_then(|name| name.to_str()?.parse().ok())
+                    .and_then(|digest| records.remove(&Key::new(kind, digest)));
+                match record {
+                    Some(record)
+                        if (kind != EntryKind::Blob
+                            || record.1.stamp.checksum == record.1.key.digest)
+                            && fs.stamp(&child).as_ref() == Some(&record.1.stamp) =>
+                    {
+                        kept.push(record);
+                    }
+                    _ => {
+                        debug!(log, "cache removing unfinished or damaged entry"; "path" => ?child);
+                        Self::remove_in_background(&mut fs, &root, &child);
+                    }
+                }
+            }
         }
 
-        Cache {
+        // Rewrite the journal so that it only has records for the entries we kept.
+        kept.sort_by_key(|(index, _)| *index);
+        let journal: String = kept.iter().map(|(_, record)| record.to_string()).collect();
+        fs.write_file(&journal_path, &journal);
+        let journal_bytes = journal.len() as u64;
+
+        let mut cache = Cache {
             fs,
             root,
             entries: Map::default(),
@@ -258,8 +444,32 @@ impl<FsT: Fs> Cache<FsT> {
             next_priority: 0,
             bytes_used: 0,
             bytes_used_target: size.into(),
+            journal_bytes,
+            journal_bytes_after_compaction: journal_bytes,
+            journal_compaction_threshold: JOURNAL_COMPACTION_THRESHOLD,
             log,
+        };
+        for (_, record) in kept {
+            let bytes_used = record.stamp.bytes_used;
+            cache.entries.insert(
+                record.key.clone(),
+                Entry::InHeap {
+                    stamp: record.stamp,
+                    priority: cache.next_priority,
+                    heap_index: HeapIndex::default(),
+                },
+            );
+            cache.heap.push(&mut cache.entries, record.key);
+            cache.next_priority = cache.next_priority.checked_add(1).unwrap();
+            cache.bytes_used = cache.bytes_used.checked_add(bytes_used).unwrap();
         }
+        debug!(cache.log, "cache restored entries";
+            "entries" => %cache.entries.len(),
+            "bytes_used" => %ByteSize::b(cache.bytes_used),
+            "byte_used_target" => %ByteSize::b(cache.bytes_used_target)
+        );
+        cache.possibly_remove_some();
+        cache
     }
 
     /// Attempt to fetch `artifact` from the cache. See [`GetArtifact`] for the meaning of the
@@ -289,14 +499,12 @@ impl<FsT: Fs> Cache<FsT> {
                         GetArtifact::Success(cache_path)
                     }
                     Entry::InHeap {
-                        bytes_used,
-                        heap_index,
-                        ..
+                        stamp, heap_index, ..
                     } => {
                         let heap_index = *heap_index;
                         *entry = Entry::InUse {
                             ref_count: NonZeroU32::new(1).unwrap(),
-                            bytes_used: *bytes_used,
+                            stamp: stamp.clone(),
                         };
                         self.heap.remove(&mut self.entries, heap_index);
                         GetArtifact::Success(cache_path)
@@ -320,13 +528,14 @@ impl<FsT: Fs> Cache<FsT> {
         jobs
     }
 
-    /// Notify the cache that an artifact fetch has successfully completed. The returned vector
-    /// lists the jobs that are affected, and the path they can use to access the artifact.
+    /// Notify the cache that an artifact fetch has successfully completed. `stamp` should be taken
+    /// once the entry is complete. The returned vector lists the jobs that are affected, and the
+    /// path they can use to access the artifact.
     pub fn got_artifact_success(
         &mut self,
         kind: EntryKind,
         digest: &Sha256Digest,
-        bytes_used: u64,
+        stamp: EntryStamp,
     ) -> (PathBuf, Vec<JobId>) {
         let key = Key::new(kind, digest.clone());
         let entry = self
@@ -339,11 +548,20 @@ impl<FsT: Fs> Cache<FsT> {
         let ref_count = jobs.len().try_into().unwrap();
         let jobs = mem::take(jobs);
         // Reference count must be > 0 since we don't allow cancellation of gets.
+        let bytes_used = stamp.bytes_used;
         *entry = Entry::InUse {
-            bytes_used,
+            stamp: stamp.clone(),
             ref_count: NonZeroU32::new(ref_count).unwrap(),
         };
         self.bytes_used = self.bytes_used.checked_add(bytes_used).unwrap();
+
+        // Record the entry in the journal so we can keep it if we're restarted.
+        let cache_path = Self::cache_path(&self.root, &key);
+        let record = JournalRecord { key, stamp }.to_string();
+        self.fs
+            .append_to_file(&Self::journal_path(&self.root), &record);
+        self.journal_bytes += record.len() as u64;
+
         debug!(self.log, "cache added artifact";
             "kind" => ?kind,
             "digest" => %digest,
@@ -353,7 +571,8 @@ impl<FsT: Fs> Cache<FsT> {
             "byte_used_target" => %ByteSize::b(self.bytes_used_target)
         );
         self.possibly_remove_some();
-        (Self::cache_path(&self.root, &key), jobs)
+        self.possibly_compact_journal();
+        (cache_path, jobs)
     }
 
     /// Notify the cache that a reference to an artifact is no longer needed.
@@ -363,18 +582,14 @@ impl<FsT: Fs> Cache<FsT> {
             .entries
             .get_mut(&key)
             .expect("Got decrement_ref_count in unexpected state");
-        let Entry::InUse {
-            bytes_used,
-            ref_count,
-        } = entry
-        else {
+        let Entry::InUse { stamp, ref_count } = entry else {
             panic!("Got decrement_ref_count with existing zero reference count");
         };
         match NonZeroU32::new(ref_count.get() - 1) {
             Some(new_ref_count) => *ref_count = new_ref_count,
             None => {
                 *entry = Entry::InHeap {
-                    bytes_used: *bytes_used,
+                    stamp: stamp.clone(),
                     priority: self.next_priority,
                     heap_index: HeapIndex::default(),
                 };
@@ -402,6 +617,54 @@ impl<FsT: Fs> Cache<FsT> {
         fs.remove_recursively_on_thread(target);
     }
 
+    /// Check to see if the journal has grown too large, and if so, rewrite it so that it only has
+    /// records for the entries currently in the cache. The records are written in the order the
+    /// entries would be removed, with the entries in use last, so that a restarted cache removes
+    /// them in about the same order we would have.
+    fn possibly_compact_journal(&mut self) {
+        let limit = self
+            .journal_compaction_threshold
+            .max(self.journal_bytes_after_compaction.saturating_mul(2));
+        if self.journal_bytes <= limit {
+            return;
+        }
+        let mut records: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|(key, entry)| match entry {
+                Entry::DownloadingAndExtracting(_) => None,
+                Entry::InHeap {
+                    stamp, priority, ..
+                } => Some(((false, *priority), key, stamp)),
+                Entry::InUse { stamp, .. } => Some(((true, 0), key, stamp)),
+            })
+            .collect();
+        records.sort();
+        let journal: String = records
+            .into_iter()
+            .map(|(_, key, stamp)| {
+                let record = JournalRecord {
+                    key: key.clone(),
+                    stamp: stamp.clone(),
+                };
+                record.to_string()
+            })
+            .collect();
+        self.fs
+            .write_file(&Self::journal_path(&self.root), &journal);
+        debug!(self.log, "cache compacted journal";
+            "old_size" => %ByteSize::b(self.journal_bytes),
+            "new_size" => %ByteSize::b(journal.len() as u64)
+        );
+        self.journal_bytes = journal.len() as u64;
+        self.journal_bytes_after_compaction = self.journal_bytes;
+    }
+
+    /// Return the path of the cache's journal.
+    fn journal_path(root: &Path) -> PathBuf {
+        root.join("journal")
+    }
+
     /// Return the directory path for the artifact referenced by `digest`.
     fn cache_path(root: &Path, key: &Key) -> PathBuf {
         let mut path = root.to_owned();
@@ -418,9 +681,10 @@ impl<FsT: Fs> Cache<FsT> {
             let Some(key) = self.heap.pop(&mut self.entries) else {
                 break;
             };
-            let Some(Entry::InHeap { bytes_used, .. }) = self.entries.remove(&key) else {
+            let Some(Entry::InHeap { stamp, .. }) = self.entries.remove(&key) else {
                 panic!("Entry popped off of heap was in unexpected state");
             };
+            let bytes_used = stamp.bytes_used;
             Self::remove_in_background(
                 &mut self.fs,
                 &self.root,
@@ -449,6 +713,7 @@ impl<FsT: Fs> Cache<FsT> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assert_matches::assert_matches;
     use itertools::Itertools;
     use maelstrom_test::*;
     use slog::{o, Discard};
@@ -462,6 +727,10 @@ mod tests {
         RemoveRecursively(PathBuf),
         MkdirRecursively(PathBuf),
         ReadDir(PathBuf),
+        ReadFile(PathBuf),
+        WriteFile(PathBuf, String),
+        AppendToFile(PathBuf, String),
+        Stamp(PathBuf),
     }
 
     #[derive(Default)]
@@ -469,6 +738,8 @@ mod tests {
         messages: Rc<RefCell<Vec<TestMessage>>>,
         existing_files: HashSet<PathBuf>,
         directories: HashMap<PathBuf, Vec<PathBuf>>,
+        files: HashMap<PathBuf, String>,
+        stamps: HashMap<PathBuf, EntryStamp>,
         last_random_number: u64,
     }
 
@@ -511,6 +782,39 @@ mod tests {
                     .into_iter(),
             )
         }
+
+        fn read_file(&self, path: &Path) -> Option<String> {
+            self.messages.borrow_mut().push(ReadFile(path.to_owned()));
+            self.files.get(path).cloned()
+        }
+
+        fn write_file(&mut self, path: &Path, contents: &str) {
+            self.messages
+                .borrow_mut()
+                .push(WriteFile(path.to_owned(), contents.to_owned()));
+        }
+
+        fn append_to_file(&mut self, path: &Path, contents: &str) {
+            self.messages
+                .borrow_mut()
+                .push(AppendToFile(path.to_owned(), contents.to_owned()));
+        }
+
+        fn stamp(&self, path: &Path) -> Option<EntryStamp> {
+            self.messages.borrow_mut().push(Stamp(path.to_owned()));
+            self.stamps.get(path).cloned()
+        }
+    }
+
+    fn stamp(bytes_used: u64, checksum: u64) -> EntryStamp {
+        EntryStamp {
+            bytes_used,
+            checksum: digest!(checksum),
+        }
+    }
+
+    fn journal_line(kind: EntryKind, digest: Sha256Digest, stamp: EntryStamp) -> String {
+        format!("{kind} {digest} {} {}\n", stamp.bytes_used, stamp.checksum)
     }
 
     struct Fixture {
@@ -589,12 +893,16 @@ mod tests {
             digest: Sha256Digest,
             bytes_used: u64,
             expected: (PathBuf, Vec<JobId>),
-            expected_fs_operations: Vec<TestMessage>,
+            mut expected_fs_operations: Vec<TestMessage>,
         ) {
-            let result = self
-                .cache
-                .got_artifact_success(EntryKind::Blob, &digest, bytes_used);
+            let result =
+                self.cache
+                    .got_artifact_success(EntryKind::Blob, &digest, stamp(bytes_used, 0));
             assert_eq!(result, expected);
+            expected_fs_operations.push(AppendToFile(
+                path_buf!("/z/journal"),
+                journal_line(EntryKind::Blob, digest, stamp(bytes_used, 0)),
+            ));
             self.expect_messages_in_any_order(expected_fs_operations);
         }
 
@@ -611,7 +919,7 @@ mod tests {
 
         fn got_artifact_success_ign(&mut self, digest: Sha256Digest, bytes_used: u64) {
             self.cache
-                .got_artifact_success(EntryKind::Blob, &digest, bytes_used);
+                .got_artifact_success(EntryKind::Blob, &digest, stamp(bytes_used, 0));
             self.clear_messages();
         }
 
@@ -1004,12 +1312,14 @@ mod tests {
         fixture.expect_messages_in_specific_order(vec![
             MkdirRecursively(path_buf!("/z/removing")),
             ReadDir(path_buf!("/z/removing")),
-            FileExists(path_buf!("/z/blob/sha256")),
+            ReadFile(path_buf!("/z/journal")),
             MkdirRecursively(path_buf!("/z/blob/sha256")),
-            FileExists(path_buf!("/z/bottom_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/blob/sha256")),
             MkdirRecursively(path_buf!("/z/bottom_fs_layer/sha256")),
-            FileExists(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/bottom_fs_layer/sha256")),
             MkdirRecursively(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/upper_fs_layer/sha256")),
+            WriteFile(path_buf!("/z/journal"), "".into()),
         ]);
     }
 
@@ -1029,52 +1339,357 @@ mod tests {
             ReadDir(path_buf!("/z/removing")),
             RemoveRecursively(short_path!("/z/removing", 10)),
             RemoveRecursively(short_path!("/z/removing", 20)),
-            FileExists(path_buf!("/z/blob/sha256")),
+            ReadFile(path_buf!("/z/journal")),
             MkdirRecursively(path_buf!("/z/blob/sha256")),
-            FileExists(path_buf!("/z/bottom_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/blob/sha256")),
             MkdirRecursively(path_buf!("/z/bottom_fs_layer/sha256")),
-            FileExists(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/bottom_fs_layer/sha256")),
             MkdirRecursively(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/upper_fs_layer/sha256")),
+            WriteFile(path_buf!("/z/journal"), "".into()),
         ]);
     }
 
     #[test]
-    fn new_removes_old_sha256_if_it_exists() {
+    fn new_keeps_entries_with_matching_journal_records() {
         let mut test_cache_fs = TestFs::default();
+        test_cache_fs.files.insert(
+            path_buf!("/z/journal"),
+            [
+                journal_line(EntryKind::UpperFsLayer, digest!(3), stamp(30, 33)),
+                journal_line(EntryKind::Blob, digest!(1), stamp(10, 1)),
+                "garbage\n".into(),
+                journal_line(EntryKind::BottomFsLayer, digest!(2), stamp(20, 22)),
+            ]
+            .concat(),
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/blob/sha256"),
+            vec![long_path!("/z/blob/sha256", 1)],
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/bottom_fs_layer/sha256"),
+            vec![long_path!("/z/bottom_fs_layer/sha256", 2)],
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/upper_fs_layer/sha256"),
+            vec![long_path!("/z/upper_fs_layer/sha256", 3)],
+        );
         test_cache_fs
-            .existing_files
-            .insert(path_buf!("/z/blob/sha256"));
+            .stamps
+            .insert(long_path!("/z/blob/sha256", 1), stamp(10, 1));
         test_cache_fs
-            .existing_files
-            .insert(path_buf!("/z/bottom_fs_layer/sha256"));
+            .stamps
+            .insert(long_path!("/z/bottom_fs_layer/sha256", 2), stamp(20, 22));
         test_cache_fs
-            .existing_files
-            .insert(path_buf!("/z/upper_fs_layer/sha256"));
+            .stamps
+            .insert(long_path!("/z/upper_fs_layer/sha256", 3), stamp(30, 33));
+        let mut fixture = Fixture::new(test_cache_fs, 1000);
+        fixture.expect_messages_in_specific_order(vec![
+            MkdirRecursively(path_buf!("/z/removing")),
+            ReadDir(path_buf!("/z/removing")),
+            ReadFile(path_buf!("/z/journal")),
+            MkdirRecursively(path_buf!("/z/blob/sha256")),
+            ReadDir(path_buf!("/z/blob/sha256")),
+            Stamp(long_path!("/z/blob/sha256", 1)),
+            MkdirRecursively(path_buf!("/z/bottom_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/bottom_fs_layer/sha256")),
+            Stamp(long_path!("/z/bottom_fs_layer/sha256", 2)),
+            MkdirRecursively(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/upper_fs_layer/sha256")),
+            Stamp(long_path!("/z/upper_fs_layer/sha256", 3)),
+            WriteFile(
+                path_buf!("/z/journal"),
+                [
+                    journal_line(EntryKind::UpperFsLayer, digest!(3), stamp(30, 33)),
+                    journal_line(EntryKind::Blob, digest!(1), stamp(10, 1)),
+                    journal_line(EntryKind::BottomFsLayer, digest!(2), stamp(20, 22)),
+                ]
+                .concat(),
+            ),
+        ]);
+
+        fixture.get_artifact(
+            digest!(1),
+            jid!(1),
+            GetArtifact::Success(long_path!("/z/blob/sha256", 1)),
+        );
+        assert_eq!(
+            fixture
+                .cache
+                .get_artifact(EntryKind::BottomFsLayer, digest!(2), jid!(1)),
+            GetArtifact::Success(long_path!("/z/bottom_fs_layer/sha256", 2)),
+        );
+
+        // The restored entries count towards the cache's size, and can be removed to make space.
+        fixture.get_artifact_ign(digest!(4), jid!(2));
+        fixture.got_artifact_success(
+            digest!(4),
+            950,
+            (long_path!("/z/blob/sha256", 4), vec![jid!(2)]),
+            vec![
+                FileExists(short_path!("/z/removing", 1)),
+                Rename(
+                    long_path!("/z/upper_fs_layer/sha256", 3),
+                    short_path!("/z/removing", 1),
+                ),
+                RemoveRecursively(short_path!("/z/removing", 1)),
+            ],
+        );
+    }
+
+    #[test]
+    fn new_removes_unfinished_and_damaged_entries() {
+        let mut test_cache_fs = TestFs::default();
+        test_cache_fs.files.insert(
+            path_buf!("/z/journal"),
+            [
+                journal_line(EntryKind::Blob, digest!(1), stamp(10, 1)),
+                journal_line(EntryKind::BottomFsLayer, digest!(2), stamp(20, 22)),
+                journal_line(EntryKind::Blob, digest!(4), stamp(40, 4)),
+            ]
+            .concat(),
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/blob/sha256"),
+            vec![
+                long_path!("/z/blob/sha256", 1),
+                long_path!("/z/blob/sha256", 3),
+                path_buf!("/z/blob/sha256/foo"),
+            ],
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/bottom_fs_layer/sha256"),
+            vec![long_path!("/z/bottom_fs_layer/sha256", 2)],
+        );
+        test_cache_fs
+            .stamps
+            .insert(long_path!("/z/blob/sha256", 1), stamp(10, 100));
+        test_cache_fs
+            .stamps
+            .insert(long_path!("/z/blob/sha256", 3), stamp(30, 3));
         let mut fixture = Fixture::new(test_cache_fs, 1000);
         fixture.expect_messages_in_specific_order(vec![
             MkdirRecursively(path_buf!("/z/removing")),
             ReadDir(path_buf!("/z/removing")),
-            FileExists(path_buf!("/z/blob/sha256")),
+            ReadFile(path_buf!("/z/journal")),
+            MkdirRecursively(path_buf!("/z/blob/sha256")),
+            ReadDir(path_buf!("/z/blob/sha256")),
+            // The stamp doesn't match the record.
+            Stamp(long_path!("/z/blob/sha256", 1)),
             FileExists(short_path!("/z/removing", 1)),
-            Rename(path_buf!("/z/blob/sha256"), short_path!("/z/removing", 1)),
+            Rename(
+                long_path!("/z/blob/sha256", 1),
+                short_path!("/z/removing", 1),
+            ),
             RemoveRecursively(short_path!("/z/removing", 1)),
-            MkdirRecursively(path_buf!("/z/blob/sha256")),
-            FileExists(path_buf!("/z/bottom_fs_layer/sha256")),
+            // There's no record, so it was never finished.
             FileExists(short_path!("/z/removing", 2)),
             Rename(
-                path_buf!("/z/bottom_fs_layer/sha256"),
+                long_path!("/z/blob/sha256", 3),
                 short_path!("/z/removing", 2),
             ),
             RemoveRecursively(short_path!("/z/removing", 2)),
-            MkdirRecursively(path_buf!("/z/bottom_fs_layer/sha256")),
-            FileExists(path_buf!("/z/upper_fs_layer/sha256")),
+            // It isn't named like an entry.
             FileExists(short_path!("/z/removing", 3)),
             Rename(
-                path_buf!("/z/upper_fs_layer/sha256"),
+                path_buf!("/z/blob/sha256/foo"),
                 short_path!("/z/removing", 3),
             ),
             RemoveRecursively(short_path!("/z/removing", 3)),
+            MkdirRecursively(path_buf!("/z/bottom_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/bottom_fs_layer/sha256")),
+            // The stamp can't be read.
+            Stamp(long_path!("/z/bottom_fs_layer/sha256", 2)),
+            FileExists(short_path!("/z/removing", 4)),
+            Rename(
+                long_path!("/z/bottom_fs_layer/sha256", 2),
+                short_path!("/z/removing", 4),
+            ),
+            RemoveRecursively(short_path!("/z/removing", 4)),
             MkdirRecursively(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/upper_fs_layer/sha256")),
+            WriteFile(path_buf!("/z/journal"), "".into()),
         ]);
+
+        fixture.get_artifact(
+            digest!(1),
+            jid!(1),
+            GetArtifact::Get(long_path!("/z/blob/sha256", 1)),
+        );
+    }
+
+    #[test]
+    fn new_removes_restored_entries_over_target() {
+        let mut test_cache_fs = TestFs::default();
+        test_cache_fs.files.insert(
+            path_buf!("/z/journal"),
+            [
+                journal_line(EntryKind::Blob, digest!(1), stamp(10, 1)),
+                journal_line(EntryKind::Blob, digest!(2), stamp(10, 2)),
+            ]
+            .concat(),
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/blob/sha256"),
+            vec![
+                long_path!("/z/blob/sha256", 2),
+                long_path!("/z/blob/sha256", 1),
+            ],
+        );
+        test_cache_fs
+            .stamps
+            .insert(long_path!("/z/blob/sha256", 1), stamp(10, 1));
+        test_cache_fs
+            .stamps
+            .insert(long_path!("/z/blob/sha256", 2), stamp(10, 2));
+        let mut fixture = Fixture::new(test_cache_fs, 15);
+        let messages = fixture.messages.borrow().clone();
+        assert_eq!(
+            messages[messages.len() - 3..],
+            [
+                FileExists(short_path!("/z/removing", 1)),
+                Rename(
+                    long_path!("/z/blob/sha256", 1),
+                    short_path!("/z/removing", 1),
+                ),
+                RemoveRecursively(short_path!("/z/removing", 1)),
+            ]
+        );
+        fixture.clear_messages();
+
+        fixture.get_artifact(
+            digest!(2),
+            jid!(1),
+            GetArtifact::Success(long_path!("/z/blob/sha256", 2)),
+        );
+    }
+
+    #[test]
+    fn new_removes_blobs_whose_checksum_is_not_their_digest() {
+        let mut test_cache_fs = TestFs::default();
+        test_cache_fs.files.insert(
+            path_buf!("/z/journal"),
+            journal_line(EntryKind::Blob, digest!(1), stamp(10, 2)),
+        );
+        test_cache_fs.directories.insert(
+            path_buf!("/z/blob/sha256"),
+            vec![long_path!("/z/blob/sha256", 1)],
+        );
+        test_cache_fs
+            .stamps
+            .insert(long_path!("/z/blob/sha256", 1), stamp(10, 2));
+        let mut fixture = Fixture::new(test_cache_fs, 1000);
+        fixture.expect_messages_in_specific_order(vec![
+            MkdirRecursively(path_buf!("/z/removing")),
+            ReadDir(path_buf!("/z/removing")),
+            ReadFile(path_buf!("/z/journal")),
+            MkdirRecursively(path_buf!("/z/blob/sha256")),
+            ReadDir(path_buf!("/z/blob/sha256")),
+            FileExists(short_path!("/z/removing", 1)),
+            Rename(
+                long_path!("/z/blob/sha256", 1),
+                short_path!("/z/removing", 1),
+            ),
+            RemoveRecursively(short_path!("/z/removing", 1)),
+            MkdirRecursively(path_buf!("/z/bottom_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/bottom_fs_layer/sha256")),
+            MkdirRecursively(path_buf!("/z/upper_fs_layer/sha256")),
+            ReadDir(path_buf!("/z/upper_fs_layer/sha256")),
+            WriteFile(path_buf!("/z/journal"), "".into()),
+        ]);
+    }
+
+    #[test]
+    fn entry_stamp_changes_with_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, b"foo").unwrap();
+        let file_stamp = EntryStamp::read(&file).unwrap();
+        assert_eq!(file_stamp.bytes_used, 3);
+        let dir_stamp = EntryStamp::read(dir.path()).unwrap();
+        assert_eq!(dir_stamp.bytes_used, 3);
+
+        fs::write(&file, b"bar").unwrap();
+        assert_ne!(EntryStamp::read(&file).unwrap(), file_stamp);
+        assert_ne!(EntryStamp::read(dir.path()).unwrap(), dir_stamp);
+
+        fs::rename(&file, dir.path().join("other")).unwrap();
+        fs::write(dir.path().join("other"), b"foo").unwrap();
+        assert_ne!(EntryStamp::read(dir.path()).unwrap(), dir_stamp);
+    }
+
+    #[test]
+    fn got_artifact_success_records_stamp_without_reading_entry() {
+        let mut fixture = Fixture::new_and_clear_messages(1000);
+        fixture
+            .cache
+            .get_artifact(EntryKind::BottomFsLayer, digest!(1), jid!(1));
+        fixture
+            .cache
+            .got_artifact_success(EntryKind::BottomFsLayer, &digest!(1), stamp(10, 11));
+        fixture.expect_messages_in_specific_order(vec![AppendToFile(
+            path_buf!("/z/journal"),
+            journal_line(EntryKind::BottomFsLayer, digest!(1), stamp(10, 11)),
+        )]);
+    }
+
+    #[test]
+    fn journal_is_compacted_once_it_grows_past_threshold() {
+        let mut fixture = Fixture::new_and_clear_messages(35);
+        let line_len = journal_line(EntryKind::Blob, digest!(1), stamp(10, 0)).len() as u64;
+        fixture.cache.journal_compaction_threshold = line_len * 3;
+
+        fixture.get_artifact_ign(digest!(1), jid!(1));
+        fixture.got_artifact_success_ign(digest!(1), 10);
+        fixture.decrement_ref_count_ign(digest!(1));
+        fixture.get_artifact_ign(digest!(2), jid!(2));
+        fixture.got_artifact_success_ign(digest!(2), 10);
+        fixture.decrement_ref_count_ign(digest!(2));
+        fixture.get_artifact_ign(digest!(3), jid!(3));
+        fixture.got_artifact_success_ign(digest!(3), 10);
+
+        // Adding 4 removes 1 to make space, and grows the journal past the threshold. The journal
+        // is rewritten with only the entries that are still there: the ones not in use first, in
+        // the order they'd be removed, then the ones in use.
+        fixture.get_artifact_ign(digest!(4), jid!(4));
+        fixture.got_artifact_success(
+            digest!(4),
+            10,
+            (long_path!("/z/blob/sha256", 4), vec![jid!(4)]),
+            vec![
+                FileExists(short_path!("/z/removing", 1)),
+                Rename(
+                    long_path!("/z/blob/sha256", 1),
+                    short_path!("/z/removing", 1),
+                ),
+                RemoveRecursively(short_path!("/z/removing", 1)),
+                WriteFile(
+                    path_buf!("/z/journal"),
+                    [
+                        journal_line(EntryKind::Blob, digest!(2), stamp(10, 0)),
+                        journal_line(EntryKind::Blob, digest!(3), stamp(10, 0)),
+                        journal_line(EntryKind::Blob, digest!(4), stamp(10, 0)),
+                    ]
+                    .concat(),
+                ),
+            ],
+        );
+
+        // The next compaction waits for the journal to double in size.
+        for n in 5..=7 {
+            fixture.get_artifact_ign(digest!(n), jid!(n));
+            fixture.got_artifact_success(
+                digest!(n),
+                0,
+                (long_path!("/z/blob/sha256", n), vec![jid!(n)]),
+                vec![],
+            );
+        }
+        fixture.get_artifact_ign(digest!(8), jid!(8));
+        fixture
+            .cache
+            .got_artifact_success(EntryKind::Blob, &digest!(8), stamp(0, 0));
+        assert_matches!(fixture.messages.borrow().last(), Some(WriteFile(..)));
     }
 }
@@ -0,0 +1,833 @@
+//! Persistent, named caches that jobs can mount with [`JobMount::Cache`].
+//!
+//! Each named cache is a directory under the cache-mount root. The `data` directory inside it is
+//! what gets bind mounted into jobs. Jobs lock the caches they use, shared or exclusive depending
+//! on the mount's [`JobCacheSharing`], and a cache is only evicted when no job has it locked.
+//!
+//! Locking happens in two steps. The dispatcher calls [`CacheMounts::try_lock_mounts`], which only
+//! looks at in-memory state. It never blocks or touches the file system, and it never waits: if a
+//! job's caches can't all be locked right away, the dispatcher leaves the job queued and tries
+//! again when another job completes. Then, on the job's own thread, [`CacheMounts::acquire`]
+//! creates the cache's directory and `flock`s the `lock` file next to it. The file lock protects
+//! the cache from other processes that share the cache-mount root. The lock file's modification
+//! time records when the cache was last used, so that least-recently-used caches can be found
+//! after a restart.
+//!
+//! The size of each cache is tracked as jobs write to it (see [`usage`]). If a cache grows past
+//! the maximum size, the jobs using it are killed, and it is evicted once they're gone. If a cache
+//! can't be tracked, because we've run out of inotify watches, it is instead measured when each
+//! job using it completes, and the maximum is only enforced then.
+//!
+//! Since everything is stored in the file system, caches survive worker restarts.
+
+mod usage;
+
+use anyhow::{anyhow, bail, Error, Result};
+use maelstrom_base::{JobCacheSharing, JobError, JobMount, JobResult, Utf8PathBuf};
+use maelstrom_linux::{self as linux, PollEvents, PollFd};
+use maelstrom_util::{
+    config::common::CacheSize,
+    fs::{File, Fs},
+    root::RootBuf,
+    sync::{self, EventReceiver, EventSender},
+};
+use slog::{debug, error, warn, Logger};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::Permissions,
+    os::unix::fs::{MetadataExt as _, PermissionsExt as _},
+    path::Path,
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use usage::Usage;
+
+pub struct CacheMountDir;
+
+const DATA: &str = "data";
+const LOCK: &str = "lock";
+
+/// The files for cache locks need to outlive any particular function, so we give them a static
+/// [`Fs`] to borrow.
+static FS: Fs = Fs;
+
+/// How a cache is currently being used by this worker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CacheLock {
+    Unlocked,
+    Shared(usize),
+    Exclusive,
+    /// The cache is being removed. It can't be locked until that's done.
+    Evicting,
+}
+
+impl CacheLock {
+    fn can_lock(self, sharing: JobCacheSharing) -> bool {
+        matches!(
+            (self, sharing),
+            (Self::Unlocked, _) | (Self::Shared(_), JobCacheSharing::Shared)
+        )
+    }
+
+    fn lock(&mut self, sharing: JobCacheSharing) {
+        *self = match (*self, sharing) {
+            (Self::Shared(count), JobCacheSharing::Shared) => Self::Shared(count + 1),
+            (Self::Unlocked, JobCacheSharing::Shared) => Self::Shared(1),
+            (Self::Unlocked, JobCacheSharing::Exclusive) => Self::Exclusive,
+            (lock, sharing) => panic!("can't lock {lock:?} cache as {sharing:?}"),
+        };
+    }
+
+    fn unlock(&mut self) {
+        *self = match *self {
+            Self::Shared(count) if count > 1 => Self::Shared(count - 1),
+            Self::Shared(_) | Self::Exclusive => Self::Unlocked,
+            lock => panic!("can't unlock {lock:?} cache"),
+        };
+    }
+}
+
+struct Cache {
+    size: u64,
+    last_used: SystemTime,
+    lock: CacheLock,
+    /// Whether [`Usage`] is tracking the cache's size. If it isn't, `size` is as of the last time
+    /// a job using it completed.
+    tracked: bool,
+    /// The jobs that have the cache locked, as keys into [`Caches::kill_senders`].
+    holders: Vec<u64>,
+}
+
+#[derive(Default)]
+struct Caches {
+    caches: BTreeMap<String, Cache>,
+    /// One event for each job holding locks. Dropping the sender kills the job.
+    kill_senders: HashMap<u64, EventSender>,
+    next_holder: u64,
+}
+
+/// The state shared between [`CacheMounts`], the [`CacheMountLocks`] it hands out, and the thread
+/// that tracks cache sizes. The `caches` mutex is never held while touching the file system, since
+/// the dispatcher takes it. If both are needed, `usage` is taken first.
+struct Shared {
+    caches: Mutex<Caches>,
+    usage: Mutex<Usage>,
+}
+
+impl Shared {
+    /// Apply any pending changes to the tracked caches' sizes, and kill the jobs using caches that
+    /// have grown past `max_size`.
+    fn update_usage(&self, max_size: Option<u64>, log: &Logger) -> Result<()> {
+        let mut usage = self.usage.lock().unwrap();
+        let changed = usage.update(&Fs::new())?;
+        let mut caches = self.caches.lock().unwrap();
+        let Caches {
+            caches,
+            kill_senders,
+            ..
+        } = &mut *caches;
+        for name in changed {
+            let Some(cache) = caches.get_mut(&name) else {
+                continue;
+            };
+            match usage.size(&name) {
+                Some(size) => cache.size = size,
+                None => cache.tracked = false,
+            }
+            if max_size.is_some_and(|max_size| cache.size > max_size) {
+                for holder in &cache.holders {
+                    if kill_senders.remove(holder).is_some() {
+                        warn!(log, "killing job using oversized cache mount";
+                            "name" => &name, "size" => cache.size);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Locks on all of the named caches a job mounts. As long as these are held, the caches won't be
+/// evicted. Caches locked exclusively can't be used by any other job either.
+pub struct CacheMountLocks {
+    shared: Arc<Shared>,
+    holder: u64,
+    locked: BTreeMap<String, JobCacheSharing>,
+    paths: BTreeMap<String, Utf8PathBuf>,
+    /// The file locks taken by [`CacheMounts::acquire`].
+    files: Vec<File<'static>>,
+    kill_event: Option<EventReceiver>,
+}
+
+impl CacheMountLocks {
+    pub fn is_empty(&self) -> bool {
+        self.locked.is_empty()
+    }
+
+    /// Take the event that fires when one of the caches grows past its maximum size. The job
+    /// should be killed when it does.
+    pub fn take_kill_event(&mut self) -> Option<EventReceiver> {
+        self.kill_event.take()
+    }
+
+    /// Replace each [`JobMount::Cache`] in `mounts` with a writable [`JobMount::Bind`] of the
+    /// cache's directory.
+    pub fn replace_mounts<'a>(&self, mounts: impl IntoIterator<Item = &'a mut JobMount>) {
+        for mount in mounts {
+            if let JobMount::Cache {
+                name, mount_point, ..
+            } = mount
+            {
+                *mount = JobMount::Bind {
+                    mount_point: mount_point.clone(),
+                    local_path: self.paths[name].clone(),
+                    read_only: false,
+                };
+            }
+        }
+    }
+}
+
+impl Drop for CacheMountLocks {
+    fn drop(&mut self) {
+        let mut caches = self.shared.caches.lock().unwrap();
+        for name in self.locked.keys() {
+            let cache = caches.caches.get_mut(name).unwrap();
+            cache.lock.unlock();
+            cache.holders.retain(|&holder| holder != self.holder);
+        }
+        caches.kill_senders.remove(&self.holder);
+    }
+}
+
+pub struct CacheMounts {
+    root: RootBuf<CacheMountDir>,
+    size: u64,
+    max_size: Option<u64>,
+    log: Logger,
+    shared: Arc<Shared>,
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+        bail!("invalid cache name {name:?}");
+    }
+    Ok(())
+}
+
+/// Return the total size of the files in `path`.
+fn dir_size(fs: &Fs, path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs.walk(path) {
+        let metadata = fs.symlink_metadata(entry?)?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Remove `path` and everything in it. Some tools, like Go, leave read-only directories in their
+/// caches, so we first make sure every directory is writable.
+fn remove_dir_all(fs: &Fs, path: &Path) -> Result<()> {
+    for entry in fs.walk(path) {
+        let entry = entry?;
+        let metadata = fs.symlink_metadata(&entry)?;
+        if metadata.is_dir() && metadata.permissions().mode() & 0o700 != 0o700 {
+            fs.set_permissions(&entry, Permissions::from_mode(0o700))?;
+        }
+    }
+    fs.remove_dir_all(path)
+}
+
+/// Apply changes to cache sizes as they happen, until the [`CacheMounts`] is dropped.
+fn usage_main(shared: Weak<Shared>, max_size: Option<u64>, log: Logger) {
+    loop {
+        let Some(shared) = shared.upgrade() else {
+            break;
+        };
+        let fd = shared.usage.lock().unwrap().fd();
+        let mut poll_fds = [PollFd::new(fd, PollEvents::IN)];
+        if let Err(err) = linux::poll(&mut poll_fds, Duration::from_secs(1)) {
+            error!(log, "error waiting for cache mount changes"; "err" => %err);
+            break;
+        }
+        if let Err(err) = shared.update_usage(max_size, &log) {
+            error!(log, "error tracking cache mount sizes"; "err" => %err);
+            break;
+        }
+    }
+}
+
+impl CacheMounts {
+    /// Create the cache-mount root if it doesn't exist, and measure any caches left in it by a
+    /// previous incarnation of the worker. The `size` is the target for all caches together. Any
+    /// single cache bigger than `max_size` is removed as soon as no job is using it.
+    pub fn new(
+        root: RootBuf<CacheMountDir>,
+        size: CacheSize,
+        max_size: Option<CacheSize>,
+        log: Logger,
+    ) -> Result<Self> {
+        let fs = Fs::new();
+        fs.create_dir_all(&root)?;
+        let mut usage = Usage::new()?;
+        let mut caches = Caches::default();
+        for entry in fs.read_dir(&root)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|name| anyhow!("cache name {name:?} isn't valid UTF-8"))?;
+            let last_used = fs
+                .metadata(path.join(LOCK))
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(UNIX_EPOCH);
+            let tracked = fs.exists(path.join(DATA)) && usage.track(&fs, &name, &path.join(DATA));
+            let size = match usage.size(&name) {
+                Some(size) => size,
+                None => dir_size(&fs, &path)?,
+            };
+            let cache = Cache {
+                size,
+                last_used,
+                lock: CacheLock::Unlocked,
+                tracked,
+                holders: vec![],
+            };
+            caches.caches.insert(name, cache);
+        }
+        let shared = Arc::new(Shared {
+            caches: Mutex::new(caches),
+            usage: Mutex::new(usage),
+        });
+        let max_size = max_size.map(u64::from);
+        let weak = Arc::downgrade(&shared);
+        let usage_log = log.clone();
+        thread::spawn(move || usage_main(weak, max_size, usage_log));
+        Ok(Self {
+            root,
+            size: size.into(),
+            max_size,
+            log,
+            shared,
+        })
+    }
+
+    /// Try to lock every cache used by `mounts` without waiting. If any of them is held by another
+    /// job in a conflicting way, or has grown too big and is waiting to be evicted, none are
+    /// locked, and `None` is returned. If a job mounts the same cache more than once, it is locked
+    /// once, exclusively if any of the mounts asks for that.
+    ///
+    /// This only updates in-memory state. The job must call [`Self::acquire`] before using the
+    /// caches.
+    pub fn try_lock_mounts<'a>(
+        &self,
+        mounts: impl IntoIterator<Item = &'a JobMount>,
+    ) -> JobResult<Option<CacheMountLocks>, Error> {
+        let mut locked = BTreeMap::<String, JobCacheSharing>::new();
+        for mount in mounts {
+            if let JobMount::Cache { name, sharing, .. } = mount {
+                validate_name(name).map_err(JobError::Execution)?;
+                let entry = locked.entry(name.clone()).or_default();
+                *entry = (*entry).max(*sharing);
+            }
+        }
+
+        let mut paths = BTreeMap::new();
+        for name in locked.keys() {
+            struct NamedCacheDir;
+            let data = self
+                .root
+                .join::<NamedCacheDir>(name)
+                .join::<()>(DATA)
+                .into_path_buf();
+            let data = Utf8PathBuf::try_from(data)
+                .map_err(|err| anyhow!("cache directory isn't valid UTF-8: {err}"))
+                .map_err(JobError::System)?;
+            paths.insert(name.clone(), data);
+        }
+
+        let mut caches = self.shared.caches.lock().unwrap();
+        for (name, &sharing) in &locked {
+            if caches
+                .caches
+                .get(name)
+                .is_some_and(|cache| !cache.lock.can_lock(sharing) || self.too_big(cache.size))
+            {
+                debug!(self.log, "cache mount in use"; "name" => name);
+                return Ok(None);
+            }
+        }
+
+        let holder = caches.next_holder;
+        caches.next_holder += 1;
+        let kill_event = (!locked.is_empty()).then(|| {
+            let (kill_sender, kill_receiver) = sync::event();
+            caches.kill_senders.insert(holder, kill_sender);
+            kill_receiver
+        });
+        let now = SystemTime::now();
+        for (name, &sharing) in &locked {
+            debug!(self.log, "locking cache mount"; "name" => name, "sharing" => ?sharing);
+            let cache = caches.caches.entry(name.clone()).or_insert(Cache {
+                size: 0,
+                last_used: now,
+                lock: CacheLock::Unlocked,
+                tracked: false,
+                holders: vec![],
+            });
+            cache.lock.lock(sharing);
+            cache.last_used = now;
+            cache.holders.push(holder);
+        }
+        Ok(Some(CacheMountLocks {
+            shared: self.shared.clone(),
+            holder,
+            locked,
+            paths,
+            files: vec![],
+            kill_event,
+        }))
+    }
+
+    fn too_big(&self, size: u64) -> bool {
+        self.max_size.is_some_and(|max_size| size > max_size)
+    }
+
+    /// Create the caches locked by [`Self::try_lock_mounts`] if they don't exist, take their file
+    /// locks, and start tracking their sizes. This may block, either on the file system, or on
+    /// another process that shares the cache-mount root.
+    pub fn acquire(&self, locks: &mut CacheMountLocks) -> Result<()> {
+        for (name, &sharing) in &locks.locked {
+            struct NamedCacheDir;
+            let dir = self.root.join::<NamedCacheDir>(name);
+            let lock_path = dir.join::<()>(LOCK);
+            let lock = loop {
+                FS.create_dir_all(&locks.paths[name])?;
+                let lock = FS.open_or_create_file(&lock_path)?;
+                match sharing {
+                    JobCacheSharing::Shared => lock.lock_shared()?,
+                    JobCacheSharing::Exclusive => lock.lock_exclusive()?,
+                }
+
+                // Another process may have evicted the cache between when we opened the lock
+                // file and when we locked it. If so, the lock file we have is no longer the one in
+                // the file system, and we need to start over.
+                let current = FS.metadata(&lock_path).ok();
+                if current.map(|metadata| metadata.ino()) == Some(lock.metadata()?.ino()) {
+                    break lock;
+                }
+            };
+            lock.set_modified(SystemTime::now())?;
+            locks.files.push(lock);
+
+            let mut usage = self.shared.usage.lock().unwrap();
+            if usage.track(&Fs::new(), name, locks.paths[name].as_std_path()) {
+                let size = usage.size(name).unwrap();
+                let mut caches = self.shared.caches.lock().unwrap();
+                let cache = caches.caches.get_mut(name).unwrap();
+                cache.tracked = true;
+                cache.size = size;
+            }
+        }
+        Ok(())
+    }
+
+    /// If one of the caches locked by `locks` has grown past the maximum size, return an execution
+    /// error saying so.
+    pub fn check_size(&self, locks: &CacheMountLocks) -> JobResult<(), Error> {
+        self.shared
+            .update_usage(self.max_size, &self.log)
+            .map_err(JobError::System)?;
+        let caches = self.shared.caches.lock().unwrap();
+        for name in locks.locked.keys() {
+            let size = caches.caches[name].size;
+            if let Some(max_size) = self.max_size.filter(|&max_size| size > max_size) {
+                return Err(JobError::Execution(anyhow!(
+                    "cache mount {name:?} grew to {size} bytes, past its maximum size of {max_size} bytes"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Release the locks held by a job that has completed, and then evict caches as necessary.
+    /// Caches whose sizes aren't being tracked are measured before the locks are released.
+    pub fn release(&self, locks: CacheMountLocks) -> Result<()> {
+        let fs = Fs::new();
+        self.shared.update_usage(self.max_size, &self.log)?;
+        let untracked = {
+            let caches = self.shared.caches.lock().unwrap();
+            locks
+                .locked
+                .keys()
+                .filter(|name| !caches.caches[*name].tracked)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let measured = untracked
+            .into_iter()
+            .map(|name| {
+                let size = dir_size(&fs, &(**self.root).join(&name))?;
+                Ok((name, size))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        {
+            let mut caches = self.shared.caches.lock().unwrap();
+            for (name, size) in measured {
+                caches.caches.get_mut(&name).unwrap().size = size;
+            }
+        }
+        drop(locks);
+        self.evict(&fs)
+    }
+
+    /// Remove the named cache if no other process is using it. Return whether it was removed.
+    fn remove(&self, fs: &Fs, name: &str) -> Result<bool> {
+        let path = (**self.root).join(name);
+        if let Ok(lock) = fs.open_file(path.join(LOCK)) {
+            if lock.try_lock_exclusive().is_err() {
+                return Ok(false);
+            }
+            self.shared.usage.lock().unwrap().untrack(name);
+            fs.remove_file(path.join(LOCK))?;
+        }
+        remove_dir_all(fs, &path)?;
+        Ok(true)
+    }
+
+    /// Evict caches that aren't in use. First, any cache over the maximum size is removed. Then,
+    /// least-recently-used caches are removed until the total size of all caches is within the
+    /// configured limit. Like the artifact cache, this limit is a target: caches in use are never
+    /// evicted, so it may be exceeded.
+    ///
+    /// The victims are chosen and marked with the mutex held, then removed without it.
+    fn evict(&self, fs: &Fs) -> Result<()> {
+        let victims = {
+            let mut caches = self.shared.caches.lock().unwrap();
+            let mut candidates = caches
+                .caches
+                .iter()
+                .filter(|(_, cache)| cache.lock == CacheLock::Unlocked)
+                .map(|(name, cache)| (cache.last_used, cache.size, name.clone()))
+                .collect::<Vec<_>>();
+            candidates.sort();
+
+            let mut total: u64 = caches.caches.values().map(|cache| cache.size).sum();
+            let mut victims = vec![];
+            for (_, size, name) in candidates {
+                if total > self.size || self.too_big(size) {
+                    caches.caches.get_mut(&name).unwrap().lock = CacheLock::Evicting;
+                    total -= size;
+                    victims.push((name, size));
+                }
+            }
+            victims
+        };
+
+        let mut result = Ok(());
+        for (name, size) in victims {
+            let removed = match self.remove(fs, &name) {
+                Ok(removed) => removed,
+                Err(err) => {
+                    result = result.and(Err(err));
+                    false
+                }
+            };
+            let mut caches = self.shared.caches.lock().unwrap();
+            if removed {
+                debug!(self.log, "evicting cache mount"; "name" => &name, "size" => size);
+                caches.caches.remove(&name);
+            } else {
+                // If we got as far as removing some of the cache, we're no longer tracking it.
+                let cache = caches.caches.get_mut(&name).unwrap();
+                cache.lock = CacheLock::Unlocked;
+                cache.tracked = false;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt as _;
+    use tempfile::TempDir;
+
+    fn cache_mounts(size: u64, max_size: Option<u64>) -> (TempDir, CacheMounts) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = RootBuf::new(temp_dir.path().join("cache-mounts"));
+        let log = Logger::root(slog::Discard, slog::o!());
+        let cache_mounts =
+            CacheMounts::new(root, size.into(), max_size.map(Into::into), log).unwrap();
+        (temp_dir, cache_mounts)
+    }
+
+    fn cache(name: &str, mount_point: &str, sharing: JobCacheSharing) -> JobMount {
+        JobMount::Cache {
+            name: name.into(),
+            mount_point: mount_point.into(),
+            sharing,
+        }
+    }
+
+    fn lock(cache_mounts: &CacheMounts, mounts: &[JobMount]) -> CacheMountLocks {
+        let mut locks = cache_mounts.try_lock_mounts(mounts).unwrap().unwrap();
+        cache_mounts.acquire(&mut locks).unwrap();
+        locks
+    }
+
+    fn size(cache_mounts: &CacheMounts, name: &str) -> u64 {
+        cache_mounts
+            .shared
+            .update_usage(cache_mounts.max_size, &cache_mounts.log)
+            .unwrap();
+        cache_mounts.shared.caches.lock().unwrap().caches[name].size
+    }
+
+    #[test]
+    fn replace_mounts_replaces_caches_with_binds() {
+        let (temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let mut mounts = vec![
+            JobMount::Tmp {
+                mount_point: "/tmp".into(),
+                size: None,
+                mode: None,
+                inodes: None,
+            },
+            cache("pip", "/root/.cache/pip", JobCacheSharing::Shared),
+        ];
+        let locks = lock(&cache_mounts, &mounts);
+        assert_eq!(locks.locked.len(), 1);
+        locks.replace_mounts(&mut mounts);
+        let local_path = temp_dir.path().join("cache-mounts/pip/data");
+        assert!(local_path.is_dir());
+        assert_eq!(
+            mounts,
+            vec![
+                JobMount::Tmp {
+                    mount_point: "/tmp".into(),
+                    size: None,
+                    mode: None,
+                    inodes: None,
+                },
+                JobMount::Bind {
+                    mount_point: "/root/.cache/pip".into(),
+                    local_path: local_path.try_into().unwrap(),
+                    read_only: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn try_lock_mounts_locks_each_cache_once() {
+        let (_temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let mounts = [
+            cache("go", "/a", JobCacheSharing::Shared),
+            cache("go", "/b", JobCacheSharing::Exclusive),
+        ];
+        let locks = lock(&cache_mounts, &mounts);
+        assert_eq!(locks.locked.len(), 1);
+    }
+
+    #[test]
+    fn try_lock_mounts_doesnt_touch_file_system() {
+        let (temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let mounts = [cache("pip", "/a", JobCacheSharing::Shared)];
+        let mut locks = cache_mounts.try_lock_mounts(&mounts).unwrap().unwrap();
+        let path = temp_dir.path().join("cache-mounts/pip");
+        assert!(!path.exists());
+
+        cache_mounts.acquire(&mut locks).unwrap();
+        assert!(path.join("data").is_dir());
+        assert!(path.join("lock").is_file());
+    }
+
+    #[test]
+    fn try_lock_mounts_bad_name() {
+        let (_temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let mounts = [cache("../etc", "/a", JobCacheSharing::Shared)];
+        let Err(JobError::Execution(err)) = cache_mounts.try_lock_mounts(&mounts) else {
+            panic!("expected an execution error");
+        };
+        assert_eq!(err.to_string(), r#"invalid cache name "../etc""#);
+    }
+
+    #[test]
+    fn try_lock_mounts_doesnt_wait_for_conflicting_locks() {
+        let (_temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let shared = [cache("pip", "/a", JobCacheSharing::Shared)];
+        let exclusive = [
+            cache("go", "/b", JobCacheSharing::Shared),
+            cache("pip", "/a", JobCacheSharing::Exclusive),
+        ];
+
+        let shared_locks = lock(&cache_mounts, &shared);
+        let other_shared_locks = lock(&cache_mounts, &shared);
+        assert!(cache_mounts.try_lock_mounts(&exclusive).unwrap().is_none());
+
+        drop(shared_locks);
+        drop(other_shared_locks);
+        let exclusive_locks = lock(&cache_mounts, &exclusive);
+        assert!(cache_mounts.try_lock_mounts(&shared).unwrap().is_none());
+
+        drop(exclusive_locks);
+        lock(&cache_mounts, &shared);
+    }
+
+    #[test]
+    fn release_evicts_and_skips_caches_in_use() {
+        let (temp_dir, cache_mounts) = cache_mounts(5, None);
+        let fs = Fs::new();
+        let root = temp_dir.path().join("cache-mounts");
+
+        let used = lock(
+            &cache_mounts,
+            &[cache("used", "/a", JobCacheSharing::Shared)],
+        );
+        fs.write(root.join("used/data/file"), "0123456789").unwrap();
+
+        let old = lock(
+            &cache_mounts,
+            &[cache("old", "/a", JobCacheSharing::Shared)],
+        );
+        fs.write(root.join("old/data/file"), "0123456789").unwrap();
+        cache_mounts.release(old).unwrap();
+        assert!(!root.join("old").exists());
+        assert!(root.join("used/data/file").exists());
+
+        cache_mounts.release(used).unwrap();
+        assert!(!root.join("used").exists());
+    }
+
+    #[test]
+    fn release_evicts_caches_over_max_size() {
+        let (temp_dir, cache_mounts) = cache_mounts(1000, Some(5));
+        let fs = Fs::new();
+        let root = temp_dir.path().join("cache-mounts");
+
+        let small = lock(
+            &cache_mounts,
+            &[cache("small", "/a", JobCacheSharing::Shared)],
+        );
+        fs.write(root.join("small/data/file"), "01234").unwrap();
+        cache_mounts.release(small).unwrap();
+
+        let big = lock(
+            &cache_mounts,
+            &[cache("big", "/a", JobCacheSharing::Shared)],
+        );
+        fs.write(root.join("big/data/file"), "0123456789").unwrap();
+        cache_mounts.release(big).unwrap();
+
+        assert!(root.join("small/data/file").exists());
+        assert!(!root.join("big").exists());
+    }
+
+    #[test]
+    fn sizes_of_existing_caches_are_measured_at_startup() {
+        let (temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let fs = Fs::new();
+        let root = temp_dir.path().join("cache-mounts");
+
+        let old = lock(
+            &cache_mounts,
+            &[cache("old", "/a", JobCacheSharing::Shared)],
+        );
+        fs.write(root.join("old/data/file"), "0123456789").unwrap();
+        cache_mounts.release(old).unwrap();
+        assert!(root.join("old/data/file").exists());
+
+        let log = Logger::root(slog::Discard, slog::o!());
+        let cache_mounts =
+            CacheMounts::new(RootBuf::new(root.clone()), 5.into(), None, log).unwrap();
+        let new = lock(
+            &cache_mounts,
+            &[cache("new", "/a", JobCacheSharing::Shared)],
+        );
+        cache_mounts.release(new).unwrap();
+        assert!(!root.join("old").exists());
+        assert!(root.join("new").exists());
+    }
+
+    #[test]
+    fn release_removes_read_only_directories() {
+        let (temp_dir, cache_mounts) = cache_mounts(0, None);
+        let fs = Fs::new();
+        let root = temp_dir.path().join("cache-mounts");
+
+        let locks = lock(&cache_mounts, &[cache("go", "/a", JobCacheSharing::Shared)]);
+        fs.create_dir(root.join("go/data/mod")).unwrap();
+        fs.write(root.join("go/data/mod/file"), "contents").unwrap();
+        fs.set_permissions(root.join("go/data/mod"), Permissions::from_mode(0o555))
+            .unwrap();
+
+        cache_mounts.release(locks).unwrap();
+        assert!(!root.join("go").exists());
+    }
+
+    #[test]
+    fn sizes_are_tracked_while_caches_are_in_use() {
+        let (temp_dir, cache_mounts) = cache_mounts(1000, None);
+        let fs = Fs::new();
+        let data = temp_dir.path().join("cache-mounts/pip/data");
+
+        let _locks = lock(
+            &cache_mounts,
+            &[cache("pip", "/a", JobCacheSharing::Shared)],
+        );
+        assert_eq!(size(&cache_mounts, "pip"), 0);
+
+        fs.write(data.join("file"), "0123456789").unwrap();
+        assert_eq!(size(&cache_mounts, "pip"), 10);
+
+        fs.create_dir_all(data.join("a/b")).unwrap();
+        fs.write(data.join("a/b/file"), "01234").unwrap();
+        assert_eq!(size(&cache_mounts, "pip"), 15);
+
+        fs.rename(data.join("a"), data.join("c")).unwrap();
+        fs.write(data.join("c/b/other"), "01").unwrap();
+        assert_eq!(size(&cache_mounts, "pip"), 17);
+
+        fs.write(data.join("file"), "0").unwrap();
+        assert_eq!(size(&cache_mounts, "pip"), 8);
+
+        fs.remove_dir_all(data.join("c")).unwrap();
+        assert_eq!(size(&cache_mounts, "pip"), 1);
+    }
+
+    #[test]
+    fn growing_past_max_size_kills_jobs() {
+        let (temp_dir, cache_mounts) = cache_mounts(1000, Some(5));
+        let fs = Fs::new();
+        let root = temp_dir.path().join("cache-mounts");
+        let mounts = [cache("big", "/a", JobCacheSharing::Shared)];
+
+        let mut locks = lock(&cache_mounts, &mounts);
+        let mut kill_event = locks.take_kill_event().unwrap();
+        fs.write(root.join("big/data/file"), "01234").unwrap();
+        cache_mounts.check_size(&locks).unwrap();
+        assert!((&mut kill_event).now_or_never().is_none());
+
+        fs.write(root.join("big/data/file"), "0123456789").unwrap();
+        let Err(JobError::Execution(err)) = cache_mounts.check_size(&locks) else {
+            panic!("expected an execution error");
+        };
+        assert_eq!(
+            err.to_string(),
+            r#"cache mount "big" grew to 10 bytes, past its maximum size of 5 bytes"#
+        );
+        assert!(kill_event.now_or_never().is_some());
+
+        // The cache can't be used by anyone else until it's evicted.
+        assert!(cache_mounts.try_lock_mounts(&mounts).unwrap().is_none());
+        cache_mounts.release(locks).unwrap();
+        assert!(!root.join("big").exists());
+        lock(&cache_mounts, &mounts);
+    }
+}
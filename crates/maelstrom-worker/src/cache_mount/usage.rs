@@ -0,0 +1,208 @@
+//! Track the sizes of named caches as jobs write to them.
+//!
+//! Every directory in a tracked cache is watched with inotify, and the size of every file in it is
+//! remembered. When an event says that something in a directory changed, only that path is looked
+//! at again. That way, a cache's size is always known without walking it, and a job that fills a
+//! cache past its limit can be stopped while it's running.
+
+use anyhow::Result;
+use maelstrom_linux::{
+    self as linux, Errno, Fd, InotifyFlags, InotifyMask, OwnedFd, WatchDescriptor,
+};
+use maelstrom_util::fs::Fs;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::{CString, OsStr},
+    ops::Bound,
+    os::unix::ffi::OsStrExt as _,
+    path::{Path, PathBuf},
+};
+
+/// What a watch descriptor refers to.
+struct Watch {
+    cache: String,
+    dir: PathBuf,
+}
+
+struct CacheUsage {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, u64>,
+    size: u64,
+}
+
+pub struct Usage {
+    inotify: OwnedFd,
+    watches: HashMap<WatchDescriptor, Watch>,
+    caches: HashMap<String, CacheUsage>,
+}
+
+fn watch_mask() -> InotifyMask {
+    InotifyMask::CLOSE_WRITE
+        | InotifyMask::CREATE
+        | InotifyMask::DELETE
+        | InotifyMask::MODIFY
+        | InotifyMask::MOVED_FROM
+        | InotifyMask::MOVED_TO
+        | InotifyMask::ONLYDIR
+        | InotifyMask::DONT_FOLLOW
+}
+
+impl Usage {
+    pub fn new() -> Result<Self> {
+        let inotify = linux::inotify_init1(InotifyFlags::CLOEXEC | InotifyFlags::NONBLOCK)?;
+        Ok(Self {
+            inotify,
+            watches: HashMap::new(),
+            caches: HashMap::new(),
+        })
+    }
+
+    /// The inotify file descriptor. It becomes readable when there are events for
+    /// [`Self::update`] to apply.
+    pub fn fd(&self) -> Fd {
+        self.inotify.as_fd()
+    }
+
+    /// Start tracking the named cache, whose contents are in `root`. This walks the cache once.
+    /// Return whether the cache is tracked. It can't be if we run out of inotify watches, in which
+    /// case the caller has to measure it some other way.
+    pub fn track(&mut self, fs: &Fs, name: &str, root: &Path) -> bool {
+        if self.caches.contains_key(name) {
+            return true;
+        }
+        let usage = CacheUsage {
+            root: root.into(),
+            files: BTreeMap::new(),
+            size: 0,
+        };
+        self.caches.insert(name.into(), usage);
+        let tracked = self.refresh(fs, name, root).is_ok();
+        if !tracked {
+            self.untrack(name);
+        }
+        tracked
+    }
+
+    /// Stop tracking the named cache, if it is being tracked.
+    pub fn untrack(&mut self, name: &str) {
+        if self.caches.remove(name).is_some() {
+            let inotify = &self.inotify;
+            self.watches.retain(|&wd, watch| {
+                if watch.cache != name {
+                    return true;
+                }
+                // The directory may already be gone, in which case so is the watch.
+                let _ = linux::inotify_rm_watch(inotify, wd);
+                false
+            });
+        }
+    }
+
+    /// Return the size of the named cache, or `None` if it isn't tracked.
+    pub fn size(&self, name: &str) -> Option<u64> {
+        self.caches.get(name).map(|cache| cache.size)
+    }
+
+    /// Apply all pending events without blocking. Return the names of the tracked caches whose
+    /// sizes may have changed. A cache that can no longer be tracked is dropped, and its name is
+    /// returned too.
+    pub fn update(&mut self, fs: &Fs) -> Result<BTreeSet<String>> {
+        let mut buf = vec![0; 64 * 1024];
+        let mut changed = BTreeSet::new();
+        loop {
+            let len = match linux::read(&self.inotify, &mut buf) {
+                Ok(len) => len,
+                Err(Errno::EAGAIN) => break,
+                Err(err) => return Err(err.into()),
+            };
+            let mut refresh = vec![];
+            for event in linux::inotify_read_events(&buf[..len]) {
+                if event.mask.contains(InotifyMask::Q_OVERFLOW) {
+                    // We've missed events, so we have to walk every cache again.
+                    refresh.extend(self.caches.keys().map(|name| (name.clone(), None)));
+                } else if event.mask.contains(InotifyMask::IGNORED) {
+                    self.watches.remove(&event.wd);
+                } else if let (Some(watch), Some(name)) = (self.watches.get(&event.wd), event.name)
+                {
+                    let path = watch.dir.join(OsStr::from_bytes(name.to_bytes()));
+                    refresh.push((watch.cache.clone(), Some(path)));
+                }
+            }
+            for (name, path) in refresh {
+                let Some(cache) = self.caches.get_mut(&name) else {
+                    continue;
+                };
+                let result = match path {
+                    Some(path) => self.refresh(fs, &name, &path),
+                    None => {
+                        let root = cache.root.clone();
+                        cache.files.clear();
+                        cache.size = 0;
+                        self.refresh(fs, &name, &root)
+                    }
+                };
+                if result.is_err() {
+                    self.untrack(&name);
+                }
+                changed.insert(name);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Look at `path` in the named cache again, and at everything under it if it's a directory.
+    /// An error means that a directory couldn't be watched.
+    fn refresh(&mut self, fs: &Fs, name: &str, path: &Path) -> Result<()> {
+        let mut stack = vec![path.to_owned()];
+        while let Some(path) = stack.pop() {
+            self.forget(name, &path);
+            match fs.symlink_metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => {
+                    self.watch(name, &path)?;
+                    if let Ok(entries) = fs.read_dir(&path) {
+                        stack.extend(entries.flatten().map(|entry| entry.path()));
+                    }
+                }
+                Ok(metadata) if metadata.is_file() => {
+                    let cache = self.caches.get_mut(name).unwrap();
+                    cache.size += metadata.len();
+                    cache.files.insert(path, metadata.len());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget the sizes of `path` and everything under it.
+    fn forget(&mut self, name: &str, path: &Path) {
+        let cache = self.caches.get_mut(name).unwrap();
+        let forgotten = cache
+            .files
+            .range::<Path, _>((Bound::Included(path), Bound::Unbounded))
+            .take_while(|(file, _)| file.starts_with(path))
+            .map(|(file, _)| file.clone())
+            .collect::<Vec<_>>();
+        for file in forgotten {
+            cache.size -= cache.files.remove(&file).unwrap();
+        }
+    }
+
+    fn watch(&mut self, name: &str, dir: &Path) -> Result<()> {
+        let dir_c = CString::new(dir.as_os_str().as_bytes())?;
+        match linux::inotify_add_watch(&self.inotify, &dir_c, watch_mask()) {
+            Ok(wd) => {
+                let watch = Watch {
+                    cache: name.into(),
+                    dir: dir.into(),
+                };
+                self.watches.insert(wd, watch);
+                Ok(())
+            }
+            // The directory was removed or replaced before we could watch it. We'll get an event
+            // for that.
+            Err(Errno::ENOENT | Errno::ENOTDIR) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Enforce per-job resource limits and measure per-job resource usage using a delegated cgroup v2
+//! subtree.
+//!
+//! When the worker starts, [`delegate`] moves the worker into a leaf cgroup named `worker`, and
+//! creates a sibling cgroup named `jobs` with the memory, cpu, and pids controllers enabled. This
+//! dance is necessary because cgroup v2 doesn't allow a cgroup that contains processes to enable
+//! controllers for its children. Each job is then moved into its own cgroup under `jobs` before it
+//! does anything else.
+//!
+//! For any of this to work, the cgroup the worker is started in must be delegated to the user
+//! the worker runs as. This is what systemd does for services with `Delegate=yes`.
+
+use anyhow::{bail, Result};
+use maelstrom_base::JobLimits;
+use maelstrom_linux::{self as linux, Pid};
+use maelstrom_util::fs::Fs;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const WORKER_CGROUP: &str = "worker";
+const JOBS_CGROUP: &str = "jobs";
+const CONTROLLERS: [&str; 3] = ["memory", "cpu", "pids"];
+
+/// The period we use when writing `cpu.max`, in microseconds. This is the kernel's default.
+const CPU_PERIOD_USEC: u64 = 100_000;
+
+/// The smallest quota the kernel will accept in `cpu.max`, in microseconds.
+const CPU_MIN_QUOTA_USEC: u64 = 1_000;
+
+/// Return the path of the cgroup the current process is in, rooted at [`CGROUP_ROOT`].
+fn current_cgroup(fs: &Fs) -> Result<PathBuf> {
+    let contents = fs.read_to_string("/proc/self/cgroup")?;
+    let Some(path) = contents.lines().find_map(|line| line.strip_prefix("0::")) else {
+        bail!("not running in a cgroup v2 hierarchy");
+    };
+    Ok(Path::new(CGROUP_ROOT).join(path.trim_start_matches('/')))
+}
+
+fn enable_controllers(fs: &Fs, cgroup: &Path) -> Result<()> {
+    let controllers = CONTROLLERS.map(|controller| format!("+{controller}"));
+    fs.write(cgroup.join("cgroup.subtree_control"), controllers.join(" "))
+}
+
+/// Move the current process into a leaf cgroup and create the cgroup under which jobs' cgroups
+/// will be created. This must be called while the process is still single-threaded, before any
+/// other processes are created, since only this process is moved.
+///
+/// If this has already been done, because the worker was restarted in the same cgroup, then this
+/// is a no-op.
+pub fn delegate() -> Result<()> {
+    let fs = Fs::new();
+    let current = current_cgroup(&fs)?;
+    if current.file_name() == Some(WORKER_CGROUP.as_ref()) {
+        return Ok(());
+    }
+    let procs = fs.read_to_string(current.join("cgroup.procs"))?;
+    if procs.lines().ne([linux::getpid().to_string()]) {
+        bail!("cgroup {} contains other processes", current.display());
+    }
+    let worker = current.join(WORKER_CGROUP);
+    let jobs = current.join(JOBS_CGROUP);
+    if !fs.exists(&worker) {
+        fs.create_dir(&worker)?;
+    }
+    if !fs.exists(&jobs) {
+        fs.create_dir(&jobs)?;
+    }
+    fs.write(worker.join("cgroup.procs"), "0")?;
+    enable_controllers(&fs, &current)?;
+    enable_controllers(&fs, &jobs)
+}
+
+/// The cgroup under which each job's cgroup is created.
+pub struct JobCgroups {
+    jobs: PathBuf,
+    next_id: AtomicU64,
+}
+
+impl JobCgroups {
+    /// Find the cgroup set up by [`delegate`] and make sure it has all of the controllers we need.
+    /// Any cgroups left behind by a previous incarnation of the worker are removed.
+    pub fn new() -> Result<Self> {
+        let fs = Fs::new();
+        let current = current_cgroup(&fs)?;
+        if current.file_name() != Some(WORKER_CGROUP.as_ref()) {
+            bail!("worker wasn't able to move itself into a delegated cgroup");
+        }
+        let jobs = current.with_file_name(JOBS_CGROUP);
+        let enabled = fs.read_to_string(jobs.join("cgroup.subtree_control"))?;
+        for controller in CONTROLLERS {
+            if !enabled.split_whitespace().any(|c| c == controller) {
+                bail!("cgroup controller {controller:?} not available");
+            }
+        }
+        for entry in fs.read_dir(&jobs)? {
+            let entry = entry?;
+            if entry.metadata()?.is_dir() {
+                let _ = remove_cgroup(&fs, &entry.path());
+            }
+        }
+        Ok(Self {
+            jobs,
+            next_id: Default::default(),
+        })
+    }
+
+    /// Create a new cgroup with the given limits. The cgroup is removed when the returned value is
+    /// dropped.
+    pub fn create(&self, limits: &JobLimits) -> Result<JobCgroup> {
+        let fs = Fs::new();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = self.jobs.join(id.to_string());
+        fs.create_dir(&path)?;
+
+        // Create the guard now so the cgroup is removed if we encounter an error below.
+        let cgroup = JobCgroup { path: path.clone() };
+        if let Some(memory) = limits.memory {
+            fs.write(path.join("memory.max"), memory.to_string())?;
+            // Don't let the job get around its limit by swapping. This file doesn't exist if swap
+            // accounting is disabled, in which case there's nothing to do.
+            if fs.exists(path.join("memory.swap.max")) {
+                fs.write(path.join("memory.swap.max"), "0")?;
+            }
+            // Kill the whole job, not just the process that allocated too much.
+            fs.write(path.join("memory.oom.group"), "1")?;
+        }
+        if let Some(cpu_millis) = limits.cpu_millis {
+            fs.write(path.join("cpu.max"), cpu_max(cpu_millis))?;
+        }
+        if let Some(pids) = limits.pids {
+            fs.write(path.join("pids.max"), pids.to_string())?;
+        }
+        Ok(cgroup)
+    }
+}
+
+/// Return the contents to write to `cpu.max` for the given limit.
+fn cpu_max(cpu_millis: u32) -> String {
+    let quota = (u64::from(cpu_millis) * CPU_PERIOD_USEC / 1000).max(CPU_MIN_QUOTA_USEC);
+    format!("{quota} {CPU_PERIOD_USEC}")
+}
+
+/// Return whether the contents of a `memory.events` file indicate that the OOM killer killed a
+/// process in the cgroup.
+fn oom_killed(memory_events: &str) -> bool {
+    memory_events.lines().any(|line| {
+        line.strip_prefix("oom_kill ")
+            .and_then(|count| count.trim().parse::<u64>().ok())
+            .is_some_and(|count| count > 0)
+    })
+}
+
+/// Kill any processes left in the cgroup and remove it. Processes don't leave the cgroup until
+/// they've been reaped, so we may have to retry a few times.
+fn remove_cgroup(fs: &Fs, path: &Path) -> Result<()> {
+    let _ = fs.write(path.join("cgroup.kill"), "1");
+    let mut tries = 0;
+    loop {
+        match fs.remove_dir(path) {
+            Ok(()) => return Ok(()),
+            Err(_) if tries < 10 => {
+                tries += 1;
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// A job's cgroup.
+pub struct JobCgroup {
+    path: PathBuf,
+}
+
+impl JobCgroup {
+    /// Move the given process into the cgroup. Any children it subsequently creates will also be
+    /// in the cgroup.
+    pub fn add_process(&self, pid: Pid) -> Result<()> {
+        Fs::new().write(self.path.join("cgroup.procs"), pid.to_string())
+    }
+
+    /// Return whether any process in the cgroup was killed for going over the memory limit.
+    pub fn oom_killed(&self) -> Result<bool> {
+        let events = Fs::new().read_to_string(self.path.join("memory.events"))?;
+        Ok(oom_killed(&events))
+    }
+
+    /// Return the peak memory usage of the cgroup, in bytes, if the kernel tracks it.
+    pub fn peak_memory(&self) -> Result<Option<u64>> {
+        self.read_optional_u64("memory.peak")
+    }
+
+    /// Read a file containing a single integer. Older kernels don't have some files, in which case
+    /// `None` is returned.
+    fn read_optional_u64(&self, name: &str) -> Result<Option<u64>> {
+        let fs = Fs::new();
+        let path = self.path.join(name);
+        if !fs.exists(&path) {
+            return Ok(None);
+        }
+        Ok(Some(fs.read_to_string(&path)?.trim().parse()?))
+    }
+}
+
+impl Drop for JobCgroup {
+    fn drop(&mut self) {
+        let _ = remove_cgroup(&Fs::new(), &self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_max_whole_cpus() {
+        assert_eq!(cpu_max(1000), "100000 100000");
+        assert_eq!(cpu_max(2000), "200000 100000");
+    }
+
+    #[test]
+    fn cpu_max_fractional_cpus() {
+        assert_eq!(cpu_max(500), "50000 100000");
+        assert_eq!(cpu_max(1), "1000 100000");
+    }
+
+    #[test]
+    fn oom_killed_from_memory_events() {
+        let events = "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\noom_group_kill 1\n";
+        assert!(oom_killed(events));
+    }
+
+    #[test]
+    fn not_oom_killed_from_memory_events() {
+        let events = "low 0\nhigh 0\nmax 0\noom 0\noom_kill 0\noom_group_kill 0\n";
+        assert!(!oom_killed(events));
+    }
+}
@@ -1,11 +1,71 @@
 use crate::WorkerCacheDir;
+use derive_more::From;
 use maelstrom_macro::Config;
 use maelstrom_util::{
-    config::common::{BrokerAddr, CacheSize, InlineLimit, LogLevel, Slots},
+    config::common::{BrokerAddr, CacheSize, InlineLimit, LogLevel, Slots, TmpfsSize},
     root::RootBuf,
 };
+use serde::Deserialize;
+use std::{
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
 use xdg::BaseDirectories;
 
+#[derive(Clone, Copy, Deserialize, From)]
+#[serde(from = "u16")]
+pub struct MaxPrefetches(u16);
+
+impl MaxPrefetches {
+    pub fn inner(&self) -> &u16 {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> u16 {
+        self.0
+    }
+}
+
+impl Debug for MaxPrefetches {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for MaxPrefetches {
+    type Err = <u16 as FromStr>::Err;
+    fn from_str(max_prefetches: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(u16::from_str(max_prefetches)?))
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, From)]
+#[serde(from = "u16")]
+pub struct ArtifactServerPort(u16);
+
+impl ArtifactServerPort {
+    pub fn inner(&self) -> &u16 {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> u16 {
+        self.0
+    }
+}
+
+impl Debug for ArtifactServerPort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for ArtifactServerPort {
+    type Err = <u16 as FromStr>::Err;
+    fn from_str(port: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(u16::from_str(port)?))
+    }
+}
+
 #[derive(Config, Debug)]
 pub struct Config {
     /// Socket address of broker.
@@ -33,10 +93,52 @@ pub struct Config {
     #[config(value_name = "BYTES", default = "CacheSize::default()")]
     pub cache_size: CacheSize,
 
+    /// The target amount of disk space to use for the named caches that jobs can mount. When this
+    /// is exceeded, the least-recently-used caches not in use are removed. SI and binary suffixes
+    /// are supported.
+    #[config(value_name = "BYTES", default = "CacheSize::default()")]
+    pub cache_mount_size: CacheSize,
+
+    /// The maximum amount of disk space any one named cache may use. Jobs using a cache that grows
+    /// bigger than this are killed, and the cache is removed as soon as no job is using it. SI and
+    /// binary suffixes are supported.
+    #[config(option, value_name = "BYTES", default = r#""no cap""#)]
+    pub max_cache_mount_size: Option<CacheSize>,
+
+    /// The maximum number of jobs the broker has hinted it will send to this worker whose layers
+    /// are fetched ahead of time. This bounds how much bandwidth prefetching can take from jobs the
+    /// worker has already been given. Zero disables prefetching.
+    #[config(value_name = "N", default = "2")]
+    pub max_prefetches: MaxPrefetches,
+
+    /// The maximum amount of cache space that layers fetched ahead of time for hinted jobs can use
+    /// before those jobs arrive. SI and binary suffixes are supported.
+    #[config(value_name = "BYTES", default = "bytesize::ByteSize::mb(250)")]
+    pub prefetch_cache_size: CacheSize,
+
+    /// The port on which to serve the artifacts in the cache to other workers. Other workers reach
+    /// it at the address this worker connects to the broker from. Zero picks an unused port. If
+    /// not given, this worker doesn't serve artifacts, though it still fetches them from other
+    /// workers when the broker says to.
+    #[config(option, value_name = "PORT", default = r#""none""#)]
+    pub artifact_server_port: Option<ArtifactServerPort>,
+
     /// The maximum amount of bytes to return inline for captured stdout and stderr.
     #[config(value_name = "BYTES", default = "InlineLimit::default()")]
     pub inline_limit: InlineLimit,
 
+    /// The largest tmpfs a job may mount, either as a tmp mount or as its writable root overlay.
+    /// Tmpfs mounts that don't specify a size get this size, and jobs that ask for a bigger one
+    /// fail. SI and binary suffixes are supported.
+    #[config(option, value_name = "BYTES", default = r#""no cap""#)]
+    pub max_tmpfs_size: Option<TmpfsSize>,
+
+    /// The host endpoints that jobs may forward into themselves. Each is either a TCP address, like
+    /// `localhost:5432`, or the path of a Unix-domain socket. Jobs that ask to forward anything
+    /// else fail.
+    #[config(list, value_name = "ENDPOINTS", default = r#""none""#)]
+    pub allowed_forwards: Vec<String>,
+
     /// Minimum log level to output.
     #[config(short = 'l', value_name = "LEVEL", default = r#""info""#)]
     pub log_level: LogLevel,
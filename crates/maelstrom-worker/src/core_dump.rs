@@ -0,0 +1,548 @@
+//! Find and save the core file dumped by a job that asked for [`JobSpec::capture_core_dump`].
+//!
+//! Where the kernel puts a core file depends on the host's `core_pattern`. It may write a file
+//! relative to the crashing process's working directory, or at an absolute path in the crashing
+//! process's mount namespace, or it may pipe the core to a program like `systemd-coredump` or
+//! `apport`. The executor has the job send us file descriptors for its root, its working
+//! directory, its program, and a pidfd for itself before it execs, so that we can get at all of
+//! these after the job is gone.
+//!
+//! A core file is only taken if it was written after the job started, and if its name matches the
+//! job's program's PID, so that we don't return a stale core file, or one from another job.
+//!
+//! [`JobSpec::capture_core_dump`]: maelstrom_base::JobSpec::capture_core_dump
+
+use anyhow::{anyhow, Result};
+use elf::{abi, endian::AnyEndian, note::Note, ElfStream};
+use maelstrom_base::JobCoreDump;
+use maelstrom_linux::OwnedFd;
+use maelstrom_util::{fs::Fs, io::Sha256Stream};
+use slog::{warn, Logger};
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, Read as _},
+    mem,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Where the kernel's core pattern can be read from.
+const CORE_PATTERN_PATH: &str = "/proc/sys/kernel/core_pattern";
+
+/// Where the kernel's setting for adding the PID to core file names can be read from.
+const CORE_USES_PID_PATH: &str = "/proc/sys/kernel/core_uses_pid";
+
+/// Where `apport` keeps the core files it's piped.
+const APPORT_COREDUMP_DIR: &str = "/var/lib/apport/coredump";
+
+/// Programs that core files are piped to handle them after the crashing process is gone, so we
+/// wait this long for them to show up.
+const PIPED_CORE_TIMEOUT: Duration = Duration::from_secs(5);
+const PIPED_CORE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// File system timestamps come from a coarse clock, so a core file written right after the job
+/// started may look a little older than the job.
+const MODIFIED_TIME_SLACK: Duration = Duration::from_millis(100);
+
+/// Where the host's `core_pattern` sends core files.
+#[derive(Debug, PartialEq)]
+pub enum CorePattern {
+    /// The kernel writes a file named by `name` in `dir`. The directory is relative to the
+    /// crashing process's root if `absolute` is true, and to its working directory otherwise.
+    File {
+        absolute: bool,
+        dir: PathBuf,
+        name: String,
+    },
+    /// The core is piped to `systemd-coredump`, and can be retrieved with `coredumpctl`.
+    SystemdCoredump,
+    /// The core is piped to `apport`, which keeps it in [`APPORT_COREDUMP_DIR`].
+    Apport,
+    /// The core goes somewhere we can't find it. The string says why.
+    Unsupported(String),
+}
+
+impl CorePattern {
+    /// Read the host's core pattern.
+    pub fn read() -> Result<Self> {
+        let fs = Fs::new();
+        let core_pattern = fs.read_to_string(CORE_PATTERN_PATH)?;
+        let core_uses_pid = fs
+            .read_to_string(CORE_USES_PID_PATH)
+            .is_ok_and(|core_uses_pid| core_uses_pid.trim() != "0");
+        Ok(Self::parse(core_pattern.trim_end(), core_uses_pid))
+    }
+
+    fn parse(core_pattern: &str, core_uses_pid: bool) -> Self {
+        if let Some(command) = core_pattern.strip_prefix('|') {
+            let program = command.split_whitespace().next().unwrap_or_default();
+            return match Path::new(program)
+                .file_name()
+                .and_then(|name| name.to_str())
+            {
+                Some("systemd-coredump") => Self::SystemdCoredump,
+                Some("apport") => Self::Apport,
+                _ => Self::Unsupported(format!("core files are piped to {program:?}")),
+            };
+        }
+        let (dir, name) = match core_pattern.rsplit_once('/') {
+            Some((dir, name)) => (dir, name),
+            None => ("", core_pattern),
+        };
+        if dir.contains('%') {
+            return Self::Unsupported(format!(
+                "core files are written to a directory that depends on the crashing process: \
+                {dir:?}"
+            ));
+        }
+        // Like the kernel, add the PID if the pattern doesn't already have it.
+        let mut name = if name.is_empty() { "core" } else { name }.to_owned();
+        if core_uses_pid && !has_specifier(&name, 'p') {
+            name.push_str(".%p");
+        }
+        Self::File {
+            absolute: core_pattern.starts_with('/'),
+            dir: dir.trim_start_matches('/').into(),
+            name,
+        }
+    }
+}
+
+/// Log a warning if the host's core pattern means we won't find the core files of jobs that ask
+/// for them.
+pub fn check_core_pattern(log: &Logger) {
+    match CorePattern::read() {
+        Ok(CorePattern::Unsupported(reason)) => {
+            warn!(log, "jobs' core dumps won't be captured"; "reason" => reason);
+        }
+        Ok(_) => {}
+        Err(err) => {
+            warn!(log, "couldn't read core pattern, so jobs' core dumps may not be captured";
+                "error" => %err);
+        }
+    }
+}
+
+/// The PIDs of a job's program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pids {
+    /// The PID in our PID namespace. We assume that this is the same as in the initial PID
+    /// namespace, which is where programs that core files are piped to see it.
+    pub host: i32,
+    /// The PID in the job's own PID namespace.
+    pub job: i32,
+}
+
+impl Pids {
+    /// Get the PIDs of the process referred to by `pidfd`. This has to be called while the process
+    /// is still running.
+    pub fn from_pidfd(pidfd: &OwnedFd) -> Result<Self> {
+        let path = format!("/proc/self/fdinfo/{}", pidfd.as_fd().as_c_int());
+        Self::parse_fdinfo(&Fs::new().read_to_string(path)?)
+            .ok_or_else(|| anyhow!("couldn't find PIDs in pidfd's fdinfo"))
+    }
+
+    /// The `NSpid` line lists the process's PID in each PID namespace, from ours down to its own.
+    fn parse_fdinfo(fdinfo: &str) -> Option<Self> {
+        let mut pids = fdinfo
+            .lines()
+            .find_map(|line| line.strip_prefix("NSpid:"))?
+            .split_whitespace()
+            .map(|pid| pid.parse().ok());
+        let host = pids.next()??;
+        let job = pids.last().unwrap_or(Some(host))?;
+        Some(Self { host, job })
+    }
+}
+
+/// What the job sent us so that we can find its core file.
+pub struct JobFiles {
+    pub root: Option<OwnedFd>,
+    pub working_directory: Option<OwnedFd>,
+    pub program: Option<OwnedFd>,
+    pub pids: Option<Pids>,
+}
+
+/// Find the core file dumped by the job's program, and if there is one, copy it to `path`. The
+/// build-id of the program is included in the result, if we can find it. If there is no core
+/// file, return why.
+pub fn save(
+    files: &JobFiles,
+    start: SystemTime,
+    path: &Path,
+) -> Result<Result<JobCoreDump, String>> {
+    let Some(pids) = files.pids else {
+        return Ok(Err("couldn't determine the job's PID".into()));
+    };
+    match CorePattern::read()? {
+        CorePattern::File {
+            absolute,
+            dir,
+            name,
+        } => {
+            let base = if absolute {
+                &files.root
+            } else {
+                &files.working_directory
+            };
+            let Some(base) = base else {
+                return Ok(Err("couldn't open the job's directories".into()));
+            };
+            let dir = fd_path(base).join(dir);
+            match find_core_file(&dir, &|file| name_matches(&name, file, pids), start)? {
+                Some(core_path) => copy_core_file(&core_path, path)?,
+                None => return Ok(Err("no core file was found".into())),
+            }
+        }
+        CorePattern::SystemdCoredump => match wait_for(|| coredumpctl_dump(pids, start, path))? {
+            Some(()) => {}
+            None => return Ok(Err("systemd-coredump didn't store a core file".into())),
+        },
+        CorePattern::Apport => {
+            let dir = Path::new(APPORT_COREDUMP_DIR);
+            let matches = |file: &str| apport_name_matches(file, pids);
+            match wait_for(|| find_core_file(dir, &matches, start))? {
+                Some(core_path) => copy_core_file(&core_path, path)?,
+                None => return Ok(Err("apport didn't store a core file".into())),
+            }
+        }
+        CorePattern::Unsupported(reason) => return Ok(Err(reason)),
+    }
+
+    let fs = Fs::new();
+    let mut reader = Sha256Stream::new(fs.open_file(path)?);
+    let size = io::copy(&mut reader, &mut io::sink())?;
+    let (_, digest) = reader.finalize();
+    Ok(Ok(JobCoreDump {
+        digest,
+        size,
+        build_id: files
+            .program
+            .as_ref()
+            .and_then(|program| build_id(&fd_path(program)).ok().flatten()),
+    }))
+}
+
+/// A path that can be used to open the file referred to by `fd`, even if it was opened with
+/// `O_PATH`.
+pub fn fd_path(fd: &OwnedFd) -> PathBuf {
+    PathBuf::from(format!("/proc/self/fd/{}", fd.as_fd().as_c_int()))
+}
+
+/// Call `f` until it finds something, or until [`PIPED_CORE_TIMEOUT`] has passed.
+fn wait_for<T>(mut f: impl FnMut() -> Result<Option<T>>) -> Result<Option<T>> {
+    let deadline = SystemTime::now() + PIPED_CORE_TIMEOUT;
+    loop {
+        if let Some(found) = f()? {
+            return Ok(Some(found));
+        }
+        if SystemTime::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(PIPED_CORE_POLL_INTERVAL);
+    }
+}
+
+/// The core file may be on a file system that goes away with the job, so we copy it instead of
+/// renaming it.
+fn copy_core_file(core_path: &Path, path: &Path) -> Result<()> {
+    let fs = Fs::new();
+    io::copy(&mut fs.open_file(core_path)?, &mut fs.create_file(path)?)?;
+    let _ = fs.remove_file(core_path);
+    Ok(())
+}
+
+/// Ask `systemd-coredump` for the core file of the process with the given PID, and write it to
+/// `path`. Return `None` if it doesn't have one (yet).
+fn coredumpctl_dump(pids: Pids, start: SystemTime, path: &Path) -> Result<Option<()>> {
+    let since = start.duration_since(SystemTime::UNIX_EPOCH)? - MODIFIED_TIME_SLACK;
+    let status = Command::new("coredumpctl")
+        .arg("--quiet")
+        .arg("--no-pager")
+        .arg(format!("--since=@{}", since.as_secs()))
+        .arg("--output")
+        .arg(path)
+        .arg("dump")
+        .arg(pids.host.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(status.success().then_some(()))
+}
+
+/// Whether a kernel core pattern's file name matches `file`. Format specifiers we know the value
+/// of have to match exactly. The others match anything.
+fn name_matches(pattern: &str, file: &str, pids: Pids) -> bool {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => literal.push('%'),
+            Some('p') => literal.push_str(&pids.job.to_string()),
+            Some('P') => literal.push_str(&pids.host.to_string()),
+            // The kernel drops a trailing `%`.
+            None => {}
+            Some(_) => {
+                segments.push(Segment::Literal(mem::take(&mut literal)));
+                segments.push(Segment::Any);
+            }
+        }
+    }
+    segments.push(Segment::Literal(literal));
+    segments_match(&segments, file)
+}
+
+/// Whether the name of a core file kept by `apport` is for the given process. The name ends with
+/// the crashing process's PID and a timestamp.
+fn apport_name_matches(file: &str, pids: Pids) -> bool {
+    let mut fields = file.rsplit('.');
+    let (Some(_timestamp), Some(pid)) = (fields.next(), fields.next()) else {
+        return false;
+    };
+    file.starts_with("core.") && pid == pids.host.to_string()
+}
+
+fn has_specifier(pattern: &str, specifier: char) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' && chars.next() == Some(specifier) {
+            return true;
+        }
+    }
+    false
+}
+
+enum Segment {
+    Literal(String),
+    Any,
+}
+
+fn segments_match(segments: &[Segment], name: &str) -> bool {
+    match segments.split_first() {
+        None => name.is_empty(),
+        Some((Segment::Literal(literal), rest)) => name
+            .strip_prefix(literal.as_str())
+            .is_some_and(|name| segments_match(rest, name)),
+        Some((Segment::Any, rest)) => (0..=name.len())
+            .filter(|&i| name.is_char_boundary(i))
+            .any(|i| segments_match(rest, &name[i..])),
+    }
+}
+
+/// Find the most recently modified core file in `dir` whose name `matches`, and that was written
+/// after `start`. We look at the contents of each file too, in case the pattern matches other
+/// files.
+fn find_core_file(
+    dir: &Path,
+    matches: &dyn Fn(&str) -> bool,
+    start: SystemTime,
+) -> Result<Option<PathBuf>> {
+    // The kernel won't create the directory, so there's no core file if it doesn't exist.
+    let fs = Fs::new();
+    if !fs.exists(dir) {
+        return Ok(None);
+    }
+    let mut candidates = vec![];
+    for entry in fs.read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let modified = metadata.modified()?;
+        if metadata.is_file()
+            && modified + MODIFIED_TIME_SLACK >= start
+            && entry.file_name().to_str().is_some_and(matches)
+            && is_core_file(&entry.path())
+        {
+            candidates.push((modified, entry.path()));
+        }
+    }
+    Ok(candidates
+        .into_iter()
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path))
+}
+
+/// Whether the file at `path` is an ELF core file. Any error is treated as "no".
+fn is_core_file(path: &Path) -> bool {
+    let mut header = [0u8; 18];
+    let Ok(()) = File::open(path).and_then(|mut file| file.read_exact(&mut header)) else {
+        return false;
+    };
+    if header[..4] != *b"\x7fELF" {
+        return false;
+    }
+    let e_type = match header[abi::EI_DATA] {
+        abi::ELFDATA2LSB => u16::from_le_bytes([header[16], header[17]]),
+        abi::ELFDATA2MSB => u16::from_be_bytes([header[16], header[17]]),
+        _ => return false,
+    };
+    e_type == abi::ET_CORE
+}
+
+/// Read the GNU build-id out of the ELF file at `path`, and return it in hex.
+fn build_id(path: &Path) -> Result<Option<String>> {
+    let mut elf = ElfStream::<AnyEndian, _>::open_stream(File::open(path)?)?;
+    let notes: Vec<_> = elf
+        .segments()
+        .iter()
+        .filter(|phdr| phdr.p_type == abi::PT_NOTE)
+        .copied()
+        .collect();
+    for phdr in notes {
+        for note in elf.segment_data_as_notes(&phdr)? {
+            if let Note::GnuBuildId(id) = note {
+                let mut hex = String::with_capacity(id.0.len() * 2);
+                for byte in id.0 {
+                    write!(hex, "{byte:02x}").unwrap();
+                }
+                return Ok(Some(hex));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIDS: Pids = Pids {
+        host: 12345,
+        job: 2,
+    };
+
+    fn file(absolute: bool, dir: &str, name: &str) -> CorePattern {
+        CorePattern::File {
+            absolute,
+            dir: dir.into(),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn parse_file_patterns() {
+        assert_eq!(CorePattern::parse("core", false), file(false, "", "core"));
+        assert_eq!(CorePattern::parse("core", true), file(false, "", "core.%p"));
+        assert_eq!(
+            CorePattern::parse("core.%e.%p", true),
+            file(false, "", "core.%e.%p")
+        );
+        assert_eq!(
+            CorePattern::parse("cores/core.%P", false),
+            file(false, "cores", "core.%P")
+        );
+        assert_eq!(
+            CorePattern::parse("/var/crash/core.%e", true),
+            file(true, "var/crash", "core.%e.%p")
+        );
+    }
+
+    #[test]
+    fn parse_pipe_patterns() {
+        assert_eq!(
+            CorePattern::parse(
+                "|/usr/lib/systemd/systemd-coredump %P %u %g %s %t %c %h",
+                true
+            ),
+            CorePattern::SystemdCoredump
+        );
+        assert_eq!(
+            CorePattern::parse(
+                "|/usr/share/apport/apport -p%p -s%s -c%c -d%d -P%P -u%u -g%g -- %E",
+                false
+            ),
+            CorePattern::Apport
+        );
+        assert_matches::assert_matches!(
+            CorePattern::parse("|/usr/libexec/abrt-hook-ccpp %s %c %p", false),
+            CorePattern::Unsupported(_)
+        );
+    }
+
+    #[test]
+    fn parse_directory_with_specifier_is_unsupported() {
+        assert_matches::assert_matches!(
+            CorePattern::parse("/var/crash/%e/core", false),
+            CorePattern::Unsupported(_)
+        );
+    }
+
+    #[test]
+    fn names_match_known_specifiers_exactly() {
+        assert!(name_matches("core", "core", PIDS));
+        assert!(!name_matches("core", "core.2", PIDS));
+        assert!(name_matches("core.%p", "core.2", PIDS));
+        assert!(!name_matches("core.%p", "core.3", PIDS));
+        assert!(name_matches("core.%P", "core.12345", PIDS));
+        assert!(!name_matches("core.%P", "core.2", PIDS));
+        assert!(name_matches("100%%.%p", "100%.2", PIDS));
+    }
+
+    #[test]
+    fn names_match_unknown_specifiers_with_anything() {
+        assert!(name_matches("core.%e.%p", "core.python3.2", PIDS));
+        assert!(name_matches("core.%e.%p", "core.my.prog.2", PIDS));
+        assert!(!name_matches("core.%e.%p", "core.python3.22", PIDS));
+        assert!(name_matches("%t-%e", "1700000000-a", PIDS));
+    }
+
+    #[test]
+    fn apport_names_match_host_pid() {
+        assert!(apport_name_matches(
+            "core._usr_bin_python3.1000.0123-4567.12345.1700000000",
+            PIDS
+        ));
+        assert!(!apport_name_matches(
+            "core._usr_bin_python3.1000.0123-4567.2.1700000000",
+            PIDS
+        ));
+        assert!(!apport_name_matches("12345.1700000000", PIDS));
+    }
+
+    #[test]
+    fn pids_from_fdinfo() {
+        let fdinfo = "pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\nPid:\t12345\n\
+            NSpid:\t12345\t2\n";
+        assert_eq!(Pids::parse_fdinfo(fdinfo), Some(PIDS));
+        assert_eq!(
+            Pids::parse_fdinfo("Pid:\t7\nNSpid:\t7\n"),
+            Some(Pids { host: 7, job: 7 })
+        );
+        assert_eq!(Pids::parse_fdinfo("Pid:\t7\n"), None);
+    }
+
+    #[test]
+    fn stale_and_unmatched_core_files_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut core = b"\x7fELF\x02\x01\x01".to_vec();
+        core.resize(16, 0);
+        core.extend(abi::ET_CORE.to_le_bytes());
+        std::fs::write(dir.path().join("core.2"), &core).unwrap();
+        std::fs::write(dir.path().join("core.3"), &core).unwrap();
+        std::fs::write(dir.path().join("notes.2"), b"not a core file").unwrap();
+
+        let matches = |file: &str| name_matches("core.%p", file, PIDS);
+        let before = SystemTime::now() - Duration::from_secs(60);
+        assert_eq!(
+            find_core_file(dir.path(), &matches, before).unwrap(),
+            Some(dir.path().join("core.2"))
+        );
+
+        let after = SystemTime::now() + Duration::from_secs(60);
+        assert_eq!(find_core_file(dir.path(), &matches, after).unwrap(), None);
+
+        let anything = |_: &str| true;
+        assert_eq!(
+            find_core_file(&dir.path().join("missing"), &anything, before).unwrap(),
+            None
+        );
+    }
+}
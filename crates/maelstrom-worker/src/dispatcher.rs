@@ -3,12 +3,12 @@
 
 mod tracker;
 
-use crate::cache::{self, GetArtifact};
+use crate::cache::{self, EntryStamp, GetArtifact};
 use anyhow::{Error, Result};
 use maelstrom_base::{
     proto::{BrokerToWorker, WorkerToBroker},
-    ArtifactType, JobCompleted, JobError, JobId, JobOutcome, JobResult, JobSpec, JobWorkerStatus,
-    Sha256Digest,
+    ArtifactType, JobCompleted, JobError, JobId, JobMount, JobOutcome, JobResult, JobSpec,
+    JobStdin, JobWorkerStatus, Sha256Digest,
 };
 use maelstrom_util::{config::common::Slots, duration, ext::OptionExt as _};
 use std::{
@@ -36,9 +36,24 @@ pub trait Deps {
     /// safe to drop this handle after the job has completed.
     type JobHandle;
 
+    /// Locks on the named caches a job mounts. These are handed to the job when it starts, and
+    /// must be released before the [`Message::JobCompleted`] message for the job is sent.
+    type CacheMountLocks;
+
+    /// Try to lock the named caches that the job and its sidecars mount, without blocking. If
+    /// another job holds a conflicting lock, return `None`. The dispatcher will try again after
+    /// some job completes.
+    fn try_lock_cache_mounts(&mut self, spec: &JobSpec) -> Option<Self::CacheMountLocks>;
+
     /// Start a new job. The dispatcher expects a [`Message::JobCompleted`] message when the job
     /// completes.
-    fn start_job(&mut self, jid: JobId, spec: JobSpec, path: PathBuf) -> Self::JobHandle;
+    fn start_job(
+        &mut self,
+        jid: JobId,
+        spec: JobSpec,
+        paths: JobLayerPaths,
+        cache_mount_locks: Self::CacheMountLocks,
+    ) -> Self::JobHandle;
 
     /// The timer handle should cancel an outstanding timer when it is dropped. It must be safe to
     /// drop this handle after the timer has completed. Dropping this handle may or may not result
@@ -106,7 +121,7 @@ pub trait Cache {
         &mut self,
         kind: cache::EntryKind,
         digest: &Sha256Digest,
-        bytes_used: u64,
+        stamp: EntryStamp,
     ) -> (PathBuf, Vec<JobId>);
     fn decrement_ref_count(&mut self, kind: cache::EntryKind, digest: &Sha256Digest);
 }
@@ -134,9 +149,9 @@ impl<FsT: cache::Fs> Cache for cache::Cache<FsT> {
         &mut self,
         kind: cache::EntryKind,
         digest: &Sha256Digest,
-        bytes_used: u64,
+        stamp: EntryStamp,
     ) -> (PathBuf, Vec<JobId>) {
-        self.got_artifact_success(kind, digest, bytes_used)
+        self.got_artifact_success(kind, digest, stamp)
     }
 
     fn decrement_ref_count(&mut self, kind: cache::EntryKind, digest: &Sha256Digest) {
@@ -144,17 +159,31 @@ impl<FsT: cache::Fs> Cache for cache::Cache<FsT> {
     }
 }
 
+/// Limits on how much work the dispatcher will do for [`BrokerToWorker::Prefetch`] hints. Hints
+/// that arrive while either limit is reached are ignored. The default disables prefetching.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrefetchBudget {
+    /// The maximum number of hinted jobs whose layers are fetched at once. This bounds how much
+    /// bandwidth prefetching can take away from jobs we've already been given.
+    pub max_jobs: usize,
+
+    /// The maximum number of bytes that layers fetched for hinted jobs can add to the cache before
+    /// those jobs are enqueued. This bounds how much of the cache prefetching can evict.
+    pub cache_bytes: u64,
+}
+
 /// An input message for the dispatcher. These come from the broker, an executor, or an artifact
 /// fetcher.
 #[derive(Debug)]
 pub enum Message {
     Broker(BrokerToWorker),
-    JobCompleted(JobId, JobResult<JobCompleted, String>),
+    JobCompleted(JobId, Box<JobResult<JobCompleted, String>>),
     JobTimer(JobId),
-    ArtifactFetchCompleted(Sha256Digest, Result<u64>),
-    BuiltBottomFsLayer(Sha256Digest, Result<u64>),
-    BuiltUpperFsLayer(Sha256Digest, Result<u64>),
+    ArtifactFetchCompleted(Sha256Digest, Result<EntryStamp>),
+    BuiltBottomFsLayer(Sha256Digest, Result<EntryStamp>),
+    BuiltUpperFsLayer(Sha256Digest, Result<EntryStamp>),
     ReadManifestDigests(Sha256Digest, JobId, Result<HashSet<Sha256Digest>>),
+    ArtifactServerRunning(bool),
     Shutdown(Error),
 }
 
@@ -174,6 +203,7 @@ where
         broker_sender: BrokerSenderT,
         cache: CacheT,
         slots: Slots,
+        prefetch_budget: PrefetchBudget,
     ) -> Self {
         Dispatcher {
             deps,
@@ -181,9 +211,15 @@ where
             broker_sender,
             cache,
             slots: slots.into_inner().into(),
+            prefetch_budget,
             awaiting_layers: HashMap::default(),
             available: BinaryHeap::default(),
             executing: HashMap::default(),
+            num_prefetching: 0,
+            prefetched: HashMap::default(),
+            prefetch_bytes: 0,
+            cache_mount_reservations: HashMap::default(),
+            serving_artifacts: false,
         }
     }
 
@@ -192,25 +228,28 @@ where
     pub fn receive_message(&mut self, msg: Message) {
         match msg {
             Message::Broker(BrokerToWorker::EnqueueJob(jid, spec)) => {
-                self.receive_enqueue_job(jid, spec)
+                self.receive_enqueue_job(jid, *spec)
             }
             Message::Broker(BrokerToWorker::CancelJob(jid)) => self.receive_cancel_job(jid),
-            Message::JobCompleted(jid, result) => self.receive_job_completed(jid, result),
+            Message::Broker(BrokerToWorker::Prefetch(jid, spec)) => {
+                self.receive_prefetch(jid, *spec)
+            }
+            Message::JobCompleted(jid, result) => self.receive_job_completed(jid, *result),
             Message::JobTimer(jid) => self.receive_job_timer(jid),
             Message::ArtifactFetchCompleted(digest, Err(err)) => {
                 self.receive_artifact_failure(digest, err)
             }
-            Message::ArtifactFetchCompleted(digest, Ok(bytes_used)) => {
-                self.receive_artifact_success(digest, bytes_used)
+            Message::ArtifactFetchCompleted(digest, Ok(stamp)) => {
+                self.receive_artifact_success(digest, stamp)
             }
-            Message::BuiltBottomFsLayer(digest, Ok(bytes_used)) => {
-                self.receive_build_bottom_fs_layer_success(digest, bytes_used)
+            Message::BuiltBottomFsLayer(digest, Ok(stamp)) => {
+                self.receive_build_bottom_fs_layer_success(digest, stamp)
             }
             Message::BuiltBottomFsLayer(digest, Err(err)) => {
                 self.receive_build_bottom_fs_layer_failure(digest, err)
             }
-            Message::BuiltUpperFsLayer(digest, Ok(bytes_used)) => {
-                self.receive_build_upper_fs_layer_success(digest, bytes_used)
+            Message::BuiltUpperFsLayer(digest, Ok(stamp)) => {
+                self.receive_build_upper_fs_layer_success(digest, stamp)
             }
             Message::BuiltUpperFsLayer(digest, Err(err)) => {
                 self.receive_build_upper_fs_layer_failure(digest, err)
@@ -221,6 +260,7 @@ where
             Message::ReadManifestDigests(digest, jid, Err(err)) => {
                 self.receive_read_manifest_digests_failure(digest, jid, err)
             }
+            Message::ArtifactServerRunning(running) => self.serving_artifacts = running,
             Message::Shutdown(_) => self.receive_shutdown(),
         }
     }
@@ -240,7 +280,33 @@ where
 /// `AvailableJob`s.
 struct AwaitingLayersJob {
     spec: JobSpec,
-    tracker: LayerTracker,
+    /// The first tracker is for the job itself. Then come the trackers for its sidecars, and then
+    /// those for its layer mounts, in order.
+    trackers: Vec<LayerTracker>,
+    /// If the broker has only hinted that it will send us this job, this is the number of bytes
+    /// its layers have added to the cache so far. It becomes `None` once the job is enqueued.
+    prefetch_bytes: Option<u64>,
+}
+
+impl AwaitingLayersJob {
+    fn is_complete(&self) -> bool {
+        self.trackers.iter().all(LayerTracker::is_complete)
+    }
+
+    fn into_cache_keys(self) -> impl Iterator<Item = cache::Key> {
+        self.trackers
+            .into_iter()
+            .flat_map(LayerTracker::into_cache_keys)
+    }
+}
+
+/// The paths of the layer-fs file systems a job runs on. There is one path in `sidecars` for each
+/// of the spec's sidecars, and one path in `layer_mounts` for each of the spec's layer mounts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JobLayerPaths {
+    pub root: PathBuf,
+    pub sidecars: Vec<PathBuf>,
+    pub layer_mounts: Vec<PathBuf>,
 }
 
 /// This struct represents a job that is ready to be executed, but isn't yet executing. These jobs
@@ -250,8 +316,10 @@ struct AwaitingLayersJob {
 struct AvailableJob {
     jid: JobId,
     spec: JobSpec,
-    path: PathBuf,
-    cache_keys: HashSet<cache::Key>,
+    paths: JobLayerPaths,
+    /// Since sidecars and layer mounts may use the same layers as the job, this may contain
+    /// duplicates. We hold a reference for each one.
+    cache_keys: Vec<cache::Key>,
 }
 
 impl PartialEq for AvailableJob {
@@ -283,6 +351,18 @@ impl Ord for AvailableJob {
     }
 }
 
+/// The names of the named caches mounted by a job or any of its sidecars.
+fn cache_mount_names(spec: &JobSpec) -> impl Iterator<Item = &String> {
+    let sidecar_mounts = spec.sidecars.iter().flat_map(|sidecar| &sidecar.mounts);
+    spec.mounts
+        .iter()
+        .chain(sidecar_mounts)
+        .filter_map(|mount| match mount {
+            JobMount::Cache { name, .. } => Some(name),
+            _ => None,
+        })
+}
+
 /// An executing job may have been canceled or timed out, or it may be executing normally.
 enum ExecutingJobState<DepsT: Deps> {
     /// The job is executing normally. It hasn't been canceled or timed-out. When it terminates,
@@ -310,7 +390,7 @@ enum ExecutingJobState<DepsT: Deps> {
 /// and destroyed when we get a `Message::JobCompleted`.
 struct ExecutingJob<DepsT: Deps> {
     state: ExecutingJobState<DepsT>,
-    cache_keys: HashSet<cache::Key>,
+    cache_keys: Vec<cache::Key>,
 }
 
 /// Manage jobs based on the slot count and requests from the broker. If the broker sends more job
@@ -324,9 +404,27 @@ pub struct Dispatcher<DepsT: Deps, ArtifactFetcherT, BrokerSenderT, CacheT> {
     broker_sender: BrokerSenderT,
     cache: CacheT,
     slots: usize,
+    prefetch_budget: PrefetchBudget,
     awaiting_layers: HashMap<JobId, AwaitingLayersJob>,
     available: BinaryHeap<AvailableJob>,
     executing: HashMap<JobId, ExecutingJob<DepsT>>,
+    /// The number of jobs in `awaiting_layers` that are only being prefetched.
+    num_prefetching: usize,
+    /// Jobs that have been prefetched but not enqueued yet, and the number of bytes they added to
+    /// the cache. We don't hold on to their layers, but we still count them against the budget
+    /// until the broker either enqueues or cancels them.
+    prefetched: HashMap<JobId, u64>,
+    /// The number of bytes added to the cache by jobs that are being prefetched or are in
+    /// `prefetched`.
+    prefetch_bytes: u64,
+    /// Named caches reserved by available jobs that couldn't lock them. No other job may start
+    /// using these caches until the reserving job starts or is canceled. Otherwise, a steady
+    /// stream of jobs sharing a cache could keep a job that needs it exclusively waiting forever.
+    cache_mount_reservations: HashMap<String, JobId>,
+    /// Whether our artifact server is accepting connections. We only tell the broker about the
+    /// artifacts we've fetched while it is, since otherwise the broker would send other workers to
+    /// us for nothing.
+    serving_artifacts: bool,
 }
 
 struct Fetcher<'dispatcher, DepsT, ArtifactFetcherT, CacheT> {
@@ -421,24 +519,61 @@ where
     CacheT: Cache,
 {
     /// Start at most one job, depending on whether there are any queued jobs and if there are any
-    /// available slots.
+    /// available slots. Jobs are considered in priority order, but jobs whose named caches are
+    /// locked by executing jobs are skipped. They stay in the queue without taking up a slot, and
+    /// they reserve their caches so that jobs behind them can't keep them locked.
+    ///
+    /// A job is only skipped because of a reservation if some other job holds it. The reserving
+    /// job is itself only waiting for executing jobs, so it will eventually start.
     fn possibly_start_job(&mut self) -> bool {
         if self.executing.len() >= self.slots {
             return false;
         }
-        let Some(AvailableJob {
-            jid,
-            spec,
-            path,
-            cache_keys,
-        }) = self.available.pop()
+        let mut skipped = vec![];
+        let started = loop {
+            let Some(job) = self.available.pop() else {
+                break None;
+            };
+            let reserved = cache_mount_names(&job.spec).any(|name| {
+                self.cache_mount_reservations
+                    .get(name)
+                    .is_some_and(|&jid| jid != job.jid)
+            });
+            if reserved {
+                skipped.push(job);
+                continue;
+            }
+            match self.deps.try_lock_cache_mounts(&job.spec) {
+                Some(cache_mount_locks) => {
+                    self.cache_mount_reservations
+                        .retain(|_, &mut jid| jid != job.jid);
+                    break Some((job, cache_mount_locks));
+                }
+                None => {
+                    for name in cache_mount_names(&job.spec) {
+                        self.cache_mount_reservations.insert(name.clone(), job.jid);
+                    }
+                    skipped.push(job);
+                }
+            }
+        };
+        self.available.extend(skipped);
+        let Some((
+            AvailableJob {
+                jid,
+                spec,
+                paths,
+                cache_keys,
+            },
+            cache_mount_locks,
+        )) = started
         else {
             return false;
         };
         let timer_handle = spec
             .timeout
             .map(|timeout| self.deps.start_timer(jid, Duration::from(timeout)));
-        let job_handle = self.deps.start_job(jid, spec, path);
+        let job_handle = self.deps.start_job(jid, spec, paths, cache_mount_locks);
         let executing_job = ExecutingJob {
             state: ExecutingJobState::Nominal {
                 _job_handle: job_handle,
@@ -456,12 +591,24 @@ where
     }
 
     /// Put a job on the available jobs queue. At this point, it must have all of its artifacts.
-    fn make_job_available(&mut self, jid: JobId, spec: JobSpec, tracker: LayerTracker) {
-        let (path, cache_keys) = tracker.into_path_and_cache_keys();
+    fn make_job_available(&mut self, jid: JobId, spec: JobSpec, trackers: Vec<LayerTracker>) {
+        let mut paths = vec![];
+        let mut cache_keys = vec![];
+        for tracker in trackers {
+            let (path, tracker_cache_keys) = tracker.into_path_and_cache_keys();
+            paths.push(path);
+            cache_keys.extend(tracker_cache_keys);
+        }
+        let layer_mounts = paths.split_off(1 + spec.sidecars.len());
+        let sidecars = paths.split_off(1);
         self.available.push(AvailableJob {
             jid,
             spec,
-            path,
+            paths: JobLayerPaths {
+                root: paths.pop().unwrap(),
+                sidecars,
+                layer_mounts,
+            },
             cache_keys,
         });
         if !self.possibly_start_job() {
@@ -473,20 +620,61 @@ where
         }
     }
 
-    fn receive_enqueue_job(&mut self, jid: JobId, spec: JobSpec) {
+    /// Start getting everything the job needs from the cache.
+    fn start_getting_layers(
+        &mut self,
+        jid: JobId,
+        spec: JobSpec,
+        prefetch_bytes: Option<u64>,
+    ) -> AwaitingLayersJob {
         let mut fetcher = Fetcher {
             deps: &mut self.deps,
             artifact_fetcher: &mut self.artifact_fetcher,
             cache: &mut self.cache,
             jid,
         };
-        let tracker = LayerTracker::new(&spec.layers, &mut fetcher);
-        if tracker.is_complete() {
-            self.make_job_available(jid, spec, tracker);
+        let mut tracker = LayerTracker::new(&spec.layers, &mut fetcher);
+        if let Some(JobStdin::Artifact(digest)) = &spec.stdin {
+            tracker.add_blob(digest, &mut fetcher);
+        }
+        let mut trackers = vec![tracker];
+        for sidecar in &spec.sidecars {
+            trackers.push(LayerTracker::new(&sidecar.layers, &mut fetcher));
+        }
+        for layers in spec.layer_mounts() {
+            trackers.push(LayerTracker::new(layers, &mut fetcher));
+        }
+        AwaitingLayersJob {
+            spec,
+            trackers,
+            prefetch_bytes,
+        }
+    }
+
+    fn receive_enqueue_job(&mut self, jid: JobId, spec: JobSpec) {
+        if let Some(bytes) = self.prefetched.remove(&jid) {
+            // Its layers should still be in the cache, unless they've been evicted since.
+            self.prefetch_bytes -= bytes;
+        } else if let Some(job) = self.awaiting_layers.get_mut(&jid) {
+            if let Some(bytes) = job.prefetch_bytes.take() {
+                // We're already getting the job's layers because of a prefetch hint. From here
+                // on, it's just like any other job.
+                self.num_prefetching -= 1;
+                self.prefetch_bytes -= bytes;
+                self.broker_sender
+                    .send_message_to_broker(WorkerToBroker::JobStatusUpdate(
+                        jid,
+                        JobWorkerStatus::WaitingForLayers,
+                    ));
+                return;
+            }
+        }
+
+        let job = self.start_getting_layers(jid, spec, None);
+        if job.is_complete() {
+            self.make_job_available(jid, job.spec, job.trackers);
         } else {
-            self.awaiting_layers
-                .insert(jid, AwaitingLayersJob { spec, tracker })
-                .assert_is_none();
+            self.awaiting_layers.insert(jid, job).assert_is_none();
             self.broker_sender
                 .send_message_to_broker(WorkerToBroker::JobStatusUpdate(
                     jid,
@@ -495,12 +683,49 @@ where
         }
     }
 
+    /// The broker has hinted that it will send us this job soon. Start getting its layers if we
+    /// have the budget for it. Once we've gotten them, we let them go, so they stay in the cache
+    /// but can be evicted like anything else that isn't in use.
+    fn receive_prefetch(&mut self, jid: JobId, spec: JobSpec) {
+        if self.num_prefetching >= self.prefetch_budget.max_jobs
+            || self.prefetch_bytes >= self.prefetch_budget.cache_bytes
+        {
+            return;
+        }
+        let job = self.start_getting_layers(jid, spec, Some(0));
+        if job.is_complete() {
+            // Everything was already in the cache.
+            for cache::Key { kind, digest } in job.into_cache_keys() {
+                self.cache.decrement_ref_count(kind, &digest);
+            }
+        } else {
+            self.awaiting_layers.insert(jid, job).assert_is_none();
+            self.num_prefetching += 1;
+        }
+    }
+
+    /// A job that was being prefetched got all of its layers.
+    fn finish_prefetch(&mut self, jid: JobId, job: AwaitingLayersJob, bytes: u64) {
+        self.num_prefetching -= 1;
+        self.prefetched.insert(jid, bytes).assert_is_none();
+        for cache::Key { kind, digest } in job.into_cache_keys() {
+            self.cache.decrement_ref_count(kind, &digest);
+        }
+    }
+
     fn receive_cancel_job(&mut self, jid: JobId) {
         if let Some(entry) = self.awaiting_layers.remove(&jid) {
+            if let Some(bytes) = entry.prefetch_bytes {
+                self.num_prefetching -= 1;
+                self.prefetch_bytes -= bytes;
+            }
             // We may have already gotten some layers. Make sure we release those.
-            for cache::Key { kind, digest } in entry.tracker.into_cache_keys() {
+            for cache::Key { kind, digest } in entry.into_cache_keys() {
                 self.cache.decrement_ref_count(kind, &digest);
             }
+        } else if let Some(bytes) = self.prefetched.remove(&jid) {
+            // The job was prefetched, but the broker sent it to another worker.
+            self.prefetch_bytes -= bytes;
         } else if let Some(&mut ExecutingJob { ref mut state, .. }) = self.executing.get_mut(&jid) {
             // The job was executing. We kill the job and cancel a timer if there is one, but we
             // wait around until it's actually teriminated. We don't want to release the layers
@@ -519,6 +744,8 @@ where
                     false
                 }
             });
+            self.cache_mount_reservations
+                .retain(|_, &mut reserver| reserver != jid);
             for cache::Key { kind, digest } in keys_to_drop {
                 self.cache.decrement_ref_count(kind, &digest);
             }
@@ -535,7 +762,7 @@ where
                 self.broker_sender
                     .send_message_to_broker(WorkerToBroker::JobResponse(
                         jid,
-                        result.map(JobOutcome::Completed),
+                        Box::new(result.map(JobOutcome::Completed)),
                     ));
             }
             ExecutingJobState::Canceled => {}
@@ -543,7 +770,7 @@ where
                 self.broker_sender
                     .send_message_to_broker(WorkerToBroker::JobResponse(
                         jid,
-                        result.map(|c| JobOutcome::TimedOut(c.effects)),
+                        Box::new(result.map(|c| JobOutcome::TimedOut(c.effects))),
                     ))
             }
         }
@@ -551,7 +778,10 @@ where
         for cache::Key { kind, digest } in cache_keys {
             self.cache.decrement_ref_count(kind, &digest);
         }
-        self.possibly_start_job();
+
+        // The job released its named caches, so jobs that were waiting for them may be able to
+        // start, in addition to the one that can use this job's slot.
+        while self.possibly_start_job() {}
     }
 
     fn receive_job_timer(&mut self, jid: JobId) {
@@ -584,12 +814,20 @@ where
             //
             // Otherwise, it means that there were previous errors for this entry, or it was
             // canceled, and there's nothing to do here.
-            self.broker_sender
-                .send_message_to_broker(WorkerToBroker::JobResponse(
-                    jid,
-                    Err(JobError::System(format!("{msg} {digest}: {err:?}"))),
-                ));
-            for cache::Key { kind, digest } in entry.tracker.into_cache_keys() {
+            //
+            // If the job was only being prefetched, we don't tell the broker. If it does send us
+            // the job, we'll try again.
+            if let Some(bytes) = entry.prefetch_bytes {
+                self.num_prefetching -= 1;
+                self.prefetch_bytes -= bytes;
+            } else {
+                self.broker_sender
+                    .send_message_to_broker(WorkerToBroker::JobResponse(
+                        jid,
+                        Box::new(Err(JobError::System(format!("{msg} {digest}: {err:?}")))),
+                    ));
+            }
+            for cache::Key { kind, digest } in entry.into_cache_keys() {
                 self.cache.decrement_ref_count(kind, &digest);
             }
         }
@@ -617,6 +855,7 @@ where
         jid: JobId,
         kind: cache::EntryKind,
         digest: &Sha256Digest,
+        is_waiting: impl Fn(&LayerTracker) -> bool,
         cb: impl FnOnce(
             &mut LayerTracker,
             &Sha256Digest,
@@ -638,10 +877,30 @@ where
                     cache: &mut self.cache,
                     jid,
                 };
-                cb(&mut entry.get_mut().tracker, digest, &mut fetcher);
-                if entry.get().tracker.is_complete() {
-                    let AwaitingLayersJob { spec, tracker } = entry.remove();
-                    self.make_job_available(jid, spec, tracker);
+                //
+                // If the job has sidecars or layer mounts, more than one of its trackers may be
+                // waiting on this entry. In that case, the job will be listed once for each of
+                // them, so we just give it to the first one still waiting.
+                //
+                // If none of them are waiting, the job must also have been listed for an earlier
+                // prefetch of it that was abandoned. We release the layer just like we would for
+                // a job that's gone.
+                let Some(tracker) = entry
+                    .get_mut()
+                    .trackers
+                    .iter_mut()
+                    .find(|tracker| is_waiting(tracker))
+                else {
+                    self.cache.decrement_ref_count(kind, digest);
+                    return;
+                };
+                cb(tracker, digest, &mut fetcher);
+                if entry.get().is_complete() {
+                    let job = entry.remove();
+                    match job.prefetch_bytes {
+                        None => self.make_job_available(jid, job.spec, job.trackers),
+                        Some(bytes) => self.finish_prefetch(jid, job, bytes),
+                    }
                 }
             }
         }
@@ -651,7 +910,7 @@ where
         &mut self,
         kind: cache::EntryKind,
         digest: Sha256Digest,
-        bytes_used: u64,
+        stamp: EntryStamp,
         cb: impl Fn(
             &mut LayerTracker,
             &Sha256Digest,
@@ -659,28 +918,46 @@ where
             &mut Fetcher<'_, DepsT, ArtifactFetcherT, CacheT>,
         ),
     ) {
-        let (path, jobs) = self.cache.got_artifact_success(kind, &digest, bytes_used);
+        let bytes_used = stamp.bytes_used;
+        let (path, jobs) = self.cache.got_artifact_success(kind, &digest, stamp);
         for jid in jobs {
-            self.advance_job(jid, kind, &digest, |tracker, digest, fetcher| {
-                cb(tracker, digest, path.clone(), fetcher)
-            });
+            if let Some(AwaitingLayersJob {
+                prefetch_bytes: Some(bytes),
+                ..
+            }) = self.awaiting_layers.get_mut(&jid)
+            {
+                *bytes += bytes_used;
+                self.prefetch_bytes += bytes_used;
+            }
+            self.advance_job(
+                jid,
+                kind,
+                &digest,
+                |tracker| tracker.is_waiting_for(kind, &digest),
+                |tracker, digest, fetcher| cb(tracker, digest, path.clone(), fetcher),
+            );
         }
     }
 
-    fn receive_artifact_success(&mut self, digest: Sha256Digest, bytes_used: u64) {
+    fn receive_artifact_success(&mut self, digest: Sha256Digest, stamp: EntryStamp) {
+        // Let the broker know, so it can send other workers to us for the artifact.
+        if self.serving_artifacts {
+            self.broker_sender
+                .send_message_to_broker(WorkerToBroker::ArtifactCached(digest.clone()));
+        }
         self.cache_fill_success(
             cache::EntryKind::Blob,
             digest,
-            bytes_used,
+            stamp,
             |tracker, digest, path, fetcher| tracker.got_artifact(digest, path, fetcher),
         )
     }
 
-    fn receive_build_bottom_fs_layer_success(&mut self, digest: Sha256Digest, bytes_used: u64) {
+    fn receive_build_bottom_fs_layer_success(&mut self, digest: Sha256Digest, stamp: EntryStamp) {
         self.cache_fill_success(
             cache::EntryKind::BottomFsLayer,
             digest,
-            bytes_used,
+            stamp,
             |tracker, digest, path, fetcher| tracker.got_bottom_fs_layer(digest, path, fetcher),
         )
     }
@@ -690,11 +967,11 @@ where
         self.cache_fill_failure(cache::EntryKind::BottomFsLayer, digest, msg, err)
     }
 
-    fn receive_build_upper_fs_layer_success(&mut self, digest: Sha256Digest, bytes_used: u64) {
+    fn receive_build_upper_fs_layer_success(&mut self, digest: Sha256Digest, stamp: EntryStamp) {
         self.cache_fill_success(
             cache::EntryKind::UpperFsLayer,
             digest,
-            bytes_used,
+            stamp,
             |tracker, digest, path, fetcher| tracker.got_upper_fs_layer(digest, path, fetcher),
         )
     }
@@ -714,6 +991,7 @@ where
             jid,
             cache::EntryKind::Blob,
             &digest,
+            |tracker| tracker.is_waiting_for_manifest_digests(&digest),
             move |tracker, digest, fetcher| {
                 tracker.got_manifest_digests(digest, digests, fetcher);
             },
@@ -734,6 +1012,10 @@ where
         self.broker_sender.close();
         self.awaiting_layers = Default::default();
         self.available = Default::default();
+        self.cache_mount_reservations = Default::default();
+        self.num_prefetching = 0;
+        self.prefetched = Default::default();
+        self.prefetch_bytes = 0;
 
         for jid in self.executing.keys().cloned().collect::<Vec<_>>() {
             self.receive_cancel_job(jid);
@@ -759,14 +1041,17 @@ mod tests {
     use super::{Message::*, *};
     use crate::cache::EntryKind::*;
     use anyhow::anyhow;
-    use maelstrom_base::{self as base, JobEffects, JobOutputResult, JobTerminationStatus};
+    use maelstrom_base::{
+        self as base, nonempty, JobCacheSharing, JobEffects, JobMount, JobOutputResult, JobSidecar,
+        JobTerminationStatus,
+    };
     use maelstrom_test::*;
     use std::{cell::RefCell, rc::Rc, time::Duration};
     use BrokerToWorker::*;
 
     #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
     enum TestMessage {
-        StartJob(JobId, JobSpec, PathBuf),
+        StartJob(JobId, Box<JobSpec>, PathBuf, Vec<PathBuf>, Vec<PathBuf>),
         SendMessageToBroker(WorkerToBroker),
         StartArtifactFetch(Sha256Digest, PathBuf),
         BuildBottomFsLayer(Sha256Digest, PathBuf, ArtifactType, PathBuf),
@@ -788,6 +1073,7 @@ mod tests {
         get_artifact_returns: HashMap<cache::Key, GetArtifact>,
         got_artifact_success_returns: HashMap<cache::Key, (PathBuf, Vec<JobId>)>,
         got_artifact_failure_returns: HashMap<cache::Key, Vec<JobId>>,
+        busy_cache_mounts: HashSet<String>,
         closed: bool,
     }
 
@@ -802,9 +1088,34 @@ mod tests {
     impl Deps for Rc<RefCell<TestState>> {
         type JobHandle = TestHandle;
 
-        fn start_job(&mut self, jid: JobId, spec: JobSpec, path: PathBuf) -> Self::JobHandle {
+        type CacheMountLocks = ();
+
+        fn try_lock_cache_mounts(&mut self, spec: &JobSpec) -> Option<()> {
+            let state = self.borrow();
+            let blocked = spec.mounts.iter().any(|mount| {
+                matches!(
+                    mount,
+                    JobMount::Cache { name, .. } if state.busy_cache_mounts.contains(name)
+                )
+            });
+            (!blocked).then_some(())
+        }
+
+        fn start_job(
+            &mut self,
+            jid: JobId,
+            spec: JobSpec,
+            paths: JobLayerPaths,
+            _cache_mount_locks: (),
+        ) -> Self::JobHandle {
             let mut mut_ref = self.borrow_mut();
-            mut_ref.messages.push(StartJob(jid, spec, path));
+            mut_ref.messages.push(StartJob(
+                jid,
+                Box::new(spec),
+                paths.root,
+                paths.sidecars,
+                paths.layer_mounts,
+            ));
             TestHandle(TestMessage::JobHandleDropped(jid), self.clone())
         }
 
@@ -908,12 +1219,12 @@ mod tests {
             &mut self,
             kind: cache::EntryKind,
             digest: &Sha256Digest,
-            bytes_used: u64,
+            stamp: EntryStamp,
         ) -> (PathBuf, Vec<JobId>) {
             self.borrow_mut().messages.push(CacheGotArtifactSuccess(
                 kind,
                 digest.clone(),
-                bytes_used,
+                stamp.bytes_used,
             ));
             self.borrow_mut()
                 .got_artifact_success_returns
@@ -950,6 +1261,7 @@ mod tests {
                 get_artifact_returns: HashMap::from(get_artifact_returns),
                 got_artifact_success_returns: HashMap::from(got_artifact_success_returns),
                 got_artifact_failure_returns: HashMap::from(got_artifact_failure_returns),
+                busy_cache_mounts: HashSet::default(),
                 closed: false,
             }));
             let dispatcher = Dispatcher::new(
@@ -958,6 +1270,7 @@ mod tests {
                 test_state.clone(),
                 test_state.clone(),
                 Slots::try_from(slots).unwrap(),
+                PrefetchBudget::default(),
             );
             Fixture {
                 test_state,
@@ -965,6 +1278,20 @@ mod tests {
             }
         }
 
+        fn prefetch_budget(mut self, max_jobs: usize, cache_bytes: u64) -> Self {
+            self.dispatcher.prefetch_budget = PrefetchBudget {
+                max_jobs,
+                cache_bytes,
+            };
+            self
+        }
+
+        fn serving_artifacts(mut self) -> Self {
+            self.dispatcher
+                .receive_message(Message::ArtifactServerRunning(true));
+            self
+        }
+
         fn expect_messages_in_any_order(&mut self, mut expected: Vec<TestMessage>) {
             expected.sort();
             let messages = &mut self.test_state.borrow_mut().messages;
@@ -1004,6 +1331,13 @@ mod tests {
         };
     }
 
+    fn stamp(bytes_used: u64) -> EntryStamp {
+        EntryStamp {
+            bytes_used,
+            checksum: digest!(0),
+        }
+    }
+
     macro_rules! script_test {
         ($test_name:ident, $fixture:expr, $($in_msg:expr => { $($out_msg:expr),* $(,)? });+ $(;)?) => {
             #[test]
@@ -1026,13 +1360,13 @@ mod tests {
             (cache_key!(BottomFsLayer, 42), GetArtifact::Success(path_buf!("/b"))),
             (cache_key!(UpperFsLayer, 42, 41), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(1)),
             CacheGetArtifact(Blob, digest!(42), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(42), jid!(1)),
             CacheGetArtifact(UpperFsLayer, upper_digest!(42, 41), jid!(1)),
-            StartJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
         Broker(CancelJob(jid!(1))) => {
@@ -1048,7 +1382,7 @@ mod tests {
             (cache_key!(Blob, 43), GetArtifact::Wait),
             (cache_key!(BottomFsLayer, 41), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(41, Tar), (42, Tar), (43, Tar)]))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar), (43, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(1)),
             CacheGetArtifact(Blob, digest!(42), jid!(1)),
@@ -1076,29 +1410,29 @@ mod tests {
             (cache_key!(BottomFsLayer, 4), GetArtifact::Success(path_buf!("/d"))),
             (cache_key!(BottomFsLayer, 5), GetArtifact::Success(path_buf!("/e"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/b")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/b"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(3), spec!(3, Tar).estimated_duration(Some(millis!(10))))) => {
+        Broker(EnqueueJob(jid!(3), Box::new(spec!(3, Tar).estimated_duration(Some(millis!(10)))))) => {
             CacheGetArtifact(Blob, digest!(3), jid!(3)),
             CacheGetArtifact(BottomFsLayer, digest!(3), jid!(3)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(4), spec!(4, Tar).estimated_duration(Some(millis!(100))))) => {
+        Broker(EnqueueJob(jid!(4), Box::new(spec!(4, Tar).estimated_duration(Some(millis!(100)))))) => {
             CacheGetArtifact(Blob, digest!(4), jid!(4)),
             CacheGetArtifact(BottomFsLayer, digest!(4), jid!(4)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(4), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(5), spec!(5, Tar))) => {
+        Broker(EnqueueJob(jid!(5), Box::new(spec!(5, Tar)))) => {
             CacheGetArtifact(Blob, digest!(5), jid!(5)),
             CacheGetArtifact(BottomFsLayer, digest!(5), jid!(5)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(5), JobWorkerStatus::WaitingToExecute)),
@@ -1107,30 +1441,30 @@ mod tests {
         Broker(CancelJob(jid!(1))) => {
             JobHandleDropped(jid!(1)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
-            StartJob(jid!(5), spec!(5, Tar), path_buf!("/e")),
+            StartJob(jid!(5), Box::new(spec!(5, Tar)), path_buf!("/e"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(5), JobWorkerStatus::Executing)),
         };
 
         Broker(CancelJob(jid!(2))) => {
             JobHandleDropped(jid!(2)),
         };
-        Message::JobCompleted(jid!(2), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(2), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(2)),
             CacheDecrementRefCount(BottomFsLayer, digest!(2)),
-            StartJob(jid!(4), spec!(4, Tar).estimated_duration(Some(millis!(100))), path_buf!("/d")),
+            StartJob(jid!(4), Box::new(spec!(4, Tar).estimated_duration(Some(millis!(100)))), path_buf!("/d"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(4), JobWorkerStatus::Executing)),
         };
 
         Broker(CancelJob(jid!(5))) => {
             JobHandleDropped(jid!(5)),
         };
-        Message::JobCompleted(jid!(5), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(5), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(5)),
             CacheDecrementRefCount(BottomFsLayer, digest!(5)),
-            StartJob(jid!(3), spec!(3, Tar).estimated_duration(Some(millis!(10))), path_buf!("/c")),
+            StartJob(jid!(3), Box::new(spec!(3, Tar).estimated_duration(Some(millis!(10)))), path_buf!("/c"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::Executing)),
         };
     }
@@ -1153,39 +1487,39 @@ mod tests {
             (cache_key!(BottomFsLayer, 6), GetArtifact::Success(path_buf!("/f"))),
             (cache_key!(BottomFsLayer, 7), GetArtifact::Success(path_buf!("/g"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/b")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/b"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(3), spec!(3, Tar).estimated_duration(Some(millis!(30))))) => {
+        Broker(EnqueueJob(jid!(3), Box::new(spec!(3, Tar).estimated_duration(Some(millis!(30)))))) => {
             CacheGetArtifact(Blob, digest!(3), jid!(3)),
             CacheGetArtifact(BottomFsLayer, digest!(3), jid!(3)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(4), spec!(4, Tar).estimated_duration(Some(millis!(40))))) => {
+        Broker(EnqueueJob(jid!(4), Box::new(spec!(4, Tar).estimated_duration(Some(millis!(40)))))) => {
             CacheGetArtifact(Blob, digest!(4), jid!(4)),
             CacheGetArtifact(BottomFsLayer, digest!(4), jid!(4)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(4), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(5), spec!(5, Tar).priority(1).estimated_duration(Some(millis!(10))))) => {
+        Broker(EnqueueJob(jid!(5), Box::new(spec!(5, Tar).priority(1).estimated_duration(Some(millis!(10)))))) => {
             CacheGetArtifact(Blob, digest!(5), jid!(5)),
             CacheGetArtifact(BottomFsLayer, digest!(5), jid!(5)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(5), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(6), spec!(6, Tar).priority(1).estimated_duration(Some(millis!(20))))) => {
+        Broker(EnqueueJob(jid!(6), Box::new(spec!(6, Tar).priority(1).estimated_duration(Some(millis!(20)))))) => {
             CacheGetArtifact(Blob, digest!(6), jid!(6)),
             CacheGetArtifact(BottomFsLayer, digest!(6), jid!(6)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(6), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(7), spec!(7, Tar).priority(-1).estimated_duration(Some(millis!(100))))) => {
+        Broker(EnqueueJob(jid!(7), Box::new(spec!(7, Tar).priority(-1).estimated_duration(Some(millis!(100)))))) => {
             CacheGetArtifact(Blob, digest!(7), jid!(7)),
             CacheGetArtifact(BottomFsLayer, digest!(7), jid!(7)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(7), JobWorkerStatus::WaitingToExecute)),
@@ -1194,50 +1528,50 @@ mod tests {
         Broker(CancelJob(jid!(1))) => {
             JobHandleDropped(jid!(1)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
-            StartJob(jid!(6), spec!(6, Tar).priority(1).estimated_duration(Some(millis!(20))), path_buf!("/f")),
+            StartJob(jid!(6), Box::new(spec!(6, Tar).priority(1).estimated_duration(Some(millis!(20)))), path_buf!("/f"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(6), JobWorkerStatus::Executing)),
         };
 
         Broker(CancelJob(jid!(2))) => {
             JobHandleDropped(jid!(2)),
         };
-        Message::JobCompleted(jid!(2), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(2), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(2)),
             CacheDecrementRefCount(BottomFsLayer, digest!(2)),
-            StartJob(jid!(5), spec!(5, Tar).priority(1).estimated_duration(Some(millis!(10))), path_buf!("/e")),
+            StartJob(jid!(5), Box::new(spec!(5, Tar).priority(1).estimated_duration(Some(millis!(10)))), path_buf!("/e"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(5), JobWorkerStatus::Executing)),
         };
 
         Broker(CancelJob(jid!(6))) => {
             JobHandleDropped(jid!(6)),
         };
-        Message::JobCompleted(jid!(6), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(6), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(6)),
             CacheDecrementRefCount(BottomFsLayer, digest!(6)),
-            StartJob(jid!(4), spec!(4, Tar).estimated_duration(Some(millis!(40))), path_buf!("/d")),
+            StartJob(jid!(4), Box::new(spec!(4, Tar).estimated_duration(Some(millis!(40)))), path_buf!("/d"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(4), JobWorkerStatus::Executing)),
         };
 
         Broker(CancelJob(jid!(5))) => {
             JobHandleDropped(jid!(5)),
         };
-        Message::JobCompleted(jid!(5), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(5), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(5)),
             CacheDecrementRefCount(BottomFsLayer, digest!(5)),
-            StartJob(jid!(3), spec!(3, Tar).estimated_duration(Some(millis!(30))), path_buf!("/c")),
+            StartJob(jid!(3), Box::new(spec!(3, Tar).estimated_duration(Some(millis!(30)))), path_buf!("/c"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::Executing)),
         };
 
         Broker(CancelJob(jid!(4))) => {
             JobHandleDropped(jid!(4)),
         };
-        Message::JobCompleted(jid!(4), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(4), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(4)),
             CacheDecrementRefCount(BottomFsLayer, digest!(4)),
-            StartJob(jid!(7), spec!(7, Tar).priority(-1).estimated_duration(Some(millis!(100))), path_buf!("/g")),
+            StartJob(jid!(7), Box::new(spec!(7, Tar).priority(-1).estimated_duration(Some(millis!(100)))), path_buf!("/g"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(7), JobWorkerStatus::Executing)),
         };
     }
@@ -1249,7 +1583,7 @@ mod tests {
             (cache_key!(BottomFsLayer, 41), GetArtifact::Success(path_buf!("/a"))),
             (cache_key!(Blob, 42), GetArtifact::Wait),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(1)),
             CacheGetArtifact(Blob, digest!(42), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(1)),
@@ -1272,16 +1606,16 @@ mod tests {
             (cache_key!(BottomFsLayer, 43), GetArtifact::Success(path_buf!("/c"))),
             (cache_key!(UpperFsLayer, 42, 41), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(1)),
             CacheGetArtifact(Blob, digest!(42), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(42), jid!(1)),
             CacheGetArtifact(UpperFsLayer, upper_digest!(42, 41), jid!(1)),
-            StartJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, [(43, Tar)]))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, [(43, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(43), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(43), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
@@ -1289,13 +1623,13 @@ mod tests {
         Broker(CancelJob(jid!(1))) => {
             JobHandleDropped(jid!(1)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(BottomFsLayer, digest!(41)),
             CacheDecrementRefCount(BottomFsLayer, digest!(42)),
             CacheDecrementRefCount(UpperFsLayer, upper_digest!(42, 41)),
             CacheDecrementRefCount(Blob, digest!(41)),
             CacheDecrementRefCount(Blob, digest!(42)),
-            StartJob(jid!(2), spec!(2, [(43, Tar)]), path_buf!("/c")),
+            StartJob(jid!(2), Box::new(spec!(2, [(43, Tar)])), path_buf!("/c"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
     }
@@ -1316,19 +1650,19 @@ mod tests {
             (cache_key!(UpperFsLayer, 42, 41), GetArtifact::Success(path_buf!("/a"))),
             (cache_key!(UpperFsLayer, 41, 42, 41), GetArtifact::Success(path_buf!("/b"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/1")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/1"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/2")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/2"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(3), spec!(3, [(41, Tar), (42, Tar), (41, Tar)]))) => {
+        Broker(EnqueueJob(jid!(3), Box::new(spec!(3, [(41, Tar), (42, Tar), (41, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(3)),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(3)),
             CacheGetArtifact(Blob, digest!(42), jid!(3)),
@@ -1337,7 +1671,7 @@ mod tests {
             CacheGetArtifact(UpperFsLayer, upper_digest!(41, 42, 41), jid!(3)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(4), spec!(4, Tar))) => {
+        Broker(EnqueueJob(jid!(4), Box::new(spec!(4, Tar)))) => {
             CacheGetArtifact(Blob, digest!(4), jid!(4)),
             CacheGetArtifact(BottomFsLayer, digest!(4), jid!(4)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(4), JobWorkerStatus::WaitingToExecute)),
@@ -1350,12 +1684,12 @@ mod tests {
             CacheDecrementRefCount(UpperFsLayer, upper_digest!(42, 41)),
             CacheDecrementRefCount(UpperFsLayer, upper_digest!(41, 42, 41)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
             JobHandleDropped(jid!(1)),
-            StartJob(jid!(4), spec!(4, Tar), path_buf!("/4")),
+            StartJob(jid!(4), Box::new(spec!(4, Tar)), path_buf!("/4"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(4), JobWorkerStatus::Executing)),
         };
     }
@@ -1372,10 +1706,10 @@ mod tests {
             (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
         Broker(CancelJob(jid!(1))) => { JobHandleDropped(jid!(1)) };
@@ -1391,14 +1725,14 @@ mod tests {
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/1"))),
             (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/2"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)), path_buf!("/1")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))), path_buf!("/1"), vec![], vec![]),
             StartTimer(jid!(1), Duration::from_secs(1)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
@@ -1408,10 +1742,10 @@ mod tests {
             TimerHandleDropped(jid!(1)),
         };
         Broker(CancelJob(jid!(1))) => {};
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/2")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/2"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
     }
@@ -1438,11 +1772,17 @@ mod tests {
 
         fixture
             .dispatcher
-            .receive_message(Broker(EnqueueJob(jid!(1), spec!(1, Tar))));
+            .receive_message(Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))));
         fixture.expect_messages_in_any_order(vec![
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/1")),
+            StartJob(
+                jid!(1),
+                Box::new(spec!(1, Tar)),
+                path_buf!("/1"),
+                vec![],
+                vec![],
+            ),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
                 jid!(1),
                 JobWorkerStatus::Executing,
@@ -1451,7 +1791,7 @@ mod tests {
 
         fixture
             .dispatcher
-            .receive_message(Broker(EnqueueJob(jid!(2), spec!(2, Tar))));
+            .receive_message(Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))));
         fixture.expect_messages_in_any_order(vec![
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
@@ -1471,7 +1811,7 @@ mod tests {
 
         fixture
             .dispatcher
-            .receive_message(JobCompleted(jid!(1), Ok(completed!(3))));
+            .receive_message(JobCompleted(jid!(1), Box::new(Ok(completed!(3)))));
 
         assert_eq!(fixture.dispatcher.num_executing(), 0);
     }
@@ -1484,27 +1824,256 @@ mod tests {
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
             (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/b"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
             JobHandleDropped(jid!(1)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/b")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/b"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
     }
 
+    #[test]
+    fn job_waiting_for_cache_mount_doesnt_take_slot() {
+        let cache_spec = |spec: JobSpec| {
+            spec.mounts([JobMount::Cache {
+                name: "cargo".into(),
+                mount_point: "/cargo".into(),
+                sharing: JobCacheSharing::Exclusive,
+            }])
+        };
+        let mut fixture = Fixture::new(
+            2,
+            [
+                (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/1"))),
+                (cache_key!(Blob, 2), GetArtifact::Success(path_buf!("/2"))),
+                (cache_key!(Blob, 3), GetArtifact::Success(path_buf!("/3"))),
+                (
+                    cache_key!(BottomFsLayer, 1),
+                    GetArtifact::Success(path_buf!("/1")),
+                ),
+                (
+                    cache_key!(BottomFsLayer, 2),
+                    GetArtifact::Success(path_buf!("/2")),
+                ),
+                (
+                    cache_key!(BottomFsLayer, 3),
+                    GetArtifact::Success(path_buf!("/3")),
+                ),
+            ],
+            [],
+            [],
+        );
+        fixture
+            .test_state
+            .borrow_mut()
+            .busy_cache_mounts
+            .insert("cargo".into());
+
+        fixture.dispatcher.receive_message(Broker(EnqueueJob(
+            jid!(1),
+            Box::new(cache_spec(spec!(1, Tar))),
+        )));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::WaitingToExecute,
+            )),
+        ]);
+        assert_eq!(fixture.dispatcher.num_executing(), 0);
+
+        fixture
+            .dispatcher
+            .receive_message(Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(Blob, digest!(2), jid!(2)),
+            CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
+            StartJob(
+                jid!(2),
+                Box::new(spec!(2, Tar)),
+                path_buf!("/2"),
+                vec![],
+                vec![],
+            ),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(2),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+
+        fixture.dispatcher.receive_message(Broker(EnqueueJob(
+            jid!(3),
+            Box::new(cache_spec(spec!(3, Tar))),
+        )));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(Blob, digest!(3), jid!(3)),
+            CacheGetArtifact(BottomFsLayer, digest!(3), jid!(3)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(3),
+                JobWorkerStatus::WaitingToExecute,
+            )),
+        ]);
+
+        // A job that is waiting for a cache mount can be canceled without waiting for the lock.
+        fixture
+            .dispatcher
+            .receive_message(Broker(CancelJob(jid!(3))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheDecrementRefCount(Blob, digest!(3)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(3)),
+        ]);
+
+        fixture.test_state.borrow_mut().busy_cache_mounts.clear();
+        fixture
+            .dispatcher
+            .receive_message(JobCompleted(jid!(2), Box::new(Ok(completed!(2)))));
+        fixture.expect_messages_in_any_order(vec![
+            SendMessageToBroker(WorkerToBroker::JobResponse(
+                jid!(2),
+                Box::new(Ok(outcome!(2))),
+            )),
+            CacheDecrementRefCount(Blob, digest!(2)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(2)),
+            JobHandleDropped(jid!(2)),
+            StartJob(
+                jid!(1),
+                Box::new(cache_spec(spec!(1, Tar))),
+                path_buf!("/1"),
+                vec![],
+                vec![],
+            ),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+        assert_eq!(fixture.dispatcher.num_executing(), 1);
+    }
+
+    #[test]
+    fn job_waiting_for_cache_mount_reserves_its_caches() {
+        let cache = |name: &str, sharing| JobMount::Cache {
+            name: name.into(),
+            mount_point: format!("/{name}").into(),
+            sharing,
+        };
+        let spec_1 = spec!(1, Tar).mounts([
+            cache("cargo", JobCacheSharing::Exclusive),
+            cache("pip", JobCacheSharing::Exclusive),
+        ]);
+        let spec_2 = spec!(2, Tar).mounts([cache("pip", JobCacheSharing::Shared)]);
+        let mut fixture = Fixture::new(
+            3,
+            [
+                (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/1"))),
+                (cache_key!(Blob, 2), GetArtifact::Success(path_buf!("/2"))),
+                (cache_key!(Blob, 3), GetArtifact::Success(path_buf!("/3"))),
+                (
+                    cache_key!(BottomFsLayer, 1),
+                    GetArtifact::Success(path_buf!("/1")),
+                ),
+                (
+                    cache_key!(BottomFsLayer, 2),
+                    GetArtifact::Success(path_buf!("/2")),
+                ),
+                (
+                    cache_key!(BottomFsLayer, 3),
+                    GetArtifact::Success(path_buf!("/3")),
+                ),
+            ],
+            [],
+            [],
+        );
+        fixture
+            .test_state
+            .borrow_mut()
+            .busy_cache_mounts
+            .insert("cargo".into());
+
+        fixture
+            .dispatcher
+            .receive_message(Broker(EnqueueJob(jid!(3), Box::new(spec!(3, Tar)))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(Blob, digest!(3), jid!(3)),
+            CacheGetArtifact(BottomFsLayer, digest!(3), jid!(3)),
+            StartJob(
+                jid!(3),
+                Box::new(spec!(3, Tar)),
+                path_buf!("/3"),
+                vec![],
+                vec![],
+            ),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(3),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+
+        fixture
+            .dispatcher
+            .receive_message(Broker(EnqueueJob(jid!(1), Box::new(spec_1.clone()))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::WaitingToExecute,
+            )),
+        ]);
+
+        // Nothing holds the pip cache, but job 1 is waiting for it, so job 2 can't take it.
+        fixture
+            .dispatcher
+            .receive_message(Broker(EnqueueJob(jid!(2), Box::new(spec_2.clone()))));
+        fixture.expect_messages_in_any_order(vec![
+            CacheGetArtifact(Blob, digest!(2), jid!(2)),
+            CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(2),
+                JobWorkerStatus::WaitingToExecute,
+            )),
+        ]);
+        assert_eq!(fixture.dispatcher.num_executing(), 1);
+
+        fixture.test_state.borrow_mut().busy_cache_mounts.clear();
+        fixture
+            .dispatcher
+            .receive_message(JobCompleted(jid!(3), Box::new(Ok(completed!(3)))));
+        fixture.expect_messages_in_any_order(vec![
+            SendMessageToBroker(WorkerToBroker::JobResponse(
+                jid!(3),
+                Box::new(Ok(outcome!(3))),
+            )),
+            CacheDecrementRefCount(Blob, digest!(3)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(3)),
+            JobHandleDropped(jid!(3)),
+            StartJob(jid!(1), Box::new(spec_1), path_buf!("/1"), vec![], vec![]),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(1),
+                JobWorkerStatus::Executing,
+            )),
+            StartJob(jid!(2), Box::new(spec_2), path_buf!("/2"), vec![], vec![]),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(
+                jid!(2),
+                JobWorkerStatus::Executing,
+            )),
+        ]);
+    }
+
     script_test! {
         receive_error_job_completed_executing,
         Fixture::new(1, [
@@ -1515,38 +2084,38 @@ mod tests {
             (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/b"))),
             (cache_key!(BottomFsLayer, 3), GetArtifact::Success(path_buf!("/c"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
         };
-        Broker(EnqueueJob(jid!(3), spec!(3, Tar).estimated_duration(Some(millis!(10))))) => {
+        Broker(EnqueueJob(jid!(3), Box::new(spec!(3, Tar).estimated_duration(Some(millis!(10)))))) => {
             CacheGetArtifact(Blob, digest!(3), jid!(3)),
             CacheGetArtifact(BottomFsLayer, digest!(3), jid!(3)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::WaitingToExecute)),
         };
-        Message::JobCompleted(jid!(1), Err(JobError::System(string!("system error")))) => {
+        Message::JobCompleted(jid!(1), Box::new(Err(JobError::System(string!("system error"))))) => {
             SendMessageToBroker(WorkerToBroker::JobResponse(
-                jid!(1), Err(JobError::System(string!("system error"))))),
+                jid!(1), Box::new(Err(JobError::System(string!("system error")))))),
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
             JobHandleDropped(jid!(1)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/b")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/b"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
-        Message::JobCompleted(jid!(2), Err(JobError::Execution(string!("execution error")))) => {
+        Message::JobCompleted(jid!(2), Box::new(Err(JobError::Execution(string!("execution error"))))) => {
             SendMessageToBroker(WorkerToBroker::JobResponse(
-                jid!(2), Err(JobError::Execution(string!("execution error"))))),
+                jid!(2), Box::new(Err(JobError::Execution(string!("execution error")))))),
             CacheDecrementRefCount(Blob, digest!(2)),
             CacheDecrementRefCount(BottomFsLayer, digest!(2)),
             JobHandleDropped(jid!(2)),
-            StartJob(jid!(3), spec!(3, Tar).estimated_duration(Some(millis!(10))), path_buf!("/c")),
+            StartJob(jid!(3), Box::new(spec!(3, Tar).estimated_duration(Some(millis!(10)))), path_buf!("/c"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(3), JobWorkerStatus::Executing)),
         };
     }
@@ -1560,19 +2129,19 @@ mod tests {
             (cache_key!(BottomFsLayer, 42), GetArtifact::Success(path_buf!("/b"))),
             (cache_key!(UpperFsLayer, 42, 41), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(1)),
             CacheGetArtifact(Blob, digest!(42), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(42), jid!(1)),
             CacheGetArtifact(UpperFsLayer, upper_digest!(42, 41), jid!(1)),
-            StartJob(jid!(1), spec!(1, [(41, Tar), (42, Tar)]), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar)])), path_buf!("/a"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
         Broker(CancelJob(jid!(1))) => {
             JobHandleDropped(jid!(1)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(3))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(3)))) => {
             CacheDecrementRefCount(Blob, digest!(41)),
             CacheDecrementRefCount(BottomFsLayer, digest!(41)),
             CacheDecrementRefCount(Blob, digest!(42)),
@@ -1587,7 +2156,7 @@ mod tests {
         let mut fixture = Fixture::new(1, [], [], []);
         fixture
             .dispatcher
-            .receive_message(Message::JobCompleted(jid!(1), Ok(completed!(1))));
+            .receive_message(Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))));
     }
 
     script_test! {
@@ -1596,15 +2165,15 @@ mod tests {
             (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar).timeout(timeout!(33)))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(33))))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar).timeout(timeout!(33)), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(33))), path_buf!("/a"), vec![], vec![]),
             StartTimer(jid!(1), Duration::from_secs(33)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
             TimerHandleDropped(jid!(1)),
@@ -1618,10 +2187,10 @@ mod tests {
             (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar).timeout(timeout!(33)))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(33))))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar).timeout(timeout!(33)), path_buf!("/a")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(33))), path_buf!("/a"), vec![], vec![]),
             StartTimer(jid!(1), Duration::from_secs(33)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
@@ -1639,14 +2208,14 @@ mod tests {
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/1"))),
             (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/2"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)), path_buf!("/1")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))), path_buf!("/1"), vec![], vec![]),
             StartTimer(jid!(1), Duration::from_secs(1)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
@@ -1655,22 +2224,34 @@ mod tests {
             JobHandleDropped(jid!(1)),
             TimerHandleDropped(jid!(1)),
         };
-        Message::JobCompleted(jid!(1), Ok(base::JobCompleted {
+        Message::JobCompleted(jid!(1), Box::new(Ok(base::JobCompleted {
             status: JobTerminationStatus::Exited(0),
             effects: JobEffects {
                 stdout: JobOutputResult::Inline(boxed_u8!(b"stdout")),
                 stderr: JobOutputResult::Inline(boxed_u8!(b"stderr")),
                 duration: std::time::Duration::from_secs(1),
+                resource_usage: None,
+                core_dump: None,
+                core_dump_not_captured: None,
+                writable_layer: None,
+                full_tmpfs_mounts: vec![],
+                blocked_syscalls: vec![],
             }
-        })) => {
+        }))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(JobOutcome::TimedOut(JobEffects {
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(JobOutcome::TimedOut(JobEffects {
                 stdout: JobOutputResult::Inline(boxed_u8!(b"stdout")),
                 stderr: JobOutputResult::Inline(boxed_u8!(b"stderr")),
                 duration: std::time::Duration::from_secs(1),
-            })))),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/2")),
+                resource_usage: None,
+                core_dump: None,
+                core_dump_not_captured: None,
+                writable_layer: None,
+                full_tmpfs_mounts: vec![],
+                blocked_syscalls: vec![],
+            }))))),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/2"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
     }
@@ -1683,25 +2264,25 @@ mod tests {
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/1"))),
             (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/2"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)), path_buf!("/1")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))), path_buf!("/1"), vec![], vec![]),
             StartTimer(jid!(1), Duration::from_secs(1)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
             TimerHandleDropped(jid!(1)),
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
             JobHandleDropped(jid!(1)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/2")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/2"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
         JobTimer(jid!(1)) => {};
@@ -1715,14 +2296,14 @@ mod tests {
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/1"))),
             (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/2"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
-            StartJob(jid!(1), spec!(1, Tar).timeout(timeout!(1)), path_buf!("/1")),
+            StartJob(jid!(1), Box::new(spec!(1, Tar).timeout(timeout!(1))), path_buf!("/1"), vec![], vec![]),
             StartTimer(jid!(1), Duration::from_secs(1)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Broker(EnqueueJob(jid!(2), spec!(2, Tar))) => {
+        Broker(EnqueueJob(jid!(2), Box::new(spec!(2, Tar)))) => {
             CacheGetArtifact(Blob, digest!(2), jid!(2)),
             CacheGetArtifact(BottomFsLayer, digest!(2), jid!(2)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::WaitingToExecute)),
@@ -1732,10 +2313,10 @@ mod tests {
             TimerHandleDropped(jid!(1)),
         };
         JobTimer(jid!(1)) => {};
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
-            StartJob(jid!(2), spec!(2, Tar), path_buf!("/2")),
+            StartJob(jid!(2), Box::new(spec!(2, Tar)), path_buf!("/2"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(2), JobWorkerStatus::Executing)),
         };
     }
@@ -1755,26 +2336,28 @@ mod tests {
         ], [
             (cache_key!(Blob, 42), vec![jid!(1)]),
             (cache_key!(Blob, 44), vec![jid!(1)]),
-        ]),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(41, Tar), (42, Tar), (43, Tar), (44, Tar)]))) => {
+        ]).serving_artifacts(),
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(41, Tar), (42, Tar), (43, Tar), (44, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(41), jid!(1)),
             CacheGetArtifact(Blob, digest!(42), jid!(1)),
             CacheGetArtifact(Blob, digest!(43), jid!(1)),
             CacheGetArtifact(Blob, digest!(44), jid!(1)),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::WaitingForLayers)),
         };
-        ArtifactFetchCompleted(digest!(41), Ok(101)) => {
+        ArtifactFetchCompleted(digest!(41), Ok(stamp(101))) => {
             CacheGotArtifactSuccess(Blob, digest!(41), 101),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(41))),
             CacheGetArtifact(BottomFsLayer, digest!(41), jid!(1)),
         };
         ArtifactFetchCompleted(digest!(42), Err(anyhow!("foo"))) => {
             CacheGotArtifactFailure(Blob, digest!(42)),
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Err(JobError::System(
-                string!("Failed to download and extract layer artifact 000000000000000000000000000000000000000000000000000000000000002a: foo"))))),
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Err(JobError::System(
+                string!("Failed to download and extract layer artifact 000000000000000000000000000000000000000000000000000000000000002a: foo")))))),
             CacheDecrementRefCount(Blob, digest!(41))
         };
-        ArtifactFetchCompleted(digest!(43), Ok(103)) => {
+        ArtifactFetchCompleted(digest!(43), Ok(stamp(103))) => {
             CacheGotArtifactSuccess(Blob, digest!(43), 103),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(43))),
             CacheDecrementRefCount(Blob, digest!(43))
         };
         ArtifactFetchCompleted(digest!(44), Err(anyhow!("foo"))) => {
@@ -1804,10 +2387,10 @@ mod tests {
         );
         fixture
             .dispatcher
-            .receive_message(Broker(EnqueueJob(jid!(1), spec!(1, Tar))));
+            .receive_message(Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))));
         fixture
             .dispatcher
-            .receive_message(Broker(EnqueueJob(jid!(1), spec!(2, Tar))));
+            .receive_message(Broker(EnqueueJob(jid!(1), Box::new(spec!(2, Tar)))));
     }
 
     script_test! {
@@ -1817,19 +2400,272 @@ mod tests {
             (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/1"))),
             (cache_key!(UpperFsLayer, 1, 1), GetArtifact::Success(path_buf!("/1"))),
         ], [], []),
-        Broker(EnqueueJob(jid!(1), spec!(1, [(1, Tar), (1, Tar)]))) => {
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, [(1, Tar), (1, Tar)])))) => {
             CacheGetArtifact(Blob, digest!(1), jid!(1)),
             CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
             CacheGetArtifact(UpperFsLayer, upper_digest!(1, 1), jid!(1)),
-            StartJob(jid!(1), spec!(1, [(1, Tar), (1, Tar)]), path_buf!("/1")),
+            StartJob(jid!(1), Box::new(spec!(1, [(1, Tar), (1, Tar)])), path_buf!("/1"), vec![], vec![]),
             SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
         };
-        Message::JobCompleted(jid!(1), Ok(completed!(1))) => {
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
             CacheDecrementRefCount(Blob, digest!(1)),
             CacheDecrementRefCount(BottomFsLayer, digest!(1)),
             CacheDecrementRefCount(UpperFsLayer, upper_digest!(1, 1)),
-            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Ok(outcome!(1)))),
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
+            JobHandleDropped(jid!(1)),
+        };
+    }
+
+    script_test! {
+        sidecar_layers_are_fetched_before_job_starts,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
+            (cache_key!(Blob, 2), GetArtifact::Get(path_buf!("/b"))),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+            (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/b"))),
+        ], [
+            (cache_key!(Blob, 2), (path_buf!("/b"), vec![jid!(1)])),
+        ], []).serving_artifacts(),
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).sidecars([
+            JobSidecar::new("db", "/db", nonempty![(digest!(2), ArtifactType::Tar)]),
+        ])))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            CacheGetArtifact(Blob, digest!(2), jid!(1)),
+            StartArtifactFetch(digest!(2), path_buf!("/b")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::WaitingForLayers)),
+        };
+        ArtifactFetchCompleted(digest!(2), Ok(stamp(102))) => {
+            CacheGotArtifactSuccess(Blob, digest!(2), 102),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(2))),
+            CacheGetArtifact(BottomFsLayer, digest!(2), jid!(1)),
+            StartJob(
+                jid!(1),
+                Box::new(spec!(1, Tar).sidecars([
+                    JobSidecar::new("db", "/db", nonempty![(digest!(2), ArtifactType::Tar)]),
+                ])),
+                path_buf!("/a"),
+                vec![path_buf!("/b")],
+                vec![],
+            ),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
+        };
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
+            CacheDecrementRefCount(Blob, digest!(1)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(1)),
+            CacheDecrementRefCount(Blob, digest!(2)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(2)),
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
             JobHandleDropped(jid!(1)),
         };
     }
+
+    script_test! {
+        layer_mount_layers_are_fetched_before_job_starts,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
+            (cache_key!(Blob, 2), GetArtifact::Get(path_buf!("/b"))),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+            (cache_key!(BottomFsLayer, 2), GetArtifact::Success(path_buf!("/b"))),
+        ], [
+            (cache_key!(Blob, 2), (path_buf!("/b"), vec![jid!(1)])),
+        ], []).serving_artifacts(),
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar).mounts([
+            JobMount::Layer {
+                mount_point: "/opt".into(),
+                layers: nonempty![(digest!(2), ArtifactType::Tar)],
+            },
+        ])))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            CacheGetArtifact(Blob, digest!(2), jid!(1)),
+            StartArtifactFetch(digest!(2), path_buf!("/b")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::WaitingForLayers)),
+        };
+        ArtifactFetchCompleted(digest!(2), Ok(stamp(102))) => {
+            CacheGotArtifactSuccess(Blob, digest!(2), 102),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(2))),
+            CacheGetArtifact(BottomFsLayer, digest!(2), jid!(1)),
+            StartJob(
+                jid!(1),
+                Box::new(spec!(1, Tar).mounts([
+                    JobMount::Layer {
+                        mount_point: "/opt".into(),
+                        layers: nonempty![(digest!(2), ArtifactType::Tar)],
+                    },
+                ])),
+                path_buf!("/a"),
+                vec![],
+                vec![path_buf!("/b")],
+            ),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
+        };
+        Message::JobCompleted(jid!(1), Box::new(Ok(completed!(1)))) => {
+            CacheDecrementRefCount(Blob, digest!(1)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(1)),
+            CacheDecrementRefCount(Blob, digest!(2)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(2)),
+            SendMessageToBroker(WorkerToBroker::JobResponse(jid!(1), Box::new(Ok(outcome!(1))))),
+            JobHandleDropped(jid!(1)),
+        };
+    }
+
+    script_test! {
+        artifact_cached_not_sent_once_artifact_server_stops,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Get(path_buf!("/a"))),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+        ], [
+            (cache_key!(Blob, 1), (path_buf!("/a"), vec![jid!(1)])),
+        ], []).serving_artifacts(),
+        Message::ArtifactServerRunning(false) => {};
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            StartArtifactFetch(digest!(1), path_buf!("/a")),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::WaitingForLayers)),
+        };
+        ArtifactFetchCompleted(digest!(1), Ok(stamp(101))) => {
+            CacheGotArtifactSuccess(Blob, digest!(1), 101),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
+        };
+    }
+
+    script_test! {
+        prefetch_gets_layers_then_releases_them,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Get(path_buf!("/a"))),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+        ], [
+            (cache_key!(Blob, 1), (path_buf!("/a"), vec![jid!(1)])),
+        ], []).prefetch_budget(1, 1000).serving_artifacts(),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            StartArtifactFetch(digest!(1), path_buf!("/a")),
+        };
+        ArtifactFetchCompleted(digest!(1), Ok(stamp(101))) => {
+            CacheGotArtifactSuccess(Blob, digest!(1), 101),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(1))),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            CacheDecrementRefCount(Blob, digest!(1)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(1)),
+        };
+        Broker(CancelJob(jid!(1))) => {};
+    }
+
+    script_test! {
+        prefetch_is_adopted_by_enqueue,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Get(path_buf!("/a"))),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+        ], [
+            (cache_key!(Blob, 1), (path_buf!("/a"), vec![jid!(1)])),
+        ], []).prefetch_budget(1, 1000).serving_artifacts(),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            StartArtifactFetch(digest!(1), path_buf!("/a")),
+        };
+        Broker(EnqueueJob(jid!(1), Box::new(spec!(1, Tar)))) => {
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::WaitingForLayers)),
+        };
+        ArtifactFetchCompleted(digest!(1), Ok(stamp(101))) => {
+            CacheGotArtifactSuccess(Blob, digest!(1), 101),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(1))),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            StartJob(jid!(1), Box::new(spec!(1, Tar)), path_buf!("/a"), vec![], vec![]),
+            SendMessageToBroker(WorkerToBroker::JobStatusUpdate(jid!(1), JobWorkerStatus::Executing)),
+        };
+    }
+
+    script_test! {
+        prefetch_of_cached_layers_releases_them_immediately,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+        ], [], []).prefetch_budget(1, 1000),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            CacheDecrementRefCount(Blob, digest!(1)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(1)),
+        };
+    }
+
+    script_test! {
+        prefetch_is_ignored_when_too_many_jobs_are_being_prefetched,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Wait),
+            (cache_key!(Blob, 2), GetArtifact::Wait),
+        ], [], []).prefetch_budget(1, 1000),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+        };
+        Broker(Prefetch(jid!(2), Box::new(spec!(2, Tar)))) => {};
+        Broker(CancelJob(jid!(1))) => {};
+        Broker(Prefetch(jid!(2), Box::new(spec!(2, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(2), jid!(2)),
+        };
+    }
+
+    script_test! {
+        prefetch_is_ignored_when_prefetched_layers_fill_budget,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Get(path_buf!("/a"))),
+            (cache_key!(Blob, 2), GetArtifact::Wait),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+        ], [
+            (cache_key!(Blob, 1), (path_buf!("/a"), vec![jid!(1)])),
+        ], []).prefetch_budget(2, 100).serving_artifacts(),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            StartArtifactFetch(digest!(1), path_buf!("/a")),
+        };
+        ArtifactFetchCompleted(digest!(1), Ok(stamp(101))) => {
+            CacheGotArtifactSuccess(Blob, digest!(1), 101),
+            SendMessageToBroker(WorkerToBroker::ArtifactCached(digest!(1))),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            CacheDecrementRefCount(Blob, digest!(1)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(1)),
+        };
+        Broker(Prefetch(jid!(2), Box::new(spec!(2, Tar)))) => {};
+        Broker(CancelJob(jid!(1))) => {};
+        Broker(Prefetch(jid!(2), Box::new(spec!(2, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(2), jid!(2)),
+        };
+    }
+
+    script_test! {
+        cancel_prefetch_releases_layers,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Success(path_buf!("/a"))),
+            (cache_key!(Blob, 2), GetArtifact::Wait),
+            (cache_key!(BottomFsLayer, 1), GetArtifact::Success(path_buf!("/a"))),
+        ], [], []).prefetch_budget(1, 1000),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, [(1, Tar), (2, Tar)])))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            CacheGetArtifact(BottomFsLayer, digest!(1), jid!(1)),
+            CacheGetArtifact(Blob, digest!(2), jid!(1)),
+        };
+        Broker(CancelJob(jid!(1))) => {
+            CacheDecrementRefCount(Blob, digest!(1)),
+            CacheDecrementRefCount(BottomFsLayer, digest!(1)),
+        };
+    }
+
+    script_test! {
+        prefetch_errors_are_not_sent_to_broker,
+        Fixture::new(1, [
+            (cache_key!(Blob, 1), GetArtifact::Get(path_buf!("/a"))),
+        ], [], [
+            (cache_key!(Blob, 1), vec![jid!(1)]),
+        ]).prefetch_budget(1, 1000),
+        Broker(Prefetch(jid!(1), Box::new(spec!(1, Tar)))) => {
+            CacheGetArtifact(Blob, digest!(1), jid!(1)),
+            StartArtifactFetch(digest!(1), path_buf!("/a")),
+        };
+        ArtifactFetchCompleted(digest!(1), Err(anyhow!("foo"))) => {
+            CacheGotArtifactFailure(Blob, digest!(1)),
+        };
+    }
 }
@@ -79,7 +79,8 @@ pub fn upper_layer_digest(upper_layer: &Sha256Digest, lower_layer: &Sha256Digest
     Sha256Digest::new(hasher.finalize().into())
 }
 
-/// Track which layers have been gotten from the cache.
+/// Track which layers have been gotten from the cache. This also tracks any other artifacts the job
+/// needs as plain blobs, like the job's stdin.
 #[derive(Debug)]
 pub struct LayerTracker {
     layers: NonEmpty<Sha256Digest>,
@@ -87,6 +88,7 @@ pub struct LayerTracker {
     top_fs_layer: PendingTopLayer,
     cache_keys: HashSet<Key>,
     pending_manifest_dependencies: HashMap<Sha256Digest, Vec<Sha256Digest>>,
+    pending_blobs: HashSet<Sha256Digest>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -123,6 +125,7 @@ impl LayerTracker {
             top_fs_layer: PendingTopLayer::NoStackedUpperLayers,
             cache_keys: HashSet::new(),
             pending_manifest_dependencies: HashMap::new(),
+            pending_blobs: HashSet::new(),
         };
         let mut seen = HashMap::<Sha256Digest, ArtifactType>::new();
         for (digest, type_) in layers {
@@ -152,51 +155,86 @@ impl LayerTracker {
         tracker
     }
 
+    /// Require an artifact that isn't a layer. The job won't be complete until it has been gotten.
+    pub fn add_blob(&mut self, digest: &Sha256Digest, fetcher: &mut impl Fetcher) {
+        if self
+            .cache_keys
+            .contains(&Key::new(EntryKind::Blob, digest.clone()))
+        {
+            return;
+        }
+        if self.bottom_layers.contains_key(digest)
+            || self.pending_manifest_dependencies.contains_key(digest)
+        {
+            // Already being fetched.
+            self.pending_blobs.insert(digest.clone());
+            return;
+        }
+        match fetcher.fetch_artifact(digest) {
+            FetcherResult::Got(_) => {
+                self.cache_keys
+                    .insert(Key::new(EntryKind::Blob, digest.clone()));
+            }
+            FetcherResult::Pending => {
+                self.pending_blobs.insert(digest.clone());
+            }
+        }
+    }
+
     fn bottom_layers_all_ready(&self) -> bool {
         self.bottom_layers
             .values()
             .all(|e| matches!(e, PendingBottomLayer::Ready { .. }))
     }
 
-    fn fetch_upper_layers(&mut self, fetcher: &mut impl Fetcher) {
+    /// Return the digest of the next upper layer to stack, along with the paths of the layers that
+    /// go into it. All bottom layers must be ready.
+    fn next_upper_layer(&self) -> Option<(Sha256Digest, &PathBuf, &PathBuf)> {
         if self.layers.len() < 2 {
-            return;
+            return None;
         }
-        loop {
-            let (upper_index, lower_digest, lower_path) = match &self.top_fs_layer {
-                PendingTopLayer::NoStackedUpperLayers => {
-                    let digest = &self.layers[0];
-                    let path = self
-                        .bottom_layers
-                        .get(digest)
-                        .unwrap()
-                        .assert_ready_and_get_path();
-                    (1, digest, path)
-                }
-                PendingTopLayer::StackedUpperLayers {
-                    index,
-                    top_layer_digest,
-                    top_layer_path,
-                } => (*index, top_layer_digest, top_layer_path),
-            };
-
-            if upper_index >= self.layers.len() {
-                break;
+        let (upper_index, lower_digest, lower_path) = match &self.top_fs_layer {
+            PendingTopLayer::NoStackedUpperLayers => {
+                let digest = &self.layers[0];
+                let path = self
+                    .bottom_layers
+                    .get(digest)
+                    .unwrap()
+                    .assert_ready_and_get_path();
+                (1, digest, path)
             }
+            PendingTopLayer::StackedUpperLayers {
+                index,
+                top_layer_digest,
+                top_layer_path,
+            } => (*index, top_layer_digest, top_layer_path),
+        };
+
+        if upper_index >= self.layers.len() {
+            return None;
+        }
 
-            let upper_digest = &self.layers[upper_index];
-            let upper_path = self
-                .bottom_layers
-                .get(upper_digest)
-                .unwrap()
-                .assert_ready_and_get_path();
+        let upper_digest = &self.layers[upper_index];
+        let upper_path = self
+            .bottom_layers
+            .get(upper_digest)
+            .unwrap()
+            .assert_ready_and_get_path();
+
+        Some((
+            upper_layer_digest(upper_digest, lower_digest),
+            lower_path,
+            upper_path,
+        ))
+    }
 
-            let digest = upper_layer_digest(upper_digest, lower_digest);
+    fn fetch_upper_layers(&mut self, fetcher: &mut impl Fetcher) {
+        while let Some((digest, lower_path, upper_path)) = self.next_upper_layer() {
             match fetcher.fetch_upper_fs_layer(&digest, lower_path, upper_path) {
                 FetcherResult::Got(path) => {
                     self.cache_keys
                         .insert(Key::new(EntryKind::UpperFsLayer, digest.clone()));
-                    self.top_fs_layer.add_layer(digest.clone(), path)
+                    self.top_fs_layer.add_layer(digest, path)
                 }
                 FetcherResult::Pending => break,
             }
@@ -226,11 +264,14 @@ impl LayerTracker {
         self.cache_keys
             .insert(Key::new(EntryKind::Blob, digest.clone()));
 
-        if self.pending_manifest_dependencies.contains_key(digest) {
+        let was_pending_blob = self.pending_blobs.remove(digest);
+        let was_manifest_dependency = self.pending_manifest_dependencies.contains_key(digest);
+        if was_manifest_dependency {
             self.got_manifest_artifact(digest, fetcher);
-            if !self.bottom_layers.contains_key(digest) {
-                return;
-            }
+        }
+        if (was_pending_blob || was_manifest_dependency) && !self.bottom_layers.contains_key(digest)
+        {
+            return;
         }
 
         let PendingBottomLayer::WaitingForArtifact { type_ } =
@@ -295,6 +336,7 @@ impl LayerTracker {
                 pending_entry.push(manifest_digest.clone());
                 num_remaining += 1;
             } else if bottom_layer_keys.contains(&digest)
+                || self.pending_blobs.contains(&digest)
                 || fetcher.fetch_artifact(&digest) == FetcherResult::Pending
             {
                 self.pending_manifest_dependencies
@@ -345,7 +387,51 @@ impl LayerTracker {
         self.fetch_upper_layers(fetcher);
     }
 
+    /// Return whether we've asked the fetcher for the given entry and are still waiting for it.
+    /// Since a job can have more than one tracker, it may be waiting for the same entry more than
+    /// once. This is used to decide which tracker a completed entry belongs to.
+    pub fn is_waiting_for(&self, kind: EntryKind, digest: &Sha256Digest) -> bool {
+        match kind {
+            EntryKind::Blob => {
+                !self
+                    .cache_keys
+                    .contains(&Key::new(EntryKind::Blob, digest.clone()))
+                    && (matches!(
+                        self.bottom_layers.get(digest),
+                        Some(PendingBottomLayer::WaitingForArtifact { .. })
+                    ) || self.pending_blobs.contains(digest)
+                        || self.pending_manifest_dependencies.contains_key(digest))
+            }
+            EntryKind::BottomFsLayer => matches!(
+                self.bottom_layers.get(digest),
+                Some(PendingBottomLayer::WaitingForFsLayer)
+            ),
+            EntryKind::UpperFsLayer => {
+                self.bottom_layers_all_ready()
+                    && self
+                        .next_upper_layer()
+                        .is_some_and(|(next, _, _)| next == *digest)
+            }
+        }
+    }
+
+    /// Return whether we've asked the fetcher for the digests in the given manifest and are still
+    /// waiting for them.
+    pub fn is_waiting_for_manifest_digests(&self, digest: &Sha256Digest) -> bool {
+        matches!(
+            self.bottom_layers.get(digest),
+            Some(PendingBottomLayer::WaitingForManifestDigests {
+                pending_digests: PendingManifestDigests::WaitingForDigests,
+                ..
+            })
+        )
+    }
+
     pub fn is_complete(&self) -> bool {
+        self.pending_blobs.is_empty() && self.layers_complete()
+    }
+
+    fn layers_complete(&self) -> bool {
         matches!(
             self.top_fs_layer,
             PendingTopLayer::StackedUpperLayers { index, .. } if index >= self.layers.len()
@@ -1244,4 +1330,137 @@ mod tests {
             ),
         );
     }
+
+    #[test]
+    fn one_layer_and_blob_everything_in_cache_into_path_and_cache_keys() {
+        let layers = nonempty![(digest!(1), ArtifactType::Tar)];
+        let mut fetcher = TestFetcher::new(
+            [
+                (digest!(1), FetcherResult::Got(path_buf!("/blob/1"))),
+                (digest!(2), FetcherResult::Got(path_buf!("/blob/2"))),
+            ],
+            [(digest!(1), FetcherResult::Got(path_buf!("/fs_b/1")))],
+            [],
+            [],
+        );
+        let mut tracker = LayerTracker::new(&layers, &mut fetcher);
+        tracker.add_blob(&digest!(2), &mut fetcher);
+
+        assert!(tracker.is_complete());
+        assert_eq!(
+            tracker.into_path_and_cache_keys(),
+            (
+                path_buf!("/fs_b/1"),
+                hashset! {
+                    Key::new(EntryKind::Blob, digest!(1)),
+                    Key::new(EntryKind::Blob, digest!(2)),
+                    Key::new(EntryKind::BottomFsLayer, digest!(1)),
+                }
+            ),
+        );
+    }
+
+    #[test]
+    fn one_layer_and_blob_pending_then_got_into_path_and_cache_keys() {
+        let layers = nonempty![(digest!(1), ArtifactType::Tar)];
+        let mut fetcher = TestFetcher::new(
+            [
+                (digest!(1), FetcherResult::Got(path_buf!("/blob/1"))),
+                (digest!(2), FetcherResult::Pending),
+            ],
+            [(digest!(1), FetcherResult::Got(path_buf!("/fs_b/1")))],
+            [],
+            [],
+        );
+        let mut tracker = LayerTracker::new(&layers, &mut fetcher);
+        tracker.add_blob(&digest!(2), &mut fetcher);
+        assert!(!tracker.is_complete());
+
+        tracker.got_artifact(&digest!(2), path_buf!("/blob/2"), &mut fetcher);
+        assert!(tracker.is_complete());
+        assert_eq!(
+            tracker.into_path_and_cache_keys(),
+            (
+                path_buf!("/fs_b/1"),
+                hashset! {
+                    Key::new(EntryKind::Blob, digest!(1)),
+                    Key::new(EntryKind::Blob, digest!(2)),
+                    Key::new(EntryKind::BottomFsLayer, digest!(1)),
+                }
+            ),
+        );
+    }
+
+    #[test]
+    fn blob_same_as_pending_layer_fetched_once() {
+        let layers = nonempty![(digest!(1), ArtifactType::Tar)];
+        let mut fetcher = TestFetcher::new(
+            [(digest!(1), FetcherResult::Pending)],
+            [(digest!(1), FetcherResult::Got(path_buf!("/fs_b/1")))],
+            [],
+            [],
+        );
+        let mut tracker = LayerTracker::new(&layers, &mut fetcher);
+        tracker.add_blob(&digest!(1), &mut fetcher);
+        assert!(!tracker.is_complete());
+
+        tracker.got_artifact(&digest!(1), path_buf!("/blob/1"), &mut fetcher);
+        assert!(tracker.is_complete());
+        assert_eq!(
+            tracker.into_path_and_cache_keys(),
+            (
+                path_buf!("/fs_b/1"),
+                hashset! {
+                    Key::new(EntryKind::Blob, digest!(1)),
+                    Key::new(EntryKind::BottomFsLayer, digest!(1)),
+                }
+            ),
+        );
+    }
+
+    #[test]
+    fn is_waiting_for_follows_progress() {
+        let layers = nonempty![
+            (digest!(1), ArtifactType::Tar),
+            (digest!(2), ArtifactType::Manifest)
+        ];
+        let mut fetcher = TestFetcher::new(
+            [
+                (digest!(1), FetcherResult::Pending),
+                (digest!(2), FetcherResult::Got(path_buf!("/blob/2"))),
+                (digest!(3), FetcherResult::Pending),
+            ],
+            [
+                (digest!(1), FetcherResult::Pending),
+                (digest!(2), FetcherResult::Got(path_buf!("/fs_b/2"))),
+            ],
+            [(upper_digest!(2, 1), FetcherResult::Pending)],
+            [(digest!(2), path_buf!("/blob/2"))],
+        );
+        let mut tracker = LayerTracker::new(&layers, &mut fetcher);
+        assert!(tracker.is_waiting_for(EntryKind::Blob, &digest!(1)));
+        assert!(!tracker.is_waiting_for(EntryKind::Blob, &digest!(2)));
+        assert!(tracker.is_waiting_for_manifest_digests(&digest!(2)));
+
+        tracker.got_manifest_digests(&digest!(2), hashset! { digest!(3) }, &mut fetcher);
+        assert!(!tracker.is_waiting_for_manifest_digests(&digest!(2)));
+        assert!(tracker.is_waiting_for(EntryKind::Blob, &digest!(3)));
+
+        tracker.got_artifact(&digest!(3), path_buf!("/blob/3"), &mut fetcher);
+        assert!(!tracker.is_waiting_for(EntryKind::Blob, &digest!(3)));
+
+        tracker.got_artifact(&digest!(1), path_buf!("/blob/1"), &mut fetcher);
+        assert!(!tracker.is_waiting_for(EntryKind::Blob, &digest!(1)));
+        assert!(tracker.is_waiting_for(EntryKind::BottomFsLayer, &digest!(1)));
+        assert!(!tracker.is_waiting_for(EntryKind::UpperFsLayer, &upper_digest!(2, 1)));
+
+        tracker.got_bottom_fs_layer(&digest!(1), path_buf!("/fs_b/1"), &mut fetcher);
+        assert!(!tracker.is_waiting_for(EntryKind::BottomFsLayer, &digest!(1)));
+        assert!(tracker.is_waiting_for(EntryKind::UpperFsLayer, &upper_digest!(2, 1)));
+        assert!(!tracker.is_waiting_for(EntryKind::UpperFsLayer, &upper_digest!(1, 2)));
+
+        tracker.got_upper_fs_layer(&upper_digest!(2, 1), path_buf!("/fs_u/1"), &mut fetcher);
+        assert!(!tracker.is_waiting_for(EntryKind::UpperFsLayer, &upper_digest!(2, 1)));
+        assert!(tracker.is_complete());
+    }
 }
@@ -1,5 +1,11 @@
 //! Easily start and stop processes.
 
+use crate::{
+    cgroup::{JobCgroup, JobCgroups},
+    core_dump, seccomp,
+    user_files::UserFiles,
+    writable_layer,
+};
 use anyhow::{anyhow, Error, Result};
 use bumpalo::{
     collections::{CollectIn as _, String as BumpString, Vec as BumpVec},
@@ -7,37 +13,45 @@ use bumpalo::{
 };
 use maelstrom_base::{
     tty::{self, DecodeInputChunk, DecodeInputRemainder},
-    GroupId, JobCompleted, JobDevice, JobEffects, JobError, JobMount, JobNetwork, JobOutputResult,
-    JobResult, JobRootOverlay, JobTerminationStatus, JobTty, UserId, Utf8PathBuf, WindowSize,
+    validate_hostname, GroupId, JobClockOffsets, JobCompleted, JobDevice, JobEffects, JobError,
+    JobForward, JobHost, JobLimits, JobMount, JobNetwork, JobOutputResult, JobReadinessCheck,
+    JobResourceUsage, JobResult, JobRlimit, JobRlimitResource, JobRootOverlay, JobSeccomp,
+    JobSeccompAction, JobSidecar, JobTerminationStatus, JobTty, UserId, Utf8Component, Utf8Path,
+    Utf8PathBuf, WindowSize,
 };
+use maelstrom_layer_fs::BlobDir;
 use maelstrom_linux::{
     self as linux, CloneArgs, CloneFlags, CloseRangeFirst, CloseRangeFlags, CloseRangeLast, Errno,
-    Fd, FileMode, FsconfigCommand, FsmountFlags, FsopenFlags, Gid, MountAttrs, MountFlags,
-    MoveMountFlags, OpenFlags, OpenTreeFlags, OwnedFd, Signal, SockaddrNetlink, SockaddrUnStorage,
+    Fd, FileMode, FsconfigCommand, FsmountFlags, FsopenFlags, Gid, MapFlags, MountAttrs,
+    MountFlags, MoveMountFlags, OpenFlags, OpenTreeFlags, OwnedFd, Pid, ProtFlags, Rlimit,
+    RlimitResource, Rusage, Signal, Sockaddr, SockaddrIn, SockaddrNetlink, SockaddrUnStorage,
     SocketDomain, SocketProtocol, SocketType, Uid, UmountFlags, WaitStatus,
 };
 use maelstrom_util::{
     config::common::InlineLimit,
     io::AsyncFile,
-    root::RootBuf,
+    root::{Root, RootBuf},
     sync::EventReceiver,
     time::{Clock, ClockInstant as _},
     tty::open_pseudoterminal,
 };
-use maelstrom_worker_child::{FdSlot, Syscall};
+use maelstrom_worker_child::{ChildArgs, FdSlot, ProcessCountSlot, Syscall, WaitStatusSlot};
 use netlink_packet_core::{NetlinkMessage, NLM_F_ACK, NLM_F_CREATE, NLM_F_EXCL, NLM_F_REQUEST};
 use netlink_packet_route::{rtnl::constants::RTM_SETLINK, LinkMessage, RtnlMessage, IFF_UP};
 use std::{
     cell::UnsafeCell,
+    collections::BTreeSet,
     ffi::{CStr, CString},
     fmt::Write as _,
     mem,
     os::unix::{ffi::OsStrExt as _, fs::MetadataExt},
-    result,
+    path::PathBuf,
+    result, slice,
+    time::{Duration, SystemTime},
 };
 use tokio::{
     io::{self, unix::AsyncFd, AsyncReadExt as _, AsyncWriteExt as _, Interest},
-    net::UnixStream,
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
     runtime, select,
     sync::oneshot,
     task::JoinSet,
@@ -64,10 +78,93 @@ pub struct JobSpec {
     pub user: Option<UserId>,
     pub group: Option<GroupId>,
     pub allocate_tty: Option<JobTty>,
+    pub stdin: Option<JobStdin>,
+    pub limits: JobLimits,
+    pub rlimits: Vec<JobRlimit>,
+    pub seccomp: Option<JobSeccomp>,
+    pub init: bool,
+    pub forwards: Vec<JobForward>,
+    pub sidecars: Vec<Sidecar>,
+    /// The `/etc/passwd`, `/etc/group`, and home directory to layer over the job's root. These are
+    /// computed from the job's image, so they have to be filled in separately.
+    pub user_files: Option<UserFiles>,
+    pub hostname: Option<String>,
+    /// Entries to add to the job's `/etc/hosts`. The file is generated if there are any, or if the
+    /// job has a hostname.
+    pub extra_hosts: Vec<JobHost>,
+    pub clock_offsets: Option<JobClockOffsets>,
+    /// Where to save the job's core file, if it dumps one. Core files are only looked for if this
+    /// is set.
+    pub core_dump_path: Option<PathBuf>,
+    /// Where to save a tar file of the job's root overlay and `tmp` mounts if the job fails. They
+    /// are only saved if this is set.
+    pub writable_layer_path: Option<PathBuf>,
+}
+
+/// A sidecar to run alongside a job. Only the parts of `spec` that make sense for a sidecar are
+/// filled in. The rest are left empty.
+pub struct Sidecar {
+    pub name: String,
+    pub spec: JobSpec,
+    pub ready: Option<JobReadinessCheck>,
+}
+
+impl Sidecar {
+    fn from_sidecar(sidecar: JobSidecar) -> Self {
+        let JobSidecar {
+            name,
+            program,
+            arguments,
+            environment,
+            layers: _,
+            mounts,
+            root_overlay,
+            working_directory,
+            user,
+            group,
+            ready,
+        } = sidecar;
+        Sidecar {
+            name,
+            spec: JobSpec {
+                program,
+                arguments,
+                environment,
+                mounts,
+                network: JobNetwork::Loopback,
+                root_overlay,
+                working_directory,
+                user,
+                group,
+                allocate_tty: None,
+                stdin: None,
+                limits: Default::default(),
+                rlimits: vec![],
+                seccomp: None,
+                init: false,
+                forwards: vec![],
+                sidecars: vec![],
+                user_files: None,
+                hostname: None,
+                extra_hosts: vec![],
+                clock_offsets: None,
+                core_dump_path: None,
+                writable_layer_path: None,
+            },
+            ready,
+        }
+    }
+}
+
+/// Where the job's stdin comes from. Unlike [`maelstrom_base::JobStdin`], an artifact has already
+/// been resolved to its path in the cache.
+pub enum JobStdin {
+    Inline(Box<[u8]>),
+    File(PathBuf),
 }
 
 impl JobSpec {
-    pub fn from_spec(spec: maelstrom_base::JobSpec) -> Self {
+    pub fn from_spec(spec: maelstrom_base::JobSpec, blob_dir: &Root<BlobDir>) -> Self {
         let maelstrom_base::JobSpec {
             program,
             arguments,
@@ -81,6 +178,16 @@ impl JobSpec {
             group,
             estimated_duration: _,
             allocate_tty,
+            stdin,
+            limits,
+            rlimits,
+            seccomp,
+            init,
+            forwards,
+            sidecars,
+            hostname,
+            extra_hosts,
+            clock_offsets,
             ..
         } = spec;
         JobSpec {
@@ -94,13 +201,71 @@ impl JobSpec {
             user,
             group,
             allocate_tty,
+            stdin: stdin.map(|stdin| match stdin {
+                maelstrom_base::JobStdin::Inline(bytes) => JobStdin::Inline(bytes),
+                maelstrom_base::JobStdin::Artifact(digest) => {
+                    JobStdin::File(blob_dir.join::<()>(digest.to_string()).into_path_buf())
+                }
+            }),
+            limits,
+            rlimits,
+            seccomp,
+            init,
+            forwards,
+            sidecars: sidecars.into_iter().map(Sidecar::from_sidecar).collect(),
+            user_files: None,
+            hostname,
+            extra_hosts,
+            clock_offsets,
+            core_dump_path: None,
+            writable_layer_path: None,
         }
     }
+
+    /// Whether the job needs a layer of generated files between its image and its overlay.
+    fn has_extra_layer(&self) -> bool {
+        self.user_files.is_some() || self.has_hosts_file()
+    }
+
+    /// Whether the job needs a generated `/etc/hosts`.
+    fn has_hosts_file(&self) -> bool {
+        self.hostname.is_some() || !self.extra_hosts.is_empty()
+    }
+}
+
+/// Generate an `/etc/hosts` with the usual loopback entries, an entry for `hostname`, and the
+/// extra hosts. Clients check the names before sending the job, but we check them again, since a
+/// bad name could add arbitrary entries to the file.
+fn hosts_file(hostname: Option<&str>, extra_hosts: &[JobHost]) -> JobResult<String, Error> {
+    let mut hosts =
+        String::from("127.0.0.1\tlocalhost\n::1\tlocalhost ip6-localhost ip6-loopback\n");
+    if let Some(hostname) = hostname {
+        validate_hostname(hostname).map_err(execerr)?;
+        writeln!(hosts, "127.0.1.1\t{hostname}").unwrap();
+    }
+    for JobHost { name, address } in extra_hosts {
+        validate_hostname(name).map_err(execerr)?;
+        writeln!(hosts, "{address}\t{name}").unwrap();
+    }
+    Ok(hosts)
 }
 
 pub struct MountDir;
 pub struct TmpfsDir;
 
+/// Serves the FUSE file systems of a job. [`Executor::run_job`] hands this the FUSE file descriptor
+/// of each of the job's file systems as the child mounts it.
+pub trait FuseSpawner {
+    /// Serve the job's root file system on `fd`.
+    fn spawn_root(&mut self, fd: OwnedFd);
+
+    /// Serve the root file system of the sidecar at `index` on `fd`.
+    fn spawn_sidecar(&mut self, index: usize, fd: OwnedFd);
+
+    /// Serve the job's [`JobMount::Layer`] mount at `index`, counting only layer mounts, on `fd`.
+    fn spawn_layer_mount(&mut self, index: usize, fd: OwnedFd);
+}
+
 pub struct Executor<'clock, ClockT> {
     user: UserId,
     group: GroupId,
@@ -108,9 +273,13 @@ pub struct Executor<'clock, ClockT> {
     tmpfs_dir: CString,
     upper_dir: CString,
     work_dir: CString,
+    extra_layer_dir: CString,
     root_mode: u32,
+    max_tmpfs_size: Option<u64>,
+    allowed_forwards: Option<Vec<String>>,
     netlink_socket_addr: SockaddrNetlink,
     netlink_message: Box<[u8]>,
+    cgroups: Result<JobCgroups>,
     clock: &'clock ClockT,
 }
 
@@ -118,6 +287,8 @@ impl<'clock, ClockT> Executor<'clock, ClockT> {
     pub fn new(
         mount_dir: RootBuf<MountDir>,
         tmpfs_dir: RootBuf<TmpfsDir>,
+        max_tmpfs_size: Option<u64>,
+        allowed_forwards: Option<Vec<String>>,
         clock: &'clock ClockT,
     ) -> Result<Self> {
         // Set up stdin to be a file that will always return EOF. We could do something similar
@@ -142,6 +313,7 @@ impl<'clock, ClockT> Executor<'clock, ClockT> {
 
         struct OverlayFsUpperDir;
         struct OverlayFsWorkDir;
+        struct ExtraLayerDir;
 
         let user = UserId::from(linux::getuid().as_u32());
         let group = GroupId::from(linux::getgid().as_u32());
@@ -149,9 +321,11 @@ impl<'clock, ClockT> Executor<'clock, ClockT> {
         let mount_dir = CString::new(mount_dir.as_os_str().as_bytes())?;
         let upper_dir = tmpfs_dir.join::<OverlayFsUpperDir>("upper");
         let work_dir = tmpfs_dir.join::<OverlayFsWorkDir>("work");
+        let extra_layer_dir = tmpfs_dir.join::<ExtraLayerDir>("extra");
         let tmpfs_dir = CString::new(tmpfs_dir.as_os_str().as_bytes())?;
         let upper_dir = CString::new(upper_dir.as_os_str().as_bytes())?;
         let work_dir = CString::new(work_dir.as_os_str().as_bytes())?;
+        let extra_layer_dir = CString::new(extra_layer_dir.as_os_str().as_bytes())?;
         let netlink_socket_addr = SockaddrNetlink::default();
         let mut netlink_message = LinkMessage::default();
         netlink_message.header.index = 1;
@@ -171,9 +345,15 @@ impl<'clock, ClockT> Executor<'clock, ClockT> {
             tmpfs_dir,
             upper_dir,
             work_dir,
+            extra_layer_dir,
             root_mode,
+            max_tmpfs_size,
+            allowed_forwards,
             netlink_socket_addr,
             netlink_message: buffer,
+            // Not being able to use cgroups isn't fatal. It just means we can't run jobs with
+            // resource limits.
+            cgroups: JobCgroups::new(),
             clock,
         })
     }
@@ -193,6 +373,8 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
     /// The `kill_event_receiver` is used to kill the child process. If the attached sender is ever
     /// closed, the child will be immediately killed with a SIGTERM.
     ///
+    /// The `fuse_spawner` is given the FUSE file descriptor for each of the job's file systems.
+    ///
     /// This function should be run in a `spawn_blocking` context. Ideally, this function would be
     /// async, but that doesn't work because we rely on [`bumpalo::Bump`] as a fast arena
     /// allocator, and it's not `Sync`.
@@ -201,10 +383,16 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         spec: &JobSpec,
         inline_limit: InlineLimit,
         kill_event_receiver: EventReceiver,
-        fuse_spawn: impl FnOnce(OwnedFd),
+        fuse_spawner: impl FuseSpawner,
         runtime: runtime::Handle,
     ) -> JobResult<JobCompleted, Error> {
-        self.run_job_inner(spec, inline_limit, kill_event_receiver, fuse_spawn, runtime)
+        self.run_job_inner(
+            spec,
+            inline_limit,
+            kill_event_receiver,
+            fuse_spawner,
+            runtime,
+        )
     }
 }
 
@@ -220,7 +408,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
 async fn wait_for_child(
     child_pidfd: OwnedFd,
     mut kill_event_receiver: EventReceiver,
-) -> Result<JobTerminationStatus> {
+) -> Result<(JobTerminationStatus, Rusage)> {
     let async_fd = AsyncFd::with_interest(child_pidfd, Interest::READABLE)?;
     let mut kill_event_received = false;
     loop {
@@ -235,10 +423,41 @@ async fn wait_for_child(
             },
         }
     }
-    Ok(match linux::waitid(&async_fd.into_inner())? {
+    let (status, rusage) = linux::waitid_with_rusage(&async_fd.into_inner())?;
+    Ok((termination_status(status), rusage))
+}
+
+fn termination_status(status: WaitStatus) -> JobTerminationStatus {
+    match status {
         WaitStatus::Exited(code) => JobTerminationStatus::Exited(code.as_u8()),
         WaitStatus::Signaled(signo) => JobTerminationStatus::Signaled(signo.as_u8()),
-    })
+    }
+}
+
+/// Compute the resources used by a job. The child's rusage only covers the processes it waited
+/// for, so if the job ran in its own cgroup, we prefer the cgroup's statistics where they exist.
+/// Neither can tell us how many processes the job spawned, so that comes from our init, if the job
+/// ran under it.
+fn resource_usage(
+    rusage: &Rusage,
+    cgroup: Option<&JobCgroup>,
+    spawned_processes: Option<u64>,
+) -> Result<JobResourceUsage> {
+    const BLOCK_SIZE: u64 = 512;
+    let mut usage = JobResourceUsage {
+        peak_memory: rusage.max_rss(),
+        user_time: rusage.user_time(),
+        system_time: rusage.system_time(),
+        read_bytes: rusage.in_blocks() * BLOCK_SIZE,
+        written_bytes: rusage.out_blocks() * BLOCK_SIZE,
+        spawned_processes,
+    };
+    if let Some(cgroup) = cgroup {
+        if let Some(peak_memory) = cgroup.peak_memory()? {
+            usage.peak_memory = peak_memory;
+        }
+    }
+    Ok(usage)
 }
 
 /// Read all of the contents of `stream` and return the appropriate [`JobOutputResult`].
@@ -261,6 +480,58 @@ async fn output_reader(fd: OwnedFd, inline_limit: InlineLimit) -> Result<JobOutp
     }
 }
 
+/// Write all of `bytes` to `fd`, then close it.
+async fn stdin_writer(fd: OwnedFd, bytes: &[u8]) -> Result<()> {
+    // Make the write side of the pipe non-blocking so that we can use it with Tokio.
+    linux::fcntl_setfl(&fd, OpenFlags::NONBLOCK).map_err(Error::from)?;
+    let mut stream = AsyncFile::new(fd)?;
+    stream.write_all(bytes).await?;
+    Ok(())
+}
+
+/// Accept connections on `listener`, which is listening in the job, and forward each one to the
+/// host's end of `forward`. This runs until it is canceled.
+async fn forwarder(listener: OwnedFd, forward: JobForward) -> Result<()> {
+    // Connections are put in their own JoinSet, so that they're canceled along with us.
+    let mut connections = JoinSet::new();
+    match forward {
+        JobForward::Tcp { host, .. } => {
+            let listener = TcpListener::try_from(listener)?;
+            loop {
+                let (mut inbound, _) = listener.accept().await?;
+                while connections.try_join_next().is_some() {}
+                let host = host.clone();
+                connections.spawn(async move {
+                    if let Ok(mut outbound) = TcpStream::connect(host).await {
+                        let _ = io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+            }
+        }
+        JobForward::Unix { host, .. } => {
+            let listener = UnixListener::try_from(listener)?;
+            loop {
+                let (mut inbound, _) = listener.accept().await?;
+                while connections.try_join_next().is_some() {}
+                let host = host.clone();
+                connections.spawn(async move {
+                    if let Ok(mut outbound) = UnixStream::connect(host).await {
+                        let _ = io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Read and throw away everything written to `fd`. Sidecars' output isn't reported anywhere, but we
+/// have to keep reading it so that they don't block writing to a full pipe.
+async fn output_discarder(fd: OwnedFd) -> Result<()> {
+    linux::fcntl_setfl(&fd, OpenFlags::NONBLOCK).map_err(Error::from)?;
+    io::copy(&mut AsyncFile::new(fd)?, &mut io::sink()).await?;
+    Ok(())
+}
+
 /// Task main for the output reader: Read the output and then call the callback.
 async fn output_reader_task_main(
     fd: OwnedFd,
@@ -304,6 +575,35 @@ fn bump_c_str_from_bytes<'bump>(bump: &'bump Bump, bytes: &[u8]) -> Result<&'bum
     CStr::from_bytes_with_nul(vec.into_bump_slice()).map_err(Error::new)
 }
 
+/// Return the program's path, and if it should be looked up in the job's `PATH`, the paths to try
+/// first, in order. The program's path is used as a fallback if none of the other paths work.
+fn program_paths<'bump>(
+    spec: &JobSpec,
+    bump: &'bump Bump,
+) -> JobResult<(&'bump CStr, Option<&'bump [&'bump CStr]>), Error> {
+    let program_str = spec.program.as_str();
+    let program = bump_c_str(bump, program_str).map_err(syserr)?;
+    let path = match spec.environment.iter().find(|var| var.starts_with("PATH=")) {
+        Some(path) if !program_str.contains('/') => path,
+        _ => return Ok((program, None)),
+    };
+    let paths = path
+        .strip_prefix("PATH=")
+        .unwrap()
+        .split(|c| c == ':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| {
+            let mut bump_string = BumpString::from_str_in(dir, bump);
+            bump_string.push('/');
+            bump_string.push_str(program_str);
+            bump_c_str(bump, bump_string.as_str())
+        })
+        .collect_in::<result::Result<BumpVec<_>, _>>(bump)
+        .map_err(syserr)?
+        .into_bump_slice();
+    Ok((program, Some(paths)))
+}
+
 fn syserr<E>(err: E) -> JobError<Error>
 where
     Error: From<E>,
@@ -318,10 +618,115 @@ where
     JobError::Execution(Error::from(err))
 }
 
+/// Forwarded sockets are sent from the child with this tag, or-ed with the index of the forward.
+/// Errors are sent with the index of the failing syscall in the upper 32 bits, which will never be
+/// this large. The job's FUSE file descriptor is sent with all bits set.
+const FORWARD_FD_TAG: u64 = 0xfffffffe_00000000;
+
+/// Sidecars send their FUSE file descriptors with this tag, or-ed with the index of the sidecar.
+const SIDECAR_FUSE_FD_TAG: u64 = 0xfffffffd_00000000;
+
+/// The FUSE file descriptors for the job's layer mounts are sent with this tag, or-ed with the
+/// index of the layer mount among the job's layer mounts.
+const LAYER_MOUNT_FUSE_FD_TAG: u64 = 0xfffffffc_00000000;
+
+/// If the job wants its core file, it sends file descriptors for its working directory, program,
+/// and root, and a pidfd for the process that will exec the program, with this tag, or-ed with the
+/// matching index below.
+const CORE_DUMP_FD_TAG: u64 = 0xfffffffb_00000000;
+const CORE_DUMP_DIR_FD_INDEX: usize = 0;
+const CORE_DUMP_PROGRAM_FD_INDEX: usize = 1;
+const CORE_DUMP_ROOT_FD_INDEX: usize = 2;
+const CORE_DUMP_PIDFD_INDEX: usize = 3;
+
+/// The job sends file descriptors for the upper directory of its root overlay and for each of its
+/// `tmp` mounts with this tag. We use them to check whether its tmpfs file systems filled up, and
+/// to keep its writable layer if it wants that on failure. The root overlay's is or-ed with
+/// [`WRITABLE_DIR_ROOT_FD_INDEX`], and each mount's with its index in the job's mounts.
+const WRITABLE_DIR_FD_TAG: u64 = 0xfffffffa_00000000;
+const WRITABLE_DIR_ROOT_FD_INDEX: usize = FD_TAG_INDEX_MASK as usize;
+
+/// If the job's seccomp filter makes blocked syscalls fail with an errno, the job sends the filter's
+/// listener with this tag, so that we can answer its notifications and count them.
+const SECCOMP_LISTENER_FD_TAG: u64 = 0xfffffff9_00000000;
+
+/// The job's FUSE file descriptor is sent with this tag.
+const FUSE_FD_TAG: u64 = 0xffffffff_ffffffff;
+
+/// The bits of a file descriptor's tag that hold the index.
+const FD_TAG_INDEX_MASK: u64 = 0x00000000_ffffffff;
+
+/// Sidecar scripts report errors with their syscall indices offset by the index of the sidecar,
+/// plus one, shifted left by this much. This way, the job's own script has an offset of zero.
+const SIDECAR_SCRIPT_INDEX_SHIFT: usize = 16;
+
+/// The child only runs the script, and possibly our init, so it doesn't need much stack. The same
+/// goes for sidecars until they exec.
+const CHILD_STACK_SIZE: usize = 16384;
+
+/// A stack for a cloned child, with a guard page below it. If the child overflows its stack, it
+/// will fault on the guard page instead of scribbling over whatever memory is below the stack.
+struct ChildStack {
+    mapping: *mut core::ffi::c_void,
+    mapping_len: usize,
+    guard_len: usize,
+}
+
+impl ChildStack {
+    fn new() -> Result<Self> {
+        let guard_len = linux::page_size();
+        let mapping_len = CHILD_STACK_SIZE.next_multiple_of(guard_len) + guard_len;
+        let mapping = linux::mmap_anonymous(
+            mapping_len,
+            ProtFlags::READ | ProtFlags::WRITE,
+            MapFlags::PRIVATE | MapFlags::STACK,
+        )?;
+        let stack = Self {
+            mapping,
+            mapping_len,
+            guard_len,
+        };
+        unsafe { linux::mprotect(stack.mapping, guard_len, ProtFlags::NONE) }?;
+        Ok(stack)
+    }
+
+    /// The usable part of the stack, not including the guard page.
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe {
+            slice::from_raw_parts_mut(
+                (self.mapping as *mut u8).add(self.guard_len),
+                self.mapping_len - self.guard_len,
+            )
+        }
+    }
+}
+
+impl Drop for ChildStack {
+    fn drop(&mut self) {
+        // Whoever owns the stack makes sure the child is done with it before dropping it.
+        let _ = unsafe { linux::munmap(self.mapping, self.mapping_len) };
+    }
+}
+
 fn new_fd_slot(bump: &Bump) -> FdSlot<'_> {
     FdSlot::new(bump.alloc(UnsafeCell::new(Fd::from_raw(-1))))
 }
 
+fn new_wait_status_slot(bump: &Bump) -> WaitStatusSlot<'_> {
+    WaitStatusSlot::new(bump.alloc(UnsafeCell::new(None)))
+}
+
+fn new_process_count_slot(bump: &Bump) -> ProcessCountSlot<'_> {
+    ProcessCountSlot::new(bump.alloc(UnsafeCell::new(None)))
+}
+
+/// Where our init reports back to us, if the job runs under it.
+#[derive(Clone, Copy)]
+struct InitSlots<'bump> {
+    status: WaitStatusSlot<'bump>,
+    spawned: ProcessCountSlot<'bump>,
+}
+
 struct Device {
     cstr: &'static CStr,
     str: &'static str,
@@ -343,14 +748,29 @@ impl Device {
     }
 }
 
-struct ChildProcess<'bump> {
+/// A job's cgroup, plus the pipe used to tell the child when it has been moved into the cgroup.
+struct CgroupSetup {
+    cgroup: JobCgroup,
+    _read: OwnedFd,
+    write: OwnedFd,
+}
+
+/// A sidecar that the job's child will start. We hold on to the sidecar's stdio until the child has
+/// been cloned, and keep the error transformers for the sidecar's script.
+struct SidecarSetup<'bump> {
+    stdio: Stdio,
+    pidfd: FdSlot<'bump>,
+    error_transformers: &'bump [&'bump dyn Fn(&'static str) -> JobError<Error>],
+}
+
+struct ChildProcess {
+    child_pid: Pid,
     child_pidfd: Option<OwnedFd>,
-    _stack: &'bump mut [u8],
+    _stack: ChildStack,
 }
 
-impl<'bump> ChildProcess<'bump> {
+impl ChildProcess {
     fn new(
-        bump: &'bump Bump,
         clone_flags: CloneFlags,
         func: extern "C" fn(*mut core::ffi::c_void) -> i32,
         arg: *mut core::ffi::c_void,
@@ -358,29 +778,29 @@ impl<'bump> ChildProcess<'bump> {
         let mut clone_args = CloneArgs::default()
             .flags(clone_flags)
             .exit_signal(Signal::CHLD);
-        const CHILD_STACK_SIZE: usize = 1024;
-        let stack = bump.alloc_slice_fill_default(CHILD_STACK_SIZE);
-        let stack_ptr: *mut u8 = stack.as_mut_ptr();
-        let (_, child_pidfd) = unsafe {
-            linux::clone_with_child_pidfd(
-                func,
-                stack_ptr.wrapping_add(CHILD_STACK_SIZE) as *mut _,
-                arg,
-                &mut clone_args,
-            )
+        let mut stack = ChildStack::new()?;
+        let stack_slice = stack.as_mut_slice();
+        let stack_ptr = stack_slice.as_mut_ptr_range().end;
+        let (child_pid, child_pidfd) = unsafe {
+            linux::clone_with_child_pidfd(func, stack_ptr as *mut _, arg, &mut clone_args)
         }?;
         Ok(Self {
+            child_pid,
             child_pidfd: Some(child_pidfd),
             _stack: stack,
         })
     }
 
+    fn pid(&self) -> Pid {
+        self.child_pid
+    }
+
     fn into_child_pidfd(mut self) -> OwnedFd {
         self.child_pidfd.take().unwrap()
     }
 }
 
-impl<'bump> Drop for ChildProcess<'bump> {
+impl Drop for ChildProcess {
     fn drop(&mut self) {
         if let Some(child_pidfd) = &self.child_pidfd {
             // The pidfd_send_signal really shouldn't ever give us an error. But even if it does
@@ -396,6 +816,8 @@ impl<'bump> Drop for ChildProcess<'bump> {
 
 enum Stdio {
     Pipes {
+        stdin: Option<OwnedFd>,
+        stdin_write: Option<OwnedFd>,
         stdout_read: OwnedFd,
         stdout_write: OwnedFd,
         stderr_read: OwnedFd,
@@ -474,6 +896,45 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         }
     }
 
+    fn set_up_cgroup<'bump>(
+        &'bump self,
+        spec: &JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<Option<CgroupSetup>, Error> {
+        // We run every job in its own cgroup if we can, so that we can report accurate resource
+        // usage. It's only an error if we can't and the job needs us to enforce limits.
+        let cgroups = match &self.cgroups {
+            Ok(cgroups) => cgroups,
+            Err(_) if spec.limits.is_empty() => return Ok(None),
+            Err(err) => {
+                return Err(syserr(anyhow!(
+                    "worker can't enforce resource limits: {err}"
+                )))
+            }
+        };
+        let cgroup = cgroups.create(&spec.limits).map_err(syserr)?;
+
+        // The child can't move itself into the cgroup, since the cgroup is outside of its cgroup
+        // namespace. Instead, the child blocks reading from a pipe until the parent has moved it.
+        // This way, everything the child does is accounted for in the cgroup.
+        let (read, write) = linux::pipe().map_err(syserr)?;
+        let fd = new_fd_slot(bump);
+        fd.set(read.as_fd());
+        builder.push(
+            Syscall::Read {
+                fd,
+                buf: bump.alloc_slice_fill_default(1),
+            },
+            &|err| syserr(anyhow!("waiting to be moved into cgroup: {err}")),
+        );
+        Ok(Some(CgroupSetup {
+            cgroup,
+            _read: read,
+            write,
+        }))
+    }
+
     fn set_up_session<'bump>(&'bump self, builder: &mut ScriptBuilder<'bump>) {
         // Make the child process the leader of a new session and process group. If we didn't do
         // this, then the process would be a member of a process group and session headed by a
@@ -485,13 +946,94 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         builder.push(Syscall::SetSid, &|err| syserr(anyhow!("setsid: {err}")));
     }
 
+    fn set_up_hostname<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        builder: &mut ScriptBuilder<'bump>,
+    ) {
+        if let Some(hostname) = &spec.hostname {
+            builder.push(
+                Syscall::Sethostname {
+                    name: hostname.as_bytes(),
+                },
+                &|err| execerr(anyhow!("sethostname: {err}")),
+            );
+        }
+    }
+
+    fn set_up_time_namespace<'bump>(
+        &'bump self,
+        spec: &JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<(), Error> {
+        let Some(JobClockOffsets {
+            monotonic,
+            boottime,
+        }) = spec.clock_offsets
+        else {
+            return Ok(());
+        };
+
+        builder.push(
+            Syscall::Unshare {
+                flags: CloneFlags::NEWTIME,
+            },
+            &|err| syserr(anyhow!("unshare of time namespace: {err}")),
+        );
+
+        // The offsets can only be set before any process is in the new namespace.
+        let mut offsets = BumpString::with_capacity_in(64, bump);
+        writeln!(offsets, "monotonic {monotonic} 0").map_err(syserr)?;
+        writeln!(offsets, "boottime {boottime} 0").map_err(syserr)?;
+        let fd = new_fd_slot(bump);
+        builder.push(
+            Syscall::Open {
+                path: c"/proc/self/timens_offsets",
+                flags: OpenFlags::WRONLY,
+                mode: FileMode::default(),
+                out: fd,
+            },
+            &|err| {
+                syserr(anyhow!(
+                    "opening /proc/self/timens_offsets for writing: {err}"
+                ))
+            },
+        );
+        builder.push(
+            Syscall::Write {
+                fd,
+                buf: offsets.into_bump_str().as_bytes(),
+            },
+            &|err| {
+                execerr(anyhow!(
+                    "writing clock offsets to /proc/self/timens_offsets: {err}"
+                ))
+            },
+        );
+
+        Ok(())
+    }
+
     fn set_up_stdio<'bump>(&'bump self, stdio: &Stdio, builder: &mut ScriptBuilder<'bump>) {
         match stdio {
             Stdio::Pipes {
+                stdin,
                 stdout_write,
                 stderr_write,
                 ..
             } => {
+                // If the job has stdin, dup2 it over the always-EOF stdin we inherited.
+                if let Some(stdin) = stdin {
+                    builder.push(
+                        Syscall::Dup2 {
+                            from: stdin.as_fd(),
+                            to: Fd::STDIN,
+                        },
+                        &|err| syserr(anyhow!("dup2-ing to stdin: {err}")),
+                    );
+                }
+
                 // Dup2 the pipe file descriptors to be stdout and stderr. This will close the old
                 // stdout and stderr. We don't have to worry about closing the old fds because they
                 // will be marked close-on-exec below.
@@ -548,9 +1090,14 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         }
     }
 
+    /// Map the spec's user and group to `outer_user` and `outer_group` in the parent user
+    /// namespace. For jobs, those are the worker's user and group. For sidecars, they are the
+    /// job's.
     fn set_up_user_namespace<'bump>(
         &'bump self,
         spec: &JobSpec,
+        outer_user: UserId,
+        outer_group: GroupId,
         bump: &'bump Bump,
         builder: &mut ScriptBuilder<'bump>,
     ) -> JobResult<(), Error> {
@@ -562,7 +1109,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
             uid_map_contents,
             "{} {} 1",
             spec.user.unwrap_or(0.into()),
-            self.user,
+            outer_user,
         )
         .map_err(syserr)?;
         builder.push(
@@ -603,7 +1150,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
             gid_map_contents,
             "{} {} 1",
             spec.group.unwrap_or(0.into()),
-            self.group,
+            outer_group,
         )
         .map_err(syserr)?;
         builder.push(
@@ -626,10 +1173,125 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         Ok(())
     }
 
+    /// Build a script for each sidecar, and push a syscall that clones the sidecar to run it. This
+    /// needs to happen after we've set up our user namespace, since the sidecars' user namespaces
+    /// are nested in it, and after we've brought up the loopback interface that they share with
+    /// us.
+    fn start_sidecars<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        write_sock: Fd,
+        stacks: &'bump mut [ChildStack],
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<Vec<SidecarSetup<'bump>>, Error> {
+        if !spec.sidecars.is_empty() && spec.network != JobNetwork::Loopback {
+            return Err(execerr(anyhow!("sidecars require a loopback network")));
+        }
+        let outer_user = spec.user.unwrap_or(0.into());
+        let outer_group = spec.group.unwrap_or(0.into());
+        let mut setups = vec![];
+        for (index, (sidecar, stack)) in spec.sidecars.iter().zip(stacks).enumerate() {
+            // Sysfs can only be mounted by the user namespace that owns the network namespace,
+            // which for a sidecar is the job's.
+            if sidecar
+                .spec
+                .mounts
+                .iter()
+                .any(|mount| matches!(mount, JobMount::Sys { .. }))
+            {
+                return Err(execerr(anyhow!(
+                    "sidecar `{}` can't mount a sysfs file system",
+                    sidecar.name
+                )));
+            }
+            if sidecar
+                .spec
+                .mounts
+                .iter()
+                .any(|mount| matches!(mount, JobMount::Layer { .. }))
+            {
+                return Err(execerr(anyhow!(
+                    "sidecar `{}` can't have layer mounts",
+                    sidecar.name
+                )));
+            }
+
+            // The sidecar's stdin is a pipe whose write end we close right away, so it will always
+            // read EOF. Its output is read and thrown away.
+            let (stdin, _) = linux::pipe().map_err(syserr)?;
+            let (stdout_read, stdout_write) = linux::pipe().map_err(syserr)?;
+            let (stderr_read, stderr_write) = linux::pipe().map_err(syserr)?;
+            let stdio = Stdio::Pipes {
+                stdin: Some(stdin),
+                stdin_write: None,
+                stdout_read,
+                stdout_write,
+                stderr_read,
+                stderr_write,
+            };
+
+            let spec = &sidecar.spec;
+            let new_root_path = self.mount_dir.as_c_str();
+            let mut sidecar_builder = ScriptBuilder::new(bump);
+            self.set_up_session(&mut sidecar_builder);
+            self.set_up_stdio(&stdio, &mut sidecar_builder);
+            self.set_up_user_namespace(spec, outer_user, outer_group, bump, &mut sidecar_builder)?;
+            self.set_up_fuse_root(
+                spec,
+                new_root_path,
+                SIDECAR_FUSE_FD_TAG | index as u64,
+                bump,
+                &mut sidecar_builder,
+            );
+            self.set_up_root_overlay(spec, new_root_path, bump, &mut sidecar_builder)?;
+            let mut mount_fds = BumpVec::new_in(bump);
+            self.open_mount_fds_for_mounts_pre_pivot_root(
+                spec,
+                bump,
+                &mut sidecar_builder,
+                &mut mount_fds,
+            )?;
+            self.do_pivot_root(new_root_path, &mut sidecar_builder);
+            let mut mount_fds = mount_fds.into_iter();
+            self.complete_mounts_post_pivot_root(spec, bump, &mut sidecar_builder, &mut mount_fds)?;
+            self.do_chdir(spec, bump, &mut sidecar_builder)?;
+            self.do_close_range(&mut sidecar_builder);
+            self.do_exec(spec, bump, &mut sidecar_builder)?;
+
+            let ScriptBuilder {
+                syscalls,
+                error_transformers,
+            } = sidecar_builder;
+            let pidfd = new_fd_slot(bump);
+            builder.push(
+                Syscall::StartSidecar {
+                    stack: stack.as_mut_slice(),
+                    args: bump.alloc(ChildArgs {
+                        write_sock,
+                        syscalls: syscalls.into_bump_slice_mut(),
+                        index_offset: (index + 1) << SIDECAR_SCRIPT_INDEX_SHIFT,
+                    }),
+                    out: pidfd,
+                },
+                bump.alloc(move |err| {
+                    syserr(anyhow!("starting sidecar `{}`: {err}", sidecar.name))
+                }),
+            );
+            setups.push(SidecarSetup {
+                stdio,
+                pidfd,
+                error_transformers: error_transformers.into_bump_slice(),
+            });
+        }
+        Ok(setups)
+    }
+
     fn set_up_fuse_root<'bump>(
         &'bump self,
         spec: &JobSpec,
         new_root_path: &'bump CStr,
+        tag: u64,
         bump: &'bump Bump,
         builder: &mut ScriptBuilder<'bump>,
     ) {
@@ -664,7 +1326,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // Send the fuse mount file descriptor from the child to the parent.
         builder.push(
             Syscall::SendMsg {
-                buf: &[0xFF; 8],
+                buf: bump.alloc(tag.to_ne_bytes()),
                 fd_to_send: fd,
             },
             &|err| syserr(anyhow!("sendmsg: {err}")),
@@ -677,41 +1339,60 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
     /// other file systems.
     fn set_up_root_overlay<'bump>(
         &'bump self,
-        spec: &JobSpec,
+        spec: &'bump JobSpec,
         new_root_path: &'bump CStr,
         bump: &'bump Bump,
         builder: &mut ScriptBuilder<'bump>,
     ) -> JobResult<(), Error> {
-        let (upper, work) = match spec.root_overlay {
-            JobRootOverlay::None => {
-                // There is nothing to do. We're just going to have a read-only root without an
-                // overlay on top of it.
-                return Ok(());
-            }
+        // Both a tmp overlay and the extra layer need a tmpfs to write to. It will be discarded
+        // when the job finishes.
+        let tmpfs_options = match spec.root_overlay {
+            JobRootOverlay::Tmp { size, inodes } => Some(self.tmpfs_options(size, None, inodes)?),
+            _ if spec.has_extra_layer() => Some(vec![]),
+            _ => None,
+        };
+        if let Some(options) = tmpfs_options {
+            let options = options
+                .into_iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            let data = if options.is_empty() {
+                None
+            } else {
+                Some(
+                    bump_c_str(bump, &options)
+                        .map_err(syserr)?
+                        .to_bytes_with_nul(),
+                )
+            };
+
+            // Mount a new tmpfs that's local to this mount namespace.
+            builder.push(
+                Syscall::Mount {
+                    source: None,
+                    target: self.tmpfs_dir.as_c_str(),
+                    fstype: Some(c"tmpfs"),
+                    flags: MountFlags::default(),
+                    data,
+                },
+                &|err| syserr(anyhow!("mounting tmpfs file system for overlayfs: {err}")),
+            );
+        }
+
+        if spec.has_extra_layer() {
+            self.create_extra_layer(spec, bump, builder)?;
+        }
 
-            JobRootOverlay::Tmp => {
-                // The overlay is going to write to a tmpfs that will be discarded when the job finishes.
-                // We need two directories on the same mount.
+        let upper_and_work = match spec.root_overlay {
+            JobRootOverlay::None => None,
 
+            JobRootOverlay::Tmp { .. } => {
+                // The overlay is going to write to the tmpfs we just mounted. We need two
+                // directories on the same mount.
                 let upper = self.upper_dir.as_c_str();
                 let work = self.work_dir.as_c_str();
 
-                // Mount a new tmpfs that's local to this mount namespace.
-                builder.push(
-                    Syscall::Mount {
-                        source: None,
-                        target: self.tmpfs_dir.as_c_str(),
-                        fstype: Some(c"tmpfs"),
-                        flags: MountFlags::default(),
-                        data: None,
-                    },
-                    &|err| {
-                        syserr(anyhow!(
-                            "mounting tmpfs file system for overlayfs's upperdir and workdir: {err}"
-                        ))
-                    },
-                );
-
                 // Create the two directories in the newly-created tmpfs.
                 builder.push(
                     Syscall::Mkdir {
@@ -728,7 +1409,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                     &|err| syserr(anyhow!("making workdir for overlayfs: {err}")),
                 );
 
-                (upper, work)
+                Some((upper, work))
             }
 
             JobRootOverlay::Local {
@@ -737,10 +1418,55 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
             } => {
                 // We're going to use the upper and work directories provided to us. We assume the
                 // directories have been created and are on the same file system.
-                (
+                Some((
                     bump_c_str(bump, upper.as_str()).map_err(syserr)?,
                     bump_c_str(bump, work.as_str()).map_err(syserr)?,
-                )
+                ))
+            }
+        };
+
+        // The job's mount namespace may be gone by the time we want to check whether the tmpfs
+        // filled up or save what the job wrote, so we need a file descriptor for the upper
+        // directory.
+        let want_upper = spec.writable_layer_path.is_some()
+            || matches!(spec.root_overlay, JobRootOverlay::Tmp { .. });
+        if let (true, Some((upper, _))) = (want_upper, upper_and_work) {
+            let fd = new_fd_slot(bump);
+            builder.push(
+                Syscall::Open {
+                    path: upper,
+                    flags: OpenFlags::PATH | OpenFlags::DIRECTORY,
+                    mode: FileMode::default(),
+                    out: fd,
+                },
+                &|err| syserr(anyhow!("opening upperdir for overlayfs: {err}")),
+            );
+            builder.push(
+                Syscall::SendMsg {
+                    buf: bump.alloc(
+                        (WRITABLE_DIR_FD_TAG | WRITABLE_DIR_ROOT_FD_INDEX as u64).to_ne_bytes(),
+                    ),
+                    fd_to_send: fd,
+                },
+                &|err| syserr(anyhow!("sendmsg: {err}")),
+            );
+        }
+
+        let lower = match (spec.has_extra_layer(), upper_and_work) {
+            (false, None) => {
+                // There is nothing to do. We're just going to have a read-only root without an
+                // overlay on top of it.
+                return Ok(());
+            }
+            (false, Some(_)) => new_root_path,
+            (true, _) => {
+                // The extra layer goes above the job's image. Without an upper layer, the overlay
+                // is read-only, just like the root would have been without it.
+                let mut lower = BumpString::new_in(bump);
+                lower.push_str(self.extra_layer_dir.to_str().map_err(syserr)?);
+                lower.push(':');
+                lower.push_str(new_root_path.to_str().map_err(syserr)?);
+                bump_c_str(bump, &lower).map_err(syserr)?
             }
         };
 
@@ -762,31 +1488,33 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                 fd,
                 command: FsconfigCommand::SET_STRING,
                 key: Some(c"lowerdir"),
-                value: Some(&new_root_path.to_bytes_with_nul()[0]),
+                value: Some(&lower.to_bytes_with_nul()[0]),
                 aux: None,
             },
             &|err| syserr(anyhow!("fsconfig of lowerdir for overlayfs: {err}")),
         );
-        builder.push(
-            Syscall::Fsconfig {
-                fd,
-                command: FsconfigCommand::SET_STRING,
-                key: Some(c"upperdir"),
-                value: Some(&upper.to_bytes_with_nul()[0]),
-                aux: None,
-            },
-            &|err| syserr(anyhow!("fsconfig of upperdir for overlayfs: {err}")),
-        );
-        builder.push(
-            Syscall::Fsconfig {
-                fd,
-                command: FsconfigCommand::SET_STRING,
-                key: Some(c"workdir"),
-                value: Some(&work.to_bytes_with_nul()[0]),
-                aux: None,
-            },
-            &|err| syserr(anyhow!("fsconfig of workdir for overlayfs: {err}")),
-        );
+        if let Some((upper, work)) = upper_and_work {
+            builder.push(
+                Syscall::Fsconfig {
+                    fd,
+                    command: FsconfigCommand::SET_STRING,
+                    key: Some(c"upperdir"),
+                    value: Some(&upper.to_bytes_with_nul()[0]),
+                    aux: None,
+                },
+                &|err| syserr(anyhow!("fsconfig of upperdir for overlayfs: {err}")),
+            );
+            builder.push(
+                Syscall::Fsconfig {
+                    fd,
+                    command: FsconfigCommand::SET_STRING,
+                    key: Some(c"workdir"),
+                    value: Some(&work.to_bytes_with_nul()[0]),
+                    aux: None,
+                },
+                &|err| syserr(anyhow!("fsconfig of workdir for overlayfs: {err}")),
+            );
+        }
 
         // Effect the configuration. This preps the file descriptor for the fsmount next.
         builder.push(
@@ -828,22 +1556,122 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         Ok(())
     }
 
-    fn open_mount_fds_for_mounts_pre_pivot_root<'bump>(
+    /// Create the directory that will become the extra overlay layer in the tmpfs. It holds the
+    /// generated files in `etc`, and the user's home directory.
+    fn create_extra_layer<'bump>(
         &'bump self,
         spec: &'bump JobSpec,
         bump: &'bump Bump,
         builder: &mut ScriptBuilder<'bump>,
-        mount_fds: &mut BumpVec<'bump, FdSlot<'bump>>,
     ) -> JobResult<(), Error> {
-        for mount in &spec.mounts {
-            fn normal_mount<'a>(
-                bump: &'a Bump,
-                builder: &mut ScriptBuilder<'a>,
-                mount_fds: &mut BumpVec<'a, FdSlot<'a>>,
-                cfstype: &'static CStr,
-                fstype: &'static str,
-            ) -> JobResult<(), Error> {
-                let fd = new_fd_slot(bump);
+        let layer_dir = self.extra_layer_dir.to_str().map_err(syserr)?;
+
+        // Collect every directory we need, sorted so that parents come before their children.
+        let mut dirs = BTreeSet::from([Utf8PathBuf::new(), Utf8PathBuf::from("etc")]);
+        let mut files = vec![];
+        if let Some(user_files) = &spec.user_files {
+            let mut home = Utf8PathBuf::new();
+            for component in user_files.home.components() {
+                if let Utf8Component::Normal(name) = component {
+                    home.push(name);
+                    dirs.insert(home.clone());
+                }
+            }
+            files.push(("etc/passwd", &user_files.passwd));
+            files.push(("etc/group", &user_files.group));
+        }
+        if spec.has_hosts_file() {
+            let hosts = hosts_file(spec.hostname.as_deref(), &spec.extra_hosts)?;
+            files.push(("etc/hosts", bump.alloc(hosts)));
+        }
+
+        for dir in dirs {
+            let path = Utf8Path::new(layer_dir).join(dir);
+            builder.push(
+                Syscall::Mkdir {
+                    path: bump_c_str(bump, path.as_str()).map_err(syserr)?,
+                    mode: FileMode::RWXU
+                        | FileMode::RGRP
+                        | FileMode::XGRP
+                        | FileMode::ROTH
+                        | FileMode::XOTH,
+                },
+                &|err| syserr(anyhow!("making directory for extra layer: {err}")),
+            );
+        }
+
+        let fd = new_fd_slot(bump);
+        for (name, contents) in files {
+            let path = Utf8Path::new(layer_dir).join(name);
+            builder.push(
+                Syscall::Open {
+                    path: bump_c_str(bump, path.as_str()).map_err(syserr)?,
+                    flags: OpenFlags::WRONLY | OpenFlags::CREAT | OpenFlags::TRUNC,
+                    mode: FileMode::RUSR | FileMode::WUSR | FileMode::RGRP | FileMode::ROTH,
+                    out: fd,
+                },
+                &|err| syserr(anyhow!("opening extra layer file for writing: {err}")),
+            );
+            builder.push(
+                Syscall::Write {
+                    fd,
+                    buf: contents.as_bytes(),
+                },
+                &|err| syserr(anyhow!("writing extra layer file: {err}")),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The options to give a tmpfs with the given limits. The worker's `max_tmpfs_size`, if it has
+    /// one, is used as the size if none is given. Asking for more than it is an error.
+    fn tmpfs_options(
+        &self,
+        size: Option<u64>,
+        mode: Option<u32>,
+        inodes: Option<u64>,
+    ) -> JobResult<Vec<(&'static str, String)>, Error> {
+        let size = match (size, self.max_tmpfs_size) {
+            (Some(size), Some(max)) if size > max => {
+                return Err(execerr(anyhow!(
+                    "tmpfs size of {size} bytes is larger than this worker's max-tmpfs-size of \
+                    {max} bytes"
+                )));
+            }
+            (size, max) => size.or(max),
+        };
+        let mut options = vec![];
+        if let Some(size) = size {
+            // A size of zero means no limit to tmpfs, so we ask for the smallest size possible.
+            options.push(("size", size.max(1).to_string()));
+        }
+        if let Some(mode) = mode {
+            options.push(("mode", format!("{mode:o}")));
+        }
+        if let Some(inodes) = inodes {
+            options.push(("nr_inodes", inodes.to_string()));
+        }
+        Ok(options)
+    }
+
+    fn open_mount_fds_for_mounts_pre_pivot_root<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+        mount_fds: &mut BumpVec<'bump, FdSlot<'bump>>,
+    ) -> JobResult<(), Error> {
+        for mount in &spec.mounts {
+            fn normal_mount<'a>(
+                bump: &'a Bump,
+                builder: &mut ScriptBuilder<'a>,
+                mount_fds: &mut BumpVec<'a, FdSlot<'a>>,
+                cfstype: &'static CStr,
+                fstype: &'static str,
+                options: Vec<(&'static str, String)>,
+            ) -> JobResult<(), Error> {
+                let fd = new_fd_slot(bump);
 
                 // Open an fs context for the file system.
                 builder.push(
@@ -855,6 +1683,26 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                     bump.alloc(move |err| syserr(anyhow!("fsopen for mount of {fstype}: {err}"))),
                 );
 
+                // Set the mount options. These come from the job spec, so a bad one is an
+                // execution error.
+                for (key, value) in options {
+                    let value = bump_c_str(bump, &value).map_err(syserr)?;
+                    builder.push(
+                        Syscall::Fsconfig {
+                            fd,
+                            command: FsconfigCommand::SET_STRING,
+                            key: Some(bump_c_str(bump, key).map_err(syserr)?),
+                            value: Some(&value.to_bytes_with_nul()[0]),
+                            aux: None,
+                        },
+                        bump.alloc(move |err| {
+                            execerr(anyhow!(
+                                "fsconfig of {key}={value:?} for mount of {fstype}: {err}"
+                            ))
+                        }),
+                    );
+                }
+
                 // Effect the configuration. This preps the file descriptor for the fsmount next.
                 builder.push(
                     Syscall::Fsconfig {
@@ -919,6 +1767,11 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                         }),
                     );
                 }
+                JobMount::Cache { name, .. } => {
+                    // The worker replaces cache mounts with bind mounts of the cache's directory
+                    // before the job is run.
+                    return Err(syserr(anyhow!("cache mount {name:?} wasn't resolved")));
+                }
                 JobMount::Devices { devices, .. } => {
                     // Open all of the source paths for devices before we chdir or pivot_root. We
                     // create devices in the container my bind mounting them from the host instead
@@ -950,17 +1803,36 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                     // parameters via the new mount API. As a result, we mount it using the
                     // old-style mount syscall later.
                 }
+                JobMount::Layer { .. } => {
+                    // The container's root may not have /dev/fuse, so we open it now. The FUSE
+                    // mount itself happens after the pivot_root, since its mount point is a path
+                    // in the container.
+                    let fd = new_fd_slot(bump);
+                    mount_fds.push(fd);
+                    builder.push(
+                        Syscall::Open {
+                            path: c"/dev/fuse",
+                            flags: OpenFlags::RDWR | OpenFlags::NONBLOCK,
+                            mode: FileMode::default(),
+                            out: fd,
+                        },
+                        &|err| syserr(anyhow!("open /dev/fuse for layer mount: {err}")),
+                    );
+                }
                 JobMount::Mqueue { .. } => {
-                    normal_mount(bump, builder, mount_fds, c"mqueue", "mqueue")?;
+                    normal_mount(bump, builder, mount_fds, c"mqueue", "mqueue", vec![])?;
                 }
                 JobMount::Proc { .. } => {
-                    normal_mount(bump, builder, mount_fds, c"proc", "proc")?;
+                    normal_mount(bump, builder, mount_fds, c"proc", "proc", vec![])?;
                 }
                 JobMount::Sys { .. } => {
-                    normal_mount(bump, builder, mount_fds, c"sysfs", "sysfs")?;
+                    normal_mount(bump, builder, mount_fds, c"sysfs", "sysfs", vec![])?;
                 }
-                JobMount::Tmp { .. } => {
-                    normal_mount(bump, builder, mount_fds, c"tmpfs", "tmpfs")?;
+                JobMount::Tmp {
+                    size, mode, inodes, ..
+                } => {
+                    let options = self.tmpfs_options(*size, *mode, *inodes)?;
+                    normal_mount(bump, builder, mount_fds, c"tmpfs", "tmpfs", options)?;
                 }
             }
         }
@@ -980,6 +1852,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // N.B. It seems like it's a security feature of Linux that sysfs and proc can't be mounted
         // unless they are already mounted. So we have to do this before we unmount the old root.
         // If we do the unmount first, then we'll get permission errors mounting those fs types.
+        let mut layer_mount_index = 0u64;
         for mount in &spec.mounts {
             fn normal_mount<'a>(
                 bump: &'a Bump,
@@ -1045,6 +1918,9 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                         );
                     }
                 }
+                JobMount::Cache { .. } => {
+                    unreachable!("cache mounts are rejected before pivot_root")
+                }
                 JobMount::Devices { devices } => {
                     for device in devices.iter() {
                         let Device { cstr, str } = Device::new(device);
@@ -1081,6 +1957,35 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                         }),
                     )
                 }
+                JobMount::Layer { mount_point, .. } => {
+                    let fuse_fd = mount_fds.next().unwrap();
+                    builder.push(
+                        Syscall::FuseMount {
+                            source: c"Maelstrom LayerFS",
+                            target: bump_c_str(bump, mount_point.as_str()).map_err(syserr)?,
+                            flags: MountFlags::NODEV | MountFlags::NOSUID | MountFlags::RDONLY,
+                            root_mode: self.root_mode,
+                            uid: Uid::from_u32(spec.user.unwrap_or(UserId::new(0)).into()),
+                            gid: Gid::from_u32(spec.group.unwrap_or(GroupId::new(0)).into()),
+                            fuse_fd,
+                        },
+                        bump.alloc(move |err| {
+                            execerr(anyhow!("fuse mount of layer mount at {mount_point}: {err}"))
+                        }),
+                    );
+
+                    // Send the file descriptor to the parent, which will serve the layer stack on
+                    // it.
+                    builder.push(
+                        Syscall::SendMsg {
+                            buf: bump
+                                .alloc((LAYER_MOUNT_FUSE_FD_TAG | layer_mount_index).to_ne_bytes()),
+                            fd_to_send: fuse_fd,
+                        },
+                        &|err| syserr(anyhow!("sendmsg: {err}")),
+                    );
+                    layer_mount_index += 1;
+                }
                 JobMount::Mqueue { mount_point } => normal_mount(
                     bump,
                     builder,
@@ -1102,7 +2007,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                     mount_fds.next().unwrap(),
                     mount_point,
                 )?,
-                JobMount::Tmp { mount_point } => normal_mount(
+                JobMount::Tmp { mount_point, .. } => normal_mount(
                     bump,
                     builder,
                     "tmpfs",
@@ -1146,6 +2051,83 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         );
     }
 
+    /// Create a listening socket in the job for each forward, and send it to the parent, tagged
+    /// with its index in [`FORWARD_FD_TAG`]. If the worker has an allowlist, forwards to hosts not
+    /// on it are rejected. TCP sockets are bound on the job's loopback interface.
+    /// Unix-domain sockets are bound in the job's file system, so this has to happen after all of
+    /// the mounts are complete.
+    fn do_forwards<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<(), Error> {
+        for (index, forward) in spec.forwards.iter().enumerate() {
+            if let Some(allowed_forwards) = &self.allowed_forwards {
+                let host = match forward {
+                    JobForward::Tcp { host, .. } => host.as_str(),
+                    JobForward::Unix { host, .. } => host.as_str(),
+                };
+                if !allowed_forwards.iter().any(|allowed| allowed == host) {
+                    return Err(execerr(anyhow!(
+                        "forwarding {host} isn't allowed by this worker"
+                    )));
+                }
+            }
+            let fd = new_fd_slot(bump);
+            let (domain, protocol, addr): (_, _, &Sockaddr) = match forward {
+                JobForward::Tcp { port, .. } => {
+                    if spec.network != JobNetwork::Loopback {
+                        return Err(execerr(anyhow!(
+                            "forwarding TCP port {port} requires a loopback network"
+                        )));
+                    }
+                    (
+                        SocketDomain::INET,
+                        SocketProtocol::TCP,
+                        bump.alloc(SockaddrIn::loopback(*port)),
+                    )
+                }
+                JobForward::Unix { path, .. } => (
+                    SocketDomain::UNIX,
+                    SocketProtocol::default(),
+                    bump.alloc(SockaddrUnStorage::new(path.as_str().as_bytes()).map_err(execerr)?),
+                ),
+            };
+            builder.push(
+                Syscall::Socket {
+                    domain,
+                    type_: SocketType::STREAM | SocketType::NONBLOCK | SocketType::CLOEXEC,
+                    protocol,
+                    out: fd,
+                },
+                &|err| syserr(anyhow!("opening forwarded socket: {err}")),
+            );
+            builder.push(
+                Syscall::Bind { fd, addr },
+                bump.alloc(move |err| match forward {
+                    JobForward::Tcp { port, .. } => {
+                        execerr(anyhow!("binding forwarded port {port}: {err}"))
+                    }
+                    JobForward::Unix { path, .. } => {
+                        execerr(anyhow!("binding forwarded socket {path}: {err}"))
+                    }
+                }),
+            );
+            builder.push(Syscall::Listen { fd, backlog: 128 }, &|err| {
+                syserr(anyhow!("listening on forwarded socket: {err}"))
+            });
+            builder.push(
+                Syscall::SendMsg {
+                    buf: bump.alloc((FORWARD_FD_TAG | index as u64).to_ne_bytes()),
+                    fd_to_send: fd,
+                },
+                &|err| syserr(anyhow!("sendmsg: {err}")),
+            );
+        }
+        Ok(())
+    }
+
     fn do_chdir<'bump>(
         &'bump self,
         spec: &'bump JobSpec,
@@ -1167,6 +2149,234 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         Ok(())
     }
 
+    /// Send the parent file descriptors for the job's `tmp` mounts. The root overlay's upper
+    /// directory was sent when the overlay was set up.
+    fn do_send_writable_dir_fds<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<(), Error> {
+        for (index, mount) in spec.mounts.iter().enumerate() {
+            let JobMount::Tmp { mount_point, .. } = mount else {
+                continue;
+            };
+            let fd = new_fd_slot(bump);
+            builder.push(
+                Syscall::Open {
+                    path: bump_c_str(bump, mount_point.as_str()).map_err(syserr)?,
+                    flags: OpenFlags::PATH | OpenFlags::DIRECTORY,
+                    mode: FileMode::default(),
+                    out: fd,
+                },
+                bump.alloc(move |err| syserr(anyhow!("opening tmp mount at {mount_point}: {err}"))),
+            );
+            builder.push(
+                Syscall::SendMsg {
+                    buf: bump.alloc((WRITABLE_DIR_FD_TAG | index as u64).to_ne_bytes()),
+                    fd_to_send: fd,
+                },
+                &|err| syserr(anyhow!("sendmsg: {err}")),
+            );
+        }
+        Ok(())
+    }
+
+    /// If the job wants its core file, send the parent file descriptors for the working directory,
+    /// where the kernel will write the core file, and for the program, which we need for its
+    /// build-id. The job's mount namespace may be gone by the time we look for the core file, so we
+    /// can't just use paths.
+    fn do_send_core_dump_fds<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<(), Error> {
+        if spec.core_dump_path.is_none() {
+            return Ok(());
+        }
+        let dir = new_fd_slot(bump);
+        builder.push(
+            Syscall::Open {
+                path: c".",
+                flags: OpenFlags::PATH | OpenFlags::DIRECTORY,
+                mode: FileMode::default(),
+                out: dir,
+            },
+            &|err| syserr(anyhow!("opening working directory: {err}")),
+        );
+        builder.push(
+            Syscall::SendMsg {
+                buf: bump.alloc((CORE_DUMP_FD_TAG | CORE_DUMP_DIR_FD_INDEX as u64).to_ne_bytes()),
+                fd_to_send: dir,
+            },
+            &|err| syserr(anyhow!("sendmsg: {err}")),
+        );
+
+        // We open the program the same way the exec will find it. If we can't, we report it the
+        // same way the exec would have.
+        let program = new_fd_slot(bump);
+        let (path, paths) = program_paths(spec, bump)?;
+        builder.push(
+            match paths {
+                Some(paths) => Syscall::OpenList {
+                    paths,
+                    fallback: path,
+                    flags: OpenFlags::PATH,
+                    mode: FileMode::default(),
+                    out: program,
+                },
+                None => Syscall::Open {
+                    path,
+                    flags: OpenFlags::PATH,
+                    mode: FileMode::default(),
+                    out: program,
+                },
+            },
+            &|err| execerr(anyhow!("execvc: {err}")),
+        );
+        builder.push(
+            Syscall::SendMsg {
+                buf: bump
+                    .alloc((CORE_DUMP_FD_TAG | CORE_DUMP_PROGRAM_FD_INDEX as u64).to_ne_bytes()),
+                fd_to_send: program,
+            },
+            &|err| syserr(anyhow!("sendmsg: {err}")),
+        );
+
+        // Core patterns with absolute paths are resolved relative to the job's root.
+        let root = new_fd_slot(bump);
+        builder.push(
+            Syscall::Open {
+                path: c"/",
+                flags: OpenFlags::PATH | OpenFlags::DIRECTORY,
+                mode: FileMode::default(),
+                out: root,
+            },
+            &|err| syserr(anyhow!("opening root directory: {err}")),
+        );
+        builder.push(
+            Syscall::SendMsg {
+                buf: bump.alloc((CORE_DUMP_FD_TAG | CORE_DUMP_ROOT_FD_INDEX as u64).to_ne_bytes()),
+                fd_to_send: root,
+            },
+            &|err| syserr(anyhow!("sendmsg: {err}")),
+        );
+        Ok(())
+    }
+
+    /// Send a pidfd for the process that will exec the program, so that we can tell which core
+    /// file is the program's. This has to come after the init is started, since the program runs in
+    /// the init's child.
+    fn do_send_core_dump_pidfd<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) {
+        if spec.core_dump_path.is_none() {
+            return;
+        }
+        let pidfd = new_fd_slot(bump);
+        builder.push(Syscall::PidfdOpenSelf { out: pidfd }, &|err| {
+            syserr(anyhow!("pidfd_open: {err}"))
+        });
+        builder.push(
+            Syscall::SendMsg {
+                buf: bump.alloc((CORE_DUMP_FD_TAG | CORE_DUMP_PIDFD_INDEX as u64).to_ne_bytes()),
+                fd_to_send: pidfd,
+            },
+            &|err| syserr(anyhow!("sendmsg: {err}")),
+        );
+    }
+
+    /// Wait for each sidecar with a readiness check to start listening on its port. We do this as
+    /// late as we can, so that the sidecars have as much time as possible to start up while we set
+    /// up the job, but before setting rlimits, which could keep us from opening sockets.
+    fn do_wait_for_sidecars<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        sidecars: &[SidecarSetup<'bump>],
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) {
+        for (sidecar, setup) in spec.sidecars.iter().zip(sidecars) {
+            let Some(JobReadinessCheck { port, timeout }) = sidecar.ready else {
+                continue;
+            };
+            let timeout = Duration::from(timeout);
+            builder.push(
+                Syscall::WaitUntilListening {
+                    addr: bump.alloc(SockaddrIn::loopback(port)),
+                    pidfd: setup.pidfd,
+                    attempts: (timeout.as_millis() / 100).try_into().unwrap_or(u32::MAX),
+                },
+                bump.alloc(move |err| {
+                    let name = &sidecar.name;
+                    if Some(err) == Errno::ESRCH.desc() {
+                        execerr(anyhow!(
+                            "sidecar `{name}` exited before listening on port {port}"
+                        ))
+                    } else if Some(err) == Errno::ETIMEDOUT.desc() {
+                        execerr(anyhow!(
+                            "sidecar `{name}` didn't listen on port {port} within {} seconds",
+                            timeout.as_secs()
+                        ))
+                    } else {
+                        syserr(anyhow!("waiting for sidecar `{name}`: {err}"))
+                    }
+                }),
+            );
+        }
+    }
+
+    fn do_setrlimits<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<(), Error> {
+        // If the job wants its core file, raise the soft limit for core files as high as we can,
+        // unless the job asked for a specific limit.
+        if spec.core_dump_path.is_some()
+            && !spec
+                .rlimits
+                .iter()
+                .any(|rlimit| rlimit.resource == JobRlimitResource::Core)
+        {
+            let Rlimit { max, .. } = linux::getrlimit(RlimitResource::Core).map_err(syserr)?;
+            builder.push(
+                Syscall::Setrlimit {
+                    resource: RlimitResource::Core,
+                    rlimit: Rlimit { current: max, max },
+                },
+                &|err| syserr(anyhow!("setrlimit of core file size: {err}")),
+            );
+        }
+        for rlimit in &spec.rlimits {
+            let resource = match rlimit.resource {
+                JobRlimitResource::As => RlimitResource::As,
+                JobRlimitResource::Core => RlimitResource::Core,
+                JobRlimitResource::Cpu => RlimitResource::Cpu,
+                JobRlimitResource::NoFile => RlimitResource::NoFile,
+                JobRlimitResource::Stack => RlimitResource::Stack,
+            };
+            builder.push(
+                Syscall::Setrlimit {
+                    resource,
+                    rlimit: Rlimit {
+                        current: rlimit.soft,
+                        max: rlimit.hard,
+                    },
+                },
+                bump.alloc(move |err| {
+                    execerr(anyhow!("setrlimit of {:?}: {err}", rlimit.resource))
+                }),
+            );
+        }
+        Ok(())
+    }
+
     fn do_close_range<'bump>(&'bump self, builder: &mut ScriptBuilder<'bump>) {
         // Set close-on-exec for all file descriptors except stdin, stdout, and stderr. We do this
         // last thing, right before the exec, so that we catch any file descriptors opened above.
@@ -1184,13 +2394,66 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         );
     }
 
+    fn do_start_init<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> Option<InitSlots<'bump>> {
+        spec.init.then(|| {
+            let status = new_wait_status_slot(bump);
+            let spawned = new_process_count_slot(bump);
+            builder.push(Syscall::StartInit { status, spawned }, &|err| {
+                syserr(anyhow!("starting init: {err}"))
+            });
+            InitSlots { status, spawned }
+        })
+    }
+
+    fn do_seccomp<'bump>(
+        &'bump self,
+        spec: &'bump JobSpec,
+        bump: &'bump Bump,
+        builder: &mut ScriptBuilder<'bump>,
+    ) -> JobResult<(), Error> {
+        if let Some(seccomp) = &spec.seccomp {
+            let filter = bump.alloc_slice_copy(&seccomp::build_filter(seccomp).map_err(execerr)?);
+            if seccomp::notify_errno(seccomp).is_none() {
+                builder.push(Syscall::Seccomp { filter }, &|err| {
+                    execerr(anyhow!("installing seccomp filter: {err}"))
+                });
+                return Ok(());
+            }
+
+            // The filter sends blocked syscalls to a listener, which we answer with the errno. The
+            // listener has to get to us before the job can make a blocked syscall, since the
+            // syscall won't return until we answer it.
+            let listener = new_fd_slot(bump);
+            builder.push(
+                Syscall::SeccompWithListener {
+                    filter,
+                    out: listener,
+                },
+                &|err| execerr(anyhow!("installing seccomp filter: {err}")),
+            );
+            builder.push(
+                Syscall::SendMsg {
+                    buf: bump.alloc(SECCOMP_LISTENER_FD_TAG.to_ne_bytes()),
+                    fd_to_send: listener,
+                },
+                &|err| syserr(anyhow!("sendmsg: {err}")),
+            );
+        }
+        Ok(())
+    }
+
     fn do_exec<'bump>(
         &'bump self,
         spec: &'bump JobSpec,
         bump: &'bump Bump,
         builder: &mut ScriptBuilder<'bump>,
     ) -> JobResult<(), Error> {
-        let program = bump_c_str(bump, spec.program.as_str()).map_err(syserr)?;
+        let (program, paths) = program_paths(spec, bump)?;
         let mut arguments =
             BumpVec::with_capacity_in(spec.arguments.len().checked_add(2).unwrap(), bump);
         arguments.push(Some(&program.to_bytes_with_nul()[0]));
@@ -1206,34 +2469,20 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
             environment.push(Some(&var_cstr.to_bytes_with_nul()[0]));
         }
         environment.push(None);
+        let argv = arguments.into_bump_slice();
+        let envp = environment.into_bump_slice();
         builder.push(
-            match (
-                spec.environment.iter().find(|var| var.starts_with("PATH=")),
-                spec.program.as_str(),
-            ) {
-                (Some(path), program_str) if !program_str.contains('/') => Syscall::ExecveList {
-                    paths: path
-                        .strip_prefix("PATH=")
-                        .unwrap()
-                        .split(|c| c == ':')
-                        .filter(|dir| !dir.is_empty())
-                        .map(|dir| {
-                            let mut bump_string = BumpString::from_str_in(dir, bump);
-                            bump_string.push('/');
-                            bump_string.push_str(program_str);
-                            bump_c_str(bump, bump_string.as_str())
-                        })
-                        .collect_in::<result::Result<BumpVec<_>, _>>(bump)
-                        .map_err(syserr)?
-                        .into_bump_slice(),
+            match paths {
+                Some(paths) => Syscall::ExecveList {
+                    paths,
                     fallback: program,
-                    argv: arguments.into_bump_slice(),
-                    envp: environment.into_bump_slice(),
+                    argv,
+                    envp,
                 },
-                _ => Syscall::Execve {
+                None => Syscall::Execve {
                     path: program,
-                    argv: arguments.into_bump_slice(),
-                    envp: environment.into_bump_slice(),
+                    argv,
+                    envp,
                 },
             },
             &|err| execerr(anyhow!("execvc: {err}")),
@@ -1246,7 +2495,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         spec: &JobSpec,
         inline_limit: InlineLimit,
         kill_event_receiver: EventReceiver,
-        fuse_spawn: impl FnOnce(OwnedFd),
+        mut fuse_spawner: impl FuseSpawner,
         runtime: runtime::Handle,
     ) -> JobResult<JobCompleted, Error> {
         // We're going to need three channels between the parent and child: one for stdout, one for
@@ -1260,9 +2509,24 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // complex.
         let stdio = match spec.allocate_tty {
             None => {
+                let (stdin, stdin_write) = match &spec.stdin {
+                    None => (None, None),
+                    Some(JobStdin::Inline(_)) => {
+                        let (stdin_read, stdin_write) = linux::pipe().map_err(syserr)?;
+                        (Some(stdin_read), Some(stdin_write))
+                    }
+                    Some(JobStdin::File(path)) => {
+                        let path = CString::new(path.as_os_str().as_bytes()).map_err(syserr)?;
+                        let fd = linux::open(&path, OpenFlags::RDONLY, FileMode::default())
+                            .map_err(|err| syserr(anyhow!("opening stdin artifact: {err}")))?;
+                        (Some(fd), None)
+                    }
+                };
                 let (stdout_read, stdout_write) = linux::pipe().map_err(syserr)?;
                 let (stderr_read, stderr_write) = linux::pipe().map_err(syserr)?;
                 Stdio::Pipes {
+                    stdin,
+                    stdin_write,
                     stdout_read,
                     stdout_write,
                     stderr_read,
@@ -1330,9 +2594,22 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // run on the closure, which means drop won't be run on any captured-and-moved variables.
         // As long as we only pass references in those variables, we're okay.
 
+        // The sidecars' stacks have to outlive the bump, and with it, every child that might be
+        // running on them.
+        let mut sidecar_stacks = spec
+            .sidecars
+            .iter()
+            .map(|_| ChildStack::new())
+            .collect::<Result<Vec<_>>>()
+            .map_err(syserr)?;
+
         let bump = Bump::new();
         let mut builder = ScriptBuilder::new(&bump);
 
+        // If we can, create a cgroup for the job. The first thing the child does is wait until
+        // we've moved it into the cgroup.
+        let cgroup_setup = self.set_up_cgroup(spec, &bump, &mut builder)?;
+
         // Put the child in its own session (and process group). This will make it the session and
         // group leader, and detach it from the parent's controlling terminal.
         self.set_up_session(&mut builder);
@@ -1344,11 +2621,29 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // Set up the network namespace, returning true iff we should actually create a new network
         // namespace. If `newnet` is false, we should share the parent's network namespace.
         let newnet = self.set_up_network(spec, &bump, &mut builder);
-        self.set_up_user_namespace(spec, &bump, &mut builder)?;
+        self.set_up_user_namespace(spec, self.user, self.group, &bump, &mut builder)?;
+
+        // Set the hostname in our new UTS namespace. The sidecars share the namespace, so this has
+        // to happen before they start.
+        self.set_up_hostname(spec, &mut builder);
+
+        // Start the sidecars. They share our network namespace, and their user namespaces are
+        // nested in ours.
+        let sidecars = self.start_sidecars(
+            spec,
+            write_sock.as_fd(),
+            &mut sidecar_stacks,
+            &bump,
+            &mut builder,
+        )?;
+
+        // Create a time namespace with the job's clock offsets. Only processes created after this,
+        // or that exec after this, end up in the new namespace. Neither we nor the sidecars will.
+        self.set_up_time_namespace(spec, &bump, &mut builder)?;
 
         // Set up the fuse mount and send back the open fuse fd.
         let new_root_path = self.mount_dir.as_c_str();
-        self.set_up_fuse_root(spec, new_root_path, &bump, &mut builder);
+        self.set_up_fuse_root(spec, new_root_path, FUSE_FD_TAG, &bump, &mut builder);
 
         // We need to resolve any local paths before we pivot_root, and we want to do the
         // move_mount before we complete the move_mounts below. We could split this up into two
@@ -1372,18 +2667,47 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         let mut mount_fds = mount_fds.into_iter();
         self.complete_mounts_post_pivot_root(spec, &bump, &mut builder, &mut mount_fds)?;
 
+        // Forwarded Unix-domain sockets may be bound anywhere in the job's file system, so we have
+        // to wait until the mounts are complete.
+        self.do_forwards(spec, &bump, &mut builder)?;
+
+        // The tmp mounts have to be complete before we can open them.
+        self.do_send_writable_dir_fds(spec, &bump, &mut builder)?;
+
         // We don't want to chdir until we've completed mounting, since we want clients to be able
         // to specify relative paths, and have them be relative to /.
         self.do_chdir(spec, &bump, &mut builder)?;
 
+        // The core file is written relative to the working directory, so this has to come after
+        // the chdir.
+        self.do_send_core_dump_fds(spec, &bump, &mut builder)?;
+
+        // Don't let the job start until its sidecars are ready.
+        self.do_wait_for_sidecars(spec, &sidecars, &bump, &mut builder);
+
+        // Set rlimits as late as possible, so they don't interfere with setting up the job. For
+        // example, a low RLIMIT_NOFILE could keep us from opening the files we need above.
+        self.do_setrlimits(spec, &bump, &mut builder)?;
+
         // This needs to happen last, right before the exec, so we don't leak any file descriptors.
         self.do_close_range(&mut builder);
 
+        // Start the init after everything else is set up, so that it's in the same namespaces and
+        // cgroup as the program. The filter is installed after this, so the init isn't subject to
+        // it.
+        let init_slots = self.do_start_init(spec, &bump, &mut builder);
+        self.do_send_core_dump_pidfd(spec, &bump, &mut builder);
+
+        // Once the filter is installed, the only thing left for us to do is exec, so we don't have
+        // to worry about the filter blocking any of our own syscalls.
+        self.do_seccomp(spec, &bump, &mut builder)?;
+
         // This has to come last.
         self.do_exec(spec, &bump, &mut builder)?;
 
-        // Start timing the job now.
+        // Start timing the job now. Core files older than this can't be the job's.
         let start = self.clock.now();
+        let start_time = SystemTime::now();
 
         // We're finally ready to actually clone the child.
         let mut clone_flags = CloneFlags::NEWCGROUP
@@ -1391,6 +2715,7 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
             | CloneFlags::NEWNS
             | CloneFlags::NEWPID
             | CloneFlags::NEWUSER
+            | CloneFlags::NEWUTS
             | CloneFlags::VM;
         if newnet {
             clone_flags |= CloneFlags::NEWNET;
@@ -1398,15 +2723,21 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         let mut args = maelstrom_worker_child::ChildArgs {
             write_sock: write_sock.as_fd(),
             syscalls: builder.syscalls.as_mut_slice(),
+            index_offset: 0,
         };
         let child_process = ChildProcess::new(
-            &bump,
             clone_flags,
             maelstrom_worker_child::start_and_exec_in_child_trampoline,
             &mut args as *mut _ as *mut _,
         )
         .map_err(syserr)?;
 
+        // Move the child into its cgroup and let it proceed.
+        if let Some(CgroupSetup { cgroup, write, .. }) = &cgroup_setup {
+            cgroup.add_process(child_process.pid()).map_err(syserr)?;
+            linux::write(write, &[0]).map_err(syserr)?;
+        }
+
         // Read (in a blocking manner) from the exec result socket. The child will write to the
         // socket if it has an error exec-ing. The child will mark the write side of the socket
         // exec-on-close, so we'll read an immediate EOF if the exec is successful.
@@ -1417,13 +2748,49 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // If we encounter an error here, we will run Drop on the ChildProcess. This will guarantee
         // that our child is dead before we return from this function and destroy bump.
         drop(write_sock);
-        let mut fuse_spawn = Some(fuse_spawn);
-        let mut exec_result_buf = [0; mem::size_of::<u64>()];
-        loop {
-            let (count, fd) = read_sock
-                .recv_with_fd(&mut exec_result_buf)
-                .map_err(syserr)?;
-            if count == 0 {
+
+        // Start throwing away the sidecars' output now, since they may write some before they're
+        // ready. We don't need our copies of the write sides of their pipes anymore.
+        let mut joinset = JoinSet::new();
+        let sidecars: Vec<_> = sidecars
+            .into_iter()
+            .map(|setup| {
+                let Stdio::Pipes {
+                    stdout_read,
+                    stderr_read,
+                    ..
+                } = setup.stdio
+                else {
+                    unreachable!("sidecars always use pipes");
+                };
+                for fd in [stdout_read, stderr_read] {
+                    joinset.spawn_on(
+                        async move {
+                            let _ = output_discarder(fd).await;
+                        },
+                        &runtime,
+                    );
+                }
+                setup.error_transformers
+            })
+            .collect();
+
+        let mut root_fuse_spawned = false;
+        let mut forward_listeners = vec![];
+        let mut core_dump_files = core_dump::JobFiles {
+            root: None,
+            working_directory: None,
+            program: None,
+            pids: None,
+        };
+        let mut writable_dir_fds = vec![];
+        let mut seccomp_supervisor = None;
+        let mut exec_result_buf = [0; mem::size_of::<u64>()];
+        loop {
+            let (count, fd) = read_sock
+                .recv_with_fd(&mut exec_result_buf)
+                .map_err(syserr)?;
+            if count == 0 {
                 // If we get EOF, the child successfully exec-ed.
                 break;
             }
@@ -1436,22 +2803,64 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
                 )));
             }
 
-            // If we get a file descriptor, we pass it to the FUSE callback.
+            // If we get a file descriptor, it's either a forwarded socket, or it's for FUSE, in
+            // which case we pass it to the appropriate FUSE callback.
             if let Some(fd) = fd {
-                let fuse_spawn = fuse_spawn
-                    .take()
-                    .ok_or(syserr(anyhow!("multiple FUSE fds")))?;
-                fuse_spawn(fd);
+                let tag = u64::from_ne_bytes(exec_result_buf);
+                let index = (tag & FD_TAG_INDEX_MASK) as usize;
+                match tag & !FD_TAG_INDEX_MASK {
+                    FORWARD_FD_TAG => forward_listeners.push((index, fd)),
+                    SIDECAR_FUSE_FD_TAG => fuse_spawner.spawn_sidecar(index, fd),
+                    LAYER_MOUNT_FUSE_FD_TAG => fuse_spawner.spawn_layer_mount(index, fd),
+                    CORE_DUMP_FD_TAG => match index {
+                        CORE_DUMP_DIR_FD_INDEX => core_dump_files.working_directory = Some(fd),
+                        CORE_DUMP_PROGRAM_FD_INDEX => core_dump_files.program = Some(fd),
+                        CORE_DUMP_ROOT_FD_INDEX => core_dump_files.root = Some(fd),
+                        // The process is still blocked in the script, so it's still running.
+                        _ => core_dump_files.pids = core_dump::Pids::from_pidfd(&fd).ok(),
+                    },
+                    WRITABLE_DIR_FD_TAG => writable_dir_fds.push((index, fd)),
+                    SECCOMP_LISTENER_FD_TAG => {
+                        // Start answering notifications right away, since the rest of the job's
+                        // script may make blocked syscalls. Closing the write end of the pipe
+                        // tells the supervisor that the job is done.
+                        let errno = spec.seccomp.as_ref().and_then(seccomp::notify_errno);
+                        let errno = errno.ok_or_else(|| syserr(anyhow!("unexpected listener")))?;
+                        let (stop_read, stop_write) = linux::pipe().map_err(syserr)?;
+                        let (sender, receiver) = oneshot::channel();
+                        runtime.spawn_blocking(move || {
+                            let _ = sender.send(Ok(seccomp::supervise(fd, stop_read, errno)));
+                        });
+                        seccomp_supervisor = Some((stop_write, receiver));
+                    }
+                    _ => {
+                        if mem::replace(&mut root_fuse_spawned, true) {
+                            return Err(syserr(anyhow!("multiple FUSE fds")));
+                        }
+                        fuse_spawner.spawn_root(fd);
+                    }
+                }
                 continue;
             }
 
-            // Otherwise it should be an error we got back from the child.
+            // Otherwise it should be an error we got back from the child or one of the sidecars.
             let result = u64::from_ne_bytes(exec_result_buf);
             let index = (result >> 32) as usize;
             let errno = result & 0xffffffff;
-            return Err(builder.error_transformers[index](
+            let error_transformers = match index >> SIDECAR_SCRIPT_INDEX_SHIFT {
+                0 => builder.error_transformers.as_slice(),
+                sidecar => sidecars[sidecar - 1],
+            };
+            let error = error_transformers[index & ((1 << SIDECAR_SCRIPT_INDEX_SHIFT) - 1)](
                 Errno::from_u64(errno).desc().unwrap_or("Unknown error"),
-            ));
+            );
+            return Err(match index >> SIDECAR_SCRIPT_INDEX_SHIFT {
+                0 => error,
+                sidecar => {
+                    let name = &spec.sidecars[sidecar - 1].name;
+                    error.map(|err| anyhow!("sidecar `{name}`: {err}"))
+                }
+            });
         }
 
         // At this point it's safe to destructure the ChildProcess, since we know it has exec-ed,
@@ -1461,7 +2870,18 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         // However, we want to make sure that we always wait on the child somehow, even if there is
         // an error, so that we don't end up accumlating zombie children. That's why we don't put
         // the following task into the JoinSet: we want it to run eve if we ignore its results.
-        let child_pidfd = child_process.into_child_pidfd();
+        //
+        // The exception is when the job runs under our init. The init never execs, so it keeps
+        // sharing our virtual memory, including its stack, until it exits. In that case, we hold on
+        // to the ChildProcess, so that it will kill and wait for the init if we return early, and
+        // give the waiting task its own pidfd.
+        let (child_pidfd, _init_process) = match init_slots {
+            None => (child_process.into_child_pidfd(), None),
+            Some(_) => (
+                linux::pidfd_open(child_process.pid()).map_err(syserr)?,
+                Some(child_process),
+            ),
+        };
         let (status_sender, status_receiver) = oneshot::channel();
         runtime.spawn(async move {
             // It's not clear what to do if we get an error waiting, which, in theory, should never
@@ -1473,14 +2893,41 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         let (stdout_sender, stdout_receiver) = oneshot::channel();
         let (stderr_sender, stderr_receiver) = oneshot::channel();
 
-        let mut joinset = JoinSet::new();
+        // Forward connections for as long as the job is running.
+        for (index, listener) in forward_listeners {
+            let forward = spec.forwards[index].clone();
+            joinset.spawn_on(
+                async move {
+                    let _ = forwarder(listener, forward).await;
+                },
+                &runtime,
+            );
+        }
+
         match stdio {
             Stdio::Pipes {
+                stdin,
+                stdin_write,
                 stdout_read,
                 stdout_write,
                 stderr_read,
                 stderr_write,
             } => {
+                // The child has its own copy of stdin now.
+                drop(stdin);
+
+                // Feed any inline stdin to the child. The child may exit without reading all of
+                // it, in which case the write will fail, which is fine.
+                if let (Some(fd), Some(JobStdin::Inline(bytes))) = (stdin_write, &spec.stdin) {
+                    let bytes = bytes.clone();
+                    joinset.spawn_on(
+                        async move {
+                            let _ = stdin_writer(fd, &bytes).await;
+                        },
+                        &runtime,
+                    );
+                }
+
                 // Spawn independent tasks to consume stdout and stderr. We want to do this in parallel so
                 // that we don't cause a deadlock on one while we're reading the other one.
                 //
@@ -1584,17 +3031,119 @@ impl<'clock, ClockT: Clock> Executor<'clock, ClockT> {
         }
 
         // Wait for the job to terminate.
-        let status = read_from_receiver(status_receiver)?;
+        let (mut status, rusage) = read_from_receiver(status_receiver)?;
+
+        // If the job ran under our init, report how the program terminated instead of how the init
+        // did. If the init didn't record anything, it must have been killed, so we report that.
+        if let Some(program_status) = init_slots.and_then(|slots| slots.status.get()) {
+            status = termination_status(program_status);
+        }
+
+        // If the job was killed because it went over its memory limit, report that instead of the
+        // signal it was killed with.
+        if let (JobTerminationStatus::Signaled(_), Some(CgroupSetup { cgroup, .. })) =
+            (&status, &cgroup_setup)
+        {
+            if cgroup.oom_killed().map_err(syserr)? {
+                status = JobTerminationStatus::OutOfMemory;
+            }
+        }
+
+        // Likewise, if the job was killed by the seccomp filter, report that instead of SIGSYS.
+        if let (
+            JobTerminationStatus::Signaled(signo),
+            Some(JobSeccomp {
+                action: JobSeccompAction::Kill,
+                ..
+            }),
+        ) = (&status, &spec.seccomp)
+        {
+            if *signo == Signal::SYS.as_u8() {
+                status = JobTerminationStatus::SyscallBlocked;
+            }
+        }
 
         // Stop timing the job now.
         let duration = start.elapsed();
 
+        let blocked_syscalls = match seccomp_supervisor {
+            Some((stop, receiver)) => {
+                drop(stop);
+                read_from_receiver(receiver)?
+            }
+            None => vec![],
+        };
+
+        let cgroup = cgroup_setup.as_ref().map(|setup| &setup.cgroup);
+        let spawned_processes = init_slots.and_then(|slots| slots.spawned.get());
+        let resource_usage = resource_usage(&rusage, cgroup, spawned_processes).map_err(syserr)?;
+
+        // A job that fills up a tmpfs tends to fail in confusing ways, so report which ones had no
+        // space or inodes left. A tmpfs without a limit reports zero blocks or inodes in total.
+        let mut full_tmpfs_mounts = vec![];
+        for (index, fd) in &writable_dir_fds {
+            let mount_point = match spec.mounts.get(*index) {
+                Some(JobMount::Tmp { mount_point, .. }) => mount_point.as_str(),
+                _ if matches!(spec.root_overlay, JobRootOverlay::Tmp { .. }) => "/",
+                _ => continue,
+            };
+            let statfs = linux::fstatfs(fd).map_err(|err| syserr(anyhow!("fstatfs: {err}")))?;
+            if (statfs.blocks() > 0 && statfs.blocks_available() == 0)
+                || (statfs.inodes() > 0 && statfs.inodes_free() == 0)
+            {
+                full_tmpfs_mounts.push(Utf8PathBuf::from(mount_point));
+            }
+        }
+
+        // Only look for a core file if the program was killed by a signal that dumps core. A job
+        // killed by its seccomp filter got SIGSYS.
+        let dumped_core = match status {
+            JobTerminationStatus::Signaled(signo) => Signal::CORE_DUMPING
+                .iter()
+                .any(|signal| signal.as_u8() == signo),
+            JobTerminationStatus::SyscallBlocked => true,
+            _ => false,
+        };
+        let (core_dump, core_dump_not_captured) = match &spec.core_dump_path {
+            Some(path) if dumped_core => {
+                match core_dump::save(&core_dump_files, start_time, path).map_err(syserr)? {
+                    Ok(core_dump) => (Some(core_dump), None),
+                    Err(reason) => (None, Some(reason)),
+                }
+            }
+            _ => (None, None),
+        };
+
+        // Likewise, there's nothing to keep if the job succeeded. A job that timed out was killed,
+        // so it didn't succeed.
+        let writable_layer = match &spec.writable_layer_path {
+            Some(path)
+                if status != JobTerminationStatus::Exited(0) && !writable_dir_fds.is_empty() =>
+            {
+                let dirs = writable_dir_fds.iter().map(|(index, fd)| {
+                    let mount_point = match spec.mounts.get(*index) {
+                        Some(JobMount::Tmp { mount_point, .. }) => mount_point.as_str(),
+                        _ => "/",
+                    };
+                    (mount_point, fd)
+                });
+                Some(writable_layer::save(dirs, path).map_err(syserr)?)
+            }
+            _ => None,
+        };
+
         Ok(JobCompleted {
             status,
             effects: JobEffects {
                 stdout: read_from_receiver(stdout_receiver)?,
                 stderr: read_from_receiver(stderr_receiver)?,
                 duration,
+                resource_usage: Some(resource_usage),
+                core_dump,
+                core_dump_not_captured,
+                writable_layer,
+                full_tmpfs_mounts,
+                blocked_syscalls,
             },
         })
     }
@@ -1616,17 +3165,27 @@ mod tests {
     use bytesize::ByteSize;
     use indoc::indoc;
     use maelstrom_base::{
-        enum_set, nonempty, ArtifactType, EnumSet, JobTerminationStatus, Utf8Path, WindowSize,
+        enum_set, nonempty, ArtifactType, EnumSet, JobBlockedSyscall, JobSeccompProfile,
+        JobTerminationStatus, JobUserEntries, Timeout, Utf8Path, WindowSize,
     };
     use maelstrom_layer_fs::{BlobDir, BottomLayerBuilder, LayerFs, ReaderCache};
+    use maelstrom_linux::ClockId;
     use maelstrom_test::{boxed_u8, digest, utf8_path_buf};
     use maelstrom_util::{async_fs, log::test_logger, sync, time::TickingClock};
     use std::{
-        ascii, collections::HashSet, env, fs, path::PathBuf, str, sync::Arc, time::Duration,
+        ascii,
+        collections::{BTreeMap, HashSet},
+        env, fs,
+        future::Future,
+        io::Read as _,
+        path::{Path, PathBuf},
+        str,
+        sync::Arc,
+        time::Duration,
     };
     use tempfile::{NamedTempFile, TempDir};
     use tokio::{
-        io::AsyncRead,
+        io::{AsyncRead, AsyncWrite},
         net::{TcpListener, UnixListener, UnixStream},
         sync::{oneshot, Mutex},
         task::{self, JoinHandle},
@@ -1680,18 +3239,85 @@ mod tests {
         }
     }
 
+    /// Every file system of a test job is the same tar.
+    impl FuseSpawner for &TarMount {
+        fn spawn_root(&mut self, fd: OwnedFd) {
+            self.spawn(fd)
+        }
+
+        fn spawn_sidecar(&mut self, _index: usize, fd: OwnedFd) {
+            self.spawn(fd)
+        }
+
+        fn spawn_layer_mount(&mut self, _index: usize, fd: OwnedFd) {
+            self.spawn(fd)
+        }
+    }
+
     async fn run(
         spec: maelstrom_base::JobSpec,
         inline_limit: InlineLimit,
+    ) -> JobResult<JobCompleted, Error> {
+        let output_dir = TempDir::new().unwrap();
+        run_with_output_dir(spec, inline_limit, output_dir.path()).await
+    }
+
+    /// Like [`run`], but files the job produces, like core files, are left in `output_dir`.
+    async fn run_with_output_dir(
+        spec: maelstrom_base::JobSpec,
+        inline_limit: InlineLimit,
+        output_dir: &Path,
+    ) -> JobResult<JobCompleted, Error> {
+        run_with_executor_options(spec, inline_limit, output_dir, ExecutorOptions::default()).await
+    }
+
+    /// The worker-wide settings given to [`Executor::new`].
+    #[derive(Default)]
+    struct ExecutorOptions {
+        max_tmpfs_size: Option<u64>,
+        allowed_forwards: Option<Vec<String>>,
+    }
+
+    /// Like [`run_with_output_dir`], but with the given worker-wide settings.
+    async fn run_with_executor_options(
+        spec: maelstrom_base::JobSpec,
+        inline_limit: InlineLimit,
+        output_dir: &Path,
+        options: ExecutorOptions,
     ) -> JobResult<JobCompleted, Error> {
         let clock = TickingClock::new();
         let mount = TarMount::new().await;
-        let spec = JobSpec::from_spec(spec);
+        let user_files = match &spec.user_entries {
+            Some(entries) => Some(
+                UserFiles::read(
+                    &LayerFs::from_path(&mount.data_path, &mount.blob_dir).unwrap(),
+                    &mount.cache,
+                    entries,
+                    spec.user.unwrap_or(0.into()),
+                    spec.group.unwrap_or(0.into()),
+                )
+                .await
+                .unwrap(),
+            ),
+            None => None,
+        };
+        let core_dump_path = spec.capture_core_dump.then(|| output_dir.join("core"));
+        let writable_layer_path = spec
+            .keep_writable_layer_on_failure
+            .then(|| output_dir.join("writable-layer.tar"));
+        let spec = JobSpec {
+            user_files,
+            core_dump_path,
+            writable_layer_path,
+            ..JobSpec::from_spec(spec, &mount.blob_dir)
+        };
         let (_kill_event_sender, kill_event_receiver) = sync::event();
         task::spawn_blocking(move || {
             Executor::new(
                 RootBuf::new(tempfile::tempdir().unwrap().into_path()),
                 RootBuf::new(tempfile::tempdir().unwrap().into_path()),
+                options.max_tmpfs_size,
+                options.allowed_forwards,
                 &clock,
             )
             .unwrap()
@@ -1699,7 +3325,7 @@ mod tests {
                 &spec,
                 inline_limit,
                 kill_event_receiver,
-                |fd| mount.spawn(fd),
+                &mount,
                 runtime::Handle::current(),
             )
         })
@@ -1714,6 +3340,8 @@ mod tests {
         expected_stdout: JobOutputResult,
         expected_stderr: JobOutputResult,
         expected_duration: Duration,
+        expected_full_tmpfs_mounts: Vec<Utf8PathBuf>,
+        expected_blocked_syscalls: Vec<JobBlockedSyscall>,
     }
 
     impl Test {
@@ -1725,6 +3353,8 @@ mod tests {
                 expected_stdout: JobOutputResult::None,
                 expected_stderr: JobOutputResult::None,
                 expected_duration: Duration::from_secs(1),
+                expected_full_tmpfs_mounts: vec![],
+                expected_blocked_syscalls: vec![],
             }
         }
 
@@ -1748,6 +3378,22 @@ mod tests {
             self
         }
 
+        fn expected_full_tmpfs_mounts<const N: usize>(mut self, mount_points: [&str; N]) -> Self {
+            self.expected_full_tmpfs_mounts = mount_points.into_iter().map(Into::into).collect();
+            self
+        }
+
+        fn expected_blocked_syscalls<const N: usize>(mut self, syscalls: [(&str, u64); N]) -> Self {
+            self.expected_blocked_syscalls = syscalls
+                .into_iter()
+                .map(|(name, count)| JobBlockedSyscall {
+                    name: name.into(),
+                    count,
+                })
+                .collect();
+            self
+        }
+
         async fn run(self) {
             let JobCompleted {
                 status,
@@ -1756,6 +3402,12 @@ mod tests {
                         stdout,
                         stderr,
                         duration,
+                        resource_usage,
+                        core_dump: _,
+                        core_dump_not_captured: _,
+                        writable_layer: _,
+                        full_tmpfs_mounts,
+                        blocked_syscalls,
                     },
             } = run(self.spec, self.inline_limit).await.unwrap();
 
@@ -1763,6 +3415,9 @@ mod tests {
             assert_eq!(status, self.expected_status);
             assert_eq!(stdout, self.expected_stdout);
             assert_eq!(duration, self.expected_duration);
+            assert!(resource_usage.is_some());
+            assert_eq!(full_tmpfs_mounts, self.expected_full_tmpfs_mounts);
+            assert_eq!(blocked_syscalls, self.expected_blocked_syscalls);
         }
     }
 
@@ -1813,223 +3468,871 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn stdout_inline_limit_0() {
-        Test::new(bash_spec("echo a"))
-            .inline_limit(ByteSize::b(0))
-            .expected_stdout(JobOutputResult::Truncated {
-                first: boxed_u8!(b""),
-                truncated: 2,
-            })
-            .run()
-            .await;
+    async fn stdout_inline_limit_0() {
+        Test::new(bash_spec("echo a"))
+            .inline_limit(ByteSize::b(0))
+            .expected_stdout(JobOutputResult::Truncated {
+                first: boxed_u8!(b""),
+                truncated: 2,
+            })
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stdout_inline_limit_1() {
+        Test::new(bash_spec("echo a"))
+            .inline_limit(ByteSize::b(1))
+            .expected_stdout(JobOutputResult::Truncated {
+                first: boxed_u8!(b"a"),
+                truncated: 1,
+            })
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stdout_inline_limit_2() {
+        Test::new(bash_spec("echo a"))
+            .inline_limit(ByteSize::b(2))
+            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"a\n")))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stdout_inline_limit_3() {
+        Test::new(bash_spec("echo a"))
+            .inline_limit(ByteSize::b(3))
+            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"a\n")))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stderr_inline_limit_0() {
+        Test::new(bash_spec("echo a >&2"))
+            .inline_limit(ByteSize::b(0))
+            .expected_stderr(JobOutputResult::Truncated {
+                first: boxed_u8!(b""),
+                truncated: 2,
+            })
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stderr_inline_limit_1() {
+        Test::new(bash_spec("echo a >&2"))
+            .inline_limit(ByteSize::b(1))
+            .expected_stderr(JobOutputResult::Truncated {
+                first: boxed_u8!(b"a"),
+                truncated: 1,
+            })
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stderr_inline_limit_2() {
+        Test::new(bash_spec("echo a >&2"))
+            .inline_limit(ByteSize::b(2))
+            .expected_stderr(JobOutputResult::Inline(boxed_u8!(b"a\n")))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stderr_inline_limit_3() {
+        Test::new(bash_spec("echo a >&2"))
+            .inline_limit(ByteSize::b(3))
+            .expected_stderr(JobOutputResult::Inline(boxed_u8!(b"a\n")))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn environment() {
+        Test::new(bash_spec("echo -n $FOO - $BAR").environment(["FOO=3", "BAR=4"]))
+            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"3 - 4")))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stdin_empty() {
+        Test::new(test_spec("/bin/cat")).run().await;
+    }
+
+    #[tokio::test]
+    async fn pid_ppid_pgid_and_sid() {
+        // We should be pid 1, that is, init for our namespace).
+        // We should have ppid 0, indicating that our parent isn't accessible in our namespace.
+        // We should have pgid 1, indicating that we're the group leader.
+        // We should have sid 1, indicating that we're the session leader.
+        Test::new(python_spec(indoc! {r#"
+            import os
+            print('pid:', os.getpid())
+            print('ppid:', os.getppid())
+            print('pgid:', os.getpgid(0))
+            print('sid:', os.getsid(0))
+        "#}))
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
+            pid: 1
+            ppid: 0
+            pgid: 1
+            sid: 1
+        "})))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn no_loopback() {
+        Test::new(
+            test_spec("/bin/cat")
+                .arguments(["/sys/class/net/lo/carrier"])
+                .mounts([JobMount::Sys {
+                    mount_point: utf8_path_buf!("/sys"),
+                }]),
+        )
+        .expected_status(JobTerminationStatus::Exited(1))
+        .expected_stderr(JobOutputResult::Inline(boxed_u8!(
+            b"cat: read error: Invalid argument\n"
+        )))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn loopback() {
+        Test::new(
+            test_spec("/bin/cat")
+                .arguments(["/sys/class/net/lo/carrier"])
+                .mounts([JobMount::Sys {
+                    mount_point: utf8_path_buf!("/sys"),
+                }])
+                .network(JobNetwork::Loopback),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"1\n")))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn local_network() {
+        let (port_sender, port_receiver) = oneshot::channel();
+        let listening_task = task::spawn(async move {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            port_sender
+                .send(listener.local_addr().unwrap().port())
+                .unwrap();
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut contents = String::new();
+            socket.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, "hello");
+            socket.write_all(b"goodbye").await.unwrap();
+        });
+        let port = port_receiver.await.unwrap();
+        Test::new(
+            python_spec(&format!(
+                indoc! {r#"
+                    import socket
+                    with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
+                        s.connect(("127.0.0.1", {port}))
+                        s.sendall(b"hello")
+                        s.shutdown(socket.SHUT_WR)
+                        print(s.recv(1024).decode(), end="")
+                "#},
+                port = port
+            ))
+            .network(JobNetwork::Local),
+        )
+        .expected_status(JobTerminationStatus::Exited(0))
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"goodbye")))
+        .run()
+        .await;
+        listening_task.await.unwrap()
+    }
+
+    async fn echo_once(listener: impl Future<Output = impl AsyncRead + AsyncWrite + Unpin>) {
+        let mut socket = listener.await;
+        let mut contents = String::new();
+        socket.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "hello");
+        socket.write_all(b"goodbye").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn forward_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let listening_task =
+            task::spawn(echo_once(async move { listener.accept().await.unwrap().0 }));
+        Test::new(
+            python_spec(indoc! {r#"
+                import socket
+                with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
+                    s.connect(("127.0.0.1", 8000))
+                    s.sendall(b"hello")
+                    s.shutdown(socket.SHUT_WR)
+                    print(s.recv(1024).decode(), end="")
+            "#})
+            .network(JobNetwork::Loopback)
+            .forwards([JobForward::Tcp {
+                port: 8000,
+                host: format!("127.0.0.1:{port}"),
+            }]),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"goodbye")))
+        .run()
+        .await;
+        listening_task.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn forward_unix() {
+        let temp_dir = TempDir::new().unwrap();
+        let host = Utf8PathBuf::try_from(temp_dir.path().join("host.sock")).unwrap();
+        let listener = UnixListener::bind(&host).unwrap();
+        let listening_task =
+            task::spawn(echo_once(async move { listener.accept().await.unwrap().0 }));
+        Test::new(
+            python_spec(indoc! {r#"
+                import socket
+                with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as s:
+                    s.connect("/tmp/job.sock")
+                    s.sendall(b"hello")
+                    s.shutdown(socket.SHUT_WR)
+                    print(s.recv(1024).decode(), end="")
+            "#})
+            .mounts([JobMount::Tmp {
+                mount_point: utf8_path_buf!("/tmp"),
+                size: None,
+                mode: None,
+                inodes: None,
+            }])
+            .forwards([JobForward::Unix {
+                path: utf8_path_buf!("/tmp/job.sock"),
+                host,
+            }]),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"goodbye")))
+        .run()
+        .await;
+        listening_task.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn tmp_mount_with_mode_and_size() {
+        Test::new(
+            python_spec(indoc! {r#"
+                import errno, os
+                print(oct(os.stat("/tmp").st_mode & 0o7777))
+                try:
+                    with open("/tmp/foo", "wb") as f:
+                        f.write(b"x" * 2 * 1024 * 1024)
+                except OSError as e:
+                    print(errno.errorcode[e.errno], end="")
+            "#})
+            .mounts([JobMount::Tmp {
+                mount_point: utf8_path_buf!("/tmp"),
+                size: Some(1024 * 1024),
+                mode: Some(0o1777),
+                inodes: None,
+            }]),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"0o1777\nENOSPC")))
+        .expected_full_tmpfs_mounts(["/tmp"])
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn tmp_mount_larger_than_max_tmpfs_size() {
+        let spec = test_spec("/bin/true").mounts([JobMount::Tmp {
+            mount_point: utf8_path_buf!("/tmp"),
+            size: Some(2 * 1024 * 1024),
+            mode: None,
+            inodes: None,
+        }]);
+        let output_dir = TempDir::new().unwrap();
+        let options = ExecutorOptions {
+            max_tmpfs_size: Some(1024 * 1024),
+            ..Default::default()
+        };
+        assert_matches!(
+            run_with_executor_options(spec, "0".parse().unwrap(), output_dir.path(), options).await,
+            Err(JobError::Execution(err)) if err.to_string() ==
+                "tmpfs size of 2097152 bytes is larger than this worker's max-tmpfs-size of \
+                1048576 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_not_allowed() {
+        let spec = test_spec("/bin/true")
+            .network(JobNetwork::Loopback)
+            .forwards([JobForward::Tcp {
+                port: 8000,
+                host: "127.0.0.1:8000".into(),
+            }]);
+        let output_dir = TempDir::new().unwrap();
+        let options = ExecutorOptions {
+            allowed_forwards: Some(vec!["127.0.0.1:5432".into()]),
+            ..Default::default()
+        };
+        assert_matches!(
+            run_with_executor_options(spec, "0".parse().unwrap(), output_dir.path(), options).await,
+            Err(JobError::Execution(err)) if err.to_string() ==
+                "forwarding 127.0.0.1:8000 isn't allowed by this worker"
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_tcp_without_loopback() {
+        let spec = test_spec("/bin/true").forwards([JobForward::Tcp {
+            port: 8000,
+            host: "127.0.0.1:8000".into(),
+        }]);
+        assert_execution_error(spec).await;
+    }
+
+    fn sidecar(script: &str) -> JobSidecar {
+        JobSidecar::new(
+            "server",
+            "/usr/bin/python3",
+            nonempty![(digest![0], ArtifactType::Tar)],
+        )
+        .arguments(["-c", script])
+    }
+
+    #[tokio::test]
+    async fn sidecar_is_ready_before_job_starts() {
+        Test::new(
+            python_spec(indoc! {r#"
+                import socket
+                with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
+                    s.connect(("127.0.0.1", 8000))
+                    print(s.recv(1024).decode(), end="")
+            "#})
+            .network(JobNetwork::Loopback)
+            .sidecars([sidecar(indoc! {r#"
+                import socket
+                with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
+                    s.bind(("127.0.0.1", 8000))
+                    s.listen()
+                    while True:
+                        conn, _ = s.accept()
+                        with conn:
+                            conn.sendall(b"hello")
+            "#})
+            .ready(Some(JobReadinessCheck {
+                port: 8000,
+                timeout: Timeout::new(10).unwrap(),
+            }))]),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"hello")))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn sidecar_exits_before_ready() {
+        let spec = test_spec("/bin/true")
+            .network(JobNetwork::Loopback)
+            .sidecars([sidecar("pass").ready(Some(JobReadinessCheck {
+                port: 8000,
+                timeout: Timeout::new(10).unwrap(),
+            }))]);
+        assert_execution_error(spec).await;
+    }
+
+    #[tokio::test]
+    async fn sidecar_bad_program() {
+        let spec = test_spec("/bin/true")
+            .network(JobNetwork::Loopback)
+            .sidecars([JobSidecar::new(
+                "server",
+                "a_program_that_does_not_exist",
+                nonempty![(digest![0], ArtifactType::Tar)],
+            )]);
+        assert_execution_error(spec).await;
+    }
+
+    #[tokio::test]
+    async fn sidecar_without_loopback() {
+        let spec = test_spec("/bin/true").sidecars([sidecar("pass")]);
+        assert_execution_error(spec).await;
+    }
+
+    #[tokio::test]
+    async fn user_and_group_0() {
+        Test::new(python_spec(indoc! {r#"
+            import os
+            print('uid:', os.getuid())
+            print('gid:', os.getgid())
+        "#}))
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
+            uid: 0
+            gid: 0
+        "})))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn user_and_group_nonzero() {
+        Test::new(
+            python_spec(indoc! {r#"
+                import os
+                print('uid:', os.getuid())
+                print('gid:', os.getgid())
+            "#})
+            .user(Some(43))
+            .group(Some(100)),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
+            uid: 43
+            gid: 100
+        "})))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn user_entries() {
+        Test::new(
+            python_spec(indoc! {r#"
+                import grp, os, pwd
+                user = pwd.getpwuid(os.getuid())
+                print('user:', user.pw_name)
+                print('home:', user.pw_dir, os.path.isdir(user.pw_dir))
+                print('group:', grp.getgrgid(os.getgid()).gr_name)
+            "#})
+            .user(Some(43))
+            .group(Some(100))
+            .user_entries(Some(JobUserEntries {
+                user_name: Some("alice".into()),
+                ..Default::default()
+            })),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
+            user: alice
+            home: /home/alice True
+            group: alice
+        "})))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn user_entries_with_tmp_root_overlay() {
+        Test::new(
+            bash_spec("echo hi > ~/foo && cat ~/foo && echo ~")
+                .user(Some(43))
+                .group(Some(100))
+                .root_overlay(JobRootOverlay::Tmp {
+                    size: None,
+                    inodes: None,
+                })
+                .user_entries(Some(JobUserEntries {
+                    home: Some("/work".into()),
+                    ..Default::default()
+                })),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"hi\n/work\n")))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn hostname_and_extra_hosts() {
+        Test::new(
+            python_spec(indoc! {r#"
+                import socket
+                print(socket.gethostname())
+                print(open('/etc/hosts').read(), end='')
+            "#})
+            .hostname(Some("builder"))
+            .extra_hosts([JobHost {
+                name: "db".into(),
+                address: "10.0.0.2".parse().unwrap(),
+            }]),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
+            builder
+            127.0.0.1\tlocalhost
+            ::1\tlocalhost ip6-localhost ip6-loopback
+            127.0.1.1\tbuilder
+            10.0.0.2\tdb
+        "})))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn extra_host_with_bad_name() {
+        let spec = test_spec("/bin/true").extra_hosts([JobHost {
+            name: "db\n10.0.0.3\tevil".into(),
+            address: "10.0.0.2".parse().unwrap(),
+        }]);
+        assert_matches!(
+            run(spec, "0".parse().unwrap()).await,
+            Err(JobError::Execution(err)) if err.to_string() ==
+                r#""db\n10.0.0.3\tevil" isn't a valid hostname"#
+        );
+    }
+
+    #[tokio::test]
+    async fn clock_offsets() {
+        const MONOTONIC_OFFSET: i64 = 2 * 86400;
+        const BOOTTIME_OFFSET: i64 = 86400;
+        // How far the job's clocks may be from the worker's, beyond the time it takes to run the
+        // job, before we consider the offset wrong.
+        const TOLERANCE: Duration = Duration::from_secs(1);
+
+        let clocks = || {
+            (
+                linux::clock_gettime(ClockId::MONOTONIC).unwrap(),
+                linux::clock_gettime(ClockId::BOOTTIME).unwrap(),
+            )
+        };
+        let spec = python_spec(indoc! {r#"
+            import time
+            print(time.clock_gettime(time.CLOCK_MONOTONIC))
+            print(time.clock_gettime(time.CLOCK_BOOTTIME))
+        "#})
+        .clock_offsets(Some(JobClockOffsets {
+            monotonic: MONOTONIC_OFFSET,
+            boottime: BOOTTIME_OFFSET,
+        }));
+
+        let (monotonic_before, boottime_before) = clocks();
+        let JobCompleted { status, effects } = run(spec, InlineLimit::from(ByteSize::b(1000)))
+            .await
+            .unwrap();
+        let (monotonic_after, boottime_after) = clocks();
+
+        assert_eq!(status, JobTerminationStatus::Exited(0));
+        let JobOutputResult::Inline(stdout) = effects.stdout else {
+            panic!("unexpected stdout: {:?}", effects.stdout);
+        };
+        let stdout = str::from_utf8(&stdout).unwrap();
+        let [job_monotonic, job_boottime] = stdout
+            .lines()
+            .map(|line| Duration::from_secs_f64(line.parse().unwrap()))
+            .collect::<Vec<_>>()[..]
+        else {
+            panic!("unexpected stdout: {stdout:?}");
+        };
+
+        for (job, before, after, offset) in [
+            (
+                job_monotonic,
+                monotonic_before,
+                monotonic_after,
+                MONOTONIC_OFFSET,
+            ),
+            (
+                job_boottime,
+                boottime_before,
+                boottime_after,
+                BOOTTIME_OFFSET,
+            ),
+        ] {
+            let offset = Duration::from_secs(offset.try_into().unwrap());
+            assert!(
+                job + TOLERANCE >= before + offset && job <= after + offset + TOLERANCE,
+                "job clock {job:?} not within {TOLERANCE:?} of worker clock \
+                [{before:?}, {after:?}] plus offset {offset:?}"
+            );
+        }
     }
 
+    /// This test requires a host `/proc/sys/kernel/core_pattern` that we can find core files for.
+    /// A pattern that pipes to `systemd-coredump` also requires permission to run `coredumpctl`.
+    /// Run it with `--ignored` on such a host.
     #[tokio::test]
-    async fn stdout_inline_limit_1() {
-        Test::new(bash_spec("echo a"))
-            .inline_limit(ByteSize::b(1))
-            .expected_stdout(JobOutputResult::Truncated {
-                first: boxed_u8!(b"a"),
-                truncated: 1,
+    #[ignore = "requires a supported core_pattern"]
+    async fn core_dump_is_captured() {
+        let core_pattern = core_dump::CorePattern::read().unwrap();
+        assert!(
+            !matches!(core_pattern, core_dump::CorePattern::Unsupported(_)),
+            "unsupported core_pattern: {core_pattern:?}"
+        );
+        let spec = python_spec("import os; os.abort()")
+            .root_overlay(JobRootOverlay::Tmp {
+                size: None,
+                inodes: None,
             })
-            .run()
-            .await;
+            .capture_core_dump(true);
+        let JobCompleted { status, effects } = run(spec, InlineLimit::from(ByteSize::b(1000)))
+            .await
+            .unwrap();
+        assert_matches!(status, JobTerminationStatus::Signaled(_));
+        assert_eq!(effects.core_dump_not_captured, None);
+        let core_dump = effects.core_dump.unwrap();
+        assert!(core_dump.size > 0);
     }
 
     #[tokio::test]
-    async fn stdout_inline_limit_2() {
-        Test::new(bash_spec("echo a"))
-            .inline_limit(ByteSize::b(2))
-            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"a\n")))
-            .run()
-            .await;
+    async fn core_dump_is_not_captured_if_not_asked_for() {
+        let spec = python_spec("import os; os.abort()").root_overlay(JobRootOverlay::Tmp {
+            size: None,
+            inodes: None,
+        });
+        let JobCompleted { effects, .. } = run(spec, InlineLimit::from(ByteSize::b(1000)))
+            .await
+            .unwrap();
+        assert_eq!(effects.core_dump, None);
+        assert_eq!(effects.core_dump_not_captured, None);
     }
 
-    #[tokio::test]
-    async fn stdout_inline_limit_3() {
-        Test::new(bash_spec("echo a"))
-            .inline_limit(ByteSize::b(3))
-            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"a\n")))
-            .run()
-            .await;
+    fn writable_layer_spec(script: &str) -> maelstrom_base::JobSpec {
+        bash_spec(script)
+            .root_overlay(JobRootOverlay::Tmp {
+                size: None,
+                inodes: None,
+            })
+            .mounts([JobMount::Tmp {
+                mount_point: utf8_path_buf!("/tmp"),
+                size: None,
+                mode: None,
+                inodes: None,
+            }])
+            .keep_writable_layer_on_failure(true)
     }
 
     #[tokio::test]
-    async fn stderr_inline_limit_0() {
-        Test::new(bash_spec("echo a >&2"))
-            .inline_limit(ByteSize::b(0))
-            .expected_stderr(JobOutputResult::Truncated {
-                first: boxed_u8!(b""),
-                truncated: 2,
-            })
-            .run()
-            .await;
+    async fn writable_layer_is_kept_on_failure() {
+        let output_dir = TempDir::new().unwrap();
+        let spec =
+            writable_layer_spec("echo foo > /foo && mkdir /bar && echo baz > /tmp/baz && exit 1");
+        let JobCompleted { status, effects } = run_with_output_dir(
+            spec,
+            InlineLimit::from(ByteSize::b(1000)),
+            output_dir.path(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, JobTerminationStatus::Exited(1));
+        let writable_layer = effects.writable_layer.unwrap();
+
+        let tar_path = output_dir.path().join("writable-layer.tar");
+        assert_eq!(fs::metadata(&tar_path).unwrap().len(), writable_layer.size);
+        let mut contents = BTreeMap::new();
+        for entry in tar::Archive::new(fs::File::open(&tar_path).unwrap())
+            .entries()
+            .unwrap()
+        {
+            let mut entry = entry.unwrap();
+            let path = entry
+                .path()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .trim_end_matches('/')
+                .to_owned();
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            contents.insert(path, data);
+        }
+        assert_eq!(
+            contents,
+            BTreeMap::from([
+                ("bar".into(), "".into()),
+                ("foo".into(), "foo\n".into()),
+                ("tmp".into(), "".into()),
+                ("tmp/baz".into(), "baz\n".into()),
+            ])
+        );
     }
 
     #[tokio::test]
-    async fn stderr_inline_limit_1() {
-        Test::new(bash_spec("echo a >&2"))
-            .inline_limit(ByteSize::b(1))
-            .expected_stderr(JobOutputResult::Truncated {
-                first: boxed_u8!(b"a"),
-                truncated: 1,
-            })
-            .run()
-            .await;
+    async fn writable_layer_is_not_kept_on_success() {
+        let spec = writable_layer_spec("echo foo > /foo && echo baz > /tmp/baz");
+        let JobCompleted { status, effects } = run(spec, InlineLimit::from(ByteSize::b(1000)))
+            .await
+            .unwrap();
+        assert_eq!(status, JobTerminationStatus::Exited(0));
+        assert_eq!(effects.writable_layer, None);
     }
 
-    #[tokio::test]
-    async fn stderr_inline_limit_2() {
-        Test::new(bash_spec("echo a >&2"))
-            .inline_limit(ByteSize::b(2))
-            .expected_stderr(JobOutputResult::Inline(boxed_u8!(b"a\n")))
-            .run()
-            .await;
+    async fn run_for_resource_usage(spec: maelstrom_base::JobSpec) -> JobResourceUsage {
+        let JobCompleted { status, effects } = run(spec, InlineLimit::from(ByteSize::b(1000)))
+            .await
+            .unwrap();
+        assert_eq!(status, JobTerminationStatus::Exited(0));
+        effects.resource_usage.unwrap()
     }
 
     #[tokio::test]
-    async fn stderr_inline_limit_3() {
-        Test::new(bash_spec("echo a >&2"))
-            .inline_limit(ByteSize::b(3))
-            .expected_stderr(JobOutputResult::Inline(boxed_u8!(b"a\n")))
-            .run()
-            .await;
+    async fn resource_usage_cpu_time_and_memory() {
+        let usage = run_for_resource_usage(python_spec(indoc! {r#"
+            data = b"x" * (64 * 1024 * 1024)
+            total = sum(range(10_000_000))
+        "#}))
+        .await;
+        assert!(usage.user_time > Duration::ZERO, "{usage:?}");
+        assert!(usage.user_time < Duration::from_secs(60), "{usage:?}");
+        assert!(usage.system_time < Duration::from_secs(60), "{usage:?}");
+        assert!(usage.peak_memory >= 64 * 1024 * 1024, "{usage:?}");
+        assert!(usage.peak_memory < 1024 * 1024 * 1024, "{usage:?}");
     }
 
     #[tokio::test]
-    async fn environment() {
-        Test::new(bash_spec("echo -n $FOO - $BAR").environment(["FOO=3", "BAR=4"]))
-            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"3 - 4")))
-            .run()
-            .await;
+    async fn resource_usage_spawned_processes() {
+        const CHILDREN: u64 = 4;
+        // The children run one after another, so at most two processes are ever running at once.
+        let usage = run_for_resource_usage(
+            bash_spec(&format!("for i in {{1..{CHILDREN}}}; do sleep 0; done")).init(true),
+        )
+        .await;
+        assert_eq!(usage.spawned_processes, Some(CHILDREN + 1));
     }
 
     #[tokio::test]
-    async fn stdin_empty() {
-        Test::new(test_spec("/bin/cat")).run().await;
+    async fn resource_usage_spawned_processes_without_init() {
+        let usage = run_for_resource_usage(bash_spec("sleep 0").init(false)).await;
+        assert_eq!(usage.spawned_processes, None);
     }
 
     #[tokio::test]
-    async fn pid_ppid_pgid_and_sid() {
-        // We should be pid 1, that is, init for our namespace).
-        // We should have ppid 0, indicating that our parent isn't accessible in our namespace.
-        // We should have pgid 1, indicating that we're the group leader.
-        // We should have sid 1, indicating that we're the session leader.
-        Test::new(python_spec(indoc! {r#"
-            import os
-            print('pid:', os.getpid())
-            print('ppid:', os.getppid())
-            print('pgid:', os.getpgid(0))
-            print('sid:', os.getsid(0))
-        "#}))
-        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
-            pid: 1
-            ppid: 0
-            pgid: 1
-            sid: 1
-        "})))
+    async fn rlimits() {
+        Test::new(bash_spec("ulimit -Sn; ulimit -Hn; ulimit -c").rlimits([
+            JobRlimit {
+                resource: JobRlimitResource::NoFile,
+                soft: 100,
+                hard: 200,
+            },
+            JobRlimit {
+                resource: JobRlimitResource::Core,
+                soft: 0,
+                hard: 0,
+            },
+        ]))
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"100\n200\n0\n")))
         .run()
         .await;
     }
 
     #[tokio::test]
-    async fn no_loopback() {
-        Test::new(
-            test_spec("/bin/cat")
-                .arguments(["/sys/class/net/lo/carrier"])
-                .mounts([JobMount::Sys {
-                    mount_point: utf8_path_buf!("/sys"),
-                }]),
-        )
-        .expected_status(JobTerminationStatus::Exited(1))
-        .expected_stderr(JobOutputResult::Inline(boxed_u8!(
-            b"cat: read error: Invalid argument\n"
-        )))
-        .run()
-        .await;
+    async fn rlimit_above_hard_limit() {
+        let spec = test_spec("/bin/true").rlimits([JobRlimit {
+            resource: JobRlimitResource::NoFile,
+            soft: JobRlimit::UNLIMITED,
+            hard: JobRlimit::UNLIMITED,
+        }]);
+        assert_execution_error(spec).await;
     }
 
     #[tokio::test]
-    async fn loopback() {
+    async fn seccomp_errno() {
         Test::new(
-            test_spec("/bin/cat")
-                .arguments(["/sys/class/net/lo/carrier"])
-                .mounts([JobMount::Sys {
-                    mount_point: utf8_path_buf!("/sys"),
-                }])
-                .network(JobNetwork::Loopback),
+            bash_spec("cd / 2>/dev/null; echo $?").seccomp(Some(JobSeccomp {
+                profile: JobSeccompProfile::Deny {
+                    syscalls: vec!["chdir".into()],
+                },
+                action: JobSeccompAction::Errno(1),
+            })),
         )
         .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"1\n")))
+        .expected_blocked_syscalls([("chdir", 1)])
         .run()
         .await;
     }
 
     #[tokio::test]
-    async fn local_network() {
-        let (port_sender, port_receiver) = oneshot::channel();
-        let listening_task = task::spawn(async move {
-            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-            port_sender
-                .send(listener.local_addr().unwrap().port())
-                .unwrap();
-            let (mut socket, _) = listener.accept().await.unwrap();
-            let mut contents = String::new();
-            socket.read_to_string(&mut contents).await.unwrap();
-            assert_eq!(contents, "hello");
-            socket.write_all(b"goodbye").await.unwrap();
-        });
-        let port = port_receiver.await.unwrap();
-        Test::new(
-            python_spec(&format!(
-                indoc! {r#"
-                    import socket
-                    with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
-                        s.connect(("127.0.0.1", {port}))
-                        s.sendall(b"hello")
-                        s.shutdown(socket.SHUT_WR)
-                        print(s.recv(1024).decode(), end="")
-                "#},
-                port = port
-            ))
-            .network(JobNetwork::Local),
-        )
-        .expected_status(JobTerminationStatus::Exited(0))
-        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"goodbye")))
+    async fn seccomp_kill() {
+        Test::new(bash_spec("cd /; echo hello").seccomp(Some(JobSeccomp {
+            profile: JobSeccompProfile::Deny {
+                syscalls: vec!["chdir".into()],
+            },
+            action: JobSeccompAction::Kill,
+        })))
+        .expected_status(JobTerminationStatus::SyscallBlocked)
         .run()
         .await;
-        listening_task.await.unwrap()
     }
 
     #[tokio::test]
-    async fn user_and_group_0() {
-        Test::new(python_spec(indoc! {r#"
-            import os
-            print('uid:', os.getuid())
-            print('gid:', os.getgid())
-        "#}))
-        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
-            uid: 0
-            gid: 0
-        "})))
+    async fn seccomp_unknown_syscall() {
+        let spec = test_spec("/bin/true").seccomp(Some(JobSeccomp {
+            profile: JobSeccompProfile::Deny {
+                syscalls: vec!["frobnicate".into()],
+            },
+            action: JobSeccompAction::Kill,
+        }));
+        assert_execution_error(spec).await;
+    }
+
+    #[tokio::test]
+    async fn init_is_pid_1() {
+        Test::new(bash_spec("echo $$").init(true))
+            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"2\n")))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn init_reports_exit_code() {
+        Test::new(bash_spec("exit 3").init(true))
+            .expected_status(JobTerminationStatus::Exited(3))
+            .run()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn init_reports_signal() {
+        // Without init, the program would be PID 1, and the SIGABRT would be ignored. Compare with
+        // the signaled_11 test.
+        Test::new(
+            python_spec(indoc! {r#"
+                import os
+                os.abort()
+            "#})
+            .init(true),
+        )
+        .expected_status(JobTerminationStatus::Signaled(6))
         .run()
         .await;
     }
 
     #[tokio::test]
-    async fn user_and_group_nonzero() {
+    async fn init_reaps_orphans() {
+        // The grandchild is orphaned and reparented to PID 1. We never wait for the child, so it
+        // stays a zombie, but its parent isn't PID 1.
         Test::new(
             python_spec(indoc! {r#"
+                import glob
                 import os
-                print('uid:', os.getuid())
-                print('gid:', os.getgid())
+                import time
+                if os.fork() == 0:
+                    if os.fork() == 0:
+                        os._exit(0)
+                    os._exit(0)
+                time.sleep(0.5)
+                orphaned_zombies = 0
+                for path in glob.glob('/proc/[0-9]*/stat'):
+                    fields = open(path).read().rsplit(')', 1)[1].split()
+                    if fields[0] == 'Z' and fields[1] == '1':
+                        orphaned_zombies += 1
+                print(orphaned_zombies)
             "#})
-            .user(Some(43))
-            .group(Some(100)),
+            .init(true),
         )
-        .expected_stdout(JobOutputResult::Inline(boxed_u8!(indoc! {b"
-            uid: 43
-            gid: 100
-        "})))
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"0\n")))
+        .run()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn init_forwards_signals() {
+        Test::new(
+            bash_spec("trap 'echo got TERM; exit 0' TERM; kill -TERM 1; sleep 1 & wait").init(true),
+        )
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"got TERM\n")))
         .run()
         .await;
     }
@@ -2089,11 +4392,16 @@ mod tests {
 
     #[tokio::test]
     async fn one_layer_with_tmp_root_overlay_is_writable() {
-        Test::new(bash_spec("echo bar > /foo && cat /foo").root_overlay(JobRootOverlay::Tmp))
-            .expected_status(JobTerminationStatus::Exited(0))
-            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"bar\n")))
-            .run()
-            .await;
+        Test::new(
+            bash_spec("echo bar > /foo && cat /foo").root_overlay(JobRootOverlay::Tmp {
+                size: None,
+                inodes: None,
+            }),
+        )
+        .expected_status(JobTerminationStatus::Exited(0))
+        .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"bar\n")))
+        .run()
+        .await;
 
         // Run another job to ensure that the file doesn't persist.
         Test::new(bash_spec("test -e /foo"))
@@ -2104,7 +4412,10 @@ mod tests {
 
     #[tokio::test]
     async fn multiple_layers_with_tmp_root_overlay_is_writable() {
-        let spec = bash_spec("echo bar > /foo && cat /foo").root_overlay(JobRootOverlay::Tmp);
+        let spec = bash_spec("echo bar > /foo && cat /foo").root_overlay(JobRootOverlay::Tmp {
+            size: None,
+            inodes: None,
+        });
         Test::new(spec)
             .expected_status(JobTerminationStatus::Exited(0))
             .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"bar\n")))
@@ -2112,13 +4423,37 @@ mod tests {
             .await;
 
         // Run another job to ensure that the file doesn't persist.
-        let spec = bash_spec("test -e /foo").root_overlay(JobRootOverlay::Tmp);
+        let spec = bash_spec("test -e /foo").root_overlay(JobRootOverlay::Tmp {
+            size: None,
+            inodes: None,
+        });
         Test::new(spec)
             .expected_status(JobTerminationStatus::Exited(1))
             .run()
             .await;
     }
 
+    #[tokio::test]
+    async fn tmp_root_overlay_with_size_runs_out_of_space() {
+        let spec = python_spec(indoc! {r#"
+            import errno
+            try:
+                with open("/foo", "wb") as f:
+                    f.write(b"x" * 2 * 1024 * 1024)
+            except OSError as e:
+                print(errno.errorcode[e.errno], end="")
+        "#})
+        .root_overlay(JobRootOverlay::Tmp {
+            size: Some(1024 * 1024),
+            inodes: None,
+        });
+        Test::new(spec)
+            .expected_stdout(JobOutputResult::Inline(boxed_u8!(b"ENOSPC")))
+            .expected_full_tmpfs_mounts(["/"])
+            .run()
+            .await;
+    }
+
     #[tokio::test]
     async fn local_root_overlay_is_writable_and_output_is_captured() {
         let temp_dir = TempDir::new().unwrap();
@@ -2473,6 +4808,9 @@ mod tests {
                     },
                     JobMount::Tmp {
                         mount_point: utf8_path_buf!("/tmp"),
+                        size: None,
+                        mode: None,
+                        inodes: None,
                     },
                 ]),
         )
@@ -2706,6 +5044,23 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn layer_mount() {
+        Test::new(
+            bash_spec("test -x /mnt/bin/cat && ! touch /mnt/foo 2>/dev/null").mounts([
+                JobMount::Layer {
+                    mount_point: utf8_path_buf!("/mnt"),
+                    layers: nonempty![(digest!(42), ArtifactType::Tar)],
+                },
+                JobMount::Devices {
+                    devices: enum_set!(JobDevice::Null),
+                },
+            ]),
+        )
+        .run()
+        .await;
+    }
+
     #[tokio::test]
     async fn old_mounts_are_unmounted() {
         Test::new(
@@ -1,13 +1,30 @@
 use anyhow::{anyhow, Result};
 use maelstrom_base::{
-    proto::{ArtifactFetcherToBroker, BrokerToArtifactFetcher, Hello},
+    proto::{
+        ArtifactCompression, ArtifactFetcherToBroker, ArtifactFetcherToWorker, ArtifactSource,
+        BrokerToArtifactFetcher, Hello, WorkerToArtifactFetcher,
+    },
     Sha256Digest,
 };
 use maelstrom_linux as linux;
-use maelstrom_util::{config::common::BrokerAddr, fs::Fs, io, net};
-use slog::{debug, Logger};
+use maelstrom_util::{
+    config::common::BrokerAddr,
+    fs::Fs,
+    io::{MaybeFastWriter, Sha256Stream},
+    net,
+};
+use slog::{debug, warn, Logger};
 use std::os::fd::AsRawFd as _;
-use std::{net::TcpStream, path::PathBuf};
+use std::{
+    io::{self, Read as _},
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// How long we wait on a peer before giving up on it and going to the broker instead. This applies
+/// both to connecting and to each read.
+const PEER_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub fn main(
     digest: &Sha256Digest,
@@ -15,23 +32,74 @@ pub fn main(
     broker_addr: BrokerAddr,
     log: &mut Logger,
 ) -> Result<u64> {
+    let peer = match fetch_from_broker(digest, &path, broker_addr, true, log)? {
+        ArtifactSource::Broker(size, _) => return Ok(size),
+        ArtifactSource::Peer(peer) => peer,
+    };
+    match fetch_from_peer(digest, &path, peer, log) {
+        Ok(size) => Ok(size),
+        Err(err) => {
+            warn!(log, "error fetching artifact from peer, falling back to broker";
+                "peer" => %peer, "err" => %err);
+            match fetch_from_broker(digest, &path, broker_addr, false, log)? {
+                ArtifactSource::Broker(size, _) => Ok(size),
+                ArtifactSource::Peer(_) => Err(anyhow!("broker sent us to a peer again")),
+            }
+        }
+    }
+}
+
+/// Ask the broker for the artifact. If the broker sends it, write it to `path`. If `allow_peer` is
+/// set, the broker may instead tell us which peer to get it from, in which case nothing is
+/// written. We let the broker compress what it sends, since its bandwidth is shared by all of the
+/// workers.
+fn fetch_from_broker(
+    digest: &Sha256Digest,
+    path: &Path,
+    broker_addr: BrokerAddr,
+    allow_peer: bool,
+    log: &mut Logger,
+) -> Result<ArtifactSource> {
     let mut stream = TcpStream::connect(broker_addr.inner())?;
     net::write_message_to_socket(&mut stream, Hello::ArtifactFetcher)?;
 
-    let msg = ArtifactFetcherToBroker(digest.clone());
+    let msg = ArtifactFetcherToBroker {
+        digest: digest.clone(),
+        allow_peer,
+        accept_compression: ArtifactCompression::Zstd,
+    };
     debug!(log, "artifact fetcher sending message"; "msg" => ?msg);
 
     net::write_message_to_socket(&mut stream, msg)?;
     let msg = net::read_message_from_socket::<BrokerToArtifactFetcher>(&mut stream)?;
     debug!(log, "artifact fetcher received message"; "msg" => ?msg);
-    let expected_size = msg
+    let source = msg
         .0
         .map_err(|e| anyhow!("Broker error reading artifact: {e}"))?;
+    match source {
+        ArtifactSource::Broker(expected_size, ArtifactCompression::None) => {
+            receive_artifact(&stream, path, expected_size, log)?;
+        }
+        ArtifactSource::Broker(expected_size, ArtifactCompression::Zstd) => {
+            receive_compressed_artifact(&stream, path, expected_size)?;
+        }
+        ArtifactSource::Peer(_) => {}
+    }
+    Ok(source)
+}
 
+/// Read `expected_size` bytes from `stream` into a new file at `path`. The data is trusted, so we
+/// don't verify it, and we can move it without copying it into our address space.
+fn receive_artifact(
+    stream: &TcpStream,
+    path: &Path,
+    expected_size: u64,
+    log: &mut Logger,
+) -> Result<()> {
     let fs = Fs::new();
     let file = fs.create_file(path)?;
 
-    let mut writer = io::MaybeFastWriter::new(log.clone());
+    let mut writer = MaybeFastWriter::new(log.clone());
 
     let stream_fd = linux::Fd::from_raw(stream.as_raw_fd());
     let file_fd = linux::Fd::from_raw(file.as_raw_fd());
@@ -49,5 +117,52 @@ pub fn main(
         file_offset += written as u64;
     }
 
+    Ok(())
+}
+
+/// Like [`receive_artifact`], except the body is a zstd frame which we decompress as we read it.
+fn receive_compressed_artifact(stream: &TcpStream, path: &Path, expected_size: u64) -> Result<()> {
+    let fs = Fs::new();
+    let mut file = fs.create_file(path)?;
+    let mut decoder = zstd::Decoder::new(stream)?.single_frame();
+    let copied = io::copy(&mut (&mut decoder).take(expected_size), &mut file)?;
+    if copied != expected_size {
+        return Err(anyhow!("got unexpected EOF receiving artifact"));
+    }
+    Ok(())
+}
+
+/// Get the artifact from the artifact server of the worker at `peer`, and write it to `path`. We
+/// don't trust the peer to still have the artifact, or for the artifact to be intact, so we verify
+/// the digest of what it sends us.
+fn fetch_from_peer(
+    digest: &Sha256Digest,
+    path: &Path,
+    peer: SocketAddr,
+    log: &mut Logger,
+) -> Result<u64> {
+    let mut stream = TcpStream::connect_timeout(&peer, PEER_TIMEOUT)?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+
+    let msg = ArtifactFetcherToWorker(digest.clone());
+    debug!(log, "artifact fetcher sending message to peer"; "msg" => ?msg);
+
+    net::write_message_to_socket(&mut stream, msg)?;
+    let msg = net::read_message_from_socket::<WorkerToArtifactFetcher>(&mut stream)?;
+    debug!(log, "artifact fetcher received message from peer"; "msg" => ?msg);
+    let expected_size = msg
+        .0
+        .map_err(|e| anyhow!("Peer error reading artifact: {e}"))?;
+
+    let fs = Fs::new();
+    let mut file = fs.create_file(path)?;
+    let mut reader = Sha256Stream::new((&mut stream).take(expected_size));
+    let copied = io::copy(&mut reader, &mut file)?;
+    if copied != expected_size {
+        return Err(anyhow!("got unexpected EOF receiving artifact"));
+    }
+    let (_, actual_digest) = reader.finalize();
+    actual_digest.verify(digest)?;
+
     Ok(expected_size)
 }
@@ -1,19 +1,14 @@
+use crate::cache::EntryStamp;
 use anyhow::Result;
-use futures::StreamExt as _;
 use maelstrom_base::{manifest::UnixTimestamp, ArtifactType, Sha256Digest};
 use maelstrom_layer_fs::{BlobDir, BottomLayerBuilder, LayerFs, UpperLayerBuilder};
 use maelstrom_util::{async_fs::Fs, root::Root};
-use std::path::{Path, PathBuf};
-use tokio::io::BufReader;
+use std::path::PathBuf;
+use tokio::{io::BufReader, task};
 
-async fn dir_size(fs: &Fs, path: &Path) -> Result<u64> {
-    let mut total = 0;
-    let mut entries = fs.read_dir(path).await?;
-    while let Some(e) = entries.next().await {
-        let e = e?;
-        total += e.metadata().await?.len();
-    }
-    Ok(total)
+/// Take the [`EntryStamp`] of the finished layer at `layer_path`, off of the async runtime.
+async fn layer_stamp(layer_path: PathBuf) -> Result<EntryStamp> {
+    Ok(task::spawn_blocking(move || EntryStamp::read(&layer_path)).await??)
 }
 
 pub async fn build_bottom_layer(
@@ -23,7 +18,7 @@ pub async fn build_bottom_layer(
     artifact_digest: Sha256Digest,
     artifact_type: ArtifactType,
     artifact_path: PathBuf,
-) -> Result<u64> {
+) -> Result<EntryStamp> {
     let fs = Fs::new();
     fs.create_dir_all(&layer_path).await?;
     let mut builder =
@@ -35,7 +30,7 @@ pub async fn build_bottom_layer(
     }
     builder.finish().await?;
 
-    dir_size(&fs, &layer_path).await
+    layer_stamp(layer_path).await
 }
 
 pub async fn build_upper_layer(
@@ -44,7 +39,7 @@ pub async fn build_upper_layer(
     blob_dir: &Root<BlobDir>,
     lower_layer_path: PathBuf,
     upper_layer_path: PathBuf,
-) -> Result<u64> {
+) -> Result<EntryStamp> {
     let fs = Fs::new();
     fs.create_dir_all(&layer_path).await?;
     let lower = LayerFs::from_path(&lower_layer_path, blob_dir)?;
@@ -54,5 +49,5 @@ pub async fn build_upper_layer(
     builder.fill_from_bottom_layer(&upper).await?;
     builder.finish().await?;
 
-    dir_size(&fs, &layer_path).await
+    layer_stamp(layer_path).await
 }
@@ -1,45 +1,55 @@
 //! Code for the worker binary.
 
+mod artifact_server;
 mod cache;
+mod cache_mount;
+mod cgroup;
 pub mod config;
+mod core_dump;
 mod dispatcher;
 mod executor;
 mod fetcher;
 mod layer_fs;
 pub mod local_worker;
+mod pusher;
+mod seccomp;
 pub mod signals;
+mod user_files;
+mod writable_layer;
 
-use anyhow::{anyhow, bail, Context as _, Result};
-use cache::{Cache, CacheDir, StdFs};
+use anyhow::{anyhow, bail, Context as _, Error, Result};
+use cache::{Cache, CacheDir, EntryStamp, StdFs};
+use cache_mount::{CacheMountDir, CacheMountLocks, CacheMounts};
 use config::Config;
-use dispatcher::{Deps, Dispatcher, Message};
-use executor::{Executor, MountDir, TmpfsDir};
-use futures::StreamExt as _;
+use dispatcher::{Deps, Dispatcher, JobLayerPaths, Message, PrefetchBudget};
+use executor::{Executor, FuseSpawner, MountDir, TmpfsDir};
+use futures::{future, StreamExt as _};
 use lru::LruCache;
 use maelstrom_base::{
     manifest::{ManifestEntryData, ManifestFileData},
     proto::{BrokerToWorker, Hello, WorkerToBroker},
-    ArtifactType, JobError, JobId, JobSpec, Sha256Digest,
+    ArtifactType, JobError, JobId, JobResult, JobSpec, Sha256Digest,
 };
 use maelstrom_layer_fs::{BlobDir, LayerFs, ReaderCache};
 use maelstrom_linux::{
-    self as linux, CloneArgs, CloneFlags, PollEvents, PollFd, Signal, WaitStatus,
+    self as linux, CloneArgs, CloneFlags, OwnedFd, PollEvents, PollFd, Signal, WaitStatus,
 };
 use maelstrom_util::{
     async_fs,
-    config::common::{BrokerAddr, InlineLimit, Slots},
+    config::common::{BrokerAddr, CacheSize, InlineLimit, Slots, TmpfsSize},
     fs::Fs,
     manifest::AsyncManifestReader,
     net,
-    root::RootBuf,
+    root::{Root, RootBuf},
     sync::{self, EventReceiver, EventSender},
     time::SystemMonotonicClock,
 };
-use slog::{debug, error, info, o, Logger};
+use slog::{debug, error, info, o, warn, Logger};
 use std::future::Future;
 use std::pin::pin;
 use std::{
     collections::{HashMap, HashSet},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6},
     num::NonZeroUsize,
     path::Path,
     slice,
@@ -48,14 +58,18 @@ use std::{
 };
 use tokio::{
     io::BufReader,
-    net::TcpStream,
+    net::{TcpListener, TcpStream},
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     task::{self, JoinHandle},
     time,
 };
+use user_files::UserFiles;
 
 pub struct WorkerCacheDir;
 
+/// Where files produced by jobs, like core files, are kept until they're sent to the client.
+pub struct OutputDir;
+
 async fn read_manifest(path: &Path) -> Result<HashSet<Sha256Digest>> {
     let fs = async_fs::Fs::new();
     let mut reader = AsyncManifestReader::new(fs.open_file(path).await?).await?;
@@ -164,6 +178,9 @@ pub struct DispatcherAdapter {
     layer_fs_cache: Arc<tokio::sync::Mutex<ReaderCache>>,
     manifest_digest_cache: ManifestDigestCache,
     layer_building_semaphore: Arc<tokio::sync::Semaphore>,
+    cache_mounts: Arc<CacheMounts>,
+    output_dir: RootBuf<OutputDir>,
+    broker_addr: Option<BrokerAddr>,
 }
 
 pub const MAX_IN_FLIGHT_LAYERS_BUILDS: usize = 10;
@@ -177,13 +194,38 @@ impl DispatcherAdapter {
         mount_dir: RootBuf<MountDir>,
         tmpfs_dir: RootBuf<TmpfsDir>,
         blob_dir: RootBuf<BlobDir>,
+        cache_mount_dir: RootBuf<CacheMountDir>,
+        cache_mount_size: CacheSize,
+        max_cache_mount_size: Option<CacheSize>,
+        max_tmpfs_size: Option<TmpfsSize>,
+        allowed_forwards: Option<Vec<String>>,
+        output_dir: RootBuf<OutputDir>,
+        broker_addr: Option<BrokerAddr>,
     ) -> Result<Self> {
         let fs = Fs::new();
         fs.create_dir_all(&mount_dir)?;
         fs.create_dir_all(&tmpfs_dir)?;
+
+        // Any outputs left over from a previous run have no one waiting for them anymore.
+        if fs.exists(&output_dir) {
+            fs.remove_dir_all(&output_dir)?;
+        }
+        fs.create_dir_all(&output_dir)?;
+        let cache_mounts = CacheMounts::new(
+            cache_mount_dir,
+            cache_mount_size,
+            max_cache_mount_size,
+            log.clone(),
+        )?;
         Ok(DispatcherAdapter {
             inline_limit,
-            executor: Arc::new(Executor::new(mount_dir, tmpfs_dir, &SystemMonotonicClock)?),
+            executor: Arc::new(Executor::new(
+                mount_dir,
+                tmpfs_dir,
+                max_tmpfs_size.map(u64::from),
+                allowed_forwards,
+                &SystemMonotonicClock,
+            )?),
             blob_dir,
             layer_fs_cache: Arc::new(tokio::sync::Mutex::new(ReaderCache::new())),
             manifest_digest_cache: ManifestDigestCache::new(
@@ -196,6 +238,9 @@ impl DispatcherAdapter {
             layer_building_semaphore: Arc::new(tokio::sync::Semaphore::new(
                 MAX_IN_FLIGHT_LAYERS_BUILDS,
             )),
+            cache_mounts: Arc::new(cache_mounts),
+            output_dir,
+            broker_addr,
         })
     }
 
@@ -203,7 +248,8 @@ impl DispatcherAdapter {
         &mut self,
         jid: JobId,
         spec: JobSpec,
-        layer_fs_path: PathBuf,
+        paths: JobLayerPaths,
+        cache_mount_locks: JobResult<CacheMountLocks, Error>,
         kill_event_receiver: EventReceiver,
     ) -> Result<()> {
         debug!(self.log, "job starting"; "spec" => ?spec);
@@ -213,39 +259,237 @@ impl DispatcherAdapter {
             "args" => format!("{:?}", spec.arguments)
         ));
 
-        let layer_fs = LayerFs::from_path(&layer_fs_path, self.blob_dir.as_root())?;
-        let layer_fs_cache = self.layer_fs_cache.clone();
-        let fuse_spawn = move |fd| {
-            tokio::spawn(async move {
-                if let Err(e) = layer_fs.run_fuse(log.clone(), layer_fs_cache, fd).await {
-                    slog::error!(log, "FUSE handling got error {e:?}");
-                }
-            });
+        // The user files are built from the files in the job's image, which we read directly out of
+        // the layer-fs, instead of going through FUSE.
+        let user_files_source = match &spec.user_entries {
+            Some(entries) => Some((
+                LayerFs::from_path(&paths.root, self.blob_dir.as_root())?,
+                self.layer_fs_cache.clone(),
+                entries.clone(),
+                spec.user.unwrap_or(0.into()),
+                spec.group.unwrap_or(0.into()),
+            )),
+            None => None,
         };
 
+        let fuse_spawner = self.job_fuse_spawner(&paths, log.clone())?;
+        let cache_mount_log = log;
+
+        let capture_core_dump = spec.capture_core_dump;
+        let keep_writable_layer_on_failure = spec.keep_writable_layer_on_failure;
         let executor = self.executor.clone();
-        let spec = executor::JobSpec::from_spec(spec);
+        let mut spec = executor::JobSpec::from_spec(spec, self.blob_dir.as_root());
+        if capture_core_dump {
+            spec.core_dump_path = Some(
+                self.output_dir
+                    .join::<()>(format!("{}.{}.core", jid.cid, jid.cjid))
+                    .into_path_buf(),
+            );
+        }
+        if keep_writable_layer_on_failure {
+            spec.writable_layer_path = Some(
+                self.output_dir
+                    .join::<()>(format!("{}.{}.tar", jid.cid, jid.cjid))
+                    .into_path_buf(),
+            );
+        }
+        let output_dir = self.output_dir.clone();
+        let broker_addr = self.broker_addr;
+        let output_log = self.log.clone();
         let inline_limit = self.inline_limit;
         let dispatcher_sender = self.dispatcher_sender.clone();
         let runtime = tokio::runtime::Handle::current();
+        let cache_mounts = self.cache_mounts.clone();
         task::spawn_blocking(move || {
+            let result = match user_files_source {
+                Some((layer_fs, cache, entries, user, group)) => runtime
+                    .block_on(UserFiles::read(&layer_fs, &cache, &entries, user, group))
+                    .map(|user_files| spec.user_files = Some(user_files)),
+                None => Ok(()),
+            };
+
+            // The dispatcher locked the job's cache mounts in memory before starting it. Creating
+            // the caches and taking their file locks may block, as do measuring the caches and
+            // evicting old ones, so all of that is done here. Releasing the locks has to be done
+            // before the dispatcher hears that the job completed.
+            let result = result
+                .and(cache_mount_locks)
+                .and_then(|mut cache_mount_locks| {
+                    cache_mounts
+                        .acquire(&mut cache_mount_locks)
+                        .map_err(JobError::System)?;
+                    let sidecar_mounts = spec
+                        .sidecars
+                        .iter_mut()
+                        .flat_map(|sidecar| &mut sidecar.spec.mounts);
+                    cache_mount_locks.replace_mounts(spec.mounts.iter_mut().chain(sidecar_mounts));
+
+                    // Kill the job if one of its caches grows too big, too.
+                    let kill_event_receiver = match cache_mount_locks.take_kill_event() {
+                        Some(cache_kill_event_receiver) => {
+                            let (sender, receiver) = sync::event();
+                            runtime.spawn(async move {
+                                future::select(kill_event_receiver, cache_kill_event_receiver)
+                                    .await;
+                                drop(sender);
+                            });
+                            receiver
+                        }
+                        None => kill_event_receiver,
+                    };
+                    let result = executor.run_job(
+                        &spec,
+                        inline_limit,
+                        kill_event_receiver,
+                        fuse_spawner,
+                        runtime,
+                    );
+                    if cache_mount_locks.is_empty() {
+                        return result;
+                    }
+                    let result = cache_mounts.check_size(&cache_mount_locks).and(result);
+                    if let Err(err) = cache_mounts.release(cache_mount_locks) {
+                        error!(cache_mount_log, "error evicting cache mounts"; "err" => ?err);
+                    }
+                    result
+                })
+                .map(|mut completed| {
+                    if let (Some(core_dump), Some(path)) =
+                        (&completed.effects.core_dump, &spec.core_dump_path)
+                    {
+                        if let Err(err) = store_output(
+                            path,
+                            &core_dump.digest,
+                            core_dump.size,
+                            &output_dir,
+                            broker_addr,
+                            &output_log,
+                        ) {
+                            error!(output_log, "error storing core file"; "err" => ?err);
+                            completed.effects.core_dump = None;
+                        }
+                    }
+                    if let (Some(writable_layer), Some(path)) =
+                        (&completed.effects.writable_layer, &spec.writable_layer_path)
+                    {
+                        if let Err(err) = store_output(
+                            path,
+                            &writable_layer.digest,
+                            writable_layer.size,
+                            &output_dir,
+                            broker_addr,
+                            &output_log,
+                        ) {
+                            error!(output_log, "error storing writable layer"; "err" => ?err);
+                            completed.effects.writable_layer = None;
+                        }
+                    }
+                    completed
+                });
             dispatcher_sender
                 .send(Message::JobCompleted(
                     jid,
-                    executor
-                        .run_job(
-                            &spec,
-                            inline_limit,
-                            kill_event_receiver,
-                            fuse_spawn,
-                            runtime,
-                        )
-                        .map_err(|e| e.map(|inner| inner.to_string())),
+                    Box::new(result.map_err(|e| e.map(|inner| inner.to_string()))),
                 ))
                 .ok()
         });
         Ok(())
     }
+
+    /// Open the layer-fs for each of a job's file systems, so they can be served over FUSE once the
+    /// job mounts them. Each sidecar has its own root file system, and each layer mount is its own
+    /// file system, so each gets its own FUSE connection.
+    fn job_fuse_spawner(&self, paths: &JobLayerPaths, log: Logger) -> Result<JobFuseSpawner> {
+        let open_all = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| LayerFs::from_path(path, self.blob_dir.as_root()).map(Some))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(JobFuseSpawner {
+            root: Some(LayerFs::from_path(&paths.root, self.blob_dir.as_root())?),
+            sidecars: open_all(&paths.sidecars)?,
+            layer_mounts: open_all(&paths.layer_mounts)?,
+            layer_fs_cache: self.layer_fs_cache.clone(),
+            log,
+        })
+    }
+}
+
+/// Serves a job's file systems, each from its own layer-fs. Each file system is expected to be
+/// mounted only once.
+struct JobFuseSpawner {
+    root: Option<LayerFs>,
+    sidecars: Vec<Option<LayerFs>>,
+    layer_mounts: Vec<Option<LayerFs>>,
+    layer_fs_cache: Arc<tokio::sync::Mutex<ReaderCache>>,
+    log: Logger,
+}
+
+impl JobFuseSpawner {
+    fn spawn_indexed(
+        &self,
+        layer_fs: Option<LayerFs>,
+        kind: &'static str,
+        index: usize,
+        fd: OwnedFd,
+    ) {
+        let Some(layer_fs) = layer_fs else {
+            slog::error!(self.log, "unexpected FUSE fd"; "kind" => kind, "index" => index);
+            return;
+        };
+        self.spawn(layer_fs, self.log.new(o!(kind => index)), fd);
+    }
+
+    fn spawn(&self, layer_fs: LayerFs, log: Logger, fd: OwnedFd) {
+        let layer_fs_cache = self.layer_fs_cache.clone();
+        tokio::spawn(async move {
+            if let Err(e) = layer_fs.run_fuse(log.clone(), layer_fs_cache, fd).await {
+                slog::error!(log, "FUSE handling got error {e:?}");
+            }
+        });
+    }
+}
+
+impl FuseSpawner for JobFuseSpawner {
+    fn spawn_root(&mut self, fd: OwnedFd) {
+        match self.root.take() {
+            Some(layer_fs) => self.spawn(layer_fs, self.log.clone(), fd),
+            None => slog::error!(self.log, "unexpected FUSE fd"; "kind" => "root"),
+        }
+    }
+
+    fn spawn_sidecar(&mut self, index: usize, fd: OwnedFd) {
+        let layer_fs = self.sidecars.get_mut(index).and_then(Option::take);
+        self.spawn_indexed(layer_fs, "sidecar", index, fd);
+    }
+
+    fn spawn_layer_mount(&mut self, index: usize, fd: OwnedFd) {
+        let layer_fs = self.layer_mounts.get_mut(index).and_then(Option::take);
+        self.spawn_indexed(layer_fs, "layer_mount", index, fd);
+    }
+}
+
+/// Make a file produced by a job available to the client. If we have a broker, the file is pushed to
+/// it. Otherwise, we're a client's local worker, and the file is left in `output_dir`, named by its
+/// digest, for the client to pick up. Either way, `path` is gone afterwards.
+fn store_output(
+    path: &Path,
+    digest: &Sha256Digest,
+    size: u64,
+    output_dir: &Root<OutputDir>,
+    broker_addr: Option<BrokerAddr>,
+    log: &Logger,
+) -> Result<()> {
+    let fs = Fs::new();
+    match broker_addr {
+        Some(broker_addr) => {
+            let result = pusher::main(digest, size, path, broker_addr, log);
+            fs.remove_file(path)?;
+            result
+        }
+        None => fs.rename(path, output_dir.join::<()>(digest.to_string())),
+    }
 }
 
 pub struct TimerHandle(JoinHandle<()>);
@@ -259,12 +503,30 @@ impl Drop for TimerHandle {
 impl Deps for DispatcherAdapter {
     type JobHandle = EventSender;
 
-    fn start_job(&mut self, jid: JobId, spec: JobSpec, layer_fs_path: PathBuf) -> Self::JobHandle {
+    /// If the job's cache mounts are bad, the job is started anyway, and fails with the error.
+    type CacheMountLocks = JobResult<CacheMountLocks, Error>;
+
+    fn try_lock_cache_mounts(&mut self, spec: &JobSpec) -> Option<Self::CacheMountLocks> {
+        let sidecar_mounts = spec.sidecars.iter().flat_map(|sidecar| &sidecar.mounts);
+        self.cache_mounts
+            .try_lock_mounts(spec.mounts.iter().chain(sidecar_mounts))
+            .transpose()
+    }
+
+    fn start_job(
+        &mut self,
+        jid: JobId,
+        spec: JobSpec,
+        paths: JobLayerPaths,
+        cache_mount_locks: Self::CacheMountLocks,
+    ) -> Self::JobHandle {
         let (kill_event_sender, kill_event_receiver) = sync::event();
-        if let Err(e) = self.start_job_inner(jid, spec, layer_fs_path, kill_event_receiver) {
+        if let Err(e) =
+            self.start_job_inner(jid, spec, paths, cache_mount_locks, kill_event_receiver)
+        {
             let _ = self.dispatcher_sender.send(Message::JobCompleted(
                 jid,
-                Err(JobError::System(e.to_string())),
+                Box::new(Err(JobError::System(e.to_string()))),
             ));
         }
         kill_event_sender
@@ -370,7 +632,8 @@ impl dispatcher::ArtifactFetcher for ArtifactFetcher {
         ));
         debug!(log, "artifact fetcher starting");
         thread::spawn(move || {
-            let result = fetcher::main(&digest, path, broker_addr, &mut log);
+            let result = fetcher::main(&digest, path.clone(), broker_addr, &mut log)
+                .and_then(|_| Ok(EntryStamp::read(&path)?));
             debug!(log, "artifact fetcher completed"; "result" => ?result);
             sender
                 .send(Message::ArtifactFetchCompleted(digest, result))
@@ -463,6 +726,8 @@ async fn dispatcher_main(
     let tmpfs_dir = config.cache_root.join::<TmpfsDir>("upper");
     let cache_root = config.cache_root.join::<CacheDir>("artifacts");
     let blob_dir = cache_root.join::<BlobDir>("blob/sha256");
+    let cache_mount_dir = config.cache_root.join::<CacheMountDir>("cache-mounts");
+    let output_dir = config.cache_root.join::<OutputDir>("output");
 
     let broker_sender = BrokerSender::new(broker_socket_outgoing_sender);
     let cache = Cache::new(StdFs, cache_root, config.cache_size, log.clone());
@@ -475,6 +740,13 @@ async fn dispatcher_main(
         mount_dir,
         tmpfs_dir,
         blob_dir,
+        cache_mount_dir,
+        config.cache_mount_size,
+        config.max_cache_mount_size,
+        config.max_tmpfs_size,
+        Some(config.allowed_forwards),
+        output_dir,
+        Some(config.broker),
     ) {
         Err(err) => {
             error!(log, "could not start executor"; "err" => ?err);
@@ -486,6 +758,10 @@ async fn dispatcher_main(
                 broker_sender,
                 cache,
                 config.slots,
+                PrefetchBudget {
+                    max_jobs: config.max_prefetches.into_inner().into(),
+                    cache_bytes: config.prefetch_cache_size.into(),
+                },
             );
             handle_incoming_messages(
                 log,
@@ -551,6 +827,31 @@ pub async fn main_inner(config: Config, log: Logger) -> Result<()> {
     info!(log, "started"; "config" => ?config, "pid" => process::id());
 
     check_open_file_limit(&log, config.slots, 0)?;
+    core_dump::check_core_pattern(&log);
+
+    let (artifact_server_listener, artifact_server_port) = match config.artifact_server_port {
+        None => (None, None),
+        Some(port) => {
+            let port = port.into_inner();
+            let sock_addr = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, port, 0, 0);
+            // Fall back to IPv4 on hosts where IPv6 is disabled.
+            let listener = match TcpListener::bind(sock_addr).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    warn!(log, "couldn't bind artifact server to IPv6 address, trying IPv4";
+                        "err" => %err);
+                    TcpListener::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port))
+                        .await
+                        .context("binding artifact server socket")?
+                }
+            };
+            let addr = listener
+                .local_addr()
+                .context("retrieving artifact server local address")?;
+            info!(log, "serving artifacts to peers"; "addr" => addr);
+            (Some(listener), Some(addr.port()))
+        }
+    };
 
     let (read_stream, mut write_stream) = TcpStream::connect(config.broker.inner())
         .await
@@ -565,6 +866,7 @@ pub async fn main_inner(config: Config, log: Logger) -> Result<()> {
         &mut write_stream,
         Hello::Worker {
             slots: (*config.slots.inner()).into(),
+            artifact_server_port,
         },
     )
     .await
@@ -609,6 +911,17 @@ pub async fn main_inner(config: Config, log: Logger) -> Result<()> {
         dispatcher_sender.clone(),
     ));
 
+    if let Some(listener) = artifact_server_listener {
+        let blob_dir = config
+            .cache_root
+            .join::<CacheDir>("artifacts")
+            .join::<BlobDir>("blob/sha256");
+        tokio::task::spawn(shutdown_on_error(
+            artifact_server::main(listener, blob_dir, dispatcher_sender.clone(), log.clone()),
+            dispatcher_sender.clone(),
+        ));
+    }
+
     dispatcher_main(
         config,
         dispatcher_receiver,
@@ -716,6 +1029,12 @@ async fn gen_1_process_main(gen_2_pid: linux::Pid) -> WaitStatus {
 pub fn clone_into_pid_and_user_namespace() -> Result<()> {
     maelstrom_util::thread::assert_single_threaded()?;
 
+    // Try to move ourselves into a cgroup subtree we can use to enforce jobs' resource limits.
+    // This has to happen before we create any other processes. If it doesn't work, we just won't
+    // be able to run jobs with resource limits. We don't have a logger yet, so the executor will
+    // report the error when a job asks for limits.
+    let _ = cgroup::delegate();
+
     let gen_0_uid = linux::getuid();
     let gen_0_gid = linux::getgid();
 
@@ -1,9 +1,11 @@
 pub use crate::{
-    cache::{Cache, CacheDir, GetArtifact, StdFs},
+    cache::{Cache, CacheDir, EntryStamp, GetArtifact, StdFs},
+    cache_mount::CacheMountDir,
     check_open_file_limit,
-    dispatcher::{ArtifactFetcher, BrokerSender, Deps, Dispatcher, Message},
+    core_dump::check_core_pattern,
+    dispatcher::{ArtifactFetcher, BrokerSender, Deps, Dispatcher, Message, PrefetchBudget},
     executor::MountDir,
     executor::TmpfsDir,
-    DispatcherAdapter, WorkerCacheDir,
+    DispatcherAdapter, OutputDir, WorkerCacheDir,
 };
 pub use maelstrom_layer_fs::BlobDir;
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use maelstrom_base::{
+    proto::{
+        ArtifactCompression, ArtifactPusherToBroker, BrokerToArtifactPusher,
+        BrokerToArtifactPusherHello, Hello,
+    },
+    Sha256Digest,
+};
+use maelstrom_util::{config::common::BrokerAddr, fs::Fs, net};
+use slog::{debug, Logger};
+use std::{io, net::TcpStream, path::Path};
+
+/// Push the file at `path` to the broker as an artifact. The caller is expected to have already
+/// computed its digest and size.
+pub fn main(
+    digest: &Sha256Digest,
+    size: u64,
+    path: &Path,
+    broker_addr: BrokerAddr,
+    log: &Logger,
+) -> Result<()> {
+    let mut stream = TcpStream::connect(broker_addr.inner())?;
+    net::write_message_to_socket(&mut stream, Hello::ArtifactPusher)?;
+    let BrokerToArtifactPusherHello { accept_compression } =
+        net::read_message_from_socket(&mut stream)?;
+
+    // Compress if the broker accepts it, since it may have to send the artifact on to clients.
+    let msg = ArtifactPusherToBroker(digest.clone(), size, accept_compression);
+    debug!(log, "artifact pusher sending message"; "msg" => ?msg);
+    net::write_message_to_socket(&mut stream, msg)?;
+
+    let fs = Fs::new();
+    let mut file = fs.open_file(path)?;
+    let copied = match accept_compression {
+        ArtifactCompression::None => io::copy(&mut file, &mut stream)?,
+        ArtifactCompression::Zstd => {
+            let mut encoder = zstd::Encoder::new(&mut stream, 0)?;
+            let copied = io::copy(&mut file, &mut encoder)?;
+            encoder.finish()?;
+            copied
+        }
+    };
+    if copied != size {
+        return Err(anyhow!("artifact changed size while pushing it"));
+    }
+
+    let msg = net::read_message_from_socket::<BrokerToArtifactPusher>(&mut stream)?;
+    debug!(log, "artifact pusher received message"; "msg" => ?msg);
+    msg.0
+        .map_err(|e| anyhow!("Broker error writing artifact: {e}"))
+}
@@ -0,0 +1,368 @@
+//! Build the seccomp-BPF filters installed in jobs right before they exec their program.
+//!
+//! A filter first checks that the syscall was made through the native ABI, then compares the
+//! syscall number against each listed syscall in turn. Deny lists (including the default profile)
+//! return the blocked action on a match and allow everything else. Allow lists do the opposite.
+//!
+//! Because the filter is installed before the job's program is exec'd, the syscalls the child
+//! needs to exec and report failure to the worker are always allowed in allow lists.
+//!
+//! When blocked syscalls fail with an errno, the filter doesn't return the errno itself. Instead,
+//! it sends a notification to a listener in the worker, which counts the syscall and returns the
+//! errno (see [`supervise`]). That way, the job's outcome can say which syscalls were blocked.
+
+use anyhow::{anyhow, Result};
+use maelstrom_base::{JobBlockedSyscall, JobSeccomp, JobSeccompAction, JobSeccompProfile};
+use maelstrom_linux::{self as linux, Errno, OwnedFd, PollEvents, PollFd, SockFilter};
+use std::{collections::BTreeMap, time::Duration};
+
+/// Syscalls blocked by the default profile. This is modeled after Docker's default profile, minus
+/// the syscalls that the user namespace already prevents from doing anything harmful.
+const DEFAULT_DENIED_SYSCALLS: &[&str] = &[
+    "acct",
+    "add_key",
+    "bpf",
+    "clock_adjtime",
+    "clock_settime",
+    #[cfg(target_arch = "x86_64")]
+    "create_module",
+    "delete_module",
+    "finit_module",
+    #[cfg(target_arch = "x86_64")]
+    "get_kernel_syms",
+    "get_mempolicy",
+    "init_module",
+    #[cfg(target_arch = "x86_64")]
+    "ioperm",
+    #[cfg(target_arch = "x86_64")]
+    "iopl",
+    "kcmp",
+    "kexec_file_load",
+    "kexec_load",
+    "keyctl",
+    "lookup_dcookie",
+    "mbind",
+    "mount",
+    "move_pages",
+    "name_to_handle_at",
+    #[cfg(target_arch = "x86_64")]
+    "nfsservctl",
+    "open_by_handle_at",
+    "perf_event_open",
+    "pivot_root",
+    "process_vm_readv",
+    "process_vm_writev",
+    "ptrace",
+    #[cfg(target_arch = "x86_64")]
+    "query_module",
+    "quotactl",
+    "reboot",
+    "request_key",
+    "set_mempolicy",
+    "setns",
+    "settimeofday",
+    "swapoff",
+    "swapon",
+    #[cfg(target_arch = "x86_64")]
+    "_sysctl",
+    "umount2",
+    "unshare",
+    #[cfg(target_arch = "x86_64")]
+    "uselib",
+    "userfaultfd",
+    #[cfg(target_arch = "x86_64")]
+    "ustat",
+];
+
+/// The default profile only allows these arguments to `personality`: `PER_LINUX`, and the value
+/// used to query the current personality. Other personalities can disable security features like
+/// address-space randomization.
+const DEFAULT_ALLOWED_PERSONALITIES: &[u32] = &[0, 0xffffffff];
+
+/// Syscalls that are always allowed in allow lists. The child needs these to exec the program, to
+/// send the worker the filter's listener, or to tell the worker that it couldn't exec.
+const IMPLICITLY_ALLOWED_SYSCALLS: &[&str] = &["execve", "sendmsg", "sendto", "exit_group"];
+
+/// The syscall the child uses to send the worker the filter's listener. If the filter blocks it,
+/// the child can't send the listener, so blocked syscalls can't be reported.
+const LISTENER_SYSCALL: &str = "sendmsg";
+
+fn lookup(name: &str) -> Result<u32> {
+    linux::syscall_number(name)
+        .ok_or_else(|| anyhow!("unknown syscall `{name}` in seccomp profile"))
+}
+
+/// Return the errno that blocked syscalls should fail with, if the filter for the given seccomp
+/// configuration sends them to a listener. A filter that kills the process doesn't need one.
+pub fn notify_errno(seccomp: &JobSeccomp) -> Option<u16> {
+    let JobSeccompAction::Errno(errno) = seccomp.action else {
+        return None;
+    };
+    match &seccomp.profile {
+        JobSeccompProfile::Deny { syscalls } if syscalls.iter().any(|s| s == LISTENER_SYSCALL) => {
+            None
+        }
+        _ => Some(errno),
+    }
+}
+
+fn blocked(seccomp: &JobSeccomp) -> SockFilter {
+    match seccomp.action {
+        JobSeccompAction::Errno(_) if notify_errno(seccomp).is_some() => {
+            SockFilter::return_user_notif()
+        }
+        JobSeccompAction::Errno(errno) => SockFilter::return_errno(errno),
+        JobSeccompAction::Kill => SockFilter::return_kill_process(),
+    }
+}
+
+/// Build the filter for the given seccomp configuration. An error is returned if a syscall name is
+/// unknown on this architecture.
+pub fn build_filter(seccomp: &JobSeccomp) -> Result<Vec<SockFilter>> {
+    let blocked = blocked(seccomp);
+    let (numbers, on_match, otherwise) = match &seccomp.profile {
+        JobSeccompProfile::Default => (
+            DEFAULT_DENIED_SYSCALLS
+                .iter()
+                .copied()
+                .map(lookup)
+                .collect::<Result<Vec<_>>>()?,
+            blocked,
+            SockFilter::return_allow(),
+        ),
+        JobSeccompProfile::Deny { syscalls } => (
+            syscalls
+                .iter()
+                .map(String::as_str)
+                .map(lookup)
+                .collect::<Result<Vec<_>>>()?,
+            blocked,
+            SockFilter::return_allow(),
+        ),
+        JobSeccompProfile::Allow { syscalls } => (
+            syscalls
+                .iter()
+                .map(String::as_str)
+                .chain(IMPLICITLY_ALLOWED_SYSCALLS.iter().copied())
+                .map(lookup)
+                .collect::<Result<Vec<_>>>()?,
+            SockFilter::return_allow(),
+            blocked,
+        ),
+    };
+
+    let mut filter = vec![
+        SockFilter::load_arch(),
+        SockFilter::jump_if_equal(linux::AUDIT_ARCH_NATIVE, 1, 0),
+        SockFilter::return_kill_process(),
+        SockFilter::load_syscall_number(),
+    ];
+    #[cfg(target_arch = "x86_64")]
+    filter.extend([
+        SockFilter::jump_if_greater_or_equal(linux::X32_SYSCALL_BIT, 0, 1),
+        blocked,
+    ]);
+    if seccomp.profile == JobSeccompProfile::Default {
+        // If the syscall is `personality`, check its argument, and then either allow or block it.
+        // Otherwise, skip over all of that.
+        let allowed = DEFAULT_ALLOWED_PERSONALITIES.len() as u8;
+        filter.extend([
+            SockFilter::jump_if_equal(lookup("personality")?, 0, allowed + 3),
+            SockFilter::load_syscall_arg_low(0),
+        ]);
+        for (i, &persona) in DEFAULT_ALLOWED_PERSONALITIES.iter().enumerate() {
+            filter.push(SockFilter::jump_if_equal(persona, allowed - i as u8, 0));
+        }
+        filter.extend([blocked, SockFilter::return_allow()]);
+    }
+    // Each comparison either falls through to the return right after it, or skips over it. This
+    // way, we never have to jump farther than a jump instruction can reach.
+    for nr in numbers {
+        filter.extend([SockFilter::jump_if_equal(nr, 0, 1), on_match]);
+    }
+    filter.push(otherwise);
+    Ok(filter)
+}
+
+/// Answer the notifications sent to a filter's `listener`, making each blocked syscall fail with
+/// `errno`, until the write end of the `stop` pipe is closed. Return how many times each syscall
+/// was blocked.
+pub fn supervise(listener: OwnedFd, stop: OwnedFd, errno: u16) -> Vec<JobBlockedSyscall> {
+    let mut counts = BTreeMap::<u32, u64>::new();
+    loop {
+        let mut fds = [
+            PollFd::new(listener.as_fd(), PollEvents::IN),
+            PollFd::new(stop.as_fd(), PollEvents::IN),
+        ];
+        match linux::poll(&mut fds, Duration::from_secs(60)) {
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(_) => break,
+        }
+        if fds[0].revents().contains(PollEvents::IN) {
+            // The process that made the syscall may have been killed since, in which case there's
+            // nothing to answer.
+            if let Ok(notif) = linux::seccomp_notif_recv(&listener) {
+                *counts.entry(notif.syscall_number()).or_default() += 1;
+                let _ = linux::seccomp_notif_send_errno(&listener, notif.id(), errno);
+            }
+        } else if fds[0].revents().contains(PollEvents::HUP)
+            || fds[1].revents().contains(PollEvents::HUP)
+        {
+            // Either every process using the filter is gone, or the job is done.
+            break;
+        }
+    }
+    let mut blocked: Vec<_> = counts
+        .into_iter()
+        .map(|(nr, count)| JobBlockedSyscall {
+            name: linux::syscall_name(nr)
+                .map(Into::into)
+                .unwrap_or_else(|| nr.to_string()),
+            count,
+        })
+        .collect();
+    blocked.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    blocked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prologue(blocked: SockFilter) -> Vec<SockFilter> {
+        let mut filter = vec![
+            SockFilter::load_arch(),
+            SockFilter::jump_if_equal(linux::AUDIT_ARCH_NATIVE, 1, 0),
+            SockFilter::return_kill_process(),
+            SockFilter::load_syscall_number(),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([
+            SockFilter::jump_if_greater_or_equal(linux::X32_SYSCALL_BIT, 0, 1),
+            blocked,
+        ]);
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = blocked;
+        filter
+    }
+
+    #[test]
+    fn default_profile_syscalls_are_known() {
+        for name in DEFAULT_DENIED_SYSCALLS {
+            lookup(name).unwrap();
+        }
+        for name in IMPLICITLY_ALLOWED_SYSCALLS {
+            lookup(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn deny_list() {
+        let filter = build_filter(&JobSeccomp {
+            profile: JobSeccompProfile::Deny {
+                syscalls: vec!["ptrace".into(), "mount".into()],
+            },
+            action: JobSeccompAction::Errno(1),
+        })
+        .unwrap();
+        let blocked = SockFilter::return_user_notif();
+        let mut expected = prologue(blocked);
+        expected.extend([
+            SockFilter::jump_if_equal(lookup("ptrace").unwrap(), 0, 1),
+            blocked,
+            SockFilter::jump_if_equal(lookup("mount").unwrap(), 0, 1),
+            blocked,
+            SockFilter::return_allow(),
+        ]);
+        assert_eq!(filter, expected);
+    }
+
+    #[test]
+    fn allow_list() {
+        let filter = build_filter(&JobSeccomp {
+            profile: JobSeccompProfile::Allow {
+                syscalls: vec!["read".into()],
+            },
+            action: JobSeccompAction::Kill,
+        })
+        .unwrap();
+        let blocked = SockFilter::return_kill_process();
+        let mut expected = prologue(blocked);
+        for name in ["read", "execve", "sendmsg", "sendto", "exit_group"] {
+            expected.extend([
+                SockFilter::jump_if_equal(lookup(name).unwrap(), 0, 1),
+                SockFilter::return_allow(),
+            ]);
+        }
+        expected.push(blocked);
+        assert_eq!(filter, expected);
+    }
+
+    #[test]
+    fn default_profile() {
+        let filter = build_filter(&JobSeccomp {
+            profile: JobSeccompProfile::Default,
+            action: JobSeccompAction::Errno(38),
+        })
+        .unwrap();
+        let blocked = SockFilter::return_user_notif();
+        let mut expected = prologue(blocked);
+        expected.extend([
+            SockFilter::jump_if_equal(lookup("personality").unwrap(), 0, 5),
+            SockFilter::load_syscall_arg_low(0),
+            SockFilter::jump_if_equal(0, 2, 0),
+            SockFilter::jump_if_equal(0xffffffff, 1, 0),
+            blocked,
+            SockFilter::return_allow(),
+        ]);
+        assert_eq!(filter[..expected.len()], expected);
+        assert_eq!(
+            filter.len(),
+            expected.len() + DEFAULT_DENIED_SYSCALLS.len() * 2 + 1
+        );
+        assert_eq!(filter.last(), Some(&SockFilter::return_allow()));
+    }
+
+    #[test]
+    fn errno_action_notifies_unless_sendmsg_is_denied() {
+        let seccomp = |syscalls: &[&str], action| JobSeccomp {
+            profile: JobSeccompProfile::Deny {
+                syscalls: syscalls.iter().copied().map(Into::into).collect(),
+            },
+            action,
+        };
+        assert_eq!(
+            notify_errno(&seccomp(&["mount"], JobSeccompAction::Errno(1))),
+            Some(1)
+        );
+        assert_eq!(
+            notify_errno(&seccomp(&["mount"], JobSeccompAction::Kill)),
+            None
+        );
+
+        let filter = seccomp(&["sendmsg"], JobSeccompAction::Errno(1));
+        assert_eq!(notify_errno(&filter), None);
+        assert_eq!(
+            build_filter(&filter).unwrap().last(),
+            Some(&SockFilter::return_allow())
+        );
+        assert!(build_filter(&filter)
+            .unwrap()
+            .contains(&SockFilter::return_errno(1)));
+    }
+
+    #[test]
+    fn unknown_syscall() {
+        let err = build_filter(&JobSeccomp {
+            profile: JobSeccompProfile::Deny {
+                syscalls: vec!["frobnicate".into()],
+            },
+            action: JobSeccompAction::Kill,
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown syscall `frobnicate` in seccomp profile"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Synthesize the `/etc/passwd` and `/etc/group` files given to a job that asks for
+//! [`JobUserEntries`].
+//!
+//! The files are read out of the job's image, if it has them. If the image already has an entry
+//! for the job's user or group ID, and the job didn't ask for anything different, the image's entry
+//! is kept as is. Otherwise, an entry is synthesized and appended, replacing any existing entries
+//! with the same name or ID. The executor puts the resulting files in a lower layer of the job's
+//! root overlay, along with the user's home directory.
+
+use anyhow::{Error, Result};
+use maelstrom_base::{
+    GroupId, InvalidUserEntriesError, JobError, JobResult, JobUserEntries, UserId, Utf8Path,
+    Utf8PathBuf,
+};
+use maelstrom_layer_fs::{LayerFs, ReaderCache};
+use tokio::sync::Mutex;
+
+/// The files and directory the executor needs to create for a job's user and group.
+#[derive(Debug, Eq, PartialEq)]
+pub struct UserFiles {
+    pub passwd: String,
+    pub group: String,
+    pub home: Utf8PathBuf,
+}
+
+impl UserFiles {
+    /// Build the files for `user` and `group`, starting from the files found in the job's image.
+    /// Invalid entries are an execution error, while failing to read the image is a system error.
+    pub async fn read(
+        layer_fs: &LayerFs,
+        cache: &Mutex<ReaderCache>,
+        entries: &JobUserEntries,
+        user: UserId,
+        group: GroupId,
+    ) -> JobResult<Self, Error> {
+        let read = |path: &'static str| async move {
+            let contents = layer_fs.read_file(cache, Utf8Path::new(path)).await?;
+            Result::<_>::Ok(String::from_utf8_lossy(&contents.unwrap_or_default()).into_owned())
+        };
+        let passwd = read("/etc/passwd").await.map_err(JobError::System)?;
+        let group_file = read("/etc/group").await.map_err(JobError::System)?;
+        Self::new(&passwd, &group_file, entries, user, group)
+            .map_err(|err| JobError::Execution(err.into()))
+    }
+
+    /// Clients check the entries before sending the job, but we check them again, since a bad name
+    /// or home directory could add arbitrary entries to the files.
+    fn new(
+        passwd: &str,
+        group_file: &str,
+        entries: &JobUserEntries,
+        user: UserId,
+        group: GroupId,
+    ) -> Result<Self, InvalidUserEntriesError> {
+        entries.validate()?;
+
+        // The image's entry for the user is kept, unless the job gave a name. If the job only gave
+        // a home directory, that's the only field of the image's entry we change.
+        let image_user = find_entry(passwd, user.into()).filter(|_| entries.user_name.is_none());
+        let (passwd, user_name, home) = match image_user {
+            Some(mut fields) if fields.len() == 7 => {
+                let user_name = fields[0];
+                match &entries.home {
+                    None => (passwd.to_owned(), user_name, Utf8PathBuf::from(fields[5])),
+                    Some(home) => {
+                        fields[5] = home.as_str();
+                        let passwd = merge_entry(passwd, user_name, user.into(), &fields.join(":"));
+                        (passwd, user_name, home.clone())
+                    }
+                }
+            }
+            _ => {
+                let user_name = entries.user_name(user);
+                let home = entries.home(user);
+                let passwd = merge_entry(
+                    passwd,
+                    user_name,
+                    user.into(),
+                    &format!("{user_name}:x:{user}:{group}::{home}:/bin/sh"),
+                );
+                (passwd, user_name, home)
+            }
+        };
+
+        // Likewise for the group. If the job didn't give a name, it's named after the user, which
+        // may be the name from the image's entry.
+        let group = match find_entry(group_file, group.into()) {
+            Some(_) if entries.group_name.is_none() => group_file.to_owned(),
+            _ => {
+                let group_name = match &entries.group_name {
+                    Some(group_name) => group_name,
+                    None if group == GroupId::new(0) => "root",
+                    None => user_name,
+                };
+                merge_entry(
+                    group_file,
+                    group_name,
+                    group.into(),
+                    &format!("{group_name}:x:{group}:"),
+                )
+            }
+        };
+
+        Ok(Self {
+            passwd,
+            group,
+            home,
+        })
+    }
+}
+
+/// Return the fields of the first line in `contents` with the given ID. Both `/etc/passwd` and
+/// `/etc/group` have the ID in the third field.
+fn find_entry(contents: &str, id: u32) -> Option<Vec<&str>> {
+    contents
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.get(2).and_then(|line_id| line_id.parse().ok()) == Some(id))
+}
+
+/// Append `entry` to `contents`, removing any existing lines with the same name or ID. Both
+/// `/etc/passwd` and `/etc/group` have the name in the first field and the ID in the third field.
+fn merge_entry(contents: &str, name: &str, id: u32, entry: &str) -> String {
+    let mut merged = String::new();
+    for line in contents.lines() {
+        let mut fields = line.split(':');
+        let line_name = fields.next();
+        let line_id = fields.nth(1).and_then(|id| id.parse::<u32>().ok());
+        if line_name == Some(name) || line_id == Some(id) {
+            continue;
+        }
+        merged.push_str(line);
+        merged.push('\n');
+    }
+    merged.push_str(entry);
+    merged.push('\n');
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(
+        user_name: Option<&str>,
+        group_name: Option<&str>,
+        home: Option<&str>,
+    ) -> JobUserEntries {
+        JobUserEntries {
+            user_name: user_name.map(Into::into),
+            group_name: group_name.map(Into::into),
+            home: home.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn defaults_for_empty_image() {
+        let files = UserFiles::new(
+            "",
+            "",
+            &entries(None, None, None),
+            UserId::new(1000),
+            GroupId::new(1001),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            UserFiles {
+                passwd: "maelstrom:x:1000:1001::/home/maelstrom:/bin/sh\n".into(),
+                group: "maelstrom:x:1001:\n".into(),
+                home: "/home/maelstrom".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn defaults_for_root() {
+        let files = UserFiles::new(
+            "",
+            "",
+            &entries(None, None, None),
+            UserId::new(0),
+            GroupId::new(0),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            UserFiles {
+                passwd: "root:x:0:0::/root:/bin/sh\n".into(),
+                group: "root:x:0:\n".into(),
+                home: "/root".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn explicit_names_and_home() {
+        let files = UserFiles::new(
+            "",
+            "",
+            &entries(Some("alice"), Some("staff"), Some("/work")),
+            UserId::new(1000),
+            GroupId::new(50),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            UserFiles {
+                passwd: "alice:x:1000:50::/work:/bin/sh\n".into(),
+                group: "staff:x:50:\n".into(),
+                home: "/work".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn existing_entries_are_kept() {
+        let files = UserFiles::new(
+            "root:x:0:0:root:/root:/bin/bash\nnobody:x:65534:65534::/:/bin/false",
+            "root:x:0:\nnogroup:x:65534:\n",
+            &entries(None, None, None),
+            UserId::new(1000),
+            GroupId::new(1000),
+        )
+        .unwrap();
+        assert_eq!(
+            files.passwd,
+            "root:x:0:0:root:/root:/bin/bash\n\
+             nobody:x:65534:65534::/:/bin/false\n\
+             maelstrom:x:1000:1000::/home/maelstrom:/bin/sh\n",
+        );
+        assert_eq!(
+            files.group,
+            "root:x:0:\nnogroup:x:65534:\nmaelstrom:x:1000:\n",
+        );
+    }
+
+    #[test]
+    fn conflicting_entries_are_replaced() {
+        let files = UserFiles::new(
+            "root:x:0:0:root:/root:/bin/bash\n\
+             alice:x:1001:1001::/home/alice:/bin/bash\n\
+             bob:x:1000:1000::/home/bob:/bin/bash\n",
+            "root:x:0:\nalice:x:1001:\nbob:x:1000:\n",
+            &entries(Some("alice"), None, None),
+            UserId::new(1000),
+            GroupId::new(1000),
+        )
+        .unwrap();
+        assert_eq!(
+            files.passwd,
+            "root:x:0:0:root:/root:/bin/bash\n\
+             alice:x:1000:1000::/home/alice:/bin/sh\n",
+        );
+        // No group name was given, so the image's entry for the group is kept.
+        assert_eq!(files.group, "root:x:0:\nalice:x:1001:\nbob:x:1000:\n");
+    }
+
+    #[test]
+    fn image_entries_for_ids_are_kept() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n\
+                      ubuntu:x:1000:1000:Ubuntu:/home/ubuntu:/usr/bin/zsh\n";
+        let group = "root:x:0:\nubuntu:x:1000:\n";
+        let files = UserFiles::new(
+            passwd,
+            group,
+            &entries(None, None, None),
+            UserId::new(1000),
+            GroupId::new(1000),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            UserFiles {
+                passwd: passwd.into(),
+                group: group.into(),
+                home: "/home/ubuntu".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn image_entry_for_user_gets_explicit_home() {
+        let files = UserFiles::new(
+            "ubuntu:x:1000:1000:Ubuntu:/home/ubuntu:/usr/bin/zsh\n",
+            "",
+            &entries(None, None, Some("/work")),
+            UserId::new(1000),
+            GroupId::new(1001),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            UserFiles {
+                passwd: "ubuntu:x:1000:1000:Ubuntu:/work:/usr/bin/zsh\n".into(),
+                group: "ubuntu:x:1001:\n".into(),
+                home: "/work".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn name_with_separator_rejected() {
+        for (user_name, group_name) in [
+            (Some("alice:x:0:0::/root:/bin/sh"), None),
+            (Some("alice\nroot:x:0:0::/root:/bin/sh"), None),
+            (None, Some("staff,root")),
+            (None, Some("")),
+        ] {
+            let name = user_name.or(group_name).unwrap();
+            assert_eq!(
+                UserFiles::new(
+                    "",
+                    "",
+                    &entries(user_name, group_name, None),
+                    UserId::new(1000),
+                    GroupId::new(1000),
+                )
+                .unwrap_err()
+                .to_string(),
+                format!("{name:?} isn't a valid user or group name"),
+            );
+        }
+    }
+
+    #[test]
+    fn bad_home_rejected() {
+        for home in ["work", "/work:/bin/sh\nroot:x:0:0::/root"] {
+            assert_eq!(
+                UserFiles::new(
+                    "ubuntu:x:1000:1000:Ubuntu:/home/ubuntu:/usr/bin/zsh\n",
+                    "",
+                    &entries(None, None, Some(home)),
+                    UserId::new(1000),
+                    GroupId::new(1000),
+                )
+                .unwrap_err()
+                .to_string(),
+                format!("{home:?} isn't a valid home directory"),
+            );
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Save what a failed job that asked for [`JobSpec::keep_writable_layer_on_failure`] wrote to its
+//! file system.
+//!
+//! Like with core files, the job's mount namespace is usually gone by the time we get here, so the
+//! executor has the job send us file descriptors for the directories we want to save: the upper
+//! directory of its root overlay, and the root of each of its `tmp` mounts.
+//!
+//! [`JobSpec::keep_writable_layer_on_failure`]:
+//!     maelstrom_base::JobSpec::keep_writable_layer_on_failure
+
+use crate::core_dump::fd_path;
+use anyhow::Result;
+use maelstrom_base::JobWritableLayer;
+use maelstrom_linux::OwnedFd;
+use maelstrom_util::{fs::Fs, io::Sha256Stream};
+use std::{io, path::Path};
+
+/// Write a tar file to `path` containing the contents of each directory in `dirs`, placed at the
+/// path it had in the job's file system. Each directory is given as its path in the job's file
+/// system and a file descriptor for it. The root overlay's upper directory is given as `/`.
+///
+/// Only directories, regular files, and symbolic links are saved. In particular, the whiteouts
+/// overlayfs uses to record deleted files are skipped.
+pub fn save<'a>(
+    dirs: impl IntoIterator<Item = (&'a str, &'a OwnedFd)>,
+    path: &Path,
+) -> Result<JobWritableLayer> {
+    let fs = Fs::new();
+    let mut builder = tar::Builder::new(fs.create_file(path)?);
+    builder.follow_symlinks(false);
+    for (job_path, fd) in dirs {
+        let name = Path::new(job_path.trim_start_matches('/'));
+        if !name.as_os_str().is_empty() {
+            builder.append_dir(name, fd_path(fd))?;
+        }
+        append_dir_contents(&mut builder, &fd_path(fd), name)?;
+    }
+    builder.into_inner()?;
+
+    let mut reader = Sha256Stream::new(fs.open_file(path)?);
+    let size = io::copy(&mut reader, &mut io::sink())?;
+    let (_, digest) = reader.finalize();
+    Ok(JobWritableLayer { digest, size })
+}
+
+/// Recursively append the contents of `dir` to the tar file, under `name`.
+fn append_dir_contents<WriterT: io::Write>(
+    builder: &mut tar::Builder<WriterT>,
+    dir: &Path,
+    name: &Path,
+) -> Result<()> {
+    for entry in Fs::new().read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = name.join(entry.file_name());
+        let file_type = entry.metadata()?.file_type();
+        if file_type.is_dir() {
+            builder.append_dir(&name, &path)?;
+            append_dir_contents(builder, &path, &name)?;
+        } else if file_type.is_file() || file_type.is_symlink() {
+            builder.append_path_with_name(&path, &name)?;
+        }
+    }
+    Ok(())
+}